@@ -11,28 +11,29 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{net::SocketAddr, str::FromStr, sync::Arc};
+use std::{collections::HashSet, net::SocketAddr, str::FromStr, sync::Arc};
 
-use alloy_primitives::Address;
+use alloy_primitives::{Address, B256, U256};
 use anyhow::Context;
 use clap::Args;
 use rundler_builder::{
     self, BloxrouteSenderArgs, BuilderEvent, BuilderEventKind, BuilderSettings, BuilderTask,
     BuilderTaskArgs, EntryPointBuilderSettings, FlashbotsSenderArgs, LocalBuilderBuilder,
-    RawSenderArgs, TransactionSenderArgs, TransactionSenderKind,
+    RawSenderArgs, SelectionStrategy, SequencerSenderArgs, TipAmount, TipTransfer,
+    TransactionSenderArgs, TransactionSenderKind,
 };
 use rundler_pbh::PbhSubmissionProxy;
 use rundler_pool::RemotePoolClient;
-use rundler_provider::Providers;
+use rundler_provider::{FixedGasPriceSource, Providers};
 use rundler_sim::MempoolConfigs;
 use rundler_task::{
     server::{connect_with_retries_shutdown, format_socket_addr},
     TaskSpawnerExt,
 };
 use rundler_types::{
-    chain::{ChainSpec, ContractRegistry},
+    chain::{ChainSpec, ContractRegistry, TryIntoWithSpec},
     proxy::SubmissionProxy,
-    EntryPointVersion,
+    EntryPointVersion, PriorityFeeMode,
 };
 use rundler_utils::emit::{self, WithEntryPoint, EVENT_CHANNEL_CAPACITY};
 use secrecy::SecretString;
@@ -83,7 +84,7 @@ pub struct BuilderArgs {
 
     /// Choice of what sender type to to use for transaction submission.
     /// Defaults to the value of `raw`. Other options include `flashbots`,
-    /// `conditional` and `bloxroute`
+    /// `conditional`, `bloxroute` and `sequencer`
     #[arg(
         long = "builder.sender",
         name = "builder.sender",
@@ -104,6 +105,18 @@ pub struct BuilderArgs {
     )]
     pub submit_url: Option<String>,
 
+    /// Extra URLs of ETH providers that each signed transaction is also sent to,
+    /// concurrently with the submit URL. Inclusion is tracked through the submit URL.
+    ///
+    /// Only used when BUILDER_SENDER is "raw"
+    #[arg(
+        long = "builder.broadcast_urls",
+        name = "builder.broadcast_urls",
+        env = "BUILDER_BROADCAST_URLS",
+        value_delimiter = ','
+    )]
+    pub broadcast_urls: Vec<String>,
+
     /// Use the conditional RPC endpoint for transaction submission.
     ///
     /// Only used when BUILDER_SENDER is "raw"
@@ -149,6 +162,26 @@ pub struct BuilderArgs {
     )]
     bloxroute_auth_header: Option<SecretString>,
 
+    /// URL of the sequencer's private endpoint to submit bundles to
+    ///
+    /// Only used when BUILDER_SENDER is "sequencer"
+    #[arg(
+        long = "builder.sequencer_url",
+        name = "builder.sequencer_url",
+        env = "BUILDER_SEQUENCER_URL"
+    )]
+    sequencer_url: Option<String>,
+
+    /// JSON-RPC method of the sequencer endpoint that reports a bundle's inclusion
+    ///
+    /// Only used when BUILDER_SENDER is "sequencer"
+    #[arg(
+        long = "builder.sequencer_inclusion_method",
+        name = "builder.sequencer_inclusion_method",
+        env = "BUILDER_SEQUENCER_INCLUSION_METHOD"
+    )]
+    sequencer_inclusion_method: Option<String>,
+
     /// After submitting a bundle transaction, the maximum number of blocks to
     /// wait for that transaction to mine before we try resending with higher
     /// gas fees.
@@ -180,6 +213,17 @@ pub struct BuilderArgs {
     )]
     max_cancellation_fee_increases: u64,
 
+    /// If set, the on-chain nonce is checked before abandoning a cancellation that didn't
+    /// mine after the maximum fee increases. If the nonce was used, the builder waits to
+    /// learn whether the bundle or the cancellation mined instead of abandoning.
+    #[arg(
+        long = "builder.recheck_cancellation_nonce",
+        name = "builder.recheck_cancellation_nonce",
+        env = "BUILDER_RECHECK_CANCELLATION_NONCE",
+        default_value = "false"
+    )]
+    recheck_cancellation_nonce: bool,
+
     /// The maximum number of blocks to wait in a replacement underpriced state before issuing
     /// a cancellation transaction.
     #[arg(
@@ -189,6 +233,466 @@ pub struct BuilderArgs {
         default_value = "20"
     )]
     max_replacement_underpriced_blocks: u64,
+
+    /// If set, a replacement bundle re-includes the ops of the bundle it replaces first,
+    /// in the same order, before any new ops from the pool. Can't be combined with gas
+    /// price ordering.
+    #[arg(
+        long = "builder.sticky_replacement",
+        name = "builder.sticky_replacement",
+        env = "BUILDER_STICKY_REPLACEMENT",
+        default_value = "false"
+    )]
+    sticky_replacement: bool,
+
+    /// If set, the ops of a pending bundle transaction are re-simulated after this
+    /// many blocks without a mine. If any are no longer valid, the bundle is rebuilt
+    /// without them instead of waiting to increase fees on a bundle that would revert.
+    #[arg(
+        long = "builder.resim_after_blocks",
+        name = "builder.resim_after_blocks",
+        env = "BUILDER_RESIM_AFTER_BLOCKS"
+    )]
+    resim_after_blocks: Option<u64>,
+
+    /// If set, ops with the same sender and nonce as a different op in the pending
+    /// bundle transaction are skipped until it is resolved, since only one of the two
+    /// can be included.
+    #[arg(
+        long = "builder.skip_pending_nonce_duplicates",
+        name = "builder.skip_pending_nonce_duplicates",
+        env = "BUILDER_SKIP_PENDING_NONCE_DUPLICATES",
+        default_value = "false"
+    )]
+    skip_pending_nonce_duplicates: bool,
+
+    /// Maximum number of times a bundle round polls the pool again right away when
+    /// every op it considered was removed, since new ops may have arrived. Rounds that
+    /// removed no ops, or find the pool empty, wait for the next trigger.
+    #[arg(
+        long = "builder.repoll_on_empty",
+        name = "builder.repoll_on_empty",
+        env = "BUILDER_REPOLL_ON_EMPTY",
+        default_value = "0"
+    )]
+    repoll_on_empty: u64,
+
+    /// If set, the builder sends a zero-value transfer to itself when it hasn't sent a
+    /// bundle for this many blocks, to keep its nonce and its connection to the node's
+    /// mempool warm.
+    #[arg(
+        long = "builder.heartbeat_interval_blocks",
+        name = "builder.heartbeat_interval_blocks",
+        env = "BUILDER_HEARTBEAT_INTERVAL_BLOCKS"
+    )]
+    heartbeat_interval_blocks: Option<u64>,
+
+    /// If set, the priority fee increase percent required of ops is adjusted between
+    /// `builder.adaptive_priority_fee_min_percent` and this maximum, raised when recent
+    /// bundles were slow to mine and relaxed when they were mined quickly. Overrides the
+    /// configured priority fee mode once the first adjustment is made.
+    #[arg(
+        long = "builder.adaptive_priority_fee_max_percent",
+        name = "builder.adaptive_priority_fee_max_percent",
+        env = "BUILDER_ADAPTIVE_PRIORITY_FEE_MAX_PERCENT"
+    )]
+    adaptive_priority_fee_max_percent: Option<u32>,
+
+    /// Priority fee increase percent the adaptive priority fee starts at and relaxes to
+    #[arg(
+        long = "builder.adaptive_priority_fee_min_percent",
+        name = "builder.adaptive_priority_fee_min_percent",
+        env = "BUILDER_ADAPTIVE_PRIORITY_FEE_MIN_PERCENT",
+        default_value = "0"
+    )]
+    adaptive_priority_fee_min_percent: u32,
+
+    /// Amount the adaptive priority fee percent is raised or lowered by per adjustment
+    #[arg(
+        long = "builder.adaptive_priority_fee_step_percent",
+        name = "builder.adaptive_priority_fee_step_percent",
+        env = "BUILDER_ADAPTIVE_PRIORITY_FEE_STEP_PERCENT",
+        default_value = "5"
+    )]
+    adaptive_priority_fee_step_percent: u32,
+
+    /// Number of blocks from a bundle's first send to its mine at or above which it
+    /// counts as slow for the adaptive priority fee
+    #[arg(
+        long = "builder.adaptive_priority_fee_slow_blocks",
+        name = "builder.adaptive_priority_fee_slow_blocks",
+        env = "BUILDER_ADAPTIVE_PRIORITY_FEE_SLOW_BLOCKS",
+        default_value = "3"
+    )]
+    adaptive_priority_fee_slow_blocks: u64,
+
+    /// Number of consecutive bundles that must all be slow, or all fast, before the
+    /// adaptive priority fee is adjusted
+    #[arg(
+        long = "builder.adaptive_priority_fee_window",
+        name = "builder.adaptive_priority_fee_window",
+        env = "BUILDER_ADAPTIVE_PRIORITY_FEE_WINDOW",
+        default_value = "3"
+    )]
+    adaptive_priority_fee_window: usize,
+
+    /// If set, an op is removed from the pool once it has reverted on-chain in this many
+    /// mined bundles, i.e. the bundle mined without a `UserOperationEvent` for it.
+    #[arg(
+        long = "builder.max_onchain_revert_attempts",
+        name = "builder.max_onchain_revert_attempts",
+        env = "BUILDER_MAX_ONCHAIN_REVERT_ATTEMPTS"
+    )]
+    max_onchain_revert_attempts: Option<u64>,
+
+    /// If set, emits the exact `handleOps` calldata, beneficiary and gas of each
+    /// proposed bundle. Useful for reproducing reverted bundles in a debugger.
+    #[arg(
+        long = "builder.dump_calldata",
+        name = "builder.dump_calldata",
+        env = "BUILDER_DUMP_CALLDATA",
+        default_value = "false"
+    )]
+    dump_calldata: bool,
+
+    /// The maximum number of bundle rounds an operation can be skipped before the
+    /// builder removes it from the pool. If not set, operations are never removed
+    /// for being skipped.
+    #[arg(
+        long = "builder.max_skip_rounds",
+        name = "builder.max_skip_rounds",
+        env = "BUILDER_MAX_SKIP_ROUNDS"
+    )]
+    max_skip_rounds: Option<u64>,
+
+    /// The maximum size in bytes of the `handleOps` calldata of a bundle. Useful on
+    /// chains where calldata is the limiting resource. If not set, only the chain's
+    /// transaction size limit applies.
+    #[arg(
+        long = "builder.max_bundle_calldata_bytes",
+        name = "builder.max_bundle_calldata_bytes",
+        env = "BUILDER_MAX_BUNDLE_CALLDATA_BYTES"
+    )]
+    max_bundle_calldata_bytes: Option<usize>,
+
+    /// The number of times to retry the bundle gas estimation call when it fails
+    /// with a transport error. Reverts are never retried.
+    #[arg(
+        long = "builder.estimation_retries",
+        name = "builder.estimation_retries",
+        env = "BUILDER_ESTIMATION_RETRIES",
+        default_value = "2"
+    )]
+    estimation_retries: u64,
+
+    /// The maximum number of times the builder removes failing ops and re-estimates
+    /// a bundle in a single round. If not set, estimation is repeated until the
+    /// bundle succeeds or is empty.
+    #[arg(
+        long = "builder.max_whittle_iterations",
+        name = "builder.max_whittle_iterations",
+        env = "BUILDER_MAX_WHITTLE_ITERATIONS"
+    )]
+    max_whittle_iterations: Option<u64>,
+
+    /// If set, checks that the beneficiary can receive ETH before building each
+    /// bundle. The check always runs once at startup.
+    #[arg(
+        long = "builder.check_beneficiary_per_bundle",
+        name = "builder.check_beneficiary_per_bundle",
+        env = "BUILDER_CHECK_BENEFICIARY_PER_BUNDLE",
+        default_value = "false"
+    )]
+    check_beneficiary_per_bundle: bool,
+
+    /// Address that receives bundle fees. If not set, each builder's signer is paid.
+    /// Can be overridden per entry point in the builders config.
+    #[arg(
+        long = "builder.beneficiary",
+        name = "builder.beneficiary",
+        env = "BUILDER_BENEFICIARY"
+    )]
+    beneficiary: Option<Address>,
+
+    /// If set, each bundle transaction is wrapped in a multicall that also transfers a tip
+    /// to this address, only if `handleOps` succeeds. Requires `builder.tip_amount` or
+    /// `builder.tip_percent`.
+    #[arg(
+        long = "builder.tip_recipient",
+        name = "builder.tip_recipient",
+        env = "BUILDER_TIP_RECIPIENT"
+    )]
+    tip_recipient: Option<Address>,
+
+    /// Fixed tip, in wei, transferred to `builder.tip_recipient` with each bundle
+    #[arg(
+        long = "builder.tip_amount",
+        name = "builder.tip_amount",
+        env = "BUILDER_TIP_AMOUNT",
+        requires = "builder.tip_recipient",
+        conflicts_with = "builder.tip_percent"
+    )]
+    tip_amount: Option<U256>,
+
+    /// Percentage of each bundle's expected reward transferred to `builder.tip_recipient`
+    #[arg(
+        long = "builder.tip_percent",
+        name = "builder.tip_percent",
+        env = "BUILDER_TIP_PERCENT",
+        requires = "builder.tip_recipient"
+    )]
+    tip_percent: Option<u32>,
+
+    /// If set together with `builder.fixed_priority_fee`, bundles use this fixed base
+    /// fee instead of the network value. For testing only.
+    #[arg(
+        long = "builder.fixed_base_fee",
+        name = "builder.fixed_base_fee",
+        env = "BUILDER_FIXED_BASE_FEE",
+        requires = "builder.fixed_priority_fee"
+    )]
+    fixed_base_fee: Option<u128>,
+
+    /// If set together with `builder.fixed_base_fee`, bundles use this fixed priority
+    /// fee instead of the network value. For testing only.
+    #[arg(
+        long = "builder.fixed_priority_fee",
+        name = "builder.fixed_priority_fee",
+        env = "BUILDER_FIXED_PRIORITY_FEE",
+        requires = "builder.fixed_base_fee"
+    )]
+    fixed_priority_fee: Option<u128>,
+
+    /// If set, simulates each bundle as a whole before sending and raises its gas limit
+    /// if it used more gas than the summed estimate
+    #[arg(
+        long = "builder.simulate_bundle_gas",
+        name = "builder.simulate_bundle_gas",
+        env = "BUILDER_SIMULATE_BUNDLE_GAS",
+        default_value = "false"
+    )]
+    simulate_bundle_gas: bool,
+
+    /// Percentage added to the estimated gas limit of each bundle, so that minor state
+    /// changes before inclusion don't run the bundle out of gas
+    #[arg(
+        long = "builder.gas_estimate_buffer_percent",
+        name = "builder.gas_estimate_buffer_percent",
+        env = "BUILDER_GAS_ESTIMATE_BUFFER_PERCENT",
+        default_value = "5"
+    )]
+    gas_estimate_buffer_percent: u32,
+
+    /// If set, traces each bundle and logs the gas used by each op's validation,
+    /// execution and postOp. Requires a provider that supports `debug_traceCall`.
+    #[arg(
+        long = "builder.attribute_bundle_gas",
+        name = "builder.attribute_bundle_gas",
+        env = "BUILDER_ATTRIBUTE_BUNDLE_GAS",
+        default_value = "false"
+    )]
+    attribute_bundle_gas: bool,
+
+    /// If set, ops whose validAfter is in the future are skipped and not simulated
+    /// again until then, instead of being rejected
+    #[arg(
+        long = "builder.defer_not_yet_valid_ops",
+        name = "builder.defer_not_yet_valid_ops",
+        env = "BUILDER_DEFER_NOT_YET_VALID_OPS",
+        default_value = "false"
+    )]
+    defer_not_yet_valid_ops: bool,
+
+    /// Maximum time in milliseconds to spend building a bundle. Once passed, ops still
+    /// simulating are left out and the bundle is proposed from those already validated.
+    #[arg(
+        long = "builder.max_build_duration_millis",
+        name = "builder.max_build_duration_millis",
+        env = "BUILDER_MAX_BUILD_DURATION_MILLIS"
+    )]
+    max_build_duration_millis: Option<u64>,
+
+    /// Maximum number of ops using the same paymaster in a bundle. Ops past the cap are
+    /// left in the pool for a later bundle.
+    #[arg(
+        long = "builder.max_ops_per_paymaster",
+        name = "builder.max_ops_per_paymaster",
+        env = "BUILDER_MAX_OPS_PER_PAYMASTER"
+    )]
+    max_ops_per_paymaster: Option<usize>,
+
+    /// Maximum number of distinct aggregators in a bundle. Ops using other aggregators
+    /// are left in the pool for a later bundle.
+    #[arg(
+        long = "builder.max_aggregators_per_bundle",
+        name = "builder.max_aggregators_per_bundle",
+        env = "BUILDER_MAX_AGGREGATORS_PER_BUNDLE",
+        default_value = "16"
+    )]
+    max_aggregators_per_bundle: usize,
+
+    /// Maximum summed call, verification and pre-verification gas limits of an op. Ops
+    /// over it are left in the pool rather than taking up most of a bundle.
+    #[arg(
+        long = "builder.max_op_total_gas",
+        name = "builder.max_op_total_gas",
+        env = "BUILDER_MAX_OP_TOTAL_GAS",
+        default_value = "30000000"
+    )]
+    max_op_total_gas: u128,
+
+    /// If set, a bundle round given a block more than this many blocks behind the head
+    /// fetches the head block and proposes on it instead, so ops aren't simulated
+    /// against stale state.
+    #[arg(
+        long = "builder.max_sim_block_age",
+        name = "builder.max_sim_block_age",
+        env = "BUILDER_MAX_SIM_BLOCK_AGE"
+    )]
+    max_sim_block_age: Option<u64>,
+
+    /// If set, each aggregator is probed with an empty `aggregateSignatures` call before
+    /// its ops are bundled, and the ops of aggregators that don't implement `IAggregator`
+    /// are rejected. Results are cached per aggregator.
+    #[arg(
+        long = "builder.verify_aggregator_interface",
+        name = "builder.verify_aggregator_interface",
+        env = "BUILDER_VERIFY_AGGREGATOR_INTERFACE",
+        default_value = "false"
+    )]
+    verify_aggregator_interface: bool,
+
+    /// If set, candidate ops are ordered by gas price weighted by the number of bundle
+    /// rounds they have waited, so low fee ops are eventually included. Each round adds
+    /// this fraction of the op's gas price to its weight.
+    #[arg(
+        long = "builder.selection_age_weight",
+        name = "builder.selection_age_weight",
+        env = "BUILDER_SELECTION_AGE_WEIGHT"
+    )]
+    selection_age_weight: Option<f64>,
+
+    /// If set, candidate ops are ordered by effective gas price, highest first, instead
+    /// of the pool's order. Ignored if `builder.selection_age_weight` is set.
+    #[arg(
+        long = "builder.order_by_gas_price",
+        name = "builder.order_by_gas_price",
+        env = "BUILDER_ORDER_BY_GAS_PRICE",
+        default_value = "false"
+    )]
+    order_by_gas_price: bool,
+
+    /// Maximum summed prefund, in wei, of the ops in a bundle. Ops past the cap are left
+    /// in the pool for a later bundle.
+    #[arg(
+        long = "builder.max_bundle_prefund",
+        name = "builder.max_bundle_prefund",
+        env = "BUILDER_MAX_BUNDLE_PREFUND"
+    )]
+    max_bundle_prefund: Option<U256>,
+
+    /// If set, ops without a paymaster are left in the pool unless their sender's entry
+    /// point deposit and ETH balance cover the prefund. Disable for accounts that fund
+    /// the prefund some other way during validation.
+    #[arg(
+        long = "builder.check_sender_eth_balance",
+        name = "builder.check_sender_eth_balance",
+        env = "BUILDER_CHECK_SENDER_ETH_BALANCE",
+        default_value = "true"
+    )]
+    check_sender_eth_balance: bool,
+
+    /// Percentage above its prefund that the entry point deposit and ETH balance of a
+    /// self-paying sender must cover, so that minor state changes before inclusion don't
+    /// leave it underfunded. Only applies if the sender ETH balance check is enabled.
+    #[arg(
+        long = "builder.sender_deposit_buffer_percent",
+        name = "builder.sender_deposit_buffer_percent",
+        env = "BUILDER_SENDER_DEPOSIT_BUFFER_PERCENT",
+        default_value = "5"
+    )]
+    sender_deposit_buffer_percent: u32,
+
+    /// Minimum seconds that must remain before an op's validUntil for it to be bundled.
+    /// Ops expiring sooner are left in the pool since they may expire before the bundle
+    /// is mined.
+    #[arg(
+        long = "builder.min_validity_remaining_seconds",
+        name = "builder.min_validity_remaining_seconds",
+        env = "BUILDER_MIN_VALIDITY_REMAINING_SECONDS",
+        default_value = "120"
+    )]
+    min_validity_remaining_seconds: u64,
+
+    /// Redis URI used to reserve signer nonces so that builders in different processes
+    /// sharing a signer never use the same nonce. If not set, nonces are taken from the
+    /// on-chain transaction count.
+    #[arg(
+        long = "builder.nonce_redis_uri",
+        name = "builder.nonce_redis_uri",
+        env = "BUILDER_NONCE_REDIS_URI"
+    )]
+    nonce_redis_uri: Option<String>,
+
+    /// TTL of a nonce reservation in milliseconds
+    #[arg(
+        long = "builder.nonce_reservation_ttl_millis",
+        name = "builder.nonce_reservation_ttl_millis",
+        env = "BUILDER_NONCE_RESERVATION_TTL_MILLIS",
+        default_value = "600000"
+    )]
+    nonce_reservation_ttl_millis: u64,
+
+    /// Persist each builder's transaction tracker nonce and pending transactions to the
+    /// redis at `builder.nonce_redis_uri` and restore them on startup, so that a restarted
+    /// builder does not resubmit or skip a nonce. Requires `builder.nonce_redis_uri`.
+    #[arg(
+        long = "builder.persist_tracker_state",
+        name = "builder.persist_tracker_state",
+        env = "BUILDER_PERSIST_TRACKER_STATE",
+        default_value = "false"
+    )]
+    persist_tracker_state: bool,
+
+    /// Share a single confirmation poller across the transaction trackers of all
+    /// builders, rather than each tracker looking up its own receipts. Reduces RPC load
+    /// when running many builders.
+    #[arg(
+        long = "builder.share_confirmation_poller",
+        name = "builder.share_confirmation_poller",
+        env = "BUILDER_SHARE_CONFIRMATION_POLLER",
+        default_value = "false"
+    )]
+    share_confirmation_poller: bool,
+
+    /// Maximum number of retries of a signing call that failed with a transient error,
+    /// such as KMS throttling or a timeout. Permanent errors are not retried.
+    #[arg(
+        long = "builder.signing_max_retries",
+        name = "builder.signing_max_retries",
+        env = "BUILDER_SIGNING_MAX_RETRIES",
+        default_value = "3"
+    )]
+    signing_max_retries: u64,
+
+    /// Wait in milliseconds before the first signing retry, doubled for each retry after it
+    #[arg(
+        long = "builder.signing_retry_backoff_millis",
+        name = "builder.signing_retry_backoff_millis",
+        env = "BUILDER_SIGNING_RETRY_BACKOFF_MILLIS",
+        default_value = "100"
+    )]
+    signing_retry_backoff_millis: u64,
+
+    /// Delay in milliseconds between the starts of an entry point's builders, the builder
+    /// at index `n` waits `n` times this before proposing its first bundle
+    #[arg(
+        long = "builder.stagger_interval_millis",
+        name = "builder.stagger_interval_millis",
+        env = "BUILDER_STAGGER_INTERVAL_MILLIS",
+        default_value = "0"
+    )]
+    stagger_interval_millis: u64,
 }
 
 impl BuilderArgs {
@@ -209,13 +713,11 @@ impl BuilderArgs {
         let mut num_builders = 0;
 
         if !common.disable_entry_point_v0_6 {
-            let builders = entry_point_builders
-                .as_ref()
-                .and_then(|builder_configs| {
-                    builder_configs
-                        .get_for_entry_point(chain_spec.entry_point_address_v0_6)
-                        .map(|ep| ep.builders())
-                })
+            let ep_config = entry_point_builders.as_ref().and_then(|builder_configs| {
+                builder_configs.get_for_entry_point(chain_spec.entry_point_address_v0_6)
+            });
+            let builders = ep_config
+                .map(|ep| ep.builders())
                 .unwrap_or_else(|| builder_settings_from_cli(common.num_builders_v0_6));
 
             entry_points.push(EntryPointBuilderSettings {
@@ -224,18 +726,23 @@ impl BuilderArgs {
                 mempool_configs: mempool_configs
                     .get_for_entry_point(chain_spec.entry_point_address_v0_6),
                 builders,
+                priority_fee_mode: ep_config
+                    .map(|ep| ep.priority_fee_mode())
+                    .transpose()?
+                    .flatten(),
+                beneficiary: ep_config.and_then(|ep| ep.beneficiary),
+                allowed_mempools: ep_config.and_then(|ep| ep.allowed_mempools()),
+                max_bundle_gas_limit: ep_config.and_then(|ep| ep.max_bundle_gas_limit),
             });
 
             num_builders += common.num_builders_v0_6;
         }
         if !common.disable_entry_point_v0_7 {
-            let builders = entry_point_builders
-                .as_ref()
-                .and_then(|builder_configs| {
-                    builder_configs
-                        .get_for_entry_point(chain_spec.entry_point_address_v0_7)
-                        .map(|ep| ep.builders())
-                })
+            let ep_config = entry_point_builders.as_ref().and_then(|builder_configs| {
+                builder_configs.get_for_entry_point(chain_spec.entry_point_address_v0_7)
+            });
+            let builders = ep_config
+                .map(|ep| ep.builders())
                 .unwrap_or_else(|| builder_settings_from_cli(common.num_builders_v0_7));
 
             entry_points.push(EntryPointBuilderSettings {
@@ -244,6 +751,13 @@ impl BuilderArgs {
                 mempool_configs: mempool_configs
                     .get_for_entry_point(chain_spec.entry_point_address_v0_7),
                 builders,
+                priority_fee_mode: ep_config
+                    .map(|ep| ep.priority_fee_mode())
+                    .transpose()?
+                    .flatten(),
+                beneficiary: ep_config.and_then(|ep| ep.beneficiary),
+                allowed_mempools: ep_config.and_then(|ep| ep.allowed_mempools()),
+                max_bundle_gas_limit: ep_config.and_then(|ep| ep.max_bundle_gas_limit),
             });
 
             num_builders += common.num_builders_v0_7;
@@ -278,11 +792,64 @@ impl BuilderArgs {
             target_bundle_gas: bundle_limits.target_bundle_execution_gas_limit,
             max_bundle_gas: bundle_limits.max_bundle_execution_gas_limit,
             sender_args,
-            sim_settings: common.try_into()?,
+            sim_settings: common.try_into_with_spec(&chain_spec)?,
             max_blocks_to_wait_for_mine: self.max_blocks_to_wait_for_mine,
             replacement_fee_percent_increase: self.replacement_fee_percent_increase,
             max_cancellation_fee_increases: self.max_cancellation_fee_increases,
+            recheck_cancellation_nonce: self.recheck_cancellation_nonce,
             max_replacement_underpriced_blocks: self.max_replacement_underpriced_blocks,
+            sticky_replacement: self.sticky_replacement,
+            resim_after_blocks: self.resim_after_blocks,
+            skip_pending_nonce_duplicates: self.skip_pending_nonce_duplicates,
+            repoll_on_empty: self.repoll_on_empty,
+            heartbeat_interval_blocks: self.heartbeat_interval_blocks,
+            adaptive_priority_fee_max_percent: self.adaptive_priority_fee_max_percent,
+            adaptive_priority_fee_min_percent: self.adaptive_priority_fee_min_percent,
+            adaptive_priority_fee_step_percent: self.adaptive_priority_fee_step_percent,
+            adaptive_priority_fee_slow_blocks: self.adaptive_priority_fee_slow_blocks,
+            adaptive_priority_fee_window: self.adaptive_priority_fee_window,
+            max_onchain_revert_attempts: self.max_onchain_revert_attempts,
+            dump_calldata: self.dump_calldata,
+            max_skip_rounds: self.max_skip_rounds,
+            max_bundle_calldata_bytes: self.max_bundle_calldata_bytes,
+            estimation_retries: self.estimation_retries,
+            max_whittle_iterations: self.max_whittle_iterations,
+            check_beneficiary_per_bundle: self.check_beneficiary_per_bundle,
+            beneficiary: self.beneficiary,
+            tip_transfer: self.tip_transfer()?,
+            fixed_gas_price: self.fixed_base_fee.zip(self.fixed_priority_fee).map(
+                |(base_fee, priority_fee)| FixedGasPriceSource {
+                    base_fee,
+                    priority_fee,
+                },
+            ),
+            simulate_bundle_gas: self.simulate_bundle_gas,
+            gas_estimate_buffer_percent: self.gas_estimate_buffer_percent,
+            attribute_bundle_gas: self.attribute_bundle_gas,
+            defer_not_yet_valid_ops: self.defer_not_yet_valid_ops,
+            max_build_duration_millis: self.max_build_duration_millis,
+            max_ops_per_paymaster: self.max_ops_per_paymaster,
+            max_aggregators_per_bundle: self.max_aggregators_per_bundle,
+            max_op_total_gas: self.max_op_total_gas,
+            max_sim_block_age: self.max_sim_block_age,
+            verify_aggregator_interface: self.verify_aggregator_interface,
+            selection_strategy: match self.selection_age_weight {
+                Some(age_weight) => SelectionStrategy::WeightedFairness { age_weight },
+                None if self.order_by_gas_price => SelectionStrategy::EffectiveGasPrice,
+                None => SelectionStrategy::PoolOrder,
+            },
+            max_bundle_prefund: self.max_bundle_prefund,
+            check_sender_eth_balance: self.check_sender_eth_balance,
+            sender_deposit_buffer_percent: self.sender_deposit_buffer_percent,
+            min_validity_remaining_seconds: self.min_validity_remaining_seconds,
+            nonce_redis_uri: self.nonce_redis_uri.clone(),
+            nonce_reservation_ttl_millis: self.nonce_reservation_ttl_millis,
+            persist_tracker_state: self.persist_tracker_state,
+            share_confirmation_poller: self.share_confirmation_poller,
+            signing_max_retries: self.signing_max_retries,
+            signing_retry_backoff_millis: self.signing_retry_backoff_millis,
+            stagger_interval_millis: self.stagger_interval_millis,
+            canary_gas_tolerance_percent: common.canary_gas_tolerance_percent,
             remote_address,
             da_gas_tracking_enabled,
             provider_client_timeout_seconds,
@@ -293,6 +860,22 @@ impl BuilderArgs {
         })
     }
 
+    fn tip_transfer(&self) -> anyhow::Result<Option<TipTransfer>> {
+        let Some(recipient) = self.tip_recipient else {
+            return Ok(None);
+        };
+        let amount = match (self.tip_amount, self.tip_percent) {
+            (Some(amount), _) => TipAmount::Fixed(amount),
+            (None, Some(percent)) => TipAmount::PercentOfReward(percent),
+            (None, None) => {
+                return Err(anyhow::anyhow!(
+                    "builder.tip_recipient requires builder.tip_amount or builder.tip_percent"
+                ))
+            }
+        };
+        Ok(Some(TipTransfer { recipient, amount }))
+    }
+
     fn sender_args(
         &self,
         chain_spec: &ChainSpec,
@@ -301,6 +884,7 @@ impl BuilderArgs {
         match self.sender_type {
             TransactionSenderKind::Raw => Ok(TransactionSenderArgs::Raw(RawSenderArgs {
                 submit_url: self.submit_url.clone().unwrap_or_else(|| rpc_url.into()),
+                broadcast_urls: self.broadcast_urls.clone(),
                 use_conditional_rpc: self.use_conditional_rpc,
             })),
             TransactionSenderKind::Flashbots => {
@@ -331,6 +915,17 @@ impl BuilderArgs {
                         .context("should have a bloxroute auth header")?,
                 }))
             }
+            TransactionSenderKind::Sequencer => {
+                Ok(TransactionSenderArgs::Sequencer(SequencerSenderArgs {
+                    submit_url: self
+                        .sequencer_url
+                        .clone()
+                        .context("should have a sequencer URL (cli: sequencer_url)")?,
+                    inclusion_method: self.sequencer_inclusion_method.clone().context(
+                        "should have a sequencer inclusion method (cli: sequencer_inclusion_method)",
+                    )?,
+                }))
+            }
         }
     }
 }
@@ -349,6 +944,16 @@ pub(crate) struct EntryPointBuilderConfig {
     pub(crate) address: Address,
     // Builder configs
     pub(crate) builders: Vec<BuilderConfig>,
+    // Beneficiary of this entry point's bundles, overriding the global beneficiary
+    pub(crate) beneficiary: Option<Address>,
+    // Priority fee mode kind for this entry point, overriding the global mode
+    pub(crate) priority_fee_mode_kind: Option<String>,
+    // Priority fee mode value for this entry point, defaults to 0
+    pub(crate) priority_fee_mode_value: Option<u32>,
+    // Mempool IDs this entry point's builders bundle from, defaults to all mempools
+    pub(crate) allowed_mempools: Option<Vec<B256>>,
+    // Ceiling on the gas limit of this entry point's bundle transactions
+    pub(crate) max_bundle_gas_limit: Option<u64>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -409,6 +1014,21 @@ impl EntryPointBuilderConfig {
         }
         builders
     }
+
+    pub fn allowed_mempools(&self) -> Option<HashSet<B256>> {
+        self.allowed_mempools
+            .as_ref()
+            .map(|ids| ids.iter().copied().collect())
+    }
+
+    pub fn priority_fee_mode(&self) -> anyhow::Result<Option<PriorityFeeMode>> {
+        self.priority_fee_mode_kind
+            .as_deref()
+            .map(|kind| {
+                PriorityFeeMode::try_from(kind, self.priority_fee_mode_value.unwrap_or_default())
+            })
+            .transpose()
+    }
 }
 
 fn builder_settings_from_cli(count: u64) -> Vec<BuilderSettings> {
@@ -436,12 +1056,13 @@ pub struct BuilderCliArgs {
     pool_url: String,
 }
 
-pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
+pub async fn spawn_tasks<T: TaskSpawnerExt + 'static, P: Providers + 'static>(
     task_spawner: T,
     chain_spec: ChainSpec,
     builder_args: BuilderCliArgs,
     common_args: CommonArgs,
-    providers: impl Providers + 'static,
+    providers: P,
+    canary_providers: Option<P>,
 ) -> anyhow::Result<()> {
     let BuilderCliArgs {
         builder: builder_args,
@@ -499,6 +1120,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         builder_builder,
         pool,
         providers,
+        canary_providers,
         signer_manager,
     )
     .spawn(task_spawner)
@@ -15,7 +15,7 @@ use std::{sync::Arc, time::Duration};
 
 use admin::AdminCliArgs;
 use aggregator::AggregatorType;
-use alloy_primitives::U256;
+use alloy_primitives::{Address, U256};
 use anyhow::{bail, Context};
 use clap::{
     builder::{PossibleValuesParser, ValueParser},
@@ -86,7 +86,20 @@ pub async fn run() -> anyhow::Result<()> {
         entry_point_builders.set_proxies(&mut cs);
     }
 
-    let providers = construct_providers(&opt.common, &cs)?;
+    let providers = construct_providers(
+        &opt.common,
+        &cs,
+        opt.common
+            .node_http
+            .as_ref()
+            .context("must provide node_http")?,
+    )?;
+    let canary_providers = opt
+        .common
+        .canary_node_http
+        .as_ref()
+        .map(|url| construct_providers(&opt.common, &cs, url))
+        .transpose()?;
     aggregator::instantiate_aggregators(&opt.common, &mut cs, &providers);
 
     tracing::info!("Chain spec: {:#?}", cs);
@@ -99,6 +112,7 @@ pub async fn run() -> anyhow::Result<()> {
                 *args,
                 opt.common,
                 providers,
+                canary_providers,
                 mempool_configs,
                 entry_point_builders,
             )
@@ -119,7 +133,15 @@ pub async fn run() -> anyhow::Result<()> {
             rpc::spawn_tasks(task_spawner.clone(), cs, args, opt.common, providers).await?
         }
         Command::Builder(args) => {
-            builder::spawn_tasks(task_spawner.clone(), cs, args, opt.common, providers).await?
+            builder::spawn_tasks(
+                task_spawner.clone(),
+                cs,
+                args,
+                opt.common,
+                providers,
+                canary_providers,
+            )
+            .await?
         }
         Command::Admin(args) => {
             admin::run(args, cs, providers, task_spawner).await?;
@@ -211,6 +233,31 @@ pub struct CommonArgs {
     )]
     node_http: Option<String>,
 
+    /// ETH Node websocket URL used to subscribe to new blocks
+    #[arg(long = "node_ws", name = "node_ws", env = "NODE_WS", global = true)]
+    node_ws: Option<String>,
+
+    /// Secondary ETH Node HTTP URL used to run canary simulations. Disagreements with the
+    /// primary node are logged, but the primary result is always used.
+    #[arg(
+        long = "canary_node_http",
+        name = "canary_node_http",
+        env = "CANARY_NODE_HTTP",
+        global = true
+    )]
+    canary_node_http: Option<String>,
+
+    /// Percentage that the pre-op gas of a canary simulation may differ from the primary
+    /// simulation before the two are considered to disagree.
+    #[arg(
+        long = "canary_gas_tolerance_percent",
+        name = "canary_gas_tolerance_percent",
+        env = "CANARY_GAS_TOLERANCE_PERCENT",
+        default_value = "10",
+        global = true
+    )]
+    canary_gas_tolerance_percent: u32,
+
     /// Flag for turning unsafe bundling mode on
     #[arg(long = "unsafe", env = "UNSAFE", global = true)]
     unsafe_mode: bool,
@@ -224,6 +271,15 @@ pub struct CommonArgs {
     )]
     max_verification_gas: u64,
 
+    #[arg(
+        long = "max_call_gas_limit",
+        name = "max_call_gas_limit",
+        default_value = "30000000",
+        env = "MAX_CALL_GAS_LIMIT",
+        global = true
+    )]
+    max_call_gas_limit: u64,
+
     #[arg(
         long = "max_uo_cost",
         name = "max_uo_cost",
@@ -290,6 +346,270 @@ pub struct CommonArgs {
     )]
     enable_unsafe_fallback: bool,
 
+    /// Maximum size, in bytes, of a packed user operation. Larger operations are rejected before simulation.
+    #[arg(
+        long = "max_op_calldata_bytes",
+        name = "max_op_calldata_bytes",
+        env = "MAX_OP_CALLDATA_BYTES",
+        default_value = "65536",
+        global = true
+    )]
+    max_op_calldata_bytes: usize,
+
+    /// Comma separated list of senders whose operations skip the ERC-7562 storage and opcode rules.
+    /// Only use for senders fully under the operator's control.
+    #[arg(
+        long = "trusted_senders",
+        name = "trusted_senders",
+        env = "TRUSTED_SENDERS",
+        value_delimiter = ',',
+        global = true
+    )]
+    trusted_senders: Vec<Address>,
+
+    /// Comma separated list of paymasters whose operations are rejected before simulation
+    #[arg(
+        long = "denied_paymasters",
+        name = "denied_paymasters",
+        env = "DENIED_PAYMASTERS",
+        value_delimiter = ',',
+        global = true
+    )]
+    denied_paymasters: Vec<Address>,
+
+    /// Comma separated list of factories whose operations are rejected before simulation
+    #[arg(
+        long = "denied_factories",
+        name = "denied_factories",
+        env = "DENIED_FACTORIES",
+        value_delimiter = ',',
+        global = true
+    )]
+    denied_factories: Vec<Address>,
+
+    /// Reject operations with a zero call gas limit before simulation. Such operations
+    /// are valid per the spec but execute nothing.
+    #[arg(
+        long = "reject_zero_call_gas",
+        name = "reject_zero_call_gas",
+        env = "REJECT_ZERO_CALL_GAS",
+        default_value = "false",
+        global = true
+    )]
+    reject_zero_call_gas: bool,
+
+    /// Allow unstaked entities to read storage outside their associated slots [STO-033].
+    /// Matches more permissive mempools. Off by default.
+    #[arg(
+        long = "allow_unstaked_associated_reads",
+        name = "allow_unstaked_associated_reads",
+        env = "ALLOW_UNSTAKED_ASSOCIATED_READS",
+        default_value = "false",
+        global = true
+    )]
+    allow_unstaked_associated_reads: bool,
+
+    /// Allow a factory at the sender's address, as used by some vanity deploy patterns, to
+    /// access the sender's associated storage during the deploy without being staked
+    /// [STO-022]. Off by default.
+    #[arg(
+        long = "allow_sender_factory_associated_storage",
+        name = "allow_sender_factory_associated_storage",
+        env = "ALLOW_SENDER_FACTORY_ASSOCIATED_STORAGE",
+        default_value = "false",
+        global = true
+    )]
+    allow_sender_factory_associated_storage: bool,
+
+    /// If set, reject operations whose pre-verification gas is below their calldata gas
+    /// cost plus this fixed overhead before simulation
+    #[arg(
+        long = "min_pre_verification_gas_overhead",
+        name = "min_pre_verification_gas_overhead",
+        env = "MIN_PRE_VERIFICATION_GAS_OVERHEAD",
+        global = true
+    )]
+    min_pre_verification_gas_overhead: Option<u128>,
+
+    /// Treat a zero code hash, meaning validation accessed no contracts, as a violation.
+    /// This usually indicates a misconfigured simulation.
+    #[arg(
+        long = "reject_empty_code_hash",
+        name = "reject_empty_code_hash",
+        env = "REJECT_EMPTY_CODE_HASH",
+        default_value = "false",
+        global = true
+    )]
+    reject_empty_code_hash: bool,
+
+    /// Include the decoded validation output returned by the entry point in simulation
+    /// results, for debugging. Off by default.
+    #[arg(
+        long = "include_validation_output",
+        name = "include_validation_output",
+        env = "INCLUDE_VALIDATION_OUTPUT",
+        default_value = "false",
+        global = true
+    )]
+    include_validation_output: bool,
+
+    /// Maximum depth of nested external calls an entity may make during validation,
+    /// where calls made by the entry point are at depth 1
+    #[arg(
+        long = "max_validation_call_depth",
+        name = "max_validation_call_depth",
+        env = "MAX_VALIDATION_CALL_DEPTH",
+        default_value = "64",
+        global = true
+    )]
+    max_validation_call_depth: u64,
+
+    /// If nonzero, staked entities whose stake can be withdrawn within this many
+    /// seconds are flagged as having stake expiring soon
+    #[arg(
+        long = "stake_expiry_warning_window_secs",
+        name = "stake_expiry_warning_window_secs",
+        env = "STAKE_EXPIRY_WARNING_WINDOW_SECS",
+        default_value = "0",
+        global = true
+    )]
+    stake_expiry_warning_window_secs: u64,
+
+    /// Send the provider reads made after tracing, the code hash and any deposit info,
+    /// as a single JSON-RPC batch request
+    #[arg(
+        long = "batch_state_reads",
+        name = "batch_state_reads",
+        env = "BATCH_STATE_READS",
+        default_value = "false",
+        global = true
+    )]
+    batch_state_reads: bool,
+
+    /// Include the pre-op gas broken down by phase in simulation results
+    #[arg(
+        long = "include_gas_breakdown",
+        name = "include_gas_breakdown",
+        env = "INCLUDE_GAS_BREAKDOWN",
+        default_value = "false",
+        global = true
+    )]
+    include_gas_breakdown: bool,
+
+    /// Include the logs emitted during validation in simulation results
+    #[arg(
+        long = "include_validation_logs",
+        name = "include_validation_logs",
+        env = "INCLUDE_VALIDATION_LOGS",
+        default_value = "false",
+        global = true
+    )]
+    include_validation_logs: bool,
+
+    /// Reject ops whose factory doesn't deploy a contract at the sender address
+    #[arg(
+        long = "require_factory_deploys_sender",
+        name = "require_factory_deploys_sender",
+        env = "REQUIRE_FACTORY_DEPLOYS_SENDER",
+        default_value = "false",
+        global = true
+    )]
+    require_factory_deploys_sender: bool,
+
+    /// Reject ops that access storage associated with an address unrelated to the op
+    #[arg(
+        long = "reject_cross_sender_associated_storage",
+        name = "reject_cross_sender_associated_storage",
+        env = "REJECT_CROSS_SENDER_ASSOCIATED_STORAGE",
+        default_value = "false",
+        global = true
+    )]
+    reject_cross_sender_associated_storage: bool,
+
+    /// Maximum number of seconds in the future an op's validAfter may be
+    #[arg(
+        long = "max_valid_after_horizon_secs",
+        name = "max_valid_after_horizon_secs",
+        env = "MAX_VALID_AFTER_HORIZON_SECS",
+        default_value = "2592000",
+        global = true
+    )]
+    max_valid_after_horizon_secs: u64,
+
+    /// Reject ops that check the code size or hash of an address with no code
+    #[arg(
+        long = "reject_undeployed_ext_code_access",
+        name = "reject_undeployed_ext_code_access",
+        env = "REJECT_UNDEPLOYED_EXT_CODE_ACCESS",
+        default_value = "false",
+        global = true
+    )]
+    reject_undeployed_ext_code_access: bool,
+
+    /// Reject ops without an aggregator whose signature is shorter than
+    /// `min_signature_length` before simulation
+    #[arg(
+        long = "reject_malformed_signatures",
+        name = "reject_malformed_signatures",
+        env = "REJECT_MALFORMED_SIGNATURES",
+        default_value = "false",
+        global = true
+    )]
+    reject_malformed_signatures: bool,
+
+    /// Minimum signature length in bytes when rejecting malformed signatures. The default
+    /// only rejects empty signatures.
+    #[arg(
+        long = "min_signature_length",
+        name = "min_signature_length",
+        env = "MIN_SIGNATURE_LENGTH",
+        default_value = "1",
+        global = true
+    )]
+    min_signature_length: usize,
+
+    /// Maximum number of storage slots an op's validation may touch. Ops that touch more
+    /// are rejected, bounding the memory each op's expected storage holds in the pool.
+    #[arg(
+        long = "max_op_expected_storage_slots",
+        name = "max_op_expected_storage_slots",
+        env = "MAX_OP_EXPECTED_STORAGE_SLOTS",
+        default_value = "10000",
+        global = true
+    )]
+    max_op_expected_storage_slots: usize,
+
+    /// Count the ops simulated, accepted, bundled and dropped by each mempool they
+    /// match, labeled by mempool id. Adds a series per mempool per metric.
+    #[arg(
+        long = "per_mempool_metrics",
+        name = "per_mempool_metrics",
+        env = "PER_MEMPOOL_METRICS",
+        default_value = "false",
+        global = true
+    )]
+    per_mempool_metrics: bool,
+
+    /// Maximum number of simulations that may be in flight at once across the process.
+    /// If not set, simulations are unbounded.
+    #[arg(
+        long = "max_concurrent_simulations",
+        name = "max_concurrent_simulations",
+        env = "MAX_CONCURRENT_SIMULATIONS",
+        global = true
+    )]
+    max_concurrent_simulations: Option<usize>,
+
+    /// Maximum number of accessed contracts whose code contributes to an operation's
+    /// code hash, chosen by ascending address. If not set, all accessed contracts are hashed.
+    #[arg(
+        long = "code_hash_addresses_limit",
+        name = "code_hash_addresses_limit",
+        env = "CODE_HASH_ADDRESSES_LIMIT",
+        global = true
+    )]
+    code_hash_addresses_limit: Option<usize>,
+
     /// Amount of blocks to search when calling eth_getUserOperationByHash.
     /// Defaults from 0 to latest block
     #[arg(
@@ -342,7 +662,7 @@ pub struct CommonArgs {
         long = "priority_fee_mode_kind",
         name = "priority_fee_mode_kind",
         env = "PRIORITY_FEE_MODE_KIND",
-        value_parser = PossibleValuesParser::new(["base_fee_percent", "priority_fee_increase_percent"]),
+        value_parser = PossibleValuesParser::new(["base_fee_percent", "priority_fee_increase_percent", "percent_of_base_fee"]),
         default_value = "priority_fee_increase_percent",
         global = true
     )]
@@ -409,6 +729,24 @@ pub struct CommonArgs {
     )]
     pub call_gas_allowed_error_pct: u128,
 
+    #[arg(
+        long = "verification_gas_multiplier_with_paymaster",
+        name = "verification_gas_multiplier_with_paymaster",
+        env = "VERIFICATION_GAS_MULTIPLIER_WITH_PAYMASTER",
+        default_value = "1.0",
+        global = true
+    )]
+    pub verification_gas_multiplier_with_paymaster: f64,
+
+    #[arg(
+        long = "verification_gas_multiplier_with_factory",
+        name = "verification_gas_multiplier_with_factory",
+        env = "VERIFICATION_GAS_MULTIPLIER_WITH_FACTORY",
+        default_value = "1.0",
+        global = true
+    )]
+    pub verification_gas_multiplier_with_factory: f64,
+
     #[arg(
         long = "max_gas_estimation_gas",
         name = "max_gas_estimation_gas",
@@ -537,6 +875,13 @@ impl CommonArgs {
                 .transaction_gas_limit_mult(self.max_bundle_transaction_gas_limit_ratio),
         }
     }
+
+    /// The chain's maximum verification gas, falling back to `max_verification_gas`
+    pub fn max_verification_gas(&self, chain_spec: &ChainSpec) -> u64 {
+        chain_spec
+            .max_verification_gas
+            .unwrap_or(self.max_verification_gas)
+    }
 }
 
 pub struct BundleLimits {
@@ -574,15 +919,16 @@ impl TryFromWithSpec<&CommonArgs> for EstimationSettings {
 
     fn try_from_with_spec(value: &CommonArgs, chain_spec: &ChainSpec) -> Result<Self, Self::Error> {
         let bundle_limits = value.bundle_limits(chain_spec);
+        let max_verification_gas = value.max_verification_gas(chain_spec);
 
-        if value.max_verification_gas
+        if max_verification_gas
             > bundle_limits
                 .max_bundle_execution_gas_limit
                 .saturating_sub(SIMULATION_GAS_OVERHEAD) as u64
         {
             anyhow::bail!(
                 "max_verification_gas ({}) must be less than max_bundle_execution_gas ({}) by at least {}",
-                value.max_verification_gas,
+                max_verification_gas,
                 bundle_limits.max_bundle_execution_gas_limit,
                 SIMULATION_GAS_OVERHEAD
             );
@@ -597,8 +943,8 @@ impl TryFromWithSpec<&CommonArgs> for EstimationSettings {
         }
 
         Ok(Self {
-            max_verification_gas: value.max_verification_gas as u128,
-            max_paymaster_verification_gas: value.max_verification_gas as u128,
+            max_verification_gas: max_verification_gas as u128,
+            max_paymaster_verification_gas: max_verification_gas as u128,
             max_paymaster_post_op_gas: bundle_limits.max_bundle_execution_gas_limit,
             max_bundle_execution_gas: bundle_limits.max_bundle_execution_gas_limit,
             max_gas_estimation_gas: value.max_gas_estimation_gas,
@@ -608,6 +954,10 @@ impl TryFromWithSpec<&CommonArgs> for EstimationSettings {
             verification_gas_allowed_error_pct: value.verification_gas_allowed_error_pct,
             call_gas_allowed_error_pct: value.call_gas_allowed_error_pct,
             max_gas_estimation_rounds: value.max_gas_estimation_rounds,
+            verification_gas_multiplier_with_paymaster: value
+                .verification_gas_multiplier_with_paymaster,
+            verification_gas_multiplier_with_factory: value
+                .verification_gas_multiplier_with_factory,
         })
     }
 }
@@ -620,6 +970,7 @@ impl TryFromWithSpec<&CommonArgs> for PrecheckSettings {
 
         Ok(Self {
             max_verification_gas: value.max_verification_gas as u128,
+            max_call_gas_limit: value.max_call_gas_limit as u128,
             max_bundle_execution_gas: bundle_limits.max_bundle_execution_gas_limit,
             max_uo_cost: value.max_uo_cost.unwrap_or(U256::MAX),
             bundle_priority_fee_overhead_percent: value.bundle_priority_fee_overhead_percent,
@@ -635,10 +986,10 @@ impl TryFromWithSpec<&CommonArgs> for PrecheckSettings {
     }
 }
 
-impl TryFrom<&CommonArgs> for SimulationSettings {
+impl TryFromWithSpec<&CommonArgs> for SimulationSettings {
     type Error = anyhow::Error;
 
-    fn try_from(value: &CommonArgs) -> Result<Self, Self::Error> {
+    fn try_from_with_spec(value: &CommonArgs, chain_spec: &ChainSpec) -> Result<Self, Self::Error> {
         if go_parse_duration::parse_duration(&value.tracer_timeout).is_err() {
             bail!("Invalid value for tracer_timeout, must be parsable by the ParseDuration function. See docs https://pkg.go.dev/time#ParseDuration")
         }
@@ -648,6 +999,45 @@ impl TryFrom<&CommonArgs> for SimulationSettings {
             min_stake_value: U256::from(value.min_stake_value),
             tracer_timeout: value.tracer_timeout.clone(),
             enable_unsafe_fallback: value.enable_unsafe_fallback,
+            max_op_calldata_bytes: value.max_op_calldata_bytes,
+            trusted_senders: value.trusted_senders.iter().copied().collect(),
+            max_concurrent_simulations: value.max_concurrent_simulations,
+            code_hash_addresses_limit: value.code_hash_addresses_limit,
+            allow_basefee: chain_spec.basefee_allowed_in_validation,
+            allow_origin: chain_spec.origin_allowed_in_validation,
+            min_priority_fee_per_gas: chain_spec.min_priority_fee_per_gas_in_validation as u128,
+            denied_paymasters: value.denied_paymasters.iter().copied().collect(),
+            denied_factories: value.denied_factories.iter().copied().collect(),
+            reject_zero_call_gas: value.reject_zero_call_gas,
+            allow_unstaked_associated_reads: value.allow_unstaked_associated_reads,
+            allow_sender_factory_associated_storage: value.allow_sender_factory_associated_storage,
+            min_pre_verification_gas_overhead: value.min_pre_verification_gas_overhead,
+            reject_empty_code_hash: value.reject_empty_code_hash,
+            include_validation_output: value.include_validation_output,
+            max_validation_call_depth: value.max_validation_call_depth,
+            stake_expiry_warning_window_secs: value.stake_expiry_warning_window_secs,
+            batch_state_reads: value.batch_state_reads,
+            include_gas_breakdown: value.include_gas_breakdown,
+            include_validation_logs: value.include_validation_logs,
+            require_factory_deploys_sender: value.require_factory_deploys_sender,
+            reject_cross_sender_associated_storage: value.reject_cross_sender_associated_storage,
+            max_valid_after_horizon: Duration::from_secs(value.max_valid_after_horizon_secs),
+            reject_undeployed_ext_code_access: value.reject_undeployed_ext_code_access,
+            min_signature_length: value
+                .reject_malformed_signatures
+                .then_some(value.min_signature_length),
+            max_expected_storage_slots: value.max_op_expected_storage_slots,
+            per_mempool_metrics: value.per_mempool_metrics,
+            allowed_delegatecall_targets: chain_spec
+                .allowed_delegatecall_targets
+                .iter()
+                .copied()
+                .collect(),
+            allowed_create2_deployers: chain_spec
+                .allowed_create2_deployers
+                .iter()
+                .copied()
+                .collect(),
         })
     }
 }
@@ -829,9 +1219,10 @@ where
 pub fn construct_providers(
     args: &CommonArgs,
     chain_spec: &ChainSpec,
+    node_http: &str,
 ) -> anyhow::Result<impl Providers + 'static> {
     let provider = Arc::new(rundler_provider::new_alloy_provider(
-        args.node_http.as_ref().context("must provide node_http")?,
+        node_http,
         args.provider_client_timeout_seconds,
     )?);
     let (da_gas_oracle, da_gas_oracle_sync) =
@@ -849,7 +1240,7 @@ pub fn construct_providers(
     } else {
         Some(AlloyEntryPointV0_6::new(
             chain_spec.clone(),
-            args.max_verification_gas,
+            args.max_verification_gas(chain_spec),
             max_bundle_execution_gas,
             args.max_gas_estimation_gas,
             max_bundle_execution_gas,
@@ -863,7 +1254,7 @@ pub fn construct_providers(
     } else {
         Some(AlloyEntryPointV0_7::new(
             chain_spec.clone(),
-            args.max_verification_gas,
+            args.max_verification_gas(chain_spec),
             max_bundle_execution_gas,
             args.max_gas_estimation_gas,
             max_bundle_execution_gas,
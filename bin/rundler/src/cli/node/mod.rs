@@ -49,12 +49,13 @@ pub struct NodeCliArgs {
     rpc: RpcArgs,
 }
 
-pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
+pub async fn spawn_tasks<T: TaskSpawnerExt + 'static, P: Providers + 'static>(
     task_spawner: T,
     chain_spec: ChainSpec,
     bundler_args: NodeCliArgs,
     common_args: CommonArgs,
-    providers: impl Providers + 'static,
+    providers: P,
+    canary_providers: Option<P>,
     mempool_configs: Option<MempoolConfigs>,
     entry_point_builders: Option<EntryPointBuilderConfigs>,
 ) -> anyhow::Result<()> {
@@ -151,6 +152,7 @@ pub async fn spawn_tasks<T: TaskSpawnerExt + 'static>(
         builder_builder,
         pool_handle.clone(),
         providers.clone(),
+        canary_providers,
         signer_manager,
     )
     .spawn(task_spawner.clone())
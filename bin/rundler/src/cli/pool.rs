@@ -160,6 +160,14 @@ pub struct PoolArgs {
     )]
     pub reputation_tracking_enabled: bool,
 
+    #[arg(
+        long = "pool.reputation_grace_period_secs",
+        name = "pool.reputation_grace_period_secs",
+        env = "POOL_REPUTATION_GRACE_PERIOD_SECS",
+        default_value = "0"
+    )]
+    pub reputation_grace_period_secs: u64,
+
     #[arg(
         long = "pool.drop_min_num_blocks",
         name = "pool.drop_min_num_blocks",
@@ -215,12 +223,13 @@ impl PoolArgs {
             blocklist: blocklist.clone(),
             allowlist: allowlist.clone(),
             precheck_settings: common.try_into_with_spec(&chain_spec)?,
-            sim_settings: common.try_into()?,
+            sim_settings: common.try_into_with_spec(&chain_spec)?,
             throttled_entity_mempool_count: self.throttled_entity_mempool_count,
             throttled_entity_live_blocks: self.throttled_entity_live_blocks,
             paymaster_tracking_enabled: self.paymaster_tracking_enabled,
             paymaster_cache_length: self.paymaster_cache_length,
             reputation_tracking_enabled: self.reputation_tracking_enabled,
+            reputation_grace_period: Duration::from_secs(self.reputation_grace_period_secs),
             drop_min_num_blocks: self.drop_min_num_blocks,
             da_gas_tracking_enabled,
             execution_gas_limit_efficiency_reject_threshold: common
@@ -256,6 +265,7 @@ impl PoolArgs {
             chain_spec,
             unsafe_mode: common.unsafe_mode,
             http_url: common.node_http.clone().context("must provide node_http")?,
+            ws_url: common.node_ws.clone(),
             chain_poll_interval: Duration::from_millis(self.chain_poll_interval_millis),
             chain_max_sync_retries: self.chain_sync_max_retries,
             pool_configs,
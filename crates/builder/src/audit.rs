@@ -0,0 +1,85 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{fmt::Debug, sync::Mutex};
+
+use alloy_primitives::{Address, B256};
+use async_trait::async_trait;
+use rundler_types::{GasFees, UserOperationVariant, UserOpsPerAggregator};
+
+/// A bundle transaction submitted by the builder, with everything needed to replay it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleAuditRecord {
+    /// Tag of the builder that submitted the bundle
+    pub builder_tag: String,
+    /// Entry point the bundle was sent to
+    pub entry_point: Address,
+    /// Hash of the bundle transaction
+    pub tx_hash: B256,
+    /// Nonce of the bundle transaction
+    pub nonce: u64,
+    /// Number of the block the bundle was built against
+    pub block_number: u64,
+    /// Hash of the block the bundle was built against
+    pub block_hash: B256,
+    /// Operations in the bundle, grouped by aggregator
+    pub ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperationVariant>>,
+    /// Beneficiary of the bundle fees
+    pub beneficiary: Address,
+    /// Gas limit of the bundle transaction
+    pub gas_limit: u64,
+    /// Gas fees of the bundle transaction
+    pub gas_fees: GasFees,
+}
+
+/// Durable storage for the bundles the builder submits, for post-mortems and replay.
+///
+/// Unlike builder events, which are best effort, a sink is awaited for every submitted
+/// bundle. Errors are logged and don't affect bundling.
+#[async_trait]
+pub trait BundleAuditSink: Send + Sync + Debug {
+    /// Record a submitted bundle
+    async fn record(&self, record: BundleAuditRecord) -> anyhow::Result<()>;
+}
+
+/// Sink that discards every bundle
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopBundleAuditSink;
+
+#[async_trait]
+impl BundleAuditSink for NoopBundleAuditSink {
+    async fn record(&self, _record: BundleAuditRecord) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink that keeps every bundle in memory, for tests
+#[derive(Debug, Default)]
+pub struct InMemoryBundleAuditSink {
+    records: Mutex<Vec<BundleAuditRecord>>,
+}
+
+impl InMemoryBundleAuditSink {
+    /// The bundles recorded so far, in submission order
+    pub fn records(&self) -> Vec<BundleAuditRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl BundleAuditSink for InMemoryBundleAuditSink {
+    async fn record(&self, record: BundleAuditRecord) -> anyhow::Result<()> {
+        self.records.lock().unwrap().push(record);
+        Ok(())
+    }
+}
@@ -16,11 +16,12 @@ use std::{
     future::Future,
     mem,
     pin::Pin,
-    sync::Arc,
-    time::Instant,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
-use alloy_primitives::{Address, Bytes, B256, U256};
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
+use alloy_sol_types::SolCall;
 use anyhow::Context;
 use async_trait::async_trait;
 use futures::future;
@@ -29,34 +30,66 @@ use metrics::{Counter, Histogram};
 use metrics_derive::Metrics;
 #[cfg(test)]
 use mockall::automock;
+use rundler_contracts::{
+    v0_6::IAggregator as IAggregatorV0_6, v0_7::IAggregator as IAggregatorV0_7,
+};
 use rundler_provider::{
-    BundleHandler, DAGasOracleSync, DAGasProvider, EntryPoint, EvmProvider, FeeEstimator,
-    HandleOpsOut, ProvidersWithEntryPointT,
+    AccountOverride, BlockId, BundleHandler, DAGasOracleSync, DAGasProvider, EntryPoint,
+    EvmProvider, FeeEstimator, FixedGasPriceSource, GasAttribution, HandleOpsOut, ProviderError,
+    ProvidersWithEntryPointT, StateOverride, TransactionRequest,
 };
-use rundler_sim::{SimulationError, SimulationResult, Simulator, ViolationError};
+use rundler_sim::{MempoolMetrics, SimulationError, SimulationResult, Simulator, ViolationError};
 use rundler_types::{
     aggregator::SignatureAggregatorResult,
     chain::ChainSpec,
     da::DAGasBlockData,
     pool::{PoolOperation, SimulationViolation},
     proxy::SubmissionProxy,
-    BundleExpectedStorage, Entity, EntityInfo, EntityInfos, EntityType, EntityUpdate,
-    EntityUpdateType, EntryPointVersion, ExpectedStorage, GasFees, Timestamp, UserOperation,
-    UserOperationVariant, UserOpsPerAggregator, ValidTimeRange, ValidationRevert,
-    BUNDLE_BYTE_OVERHEAD, TIME_RANGE_BUFFER,
+    AaError, BundleExpectedStorage, Entity, EntityInfo, EntityInfos, EntityType, EntityUpdate,
+    EntityUpdateType, EntryPointVersion, ExpectedStorage, GasFees, PriorityFeeMode, Timestamp,
+    UserOperation, UserOperationId, UserOperationVariant, UserOpsPerAggregator, ValidTimeRange,
+    ValidationRevert, BUNDLE_BYTE_OVERHEAD, TIME_RANGE_BUFFER,
+};
+use rundler_utils::{
+    cache::LruMap,
+    emit::WithEntryPoint,
+    eth,
+    guard_timer::CustomTimerGuard,
+    math,
+    retry::{self, RetryOpts},
 };
-use rundler_utils::{emit::WithEntryPoint, eth, guard_timer::CustomTimerGuard, math};
 use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 
-use crate::emit::{BuilderEvent, ConditionNotMetReason, OpRejectionReason, SkipReason};
+use crate::{
+    emit::{BuilderEvent, ConditionNotMetReason, OpRejectionReason, SkipReason},
+    price_oracle::TokenPriceOracle,
+};
 
 /// Extra buffer percent to add on the bundle transaction gas estimate to be sure it will be enough
 const BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT: u32 = 5;
 
+/// Maximum number of operations to track skip counts for
+const SKIP_COUNTS_CACHE_SIZE: u32 = 4096;
+
+/// Maximum number of not yet valid operations to track deferrals for
+const DEFERRED_OPS_CACHE_SIZE: u32 = 4096;
+
+/// Maximum number of operations to track candidate round counts for
+const OP_ROUNDS_CACHE_SIZE: u32 = 4096;
+
+/// Backoff between retries of a bundle gas estimation call that failed in transport
+const ESTIMATION_RETRY_MIN_WAIT: Duration = Duration::from_millis(100);
+const ESTIMATION_RETRY_MAX_WAIT: Duration = Duration::from_secs(1);
+const ESTIMATION_RETRY_MAX_JITTER: Duration = Duration::from_millis(50);
+
+/// Funded sender of the simulated transfer used to check that a beneficiary can receive ETH
+const BENEFICIARY_CHECK_SENDER: Address = Address::repeat_byte(0xbe);
+
 #[derive(Debug)]
 pub(crate) struct Bundle<UO: UserOperation> {
     pub(crate) ops_per_aggregator: Vec<UserOpsPerAggregator<UO>>,
+    pub(crate) beneficiary: Address,
     pub(crate) gas_estimate: u64,
     pub(crate) gas_fees: GasFees,
     pub(crate) expected_storage: ExpectedStorage,
@@ -68,6 +101,7 @@ impl<UO: UserOperation> Default for Bundle<UO> {
     fn default() -> Self {
         Self {
             ops_per_aggregator: Vec::new(),
+            beneficiary: Address::ZERO,
             gas_estimate: 0,
             gas_fees: GasFees::default(),
             expected_storage: ExpectedStorage::default(),
@@ -92,6 +126,25 @@ impl<UO: UserOperation> Bundle<UO> {
     pub(crate) fn iter_ops(&self) -> impl Iterator<Item = &UO> + '_ {
         self.ops_per_aggregator.iter().flat_map(|ops| &ops.user_ops)
     }
+
+    /// Deterministic id of the bundle when built by the builder at `builder_index`, for
+    /// correlating it across the proposer, sender, tracker and logs. See [`bundle_id`].
+    pub(crate) fn id(&self, builder_index: u64) -> B256 {
+        bundle_id(self.iter_ops().map(|op| op.hash()), builder_index)
+    }
+}
+
+/// Returns the keccak of the sorted op hashes followed by the big-endian builder index,
+/// so the id of a bundle doesn't depend on the order of its ops
+pub(crate) fn bundle_id(op_hashes: impl IntoIterator<Item = B256>, builder_index: u64) -> B256 {
+    let mut op_hashes = op_hashes.into_iter().collect::<Vec<_>>();
+    op_hashes.sort();
+    let mut preimage = Vec::with_capacity(op_hashes.len() * 32 + 8);
+    for op_hash in op_hashes {
+        preimage.extend_from_slice(op_hash.as_slice());
+    }
+    preimage.extend_from_slice(&builder_index.to_be_bytes());
+    keccak256(preimage)
 }
 
 #[async_trait]
@@ -123,6 +176,24 @@ pub(crate) trait BundleProposer: Send + Sync {
 
     /// Notifies the proposer that a condition was not met during the last bundle proposal
     fn notify_condition_not_met(&mut self);
+
+    /// Replaces the priority fee mode required of ops, e.g. as adjusted by the sender's
+    /// priority fee controller
+    fn set_priority_fee_mode(&mut self, mode: PriorityFeeMode);
+
+    /// Re-simulates `ops` against `block_hash`, returning the hashes of the ops
+    /// that are no longer valid
+    async fn find_invalid_ops(
+        &self,
+        ops: Vec<PoolOperation>,
+        block_hash: B256,
+    ) -> BundleProposerResult<Vec<B256>>;
+
+    /// Returns the lowest `max_fee_per_gas` an op can pay to be bundled at `block_hash`,
+    /// combining the current base fee, the priority fee mode and the chain's priority fee
+    /// floor. Read only, for telling clients what fee to pay.
+    #[allow(dead_code)]
+    async fn min_acceptable_max_fee(&self, block_hash: B256) -> BundleProposerResult<U256>;
 }
 
 pub(crate) type BundleProposerResult<T> = std::result::Result<T, BundleProposerError>;
@@ -145,9 +216,39 @@ pub(crate) struct BundleProposerImpl<EP, BP> {
     bundle_providers: BP,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     condition_not_met_notified: bool,
+    skip_counts: LruMap<B256, u64>,
+    // ops skipped this round because their simulation or gas estimation failed, only
+    // these skips count towards `max_skip_rounds`
+    failed_skips: Mutex<HashSet<B256>>,
+    deferred_ops: LruMap<B256, Timestamp>,
+    // number of bundle rounds each op has been a candidate in, for weighted fairness
+    op_rounds: LruMap<B256, u64>,
+    // whether each probed aggregator implements `IAggregator`
+    aggregator_interfaces: HashMap<Address, bool>,
+    // mempools each op simulated this round matched, for per mempool metrics
+    round_op_mempools: HashMap<B256, Vec<B256>>,
     metrics: BuilderProposerMetrics,
 }
 
+/// How candidate ops are ordered before the bundle is capped
+#[derive(Clone, Copy, Debug, Default)]
+pub enum SelectionStrategy {
+    /// Keep the order ops are returned from the pool in
+    #[default]
+    PoolOrder,
+    /// Order by effective gas price, highest first
+    EffectiveGasPrice,
+    /// Order by effective gas price weighted by age, so low fee ops that have been
+    /// candidates for many rounds are eventually selected over fresh higher fee ops.
+    ///
+    /// An op's weight is `gas_price * (1 + age_weight * rounds)`, where `rounds` is the
+    /// number of earlier bundle rounds it was a candidate in.
+    WeightedFairness {
+        /// Weight of each round an op has waited relative to its gas price
+        age_weight: f64,
+    },
+}
+
 #[derive(Debug)]
 pub(crate) struct Settings {
     pub(crate) chain_spec: ChainSpec,
@@ -158,6 +259,76 @@ pub(crate) struct Settings {
     pub(crate) max_expected_storage_slots: usize,
     pub(crate) verification_gas_limit_efficiency_reject_threshold: f64,
     pub(crate) submission_proxy: Option<Arc<dyn SubmissionProxy>>,
+    pub(crate) dump_calldata: bool,
+    pub(crate) max_skip_rounds: Option<u64>,
+    pub(crate) max_bundle_calldata_bytes: Option<usize>,
+    pub(crate) estimation_retries: u64,
+    pub(crate) max_whittle_iterations: Option<u64>,
+    pub(crate) check_beneficiary_per_bundle: bool,
+    /// If set, bundle fees come from this fixed source instead of the provider
+    pub(crate) fixed_gas_price: Option<FixedGasPriceSource>,
+    /// Simulate the whole bundle after estimation and raise the gas limit to the
+    /// simulated gas used if it is higher
+    pub(crate) simulate_bundle_gas: bool,
+    /// Percentage added to the estimated bundle gas limit, as a margin for state that
+    /// changes between estimation and inclusion
+    pub(crate) gas_estimate_buffer_percent: u32,
+    /// Trace each bundle after estimation and emit the gas used by each op's validation,
+    /// execution and postOp, for diagnostics
+    pub(crate) attribute_bundle_gas: bool,
+    /// If set, only ops supported by at least one of these mempools are bundled
+    pub(crate) allowed_mempools: Option<HashSet<B256>>,
+    /// Skip ops whose validAfter is in the future and don't simulate them again until
+    /// then, instead of rejecting them
+    pub(crate) defer_not_yet_valid_ops: bool,
+    /// Maximum time to spend building a bundle. Ops still simulating once it has passed
+    /// are left out of the bundle.
+    pub(crate) max_build_duration: Option<Duration>,
+    /// If set, op fees are required by this mode instead of the fee estimator's
+    pub(crate) priority_fee_mode: Option<PriorityFeeMode>,
+    /// Address that receives bundle fees. If `None`, the sender EOA is paid.
+    pub(crate) beneficiary: Option<Address>,
+    /// Maximum number of ops using the same paymaster in a bundle. Ops past the cap are
+    /// left in the pool for a later bundle.
+    pub(crate) max_ops_per_paymaster: Option<usize>,
+    /// Maximum summed prefund, in wei, of the ops in a bundle. Bounds the capital the
+    /// bundler has at risk per bundle. Ops past the cap are left in the pool.
+    pub(crate) max_bundle_prefund: Option<U256>,
+    /// Minimum time that must remain before an op's validUntil for it to be included.
+    /// Ops expiring sooner are still valid, so they are left in the pool, not rejected.
+    pub(crate) min_validity_remaining: Duration,
+    /// How candidate ops are ordered before the bundle is capped
+    pub(crate) selection_strategy: SelectionStrategy,
+    /// Oracles valuing the ops of token paymasters in ETH, by paymaster address. Ops of
+    /// other paymasters are ranked by their ETH gas price.
+    pub(crate) token_price_oracles: HashMap<Address, Arc<dyn TokenPriceOracle>>,
+    /// Fees used in place of an op's own fees when ranking it and checking its prefund,
+    /// by op id. The op itself is never changed, so its hash is unaffected.
+    pub(crate) fee_overrides: HashMap<UserOperationId, GasFees>,
+    /// Check that the sender of each op without a paymaster can cover its prefund from
+    /// its entry point deposit and ETH balance. Ops that can't are left in the pool.
+    pub(crate) check_sender_eth_balance: bool,
+    /// Percentage above its prefund that a self-paying sender's funds must cover, as a
+    /// margin for state that changes before inclusion
+    pub(crate) sender_deposit_buffer_percent: u32,
+    /// Maximum number of distinct aggregators in a bundle. Ops using other aggregators
+    /// are left in the pool for a later bundle.
+    pub(crate) max_aggregators_per_bundle: usize,
+    /// Probe each aggregator before bundling its ops, rejecting the ops of aggregators
+    /// that don't implement `IAggregator`
+    pub(crate) verify_aggregator_interface: bool,
+    /// Maximum summed call, verification and pre-verification gas limits of an op. Ops
+    /// over it are left in the pool rather than taking up most of a bundle.
+    pub(crate) max_op_total_gas: u128,
+    /// Count the ops bundled and dropped by each mempool they matched, labeled by
+    /// mempool id
+    pub(crate) per_mempool_metrics: bool,
+    /// If set, a block hash more than this many blocks behind the head is replaced with
+    /// the head's before proposing, so ops aren't simulated against stale state
+    pub(crate) max_sim_block_age: Option<u64>,
+    /// If set, ops are dropped from the end of the bundle until its gas limit fits under
+    /// this ceiling. Dropped ops are left in the pool for a later bundle.
+    pub(crate) max_bundle_gas_limit: Option<u64>,
 }
 
 #[async_trait]
@@ -174,7 +345,6 @@ where
         required_fees: Option<GasFees>,
     ) -> BundleProposerResult<(GasFees, u128)> {
         Ok(self
-            .ep_providers
             .fee_estimator()
             .required_bundle_fees(block_hash, required_fees)
             .await?)
@@ -184,6 +354,52 @@ where
         self.condition_not_met_notified = true;
     }
 
+    fn set_priority_fee_mode(&mut self, mode: PriorityFeeMode) {
+        self.settings.priority_fee_mode = Some(mode);
+    }
+
+    async fn find_invalid_ops(
+        &self,
+        ops: Vec<PoolOperation>,
+        block_hash: B256,
+    ) -> BundleProposerResult<Vec<B256>> {
+        let results = future::join_all(ops.iter().map(|op| {
+            self.bundle_providers.simulator().simulate_validation(
+                op.uo.clone().into(),
+                op.perms.trusted,
+                block_hash.into(),
+                Some(op.expected_code_hash),
+            )
+        }))
+        .await;
+
+        // Only ops with violations are invalid, other errors may be transient
+        Ok(ops
+            .iter()
+            .zip(results)
+            .filter_map(|(op, result)| match result {
+                Err(SimulationError {
+                    violation_error: ViolationError::Violations(_),
+                    ..
+                }) => Some(op.uo.hash()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    async fn min_acceptable_max_fee(&self, block_hash: B256) -> BundleProposerResult<U256> {
+        // the bundle fees carry the base fee overhead, an op only needs to cover the base fee
+        let (bundle_fees, base_fee) = self.estimate_gas_fees(block_hash, None).await?;
+        let required_op_fees = self.required_op_fees(bundle_fees);
+        // ops paying less than the floor fail simulation, regardless of the bundle fees
+        let max_priority_fee_per_gas = required_op_fees.max_priority_fee_per_gas.max(
+            self.settings
+                .chain_spec
+                .min_priority_fee_per_gas_in_validation as u128,
+        );
+        Ok(U256::from(base_fee + max_priority_fee_per_gas))
+    }
+
     async fn make_bundle(
         &mut self,
         ops: Vec<PoolOperation>,
@@ -192,6 +408,127 @@ where
         min_gas_fees: Option<GasFees>,
         is_replacement: bool,
     ) -> BundleProposerResult<Bundle<Self::UO>> {
+        let considered = ops.iter().map(|op| op.uo.hash()).collect::<Vec<_>>();
+        self.round_op_mempools.clear();
+        self.failed_skips.get_mut().unwrap().clear();
+        let result = self
+            .propose_round(
+                ops,
+                block_hash,
+                max_bundle_fee,
+                min_gas_fees,
+                is_replacement,
+            )
+            .await;
+        self.emit_round_summary(considered, result.as_ref().ok());
+        result
+    }
+}
+
+impl<EP, BP> BundleProposerImpl<EP, BP>
+where
+    EP: ProvidersWithEntryPointT,
+    BP: BundleProposerProvidersT,
+{
+    async fn propose_round(
+        &mut self,
+        ops: Vec<PoolOperation>,
+        block_hash: B256,
+        max_bundle_fee: U256,
+        min_gas_fees: Option<GasFees>,
+        is_replacement: bool,
+    ) -> BundleProposerResult<Bundle<EP::UO>> {
+        let block_hash = self.fresh_block_hash(block_hash).await?;
+
+        // ops deferred until their validAfter are not simulated again before then
+        let now = Timestamp::now();
+        let ops = ops
+            .into_iter()
+            .filter(|op| !self.is_deferred(&op.uo.hash(), now))
+            .collect::<Vec<_>>();
+
+        let Some(max_skip_rounds) = self.settings.max_skip_rounds else {
+            return self
+                .build_bundle(
+                    ops,
+                    block_hash,
+                    max_bundle_fee,
+                    min_gas_fees,
+                    is_replacement,
+                )
+                .await;
+        };
+
+        // Reject any ops that have been skipped for too many rounds, they are unlikely to
+        // ever be bundled and are wasting proposer cycles.
+        let mut expired_ops = vec![];
+        let mut candidate_ops = vec![];
+        let mut candidate_hashes = vec![];
+        for op in ops {
+            let op_hash = op.uo.hash();
+            match self.skip_counts.peek(&op_hash) {
+                Some(&rounds) if rounds >= max_skip_rounds => {
+                    self.emit(BuilderEvent::rejected_op(
+                        self.builder_tag.clone(),
+                        op_hash,
+                        OpRejectionReason::MaxSkipRoundsExceeded { rounds },
+                    ));
+                    self.skip_counts.remove(&op_hash);
+                    expired_ops.push(op.uo.into());
+                }
+                _ => {
+                    candidate_hashes.push(op_hash);
+                    candidate_ops.push(op);
+                }
+            }
+        }
+
+        if candidate_ops.is_empty() && !expired_ops.is_empty() {
+            return Ok(Bundle {
+                rejected_ops: expired_ops,
+                ..Default::default()
+            });
+        }
+
+        let result = self
+            .build_bundle(
+                candidate_ops,
+                block_hash,
+                max_bundle_fee,
+                min_gas_fees,
+                is_replacement,
+            )
+            .await;
+
+        match result {
+            Ok(mut bundle) => {
+                self.update_skip_counts(&candidate_hashes, &bundle);
+                bundle.rejected_ops.extend(expired_ops);
+                Ok(bundle)
+            }
+            Err(BundleProposerError::NoOperationsAfterFeeFilter) => {
+                self.update_skip_counts(&candidate_hashes, &Bundle::default());
+                if expired_ops.is_empty() {
+                    Err(BundleProposerError::NoOperationsAfterFeeFilter)
+                } else {
+                    Ok(Bundle {
+                        rejected_ops: expired_ops,
+                        ..Default::default()
+                    })
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn build_bundle(
+        &mut self,
+        ops: Vec<PoolOperation>,
+        block_hash: B256,
+        max_bundle_fee: U256,
+        min_gas_fees: Option<GasFees>,
+        is_replacement: bool,
+    ) -> BundleProposerResult<Bundle<EP::UO>> {
         let timer = Instant::now();
         let (bundle_fees, base_fee) = self.estimate_gas_fees(block_hash, min_gas_fees).await?;
 
@@ -200,14 +537,21 @@ where
         let required_op_fees = if is_replacement {
             bundle_fees
         } else {
-            self.ep_providers
-                .fee_estimator()
-                .required_op_fees(bundle_fees)
+            self.required_op_fees(bundle_fees)
         };
         let all_paymaster_addresses = ops
             .iter()
             .filter_map(|op| op.uo.paymaster())
             .collect::<Vec<Address>>();
+        // without the check, no sender funds are loaded and no op is limited by them
+        let self_paying_senders = if self.settings.check_sender_eth_balance {
+            ops.iter()
+                .filter(|op| op.uo.paymaster().is_none())
+                .map(|op| op.uo.sender())
+                .collect::<HashSet<Address>>()
+        } else {
+            HashSet::new()
+        };
 
         let da_block_data = if self.settings.da_gas_tracking_enabled
             && self.ep_providers.da_gas_oracle_sync().is_some()
@@ -250,46 +594,138 @@ where
             return Err(BundleProposerError::NoOperationsAfterFeeFilter);
         }
 
+        // (1.4) Order by the selection strategy, the highest priority ops are considered first
+        let ops = self.order_for_selection(ops, base_fee);
+
+        // (1.5) handleOps pays a single beneficiary, only bundle the ops routed to the
+        // beneficiary of the highest priority op. The rest wait for a later bundle.
+        let (beneficiary, ops) = take_beneficiary_group(ops, |op| self.beneficiary_for(&op.op));
+        if self.settings.check_beneficiary_per_bundle {
+            check_beneficiary_can_receive(
+                self.ep_providers.evm(),
+                beneficiary,
+                Some(block_hash.into()),
+            )
+            .await?;
+        }
+
+        // (1.6) handleOps executes ops in array order, so a sender's ops sharing a nonce key
+        // must be in nonce order regardless of the order the pool returned them in
+        let ops = order_by_nonce(ops, |op| (op.op.uo.sender(), op.op.uo.nonce()));
+
         // (2) Limit the amount of operations for simulation
         let (ops, gas_limit) = self.limit_user_operations_for_simulation(ops);
 
+        // (2.5) Reject ops whose aggregator isn't an `IAggregator`, they would revert the bundle
+        let (ops, nonconforming_aggregator_ops) = if self.settings.verify_aggregator_interface {
+            self.reject_nonconforming_aggregators(ops, block_hash).await
+        } else {
+            (ops, vec![])
+        };
+
         debug!(
             "Bundle proposal after gas limit had {} ops and {:?} gas limit",
             ops.len(),
             gas_limit
         );
 
-        // (3) simulate ops
+        // (3) simulate ops, giving up on any not finished by the build deadline
+        let simulation_timeout = self
+            .settings
+            .max_build_duration
+            .map(|max| max.saturating_sub(timer.elapsed()));
         let simulation_futures = ops
             .into_iter()
-            .map(|op| self.simulate_op(op, block_hash))
+            .map(|op| {
+                let simulation = self.simulate_op(op, block_hash);
+                async move {
+                    match simulation_timeout {
+                        Some(timeout) => tokio::time::timeout(timeout, simulation).await.ok(),
+                        None => Some(simulation.await),
+                    }
+                }
+            })
             .collect::<Vec<_>>();
 
         let ops_with_simulations_future = future::join_all(simulation_futures);
         let balances_by_paymaster_future =
             self.get_balances_by_paymaster(all_paymaster_addresses, block_hash);
-
-        let (ops_with_simulations, balances_by_paymaster) =
-            tokio::join!(ops_with_simulations_future, balances_by_paymaster_future);
+        let funds_by_sender_future = self.get_funds_by_sender(self_paying_senders, block_hash);
+        let block_timestamp_future = self.get_block_timestamp(block_hash);
+
+        let (ops_with_simulations, balances_by_paymaster, funds_by_sender, block_timestamp) = tokio::join!(
+            ops_with_simulations_future,
+            balances_by_paymaster_future,
+            funds_by_sender_future,
+            block_timestamp_future
+        );
 
         let balances_by_paymaster = balances_by_paymaster?;
-        let ops_with_simulations = ops_with_simulations
+        let funds_by_sender = funds_by_sender?;
+        let unconsidered_ops = ops_with_simulations
+            .iter()
+            .filter(|op| op.is_none())
+            .count();
+        if unconsidered_ops > 0 {
+            if let Some(max_build_duration) = self.settings.max_build_duration {
+                warn!("Bundle build exceeded {max_build_duration:?}, {unconsidered_ops} ops were not considered");
+                self.emit(BuilderEvent::bundle_build_truncated(
+                    self.builder_tag.clone(),
+                    max_build_duration,
+                    unconsidered_ops,
+                ));
+            }
+        }
+        let mut ops_with_simulations = ops_with_simulations
             .into_iter()
             .flatten()
+            .flatten()
             .collect::<Vec<_>>();
+        if self.settings.per_mempool_metrics {
+            self.round_op_mempools = ops_with_simulations
+                .iter()
+                .filter_map(|(po, simulation)| {
+                    let simulation = simulation.as_ref().ok()?;
+                    Some((po.op.uo.hash(), simulation.mempools.clone()))
+                })
+                .collect();
+        }
+        if self.settings.defer_not_yet_valid_ops {
+            ops_with_simulations =
+                self.defer_not_yet_valid_ops(ops_with_simulations, block_timestamp);
+        }
         let mut context = self
             .assemble_context(
                 max_bundle_fee,
                 bundle_fees.max_fee_per_gas,
                 ops_with_simulations,
                 balances_by_paymaster,
+                funds_by_sender,
+                block_timestamp,
             )
             .await;
+        context.beneficiary = beneficiary;
+        context.rejected_ops.extend(nonconforming_aggregator_ops);
+        let mut whittle_iterations = 0;
         while !context.is_empty() {
             let gas_estimate = self
                 .estimate_gas_rejecting_failed_ops(&mut context, bundle_fees)
                 .await?;
             if let Some(gas_estimate) = gas_estimate {
+                let gas_estimate = math::increase_by_percent(
+                    gas_estimate,
+                    self.settings.gas_estimate_buffer_percent,
+                );
+
+                // never send a bundle with less gas than its estimate, trim it to fit instead
+                if self
+                    .settings
+                    .max_bundle_gas_limit
+                    .is_some_and(|max| gas_estimate > max)
+                {
+                    self.remove_last_op(&mut context).await;
+                    continue;
+                }
                 tracing::debug!(
                     "Bundle proposal succeeded with {} ops and {:?} gas limit",
                     context.iter_ops().count(),
@@ -310,8 +746,20 @@ where
                 self.metrics
                     .bundle_build_ms
                     .record(timer.elapsed().as_millis() as f64);
+
+                let ops_per_aggregator = context.to_ops_per_aggregator();
+                if self.settings.dump_calldata {
+                    self.emit_bundle_calldata(
+                        &ops_per_aggregator,
+                        beneficiary,
+                        gas_estimate,
+                        bundle_fees,
+                    );
+                }
+
                 return Ok(Bundle {
-                    ops_per_aggregator: context.to_ops_per_aggregator(),
+                    ops_per_aggregator,
+                    beneficiary,
                     gas_estimate,
                     gas_fees: bundle_fees,
                     expected_storage: context.bundle_expected_storage.inner,
@@ -321,6 +769,20 @@ where
             }
 
             self.metrics.bundle_simulation_failures.increment(1);
+            whittle_iterations += 1;
+            if self
+                .settings
+                .max_whittle_iterations
+                .is_some_and(|max| whittle_iterations >= max)
+            {
+                // remaining ops are left in the pool for the next round
+                warn!("Bundle gas estimation failed {whittle_iterations} times. Giving up on this round.");
+                self.failed_skips
+                    .get_mut()
+                    .unwrap()
+                    .extend(context.iter_ops().map(|op| op.hash()));
+                break;
+            }
             info!("Bundle gas estimation failed. Retrying after removing rejected op(s).");
         }
 
@@ -344,6 +806,14 @@ struct BuilderProposerMetrics {
     bundle_simulation_failures: Counter,
     #[metric(describe = "the distribution of bundle simulation time.")]
     bundle_simulation_ms: Histogram,
+    #[metric(
+        describe = "the number of simulated ops using the same address for multiple entity roles."
+    )]
+    entity_role_overlaps: Counter,
+    #[metric(
+        describe = "the number of rounds that proposed on the head block because the given block was too old."
+    )]
+    stale_block_refreshes: Counter,
 }
 
 impl<EP, BP> BundleProposerImpl<EP, BP>
@@ -366,10 +836,260 @@ where
             settings,
             event_sender,
             condition_not_met_notified: false,
+            skip_counts: LruMap::new(SKIP_COUNTS_CACHE_SIZE),
+            failed_skips: Mutex::new(HashSet::new()),
+            deferred_ops: LruMap::new(DEFERRED_OPS_CACHE_SIZE),
+            op_rounds: LruMap::new(OP_ROUNDS_CACHE_SIZE),
+            aggregator_interfaces: HashMap::new(),
+            round_op_mempools: HashMap::new(),
             metrics: BuilderProposerMetrics::default(),
         }
     }
 
+    // Reports how each op given to the round was resolved. Without a bundle, every op
+    // remains in the pool for a later round.
+    fn emit_round_summary(&self, considered: Vec<B256>, bundle: Option<&Bundle<EP::UO>>) {
+        let (included, dropped): (HashSet<_>, HashSet<_>) =
+            bundle.map_or_else(Default::default, |bundle| {
+                (
+                    bundle.iter_ops().map(|op| op.hash()).collect(),
+                    bundle.rejected_ops.iter().map(|op| op.hash()).collect(),
+                )
+            });
+        let (included, deferred, dropped) = split_round_ops(&considered, &included, &dropped);
+        if self.settings.per_mempool_metrics {
+            self.record_mempool_metrics(&included, &dropped);
+        }
+        self.emit(BuilderEvent::round_summary(
+            self.builder_tag.clone(),
+            considered,
+            included,
+            deferred,
+            dropped,
+        ));
+    }
+
+    // Ops that didn't simulate successfully this round matched no mempool, so aren't
+    // counted
+    fn record_mempool_metrics(&self, included: &[B256], dropped: &[B256]) {
+        let mempools = |hashes: &[B256]| {
+            hashes
+                .iter()
+                .filter_map(|hash| self.round_op_mempools.get(hash))
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        for id in mempools(included) {
+            MempoolMetrics::for_mempool(id).ops_bundled.increment(1);
+        }
+        for id in mempools(dropped) {
+            MempoolMetrics::for_mempool(id).ops_dropped.increment(1);
+        }
+    }
+
+    // Ops included in or rejected from the bundle no longer need tracking. Of the other
+    // candidate ops, only those that failed simulation or gas estimation count a skipped
+    // round, ops left out for capacity or deferred until their validAfter do not.
+    fn update_skip_counts(&mut self, candidate_hashes: &[B256], bundle: &Bundle<EP::UO>) {
+        let finished = bundle
+            .iter_ops()
+            .chain(bundle.rejected_ops.iter())
+            .map(|op| op.hash())
+            .collect::<HashSet<_>>();
+        let failed = mem::take(self.failed_skips.get_mut().unwrap());
+
+        for op_hash in candidate_hashes {
+            if finished.contains(op_hash) {
+                self.skip_counts.remove(op_hash);
+            } else if failed.contains(op_hash) {
+                if let Some(rounds) = self.skip_counts.get_or_insert(*op_hash, || 0) {
+                    *rounds += 1;
+                }
+            }
+        }
+    }
+
+    // Orders candidate ops by the selection strategy, counting this round towards the
+    // age of each op.
+    fn order_for_selection(
+        &mut self,
+        ops: Vec<PoolOperationWithSponsoredDAGas>,
+        base_fee: u128,
+    ) -> Vec<PoolOperationWithSponsoredDAGas> {
+        let age_weight = match self.settings.selection_strategy {
+            SelectionStrategy::PoolOrder => return ops,
+            SelectionStrategy::EffectiveGasPrice => {
+                return order_by_weighted_fairness(ops, 0.0, |op| {
+                    (self.ranking_gas_price(&op.op, base_fee), 0)
+                });
+            }
+            SelectionStrategy::WeightedFairness { age_weight } => age_weight,
+        };
+
+        let ops = ops
+            .into_iter()
+            .map(|op| {
+                let rounds =
+                    self.op_rounds
+                        .get_or_insert(op.op.uo.hash(), || 0)
+                        .map_or(0, |rounds| {
+                            *rounds += 1;
+                            *rounds - 1
+                        });
+                (op, rounds)
+            })
+            .collect::<Vec<_>>();
+        order_by_weighted_fairness(ops, age_weight, |(op, rounds)| {
+            (self.ranking_gas_price(&op.op, base_fee), *rounds)
+        })
+        .into_iter()
+        .map(|(op, _)| op)
+        .collect()
+    }
+
+    // The gas price an op is ranked by: the ETH-equivalent gas price from its paymaster's
+    // token price oracle if it has one, otherwise its ETH gas price.
+    fn ranking_gas_price(&self, op: &PoolOperation, base_fee: u128) -> u128 {
+        op.uo
+            .paymaster()
+            .and_then(|paymaster| self.settings.token_price_oracles.get(&paymaster))
+            .and_then(|oracle| oracle.eth_gas_price(&op.uo, base_fee))
+            .unwrap_or_else(|| self.op_gas_price(&op.uo, base_fee))
+    }
+
+    // The fees an op is treated as paying: its override if it has one, otherwise its own.
+    fn op_gas_fees(&self, op: &UserOperationVariant) -> GasFees {
+        self.settings
+            .fee_overrides
+            .get(&op.id())
+            .copied()
+            .unwrap_or(GasFees {
+                max_fee_per_gas: op.max_fee_per_gas(),
+                max_priority_fee_per_gas: op.max_priority_fee_per_gas(),
+            })
+    }
+
+    fn op_gas_price(&self, op: &UserOperationVariant, base_fee: u128) -> u128 {
+        self.op_gas_fees(op).gas_price(base_fee)
+    }
+
+    // The prefund of an op at the fees it is treated as paying. The op's max gas cost is
+    // its total gas limit times its max fee, so it is rescaled to the override's max fee.
+    fn op_max_gas_cost(&self, op: &UserOperationVariant) -> U256 {
+        let max_fee_per_gas = op.max_fee_per_gas();
+        match self.settings.fee_overrides.get(&op.id()) {
+            Some(fees) if max_fee_per_gas != 0 => {
+                op.max_gas_cost() / U256::from(max_fee_per_gas) * U256::from(fees.max_fee_per_gas)
+            }
+            _ => op.max_gas_cost(),
+        }
+    }
+
+    // Returns true if the op was deferred until a validAfter that has not passed yet,
+    // forgetting deferrals that have passed.
+    fn is_deferred(&mut self, op_hash: &B256, now: Timestamp) -> bool {
+        match self.deferred_ops.peek(op_hash) {
+            Some(&valid_after) if valid_after > now => true,
+            Some(_) => {
+                self.deferred_ops.remove(op_hash);
+                false
+            }
+            None => false,
+        }
+    }
+
+    // Skips simulated ops that only become valid after the block timestamp and defers
+    // them until then. Ops that will never be valid are left for rejection.
+    fn defer_not_yet_valid_ops(
+        &mut self,
+        ops_with_simulations: Vec<(
+            PoolOperationWithSponsoredDAGas,
+            Result<SimulationResult, SimulationError>,
+        )>,
+        block_timestamp: Timestamp,
+    ) -> Vec<(
+        PoolOperationWithSponsoredDAGas,
+        Result<SimulationResult, SimulationError>,
+    )> {
+        ops_with_simulations
+            .into_iter()
+            .filter(|(po, simulation)| {
+                let Ok(simulation) = simulation else {
+                    return true;
+                };
+                let valid_range = simulation.valid_time_range;
+                if valid_range.valid_after <= block_timestamp
+                    || !valid_range.contains(valid_range.valid_after, TIME_RANGE_BUFFER)
+                {
+                    return true;
+                }
+
+                let op_hash = po.op.uo.hash();
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_tag.clone(),
+                    op_hash,
+                    SkipReason::NotYetValid {
+                        valid_after: valid_range.valid_after,
+                    },
+                ));
+                self.deferred_ops.insert(op_hash, valid_range.valid_after);
+                false
+            })
+            .collect()
+    }
+
+    // Emits the exact `handleOps` calldata for a proposed bundle so that it can be
+    // replayed against a debugger if the bundle reverts on-chain.
+    fn emit_bundle_calldata(
+        &self,
+        ops_per_aggregator: &[UserOpsPerAggregator<EP::UO>],
+        beneficiary: Address,
+        gas_limit: u64,
+        gas_fees: GasFees,
+    ) {
+        let tx = self.ep_providers.entry_point().get_send_bundle_transaction(
+            ops_per_aggregator.to_vec(),
+            beneficiary,
+            gas_limit,
+            gas_fees,
+            self.settings.submission_proxy.as_ref().map(|p| p.address()),
+        );
+        self.emit(BuilderEvent::bundle_calldata(
+            self.builder_tag.clone(),
+            beneficiary,
+            gas_limit,
+            gas_fees,
+            tx.input.input().cloned().unwrap_or_default(),
+        ));
+    }
+
+    fn fee_estimator(&self) -> &dyn FeeEstimator {
+        match &self.settings.fixed_gas_price {
+            Some(fixed) => fixed,
+            None => self.ep_providers.fee_estimator(),
+        }
+    }
+
+    fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
+        match self.settings.priority_fee_mode {
+            Some(mode) => mode.required_fees(bundle_fees),
+            None => self.fee_estimator().required_op_fees(bundle_fees),
+        }
+    }
+
+    // Returns the address that receives the fees paid by `op` when it is bundled.
+    //
+    // This is the extension point for routing op rewards to different beneficiaries.
+    // Since handleOps pays a single beneficiary per call, ops routed to different
+    // beneficiaries are never bundled together, each bundle transaction takes the
+    // group of the highest priority op.
+    fn beneficiary_for(&self, _op: &PoolOperation) -> Address {
+        self.settings
+            .beneficiary
+            .unwrap_or(self.settings.sender_eoa)
+    }
+
     // Check fees for a single user op. Returns None if the op should be skipped.
     //
     // Filters on:
@@ -386,19 +1106,8 @@ where
     ) -> Option<PoolOperationWithSponsoredDAGas> {
         let op_hash = op.uo.hash();
 
-        let mut required_max_fee_per_gas = required_op_fees.max_fee_per_gas;
-        let mut required_max_priority_fee_per_gas = required_op_fees.max_priority_fee_per_gas;
-
-        if let Some(pct) = op.perms.underpriced_bundle_pct {
-            required_max_fee_per_gas = math::percent_ceil(required_max_fee_per_gas, pct);
-            required_max_priority_fee_per_gas =
-                math::percent_ceil(required_max_priority_fee_per_gas, pct);
-        }
-
         // filter by fees
-        if (op.uo.max_fee_per_gas() < required_max_fee_per_gas
-            || op.uo.max_priority_fee_per_gas() < required_max_priority_fee_per_gas)
-            && op.perms.bundler_sponsorship.is_none()
+        if !pays_required_fees(&op, required_op_fees) && op.perms.bundler_sponsorship.is_none()
         // skip if bundler sponsored
         {
             self.emit(BuilderEvent::skipped_op(
@@ -429,7 +1138,7 @@ where
         let gas_price = if op.perms.bundler_sponsorship.is_some() {
             required_op_fees.gas_price(base_fee)
         } else {
-            op.uo.gas_price(base_fee)
+            self.op_gas_price(&op.uo, base_fee)
         };
 
         let required_da_gas = if self.settings.da_gas_tracking_enabled
@@ -463,15 +1172,14 @@ where
                     error!(
                         "Failed to calculate required pre-verification gas for op: {e:?}, skipping"
                     );
-                    self.emit(BuilderEvent::skipped_op(
-                        self.builder_tag.clone(),
+                    self.skip_failed_op(
                         op_hash,
                         SkipReason::Other {
                             reason: Arc::new(format!(
                                 "Failed to calculate required pre-verification gas for op: {e:?}, skipping"
                             )),
                         },
-                    ));
+                    );
                     return None;
                 }
             }
@@ -538,6 +1246,48 @@ where
         })
     }
 
+    // Returns the head's block hash in place of a block hash more than the maximum age
+    // behind it
+    async fn fresh_block_hash(&self, block_hash: B256) -> BundleProposerResult<B256> {
+        let Some(max_sim_block_age) = self.settings.max_sim_block_age else {
+            return Ok(block_hash);
+        };
+        let evm = self.ep_providers.evm();
+        let block_number = evm
+            .get_block(block_hash.into())
+            .await?
+            .with_context(|| format!("block {block_hash:?} not found"))?
+            .header
+            .number;
+        let (head_hash, head_number) = evm.get_latest_block_hash_and_number().await?;
+
+        let age = head_number.saturating_sub(block_number);
+        if age <= max_sim_block_age {
+            return Ok(block_hash);
+        }
+        warn!("Block {block_hash:?} is {age} blocks behind the head, proposing on the head block {head_hash:?} instead");
+        self.metrics.stale_block_refreshes.increment(1);
+        Ok(head_hash)
+    }
+
+    // Timestamp of the block the bundle is built on, used to evaluate op time ranges.
+    // Falls back to the current time if the block can't be fetched.
+    async fn get_block_timestamp(&self, block_hash: B256) -> Timestamp {
+        match self.ep_providers.evm().get_block(block_hash.into()).await {
+            Ok(Some(block)) => block.header.timestamp.into(),
+            Ok(None) => {
+                warn!(
+                    "Block {block_hash:?} not found, evaluating time ranges against current time"
+                );
+                Timestamp::now()
+            }
+            Err(e) => {
+                warn!("Failed to get block {block_hash:?}, evaluating time ranges against current time: {e:?}");
+                Timestamp::now()
+            }
+        }
+    }
+
     // Simulate a single op. Returns None if the op should be skipped.
     //
     // Filters on any errors
@@ -559,14 +1309,24 @@ where
             .simulate_validation(
                 op.op.uo.clone().into(),
                 op.op.perms.trusted,
-                block_hash,
+                block_hash.into(),
                 Some(op.op.expected_code_hash),
             )
             .await;
         let result = match result {
-            Ok(success) => (op, Ok(success)),
-            Err(error) => match error {
-                SimulationError {
+            Ok(success) => {
+                if success.has_entity_role_overlap {
+                    self.metrics.entity_role_overlaps.increment(1);
+                    self.emit(BuilderEvent::entity_role_overlap(
+                        self.builder_tag.clone(),
+                        op_hash,
+                        success.entity_infos,
+                    ));
+                }
+                (op, Ok(success))
+            }
+            Err(error) => match error {
+                SimulationError {
                     violation_error: ViolationError::Violations(_),
                     entity_infos: _,
                 } => (op, Err(error)),
@@ -574,13 +1334,12 @@ where
                     violation_error: ViolationError::Other(error),
                     entity_infos: _,
                 } => {
-                    self.emit(BuilderEvent::skipped_op(
-                        self.builder_tag.clone(),
+                    self.skip_failed_op(
                         op_hash,
                         SkipReason::Other {
                             reason: Arc::new(format!("Failed to simulate op: {error:?}, skipping")),
                         },
-                    ));
+                    );
                     return None;
                 }
             },
@@ -598,6 +1357,8 @@ where
             Result<SimulationResult, SimulationError>,
         )>,
         mut balances_by_paymaster: HashMap<Address, U256>,
+        funds_by_sender: HashMap<Address, U256>,
+        block_timestamp: Timestamp,
     ) -> ProposalContext<<Self as BundleProposer>::UO> {
         if max_bundle_fee == U256::ZERO {
             warn!("Max bundle fee is zero, skipping bundle");
@@ -614,6 +1375,8 @@ where
         let mut context = ProposalContext::<<Self as BundleProposer>::UO>::new();
         let mut paymasters_to_reject = Vec::<EntityInfo>::new();
         let mut passed_target = false;
+        let mut ops_per_paymaster = HashMap::<Address, usize>::new();
+        let mut bundle_prefund = U256::ZERO;
 
         for (po, simulation) in ops_with_simulations {
             // first process any possible rejections
@@ -641,16 +1404,35 @@ where
                 }
             };
 
+            // Leave ops outside this builder's mempools for the builders that pull from them
+            if let Some(allowed_mempools) = &self.settings.allowed_mempools {
+                if !simulation
+                    .mempools
+                    .iter()
+                    .any(|id| allowed_mempools.contains(id))
+                {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_tag.clone(),
+                        op.hash(),
+                        SkipReason::NotInAllowedMempools {
+                            mempools: simulation.mempools,
+                        },
+                    ));
+                    continue;
+                }
+            }
+
             // filter time range
             if !simulation
                 .valid_time_range
-                .contains(Timestamp::now(), TIME_RANGE_BUFFER)
+                .contains(block_timestamp, TIME_RANGE_BUFFER)
             {
                 self.emit(BuilderEvent::rejected_op(
                     self.builder_tag.clone(),
                     op.hash(),
                     OpRejectionReason::InvalidTimeRange {
                         valid_range: simulation.valid_time_range,
+                        block_timestamp,
                     },
                 ));
                 context.rejected_ops.push((op.into(), po.op.entity_infos));
@@ -660,13 +1442,14 @@ where
                     ValidTimeRange::from_genesis(bundler_sponsorship.valid_until.into());
                 if !simulation
                     .valid_time_range
-                    .contains(Timestamp::now(), TIME_RANGE_BUFFER)
+                    .contains(block_timestamp, TIME_RANGE_BUFFER)
                 {
                     self.emit(BuilderEvent::rejected_op(
                         self.builder_tag.clone(),
                         op.hash(),
                         OpRejectionReason::InvalidTimeRange {
                             valid_range: valid_time_range,
+                            block_timestamp,
                         },
                     ));
                     context.rejected_ops.push((op.into(), po.op.entity_infos));
@@ -674,6 +1457,21 @@ where
                 }
             }
 
+            // Ops close to expiry may expire before the bundle mines, but are still valid
+            if !simulation
+                .valid_time_range
+                .contains(block_timestamp, self.settings.min_validity_remaining)
+            {
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_tag.clone(),
+                    op.hash(),
+                    SkipReason::ExpiringSoon {
+                        valid_until: simulation.valid_time_range.valid_until,
+                    },
+                ));
+                continue;
+            }
+
             // if the bundle is at or past target, skip op and continue to finish processing any rejections
             if passed_target {
                 self.emit(BuilderEvent::skipped_op(
@@ -684,6 +1482,55 @@ where
                 continue;
             }
 
+            // Limit the gas of a single op, so one op with huge limits can't crowd out the rest
+            let op_total_gas = op.pre_op_gas_limit() + op.call_gas_limit();
+            if op_total_gas > self.settings.max_op_total_gas {
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_tag.clone(),
+                    op.hash(),
+                    SkipReason::MaxOpTotalGas {
+                        op_total_gas,
+                        max_op_total_gas: self.settings.max_op_total_gas,
+                    },
+                ));
+                continue;
+            }
+
+            // Limit the number of ops sharing a paymaster, so a single paymaster can't fill
+            // the bundle and concentrate its revert risk
+            if let (Some(paymaster), Some(max_ops)) =
+                (op.paymaster(), self.settings.max_ops_per_paymaster)
+            {
+                if ops_per_paymaster
+                    .get(&paymaster)
+                    .copied()
+                    .unwrap_or_default()
+                    >= max_ops
+                {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_tag.clone(),
+                        op.hash(),
+                        SkipReason::PaymasterOpsLimit { paymaster },
+                    ));
+                    continue;
+                }
+            }
+
+            // Limit the number of aggregator groups, each of which adds a signature
+            // validation call to handleAggregatedOps
+            if let Some(aggregator) = op.aggregator() {
+                if !context.groups_by_aggregator.contains_key(&aggregator)
+                    && context.aggregator_count() >= self.settings.max_aggregators_per_bundle
+                {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_tag.clone(),
+                        op.hash(),
+                        SkipReason::AggregatorLimit { aggregator },
+                    ));
+                    continue;
+                }
+            }
+
             // Add op to candidate context
             let mut context_with_op = context.clone();
             context_with_op
@@ -735,15 +1582,32 @@ where
                 continue;
             }
 
+            // Limit by handleOps calldata size
+            if let Some(max_bundle_calldata_bytes) = self.settings.max_bundle_calldata_bytes {
+                let bundle_calldata_size =
+                    context_with_op.get_bundle_calldata_size(&self.settings.chain_spec);
+                if bundle_calldata_size > max_bundle_calldata_bytes as u128 {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_tag.clone(),
+                        op.hash(),
+                        SkipReason::CalldataSizeLimit,
+                    ));
+                    continue;
+                }
+            }
+
             // Merge the expected storage and skip if there is a conflict or if the storage is over max
-            if let Err(e) = context
+            if let Err(conflict) = context
                 .bundle_expected_storage
                 .add(&simulation.expected_storage)
             {
-                self.emit(BuilderEvent::skipped_op(
+                let kept = context.find_op_expecting_slot(conflict.address, conflict.slot);
+                self.emit(BuilderEvent::storage_conflict(
                     self.builder_tag.clone(),
+                    kept,
                     op.hash(),
-                    SkipReason::ExpectedStorageConflict(e.to_string()),
+                    conflict.address,
+                    conflict.slot,
                 ));
                 continue;
             } else if context.bundle_expected_storage.inner.num_slots()
@@ -773,26 +1637,88 @@ where
                 continue;
             }
 
+            // Limit by max bundle prefund, checked before any paymaster deposit is reserved
+            // for the op
+            let op_prefund = self.op_max_gas_cost(&op);
+            if let Some(max_bundle_prefund) = self.settings.max_bundle_prefund {
+                if bundle_prefund.saturating_add(op_prefund) > max_bundle_prefund {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_tag.clone(),
+                        op.hash(),
+                        SkipReason::MaxBundlePrefund {
+                            bundle_prefund,
+                            required_prefund: op_prefund,
+                        },
+                    ));
+                    continue;
+                }
+            }
+
+            // The paymaster's deposit must cover the prefund of every op it sponsors in the bundle,
+            // otherwise the op will revert on chain. Ops that don't fit are left in the pool.
             if let Some(paymaster) = op.paymaster() {
                 let Some(balance) = balances_by_paymaster.get_mut(&paymaster) else {
                     error!("Op had paymaster with unknown balance, but balances should have been loaded for all paymasters in bundle.");
                     continue;
                 };
-                let max_cost = op.max_gas_cost();
-                if *balance < max_cost {
-                    info!("Rejected paymaster {paymaster:?} because its balance {balance:?} was too low.");
-                    paymasters_to_reject.push(po.op.entity_infos.paymaster.unwrap());
+                if *balance < op_prefund {
+                    info!("Excluding op from {:?} because paymaster {paymaster:?} remaining deposit {balance:?} can't cover its prefund {op_prefund:?}.", op.sender());
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_tag.clone(),
+                        op.hash(),
+                        SkipReason::InsufficientPaymasterDeposit {
+                            paymaster,
+                            deposit: *balance,
+                            required_prefund: op_prefund,
+                        },
+                    ));
+                    if let Some(paymaster_info) = simulation.entity_infos.paymaster {
+                        paymasters_to_reject.push(paymaster_info);
+                    }
                     continue;
                 } else {
-                    *balance -= max_cost;
+                    *balance -= op_prefund;
+                }
+            } else if let Some(&funds) = funds_by_sender.get(&op.sender()) {
+                // Self-paying senders must be able to cover their prefund from their
+                // entry point deposit and balance, otherwise the op will revert on chain.
+                //
+                // A deploying sender has no code yet. Its funds were read at its
+                // counterfactual address, so they must have been sent there before the op
+                // is bundled, the factory does not fund the prefund.
+                let required_funds = math::increase_by_percent(
+                    op_prefund,
+                    self.settings.sender_deposit_buffer_percent,
+                );
+                if funds < required_funds {
+                    if op.factory().is_some() {
+                        info!("Excluding deploying op from {:?} because the funds {funds:?} at its counterfactual address can't cover its prefund with buffer {required_funds:?}.", op.sender());
+                    } else {
+                        info!("Excluding op from {:?} because its funds {funds:?} can't cover its prefund with buffer {required_funds:?}.", op.sender());
+                    }
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_tag.clone(),
+                        op.hash(),
+                        SkipReason::InsufficientSenderFunds {
+                            funds,
+                            required_prefund: required_funds,
+                        },
+                    ));
+                    continue;
                 }
             }
 
+            bundle_prefund = bundle_prefund.saturating_add(op_prefund);
+
             // check if we've passed the computation target
             if bundle_computation_gas_limit >= self.settings.target_bundle_gas {
                 passed_target = true;
             }
 
+            if let Some(paymaster) = op.paymaster() {
+                *ops_per_paymaster.entry(paymaster).or_default() += 1;
+            }
+
             // add the op to the context
             context
                 .groups_by_aggregator
@@ -915,6 +1841,15 @@ where
             .await;
     }
 
+    async fn remove_last_op(&self, context: &mut ProposalContext<<Self as BundleProposer>::UO>) {
+        let Some((op_hash, changed_aggregator)) = context.remove_last_op() else {
+            return;
+        };
+        self.skip_failed_op(op_hash, SkipReason::MaxGasLimit);
+        self.compute_aggregator_signatures(context, &changed_aggregator)
+            .await;
+    }
+
     async fn reject_entity(
         &self,
         context: &mut ProposalContext<<Self as BundleProposer>::UO>,
@@ -964,10 +1899,12 @@ where
         context: &mut ProposalContext<<Self as BundleProposer>::UO>,
         bundle_fees: GasFees,
     ) -> BundleProposerResult<Option<u64>> {
-        // sum up the gas needed for all the ops in the bundle
-        // and apply an overhead multiplier
+        // sum up the gas needed for all the ops in the bundle, never going below the
+        // worst case from the ops' own limits, and apply an overhead multiplier
         let gas = math::increase_by_percent(
-            context.get_bundle_gas_limit(&self.settings.chain_spec),
+            context
+                .get_bundle_gas_limit(&self.settings.chain_spec)
+                .max(context.get_bundle_worst_case_gas(&self.settings.chain_spec)),
             BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
         );
 
@@ -989,23 +1926,65 @@ where
         let validation_only = self.settings.submission_proxy.is_none()
             && self.ep_providers.entry_point().version() != EntryPointVersion::V0_6;
 
-        let handle_ops_out = self
-            .ep_providers
-            .entry_point()
-            .call_handle_ops(
-                context.to_ops_per_aggregator(),
-                self.settings.sender_eoa,
-                gas_limit,
-                bundle_fees,
-                self.settings.submission_proxy.as_ref().map(|p| p.address()),
-                validation_only,
-            )
-            .await
-            .context("should call handle ops with candidate bundle")?;
+        // only transport failures are retried, a revert is deterministic and is handled below
+        let entry_point = self.ep_providers.entry_point();
+        let ops_per_aggregator = context.to_ops_per_aggregator();
+        let beneficiary = context.beneficiary;
+        let proxy = self.settings.submission_proxy.as_ref().map(|p| p.address());
+        let handle_ops_out = retry::with_retries(
+            "estimate bundle gas",
+            move || {
+                let ops_per_aggregator = ops_per_aggregator.clone();
+                async move {
+                    match entry_point
+                        .call_handle_ops(
+                            ops_per_aggregator,
+                            beneficiary,
+                            gas_limit,
+                            bundle_fees,
+                            proxy,
+                            validation_only,
+                        )
+                        .await
+                    {
+                        Err(error) if is_retryable_estimation_error(&error) => Err(error),
+                        res => Ok(res),
+                    }
+                }
+            },
+            RetryOpts {
+                max_attempts: self.settings.estimation_retries + 1,
+                min_nonzero_wait: ESTIMATION_RETRY_MIN_WAIT,
+                max_wait: ESTIMATION_RETRY_MAX_WAIT,
+                max_jitter: ESTIMATION_RETRY_MAX_JITTER,
+            },
+        )
+        .await
+        .and_then(|res| res)
+        .context("should call handle ops with candidate bundle")?;
         self.metrics
             .bundle_simulation_ms
             .record(start.elapsed().as_millis() as f64);
 
+        // a bundle that reverts as a whole in simulation is handled like a failed estimate
+        let mut gas_limit = gas_limit;
+        let handle_ops_out = match handle_ops_out {
+            HandleOpsOut::Success if self.settings.simulate_bundle_gas => {
+                match self.simulate_bundle_gas(context, gas_limit).await? {
+                    Ok(simulated_gas_limit) => {
+                        gas_limit = simulated_gas_limit;
+                        HandleOpsOut::Success
+                    }
+                    Err(out) => out,
+                }
+            }
+            HandleOpsOut::Success if self.settings.attribute_bundle_gas => {
+                self.attribute_bundle_gas(context).await;
+                HandleOpsOut::Success
+            }
+            out => out,
+        };
+
         match handle_ops_out {
             HandleOpsOut::Success => Ok(Some(gas_limit)),
             HandleOpsOut::FailedOp(index, message) => {
@@ -1021,6 +2000,10 @@ where
             }
             HandleOpsOut::SignatureValidationFailed(aggregator) => {
                 info!("Rejected aggregator {aggregator:?} because its signature validation failed during gas estimation.");
+                self.emit(BuilderEvent::aggregator_signature_failed(
+                    self.builder_tag.clone(),
+                    aggregator,
+                ));
                 self.reject_entity(context, Entity::aggregator(aggregator), true)
                     .await;
                 Ok(None)
@@ -1063,6 +2046,85 @@ where
         }
     }
 
+    // Simulates the whole bundle to cross-check the summed estimate, returning the gas
+    // limit to use or the decoded revert if the bundle reverted as a whole.
+    async fn simulate_bundle_gas(
+        &self,
+        context: &ProposalContext<<Self as BundleProposer>::UO>,
+        gas_limit: u64,
+    ) -> BundleProposerResult<Result<u64, HandleOpsOut>> {
+        let sim = match self
+            .ep_providers
+            .entry_point()
+            .simulate_handle_ops(
+                context.to_ops_per_aggregator(),
+                context.beneficiary,
+                None,
+                self.settings.attribute_bundle_gas,
+            )
+            .await
+            .context("should simulate bundle")?
+        {
+            Ok(sim) => sim,
+            Err(out) => return Ok(Err(out)),
+        };
+        debug!(
+            "Bundle simulation used {} gas, per op: {:?}",
+            sim.total_gas, sim.per_op_gas
+        );
+        if self.settings.attribute_bundle_gas {
+            self.emit_gas_attribution(context, sim.gas_attribution);
+        }
+
+        let simulated_gas_limit: u64 =
+            math::increase_by_percent(sim.total_gas, BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT)
+                .try_into()
+                .context("simulated bundle gas limit is larger than u64::MAX")?;
+        if simulated_gas_limit > gas_limit {
+            warn!("Bundle gas estimate {gas_limit} is below the simulated gas limit {simulated_gas_limit}, using the simulated limit");
+            return Ok(Ok(simulated_gas_limit));
+        }
+        Ok(Ok(gas_limit))
+    }
+
+    // Traces the bundle to attribute gas to each op's phases, for diagnostics. Failures,
+    // including providers that don't support tracing, don't affect the bundle.
+    async fn attribute_bundle_gas(&self, context: &ProposalContext<<Self as BundleProposer>::UO>) {
+        match self
+            .ep_providers
+            .entry_point()
+            .simulate_handle_ops(
+                context.to_ops_per_aggregator(),
+                context.beneficiary,
+                None,
+                true,
+            )
+            .await
+        {
+            Ok(Ok(sim)) => self.emit_gas_attribution(context, sim.gas_attribution),
+            Ok(Err(out)) => warn!("Bundle reverted while tracing it for gas attribution: {out:?}"),
+            Err(e) if e.is_method_not_supported() => {
+                warn!("Provider does not support tracing, skipping bundle gas attribution: {e:?}")
+            }
+            Err(e) => warn!("Failed to trace bundle for gas attribution: {e:?}"),
+        }
+    }
+
+    fn emit_gas_attribution(
+        &self,
+        context: &ProposalContext<<Self as BundleProposer>::UO>,
+        gas_attribution: Vec<GasAttribution>,
+    ) {
+        self.emit(BuilderEvent::bundle_gas_attribution(
+            self.builder_tag.clone(),
+            context
+                .iter_ops()
+                .map(|op| op.hash())
+                .zip(gas_attribution)
+                .collect(),
+        ));
+    }
+
     async fn get_balances_by_paymaster(
         &self,
         addresses: impl IntoIterator<Item = Address>,
@@ -1082,6 +2144,30 @@ where
         Ok(HashMap::from_iter(addresses_and_deposits))
     }
 
+    // Funds available to a sender to pay its prefund: its entry point deposit plus its
+    // balance, from which the account pays any missing deposit during validation.
+    async fn get_funds_by_sender(
+        &self,
+        addresses: impl IntoIterator<Item = Address>,
+        block_hash: B256,
+    ) -> BundleProposerResult<HashMap<Address, U256>> {
+        let futures = addresses.into_iter().map(|address| async move {
+            let (deposit, balance) = tokio::try_join!(
+                self.ep_providers
+                    .entry_point()
+                    .balance_of(address, Some(block_hash.into())),
+                self.ep_providers
+                    .evm()
+                    .get_balance(address, Some(block_hash.into())),
+            )?;
+            Ok::<_, anyhow::Error>((address, deposit.saturating_add(balance)))
+        });
+        let addresses_and_funds = future::try_join_all(futures)
+            .await
+            .context("should load deposits and balances for senders")?;
+        Ok(HashMap::from_iter(addresses_and_funds))
+    }
+
     async fn aggregate_signatures(
         &self,
         aggregator: Address,
@@ -1102,6 +2188,11 @@ where
             // Mempool ops are transformed during insertion - use the original signature to aggregate
             .map(|op| op.op.clone().with_original_signature().into())
             .collect::<Vec<_>>();
+        debug!(
+            "Aggregating {} signatures with aggregator {aggregator:?}, estimate: {:?}",
+            uos.len(),
+            agg.estimate_aggregation(uos.len())
+        );
 
         let aggregated = match agg.aggregate_signatures(uos).await {
             Ok(aggregated) => aggregated,
@@ -1119,10 +2210,12 @@ where
         index: usize,
         message: String,
     ) -> anyhow::Result<()> {
-        match &message[..4] {
+        match AaError::parse(&message) {
             // Entrypoint error codes that we want to reject the factory for.
             // AA10 is an internal error and is ignored
-            "AA13" | "AA14" | "AA15" => {
+            Some(
+                AaError::InitCodeFailed | AaError::InitCodeWrongSender | AaError::InitCodeNoSender,
+            ) => {
                 let op_with_sim = context.get_op_at(index)?;
                 let factory = op_with_sim.op.factory().context("op failed during gas estimation with factory error, but did not include a factory")?;
                 info!("Rejected op because it failed during gas estimation with factory {factory:?} error {message}.");
@@ -1139,7 +2232,12 @@ where
             }
             // Entrypoint error codes that we want to reject the paymaster for.
             // Note: AA32 is not included as this is a time expiry error.
-            "AA30" | "AA31" | "AA33" | "AA34" => {
+            Some(
+                AaError::PaymasterNotDeployed
+                | AaError::PaymasterDepositTooLow
+                | AaError::PaymasterReverted
+                | AaError::PaymasterSignature,
+            ) => {
                 let op_with_sim = context.get_op_at(index)?;
                 let paymaster = op_with_sim.op.paymaster().context(
                     "op failed during gas estimation with {message}, but had no paymaster",
@@ -1320,6 +2418,61 @@ where
         }
     }
 
+    // Splits off the ops whose aggregator doesn't implement `IAggregator`. Each aggregator
+    // is probed once, if the probe fails its ops are kept and it is probed again next time.
+    async fn reject_nonconforming_aggregators(
+        &mut self,
+        ops: Vec<PoolOperationWithSponsoredDAGas>,
+        block_hash: B256,
+    ) -> (
+        Vec<PoolOperationWithSponsoredDAGas>,
+        Vec<(EP::UO, EntityInfos)>,
+    ) {
+        let unchecked = ops
+            .iter()
+            .filter_map(|op| op.op.uo.aggregator())
+            .filter(|aggregator| !self.aggregator_interfaces.contains_key(aggregator))
+            .collect::<HashSet<_>>();
+        let evm = self.ep_providers.evm();
+        let version = self.ep_providers.entry_point().version();
+        let checks = future::join_all(unchecked.into_iter().map(|aggregator| async move {
+            let result =
+                check_aggregator_interface(evm, version, aggregator, Some(block_hash.into())).await;
+            (aggregator, result)
+        }))
+        .await;
+        for (aggregator, result) in checks {
+            match result {
+                Ok(conforms) => {
+                    if !conforms {
+                        warn!("Aggregator {aggregator:?} does not implement IAggregator, rejecting its ops");
+                    }
+                    self.aggregator_interfaces.insert(aggregator, conforms);
+                }
+                Err(e) => {
+                    warn!("Failed to check the interface of aggregator {aggregator:?}: {e:?}")
+                }
+            }
+        }
+
+        let mut rejected = vec![];
+        let mut conforming = vec![];
+        for op in ops {
+            match op.op.uo.aggregator() {
+                Some(aggregator) if self.aggregator_interfaces.get(&aggregator) == Some(&false) => {
+                    self.emit(BuilderEvent::rejected_op(
+                        self.builder_tag.clone(),
+                        op.op.uo.hash(),
+                        OpRejectionReason::NonconformingAggregator { aggregator },
+                    ));
+                    rejected.push((op.op.uo.into(), op.op.entity_infos));
+                }
+                _ => conforming.push(op),
+            }
+        }
+        (conforming, rejected)
+    }
+
     fn limit_user_operations_for_simulation(
         &self,
         ops: Vec<PoolOperationWithSponsoredDAGas>,
@@ -1367,6 +2520,17 @@ where
         )
     }
 
+    // Skips an op whose simulation or gas estimation failed, counting the round towards
+    // its `max_skip_rounds`
+    fn skip_failed_op(&self, op_hash: B256, reason: SkipReason) {
+        self.failed_skips.lock().unwrap().insert(op_hash);
+        self.emit(BuilderEvent::skipped_op(
+            self.builder_tag.clone(),
+            op_hash,
+            reason,
+        ));
+    }
+
     fn emit(&self, event: BuilderEvent) {
         let _ = self.event_sender.send(WithEntryPoint {
             entry_point: *self.ep_providers.entry_point().address(),
@@ -1426,6 +2590,7 @@ struct OpWithSimulation<UO> {
 #[derive(Debug, Clone)]
 struct ProposalContext<UO> {
     groups_by_aggregator: LinkedHashMap<Address, AggregatorGroup<UO>>,
+    beneficiary: Address,
     rejected_ops: Vec<(UO, EntityInfos)>,
     // This is a BTreeMap so that the conversion to a Vec<EntityUpdate> is deterministic, mainly for tests
     entity_updates: BTreeMap<Address, EntityUpdate>,
@@ -1451,6 +2616,7 @@ impl<UO: UserOperation> ProposalContext<UO> {
     fn new() -> Self {
         Self {
             groups_by_aggregator: LinkedHashMap::<Address, AggregatorGroup<UO>>::new(),
+            beneficiary: Address::ZERO,
             rejected_ops: Vec::<(UO, EntityInfos)>::new(),
             entity_updates: BTreeMap::new(),
             bundle_expected_storage: BundleExpectedStorage::default(),
@@ -1461,6 +2627,14 @@ impl<UO: UserOperation> ProposalContext<UO> {
         self.groups_by_aggregator.is_empty()
     }
 
+    // Number of distinct aggregators, not counting the group of unaggregated ops
+    fn aggregator_count(&self) -> usize {
+        self.groups_by_aggregator
+            .keys()
+            .filter(|aggregator| !aggregator.is_zero())
+            .count()
+    }
+
     fn apply_aggregation_signature_result(
         &mut self,
         aggregator: Address,
@@ -1529,6 +2703,27 @@ impl<UO: UserOperation> ProposalContext<UO> {
         }
     }
 
+    /// Removes the last op of the bundle without rejecting it, so it stays in the pool.
+    /// Returns the op's hash and the address of its aggregator if the aggregator's
+    /// signature may need to be recomputed.
+    #[must_use = "removed op but did not update aggregator signatures"]
+    fn remove_last_op(&mut self) -> Option<(B256, Option<Address>)> {
+        let (&aggregator, group) = self.groups_by_aggregator.iter_mut().next_back()?;
+        let removed = group.ops_with_simulations.pop()?;
+        let group_is_empty = group.ops_with_simulations.is_empty();
+        self.bundle_expected_storage
+            .remove(&removed.simulation.expected_storage);
+
+        // If we just removed the last op from a group, delete that group.
+        // Otherwise, the signature is invalidated and we need to recompute it.
+        if group_is_empty {
+            self.groups_by_aggregator.remove(&aggregator);
+            Some((removed.op.hash(), None))
+        } else {
+            Some((removed.op.hash(), Some(aggregator)))
+        }
+    }
+
     /// Returns the addresses of any aggregators whose signature may need to be recomputed.
     #[must_use = "rejected entity but did not update aggregator signatures"]
     fn reject_entity(&mut self, entity: Entity, is_staked: bool) -> Vec<Address> {
@@ -1647,23 +2842,24 @@ impl<UO: UserOperation> ProposalContext<UO> {
     }
 
     fn get_bundle_transaction_size(&self, chain_spec: &ChainSpec) -> u128 {
-        let bundle_overhead = self.bundle_overhead_bytes(chain_spec);
-        let (ops_data_size, auth_list_count) = self
+        let auth_list_count = self
             .iter_ops_with_simulations()
-            .map(|sim_op| {
-                (
-                    sim_op.op.abi_encoded_size() as u128,
-                    sim_op.op.authorization_tuple().map_or(0, |_| 1),
-                )
-            })
-            .fold((0u128, 0), |(acc_size, acc_count), (size, count)| {
-                (acc_size + size, acc_count + count)
-            });
+            .filter(|sim_op| sim_op.op.authorization_tuple().is_some())
+            .count();
 
-        let bundle_data_size = (bundle_overhead + ops_data_size) as usize;
+        let bundle_data_size = self.get_bundle_calldata_size(chain_spec) as usize;
         eth::calculate_transaction_size(bundle_data_size, auth_list_count) as u128
     }
 
+    // Get the size of the handleOps calldata, the ABI encoded ops plus the bundle overhead
+    fn get_bundle_calldata_size(&self, chain_spec: &ChainSpec) -> u128 {
+        self.bundle_overhead_bytes(chain_spec)
+            + self
+                .iter_ops_with_simulations()
+                .map(|sim_op| sim_op.op.abi_encoded_size() as u128)
+                .sum::<u128>()
+    }
+
     // Get the computation gas limit in the bundle
     fn get_bundle_computation_gas_limit(&self, chain_spec: &ChainSpec) -> u128 {
         self.get_bundle_gas_limit_inner(chain_spec, false)
@@ -1679,6 +2875,11 @@ impl<UO: UserOperation> ProposalContext<UO> {
         self.get_bundle_gas_limit_inner(chain_spec, chain_spec.include_da_gas_in_gas_limit)
     }
 
+    // Get the worst case gas for the bundle from the ops' own limits
+    fn get_bundle_worst_case_gas(&self, chain_spec: &ChainSpec) -> u128 {
+        bundle_worst_case_gas(self.iter_ops(), chain_spec)
+    }
+
     fn bundle_overhead_bytes(&self, chain_spec: &ChainSpec) -> u128 {
         let mut bundle_overhead_bytes = BUNDLE_BYTE_OVERHEAD as u128;
 
@@ -1707,8 +2908,9 @@ impl<UO: UserOperation> ProposalContext<UO> {
                     // this should be checked prior to calling this function
                     panic!("BUG: aggregator {agg:?} not found in chain spec");
                 };
-                bundle_overhead_bytes += agg.costs().sig_fixed_length
-                    + agg.costs().sig_variable_length * group.ops_with_simulations.len() as u128;
+                bundle_overhead_bytes += agg
+                    .estimate_aggregation(group.ops_with_simulations.len())
+                    .signature_length;
             }
         }
 
@@ -1765,6 +2967,19 @@ impl<UO: UserOperation> ProposalContext<UO> {
             .flat_map(|group| &group.ops_with_simulations)
     }
 
+    // Hash of the first op in the bundle that expects a value for the slot
+    fn find_op_expecting_slot(&self, address: Address, slot: B256) -> Option<B256> {
+        self.iter_ops_with_simulations()
+            .find(|op| {
+                op.simulation
+                    .expected_storage
+                    .0
+                    .get(&address)
+                    .is_some_and(|slots| slots.contains_key(&slot))
+            })
+            .map(|op| op.op.hash())
+    }
+
     fn iter_ops(&self) -> impl Iterator<Item = &UO> + '_ {
         self.iter_ops_with_simulations().map(|op| &op.op)
     }
@@ -1820,7 +3035,10 @@ impl<UO: UserOperation> ProposalContext<UO> {
         // If not a staked factory or sender, attribute errors to each entity directly.
         // For a given op, there can only be a single update per entity so we don't double count the [UREP-030] throttle penalty.
         let mut paymaster_amendment_required = false;
-        for violation in violations {
+        for violation in violations
+            .into_iter()
+            .flat_map(SimulationViolation::flatten)
+        {
             match violation {
                 SimulationViolation::UsedForbiddenOpcode(entity, _, _) => {
                     self.add_entity_update(entity, entity_infos)
@@ -1883,6 +3101,9 @@ impl<UO: UserOperation> ProposalContext<UO> {
                 SimulationViolation::OutOfGas(entity) => {
                     self.add_entity_update(entity, entity_infos)
                 }
+                SimulationViolation::CallDepthTooDeep(entity, _, _) => {
+                    self.add_entity_update(entity, entity_infos)
+                }
                 _ => continue,
             }
         }
@@ -1975,30 +3196,234 @@ impl<UO: UserOperation> ProposalContext<UO> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::time::Duration;
+/// Splits `ops` into the beneficiary of the first op and the ops routed to it, in order.
+///
+/// `ops` must not be empty.
+fn take_beneficiary_group<T>(
+    ops: Vec<T>,
+    beneficiary_for: impl Fn(&T) -> Address,
+) -> (Address, Vec<T>) {
+    let beneficiary = beneficiary_for(&ops[0]);
+    let group = ops
+        .into_iter()
+        .filter(|op| beneficiary_for(op) == beneficiary)
+        .collect();
+    (beneficiary, group)
+}
 
-    use alloy_primitives::{utils::parse_units, Address, B256};
-    use anyhow::anyhow;
-    use rundler_provider::{
-        MockDAGasOracleSync, MockEntryPointV0_6, MockEvmProvider, MockFeeEstimator,
-        ProvidersWithEntryPoint,
-    };
-    use rundler_sim::MockSimulator;
-    use rundler_types::{
-        aggregator::{
-            AggregatorCosts, MockSignatureAggregator, SignatureAggregator, SignatureAggregatorError,
-        },
-        chain::ContractRegistry,
-        da::BedrockDAGasBlockData,
-        pool::SimulationViolation,
-        proxy::MockSubmissionProxy,
-        v0_6::{UserOperation, UserOperationBuilder, UserOperationRequiredFields},
-        BundlerSponsorship, UserOperation as _, UserOperationPermissions, ValidTimeRange,
-    };
+/// Checks that `beneficiary` can receive the payout of a bundle.
+///
+/// An EOA always can. A contract must accept a plain ETH transfer, else every bundle
+/// paying it reverts at payout.
+pub(crate) async fn check_beneficiary_can_receive<E: EvmProvider>(
+    evm: &E,
+    beneficiary: Address,
+    block: Option<BlockId>,
+) -> anyhow::Result<()> {
+    let code = evm
+        .get_code(beneficiary, block)
+        .await
+        .context("should get beneficiary code")?;
+    if code.is_empty() {
+        return Ok(());
+    }
 
-    use super::*;
+    let mut state_override = StateOverride::default();
+    state_override.insert(
+        BENEFICIARY_CHECK_SENDER,
+        AccountOverride {
+            balance: Some(U256::from(1)),
+            ..Default::default()
+        },
+    );
+    let tx = TransactionRequest::default()
+        .from(BENEFICIARY_CHECK_SENDER)
+        .to(beneficiary)
+        .value(U256::from(1));
+    match evm.call(tx, block, Some(state_override)).await {
+        Ok(_) => Ok(()),
+        Err(ProviderError::RPC(e)) if e.is_error_resp() => Err(anyhow::anyhow!(
+            "beneficiary {beneficiary:?} is a contract that reverts when receiving ETH, bundles paying it would revert: {e}"
+        )),
+        Err(e) => Err(e).context("should call beneficiary with an ETH transfer"),
+    }
+}
+
+/// Returns whether `aggregator` implements `IAggregator`, by probing it with an empty
+/// `aggregateSignatures` call. A conforming aggregator returns an ABI encoded signature,
+/// while other contracts revert and accounts without code return nothing.
+pub(crate) async fn check_aggregator_interface<E: EvmProvider>(
+    evm: &E,
+    version: EntryPointVersion,
+    aggregator: Address,
+    block: Option<BlockId>,
+) -> anyhow::Result<bool> {
+    let call_data = match version {
+        EntryPointVersion::V0_6 => {
+            IAggregatorV0_6::aggregateSignaturesCall { userOps: vec![] }.abi_encode()
+        }
+        EntryPointVersion::V0_7 => {
+            IAggregatorV0_7::aggregateSignaturesCall { userOps: vec![] }.abi_encode()
+        }
+        EntryPointVersion::Unspecified => {
+            anyhow::bail!("can't check aggregator interface for unspecified entry point version")
+        }
+    };
+    let tx = TransactionRequest::default()
+        .to(aggregator)
+        .input(Bytes::from(call_data).into());
+    let ret = match evm.call(tx, block, None).await {
+        Ok(ret) => ret,
+        Err(ProviderError::RPC(e)) if e.is_error_resp() => return Ok(false),
+        Err(e) => return Err(e).context("should call aggregator aggregateSignatures"),
+    };
+    // both versions return a single `bytes`
+    Ok(IAggregatorV0_7::aggregateSignaturesCall::abi_decode_returns(&ret).is_ok())
+}
+
+/// Whether `op` pays at least `required_op_fees`, discounted by the op's underpriced
+/// bundle percentage if it has one.
+fn pays_required_fees(op: &PoolOperation, required_op_fees: GasFees) -> bool {
+    let mut required_max_fee_per_gas = required_op_fees.max_fee_per_gas;
+    let mut required_max_priority_fee_per_gas = required_op_fees.max_priority_fee_per_gas;
+
+    if let Some(pct) = op.perms.underpriced_bundle_pct {
+        required_max_fee_per_gas = math::percent_ceil(required_max_fee_per_gas, pct);
+        required_max_priority_fee_per_gas =
+            math::percent_ceil(required_max_priority_fee_per_gas, pct);
+    }
+
+    op.uo.max_fee_per_gas() >= required_max_fee_per_gas
+        && op.uo.max_priority_fee_per_gas() >= required_max_priority_fee_per_gas
+}
+
+/// Whether a failed bundle gas estimation call may succeed if made again.
+///
+/// Only transport failures qualify, an error response from the node is deterministic.
+fn is_retryable_estimation_error(error: &ProviderError) -> bool {
+    matches!(error, ProviderError::RPC(e) if e.is_transport_error())
+}
+
+/// Orders the ops of each sender and nonce key by nonce.
+///
+/// Each group is reordered within the positions it already occupies, so ops of other
+/// groups keep their place.
+fn order_by_nonce<T>(ops: Vec<T>, sender_and_nonce: impl Fn(&T) -> (Address, U256)) -> Vec<T> {
+    let nonces = ops
+        .iter()
+        .map(|op| {
+            let (sender, nonce) = sender_and_nonce(op);
+            ((sender, nonce >> 64), nonce)
+        })
+        .collect::<Vec<_>>();
+    let mut positions_by_group: HashMap<(Address, U256), Vec<usize>> = HashMap::new();
+    for (i, (group, _)) in nonces.iter().enumerate() {
+        positions_by_group.entry(*group).or_default().push(i);
+    }
+
+    let mut ops = ops.into_iter().map(Some).collect::<Vec<_>>();
+    let mut ordered = (0..ops.len()).map(|_| None).collect::<Vec<_>>();
+    for positions in positions_by_group.into_values() {
+        let mut by_nonce = positions.clone();
+        by_nonce.sort_by_key(|&i| nonces[i].1);
+        for (position, i) in positions.into_iter().zip(by_nonce) {
+            ordered[position] = ops[i].take();
+        }
+    }
+    ordered.into_iter().flatten().collect()
+}
+
+/// Orders ops by gas price weighted by the number of rounds they have waited, highest
+/// first. Ops of equal weight keep their order.
+fn order_by_weighted_fairness<T>(
+    ops: Vec<T>,
+    age_weight: f64,
+    gas_price_and_rounds: impl Fn(&T) -> (u128, u64),
+) -> Vec<T> {
+    let mut weighted = ops
+        .into_iter()
+        .map(|op| {
+            let (gas_price, rounds) = gas_price_and_rounds(&op);
+            let weight = gas_price as f64 * (1.0 + age_weight * rounds as f64);
+            (weight, op)
+        })
+        .collect::<Vec<_>>();
+    // stable sort, so equal weights keep the pool order
+    weighted.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    weighted.into_iter().map(|(_, op)| op).collect()
+}
+
+/// Splits the ops considered in a round into those included in the bundle, deferred to a
+/// later round, and dropped from the pool, each in considered order.
+///
+/// Every considered op lands in exactly one category, an op both included and dropped
+/// counts as included.
+fn split_round_ops(
+    considered: &[B256],
+    included: &HashSet<B256>,
+    dropped: &HashSet<B256>,
+) -> (Vec<B256>, Vec<B256>, Vec<B256>) {
+    let mut split = (vec![], vec![], vec![]);
+    for op_hash in considered {
+        if included.contains(op_hash) {
+            split.0.push(*op_hash);
+        } else if dropped.contains(op_hash) {
+            split.2.push(*op_hash);
+        } else {
+            split.1.push(*op_hash);
+        }
+    }
+    split
+}
+
+/// Returns the most gas a bundle of `ops` can use according to the ops' own limits.
+///
+/// This is the fixed bundle overhead plus each op's verification gas limit, call gas limit,
+/// and pre-verification gas, capped at the chain's transaction gas limit. The bundle gas
+/// estimate is never set below it, so a low estimate can't underfund the transaction.
+fn bundle_worst_case_gas<'a, UO: UserOperation + 'a>(
+    ops: impl IntoIterator<Item = &'a UO>,
+    chain_spec: &ChainSpec,
+) -> u128 {
+    let ops_gas = ops
+        .into_iter()
+        .map(|op| {
+            op.total_verification_gas_limit() + op.call_gas_limit() + op.pre_verification_gas()
+        })
+        .sum::<u128>();
+    (rundler_types::bundle_shared_gas(chain_spec) + ops_gas).min(chain_spec.transaction_gas_limit())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy_json_rpc::ErrorPayload;
+    use alloy_primitives::{utils::parse_units, Address, B256};
+    use alloy_rpc_types_eth::Block as AlloyBlock;
+    use alloy_sol_types::SolValue;
+    use alloy_transport::{TransportError, TransportErrorKind};
+    use anyhow::anyhow;
+    use rundler_provider::{
+        AnyHeader, Block, BlockHeader, BlockId, BundleSimResult, MockDAGasOracleSync,
+        MockEntryPointV0_6, MockEvmProvider, MockFeeEstimator, ProviderResult,
+        ProvidersWithEntryPoint, WithOtherFields,
+    };
+    use rundler_sim::{FaultInjectingSimulator, MockSimulator, SimulationFault};
+    use rundler_types::{
+        aggregator::{
+            AggregatorCosts, MockSignatureAggregator, SignatureAggregator, SignatureAggregatorError,
+        },
+        chain::ContractRegistry,
+        da::BedrockDAGasBlockData,
+        pool::SimulationViolation,
+        proxy::MockSubmissionProxy,
+        v0_6::{UserOperation, UserOperationBuilder, UserOperationRequiredFields},
+        BundlerSponsorship, UserOperation as _, UserOperationPermissions, ValidTimeRange,
+    };
+
+    use super::*;
+    use crate::emit::BuilderEventKind;
 
     #[tokio::test]
     async fn test_singleton_valid_bundle() {
@@ -2019,7 +3444,8 @@ mod tests {
         let cs = ChainSpec::default();
 
         let expected_gas: u64 = math::increase_by_percent(
-            op.bundle_gas_limit(&cs, Some(1)),
+            op.bundle_gas_limit(&cs, Some(1))
+                .max(bundle_worst_case_gas([&op], &cs)),
             BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
         )
         .try_into()
@@ -2073,6 +3499,161 @@ mod tests {
         assert!(bundle.rejected_ops.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_calldata_size_limit_enforcement() {
+        let op_with_call_data = |sender| {
+            op_from_required(UserOperationRequiredFields {
+                sender,
+                call_data: vec![1u8; 1000].into(),
+                pre_verification_gas: DEFAULT_PVG,
+                ..Default::default()
+            })
+        };
+        let ops = (1..=4)
+            .map(|i| op_with_call_data(address(i)))
+            .collect::<Vec<_>>();
+
+        // room for the handleOps overhead and two ops, but not a third
+        let op_size = ops[0].abi_encoded_size();
+        let max_bundle_calldata_bytes = BUNDLE_BYTE_OVERHEAD + 2 * (32 + op_size);
+
+        let bundle = mock_make_bundle_with_calldata_limit(
+            ops.iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                })
+                .collect(),
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+            HashMap::new(),
+            Some(max_bundle_calldata_bytes),
+            None,
+            true,
+            0,
+            usize::MAX,
+            u128::MAX,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // the remaining ops are skipped, not rejected
+        assert!(bundle.rejected_ops.is_empty());
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: ops[..2].to_vec(),
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_bundle_gas_limit_trims_ops() {
+        let ops = vec![op_with_sender(address(1)), op_with_sender(address(2))];
+        let make_bundle = |ops: Vec<UserOperation>, max_bundle_gas_limit| {
+            mock_make_bundle_with_calldata_limit(
+                ops.into_iter()
+                    .map(|op| MockOp {
+                        op,
+                        simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                        perms: UserOperationPermissions::default(),
+                    })
+                    .collect(),
+                vec![],
+                vec![HandleOpsOut::Success, HandleOpsOut::Success],
+                vec![],
+                0,
+                0,
+                false,
+                ExpectedStorage::default(),
+                false,
+                vec![],
+                None,
+                U256::MAX,
+                None,
+                HashMap::new(),
+                None,
+                None,
+                true,
+                0,
+                usize::MAX,
+                u128::MAX,
+                max_bundle_gas_limit,
+            )
+        };
+
+        // room for a bundle with the first op, but not both
+        let single_op_bundle = make_bundle(ops[..1].to_vec(), None).await.unwrap();
+        let max_bundle_gas_limit = single_op_bundle.gas_estimate;
+
+        let bundle = make_bundle(ops.clone(), Some(max_bundle_gas_limit))
+            .await
+            .unwrap();
+
+        // the second op is left in the pool, not rejected, and the gas limit is never
+        // lowered below the estimate
+        assert!(bundle.rejected_ops.is_empty());
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: ops[..1].to_vec(),
+                ..Default::default()
+            }]
+        );
+        assert_eq!(bundle.gas_estimate, max_bundle_gas_limit);
+    }
+
+    #[tokio::test]
+    async fn test_max_bundle_gas_limit_below_single_op() {
+        let op = op_with_sender(address(1));
+        let bundle = mock_make_bundle_with_calldata_limit(
+            vec![MockOp {
+                op,
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                perms: UserOperationPermissions::default(),
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+            HashMap::new(),
+            None,
+            None,
+            true,
+            0,
+            usize::MAX,
+            u128::MAX,
+            Some(1),
+        )
+        .await
+        .unwrap();
+
+        // no bundle is sent, and the op stays in the pool
+        assert!(bundle.ops_per_aggregator.is_empty());
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
     #[tokio::test]
     async fn test_rejects_on_violation() {
         let op = default_op();
@@ -2153,6 +3734,75 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_time_range_evaluated_at_block_timestamp() {
+        let block_timestamp = Timestamp::new(1_000_000);
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_block()
+            .withf(|block_id| *block_id == BlockId::from(hash(125)))
+            .returning(move |_| Ok(Some(block_with_timestamp(block_timestamp))));
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(ChainSpec::default().entry_point_address_v0_6);
+
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let proposer = test_proposer(
+            provider,
+            entry_point,
+            MockFeeEstimator::new(),
+            MockSimulator::new(),
+            test_settings(),
+            event_sender,
+        );
+
+        assert_eq!(
+            proposer.get_block_timestamp(hash(125)).await,
+            block_timestamp
+        );
+
+        // valid now, but not yet valid at the block timestamp
+        let valid_range =
+            ValidTimeRange::new(block_timestamp + Duration::from_secs(3600), Timestamp::MAX);
+        let op = default_op();
+        let context = proposer
+            .assemble_context(
+                U256::MAX,
+                0,
+                vec![(
+                    PoolOperationWithSponsoredDAGas {
+                        op: pool_op_from(op.clone()),
+                        sponsored_da_gas: 0,
+                    },
+                    Ok(SimulationResult {
+                        valid_time_range: valid_range,
+                        ..Default::default()
+                    }),
+                )],
+                HashMap::new(),
+                HashMap::new(),
+                block_timestamp,
+            )
+            .await;
+        assert_eq!(context.rejected_ops.len(), 1);
+
+        let event = event_receiver.try_recv().unwrap().event;
+        let BuilderEventKind::RejectedOp {
+            reason:
+                OpRejectionReason::InvalidTimeRange {
+                    valid_range: reported_range,
+                    block_timestamp: reported_timestamp,
+                },
+            ..
+        } = event.kind
+        else {
+            panic!("expected an invalid time range rejection");
+        };
+        assert_eq!(reported_range, valid_range);
+        assert_eq!(reported_timestamp, block_timestamp);
+    }
+
     #[tokio::test]
     async fn test_skips_but_not_rejects_op_accessing_another_sender() {
         let op1 = op_with_sender(address(1));
@@ -2432,67 +4082,32 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_reject_aggregator() {
-        // One op with no aggregator, two from aggregator A, and one from
-        // aggregator B.
-        let aggregator_a_address = address(10);
-        let aggregator_b_address = address(11);
-        let unaggregated_op = op_with_sender(address(1));
-        let aggregated_op_a1 =
-            op_with_sender_aggregator(address(2), aggregator_a_address, Bytes::new());
-        let aggregated_op_a2 =
-            op_with_sender_aggregator(address(3), aggregator_a_address, Bytes::new());
-        let aggregated_op_b =
-            op_with_sender_aggregator(address(4), aggregator_b_address, Bytes::new());
-
-        let aggregator_a_signature = 101;
-        let aggregator_b_signature = 102;
-
-        let agg_a = mock_signature_aggregator(aggregator_a_address, bytes(aggregator_a_signature));
-
-        // Aggregator B fails validation and should be rejected with its ops
-        let mut agg_b = MockSignatureAggregator::default();
-        agg_b.expect_address().return_const(aggregator_b_address);
-        agg_b
-            .expect_costs()
-            .return_const(AggregatorCosts::default());
-        agg_b
-            .expect_aggregate_signatures()
-            .returning(move |_| Err(SignatureAggregatorError::ValidationReverted(Bytes::new())));
+    async fn test_max_aggregators_per_bundle() {
+        // One op from each of aggregators A, B and C, with room for two aggregators
+        let aggregator_addresses = [address(10), address(11), address(12)];
+        let ops = aggregator_addresses
+            .iter()
+            .enumerate()
+            .map(|(i, &aggregator)| {
+                op_with_sender_aggregator(address(i as u8 + 1), aggregator, Bytes::new())
+            })
+            .collect::<Vec<_>>();
 
-        let mut bundle = mock_make_bundle(
-            vec![
-                MockOp {
-                    op: unaggregated_op.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: aggregated_op_a1.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: aggregated_op_a2.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: aggregated_op_b.clone(),
+        let bundle = mock_make_bundle_with_calldata_limit(
+            ops.iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
-                },
-            ],
-            vec![
-                MockAggregator {
-                    address: aggregator_a_address,
-                    signature: Box::new(move || Ok(Some(bytes(aggregator_a_signature)))),
-                },
-                MockAggregator {
-                    address: aggregator_b_address,
-                    signature: Box::new(move || Ok(Some(bytes(aggregator_b_signature)))),
-                },
-            ],
+                })
+                .collect(),
+            aggregator_addresses
+                .iter()
+                .map(|&address| MockAggregator {
+                    address,
+                    signature: Box::new(move || Ok(Some(bytes(address[0])))),
+                })
+                .collect(),
             vec![HandleOpsOut::Success],
             vec![],
             0,
@@ -2500,353 +4115,500 @@ mod tests {
             false,
             ExpectedStorage::default(),
             false,
-            vec![agg_a, agg_b],
+            aggregator_addresses
+                .iter()
+                .map(|&address| mock_signature_aggregator(address, bytes(address[0])))
+                .collect(),
             None,
             U256::MAX,
             None,
+            HashMap::new(),
+            None,
+            None,
+            true,
+            0,
+            2,
+            u128::MAX,
+            None,
         )
-        .await;
-
-        bundle
-            .ops_per_aggregator
-            .sort_by(|a, b| a.aggregator.cmp(&b.aggregator));
+        .await
+        .unwrap();
 
+        // the op from aggregator C is left in the pool for a later bundle
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![
                 UserOpsPerAggregator {
-                    user_ops: vec![unaggregated_op],
-                    ..Default::default()
+                    user_ops: vec![ops[0].clone()],
+                    aggregator: aggregator_addresses[0],
+                    signature: bytes(10),
                 },
                 UserOpsPerAggregator {
-                    user_ops: vec![aggregated_op_a1, aggregated_op_a2,],
-                    aggregator: aggregator_a_address,
-                    signature: bytes(aggregator_a_signature)
+                    user_ops: vec![ops[1].clone()],
+                    aggregator: aggregator_addresses[1],
+                    signature: bytes(11),
                 },
             ],
         );
+        assert!(bundle.rejected_ops.is_empty());
+    }
 
-        assert_eq!(bundle.rejected_ops, vec![aggregated_op_b]);
+    #[tokio::test]
+    async fn test_rejects_nonconforming_aggregator() {
+        let conforming = address(10);
+        let nonconforming = address(11);
+        let ops = vec![
+            op_with_sender_aggregator(address(1), conforming, Bytes::new()),
+            op_with_sender_aggregator(address(2), nonconforming, Bytes::new()),
+        ];
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let mut chain_spec = ChainSpec::default();
+        let mut registry = ContractRegistry::<Arc<dyn SignatureAggregator>>::default();
+        for aggregator in [conforming, nonconforming] {
+            registry.register(
+                aggregator,
+                Arc::new(mock_signature_aggregator(aggregator, bytes(aggregator[0]))),
+            );
+        }
+        chain_spec.set_signature_aggregators(Arc::new(registry));
+
+        let pool_ops = ops
+            .iter()
+            .map(|op| pool_op_from(op.clone()))
+            .collect::<Vec<_>>();
+
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _, _| Ok(SimulationResult::default()));
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+        // each aggregator is probed once, the result is reused by the second bundle
+        provider.expect_call().times(2).returning(move |tx, _, _| {
+            if tx.to == Some(conforming.into()) {
+                Ok((bytes(1),).abi_encode_params().into())
+            } else {
+                Err(ProviderError::RPC(TransportError::ErrorResp(
+                    ErrorPayload {
+                        code: 3,
+                        message: "execution reverted".into(),
+                        data: None,
+                    },
+                )))
+            }
+        });
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, _) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                chain_spec,
+                verify_aggregator_interface: true,
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        for _ in 0..2 {
+            let bundle = proposer
+                .make_bundle(pool_ops.clone(), hash(125), U256::MAX, None, false)
+                .await
+                .expect("should make a bundle");
+            assert_eq!(
+                bundle.ops_per_aggregator,
+                vec![UserOpsPerAggregator {
+                    user_ops: vec![ops[0].clone()],
+                    aggregator: conforming,
+                    signature: bytes(10),
+                }],
+            );
+            assert_eq!(bundle.rejected_ops, vec![ops[1].clone()]);
+        }
     }
 
     #[tokio::test]
-    async fn test_reject_entities() {
-        let op1 = op_with_sender_paymaster(address(1), address(1));
-        let op2 = op_with_sender_paymaster(address(2), address(1));
-        let op3 = op_with_sender_paymaster(address(3), address(2));
-        let op4 = op_with_sender_factory(address(4), address(3));
-        let op5 = op_with_sender_factory(address(5), address(3));
-        let op6 = op_with_sender_factory(address(6), address(4));
-        let deposit = parse_units("1", "ether").unwrap().into();
+    async fn test_refreshes_stale_block_hash() {
+        let op = op_with_sender_call_gas_limit(address(1), 100_000);
+        let stale_hash = hash(125);
+        let head_hash = hash(126);
 
-        let bundle = mock_make_bundle(
-            vec![
-                MockOp {
-                    op: op1.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: op2.clone(),
+        // ops are simulated and fees estimated on the head block, not the stale one
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .withf(move |_, _, block, _| *block == head_hash.into())
+            .returning(|_, _, _, _| Ok(SimulationResult::default()));
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .withf(move |block_hash, _| *block_hash == head_hash)
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        // the given block is 10 blocks behind the head
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_block()
+            .withf(move |block_id| *block_id == BlockId::from(stale_hash))
+            .returning(|_| Ok(Some(block_with_number(10))));
+        provider
+            .expect_get_block()
+            .withf(move |block_id| *block_id == BlockId::from(head_hash))
+            .returning(|_| Ok(Some(block_with_number(20))));
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((head_hash, 20)));
+
+        let chain_spec = ChainSpec::default();
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let pool_op = PoolOperation {
+            uo: op.clone().into(),
+            expected_code_hash: B256::ZERO,
+            entry_point: chain_spec.entry_point_address_v0_6,
+            sim_block_hash: stale_hash,
+            sim_block_number: 10,
+            account_is_staked: false,
+            valid_time_range: ValidTimeRange::default(),
+            entity_infos: EntityInfos::default(),
+            aggregator: None,
+            da_gas_data: Default::default(),
+            filter_id: None,
+            perms: UserOperationPermissions::default(),
+        };
+
+        let (event_sender, _) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                check_sender_eth_balance: false,
+                max_sim_block_age: Some(5),
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        assert_eq!(
+            proposer.fresh_block_hash(head_hash).await.unwrap(),
+            head_hash
+        );
+        let bundle = proposer
+            .make_bundle(vec![pool_op], stale_hash, U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        assert_eq!(bundle.iter_ops().cloned().collect::<Vec<_>>(), vec![op]);
+    }
+
+    #[tokio::test]
+    async fn test_max_op_total_gas() {
+        // One op whose gas limits sum past the maximum among several normal ops
+        let ops = [100_000, 5_000_000, 100_000, 100_000]
+            .into_iter()
+            .enumerate()
+            .map(|(i, call_gas_limit)| {
+                op_with_sender_call_gas_limit(address(i as u8 + 1), call_gas_limit)
+            })
+            .collect::<Vec<_>>();
+
+        let bundle = mock_make_bundle_with_calldata_limit(
+            ops.iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
-                },
+                })
+                .collect(),
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+            HashMap::new(),
+            None,
+            None,
+            true,
+            0,
+            usize::MAX,
+            2_000_000,
+            None,
+        )
+        .await
+        .unwrap();
+
+        // the oversized op is left in the pool rather than rejected
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![ops[0].clone(), ops[2].clone(), ops[3].clone()],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reject_aggregator() {
+        // One op with no aggregator, two from aggregator A, and one from
+        // aggregator B.
+        let aggregator_a_address = address(10);
+        let aggregator_b_address = address(11);
+        let unaggregated_op = op_with_sender(address(1));
+        let aggregated_op_a1 =
+            op_with_sender_aggregator(address(2), aggregator_a_address, Bytes::new());
+        let aggregated_op_a2 =
+            op_with_sender_aggregator(address(3), aggregator_a_address, Bytes::new());
+        let aggregated_op_b =
+            op_with_sender_aggregator(address(4), aggregator_b_address, Bytes::new());
+
+        let aggregator_a_signature = 101;
+        let aggregator_b_signature = 102;
+
+        let agg_a = mock_signature_aggregator(aggregator_a_address, bytes(aggregator_a_signature));
+
+        // Aggregator B fails validation and should be rejected with its ops
+        let mut agg_b = MockSignatureAggregator::default();
+        agg_b.expect_address().return_const(aggregator_b_address);
+        agg_b
+            .expect_costs()
+            .return_const(AggregatorCosts::default());
+        agg_b
+            .expect_aggregate_signatures()
+            .returning(move |_| Err(SignatureAggregatorError::ValidationReverted(Bytes::new())));
+
+        let mut bundle = mock_make_bundle(
+            vec![
                 MockOp {
-                    op: op3.clone(),
+                    op: unaggregated_op.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op4.clone(),
+                    op: aggregated_op_a1.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op5.clone(),
+                    op: aggregated_op_a2.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op6.clone(),
+                    op: aggregated_op_b.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
             ],
-            vec![],
             vec![
-                HandleOpsOut::FailedOp(0, "AA30: reject paymaster".to_string()),
-                HandleOpsOut::FailedOp(1, "AA13: reject factory".to_string()),
-                HandleOpsOut::Success,
+                MockAggregator {
+                    address: aggregator_a_address,
+                    signature: Box::new(move || Ok(Some(bytes(aggregator_a_signature)))),
+                },
+                MockAggregator {
+                    address: aggregator_b_address,
+                    signature: Box::new(move || Ok(Some(bytes(aggregator_b_signature)))),
+                },
             ],
-            vec![deposit, deposit, deposit],
+            vec![HandleOpsOut::Success],
+            vec![],
             0,
             0,
             false,
             ExpectedStorage::default(),
             false,
-            vec![],
+            vec![agg_a, agg_b],
             None,
             U256::MAX,
             None,
         )
         .await;
 
+        bundle
+            .ops_per_aggregator
+            .sort_by(|a, b| a.aggregator.cmp(&b.aggregator));
+
         assert_eq!(
-            bundle.entity_updates,
+            bundle.ops_per_aggregator,
             vec![
-                EntityUpdate {
-                    entity: Entity::paymaster(address(1)),
-                    update_type: EntityUpdateType::UnstakedInvalidation,
+                UserOpsPerAggregator {
+                    user_ops: vec![unaggregated_op],
                     ..Default::default()
                 },
-                EntityUpdate {
-                    entity: Entity::factory(address(3)),
-                    update_type: EntityUpdateType::UnstakedInvalidation,
-                    ..Default::default()
+                UserOpsPerAggregator {
+                    user_ops: vec![aggregated_op_a1, aggregated_op_a2,],
+                    aggregator: aggregator_a_address,
+                    signature: bytes(aggregator_a_signature)
                 },
-            ]
-        );
-        assert_eq!(bundle.rejected_ops, vec![op1, op2, op4, op5]);
-        assert_eq!(
-            bundle.ops_per_aggregator,
-            vec![UserOpsPerAggregator {
-                user_ops: vec![op3, op6],
-                ..Default::default()
-            }]
+            ],
         );
+
+        assert_eq!(bundle.rejected_ops, vec![aggregated_op_b]);
     }
 
     #[tokio::test]
-    async fn test_paymaster_amended_by_staked_factory_revert() {
-        let sender = address(1);
-        let staked_factory = address(2);
-        let paymaster = address(3);
+    async fn test_aggregator_signature_validation_failed() {
+        // handleOps fails signature validation for aggregator B, its group is dropped and
+        // the rest of the bundle is retried
+        let aggregator_a_address = address(10);
+        let aggregator_b_address = address(11);
+        let unaggregated_op = op_with_sender(address(1));
+        let aggregated_op_a =
+            op_with_sender_aggregator(address(2), aggregator_a_address, Bytes::new());
+        let aggregated_op_b1 =
+            op_with_sender_aggregator(address(3), aggregator_b_address, Bytes::new());
+        let aggregated_op_b2 =
+            op_with_sender_aggregator(address(4), aggregator_b_address, Bytes::new());
+        let aggregator_a_signature = 101;
+        let aggregator_b_signature = 102;
 
-        let deposit = parse_units("1", "ether").unwrap().into();
+        let agg_a = mock_signature_aggregator(aggregator_a_address, bytes(aggregator_a_signature));
+        let agg_b = mock_signature_aggregator(aggregator_b_address, bytes(aggregator_b_signature));
 
-        let entity_infos = EntityInfos {
-            sender: EntityInfo::new(Entity::account(sender), false),
-            factory: Some(EntityInfo::new(Entity::factory(staked_factory), true)),
-            paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
-            aggregator: None,
-        };
-
-        // EREP-015: If a staked factory or sender is present, we attribute errors to them directly.
-        // Expect EntityUpdateType::PaymasterOpsSeenDecrement to be recorded.
-        let op = op_with_sender_factory_paymaster(sender, staked_factory, paymaster);
-        let bundle = mock_make_bundle(
-            vec![MockOp {
-                op: op.clone(),
-                simulation_result: Box::new(move || {
-                    Err(SimulationError {
-                        violation_error: ViolationError::Violations(vec![]),
-                        entity_infos: Some(entity_infos),
-                    })
-                }),
-                perms: UserOperationPermissions::default(),
-            }],
-            vec![],
-            vec![],
-            vec![deposit],
-            0,
-            0,
-            false,
-            ExpectedStorage::default(),
-            false,
-            vec![],
-            None,
-            U256::MAX,
-            None,
-        )
-        .await;
-
-        let mut actual_entity_updates = bundle.entity_updates;
-        let mut expected_entity_updates = vec![
-            EntityUpdate {
-                entity: Entity::factory(staked_factory),
-                update_type: EntityUpdateType::StakedInvalidation,
-                ..Default::default()
-            },
-            EntityUpdate {
-                entity: Entity::paymaster(paymaster),
-                update_type: EntityUpdateType::PaymasterOpsSeenDecrement,
-                value: Some(1),
-            },
-        ];
-
-        // we want to check that the entity updates are the same regardless of order
-        actual_entity_updates.sort_by(|a, b| a.entity.address.cmp(&b.entity.address));
-        expected_entity_updates.sort_by(|a, b| a.entity.address.cmp(&b.entity.address));
-
-        assert_eq!(actual_entity_updates, expected_entity_updates);
-    }
-
-    #[tokio::test]
-    async fn test_paymaster_amended_by_staked_sender_revert() {
-        let sender = address(1);
-        let paymaster = address(2);
-
-        let deposit = parse_units("1", "ether").unwrap().into();
-
-        let entity_infos = EntityInfos {
-            sender: EntityInfo::new(Entity::account(sender), true),
-            factory: None,
-            paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
-            aggregator: None,
-        };
-
-        // EREP-015: If not a staked factory or sender, attribute errors to each entity directly.
-        // Expect EntityUpdateType::PaymasterOpsSeenDecrement to be recorded.
-        let op = op_with_sender_paymaster(sender, paymaster);
-        let bundle = mock_make_bundle(
-            vec![MockOp {
-                op: op.clone(),
-                simulation_result: Box::new(move || {
-                    Err(SimulationError {
-                        violation_error: ViolationError::Violations(vec![]),
-                        entity_infos: Some(entity_infos),
-                    })
-                }),
-                perms: UserOperationPermissions::default(),
-            }],
-            vec![],
-            vec![],
-            vec![deposit],
-            0,
-            0,
-            false,
-            ExpectedStorage::default(),
-            false,
-            vec![],
-            None,
-            U256::MAX,
-            None,
-        )
-        .await;
-
-        let mut actual_entity_updates = bundle.entity_updates;
-        let mut expected_entity_updates = vec![
-            EntityUpdate {
-                entity: Entity::account(sender),
-                update_type: EntityUpdateType::StakedInvalidation,
-                ..Default::default()
-            },
-            EntityUpdate {
-                entity: Entity::paymaster(paymaster),
-                update_type: EntityUpdateType::PaymasterOpsSeenDecrement,
-                value: Some(1),
-            },
-        ];
-
-        // we want to check that the entity updates are the same regardless of order
-        actual_entity_updates.sort_by(|a, b| a.entity.address.cmp(&b.entity.address));
-        expected_entity_updates.sort_by(|a, b| a.entity.address.cmp(&b.entity.address));
-
-        assert_eq!(actual_entity_updates, expected_entity_updates);
-    }
-
-    #[tokio::test]
-    async fn test_paymaster_amended_by_factory_or_sender_revert() {
-        let sender_1 = address(1);
-        let sender_2 = address(2);
-        let factory = address(3);
-        let paymaster = address(4);
-
-        let deposit = parse_units("1", "ether").unwrap().into();
-
-        let entity_infos_1 = EntityInfos {
-            sender: EntityInfo::new(Entity::account(sender_1), false),
-            factory: Some(EntityInfo::new(Entity::factory(factory), false)),
-            paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
-            aggregator: None,
-        };
-
-        let entity_infos_2 = EntityInfos {
-            sender: EntityInfo::new(Entity::account(sender_2), false),
-            factory: Some(EntityInfo::new(Entity::factory(factory), false)),
-            paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
-            aggregator: None,
-        };
-
-        // EREP-015: If a staked factory or sender is present, we attribute errors to them directly.
-        // Expect EntityUpdateType::PaymasterOpsSeenDecrement to be recorded.
-        let op_1 = op_with_sender_factory_paymaster(sender_1, factory, paymaster);
-        let op_2 = op_with_sender_factory_paymaster(sender_2, factory, paymaster);
-        let bundle = mock_make_bundle(
+        let mut bundle = mock_make_bundle(
             vec![
                 MockOp {
-                    op: op_1.clone(),
-                    simulation_result: Box::new(move || {
-                        Err(SimulationError {
-                            violation_error: ViolationError::Violations(vec![
-                                SimulationViolation::ValidationRevert(
-                                    ValidationRevert::Operation {
-                                        entry_point_reason: "AA1x: factory related errors"
-                                            .to_string(),
-                                        inner_revert_reason: None,
-                                        inner_revert_data: Bytes::new(),
-                                    },
-                                ),
-                            ]),
-                            entity_infos: Some(entity_infos_1),
-                        })
-                    }),
+                    op: unaggregated_op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op_2.clone(),
-                    simulation_result: Box::new(move || {
-                        Err(SimulationError {
-                            violation_error: ViolationError::Violations(vec![
-                                SimulationViolation::ValidationRevert(
-                                    ValidationRevert::Operation {
-                                        entry_point_reason: "AA2x: sender related errors"
-                                            .to_string(),
-                                        inner_revert_reason: None,
-                                        inner_revert_data: Bytes::new(),
-                                    },
-                                ),
-                            ]),
-                            entity_infos: Some(entity_infos_2),
-                        })
-                    }),
+                    op: aggregated_op_a.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: aggregated_op_b1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: aggregated_op_b2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
             ],
+            vec![
+                MockAggregator {
+                    address: aggregator_a_address,
+                    signature: Box::new(move || Ok(Some(bytes(aggregator_a_signature)))),
+                },
+                MockAggregator {
+                    address: aggregator_b_address,
+                    signature: Box::new(move || Ok(Some(bytes(aggregator_b_signature)))),
+                },
+            ],
+            vec![
+                HandleOpsOut::SignatureValidationFailed(aggregator_b_address),
+                HandleOpsOut::Success,
+            ],
             vec![],
-            vec![],
-            vec![deposit, deposit],
             0,
             0,
             false,
             ExpectedStorage::default(),
             false,
-            vec![],
+            vec![agg_a, agg_b],
             None,
             U256::MAX,
             None,
         )
         .await;
 
-        let actual_entity_updates = bundle.entity_updates;
-        let expected_entity_updates = vec![EntityUpdate {
-            entity: Entity::paymaster(paymaster),
-            update_type: EntityUpdateType::PaymasterOpsSeenDecrement,
-            value: Some(2),
-        }];
+        bundle
+            .ops_per_aggregator
+            .sort_by(|a, b| a.aggregator.cmp(&b.aggregator));
 
-        assert_eq!(actual_entity_updates, expected_entity_updates);
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![
+                UserOpsPerAggregator {
+                    user_ops: vec![unaggregated_op],
+                    ..Default::default()
+                },
+                UserOpsPerAggregator {
+                    user_ops: vec![aggregated_op_a],
+                    aggregator: aggregator_a_address,
+                    signature: bytes(aggregator_a_signature)
+                },
+            ],
+        );
+        assert_eq!(
+            bundle.rejected_ops,
+            vec![aggregated_op_b1, aggregated_op_b2]
+        );
+        assert_eq!(
+            bundle.entity_updates,
+            vec![EntityUpdate {
+                entity: Entity::aggregator(aggregator_b_address),
+                update_type: EntityUpdateType::StakedInvalidation,
+                value: None,
+            }]
+        );
     }
 
     #[tokio::test]
-    async fn test_bundle_gas_limit_max() {
-        // Target is 10M, max is 25M
-        // Each OP has 1M pre_verification_gas, 0 verification_gas_limit, call_gas_limit is passed in
-        let op1 = op_with_sender_call_gas_limit(address(1), 4_000_000);
-        let op2 = op_with_sender_call_gas_limit(address(2), 3_000_000);
-        // these two shouldn't be included in the bundle as they put us over the max
-        let op3 = op_with_sender_call_gas_limit(address(3), 20_000_000);
-        let op4 = op_with_sender_call_gas_limit(address(4), 20_000_000);
+    async fn test_reject_entities() {
+        let op1 = op_with_sender_paymaster(address(1), address(1));
+        let op2 = op_with_sender_paymaster(address(2), address(1));
+        let op3 = op_with_sender_paymaster(address(3), address(2));
+        let op4 = op_with_sender_factory(address(4), address(3));
+        let op5 = op_with_sender_factory(address(5), address(3));
+        let op6 = op_with_sender_factory(address(6), address(4));
         let deposit = parse_units("1", "ether").unwrap().into();
 
         let bundle = mock_make_bundle(
@@ -2871,11 +4633,25 @@ mod tests {
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
-            ],
-            vec![],
-            vec![HandleOpsOut::Success],
-            vec![deposit, deposit, deposit],
-            0,
+                MockOp {
+                    op: op5.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: op6.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+            ],
+            vec![],
+            vec![
+                HandleOpsOut::FailedOp(0, "AA30: reject paymaster".to_string()),
+                HandleOpsOut::FailedOp(1, "AA13: reject factory".to_string()),
+                HandleOpsOut::Success,
+            ],
+            vec![deposit, deposit, deposit],
+            0,
             0,
             false,
             ExpectedStorage::default(),
@@ -2887,59 +4663,49 @@ mod tests {
         )
         .await;
 
-        let cs = ChainSpec::default();
-        let expected_gas_limit: u64 = (op1.bundle_gas_limit(&cs, None)
-            + op2.bundle_gas_limit(&cs, None)
-            + rundler_types::bundle_shared_gas(&cs))
-        .try_into()
-        .unwrap();
-
-        assert_eq!(bundle.entity_updates, vec![]);
-        assert_eq!(bundle.rejected_ops, vec![]);
+        assert_eq!(
+            bundle.entity_updates,
+            vec![
+                EntityUpdate {
+                    entity: Entity::paymaster(address(1)),
+                    update_type: EntityUpdateType::UnstakedInvalidation,
+                    ..Default::default()
+                },
+                EntityUpdate {
+                    entity: Entity::factory(address(3)),
+                    update_type: EntityUpdateType::UnstakedInvalidation,
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(bundle.rejected_ops, vec![op1, op2, op4, op5]);
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op1, op2],
+                user_ops: vec![op3, op6],
                 ..Default::default()
             }]
         );
-        assert_eq!(
-            bundle.gas_estimate,
-            math::increase_by_percent(expected_gas_limit, BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT)
-        );
     }
 
     #[tokio::test]
-    async fn test_bundle_gas_limit_target() {
-        // Target is 10M, max is 25M
-        // Each OP has 1M pre_verification_gas, 0 verification_gas_limit, call_gas_limit is passed in
-        let op1 = op_with_sender_call_gas_limit(address(1), 4_000_000);
-        let op2 = op_with_sender_call_gas_limit(address(2), 6_000_000);
-        // this op should be included in the bundle as we're already at the target
-        let op3 = op_with_sender_call_gas_limit(address(3), 1_000_000);
-        let deposit = parse_units("1", "ether").unwrap().into();
-
+    async fn test_rejects_ops_failing_in_sequence() {
+        let ops: Vec<_> = (1..=3).map(|i| op_with_sender(address(i))).collect();
         let bundle = mock_make_bundle(
-            vec![
-                MockOp {
-                    op: op1.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: op2.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: op3.clone(),
+            ops.iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
-                },
+                })
+                .collect(),
+            vec![],
+            vec![
+                HandleOpsOut::FailedOp(0, "AA25 invalid account nonce".to_string()),
+                HandleOpsOut::FailedOp(0, "AA25 invalid account nonce".to_string()),
+                HandleOpsOut::Success,
             ],
             vec![],
-            vec![HandleOpsOut::Success],
-            vec![deposit, deposit, deposit],
             0,
             0,
             false,
@@ -2952,135 +4718,32 @@ mod tests {
         )
         .await;
 
-        let cs = ChainSpec::default();
-        let expected_gas_limit: u64 = (op1.bundle_gas_limit(&cs, None)
-            + op2.bundle_gas_limit(&cs, None)
-            + rundler_types::bundle_shared_gas(&cs))
-        .try_into()
-        .unwrap();
-
-        assert_eq!(bundle.entity_updates, vec![]);
-        assert_eq!(bundle.rejected_ops, vec![]);
+        assert_eq!(bundle.rejected_ops, ops[..2].to_vec());
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op1, op2],
+                user_ops: ops[2..].to_vec(),
                 ..Default::default()
             }]
         );
-        assert_eq!(
-            bundle.gas_estimate,
-            math::increase_by_percent(expected_gas_limit, BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT)
-        );
-    }
-
-    #[tokio::test]
-    async fn test_bundle_gas_limit_da_gas() {
-        let cs = ChainSpec {
-            include_da_gas_in_gas_limit: false,
-            ..Default::default()
-        };
-        let op1 = op_with_gas(100_000, 100_000, 1_000_000, false);
-        let op2 = op_with_gas(100_000, 100_000, 200_000, false);
-        let mut groups_by_aggregator = LinkedHashMap::new();
-        groups_by_aggregator.insert(
-            Address::ZERO,
-            AggregatorGroup {
-                ops_with_simulations: vec![
-                    OpWithSimulation {
-                        op: op1.clone(),
-                        simulation: SimulationResult {
-                            requires_post_op: false,
-                            ..Default::default()
-                        },
-                        sponsored_da_gas: 100_000,
-                    },
-                    OpWithSimulation {
-                        op: op2.clone(),
-                        simulation: SimulationResult {
-                            requires_post_op: false,
-                            ..Default::default()
-                        },
-                        sponsored_da_gas: 0,
-                    },
-                ],
-                signature: Default::default(),
-            },
-        );
-        let context = ProposalContext {
-            groups_by_aggregator,
-            rejected_ops: vec![],
-            entity_updates: BTreeMap::new(),
-            bundle_expected_storage: BundleExpectedStorage::default(),
-        };
-
-        // DA gas is not included in the gas limit
-        let expected_gas_limit = op1.bundle_gas_limit(&cs, None)
-            + op2.bundle_gas_limit(&cs, None)
-            + rundler_types::bundle_shared_gas(&cs);
-        assert_eq!(context.get_bundle_gas_limit(&cs), expected_gas_limit);
-
-        // DA gas is included in the gas cost (gas price is 1)
-        let expected_gas_cost = U256::from(expected_gas_limit) + U256::from(100_000);
-        assert_eq!(context.get_bundle_cost(&cs, 1), expected_gas_cost);
-    }
-
-    #[tokio::test]
-    async fn test_bundle_gas_limit_with_paymaster_op() {
-        let cs = ChainSpec::default();
-        let op1 = op_with_gas(100_000, 100_000, 1_000_000, true); // has paymaster
-        let op2 = op_with_gas(100_000, 100_000, 200_000, false);
-        let mut groups_by_aggregator = LinkedHashMap::new();
-        groups_by_aggregator.insert(
-            Address::ZERO,
-            AggregatorGroup {
-                ops_with_simulations: vec![
-                    OpWithSimulation {
-                        op: op1.clone(),
-                        simulation: SimulationResult {
-                            requires_post_op: true, // uses postOp
-                            ..Default::default()
-                        },
-                        sponsored_da_gas: 0,
-                    },
-                    OpWithSimulation {
-                        op: op2.clone(),
-                        simulation: SimulationResult {
-                            requires_post_op: false,
-                            ..Default::default()
-                        },
-                        sponsored_da_gas: 0,
-                    },
-                ],
-                signature: Default::default(),
-            },
-        );
-        let context = ProposalContext {
-            groups_by_aggregator,
-            rejected_ops: vec![],
-            entity_updates: BTreeMap::new(),
-            bundle_expected_storage: BundleExpectedStorage::default(),
-        };
-        let gas_limit = context.get_bundle_gas_limit(&cs);
-
-        let expected_gas_limit = op1.bundle_gas_limit(&cs, None)
-            + op2.bundle_gas_limit(&cs, None)
-            + rundler_types::bundle_shared_gas(&cs);
-
-        assert_eq!(gas_limit, expected_gas_limit);
     }
 
     #[tokio::test]
-    async fn test_post_op_revert() {
-        let op1 = op_with_sender(address(1));
-        let bundle = mock_make_bundle(
-            vec![MockOp {
-                op: op1.clone(),
-                simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                perms: UserOperationPermissions::default(),
-            }],
+    async fn test_stops_after_max_whittle_iterations() {
+        let ops: Vec<_> = (1..=3).map(|i| op_with_sender(address(i))).collect();
+        let bundle = mock_make_bundle_with_calldata_limit(
+            ops.iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                })
+                .collect(),
             vec![],
-            vec![HandleOpsOut::PostOpRevert, HandleOpsOut::PostOpRevert],
+            vec![
+                HandleOpsOut::FailedOp(0, "AA25 invalid account nonce".to_string()),
+                HandleOpsOut::FailedOp(0, "AA25 invalid account nonce".to_string()),
+            ],
             vec![],
             0,
             0,
@@ -3091,38 +4754,67 @@ mod tests {
             None,
             U256::MAX,
             None,
+            HashMap::new(),
+            None,
+            Some(2),
+            true,
+            0,
+            usize::MAX,
+            u128::MAX,
+            None,
         )
-        .await;
+        .await
+        .unwrap();
 
-        assert_eq!(bundle.rejected_ops, vec![op1]);
+        // the last op is left in the pool, not rejected
+        assert_eq!(bundle.rejected_ops, ops[..2].to_vec());
         assert!(bundle.ops_per_aggregator.is_empty());
     }
 
     #[tokio::test]
-    async fn test_post_op_revert_two() {
-        let op1 = op_with_sender(address(1));
-        let op2 = op_with_sender(address(2));
+    async fn test_rejects_paymaster_over_deposit() {
+        let paymaster = address(1);
+        let op_with_fees = |sender| {
+            op_from_required(UserOperationRequiredFields {
+                sender,
+                paymaster_and_data: paymaster.to_vec().into(),
+                pre_verification_gas: DEFAULT_PVG,
+                max_fee_per_gas: 1,
+                max_priority_fee_per_gas: 1,
+                ..Default::default()
+            })
+        };
+        let op1 = op_with_fees(address(2));
+        let op2 = op_with_fees(address(3));
+        // only enough deposit to cover the first op
+        let deposit = op1.max_gas_cost();
+        let simulation_result = move || {
+            Ok(SimulationResult {
+                entity_infos: EntityInfos {
+                    paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        };
+
         let bundle = mock_make_bundle(
             vec![
                 MockOp {
                     op: op1.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    simulation_result: Box::new(simulation_result),
                     perms: UserOperationPermissions::default(),
                 },
                 MockOp {
                     op: op2.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    simulation_result: Box::new(simulation_result),
                     perms: UserOperationPermissions::default(),
                 },
             ],
             vec![],
-            vec![
-                HandleOpsOut::PostOpRevert,
-                HandleOpsOut::PostOpRevert,
-                HandleOpsOut::Success,
-                HandleOpsOut::Success,
-            ],
-            vec![],
+            vec![HandleOpsOut::Success],
+            // the deposit is read for each op using the paymaster
+            vec![deposit, deposit],
             0,
             0,
             false,
@@ -3135,182 +4827,146 @@ mod tests {
         )
         .await;
 
+        // the op over the deposit is skipped, and the underfunded paymaster's other ops
+        // are rejected with it
+        assert!(bundle.ops_per_aggregator.is_empty());
         assert_eq!(bundle.rejected_ops, vec![op1]);
         assert_eq!(
-            bundle.ops_per_aggregator,
-            vec![UserOpsPerAggregator {
-                user_ops: vec![op2],
-                ..Default::default()
+            bundle.entity_updates,
+            vec![EntityUpdate {
+                entity: Entity::paymaster(paymaster),
+                update_type: EntityUpdateType::UnstakedInvalidation,
+                value: None,
             }]
         );
     }
 
     #[tokio::test]
-    async fn test_post_op_revert_agg() {
-        let aggregator_a_address = address(10);
-        let unaggregated_op = op_with_sender(address(1));
-        let aggregated_op_a1 =
-            op_with_sender_aggregator(address(2), aggregator_a_address, Bytes::new());
-        let aggregated_op_a2 =
-            op_with_sender_aggregator(address(3), aggregator_a_address, Bytes::new());
-
-        let aggregator_a_signature = 101;
-
-        let agg_a = mock_signature_aggregator(aggregator_a_address, bytes(aggregator_a_signature));
+    async fn test_skips_underfunded_sender() {
+        let op_with_fees = |sender| {
+            op_from_required(UserOperationRequiredFields {
+                sender,
+                pre_verification_gas: DEFAULT_PVG,
+                max_fee_per_gas: 1,
+                max_priority_fee_per_gas: 1,
+                ..Default::default()
+            })
+        };
+        let funded = op_with_fees(address(1));
+        let underfunded = op_with_fees(address(2));
+        let sender_funds =
+            HashMap::from([(address(2), underfunded.max_gas_cost() - U256::from(1))]);
 
-        let bundle = mock_make_bundle(
+        let bundle = mock_make_bundle_with_sender_funds(
             vec![
                 MockOp {
-                    op: unaggregated_op.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: aggregated_op_a1.clone(),
+                    op: funded.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: aggregated_op_a2.clone(),
+                    op: underfunded.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
             ],
-            vec![MockAggregator {
-                address: aggregator_a_address,
-                signature: Box::new(move || Ok(Some(bytes(aggregator_a_signature)))),
-            }],
-            vec![
-                HandleOpsOut::PostOpRevert, // bundle
-                HandleOpsOut::Success,      // unaggregated check
-                HandleOpsOut::PostOpRevert, // aggregated check
-                HandleOpsOut::Success,      // after remove
-            ],
+            vec![],
+            vec![HandleOpsOut::Success],
             vec![],
             0,
             0,
             false,
             ExpectedStorage::default(),
             false,
-            vec![agg_a],
+            vec![],
             None,
             U256::MAX,
             None,
+            sender_funds,
         )
-        .await;
+        .await
+        .unwrap();
 
-        assert_eq!(
-            bundle.rejected_ops,
-            vec![aggregated_op_a2, aggregated_op_a1]
-        );
+        assert!(bundle.rejected_ops.is_empty());
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![unaggregated_op],
+                user_ops: vec![funded],
                 ..Default::default()
             }]
         );
     }
 
     #[tokio::test]
-    async fn test_condition_not_met_match() {
-        let op = default_op();
-
-        let mut expected_storage = ExpectedStorage::default();
-        expected_storage.insert(address(1), U256::ZERO, U256::ZERO);
-        let actual_storage = expected_storage.clone();
-
-        let bundle = mock_make_bundle(
-            vec![MockOp {
-                op: op.clone(),
-                simulation_result: Box::new(move || {
-                    Ok(SimulationResult {
-                        expected_storage: expected_storage.clone(),
-                        ..Default::default()
-                    })
-                }),
-                perms: UserOperationPermissions::default(),
-            }],
-            vec![],
-            vec![HandleOpsOut::Success],
-            vec![],
-            0,
-            0,
-            true,
-            actual_storage,
-            false,
-            vec![],
-            None,
-            U256::MAX,
-            None,
-        )
-        .await;
+    async fn test_sender_deposit_buffer() {
+        let op = op_from_required(UserOperationRequiredFields {
+            sender: address(1),
+            pre_verification_gas: DEFAULT_PVG,
+            max_fee_per_gas: 1,
+            max_priority_fee_per_gas: 1,
+            ..Default::default()
+        });
+        let make_bundle = |sender_deposit_buffer_percent| {
+            mock_make_bundle_with_calldata_limit(
+                vec![MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                }],
+                vec![],
+                vec![HandleOpsOut::Success],
+                vec![],
+                0,
+                0,
+                false,
+                ExpectedStorage::default(),
+                false,
+                vec![],
+                None,
+                U256::MAX,
+                None,
+                // exactly enough to cover the prefund
+                HashMap::from([(address(1), op.max_gas_cost())]),
+                None,
+                None,
+                true,
+                sender_deposit_buffer_percent,
+                usize::MAX,
+                u128::MAX,
+                None,
+            )
+        };
 
+        let bundle = make_bundle(0).await.unwrap();
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op],
+                user_ops: vec![op.clone()],
                 ..Default::default()
             }]
         );
-    }
-
-    #[tokio::test]
-    async fn test_condition_not_met_mismatch() {
-        let op = default_op();
-
-        let mut expected_storage = ExpectedStorage::default();
-        expected_storage.insert(address(1), U256::ZERO, U256::ZERO);
-        let mut actual_storage = ExpectedStorage::default();
-        actual_storage.insert(address(1), U256::ZERO, U256::from(1));
-
-        let bundle = mock_make_bundle(
-            vec![MockOp {
-                op: op.clone(),
-                simulation_result: Box::new(move || {
-                    Ok(SimulationResult {
-                        expected_storage: expected_storage.clone(),
-                        ..Default::default()
-                    })
-                }),
-                perms: UserOperationPermissions::default(),
-            }],
-            vec![],
-            vec![HandleOpsOut::Success],
-            vec![],
-            0,
-            0,
-            true,
-            actual_storage,
-            false,
-            vec![],
-            None,
-            U256::MAX,
-            None,
-        )
-        .await;
 
+        // the op is left in the pool once a buffer is required above the prefund
+        let bundle = make_bundle(10).await.unwrap();
+        assert!(bundle.rejected_ops.is_empty());
         assert!(bundle.ops_per_aggregator.is_empty());
-        assert_eq!(bundle.rejected_ops, vec![op]);
     }
 
     #[tokio::test]
-    async fn test_single_uo_max_expected_storage_slots() {
-        let op = default_op();
-        let mut expected_storage = ExpectedStorage::default();
-        for _ in 0..=MAX_EXPECTED_STORAGE_SLOTS {
-            expected_storage.insert(Address::random(), U256::ZERO, U256::ZERO);
-        }
+    async fn test_sender_eth_balance_check_disabled() {
+        let op = op_from_required(UserOperationRequiredFields {
+            sender: address(1),
+            pre_verification_gas: DEFAULT_PVG,
+            max_fee_per_gas: 1,
+            max_priority_fee_per_gas: 1,
+            ..Default::default()
+        });
+        let sender_funds = HashMap::from([(address(1), U256::ZERO)]);
 
-        let bundle = mock_make_bundle(
+        let bundle = mock_make_bundle_with_calldata_limit(
             vec![MockOp {
-                op,
-                simulation_result: Box::new(move || {
-                    Ok(SimulationResult {
-                        expected_storage: expected_storage.clone(),
-                        ..Default::default()
-                    })
-                }),
+                op: op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
                 perms: UserOperationPermissions::default(),
             }],
             vec![],
@@ -3325,43 +4981,61 @@ mod tests {
             None,
             U256::MAX,
             None,
+            sender_funds,
+            None,
+            None,
+            false,
+            0,
+            usize::MAX,
+            u128::MAX,
+            None,
         )
-        .await;
+        .await
+        .unwrap();
 
-        assert!(bundle.is_empty())
+        // the account funds its prefund some other way, so the underfunded op is bundled
+        assert!(bundle.rejected_ops.is_empty());
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
     }
 
     #[tokio::test]
-    async fn test_skip_max_expected_storage_slots() {
-        let op0 = op_with_sender(address(1));
-        let op1 = op_with_sender(address(2));
-        let mut expected_storage0 = ExpectedStorage::default();
-        for _ in 0..MAX_EXPECTED_STORAGE_SLOTS {
-            expected_storage0.insert(Address::random(), U256::ZERO, U256::ZERO);
-        }
-        let mut expected_storage1 = ExpectedStorage::default();
-        expected_storage1.insert(Address::random(), U256::ZERO, U256::ZERO);
-
-        let bundle = mock_make_bundle(
+    async fn test_deploying_self_paying_op_uses_counterfactual_funds() {
+        let factory = address(10);
+        let deploying_op = |sender| {
+            op_from_required(UserOperationRequiredFields {
+                sender,
+                init_code: factory.to_vec().into(),
+                pre_verification_gas: DEFAULT_PVG,
+                max_fee_per_gas: 1,
+                max_priority_fee_per_gas: 1,
+                ..Default::default()
+            })
+        };
+        let funded = deploying_op(address(1));
+        let underfunded = deploying_op(address(2));
+        assert!(funded.factory().is_some());
+        // the senders have no code, their funds are read at the counterfactual addresses
+        let sender_funds = HashMap::from([
+            (address(1), funded.max_gas_cost()),
+            (address(2), underfunded.max_gas_cost() - U256::from(1)),
+        ]);
+
+        let bundle = mock_make_bundle_with_sender_funds(
             vec![
                 MockOp {
-                    op: op0.clone(),
-                    simulation_result: Box::new(move || {
-                        Ok(SimulationResult {
-                            expected_storage: expected_storage0.clone(),
-                            ..Default::default()
-                        })
-                    }),
+                    op: funded.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op1.clone(),
-                    simulation_result: Box::new(move || {
-                        Ok(SimulationResult {
-                            expected_storage: expected_storage1.clone(),
-                            ..Default::default()
-                        })
-                    }),
+                    op: underfunded.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
             ],
@@ -3377,139 +5051,221 @@ mod tests {
             None,
             U256::MAX,
             None,
+            sender_funds,
         )
-        .await;
+        .await
+        .unwrap();
 
+        assert!(bundle.rejected_ops.is_empty());
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op0],
+                user_ops: vec![funded],
                 ..Default::default()
             }]
         );
     }
 
     #[tokio::test]
-    async fn test_submitter_proxy() {
-        let op = default_op();
-        let proxy_address = address(1);
+    async fn test_paymaster_amended_by_staked_factory_revert() {
+        let sender = address(1);
+        let staked_factory = address(2);
+        let paymaster = address(3);
 
-        let mut proxy = MockSubmissionProxy::new();
-        proxy.expect_address().returning(move || proxy_address);
+        let deposit = parse_units("1", "ether").unwrap().into();
 
-        // will throw if proxy doesn't match
+        let entity_infos = EntityInfos {
+            sender: EntityInfo::new(Entity::account(sender), false),
+            factory: Some(EntityInfo::new(Entity::factory(staked_factory), true)),
+            paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
+            aggregator: None,
+        };
+
+        // EREP-015: If a staked factory or sender is present, we attribute errors to them directly.
+        // Expect EntityUpdateType::PaymasterOpsSeenDecrement to be recorded.
+        let op = op_with_sender_factory_paymaster(sender, staked_factory, paymaster);
         let bundle = mock_make_bundle(
             vec![MockOp {
                 op: op.clone(),
-                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                simulation_result: Box::new(move || {
+                    Err(SimulationError {
+                        violation_error: ViolationError::Violations(vec![]),
+                        entity_infos: Some(entity_infos),
+                    })
+                }),
                 perms: UserOperationPermissions::default(),
             }],
             vec![],
-            vec![HandleOpsOut::Success],
             vec![],
+            vec![deposit],
             0,
             0,
             false,
             ExpectedStorage::default(),
             false,
             vec![],
-            Some(proxy),
+            None,
             U256::MAX,
             None,
         )
         .await;
 
-        assert_eq!(
-            bundle.ops_per_aggregator,
-            vec![UserOpsPerAggregator {
-                user_ops: vec![op],
+        let mut actual_entity_updates = bundle.entity_updates;
+        let mut expected_entity_updates = vec![
+            EntityUpdate {
+                entity: Entity::factory(staked_factory),
+                update_type: EntityUpdateType::StakedInvalidation,
                 ..Default::default()
-            }]
-        );
+            },
+            EntityUpdate {
+                entity: Entity::paymaster(paymaster),
+                update_type: EntityUpdateType::PaymasterOpsSeenDecrement,
+                value: Some(1),
+            },
+        ];
+
+        // we want to check that the entity updates are the same regardless of order
+        actual_entity_updates.sort_by(|a, b| a.entity.address.cmp(&b.entity.address));
+        expected_entity_updates.sort_by(|a, b| a.entity.address.cmp(&b.entity.address));
+
+        assert_eq!(actual_entity_updates, expected_entity_updates);
     }
 
     #[tokio::test]
-    async fn test_submitter_proxy_revert() {
-        let op = default_op();
-        let op_hash = op.hash();
-        let proxy_address = address(1);
-        let bytes = bytes(1);
-        let cloned_bytes = bytes.clone();
+    async fn test_paymaster_amended_by_staked_sender_revert() {
+        let sender = address(1);
+        let paymaster = address(2);
 
-        let mut proxy = MockSubmissionProxy::new();
-        proxy.expect_address().returning(move || proxy_address);
-        proxy
-            .expect_process_revert()
-            .withf(move |b, _| *b == cloned_bytes)
-            .returning(move |_, _| vec![op_hash]);
+        let deposit = parse_units("1", "ether").unwrap().into();
 
-        let bundle = mock_make_bundle(
+        let entity_infos = EntityInfos {
+            sender: EntityInfo::new(Entity::account(sender), true),
+            factory: None,
+            paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
+            aggregator: None,
+        };
+
+        // EREP-015: If not a staked factory or sender, attribute errors to each entity directly.
+        // Expect EntityUpdateType::PaymasterOpsSeenDecrement to be recorded.
+        let op = op_with_sender_paymaster(sender, paymaster);
+        let bundle = mock_make_bundle(
             vec![MockOp {
                 op: op.clone(),
-                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                simulation_result: Box::new(move || {
+                    Err(SimulationError {
+                        violation_error: ViolationError::Violations(vec![]),
+                        entity_infos: Some(entity_infos),
+                    })
+                }),
                 perms: UserOperationPermissions::default(),
             }],
             vec![],
-            vec![HandleOpsOut::Revert(bytes)],
             vec![],
+            vec![deposit],
             0,
             0,
             false,
             ExpectedStorage::default(),
             false,
             vec![],
-            Some(proxy),
+            None,
             U256::MAX,
             None,
         )
         .await;
 
-        assert_eq!(bundle.ops_per_aggregator, vec![]);
+        let mut actual_entity_updates = bundle.entity_updates;
+        let mut expected_entity_updates = vec![
+            EntityUpdate {
+                entity: Entity::account(sender),
+                update_type: EntityUpdateType::StakedInvalidation,
+                ..Default::default()
+            },
+            EntityUpdate {
+                entity: Entity::paymaster(paymaster),
+                update_type: EntityUpdateType::PaymasterOpsSeenDecrement,
+                value: Some(1),
+            },
+        ];
+
+        // we want to check that the entity updates are the same regardless of order
+        actual_entity_updates.sort_by(|a, b| a.entity.address.cmp(&b.entity.address));
+        expected_entity_updates.sort_by(|a, b| a.entity.address.cmp(&b.entity.address));
+
+        assert_eq!(actual_entity_updates, expected_entity_updates);
     }
 
     #[tokio::test]
-    async fn test_bundle_expected_storage_remove() {
-        let op0 = op_with_sender(address(1));
-        let op1 = op_with_sender(address(2));
+    async fn test_paymaster_amended_by_factory_or_sender_revert() {
+        let sender_1 = address(1);
+        let sender_2 = address(2);
+        let factory = address(3);
+        let paymaster = address(4);
 
-        let address0 = Address::random();
-        let mut expected_storage0 = ExpectedStorage::default();
-        expected_storage0.insert(address0, U256::ZERO, U256::ZERO);
-        expected_storage0.insert(address0, U256::from(1), U256::ZERO);
-        let mut expected_storage1 = ExpectedStorage::default();
-        expected_storage1.insert(address0, U256::ZERO, U256::ZERO);
-        expected_storage1.insert(address0, U256::from(2), U256::ZERO);
-        let expected_storage1_clone = expected_storage1.clone();
+        let deposit = parse_units("1", "ether").unwrap().into();
+
+        let entity_infos_1 = EntityInfos {
+            sender: EntityInfo::new(Entity::account(sender_1), false),
+            factory: Some(EntityInfo::new(Entity::factory(factory), false)),
+            paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
+            aggregator: None,
+        };
+
+        let entity_infos_2 = EntityInfos {
+            sender: EntityInfo::new(Entity::account(sender_2), false),
+            factory: Some(EntityInfo::new(Entity::factory(factory), false)),
+            paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
+            aggregator: None,
+        };
 
+        // EREP-015: If a staked factory or sender is present, we attribute errors to them directly.
+        // Expect EntityUpdateType::PaymasterOpsSeenDecrement to be recorded.
+        let op_1 = op_with_sender_factory_paymaster(sender_1, factory, paymaster);
+        let op_2 = op_with_sender_factory_paymaster(sender_2, factory, paymaster);
         let bundle = mock_make_bundle(
             vec![
                 MockOp {
-                    op: op0.clone(),
+                    op: op_1.clone(),
                     simulation_result: Box::new(move || {
-                        Ok(SimulationResult {
-                            expected_storage: expected_storage0.clone(),
-                            ..Default::default()
+                        Err(SimulationError {
+                            violation_error: ViolationError::Violations(vec![
+                                SimulationViolation::ValidationRevert(
+                                    ValidationRevert::Operation {
+                                        entry_point_reason: "AA1x: factory related errors"
+                                            .to_string(),
+                                        inner_revert_reason: None,
+                                        inner_revert_data: Bytes::new(),
+                                    },
+                                ),
+                            ]),
+                            entity_infos: Some(entity_infos_1),
                         })
                     }),
                     perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op1.clone(),
+                    op: op_2.clone(),
                     simulation_result: Box::new(move || {
-                        Ok(SimulationResult {
-                            expected_storage: expected_storage1.clone(),
-                            ..Default::default()
+                        Err(SimulationError {
+                            violation_error: ViolationError::Violations(vec![
+                                SimulationViolation::ValidationRevert(
+                                    ValidationRevert::Operation {
+                                        entry_point_reason: "AA2x: sender related errors"
+                                            .to_string(),
+                                        inner_revert_reason: None,
+                                        inner_revert_data: Bytes::new(),
+                                    },
+                                ),
+                            ]),
+                            entity_infos: Some(entity_infos_2),
                         })
                     }),
                     perms: UserOperationPermissions::default(),
                 },
             ],
             vec![],
-            vec![
-                HandleOpsOut::FailedOp(0, "AA25: invalid nonce".to_string()),
-                HandleOpsOut::Success,
-            ],
             vec![],
+            vec![deposit, deposit],
             0,
             0,
             false,
@@ -3522,32 +5278,53 @@ mod tests {
         )
         .await;
 
-        assert_eq!(bundle.rejected_ops, vec![op0]);
-        assert_eq!(
-            bundle.ops_per_aggregator,
-            vec![UserOpsPerAggregator {
-                user_ops: vec![op1],
-                ..Default::default()
-            }]
-        );
-        assert_eq!(bundle.expected_storage.0, expected_storage1_clone.0);
+        let actual_entity_updates = bundle.entity_updates;
+        let expected_entity_updates = vec![EntityUpdate {
+            entity: Entity::paymaster(paymaster),
+            update_type: EntityUpdateType::PaymasterOpsSeenDecrement,
+            value: Some(2),
+        }];
+
+        assert_eq!(actual_entity_updates, expected_entity_updates);
     }
 
     #[tokio::test]
-    async fn test_trusted_op() {
-        let op = default_op();
+    async fn test_bundle_gas_limit_max() {
+        // Target is 10M, max is 25M
+        // Each OP has 1M pre_verification_gas, 0 verification_gas_limit, call_gas_limit is passed in
+        let op1 = op_with_sender_call_gas_limit(address(1), 4_000_000);
+        let op2 = op_with_sender_call_gas_limit(address(2), 3_000_000);
+        // these two shouldn't be included in the bundle as they put us over the max
+        let op3 = op_with_sender_call_gas_limit(address(3), 20_000_000);
+        let op4 = op_with_sender_call_gas_limit(address(4), 20_000_000);
+        let deposit = parse_units("1", "ether").unwrap().into();
+
         let bundle = mock_make_bundle(
-            vec![MockOp {
-                op: op.clone(),
-                simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                perms: UserOperationPermissions {
-                    trusted: true,
-                    ..Default::default()
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
                 },
-            }],
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: op3.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: op4.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+            ],
             vec![],
             vec![HandleOpsOut::Success],
-            vec![],
+            vec![deposit, deposit, deposit],
             0,
             0,
             false,
@@ -3560,38 +5337,60 @@ mod tests {
         )
         .await;
 
+        let cs = ChainSpec::default();
+        let expected_gas_limit: u64 = (op1.bundle_gas_limit(&cs, None)
+            + op2.bundle_gas_limit(&cs, None)
+            + rundler_types::bundle_shared_gas(&cs))
+        .max(bundle_worst_case_gas([&op1, &op2], &cs))
+        .try_into()
+        .unwrap();
+
+        assert_eq!(bundle.entity_updates, vec![]);
+        assert_eq!(bundle.rejected_ops, vec![]);
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op],
+                user_ops: vec![op1, op2],
                 ..Default::default()
             }]
         );
+        assert_eq!(
+            bundle.gas_estimate,
+            math::increase_by_percent(expected_gas_limit, BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT)
+        );
     }
 
     #[tokio::test]
-    async fn test_trusted_untrusted_ops() {
-        let op0 = op_with_sender(address(1));
-        let op1 = op_with_sender(address(2));
+    async fn test_bundle_gas_limit_target() {
+        // Target is 10M, max is 25M
+        // Each OP has 1M pre_verification_gas, 0 verification_gas_limit, call_gas_limit is passed in
+        let op1 = op_with_sender_call_gas_limit(address(1), 4_000_000);
+        let op2 = op_with_sender_call_gas_limit(address(2), 6_000_000);
+        // this op should be included in the bundle as we're already at the target
+        let op3 = op_with_sender_call_gas_limit(address(3), 1_000_000);
+        let deposit = parse_units("1", "ether").unwrap().into();
+
         let bundle = mock_make_bundle(
             vec![
                 MockOp {
-                    op: op0.clone(),
+                    op: op1.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions {
-                        trusted: true,
-                        ..Default::default()
-                    },
+                    perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op1.clone(),
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: op3.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                     perms: UserOperationPermissions::default(),
                 },
             ],
             vec![],
             vec![HandleOpsOut::Success],
-            vec![],
+            vec![deposit, deposit, deposit],
             0,
             0,
             false,
@@ -3604,42 +5403,339 @@ mod tests {
         )
         .await;
 
+        let cs = ChainSpec::default();
+        let expected_gas_limit: u64 = (op1.bundle_gas_limit(&cs, None)
+            + op2.bundle_gas_limit(&cs, None)
+            + rundler_types::bundle_shared_gas(&cs))
+        .max(bundle_worst_case_gas([&op1, &op2], &cs))
+        .try_into()
+        .unwrap();
+
+        assert_eq!(bundle.entity_updates, vec![]);
+        assert_eq!(bundle.rejected_ops, vec![]);
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op0, op1],
+                user_ops: vec![op1, op2],
                 ..Default::default()
             }]
         );
+        assert_eq!(
+            bundle.gas_estimate,
+            math::increase_by_percent(expected_gas_limit, BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT)
+        );
     }
 
-    #[tokio::test]
-    async fn test_underpriced_op_perms() {
-        let op0 = op_with_sender_and_fees(address(1), 7500, 0, DEFAULT_PVG); // accept
-        let op1 = op_with_sender_and_fees(address(2), 7499, 0, DEFAULT_PVG); // reject w/o remove
-        let bundle = mock_make_bundle(
+    #[test]
+    fn test_bundle_id_ignores_op_order() {
+        let ops = (1..=3)
+            .map(|n| op_with_sender(address(n)))
+            .collect::<Vec<_>>();
+        let bundle = |user_ops: Vec<UserOperation>| Bundle {
+            ops_per_aggregator: vec![UserOpsPerAggregator {
+                user_ops,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let id = bundle(ops.clone()).id(0);
+        let reordered = bundle(vec![ops[2].clone(), ops[0].clone(), ops[1].clone()]);
+        assert_eq!(reordered.id(0), id);
+        assert_eq!(bundle_id(ops.iter().rev().map(|op| op.hash()), 0), id);
+
+        // the same ops from another builder, or a different set of ops, have another id
+        assert_ne!(reordered.id(1), id);
+        assert_ne!(bundle(ops[..2].to_vec()).id(0), id);
+    }
+
+    #[test]
+    fn test_take_beneficiary_group() {
+        // (op id, beneficiary)
+        let ops = vec![
+            (1, address(1)),
+            (2, address(2)),
+            (3, address(1)),
+            (4, address(3)),
+        ];
+
+        let (beneficiary, group) = take_beneficiary_group(ops, |op| op.1);
+
+        assert_eq!(beneficiary, address(1));
+        assert_eq!(group, vec![(1, address(1)), (3, address(1))]);
+    }
+
+    #[test]
+    fn test_order_by_nonce() {
+        let key = U256::from(1) << 64;
+        // (sender, nonce), shuffled
+        let ops = vec![
+            (address(1), U256::from(2)),
+            (address(2), U256::from(7)),
+            (address(1), key + U256::from(1)),
+            (address(1), U256::from(0)),
+            (address(2), U256::from(5)),
+            (address(1), key),
+            (address(1), U256::from(1)),
+        ];
+
+        let ordered = order_by_nonce(ops, |op| *op);
+
+        assert_eq!(
+            ordered,
+            vec![
+                (address(1), U256::from(0)),
+                (address(2), U256::from(5)),
+                (address(1), key),
+                (address(1), U256::from(1)),
+                (address(2), U256::from(7)),
+                (address(1), key + U256::from(1)),
+                (address(1), U256::from(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_by_weighted_fairness() {
+        // the low fee op waits while fresh high fee ops arrive every round
+        let mut selected_round = None;
+        for rounds in 0..10 {
+            // (op id, gas price, rounds waited)
+            let ops = vec![(1, 150, 0), (0, 100, rounds), (2, 120, 0)];
+            let ordered =
+                order_by_weighted_fairness(ops, 0.1, |&(_, gas_price, rounds)| (gas_price, rounds));
+            if ordered[0].0 == 0 {
+                selected_round = Some(rounds);
+                break;
+            }
+        }
+
+        // 100 * (1 + 0.1 * 6) = 160 > 150, at 5 rounds it ties and keeps its place
+        assert_eq!(selected_round, Some(6));
+    }
+
+    #[test]
+    fn test_order_by_weighted_fairness_no_age_weight() {
+        let ops = vec![(0, 100, 50), (1, 150, 0), (2, 150, 0)];
+
+        let ordered =
+            order_by_weighted_fairness(ops, 0.0, |&(_, gas_price, rounds)| (gas_price, rounds));
+
+        // only the gas price matters, ties keep their order
+        assert_eq!(
+            ordered.iter().map(|op| op.0).collect::<Vec<_>>(),
+            vec![1, 2, 0]
+        );
+    }
+
+    #[test]
+    fn test_split_round_ops() {
+        let considered = (1..=5).map(hash).collect::<Vec<_>>();
+        // hash(2) was both included and rejected, hash(9) wasn't considered this round
+        let included = HashSet::from([hash(2), hash(4)]);
+        let dropped = HashSet::from([hash(2), hash(3), hash(9)]);
+
+        let (included, deferred, dropped) = split_round_ops(&considered, &included, &dropped);
+
+        assert_eq!(included, vec![hash(2), hash(4)]);
+        assert_eq!(deferred, vec![hash(1), hash(5)]);
+        assert_eq!(dropped, vec![hash(3)]);
+        assert_eq!(
+            included.len() + deferred.len() + dropped.len(),
+            considered.len()
+        );
+    }
+
+    #[test]
+    fn test_split_round_ops_no_bundle() {
+        let considered = vec![hash(1), hash(2)];
+
+        let (included, deferred, dropped) =
+            split_round_ops(&considered, &HashSet::new(), &HashSet::new());
+
+        assert!(included.is_empty());
+        assert_eq!(deferred, considered);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn test_bundle_worst_case_gas() {
+        let op1 = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: 50_000,
+            verification_gas_limit: 100_000,
+            call_gas_limit: 200_000,
+            ..Default::default()
+        });
+        let op2 = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: 60_000,
+            verification_gas_limit: 300_000,
+            call_gas_limit: 400_000,
+            ..Default::default()
+        });
+        let mut cs = ChainSpec::default();
+
+        assert_eq!(
+            bundle_worst_case_gas([&op1, &op2], &cs),
+            rundler_types::bundle_shared_gas(&cs) + 350_000 + 760_000
+        );
+
+        // capped at the transaction gas limit
+        cs.transaction_gas_limit = 1_000_000;
+        assert_eq!(bundle_worst_case_gas([&op1, &op2], &cs), 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_gas_limit_da_gas() {
+        let cs = ChainSpec {
+            include_da_gas_in_gas_limit: false,
+            ..Default::default()
+        };
+        let op1 = op_with_gas(100_000, 100_000, 1_000_000, false);
+        let op2 = op_with_gas(100_000, 100_000, 200_000, false);
+        let mut groups_by_aggregator = LinkedHashMap::new();
+        groups_by_aggregator.insert(
+            Address::ZERO,
+            AggregatorGroup {
+                ops_with_simulations: vec![
+                    OpWithSimulation {
+                        op: op1.clone(),
+                        simulation: SimulationResult {
+                            requires_post_op: false,
+                            ..Default::default()
+                        },
+                        sponsored_da_gas: 100_000,
+                    },
+                    OpWithSimulation {
+                        op: op2.clone(),
+                        simulation: SimulationResult {
+                            requires_post_op: false,
+                            ..Default::default()
+                        },
+                        sponsored_da_gas: 0,
+                    },
+                ],
+                signature: Default::default(),
+            },
+        );
+        let context = ProposalContext {
+            groups_by_aggregator,
+            beneficiary: Address::ZERO,
+            rejected_ops: vec![],
+            entity_updates: BTreeMap::new(),
+            bundle_expected_storage: BundleExpectedStorage::default(),
+        };
+
+        // DA gas is not included in the gas limit
+        let expected_gas_limit = op1.bundle_gas_limit(&cs, None)
+            + op2.bundle_gas_limit(&cs, None)
+            + rundler_types::bundle_shared_gas(&cs);
+        assert_eq!(context.get_bundle_gas_limit(&cs), expected_gas_limit);
+
+        // DA gas is included in the gas cost (gas price is 1)
+        let expected_gas_cost = U256::from(expected_gas_limit) + U256::from(100_000);
+        assert_eq!(context.get_bundle_cost(&cs, 1), expected_gas_cost);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_gas_limit_with_paymaster_op() {
+        let cs = ChainSpec::default();
+        let op1 = op_with_gas(100_000, 100_000, 1_000_000, true); // has paymaster
+        let op2 = op_with_gas(100_000, 100_000, 200_000, false);
+        let mut groups_by_aggregator = LinkedHashMap::new();
+        groups_by_aggregator.insert(
+            Address::ZERO,
+            AggregatorGroup {
+                ops_with_simulations: vec![
+                    OpWithSimulation {
+                        op: op1.clone(),
+                        simulation: SimulationResult {
+                            requires_post_op: true, // uses postOp
+                            ..Default::default()
+                        },
+                        sponsored_da_gas: 0,
+                    },
+                    OpWithSimulation {
+                        op: op2.clone(),
+                        simulation: SimulationResult {
+                            requires_post_op: false,
+                            ..Default::default()
+                        },
+                        sponsored_da_gas: 0,
+                    },
+                ],
+                signature: Default::default(),
+            },
+        );
+        let context = ProposalContext {
+            groups_by_aggregator,
+            beneficiary: Address::ZERO,
+            rejected_ops: vec![],
+            entity_updates: BTreeMap::new(),
+            bundle_expected_storage: BundleExpectedStorage::default(),
+        };
+        let gas_limit = context.get_bundle_gas_limit(&cs);
+
+        let expected_gas_limit = op1.bundle_gas_limit(&cs, None)
+            + op2.bundle_gas_limit(&cs, None)
+            + rundler_types::bundle_shared_gas(&cs);
+
+        assert_eq!(gas_limit, expected_gas_limit);
+    }
+
+    #[tokio::test]
+    async fn test_post_op_revert() {
+        let op1 = op_with_sender(address(1));
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op1.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                perms: UserOperationPermissions::default(),
+            }],
+            vec![],
+            vec![HandleOpsOut::PostOpRevert, HandleOpsOut::PostOpRevert],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert_eq!(bundle.rejected_ops, vec![op1]);
+        assert!(bundle.ops_per_aggregator.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_op_revert_two() {
+        let op1 = op_with_sender(address(1));
+        let op2 = op_with_sender(address(2));
+        let bundle = mock_make_bundle(
             vec![
                 MockOp {
-                    op: op0.clone(),
+                    op: op1.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions {
-                        underpriced_bundle_pct: Some(75),
-                        ..Default::default()
-                    },
+                    perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op1.clone(),
+                    op: op2.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions {
-                        underpriced_bundle_pct: Some(75),
-                        ..Default::default()
-                    },
+                    perms: UserOperationPermissions::default(),
                 },
             ],
             vec![],
-            vec![HandleOpsOut::Success],
+            vec![
+                HandleOpsOut::PostOpRevert,
+                HandleOpsOut::PostOpRevert,
+                HandleOpsOut::Success,
+                HandleOpsOut::Success,
+            ],
             vec![],
-            10000,
+            0,
             0,
             false,
             ExpectedStorage::default(),
@@ -3651,96 +5747,110 @@ mod tests {
         )
         .await;
 
+        assert_eq!(bundle.rejected_ops, vec![op1]);
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op0],
+                user_ops: vec![op2],
                 ..Default::default()
             }]
         );
     }
 
     #[tokio::test]
-    async fn test_underpriced_op_perms_pvg() {
-        let cs = ChainSpec {
-            da_pre_verification_gas: true,
-            ..Default::default()
-        };
-        let mock_op0 = op_with_sender_and_fees(address(1), 10000, 0, DEFAULT_PVG);
-        let required_pvg =
-            mock_op0.required_pre_verification_gas(&cs, 1, DEFAULT_DA_PVG, Some(0.5));
+    async fn test_post_op_revert_agg() {
+        let aggregator_a_address = address(10);
+        let unaggregated_op = op_with_sender(address(1));
+        let aggregated_op_a1 =
+            op_with_sender_aggregator(address(2), aggregator_a_address, Bytes::new());
+        let aggregated_op_a2 =
+            op_with_sender_aggregator(address(3), aggregator_a_address, Bytes::new());
+
+        let aggregator_a_signature = 101;
+
+        let agg_a = mock_signature_aggregator(aggregator_a_address, bytes(aggregator_a_signature));
 
-        let op0 = op_with_sender_and_fees(address(1), 10000, 0, math::percent(required_pvg, 75)); // accept
-        let op1 =
-            op_with_sender_and_fees(address(2), 10000, 0, math::percent(required_pvg, 75 - 1)); // reject w/o remove
         let bundle = mock_make_bundle(
             vec![
                 MockOp {
-                    op: op0.clone(),
+                    op: unaggregated_op.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions {
-                        underpriced_bundle_pct: Some(75),
-                        ..Default::default()
-                    },
+                    perms: UserOperationPermissions::default(),
                 },
                 MockOp {
-                    op: op1.clone(),
+                    op: aggregated_op_a1.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions {
-                        underpriced_bundle_pct: Some(75),
-                        ..Default::default()
-                    },
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: aggregated_op_a2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
                 },
             ],
+            vec![MockAggregator {
+                address: aggregator_a_address,
+                signature: Box::new(move || Ok(Some(bytes(aggregator_a_signature)))),
+            }],
+            vec![
+                HandleOpsOut::PostOpRevert, // bundle
+                HandleOpsOut::Success,      // unaggregated check
+                HandleOpsOut::PostOpRevert, // aggregated check
+                HandleOpsOut::Success,      // after remove
+            ],
             vec![],
-            vec![HandleOpsOut::Success],
-            vec![],
-            10000,
+            0,
             0,
             false,
             ExpectedStorage::default(),
-            true, // da_gas_tracking_enabled == dynamic PVG
-            vec![],
+            false,
+            vec![agg_a],
             None,
             U256::MAX,
             None,
         )
         .await;
 
+        assert_eq!(
+            bundle.rejected_ops,
+            vec![aggregated_op_a2, aggregated_op_a1]
+        );
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op0],
+                user_ops: vec![unaggregated_op],
                 ..Default::default()
             }]
         );
     }
 
     #[tokio::test]
-    async fn test_bundler_sponsorship_skip_fee_check() {
-        let mock_op = op_with_sender_and_fees(address(1), 0, 0, 0); // Zero fees
-        let perms = UserOperationPermissions {
-            bundler_sponsorship: Some(BundlerSponsorship {
-                max_cost: U256::from(1000000000000_u64), // High enough max cost
-                valid_until: u64::MAX,                   // Never expires
-            }),
-            ..Default::default()
-        };
+    async fn test_condition_not_met_match() {
+        let op = default_op();
+
+        let mut expected_storage = ExpectedStorage::default();
+        expected_storage.insert(address(1), U256::ZERO, U256::ZERO);
+        let actual_storage = expected_storage.clone();
 
         let bundle = mock_make_bundle(
             vec![MockOp {
-                op: mock_op.clone(),
-                simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                perms,
+                op: op.clone(),
+                simulation_result: Box::new(move || {
+                    Ok(SimulationResult {
+                        expected_storage: expected_storage.clone(),
+                        ..Default::default()
+                    })
+                }),
+                perms: UserOperationPermissions::default(),
             }],
             vec![],
             vec![HandleOpsOut::Success],
             vec![],
-            100,
-            10,
-            false,
-            ExpectedStorage::default(),
+            0,
+            0,
             true,
+            actual_storage,
+            false,
             vec![],
             None,
             U256::MAX,
@@ -3748,161 +5858,2255 @@ mod tests {
         )
         .await;
 
-        // Operation should be included despite having zero fees
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![mock_op],
+                user_ops: vec![op],
                 ..Default::default()
             }]
         );
     }
 
     #[tokio::test]
-    async fn test_bundler_sponsorship_expired() {
-        let mock_op = op_with_sender_and_fees(address(1), 0, 0, 0); // Zero fees
-        let perms = UserOperationPermissions {
-            bundler_sponsorship: Some(BundlerSponsorship {
-                max_cost: U256::from(1000), // High enough max cost
-                valid_until: u64::MAX,      // Never expires
-            }),
-            ..Default::default()
-        };
-        let error = mock_make_bundle_allow_error(
+    async fn test_condition_not_met_mismatch() {
+        let op = default_op();
+
+        let mut expected_storage = ExpectedStorage::default();
+        expected_storage.insert(address(1), U256::ZERO, U256::ZERO);
+        let mut actual_storage = ExpectedStorage::default();
+        actual_storage.insert(address(1), U256::ZERO, U256::from(1));
+
+        let bundle = mock_make_bundle(
             vec![MockOp {
-                op: mock_op.clone(),
-                simulation_result: Box::new(|| {
+                op: op.clone(),
+                simulation_result: Box::new(move || {
                     Ok(SimulationResult {
-                        valid_time_range: ValidTimeRange::from_genesis(200.into()), // Current time > valid_until
+                        expected_storage: expected_storage.clone(),
                         ..Default::default()
                     })
                 }),
-                perms,
+                perms: UserOperationPermissions::default(),
             }],
             vec![],
             vec![HandleOpsOut::Success],
             vec![],
-            10000,
-            1000,
+            0,
+            0,
+            true,
+            actual_storage,
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert!(bundle.ops_per_aggregator.is_empty());
+        assert_eq!(bundle.rejected_ops, vec![op]);
+    }
+
+    #[tokio::test]
+    async fn test_single_uo_max_expected_storage_slots() {
+        let op = default_op();
+        let mut expected_storage = ExpectedStorage::default();
+        for _ in 0..=MAX_EXPECTED_STORAGE_SLOTS {
+            expected_storage.insert(Address::random(), U256::ZERO, U256::ZERO);
+        }
+
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op,
+                simulation_result: Box::new(move || {
+                    Ok(SimulationResult {
+                        expected_storage: expected_storage.clone(),
+                        ..Default::default()
+                    })
+                }),
+                perms: UserOperationPermissions::default(),
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
             false,
             ExpectedStorage::default(),
-            true,
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert!(bundle.is_empty())
+    }
+
+    #[tokio::test]
+    async fn test_skip_max_expected_storage_slots() {
+        let op0 = op_with_sender(address(1));
+        let op1 = op_with_sender(address(2));
+        let mut expected_storage0 = ExpectedStorage::default();
+        for _ in 0..MAX_EXPECTED_STORAGE_SLOTS {
+            expected_storage0.insert(Address::random(), U256::ZERO, U256::ZERO);
+        }
+        let mut expected_storage1 = ExpectedStorage::default();
+        expected_storage1.insert(Address::random(), U256::ZERO, U256::ZERO);
+
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op0.clone(),
+                    simulation_result: Box::new(move || {
+                        Ok(SimulationResult {
+                            expected_storage: expected_storage0.clone(),
+                            ..Default::default()
+                        })
+                    }),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(move || {
+                        Ok(SimulationResult {
+                            expected_storage: expected_storage1.clone(),
+                            ..Default::default()
+                        })
+                    }),
+                    perms: UserOperationPermissions::default(),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op0],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_submitter_proxy() {
+        let op = default_op();
+        let proxy_address = address(1);
+
+        let mut proxy = MockSubmissionProxy::new();
+        proxy.expect_address().returning(move || proxy_address);
+
+        // will throw if proxy doesn't match
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                perms: UserOperationPermissions::default(),
+            }],
             vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            Some(proxy),
+            U256::MAX,
             None,
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_submitter_proxy_revert() {
+        let op = default_op();
+        let op_hash = op.hash();
+        let proxy_address = address(1);
+        let bytes = bytes(1);
+        let cloned_bytes = bytes.clone();
+
+        let mut proxy = MockSubmissionProxy::new();
+        proxy.expect_address().returning(move || proxy_address);
+        proxy
+            .expect_process_revert()
+            .withf(move |b, _| *b == cloned_bytes)
+            .returning(move |_, _| vec![op_hash]);
+
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                perms: UserOperationPermissions::default(),
+            }],
+            vec![],
+            vec![HandleOpsOut::Revert(bytes)],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            Some(proxy),
             U256::MAX,
             None,
         )
-        .await
-        .expect_err("should fail to bundle");
+        .await;
+
+        assert_eq!(bundle.ops_per_aggregator, vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_expected_storage_remove() {
+        let op0 = op_with_sender(address(1));
+        let op1 = op_with_sender(address(2));
+
+        let address0 = Address::random();
+        let mut expected_storage0 = ExpectedStorage::default();
+        expected_storage0.insert(address0, U256::ZERO, U256::ZERO);
+        expected_storage0.insert(address0, U256::from(1), U256::ZERO);
+        let mut expected_storage1 = ExpectedStorage::default();
+        expected_storage1.insert(address0, U256::ZERO, U256::ZERO);
+        expected_storage1.insert(address0, U256::from(2), U256::ZERO);
+        let expected_storage1_clone = expected_storage1.clone();
+
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op0.clone(),
+                    simulation_result: Box::new(move || {
+                        Ok(SimulationResult {
+                            expected_storage: expected_storage0.clone(),
+                            ..Default::default()
+                        })
+                    }),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(move || {
+                        Ok(SimulationResult {
+                            expected_storage: expected_storage1.clone(),
+                            ..Default::default()
+                        })
+                    }),
+                    perms: UserOperationPermissions::default(),
+                },
+            ],
+            vec![],
+            vec![
+                HandleOpsOut::FailedOp(0, "AA25: invalid nonce".to_string()),
+                HandleOpsOut::Success,
+            ],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert_eq!(bundle.rejected_ops, vec![op0]);
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(bundle.expected_storage.0, expected_storage1_clone.0);
+    }
+
+    #[tokio::test]
+    async fn test_trusted_op() {
+        let op = default_op();
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                perms: UserOperationPermissions {
+                    trusted: true,
+                    ..Default::default()
+                },
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trusted_untrusted_ops() {
+        let op0 = op_with_sender(address(1));
+        let op1 = op_with_sender(address(2));
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op0.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions {
+                        trusted: true,
+                        ..Default::default()
+                    },
+                },
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            0,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op0, op1],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_underpriced_op_perms() {
+        let op0 = op_with_sender_and_fees(address(1), 7500, 0, DEFAULT_PVG); // accept
+        let op1 = op_with_sender_and_fees(address(2), 7499, 0, DEFAULT_PVG); // reject w/o remove
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op0.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions {
+                        underpriced_bundle_pct: Some(75),
+                        ..Default::default()
+                    },
+                },
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions {
+                        underpriced_bundle_pct: Some(75),
+                        ..Default::default()
+                    },
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            10000,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op0],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_underpriced_op_perms_pvg() {
+        let cs = ChainSpec {
+            da_pre_verification_gas: true,
+            ..Default::default()
+        };
+        let mock_op0 = op_with_sender_and_fees(address(1), 10000, 0, DEFAULT_PVG);
+        let required_pvg =
+            mock_op0.required_pre_verification_gas(&cs, 1, DEFAULT_DA_PVG, Some(0.5));
+
+        let op0 = op_with_sender_and_fees(address(1), 10000, 0, math::percent(required_pvg, 75)); // accept
+        let op1 =
+            op_with_sender_and_fees(address(2), 10000, 0, math::percent(required_pvg, 75 - 1)); // reject w/o remove
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op0.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions {
+                        underpriced_bundle_pct: Some(75),
+                        ..Default::default()
+                    },
+                },
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions {
+                        underpriced_bundle_pct: Some(75),
+                        ..Default::default()
+                    },
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            10000,
+            0,
+            false,
+            ExpectedStorage::default(),
+            true, // da_gas_tracking_enabled == dynamic PVG
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op0],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bundler_sponsorship_skip_fee_check() {
+        let mock_op = op_with_sender_and_fees(address(1), 0, 0, 0); // Zero fees
+        let perms = UserOperationPermissions {
+            bundler_sponsorship: Some(BundlerSponsorship {
+                max_cost: U256::from(1000000000000_u64), // High enough max cost
+                valid_until: u64::MAX,                   // Never expires
+            }),
+            ..Default::default()
+        };
+
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: mock_op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                perms,
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            100,
+            10,
+            false,
+            ExpectedStorage::default(),
+            true,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await;
+
+        // Operation should be included despite having zero fees
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![mock_op],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bundler_sponsorship_expired() {
+        let mock_op = op_with_sender_and_fees(address(1), 0, 0, 0); // Zero fees
+        let perms = UserOperationPermissions {
+            bundler_sponsorship: Some(BundlerSponsorship {
+                max_cost: U256::from(1000), // High enough max cost
+                valid_until: u64::MAX,      // Never expires
+            }),
+            ..Default::default()
+        };
+        let error = mock_make_bundle_allow_error(
+            vec![MockOp {
+                op: mock_op.clone(),
+                simulation_result: Box::new(|| {
+                    Ok(SimulationResult {
+                        valid_time_range: ValidTimeRange::from_genesis(200.into()), // Current time > valid_until
+                        ..Default::default()
+                    })
+                }),
+                perms,
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            10000,
+            1000,
+            false,
+            ExpectedStorage::default(),
+            true,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await
+        .expect_err("should fail to bundle");
+
+        assert!(matches!(
+            error,
+            BundleProposerError::NoOperationsAfterFeeFilter
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bundler_sponsorship_over_max_cost() {
+        let mock_op = op_with_gas(0, 100_000, 100_000, false); // High gas limits
+        let perms = UserOperationPermissions {
+            bundler_sponsorship: Some(BundlerSponsorship {
+                max_cost: U256::from(1_000_000), // Max cost too low for gas price * gas limit
+                valid_until: u64::MAX,
+            }),
+            ..Default::default()
+        };
+
+        let error = mock_make_bundle_allow_error(
+            vec![MockOp {
+                op: mock_op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                perms,
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            10_000, // High base fee
+            1_000,  // High priority fee
+            false,
+            ExpectedStorage::default(),
+            true,
+            vec![],
+            None,
+            U256::MAX,
+            None,
+        )
+        .await
+        .expect_err("should fail to bundle");
+
+        assert!(matches!(
+            error,
+            BundleProposerError::NoOperationsAfterFeeFilter
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_bundle_fee() {
+        let gas_price = 1_000_000_000;
+        // Create operations with different gas limits
+        let op1 =
+            op_with_sender_call_gas_limit_and_fees(address(1), 1_000_000, gas_price, gas_price);
+        let op2 =
+            op_with_sender_call_gas_limit_and_fees(address(2), 2_000_000, gas_price, gas_price);
+        let op3 =
+            op_with_sender_call_gas_limit_and_fees(address(3), 3_000_000, gas_price, gas_price);
+
+        let cs = ChainSpec::default();
+        let total_gas_cost = U256::from(
+            op1.bundle_gas_limit(&cs, None)
+                + op2.bundle_gas_limit(&cs, None)
+                + op3.bundle_gas_limit(&cs, None),
+        ) * U256::from(gas_price);
+
+        // set max bundle fee to 1 gwei less than the total gas cost, op3 will be skipped
+        let max_bundle_fee = total_gas_cost - U256::from(1_000_000_000);
+
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+                MockOp {
+                    op: op3.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    perms: UserOperationPermissions::default(),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            gas_price,
+            0,
+            false,
+            ExpectedStorage::default(),
+            false,
+            vec![],
+            None,
+            max_bundle_fee,
+            None,
+        )
+        .await;
+
+        assert_eq!(bundle.entity_updates, vec![]);
+        assert_eq!(bundle.rejected_ops, vec![]);
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1, op2],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_op_after_max_skip_rounds() {
+        let base_fee = 1000;
+        let max_priority_fee_per_gas = 50;
+        let bundle_fees = GasFees {
+            max_fee_per_gas: base_fee + max_priority_fee_per_gas,
+            max_priority_fee_per_gas,
+        };
+        // the first op fails simulation every round, the second never bids enough
+        // priority fee. Only failures count towards the skip limit.
+        let failing = op_with_sender_and_fees(address(1), 2050, 50, DEFAULT_PVG);
+        let underpriced = op_with_sender_and_fees(address(2), 2050, 0, DEFAULT_PVG);
+        let failing_hash = failing.hash();
+        let pool_ops = vec![
+            pool_op_from(failing.clone()),
+            pool_op_from(underpriced.clone()),
+        ];
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(move |_, _| Ok((bundle_fees, base_fee)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(move |_| bundle_fees);
+
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .times(2)
+            .withf(move |op, _, _, _| op.hash() == failing_hash)
+            .returning(|_, _, _, _| {
+                Err(SimulationError {
+                    violation_error: ViolationError::Other(anyhow!("simulation failed")),
+                    entity_infos: None,
+                })
+            });
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(ChainSpec::default().entry_point_address_v0_6);
+
+        let (event_sender, _) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                max_skip_rounds: Some(2),
+                check_sender_eth_balance: false,
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        for _ in 0..2 {
+            let bundle = proposer
+                .make_bundle(pool_ops.clone(), hash(125), U256::MAX, None, false)
+                .await
+                .expect("should make a bundle");
+            assert!(bundle.is_empty());
+            assert!(bundle.rejected_ops.is_empty());
+        }
+
+        let bundle = proposer
+            .make_bundle(pool_ops.clone(), hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        assert!(bundle.is_empty());
+        assert_eq!(bundle.rejected_ops, vec![failing]);
+
+        // once the failing op is gone, the underpriced op alone is still only filtered
+        let res = proposer
+            .make_bundle(
+                vec![pool_op_from(underpriced)],
+                hash(125),
+                U256::MAX,
+                None,
+                false,
+            )
+            .await;
+        assert!(matches!(
+            res,
+            Err(BundleProposerError::NoOperationsAfterFeeFilter)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_defers_op_with_future_valid_after() {
+        let op = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: DEFAULT_PVG,
+            verification_gas_limit: 10000,
+            call_gas_limit: 100000,
+            ..Default::default()
+        });
+        let valid_after = Timestamp::now() + Duration::from_secs(3600);
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_op = pool_op_from(op.clone());
+
+        // simulated only once, the second round skips the deferred op
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .times(1)
+            .returning(move |_, _, _, _| {
+                Ok(SimulationResult {
+                    valid_time_range: ValidTimeRange::new(valid_after, Timestamp::MAX),
+                    ..Default::default()
+                })
+            });
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+
+        let (event_sender, _) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                defer_not_yet_valid_ops: true,
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(vec![pool_op.clone()], hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        assert!(bundle.ops_per_aggregator.is_empty());
+        assert!(bundle.rejected_ops.is_empty());
+
+        let res = proposer
+            .make_bundle(vec![pool_op], hash(125), U256::MAX, None, false)
+            .await;
+        assert!(matches!(
+            res,
+            Err(BundleProposerError::NoOperationsAfterFeeFilter)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_skips_op_expiring_within_min_validity_remaining() {
+        let min_validity_remaining = Duration::from_secs(120);
+        let now = Timestamp::now();
+        let at_horizon = op_with_sender(address(1));
+        let inside_horizon = op_with_sender(address(2));
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_ops = [&at_horizon, &inside_horizon]
+            .into_iter()
+            .map(|op| pool_op_from(op.clone()))
+            .collect::<Vec<_>>();
+
+        // the first op expires exactly at the horizon, the second one second before it
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(move |op, _, _, _| {
+                let remaining = if op.sender() == address(1) {
+                    min_validity_remaining
+                } else {
+                    min_validity_remaining - Duration::from_secs(1)
+                };
+                Ok(SimulationResult {
+                    valid_time_range: ValidTimeRange::new(Timestamp::MIN, now + remaining),
+                    ..Default::default()
+                })
+            });
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(move |_| Ok(Some(block_with_timestamp(now))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                min_validity_remaining,
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(pool_ops, hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        // the op inside the horizon is left in the pool, not rejected
+        assert_eq!(
+            bundle.iter_ops().cloned().collect::<Vec<_>>(),
+            vec![at_horizon]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+
+        let mut expiring_skips = vec![];
+        while let Ok(event) = event_receiver.try_recv() {
+            if let BuilderEventKind::SkippedOp {
+                op_hash,
+                reason: SkipReason::ExpiringSoon { .. },
+            } = event.kind
+            {
+                expiring_skips.push(op_hash);
+            }
+        }
+        assert_eq!(expiring_skips, vec![inside_horizon.hash()]);
+    }
+
+    #[test]
+    fn test_divergent_priority_fee_modes_per_entry_point() {
+        // bundle fees have a base fee of 1000 and a priority fee of 50
+        let fees = GasFees {
+            max_fee_per_gas: 1050,
+            max_priority_fee_per_gas: 50,
+        };
+        let op = pool_op_from(op_with_sender_and_fees(address(1), 1050, 50, DEFAULT_PVG));
+        let pays_fees = |priority_fee_mode| {
+            let chain_spec = ChainSpec::default();
+            let mut entry_point = MockEntryPointV0_6::new();
+            entry_point
+                .expect_address()
+                .return_const(chain_spec.entry_point_address_v0_6);
+            let (event_sender, _) = broadcast::channel(16);
+            let proposer = test_proposer(
+                MockEvmProvider::new(),
+                entry_point,
+                MockFeeEstimator::new(),
+                MockSimulator::new(),
+                Settings {
+                    priority_fee_mode: Some(priority_fee_mode),
+                    ..test_settings()
+                },
+                event_sender,
+            );
+            pays_required_fees(&op, proposer.required_op_fees(fees))
+        };
+
+        assert!(pays_fees(PriorityFeeMode::PriorityFeeIncreasePercent(0)));
+        // requires a priority fee of 100
+        assert!(!pays_fees(PriorityFeeMode::BaseFeePercent(10)));
+    }
+
+    #[tokio::test]
+    async fn test_caps_ops_per_paymaster() {
+        let paymaster = address(10);
+        let other_paymaster = address(11);
+        let ops = vec![
+            op_with_sender_paymaster(address(1), paymaster),
+            op_with_sender_paymaster(address(2), paymaster),
+            op_with_sender_paymaster(address(3), paymaster),
+            op_with_sender_paymaster(address(4), other_paymaster),
+            op_with_sender_paymaster(address(5), paymaster),
+        ];
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_ops = ops
+            .iter()
+            .map(|op| pool_op_from(op.clone()))
+            .collect::<Vec<_>>();
+
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _, _| Ok(SimulationResult::default()));
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, _) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                max_ops_per_paymaster: Some(2),
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(pool_ops, hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        // the third and fifth ops of the shared paymaster are left in the pool
+        assert_eq!(
+            bundle.iter_ops().cloned().collect::<Vec<_>>(),
+            vec![ops[0].clone(), ops[1].clone(), ops[3].clone()]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_caps_bundle_prefund() {
+        let ops = (0..4)
+            .map(|i| {
+                op_from_required(UserOperationRequiredFields {
+                    sender: address(i),
+                    pre_verification_gas: DEFAULT_PVG,
+                    verification_gas_limit: 10000,
+                    call_gas_limit: 100000,
+                    max_fee_per_gas: 10,
+                    max_priority_fee_per_gas: 10,
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+        // room for two ops, but not a third
+        let max_bundle_prefund = ops[0].max_gas_cost() * U256::from(5) / U256::from(2);
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_ops = ops
+            .iter()
+            .map(|op| pool_op_from(op.clone()))
+            .collect::<Vec<_>>();
+
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _, _| Ok(SimulationResult::default()));
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                max_bundle_prefund: Some(max_bundle_prefund),
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(pool_ops, hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        // the ops past the prefund cap are left in the pool
+        assert_eq!(
+            bundle.iter_ops().cloned().collect::<Vec<_>>(),
+            vec![ops[0].clone(), ops[1].clone()]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+
+        let mut prefund_skips = 0;
+        while let Ok(event) = event_receiver.try_recv() {
+            if let BuilderEventKind::SkippedOp {
+                reason: SkipReason::MaxBundlePrefund { .. },
+                ..
+            } = event.kind
+            {
+                prefund_skips += 1;
+            }
+        }
+        assert_eq!(prefund_skips, 2);
+    }
+
+    #[tokio::test]
+    async fn test_prefund_cap_skip_keeps_paymaster_deposit() {
+        let paymaster = address(10);
+        let op_with_call_gas = |sender, call_gas_limit| {
+            op_from_required(UserOperationRequiredFields {
+                sender,
+                paymaster_and_data: paymaster.to_vec().into(),
+                pre_verification_gas: DEFAULT_PVG,
+                verification_gas_limit: 10000,
+                call_gas_limit,
+                max_fee_per_gas: 10,
+                max_priority_fee_per_gas: 10,
+                ..Default::default()
+            })
+        };
+        let expensive = op_with_call_gas(address(1), 1_000_000);
+        let cheap = op_with_call_gas(address(2), 100_000);
+        // the cap only fits the cheap op, the deposit only fits one of the two
+        let max_bundle_prefund = cheap.max_gas_cost();
+        let deposit = expensive.max_gas_cost();
+        let pool_ops = vec![pool_op_from(expensive.clone()), pool_op_from(cheap.clone())];
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _, _| Ok(SimulationResult::default()));
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(ChainSpec::default().entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(move |_, _| Ok(deposit));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, _) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                max_bundle_prefund: Some(max_bundle_prefund),
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(pool_ops, hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        // the expensive op skipped by the cap doesn't use up the paymaster's deposit
+        assert_eq!(bundle.iter_ops().cloned().collect::<Vec<_>>(), vec![cheap]);
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skips_op_with_storage_conflict() {
+        let ops = (0..3)
+            .map(|i| op_with_sender(address(i)))
+            .collect::<Vec<_>>();
+        let slot_address = address(10);
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_ops = ops
+            .iter()
+            .map(|op| pool_op_from(op.clone()))
+            .collect::<Vec<_>>();
+
+        // the first two ops expect different values in the same slot, the third agrees
+        // with the first
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(move |op, _, _, _| {
+                let value = if op.sender() == address(1) {
+                    U256::from(2)
+                } else {
+                    U256::from(1)
+                };
+                let mut expected_storage = ExpectedStorage::default();
+                expected_storage.insert(slot_address, U256::ZERO, value);
+                Ok(SimulationResult {
+                    expected_storage,
+                    ..Default::default()
+                })
+            });
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            test_settings(),
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(pool_ops, hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        // the conflicting op is left in the pool
+        assert_eq!(
+            bundle.iter_ops().cloned().collect::<Vec<_>>(),
+            vec![ops[0].clone(), ops[2].clone()]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+
+        let mut conflicts = vec![];
+        while let Ok(event) = event_receiver.try_recv() {
+            if let BuilderEventKind::StorageConflict {
+                kept,
+                dropped,
+                address,
+                slot,
+            } = event.kind
+            {
+                conflicts.push((kept, dropped, address, slot));
+            }
+        }
+        assert_eq!(
+            conflicts,
+            vec![(Some(ops[0].hash()), ops[1].hash(), slot_address, B256::ZERO)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bundles_only_allowed_mempools() {
+        let ops = (0..3)
+            .map(|i| op_with_sender(address(i)))
+            .collect::<Vec<_>>();
+        let (allowed, other) = (B256::repeat_byte(1), B256::repeat_byte(2));
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_ops = ops
+            .iter()
+            .map(|op| pool_op_from(op.clone()))
+            .collect::<Vec<_>>();
+
+        // the first op is only supported by the other mempool, the rest by both
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(move |op, _, _, _| {
+                let mempools = if op.sender() == address(0) {
+                    vec![other]
+                } else {
+                    vec![allowed, other]
+                };
+                Ok(SimulationResult {
+                    mempools,
+                    ..Default::default()
+                })
+            });
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                allowed_mempools: Some(HashSet::from([allowed])),
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(pool_ops, hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        // the op outside the allowed mempool is left in the pool
+        assert_eq!(
+            bundle.iter_ops().cloned().collect::<Vec<_>>(),
+            vec![ops[1].clone(), ops[2].clone()]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+
+        let mut mempool_skips = vec![];
+        while let Ok(event) = event_receiver.try_recv() {
+            if let BuilderEventKind::SkippedOp {
+                op_hash,
+                reason: SkipReason::NotInAllowedMempools { .. },
+            } = event.kind
+            {
+                mempool_skips.push(op_hash);
+            }
+        }
+        assert_eq!(mempool_skips, vec![ops[0].hash()]);
+    }
+
+    #[tokio::test]
+    async fn test_truncates_bundle_after_max_build_duration() {
+        let ops = (0..5)
+            .map(|i| {
+                op_from_required(UserOperationRequiredFields {
+                    sender: address(i),
+                    pre_verification_gas: DEFAULT_PVG,
+                    verification_gas_limit: 10000,
+                    call_gas_limit: 100000,
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_ops = ops
+            .iter()
+            .map(|op| pool_op_from(op.clone()))
+            .collect::<Vec<_>>();
+
+        // the last two simulations never finish
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _, _| Ok(SimulationResult::default()));
+        let simulator = FaultInjectingSimulator::new(
+            simulator,
+            [
+                None,
+                None,
+                None,
+                Some(SimulationFault::Drop),
+                Some(SimulationFault::Drop),
+            ],
+        );
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                max_build_duration: Some(Duration::from_millis(100)),
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(pool_ops, hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        assert_eq!(bundle.iter_ops().count(), 3);
+        assert!(bundle.rejected_ops.is_empty());
+
+        let mut unconsidered = None;
+        while let Ok(event) = event_receiver.try_recv() {
+            if let BuilderEventKind::BundleBuildTruncated {
+                unconsidered_ops, ..
+            } = event.event.kind
+            {
+                unconsidered = Some(unconsidered_ops);
+            }
+        }
+        assert_eq!(unconsidered, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_retries_gas_estimation_after_transport_error() {
+        let op = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: DEFAULT_PVG,
+            verification_gas_limit: 10000,
+            call_gas_limit: 100000,
+            ..Default::default()
+        });
+
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_op = pool_op_from(op.clone());
+
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _, _| Ok(SimulationResult::default()));
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        // fails twice in transport, then succeeds
+        for _ in 0..2 {
+            entry_point
+                .expect_call_handle_ops()
+                .times(1)
+                .returning(|_, _, _, _, _, _| {
+                    Err(ProviderError::RPC(TransportErrorKind::custom_str(
+                        "connection reset",
+                    )))
+                });
+        }
+        entry_point
+            .expect_call_handle_ops()
+            .times(1)
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+
+        let (event_sender, _) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                estimation_retries: 2,
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(vec![pool_op], hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fixed_gas_price_overrides_fee_estimator() {
+        let chain_spec = ChainSpec::default();
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+
+        let (event_sender, _) = broadcast::channel(16);
+        // the fee estimator has no expectations, so any call to it panics
+        let proposer = test_proposer(
+            MockEvmProvider::new(),
+            entry_point,
+            MockFeeEstimator::new(),
+            MockSimulator::new(),
+            Settings {
+                fixed_gas_price: Some(FixedGasPriceSource {
+                    base_fee: 100,
+                    priority_fee: 10,
+                }),
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let (fees, base_fee) = proposer
+            .estimate_gas_fees(hash(1), None)
+            .await
+            .expect("should estimate fees");
+        assert_eq!(
+            fees,
+            GasFees {
+                max_fee_per_gas: 110,
+                max_priority_fee_per_gas: 10,
+            }
+        );
+        assert_eq!(base_fee, 100);
+    }
+
+    #[tokio::test]
+    async fn test_min_acceptable_max_fee() {
+        // base fee of 100, a priority fee of 20% of the base fee is required
+        let mode = Some(PriorityFeeMode::BaseFeePercent(20));
+        let proposer = fixed_fee_proposer(ChainSpec::default(), mode);
+        assert_eq!(
+            proposer.min_acceptable_max_fee(hash(1)).await.unwrap(),
+            U256::from(120)
+        );
+
+        // the chain's priority fee floor is above the required priority fee
+        let chain_spec = ChainSpec {
+            min_priority_fee_per_gas_in_validation: 30,
+            ..Default::default()
+        };
+        let proposer = fixed_fee_proposer(chain_spec, mode);
+        assert_eq!(
+            proposer.min_acceptable_max_fee(hash(1)).await.unwrap(),
+            U256::from(130)
+        );
+
+        // the bundle priority fee of 10 is above 5% of the base fee
+        let proposer = fixed_fee_proposer(
+            ChainSpec::default(),
+            Some(PriorityFeeMode::PercentOfBaseFee(5)),
+        );
+        assert_eq!(
+            proposer.min_acceptable_max_fee(hash(1)).await.unwrap(),
+            U256::from(110)
+        );
+
+        // 20% of the base fee is above the bundle priority fee
+        let proposer = fixed_fee_proposer(
+            ChainSpec::default(),
+            Some(PriorityFeeMode::PercentOfBaseFee(20)),
+        );
+        assert_eq!(
+            proposer.min_acceptable_max_fee(hash(1)).await.unwrap(),
+            U256::from(120)
+        );
+
+        // the overhead on the bundle base fee is not required of ops
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(ChainSpec::default().entry_point_address_v0_6);
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| {
+                Ok((
+                    GasFees {
+                        max_fee_per_gas: 160,
+                        max_priority_fee_per_gas: 10,
+                    },
+                    100,
+                ))
+            });
+        let (event_sender, _) = broadcast::channel(16);
+        let proposer = test_proposer(
+            MockEvmProvider::new(),
+            entry_point,
+            fee_estimator,
+            MockSimulator::new(),
+            Settings {
+                priority_fee_mode: Some(PriorityFeeMode::PriorityFeeIncreasePercent(0)),
+                ..test_settings()
+            },
+            event_sender,
+        );
+        assert_eq!(
+            proposer.min_acceptable_max_fee(hash(1)).await.unwrap(),
+            U256::from(110)
+        );
+    }
+
+    #[derive(Debug)]
+    struct MockTokenPriceOracle(u128);
+
+    impl TokenPriceOracle for MockTokenPriceOracle {
+        fn eth_gas_price(&self, _op: &UserOperationVariant, _base_fee: u128) -> Option<u128> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn test_token_price_oracle_ranking() {
+        let pool_op = |sender: u8, priority_fee: u128, paymaster: Option<Address>| {
+            pool_op_from(op_from_required(UserOperationRequiredFields {
+                sender: address(sender),
+                max_fee_per_gas: 1000,
+                max_priority_fee_per_gas: priority_fee,
+                paymaster_and_data: paymaster.map_or(Bytes::new(), |p| p.to_vec().into()),
+                pre_verification_gas: DEFAULT_PVG,
+                ..Default::default()
+            }))
+        };
+        let token_paymaster = address(10);
+        // pays the lowest gas price in ETH
+        let token_op = pool_op(5, 5, Some(token_paymaster));
+        let candidates = vec![
+            pool_op(1, 40, None),
+            pool_op(2, 30, None),
+            pool_op(3, 20, None),
+            pool_op(4, 10, None),
+            token_op.clone(),
+        ];
+
+        // without an oracle, the op is ranked by its ETH gas price
+        let mut proposer = ranking_fixed_fee_proposer(
+            ChainSpec::default(),
+            None,
+            SelectionStrategy::EffectiveGasPrice,
+            HashMap::new(),
+            HashMap::new(),
+        );
+        assert_eq!(
+            selection_rank(&mut proposer, candidates.clone(), token_op.uo.hash()),
+            4
+        );
+
+        // the oracle values the op above every candidate
+        let mut proposer = ranking_fixed_fee_proposer(
+            ChainSpec::default(),
+            None,
+            SelectionStrategy::EffectiveGasPrice,
+            HashMap::from([(
+                token_paymaster,
+                Arc::new(MockTokenPriceOracle(200)) as Arc<dyn TokenPriceOracle>,
+            )]),
+            HashMap::new(),
+        );
+        assert_eq!(
+            selection_rank(&mut proposer, candidates, token_op.uo.hash()),
+            0
+        );
+    }
+
+    #[test]
+    fn test_fee_override_ranking() {
+        let proposer = |fee_overrides| {
+            ranking_fixed_fee_proposer(
+                ChainSpec::default(),
+                None,
+                SelectionStrategy::EffectiveGasPrice,
+                HashMap::new(),
+                fee_overrides,
+            )
+        };
+        let pool_op = |sender: u8, priority_fee: u128| {
+            pool_op_from(op_with_sender_and_fees(
+                address(sender),
+                1000,
+                priority_fee,
+                DEFAULT_PVG,
+            ))
+        };
+        let op = pool_op(5, 5);
+        let op_hash = op.uo.hash();
+        let candidates = vec![
+            pool_op(1, 40),
+            pool_op(2, 30),
+            pool_op(3, 20),
+            pool_op(4, 10),
+            op.clone(),
+        ];
+
+        // without an override, the op is ranked by its own fees
+        assert_eq!(
+            selection_rank(&mut proposer(HashMap::new()), candidates.clone(), op_hash),
+            4
+        );
+
+        // the override's priority fee ranks the op above every candidate
+        let fee_overrides = HashMap::from([(
+            op.uo.id(),
+            GasFees {
+                max_fee_per_gas: 1000,
+                max_priority_fee_per_gas: 50,
+            },
+        )]);
+        assert_eq!(
+            selection_rank(&mut proposer(fee_overrides), candidates, op_hash),
+            0
+        );
+        assert_eq!(op.uo.max_priority_fee_per_gas(), 5);
+    }
+
+    // Returns the position of the op with `op_hash` among `candidates` once ordered for
+    // selection at a base fee of 100
+    fn selection_rank(
+        proposer: &mut BundleProposerImpl<MockProviders, BundleProposerProviders<MockSimulator>>,
+        candidates: Vec<PoolOperation>,
+        op_hash: B256,
+    ) -> usize {
+        let ops = candidates
+            .into_iter()
+            .map(|op| PoolOperationWithSponsoredDAGas {
+                op,
+                sponsored_da_gas: 0,
+            })
+            .collect();
+        proposer
+            .order_for_selection(ops, 100)
+            .iter()
+            .position(|op| op.op.uo.hash() == op_hash)
+            .expect("op should be a candidate")
+    }
+
+    fn fixed_fee_proposer(
+        chain_spec: ChainSpec,
+        priority_fee_mode: Option<PriorityFeeMode>,
+    ) -> impl BundleProposer {
+        ranking_fixed_fee_proposer(
+            chain_spec,
+            priority_fee_mode,
+            SelectionStrategy::PoolOrder,
+            HashMap::new(),
+            HashMap::new(),
+        )
+    }
+
+    fn ranking_fixed_fee_proposer(
+        chain_spec: ChainSpec,
+        priority_fee_mode: Option<PriorityFeeMode>,
+        selection_strategy: SelectionStrategy,
+        token_price_oracles: HashMap<Address, Arc<dyn TokenPriceOracle>>,
+        fee_overrides: HashMap<UserOperationId, GasFees>,
+    ) -> BundleProposerImpl<MockProviders, BundleProposerProviders<MockSimulator>> {
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+
+        let (event_sender, _) = broadcast::channel(16);
+        test_proposer(
+            MockEvmProvider::new(),
+            entry_point,
+            MockFeeEstimator::new(),
+            MockSimulator::new(),
+            Settings {
+                chain_spec,
+                fixed_gas_price: Some(FixedGasPriceSource {
+                    base_fee: 100,
+                    priority_fee: 10,
+                }),
+                priority_fee_mode,
+                selection_strategy,
+                token_price_oracles,
+                fee_overrides,
+                ..test_settings()
+            },
+            event_sender,
+        )
+    }
+
+    async fn mock_make_bundle_with_bundle_sim(
+        op: UserOperation,
+        bundle_sim: Result<BundleSimResult, HandleOpsOut>,
+    ) -> Bundle<UserOperation> {
+        mock_make_bundle_with_trace(op, Ok(bundle_sim), true, false, 0)
+            .await
+            .0
+    }
+
+    // Makes a bundle of a single op whose bundle trace returns `bundle_sim`, returning
+    // the bundle and the events emitted while making it
+    async fn mock_make_bundle_with_trace(
+        op: UserOperation,
+        bundle_sim: ProviderResult<Result<BundleSimResult, HandleOpsOut>>,
+        simulate_bundle_gas: bool,
+        attribute_bundle_gas: bool,
+        gas_estimate_buffer_percent: u32,
+    ) -> (Bundle<UserOperation>, Vec<BuilderEvent>) {
+        let mut fee_estimator = MockFeeEstimator::new();
+        fee_estimator
+            .expect_required_bundle_fees()
+            .returning(|_, _| Ok((GasFees::default(), 0)));
+        fee_estimator
+            .expect_required_op_fees()
+            .returning(|_| GasFees::default());
+
+        let chain_spec = ChainSpec::default();
+        let pool_op = pool_op_from(op);
+
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|_, _, _, _| Ok(SimulationResult::default()));
+
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((hash(125), 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_version()
+            .returning(|| EntryPointVersion::V0_6);
+        entry_point
+            .expect_address()
+            .return_const(chain_spec.entry_point_address_v0_6);
+        entry_point
+            .expect_balance_of()
+            .returning(|_, _| Ok(U256::MAX));
+        entry_point
+            .expect_call_handle_ops()
+            .returning(|_, _, _, _, _, _| Ok(HandleOpsOut::Success));
+        entry_point
+            .expect_simulate_handle_ops()
+            .times(1)
+            .return_once(move |_, _, _, _| bundle_sim);
+
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let mut proposer = test_proposer(
+            provider,
+            entry_point,
+            fee_estimator,
+            simulator,
+            Settings {
+                simulate_bundle_gas,
+                gas_estimate_buffer_percent,
+                attribute_bundle_gas,
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let bundle = proposer
+            .make_bundle(vec![pool_op], hash(125), U256::MAX, None, false)
+            .await
+            .expect("should make a bundle");
+        let mut events = vec![];
+        while let Ok(event) = event_receiver.try_recv() {
+            events.push(event);
+        }
+        (bundle, events)
+    }
+
+    #[tokio::test]
+    async fn test_bundle_sim_raises_gas_limit() {
+        let op = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: DEFAULT_PVG,
+            verification_gas_limit: 10000,
+            call_gas_limit: 100000,
+            ..Default::default()
+        });
+
+        let bundle = mock_make_bundle_with_bundle_sim(
+            op.clone(),
+            Ok(BundleSimResult {
+                total_gas: U256::from(20_000_000),
+                per_op_gas: vec![U256::from(19_000_000)],
+                ..Default::default()
+            }),
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(bundle.gas_estimate, 21_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_gas_estimate_buffer() {
+        let op = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: DEFAULT_PVG,
+            verification_gas_limit: 10000,
+            call_gas_limit: 100000,
+            ..Default::default()
+        });
+
+        let (bundle, _) = mock_make_bundle_with_trace(
+            op.clone(),
+            Ok(Ok(BundleSimResult {
+                total_gas: U256::from(20_000_000),
+                per_op_gas: vec![U256::from(19_000_000)],
+                ..Default::default()
+            })),
+            true,
+            false,
+            10,
+        )
+        .await;
+
+        // the buffer is applied on top of the simulated gas limit
+        assert_eq!(bundle.iter_ops().cloned().collect::<Vec<_>>(), vec![op]);
+        assert_eq!(bundle.gas_estimate, 23_100_000);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_sim_revert_rejects_op() {
+        let op = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: DEFAULT_PVG,
+            verification_gas_limit: 10000,
+            call_gas_limit: 100000,
+            ..Default::default()
+        });
+
+        let bundle = mock_make_bundle_with_bundle_sim(
+            op.clone(),
+            Err(HandleOpsOut::FailedOp(
+                0,
+                "AA25 invalid account nonce".to_string(),
+            )),
+        )
+        .await;
+
+        assert!(bundle.ops_per_aggregator.is_empty());
+        assert_eq!(bundle.rejected_ops, vec![op]);
+    }
+
+    #[tokio::test]
+    async fn test_bundle_gas_attribution() {
+        let op = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: DEFAULT_PVG,
+            verification_gas_limit: 10000,
+            call_gas_limit: 100000,
+            ..Default::default()
+        });
+        let attribution = GasAttribution {
+            validation_gas: U256::from(30_000),
+            execution_gas: U256::from(50_000),
+            post_op_gas: U256::ZERO,
+        };
+
+        let (bundle, events) = mock_make_bundle_with_trace(
+            op.clone(),
+            Ok(Ok(BundleSimResult {
+                total_gas: U256::from(100_000),
+                per_op_gas: vec![U256::from(90_000)],
+                gas_attribution: vec![attribution],
+            })),
+            false,
+            true,
+            0,
+        )
+        .await;
 
-        assert!(matches!(
-            error,
-            BundleProposerError::NoOperationsAfterFeeFilter
-        ));
+        assert_eq!(
+            bundle.iter_ops().cloned().collect::<Vec<_>>(),
+            vec![op.clone()]
+        );
+        let attributions = events
+            .into_iter()
+            .filter_map(|event| match event.kind {
+                BuilderEventKind::BundleGasAttribution { ops } => Some(ops),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(attributions, vec![vec![(op.hash(), attribution)]]);
     }
 
     #[tokio::test]
-    async fn test_bundler_sponsorship_over_max_cost() {
-        let mock_op = op_with_gas(0, 100_000, 100_000, false); // High gas limits
-        let perms = UserOperationPermissions {
-            bundler_sponsorship: Some(BundlerSponsorship {
-                max_cost: U256::from(1_000_000), // Max cost too low for gas price * gas limit
-                valid_until: u64::MAX,
-            }),
+    async fn test_bundle_gas_attribution_tracing_not_supported() {
+        let op = op_from_required(UserOperationRequiredFields {
+            pre_verification_gas: DEFAULT_PVG,
+            verification_gas_limit: 10000,
+            call_gas_limit: 100000,
             ..Default::default()
-        };
+        });
 
-        let error = mock_make_bundle_allow_error(
-            vec![MockOp {
-                op: mock_op.clone(),
-                simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                perms,
-            }],
-            vec![],
-            vec![HandleOpsOut::Success],
-            vec![],
-            10_000, // High base fee
-            1_000,  // High priority fee
+        let (bundle, events) = mock_make_bundle_with_trace(
+            op.clone(),
+            Err(ProviderError::RPC(TransportError::ErrorResp(
+                ErrorPayload {
+                    code: -32601,
+                    message: "the method debug_traceCall does not exist/is not available".into(),
+                    data: None,
+                },
+            ))),
             false,
-            ExpectedStorage::default(),
             true,
-            vec![],
-            None,
-            U256::MAX,
-            None,
+            0,
         )
-        .await
-        .expect_err("should fail to bundle");
+        .await;
 
-        assert!(matches!(
-            error,
-            BundleProposerError::NoOperationsAfterFeeFilter
-        ));
+        // the bundle is unaffected
+        assert_eq!(bundle.iter_ops().cloned().collect::<Vec<_>>(), vec![op]);
+        assert!(bundle.rejected_ops.is_empty());
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event.kind, BuilderEventKind::BundleGasAttribution { .. })));
     }
 
     #[tokio::test]
-    async fn test_max_bundle_fee() {
-        let gas_price = 1_000_000_000;
-        // Create operations with different gas limits
-        let op1 =
-            op_with_sender_call_gas_limit_and_fees(address(1), 1_000_000, gas_price, gas_price);
-        let op2 =
-            op_with_sender_call_gas_limit_and_fees(address(2), 2_000_000, gas_price, gas_price);
-        let op3 =
-            op_with_sender_call_gas_limit_and_fees(address(3), 3_000_000, gas_price, gas_price);
+    async fn test_check_beneficiary_can_receive() {
+        let eoa = address(1);
+        let payable = address(2);
+        let non_payable = address(3);
 
-        let cs = ChainSpec::default();
-        let total_gas_cost = U256::from(
-            op1.bundle_gas_limit(&cs, None)
-                + op2.bundle_gas_limit(&cs, None)
-                + op3.bundle_gas_limit(&cs, None),
-        ) * U256::from(gas_price);
+        let mut provider = MockEvmProvider::new();
+        provider.expect_get_code().returning(move |address, _| {
+            if address == eoa {
+                Ok(Bytes::new())
+            } else {
+                Ok(bytes(1))
+            }
+        });
+        provider
+            .expect_call()
+            .returning(move |tx, _, state_override| {
+                assert_eq!(tx.from, Some(BENEFICIARY_CHECK_SENDER));
+                assert!(state_override.is_some_and(|s| s.contains_key(&BENEFICIARY_CHECK_SENDER)));
+                if tx.to == Some(non_payable.into()) {
+                    Err(ProviderError::RPC(TransportError::ErrorResp(
+                        ErrorPayload {
+                            code: 3,
+                            message: "execution reverted".into(),
+                            data: None,
+                        },
+                    )))
+                } else {
+                    Ok(Bytes::new())
+                }
+            });
 
-        // set max bundle fee to 1 gwei less than the total gas cost, op3 will be skipped
-        let max_bundle_fee = total_gas_cost - U256::from(1_000_000_000);
+        check_beneficiary_can_receive(&provider, eoa, None)
+            .await
+            .unwrap();
+        check_beneficiary_can_receive(&provider, payable, None)
+            .await
+            .unwrap();
+        let err = check_beneficiary_can_receive(&provider, non_payable, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("reverts when receiving ETH"));
+    }
 
-        let bundle = mock_make_bundle(
-            vec![
-                MockOp {
-                    op: op1.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: op2.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-                MockOp {
-                    op: op3.clone(),
-                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
-                    perms: UserOperationPermissions::default(),
-                },
-            ],
-            vec![],
-            vec![HandleOpsOut::Success],
-            vec![],
-            gas_price,
-            0,
-            false,
-            ExpectedStorage::default(),
-            false,
-            vec![],
-            None,
-            max_bundle_fee,
-            None,
-        )
-        .await;
+    #[tokio::test]
+    async fn test_check_aggregator_interface() {
+        let conforming = address(1);
+        let reverting = address(2);
+        let no_code = address(3);
 
-        assert_eq!(bundle.entity_updates, vec![]);
-        assert_eq!(bundle.rejected_ops, vec![]);
-        assert_eq!(
-            bundle.ops_per_aggregator,
-            vec![UserOpsPerAggregator {
-                user_ops: vec![op1, op2],
-                ..Default::default()
-            }]
-        );
+        let mut provider = MockEvmProvider::new();
+        provider.expect_call().returning(move |tx, _, _| {
+            if tx.to == Some(conforming.into()) {
+                Ok((bytes(1),).abi_encode_params().into())
+            } else if tx.to == Some(reverting.into()) {
+                Err(ProviderError::RPC(TransportError::ErrorResp(
+                    ErrorPayload {
+                        code: 3,
+                        message: "execution reverted".into(),
+                        data: None,
+                    },
+                )))
+            } else {
+                Ok(Bytes::new())
+            }
+        });
+
+        for version in [EntryPointVersion::V0_6, EntryPointVersion::V0_7] {
+            assert!(
+                check_aggregator_interface(&provider, version, conforming, None)
+                    .await
+                    .unwrap()
+            );
+            assert!(
+                !check_aggregator_interface(&provider, version, reverting, None)
+                    .await
+                    .unwrap()
+            );
+            assert!(
+                !check_aggregator_interface(&provider, version, no_code, None)
+                    .await
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimation_error_response_not_retryable() {
+        let error_resp = ProviderError::RPC(TransportError::ErrorResp(ErrorPayload {
+            code: 3,
+            message: "execution reverted".into(),
+            data: None,
+        }));
+        assert!(!is_retryable_estimation_error(&error_resp));
+        assert!(is_retryable_estimation_error(&ProviderError::RPC(
+            TransportErrorKind::custom_str("connection reset")
+        )));
     }
 
     struct MockOp {
@@ -3937,6 +8141,98 @@ mod tests {
 
     const MAX_EXPECTED_STORAGE_SLOTS: usize = 100;
 
+    type MockProviders = ProvidersWithEntryPoint<
+        UserOperation,
+        Arc<MockEvmProvider>,
+        Arc<MockEntryPointV0_6>,
+        Arc<MockDAGasOracleSync>,
+        Arc<MockFeeEstimator>,
+    >;
+
+    // Settings that neither limit nor change the bundle, tests override the ones they exercise
+    fn test_settings() -> Settings {
+        Settings {
+            chain_spec: ChainSpec::default(),
+            target_bundle_gas: 10_000_000,
+            max_bundle_gas: 25_000_000,
+            sender_eoa: address(124),
+            da_gas_tracking_enabled: false,
+            max_expected_storage_slots: MAX_EXPECTED_STORAGE_SLOTS,
+            verification_gas_limit_efficiency_reject_threshold: 0.5,
+            submission_proxy: None,
+            dump_calldata: false,
+            max_skip_rounds: None,
+            max_bundle_calldata_bytes: None,
+            estimation_retries: 0,
+            max_whittle_iterations: None,
+            check_beneficiary_per_bundle: false,
+            fixed_gas_price: None,
+            simulate_bundle_gas: false,
+            gas_estimate_buffer_percent: 0,
+            attribute_bundle_gas: false,
+            allowed_mempools: None,
+            defer_not_yet_valid_ops: false,
+            max_build_duration: None,
+            priority_fee_mode: None,
+            beneficiary: None,
+            max_ops_per_paymaster: None,
+            max_bundle_prefund: None,
+            min_validity_remaining: Duration::ZERO,
+            selection_strategy: SelectionStrategy::PoolOrder,
+            token_price_oracles: HashMap::new(),
+            fee_overrides: HashMap::new(),
+            check_sender_eth_balance: true,
+            sender_deposit_buffer_percent: 0,
+            max_aggregators_per_bundle: usize::MAX,
+            verify_aggregator_interface: false,
+            max_op_total_gas: u128::MAX,
+            per_mempool_metrics: false,
+            max_sim_block_age: None,
+            max_bundle_gas_limit: None,
+        }
+    }
+
+    // Proposer over the given mocks, without a DA gas oracle
+    fn test_proposer<S>(
+        provider: MockEvmProvider,
+        entry_point: MockEntryPointV0_6,
+        fee_estimator: MockFeeEstimator,
+        simulator: S,
+        settings: Settings,
+        event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+    ) -> BundleProposerImpl<MockProviders, BundleProposerProviders<S>> {
+        BundleProposerImpl::new(
+            "test".to_string(),
+            ProvidersWithEntryPoint::new(
+                Arc::new(provider),
+                Arc::new(entry_point),
+                None,
+                Arc::new(fee_estimator),
+            ),
+            BundleProposerProviders::new(simulator),
+            settings,
+            event_sender,
+        )
+    }
+
+    // Pool op of a v0.6 op, simulated at `hash(125)`
+    fn pool_op_from(op: UserOperation) -> PoolOperation {
+        PoolOperation {
+            uo: op.into(),
+            expected_code_hash: hash(126),
+            entry_point: ChainSpec::default().entry_point_address_v0_6,
+            sim_block_hash: hash(125),
+            sim_block_number: 0,
+            account_is_staked: false,
+            valid_time_range: ValidTimeRange::default(),
+            entity_infos: EntityInfos::default(),
+            aggregator: None,
+            da_gas_data: Default::default(),
+            filter_id: None,
+            perms: UserOperationPermissions::default(),
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn mock_make_bundle(
         mock_ops: Vec<MockOp>,
@@ -3987,6 +8283,93 @@ mod tests {
         proxy: Option<MockSubmissionProxy>,
         max_bundle_fee: U256,
         max_transaction_size_bytes: Option<usize>,
+    ) -> BundleProposerResult<Bundle<UserOperation>> {
+        mock_make_bundle_with_sender_funds(
+            mock_ops,
+            mock_aggregators,
+            mock_handle_ops_call_results,
+            mock_paymaster_deposits,
+            base_fee,
+            max_priority_fee_per_gas,
+            notify_condition_not_met,
+            actual_storage,
+            da_gas_tracking_enabled,
+            aggregators,
+            proxy,
+            max_bundle_fee,
+            max_transaction_size_bytes,
+            HashMap::new(),
+        )
+        .await
+    }
+
+    /// Senders of ops without a paymaster are fully funded unless given in `sender_funds`
+    #[allow(clippy::too_many_arguments)]
+    async fn mock_make_bundle_with_sender_funds(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: u128,
+        max_priority_fee_per_gas: u128,
+        notify_condition_not_met: bool,
+        actual_storage: ExpectedStorage,
+        da_gas_tracking_enabled: bool,
+        aggregators: Vec<MockSignatureAggregator>,
+        proxy: Option<MockSubmissionProxy>,
+        max_bundle_fee: U256,
+        max_transaction_size_bytes: Option<usize>,
+        sender_funds: HashMap<Address, U256>,
+    ) -> BundleProposerResult<Bundle<UserOperation>> {
+        mock_make_bundle_with_calldata_limit(
+            mock_ops,
+            mock_aggregators,
+            mock_handle_ops_call_results,
+            mock_paymaster_deposits,
+            base_fee,
+            max_priority_fee_per_gas,
+            notify_condition_not_met,
+            actual_storage,
+            da_gas_tracking_enabled,
+            aggregators,
+            proxy,
+            max_bundle_fee,
+            max_transaction_size_bytes,
+            sender_funds,
+            None,
+            None,
+            true,
+            0,
+            usize::MAX,
+            u128::MAX,
+            None,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn mock_make_bundle_with_calldata_limit(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: u128,
+        max_priority_fee_per_gas: u128,
+        notify_condition_not_met: bool,
+        actual_storage: ExpectedStorage,
+        da_gas_tracking_enabled: bool,
+        aggregators: Vec<MockSignatureAggregator>,
+        proxy: Option<MockSubmissionProxy>,
+        max_bundle_fee: U256,
+        max_transaction_size_bytes: Option<usize>,
+        sender_funds: HashMap<Address, U256>,
+        max_bundle_calldata_bytes: Option<usize>,
+        max_whittle_iterations: Option<u64>,
+        check_sender_eth_balance: bool,
+        sender_deposit_buffer_percent: u32,
+        max_aggregators_per_bundle: usize,
+        max_op_total_gas: u128,
+        max_bundle_gas_limit: Option<u64>,
     ) -> BundleProposerResult<Bundle<UserOperation>> {
         let mut chain_spec = ChainSpec {
             da_pre_verification_gas: da_gas_tracking_enabled,
@@ -4015,6 +8398,14 @@ mod tests {
             })
             .collect();
 
+        let self_paying_senders: Arc<HashSet<Address>> = Arc::new(
+            mock_ops
+                .iter()
+                .filter(|mock_op| mock_op.op.paymaster().is_none())
+                .map(|mock_op| mock_op.op.sender())
+                .collect(),
+        );
+
         let simulations_by_op: Arc<HashMap<B256, MockOp>> = Arc::from(
             mock_ops
                 .into_iter()
@@ -4027,7 +8418,7 @@ mod tests {
         simulator
             .expect_simulate_validation()
             .withf(move |op, &trusted, &block_hash, &code_hash| {
-                block_hash == current_block_hash
+                block_hash == current_block_hash.into()
                     && code_hash == Some(expected_code_hash)
                     && simulations_by_op_cloned[&op.hash()].perms.trusted == trusted
             })
@@ -4048,6 +8439,14 @@ mod tests {
                 .withf(move |_, &b, _, _, &p, _| b == sender_eoa && p == proxy_address)
                 .return_once(|_, _, _, _, _, _| Ok(call_res));
         }
+        // sender deposits are matched by address so they don't consume the paymaster deposits
+        let senders = Arc::clone(&self_paying_senders);
+        entry_point
+            .expect_balance_of()
+            .withf(move |address, _| senders.contains(address))
+            .returning(move |address, _| {
+                Ok(sender_funds.get(&address).copied().unwrap_or(U256::MAX))
+            });
         for deposit in mock_paymaster_deposits {
             entry_point
                 .expect_balance_of()
@@ -4064,6 +8463,13 @@ mod tests {
         provider
             .expect_get_latest_block_hash_and_number()
             .returning(move || Ok((current_block_hash, 0)));
+        provider
+            .expect_get_block()
+            .returning(|_| Ok(Some(block_with_timestamp(Timestamp::now()))));
+        provider
+            .expect_get_balance()
+            .withf(move |address, _| self_paying_senders.contains(address))
+            .returning(|_, _| Ok(U256::ZERO));
 
         let mut fee_estimator = MockFeeEstimator::new();
         fee_estimator
@@ -4138,13 +8544,17 @@ mod tests {
             BundleProposerProviders::new(simulator),
             Settings {
                 chain_spec,
-                target_bundle_gas: 10_000_000,
-                max_bundle_gas: 25_000_000,
                 sender_eoa,
                 da_gas_tracking_enabled,
-                max_expected_storage_slots: MAX_EXPECTED_STORAGE_SLOTS,
-                verification_gas_limit_efficiency_reject_threshold: 0.5,
                 submission_proxy,
+                max_bundle_calldata_bytes,
+                max_whittle_iterations,
+                check_sender_eth_balance,
+                sender_deposit_buffer_percent,
+                max_aggregators_per_bundle,
+                max_op_total_gas,
+                max_bundle_gas_limit,
+                ..test_settings()
             },
             event_sender,
         );
@@ -4158,6 +8568,33 @@ mod tests {
             .await
     }
 
+    fn block_with_timestamp(timestamp: Timestamp) -> Block {
+        Block::new(WithOtherFields::new(AlloyBlock {
+            header: BlockHeader {
+                inner: AnyHeader {
+                    timestamp: timestamp.seconds_since_epoch(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+    }
+
+    fn block_with_number(number: u64) -> Block {
+        Block::new(WithOtherFields::new(AlloyBlock {
+            header: BlockHeader {
+                inner: AnyHeader {
+                    number,
+                    timestamp: Timestamp::now().seconds_since_epoch(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+    }
+
     fn address(n: u8) -> Address {
         let mut bytes = [0_u8; 20];
         bytes[0] = n;
@@ -11,14 +11,19 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{pin::Pin, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
 
 use alloy_primitives::{Address, B256};
 use anyhow::{bail, Context};
 use async_trait::async_trait;
 use futures::Stream;
 use futures_util::StreamExt;
-use metrics::{Counter, Histogram};
+use metrics::{Counter, Gauge, Histogram};
 use metrics_derive::Metrics;
 #[cfg(test)]
 use mockall::automock;
@@ -33,9 +38,10 @@ use rundler_types::{
     chain::ChainSpec,
     pool::{AddressUpdate, NewHead, Pool, PoolOperation},
     proxy::SubmissionProxy,
-    EntityUpdate, ExpectedStorage, UserOperation,
+    Entity, EntityUpdate, EntityUpdateType, ExpectedStorage, PriorityFeeMode, UserOperation,
+    UserOperationId,
 };
-use rundler_utils::{emit::WithEntryPoint, eth};
+use rundler_utils::{cache::LruMap, emit::WithEntryPoint, eth};
 use tokio::{
     join,
     sync::{
@@ -48,14 +54,28 @@ use tracing::{debug, error, info, instrument, warn};
 
 use crate::{
     assigner::Assigner,
-    bundle_proposer::{Bundle, BundleProposer, BundleProposerError},
-    emit::{BuilderEvent, BundleTxDetails},
+    audit::{BundleAuditRecord, BundleAuditSink},
+    bundle_proposer::{self, Bundle, BundleProposer, BundleProposerError},
+    emit::{
+        BuilderEvent, BundleTxDetails, CancellationOutcome, MinedOpOutcome, OpRejectionReason,
+        SkipReason,
+    },
+    fee_controller::{self, PriorityFeeController},
+    sender::create_hard_cancel_tx,
+    tip::{self, TipTransfer},
     transaction_tracker::{
-        TrackerState, TrackerUpdate, TransactionTracker, TransactionTrackerError,
+        MinedOpResult, TrackerState, TrackerUpdate, TransactionTracker, TransactionTrackerError,
     },
+    transformer::TransactionTransformer,
     BuilderSettings,
 };
 
+/// Minimum number of blocks between idle events while the builder has nothing to bundle
+const IDLE_EVENT_INTERVAL_BLOCKS: u64 = 100;
+
+/// Maximum number of operations to track on-chain revert counts for
+const ONCHAIN_REVERT_COUNTS_CACHE_SIZE: u32 = 4096;
+
 #[async_trait]
 pub(crate) trait BundleSender: Send + Sync {
     async fn send_bundles_in_loop<T: TaskSpawner>(self, task_spawner: T);
@@ -66,6 +86,41 @@ pub(crate) struct Settings {
     pub(crate) max_replacement_underpriced_blocks: u64,
     pub(crate) max_cancellation_fee_increases: u64,
     pub(crate) max_blocks_to_wait_for_mine: u64,
+    /// If set, a replacement bundle re-includes the ops of the bundle it replaces
+    /// first, in the same order, before any new ops from the pool
+    pub(crate) sticky_replacement: bool,
+    /// If set, the ops of a pending bundle are re-simulated after this many blocks
+    /// without a mine, and the bundle is rebuilt without waiting for the fee increase
+    /// if any of them are no longer valid
+    pub(crate) resim_after_blocks: Option<u64>,
+    /// If set, ops with the same sender and nonce as an op in the pending bundle, but a
+    /// different hash, are skipped until the pending bundle resolves
+    pub(crate) skip_pending_nonce_duplicates: bool,
+    /// Delay before the sender proposes its first bundle, used to stagger the start of
+    /// builders that would otherwise compete for the same ops
+    pub(crate) warm_up: Duration,
+    /// Maximum number of times a round polls the pool again right away when its bundle
+    /// empties out because every op was removed, instead of waiting for the next trigger
+    pub(crate) repoll_on_empty: u64,
+    /// If set, a zero-value self-transfer is sent when no bundle has been sent for this
+    /// many blocks, to keep the nonce and the connection to the node's mempool warm
+    pub(crate) heartbeat_interval_blocks: Option<u64>,
+    /// If set, the priority fee mode required of ops is adjusted within these bounds
+    /// based on how quickly recent bundles were mined
+    pub(crate) priority_fee_controller: Option<fee_controller::Settings>,
+    /// If set, an op is removed from the pool once this many of its mined bundles have
+    /// included it without a `UserOperationEvent`, i.e. it reverted on-chain
+    pub(crate) max_onchain_revert_attempts: Option<u64>,
+    /// Index of the builder among the entry point's builders, part of the id of each
+    /// bundle it sends
+    pub(crate) builder_index: u64,
+    /// If set, before abandoning a cancellation that didn't mine after the maximum fee
+    /// increases, the on-chain nonce is checked, and the sender keeps waiting for the
+    /// tracker to report the mined transaction if the nonce was used
+    pub(crate) recheck_cancellation_nonce: bool,
+    /// If set, each bundle transaction is wrapped in a multicall that also transfers a
+    /// tip to the configured recipient if `handleOps` succeeds
+    pub(crate) tip_transfer: Option<TipTransfer>,
 }
 
 pub(crate) struct BundleSenderImpl<P, EP, T, C> {
@@ -76,6 +131,9 @@ pub(crate) struct BundleSenderImpl<P, EP, T, C> {
     sender_eoa: Address,
     // Optional submission proxy - bundles are sent through this contract
     submission_proxy: Option<Arc<dyn SubmissionProxy>>,
+    transaction_transformer: Arc<dyn TransactionTransformer>,
+    // Durable record of every bundle transaction sent
+    audit_sink: Arc<dyn BundleAuditSink>,
     proposer: P,
     ep_providers: EP,
     transaction_tracker: Option<T>,
@@ -85,6 +143,32 @@ pub(crate) struct BundleSenderImpl<P, EP, T, C> {
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     metrics: BuilderMetric,
     ep_address: Address,
+    // (sender, op hash) pairs of the last bundle sent, used for sticky replacement
+    last_bundle_ops: Option<Arc<Vec<(Address, B256)>>>,
+    // ops of each bundle transaction sent for the current nonce, keyed by transaction hash
+    sent_bundle_ops: HashMap<B256, Arc<Vec<(Address, B256)>>>,
+    // submission metadata of the ops in `sent_bundle_ops`, keyed by op hash
+    sent_op_metadata: HashMap<B256, OpMetadata>,
+    // hashes of the ops in `sent_bundle_ops`, keyed by op id
+    sent_op_ids: HashMap<UserOperationId, B256>,
+    // block of the last idle event, cleared when a bundle is sent
+    last_idle_event_block: Option<u64>,
+    // block of the last bundle or heartbeat sent, or of the first idle round if none
+    // has been sent yet
+    last_send_block: Option<u64>,
+    // block the first bundle transaction for the current nonce was sent at
+    first_send_block: Option<u64>,
+    fee_controller: Option<PriorityFeeController>,
+    // number of mined bundles each op reverted on-chain in, keyed by op hash
+    onchain_revert_counts: LruMap<B256, u64>,
+    clock: Arc<dyn Clock>,
+}
+
+// Metadata supplied with an op at submission, echoed on its mined op event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct OpMetadata {
+    client_tag: Option<String>,
+    submission_source: Option<String>,
 }
 
 #[derive(Debug)]
@@ -97,6 +181,8 @@ struct BundleTx {
 pub enum BundleSenderAction {
     SendBundle(SendBundleRequest),
     ChangeMode(BundlingMode),
+    // Cancel the pending bundle if it contains the op with this hash
+    CancelContaining(B256),
 }
 
 pub struct SendBundleRequest {
@@ -127,8 +213,9 @@ enum SendBundleAttemptResult {
     NoOperationsInitially,
     // There were no operations after the fee was increased
     NoOperationsAfterFeeFilter,
-    // There were no operations after the bundle was simulated
-    NoOperationsAfterSimulation,
+    // There were no operations after the bundle was simulated. `removed_ops` is set if
+    // the bundle rejected ops, which are removed from the pool.
+    NoOperationsAfterSimulation { removed_ops: bool },
     // Underpriced
     Underpriced,
     // Replacement Underpriced
@@ -155,6 +242,8 @@ where
     /// then waiting for one bundle to be mined or dropped before forming the
     /// next one.
     async fn send_bundles_in_loop<TS: TaskSpawner>(mut self, task_spawner: TS) {
+        self.warm_up().await;
+
         // trigger for sending bundles
         let sender_trigger = BundleSenderTrigger::new(
             &task_spawner,
@@ -197,6 +286,8 @@ where
         chain_spec: ChainSpec,
         sender_eoa: Address,
         submission_proxy: Option<Arc<dyn SubmissionProxy>>,
+        transaction_transformer: Arc<dyn TransactionTransformer>,
+        audit_sink: Arc<dyn BundleAuditSink>,
         proposer: P,
         ep_providers: EP,
         transaction_tracker: T,
@@ -220,15 +311,40 @@ where
             chain_spec,
             sender_eoa,
             submission_proxy,
+            transaction_transformer,
+            audit_sink,
             proposer,
             transaction_tracker: Some(transaction_tracker),
             assigner,
             pool,
+            fee_controller: settings
+                .priority_fee_controller
+                .map(PriorityFeeController::new),
             settings,
             event_sender,
             ep_address: *ep_providers.entry_point().address(),
             ep_providers,
+            last_bundle_ops: None,
+            sent_bundle_ops: HashMap::new(),
+            sent_op_metadata: HashMap::new(),
+            sent_op_ids: HashMap::new(),
+            last_idle_event_block: None,
+            last_send_block: None,
+            first_send_block: None,
+            onchain_revert_counts: LruMap::new(ONCHAIN_REVERT_COUNTS_CACHE_SIZE),
+            clock: Arc::new(TokioClock),
+        }
+    }
+
+    async fn warm_up(&self) {
+        if self.settings.warm_up.is_zero() {
+            return;
         }
+        info!(
+            "Waiting {:?} before proposing the first bundle",
+            self.settings.warm_up
+        );
+        self.clock.sleep(self.settings.warm_up).await;
     }
 
     #[instrument(skip_all, fields(entry_point = self.ep_address.to_string(), tag = self.builder_tag))]
@@ -242,6 +358,10 @@ where
             self.assigner.release_all(self.sender_eoa);
         }
 
+        for op_hash in state.trigger.take_cancel_requests() {
+            self.handle_cancel_request(state, op_hash, tracker_update.is_some());
+        }
+
         match state.inner {
             InnerState::Building(building_state) => {
                 self.handle_building_state(state, building_state).await?;
@@ -263,6 +383,39 @@ where
         Ok(())
     }
 
+    // Moves a pending bundle that contains `op_hash` to the cancelling state. A no-op if
+    // there is no pending bundle, it doesn't contain the op, or it was just resolved.
+    fn handle_cancel_request<TRIG: Trigger>(
+        &mut self,
+        state: &mut SenderMachineState<T, TRIG>,
+        op_hash: B256,
+        has_tracker_update: bool,
+    ) {
+        let cancelling = match state.inner {
+            InnerState::Pending(pending_state) if !has_tracker_update => {
+                let contains_op = self
+                    .last_bundle_ops
+                    .as_ref()
+                    .is_some_and(|ops| ops.iter().any(|(_, hash)| *hash == op_hash));
+                if contains_op {
+                    info!("Cancelling pending bundle containing op {op_hash:?}");
+                    state.update(InnerState::Cancelling(pending_state.to_cancelling()));
+                }
+                contains_op
+            }
+            _ => false,
+        };
+        if !cancelling {
+            info!("No pending bundle contains op {op_hash:?}, ignoring cancellation request");
+        }
+
+        self.emit(BuilderEvent::cancel_containing_op(
+            self.builder_tag.clone(),
+            op_hash,
+            cancelling,
+        ));
+    }
+
     async fn handle_building_state<TRIG: Trigger>(
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
@@ -278,8 +431,12 @@ where
             Ok(SendBundleAttemptResult::Success(_)) => {
                 // sent the bundle
                 info!("Bundle sent successfully");
+                self.last_idle_event_block = None;
+                self.last_send_block = Some(block_number);
+                self.first_send_block.get_or_insert(block_number);
                 state.update(InnerState::Pending(inner.to_pending(
                     block_number + self.settings.max_blocks_to_wait_for_mine,
+                    self.settings.resim_after_blocks.map(|b| block_number + b),
                 )));
             }
             Ok(SendBundleAttemptResult::NoOperationsInitially) => {
@@ -287,13 +444,17 @@ where
                 if inner.fee_increase_count > 0 {
                     state.transaction_tracker.abandon();
                 }
+                self.idle_round(block_number);
+                self.heartbeat(state).await;
                 state.no_operations();
             }
-            Ok(SendBundleAttemptResult::NoOperationsAfterSimulation) => {
+            Ok(SendBundleAttemptResult::NoOperationsAfterSimulation { .. }) => {
                 debug!("No operations available after simulation");
                 if inner.fee_increase_count > 0 {
                     state.transaction_tracker.abandon();
                 }
+                self.idle_round(block_number);
+                self.heartbeat(state).await;
                 state.no_operations();
             }
             Ok(SendBundleAttemptResult::NoOperationsAfterFeeFilter) => {
@@ -327,6 +488,8 @@ where
                     state.initial();
                 } else {
                     debug!("No operations available, waiting for next trigger");
+                    self.idle_round(block_number);
+                    self.heartbeat(state).await;
                     state.no_operations();
                 }
             }
@@ -394,25 +557,22 @@ where
                     tx_hash,
                     nonce,
                     is_success,
+                    op_results,
                     ..
                 } => {
                     info!("Bundle transaction mined: block number {block_number}, attempt number {attempt_number}, gas limit {gas_limit:?}, gas used {gas_used:?}, tx hash {tx_hash}, nonce {nonce}, success {is_success}");
 
                     self.metrics
                         .process_bundle_txn_mined(gas_limit, gas_used, is_success);
-
-                    if !is_success {
-                        if let Err(e) = self.process_revert(tx_hash).await {
-                            warn!("Failed to process revert for bundle transaction {tx_hash:?}: {e:#?}");
-                        }
-                    }
-
-                    self.emit(BuilderEvent::transaction_mined(
-                        self.builder_tag.clone(),
+                    self.record_inclusion(block_number);
+                    self.process_mined_bundle(
                         tx_hash,
                         nonce,
                         block_number,
-                    ));
+                        is_success,
+                        &op_results,
+                    )
+                    .await;
                     state.bundle_mined(block_number, attempt_number, tx_hash);
                 }
                 TrackerUpdate::LatestTxDropped { nonce } => {
@@ -432,25 +592,82 @@ where
                         nonce,
                     ));
                     self.metrics.bundle_txns_nonce_used.increment(1);
+                    self.sent_bundle_ops.clear();
+                    self.sent_op_metadata.clear();
+                    self.sent_op_ids.clear();
                     state.reset();
                 }
             }
-        } else if state.block_number() >= inner.until {
-            // start replacement, don't wait for trigger. Continue
-            // to attempt until there are no longer any UOs priced high enough
-            // to bundle.
-            info!(
-                "Not mined after {} blocks, increasing fees, attempt: {}",
-                self.settings.max_blocks_to_wait_for_mine,
-                inner.fee_increase_count + 1
-            );
-            self.metrics.bundle_txn_fee_increases.increment(1);
-            state.update(InnerState::Building(inner.to_building()))
+        } else {
+            let block_number = state.block_number();
+            if block_number >= inner.until {
+                // start replacement, don't wait for trigger. Continue
+                // to attempt until there are no longer any UOs priced high enough
+                // to bundle.
+                info!(
+                    "Not mined after {} blocks, increasing fees, attempt: {}",
+                    self.settings.max_blocks_to_wait_for_mine,
+                    inner.fee_increase_count + 1
+                );
+                self.metrics.bundle_txn_fee_increases.increment(1);
+                state.update(InnerState::Building(inner.to_building()))
+            } else if inner.resim_at.is_some_and(|b| block_number >= b) {
+                match self
+                    .pending_bundle_has_invalid_ops(state.block_hash())
+                    .await
+                {
+                    Ok(true) => {
+                        // rebuild now, the rebuilt bundle drops the invalid ops
+                        info!(
+                            "Pending bundle has invalid ops at block {block_number}, rebuilding, attempt: {}",
+                            inner.fee_increase_count + 1
+                        );
+                        self.metrics.bundle_txn_resim_rebuilds.increment(1);
+                        state.update(InnerState::Building(inner.to_building()))
+                    }
+                    Ok(false) => {
+                        debug!("Pending bundle ops still valid at block {block_number}");
+                        state.update(InnerState::Pending(
+                            inner.resimulated(self.settings.resim_after_blocks, block_number),
+                        ))
+                    }
+                    Err(e) => {
+                        warn!("Failed to re-simulate pending bundle ops: {e:?}");
+                        state.update(InnerState::Pending(
+                            inner.resimulated(self.settings.resim_after_blocks, block_number),
+                        ))
+                    }
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Re-simulates the ops of the pending bundle, returning true if any of them
+    /// are no longer valid or have been removed from the pool
+    async fn pending_bundle_has_invalid_ops(&self, block_hash: B256) -> anyhow::Result<bool> {
+        let Some(bundle_ops) = &self.last_bundle_ops else {
+            return Ok(false);
+        };
+        let hashes = bundle_ops.iter().map(|(_, hash)| *hash).collect::<Vec<_>>();
+        let ops = self
+            .pool
+            .get_ops_by_hashes(self.ep_address, hashes.clone())
+            .await
+            .context("should get pending bundle ops from pool")?;
+        if ops.len() < hashes.len() {
+            return Ok(true);
+        }
+
+        let invalid = self
+            .proposer
+            .find_invalid_ops(ops, block_hash)
+            .await
+            .context("should re-simulate pending bundle ops")?;
+        Ok(!invalid.is_empty())
+    }
+
     async fn handle_cancelling_state<TRIG: Trigger>(
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
@@ -496,6 +713,10 @@ where
                     // abandon the cancellation
                     warn!("Abandoning cancellation after max fee increases {}, starting new bundle attempt", inner.fee_increase_count);
                     self.metrics.cancellations_abandoned.increment(1);
+                    self.emit(BuilderEvent::cancellation_resolved(
+                        self.builder_tag.clone(),
+                        CancellationOutcome::Abandoned,
+                    ));
                     state.reset();
                 } else {
                     // Increase fees again
@@ -541,10 +762,37 @@ where
     ) -> anyhow::Result<()> {
         // check for transaction update
         if let Some(update) = tracker_update {
-            match update {
+            let outcome = match update {
+                // the tracker reports whichever of our transactions used the nonce, which
+                // may be the bundle being cancelled
+                TrackerUpdate::Mined {
+                    tx_hash,
+                    nonce,
+                    block_number,
+                    gas_limit,
+                    gas_used,
+                    is_success,
+                    op_results,
+                    ..
+                } if self.sent_bundle_ops.contains_key(&tx_hash) => {
+                    info!("Bundle transaction {tx_hash:?} mined before its cancellation, success {is_success}");
+                    self.metrics
+                        .process_bundle_txn_mined(gas_limit, gas_used, is_success);
+                    self.process_mined_bundle(
+                        tx_hash,
+                        nonce,
+                        block_number,
+                        is_success,
+                        &op_results,
+                    )
+                    .await;
+                    CancellationOutcome::BundleMined { tx_hash }
+                }
                 TrackerUpdate::Mined {
+                    tx_hash,
                     gas_used,
                     gas_price,
+                    is_success,
                     ..
                 } => {
                     let fee = gas_used
@@ -557,26 +805,54 @@ where
                             .cancellation_txns_total_fee
                             .increment(fee as u64);
                     };
+                    if is_success {
+                        CancellationOutcome::Cancelled { tx_hash }
+                    } else {
+                        // the nonce is used all the same, so the bundle can't mine
+                        warn!("Cancellation transaction {tx_hash:?} reverted");
+                        self.metrics.cancellation_txns_reverted.increment(1);
+                        CancellationOutcome::CancelReverted { tx_hash }
+                    }
                 }
                 TrackerUpdate::LatestTxDropped { .. } => {
                     // If a cancellation gets dropped, move to bundling state as there is no
                     // longer a pending transaction
                     info!("Cancellation transaction dropped, starting new bundle attempt");
+                    CancellationOutcome::Dropped
                 }
                 TrackerUpdate::NonceUsedForOtherTx { .. } => {
                     // If a nonce is used externally, move to bundling state as there is no longer
                     // a pending transaction
                     info!("Nonce used externally while cancelling, starting new bundle attempt");
+                    CancellationOutcome::NonceUsedForOtherTransaction
                 }
-            }
+            };
+            self.emit(BuilderEvent::cancellation_resolved(
+                self.builder_tag.clone(),
+                outcome,
+            ));
+            self.sent_bundle_ops.clear();
+            self.sent_op_metadata.clear();
+            self.sent_op_ids.clear();
             state.reset();
         } else if state.block_number() >= inner.until {
             if inner.fee_increase_count >= self.settings.max_cancellation_fee_increases {
+                if self.settings.recheck_cancellation_nonce
+                    && self.cancelled_nonce_used(state).await
+                {
+                    // one of the transactions mined, wait for the tracker to report which
+                    info!("Nonce of the cancelled bundle was used on-chain, waiting for the mined transaction before abandoning the cancellation");
+                    return Ok(());
+                }
                 // abandon the cancellation
                 // release all operations after the cancellation abandonment
                 self.assigner.release_all(self.sender_eoa);
                 warn!("Abandoning cancellation after max fee increases {}, starting new bundle attempt", inner.fee_increase_count);
                 self.metrics.cancellations_abandoned.increment(1);
+                self.emit(BuilderEvent::cancellation_resolved(
+                    self.builder_tag.clone(),
+                    CancellationOutcome::Abandoned,
+                ));
                 state.reset();
             } else {
                 // start replacement, don't wait for trigger
@@ -596,6 +872,32 @@ where
         &mut self,
         state: &mut SenderMachineState<T, TRIG>,
         fee_increase_count: u64,
+    ) -> anyhow::Result<SendBundleAttemptResult> {
+        let mut repolls = 0;
+        loop {
+            let result = self.send_bundle_once(state, fee_increase_count).await;
+            // Only repoll if ops were removed, otherwise the pool would return the same ops.
+            // A genuinely empty pool ends the loop with `NoOperationsInitially`.
+            if matches!(
+                result,
+                Ok(SendBundleAttemptResult::NoOperationsAfterSimulation { removed_ops: true })
+            ) && repolls < self.settings.repoll_on_empty
+            {
+                repolls += 1;
+                self.metrics.bundle_repolls.increment(1);
+                debug!(
+                    "Bundle emptied out after removing ops, repolling the pool. Repoll: {repolls}"
+                );
+                continue;
+            }
+            return result;
+        }
+    }
+
+    async fn send_bundle_once<TRIG: Trigger>(
+        &mut self,
+        state: &mut SenderMachineState<T, TRIG>,
+        fee_increase_count: u64,
     ) -> anyhow::Result<SendBundleAttemptResult> {
         let ops = self
             .assigner
@@ -621,7 +923,7 @@ where
             Ok(SendBundleAttemptResult::NonceTooLow) => {
                 self.assigner.release_all(self.sender_eoa);
             }
-            Ok(SendBundleAttemptResult::NoOperationsAfterSimulation) => {
+            Ok(SendBundleAttemptResult::NoOperationsAfterSimulation { .. }) => {
                 // all UOs for this sender are invalid, so we can release all from the assigner
                 self.assigner.release_all(self.sender_eoa);
             }
@@ -658,6 +960,24 @@ where
             balance,
         } = state.transaction_tracker.get_state()?;
 
+        let is_replacement = fee_increase_count > 0;
+        let ops = match &self.last_bundle_ops {
+            Some(previous) if is_replacement && self.settings.sticky_replacement => {
+                prioritize_previous_ops(ops, previous)
+            }
+            _ => ops,
+        };
+        let ops = if self.settings.skip_pending_nonce_duplicates {
+            self.skip_pending_nonce_duplicates(ops)
+        } else {
+            ops
+        };
+        let op_metadata = collect_op_metadata(&ops);
+        let op_ids = ops
+            .iter()
+            .map(|op| (op.uo.hash(), op.uo.id()))
+            .collect::<HashMap<_, _>>();
+
         let bundle = match self
             .proposer
             .make_bundle(
@@ -665,7 +985,7 @@ where
                 state.block_hash(),
                 balance,
                 required_fees,
-                fee_increase_count > 0,
+                is_replacement,
             )
             .await
         {
@@ -676,6 +996,17 @@ where
             Err(e) => bail!("Failed to make bundle: {e:?}"),
         };
 
+        let audit_ops = bundle
+            .ops_per_aggregator
+            .iter()
+            .cloned()
+            .map(|ops| ops.into_uo_variants())
+            .collect::<Vec<_>>();
+        let (beneficiary, gas_limit, gas_fees) =
+            (bundle.beneficiary, bundle.gas_estimate, bundle.gas_fees);
+        let removed_ops = !bundle.rejected_ops.is_empty();
+        let bundle_id = bundle.id(self.settings.builder_index);
+
         let Some(bundle_tx) = self.get_bundle_tx(nonce, bundle).await? else {
             self.emit(BuilderEvent::formed_bundle(
                 self.builder_tag.clone(),
@@ -684,7 +1015,7 @@ where
                 fee_increase_count,
                 required_fees,
             ));
-            return Ok(SendBundleAttemptResult::NoOperationsAfterSimulation);
+            return Ok(SendBundleAttemptResult::NoOperationsAfterSimulation { removed_ops });
         };
 
         let BundleTx {
@@ -710,9 +1041,33 @@ where
         match send_result {
             Ok(tx_hash) => {
                 let ops = Arc::new(ops);
+                self.last_bundle_ops = Some(ops.clone());
+                self.sent_bundle_ops.insert(tx_hash, ops.clone());
+                for (_, op_hash) in ops.iter() {
+                    if let Some(metadata) = op_metadata.get(op_hash) {
+                        self.sent_op_metadata.insert(*op_hash, metadata.clone());
+                    }
+                    if let Some(id) = op_ids.get(op_hash) {
+                        self.sent_op_ids.insert(*id, *op_hash);
+                    }
+                }
+                self.record_bundle(BundleAuditRecord {
+                    builder_tag: self.builder_tag.clone(),
+                    entry_point: self.ep_address,
+                    tx_hash,
+                    nonce,
+                    block_number: state.block_number(),
+                    block_hash: state.block_hash(),
+                    ops_per_aggregator: audit_ops,
+                    beneficiary,
+                    gas_limit,
+                    gas_fees,
+                })
+                .await;
                 self.emit(BuilderEvent::formed_bundle(
                     self.builder_tag.clone(),
                     Some(BundleTxDetails {
+                        bundle_id,
                         tx_hash,
                         tx,
                         ops: ops.clone(),
@@ -761,6 +1116,30 @@ where
         }
     }
 
+    /// Drops ops that share a sender and nonce with an op in a bundle transaction sent
+    /// for the current nonce. Only one of them can be included, so the other reverts.
+    fn skip_pending_nonce_duplicates(&self, ops: Vec<PoolOperation>) -> Vec<PoolOperation> {
+        ops.into_iter()
+            .filter(|op| {
+                let op_hash = op.uo.hash();
+                match self.sent_op_ids.get(&op.uo.id()) {
+                    Some(pending_op_hash) if *pending_op_hash != op_hash => {
+                        debug!("Skipping op {op_hash:?}, its nonce is used by pending op {pending_op_hash:?}");
+                        self.emit(BuilderEvent::skipped_op(
+                            self.builder_tag.clone(),
+                            op_hash,
+                            SkipReason::PendingNonceDuplicate {
+                                pending_op_hash: *pending_op_hash,
+                            },
+                        ));
+                        false
+                    }
+                    _ => true,
+                }
+            })
+            .collect()
+    }
+
     /// Builds a bundle and returns some metadata and the transaction to send
     /// it, or `None` if there are no valid operations available.
     async fn get_bundle_tx(
@@ -812,16 +1191,27 @@ where
             .iter_ops()
             .map(|op| (op.sender(), op.hash()))
             .collect();
+        let tip = self
+            .settings
+            .tip_transfer
+            .map(|tip_transfer| tip_transfer.tip(tip::expected_reward(&bundle, &self.chain_spec)));
 
         let mut tx = self.ep_providers.entry_point().get_send_bundle_transaction(
             bundle.ops_per_aggregator,
-            self.sender_eoa,
+            bundle.beneficiary,
             bundle.gas_estimate,
             bundle.gas_fees,
             self.submission_proxy.as_ref().map(|p| p.address()),
         );
 
-        tx = tx.nonce(nonce);
+        if let Some((tip_transfer, tip)) = self.settings.tip_transfer.zip(tip) {
+            debug!(
+                "Wrapping bundle with a tip transfer of {tip} to {}",
+                tip_transfer.recipient
+            );
+            tx = tip_transfer.wrap(tx, self.chain_spec.multicall3_address, tip);
+        }
+        tx = self.transaction_transformer.transform(tx).nonce(nonce);
         Ok(Some(BundleTx {
             tx,
             expected_storage: bundle.expected_storage,
@@ -853,10 +1243,16 @@ where
     async fn process_revert(&self, tx_hash: B256) -> anyhow::Result<()> {
         warn!("Bundle transaction {tx_hash:?} reverted onchain");
 
+        // a bundle wrapped with a tip transfer calls the entry point from the multicall, so
+        // the nested calls are needed to find it
+        let mut call_config = GethDebugTracerCallConfig::default();
+        if self.settings.tip_transfer.is_none() {
+            call_config = call_config.only_top_call();
+        }
         let trace_options = GethDebugTracingOptions::new_tracer(
             GethDebugTracerType::BuiltInTracer(GethDebugBuiltInTracerType::CallTracer),
         )
-        .with_call_config(GethDebugTracerCallConfig::default().only_top_call());
+        .with_call_config(call_config);
 
         let trace = self
             .ep_providers
@@ -865,9 +1261,13 @@ where
             .await
             .context("should have fetched trace from provider")?;
 
-        let frame = trace
+        let mut frame = trace
             .try_into_call_frame()
             .context("trace is not a call tracer")?;
+        if self.settings.tip_transfer.is_some() {
+            frame = tip::find_call_to(frame, self.ep_address)
+                .context("trace has no call to the entry point")?;
+        }
 
         let ops = EP::EntryPoint::decode_ops_from_calldata(&self.chain_spec, &frame.input);
 
@@ -918,6 +1318,18 @@ where
                 tracing::warn!(
                     "removing all ops from pool for reverted bundle for aggregator {aggregator:?}",
                 );
+                self.emit(BuilderEvent::aggregator_signature_failed(
+                    self.builder_tag.clone(),
+                    aggregator,
+                ));
+                let update = EntityUpdate {
+                    entity: Entity::aggregator(aggregator),
+                    update_type: EntityUpdateType::StakedInvalidation,
+                    value: None,
+                };
+                if let Err(error) = self.update_entities_in_pool(&[update]).await {
+                    error!("Failed to update aggregator {aggregator:?} in pool: {error}");
+                }
                 ops.iter()
                     .find(|op| op.aggregator == aggregator)
                     .map(|ops| ops.user_ops.iter().map(|op| op.hash()).collect())
@@ -934,12 +1346,255 @@ where
         self.remove_ops_from_pool_by_hash(to_remove).await
     }
 
+    // Reports the outcome of each op of a mined bundle, flagging ops that the entry
+    // point dropped without emitting a `UserOperationEvent`. Returns the hashes of ops
+    // that have now reverted on-chain too many times and should be removed from the pool.
+    //
+    // A reverted transaction has no events for any of its ops, so its dropped ops are not
+    // counted here. `process_revert` attributes the revert to the offending ops instead.
+    fn process_mined_ops(
+        &mut self,
+        tx_hash: B256,
+        is_success: bool,
+        op_results: &[MinedOpResult],
+    ) -> Vec<B256> {
+        let Some(ops) = self.sent_bundle_ops.get(&tx_hash).cloned() else {
+            warn!("Mined bundle transaction {tx_hash:?} not found in sent bundles, cannot report op outcomes");
+            return vec![];
+        };
+
+        let bundle_id = self.bundle_id_of(&ops);
+        let mut exhausted_ops = vec![];
+        for (op_hash, outcome) in match_op_outcomes(&ops, op_results) {
+            match outcome {
+                MinedOpOutcome::Succeeded { .. } => {
+                    self.metrics.bundle_ops_succeeded.increment(1);
+                    self.onchain_revert_counts.remove(&op_hash);
+                }
+                MinedOpOutcome::Reverted { .. } => {
+                    self.metrics.bundle_ops_reverted.increment(1);
+                    self.onchain_revert_counts.remove(&op_hash);
+                }
+                MinedOpOutcome::Dropped => {
+                    warn!("Op {op_hash:?} was in mined bundle transaction {tx_hash:?} but has no UserOperationEvent, it was silently dropped");
+                    self.metrics.bundle_ops_dropped.increment(1);
+                    if is_success && self.record_onchain_revert(op_hash) {
+                        exhausted_ops.push(op_hash);
+                    }
+                }
+            }
+            let metadata = self
+                .sent_op_metadata
+                .get(&op_hash)
+                .cloned()
+                .unwrap_or_default();
+            self.emit(BuilderEvent::mined_op(
+                self.builder_tag.clone(),
+                bundle_id,
+                tx_hash,
+                op_hash,
+                outcome,
+                metadata.client_tag,
+                metadata.submission_source,
+            ));
+        }
+        exhausted_ops
+    }
+
+    // Checks the on-chain nonce of the sender against the nonce of the pending
+    // transactions, returning true if one of them has mined
+    async fn cancelled_nonce_used<TRIG: Trigger>(
+        &self,
+        state: &SenderMachineState<T, TRIG>,
+    ) -> bool {
+        let result = async {
+            let nonce = state.transaction_tracker.get_state()?.nonce;
+            let onchain_nonce = self
+                .ep_providers
+                .evm()
+                .get_transaction_count(self.sender_eoa)
+                .await?;
+            anyhow::Ok(onchain_nonce > nonce)
+        }
+        .await;
+        result.unwrap_or_else(|e| {
+            warn!("Failed to check the on-chain nonce of a stuck cancellation: {e:#?}");
+            false
+        })
+    }
+
+    // Reports a mined bundle transaction and the outcomes of its ops, processing its revert
+    // if it failed on-chain
+    async fn process_mined_bundle(
+        &mut self,
+        tx_hash: B256,
+        nonce: u64,
+        block_number: u64,
+        is_success: bool,
+        op_results: &[MinedOpResult],
+    ) {
+        let bundle_id = self.sent_bundle_id(tx_hash);
+        let exhausted_ops = self.process_mined_ops(tx_hash, is_success, op_results);
+        self.sent_bundle_ops.clear();
+        self.sent_op_metadata.clear();
+        self.sent_op_ids.clear();
+
+        if !is_success {
+            if let Err(e) = self.process_revert(tx_hash).await {
+                warn!("Failed to process revert for bundle transaction {tx_hash:?}: {e:#?}");
+            }
+        }
+        if !exhausted_ops.is_empty() {
+            if let Err(e) = self.remove_ops_from_pool_by_hash(exhausted_ops).await {
+                warn!("Failed to remove repeatedly reverting ops from pool: {e:#?}");
+            }
+        }
+
+        self.emit(BuilderEvent::transaction_mined(
+            self.builder_tag.clone(),
+            bundle_id,
+            tx_hash,
+            nonce,
+            block_number,
+        ));
+    }
+
+    // Id of a sent bundle, `None` if it was sent before a restart
+    fn sent_bundle_id(&self, tx_hash: B256) -> Option<B256> {
+        self.sent_bundle_ops
+            .get(&tx_hash)
+            .map(|ops| self.bundle_id_of(ops))
+    }
+
+    // Matches `Bundle::id` for the bundle the ops were sent in
+    fn bundle_id_of(&self, ops: &[(Address, B256)]) -> B256 {
+        bundle_proposer::bundle_id(
+            ops.iter().map(|(_, op_hash)| *op_hash),
+            self.settings.builder_index,
+        )
+    }
+
+    // Counts an on-chain revert of an op, returning true if it has reached the maximum
+    // number of attempts and should be removed from the pool
+    fn record_onchain_revert(&mut self, op_hash: B256) -> bool {
+        let Some(max_attempts) = self.settings.max_onchain_revert_attempts else {
+            return false;
+        };
+        let Some(attempts) = self.onchain_revert_counts.get_or_insert(op_hash, || 0) else {
+            return false;
+        };
+        *attempts += 1;
+        let attempts = *attempts;
+        if attempts < max_attempts {
+            return false;
+        }
+
+        warn!("Op {op_hash:?} reverted on-chain in {attempts} mined bundles, removing from pool");
+        self.onchain_revert_counts.remove(&op_hash);
+        self.metrics.bundle_ops_removed_after_reverts.increment(1);
+        self.emit(BuilderEvent::rejected_op(
+            self.builder_tag.clone(),
+            op_hash,
+            OpRejectionReason::MaxOnchainRevertsExceeded { attempts },
+        ));
+        true
+    }
+
+    async fn record_bundle(&self, record: BundleAuditRecord) {
+        let tx_hash = record.tx_hash;
+        if let Err(e) = self.audit_sink.record(record).await {
+            error!("Failed to record bundle transaction {tx_hash:?} to audit sink: {e:?}");
+        }
+    }
+
     fn emit(&self, event: BuilderEvent) {
         let _ = self.event_sender.send(WithEntryPoint {
             entry_point: self.ep_address,
             event,
         });
     }
+
+    // Counts a round with nothing to bundle, emitting an idle event at most once every
+    // `IDLE_EVENT_INTERVAL_BLOCKS` blocks
+    fn idle_round(&mut self, block_number: u64) {
+        self.metrics.bundle_idle_rounds.increment(1);
+        // any bundle sent for the nonce has been abandoned
+        self.first_send_block = None;
+        if self
+            .last_idle_event_block
+            .is_some_and(|last| block_number < last + IDLE_EVENT_INTERVAL_BLOCKS)
+        {
+            return;
+        }
+        self.last_idle_event_block = Some(block_number);
+        self.emit(BuilderEvent::idle(self.builder_tag.clone(), block_number));
+    }
+
+    // Feeds the inclusion latency of a mined bundle to the priority fee controller, if
+    // enabled, and hands the proposer the adjusted priority fee mode
+    fn record_inclusion(&mut self, mined_block: u64) {
+        let Some(sent_block) = self.first_send_block.take() else {
+            return;
+        };
+        let Some(controller) = &mut self.fee_controller else {
+            return;
+        };
+        let latency = mined_block.saturating_sub(sent_block);
+        if let Some(mode) = controller.record_inclusion(latency) {
+            info!("Bundle inclusion latency {latency} blocks, adjusting priority fee mode to {mode:?}");
+            if let PriorityFeeMode::PriorityFeeIncreasePercent(percent) = mode {
+                self.metrics
+                    .priority_fee_increase_percent
+                    .set(percent as f64);
+            }
+            self.proposer.set_priority_fee_mode(mode);
+        }
+    }
+
+    // Sends a zero-value self-transfer if heartbeats are enabled and nothing has been sent
+    // for the heartbeat interval. The transaction is tracked like a bundle, so a bundle
+    // sent while it is pending replaces it.
+    async fn heartbeat<TRIG: Trigger>(&mut self, state: &mut SenderMachineState<T, TRIG>) {
+        let Some(interval) = self.settings.heartbeat_interval_blocks else {
+            return;
+        };
+        let block_number = state.block_number();
+        let last_send_block = *self.last_send_block.get_or_insert(block_number);
+        if block_number < last_send_block + interval
+            || state.transaction_tracker.num_pending_transactions() > 0
+        {
+            return;
+        }
+
+        let tracker_state = match state.transaction_tracker.get_state() {
+            Ok(tracker_state) => tracker_state,
+            Err(e) => {
+                warn!("Failed to get transaction tracker state for heartbeat: {e:?}");
+                return;
+            }
+        };
+        let (estimated_fees, _) = self
+            .proposer
+            .estimate_gas_fees(state.block_hash(), None)
+            .await
+            .unwrap_or_default();
+        // nothing is pending, so there is no transaction to replace and no required fees
+        let tx = create_hard_cancel_tx(self.sender_eoa, tracker_state.nonce, estimated_fees);
+        match state
+            .transaction_tracker
+            .send_transaction(tx, &ExpectedStorage::default(), block_number)
+            .await
+        {
+            Ok(tx_hash) => {
+                info!("Heartbeat transaction sent after {interval} idle blocks: {tx_hash:?}");
+                self.metrics.heartbeat_txns_sent.increment(1);
+                self.last_send_block = Some(block_number);
+            }
+            Err(e) => {
+                warn!("Failed to send heartbeat transaction: {e:?}");
+            }
+        }
+    }
 }
 
 struct SenderMachineState<T, TRIG> {
@@ -1049,24 +1704,24 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
 
                 self.send_bundle_response = self.trigger.wait_for_trigger().await?;
 
-                let Some(update) = self.find_address_update() else {
+                let Some((update, block_number)) = self.find_address_update() else {
                     return Ok(None);
                 };
 
                 self.transaction_tracker
-                    .process_update(&update)
+                    .process_update(&update, block_number)
                     .await
                     .map_err(|e| anyhow::anyhow!("transaction tracker update error {e:?}"))
             }
             InnerState::Pending(..) | InnerState::CancelPending(..) => {
                 self.trigger.wait_for_block().await?;
 
-                let Some(update) = self.find_address_update() else {
+                let Some((update, block_number)) = self.find_address_update() else {
                     return Ok(None);
                 };
 
                 self.transaction_tracker
-                    .process_update(&update)
+                    .process_update(&update, block_number)
                     .await
                     .map_err(|e| anyhow::anyhow!("transaction tracker update error {e:?}"))
             }
@@ -1090,13 +1745,14 @@ impl<T: TransactionTracker, TRIG: Trigger> SenderMachineState<T, TRIG> {
         }
     }
 
-    fn find_address_update(&self) -> Option<AddressUpdate> {
-        self.trigger
-            .last_block()
+    // Returns our address's update from the last block, along with the block's number
+    fn find_address_update(&self) -> Option<(AddressUpdate, u64)> {
+        let last_block = self.trigger.last_block();
+        last_block
             .address_updates
             .iter()
             .find(|u| u.address == self.transaction_tracker.address())
-            .cloned()
+            .map(|u| (u.clone(), last_block.block_number))
     }
 }
 
@@ -1137,9 +1793,10 @@ struct UnderpricedInfo {
 
 impl BuildingState {
     // Transition to pending state
-    fn to_pending(self, until: u64) -> PendingState {
+    fn to_pending(self, until: u64, resim_at: Option<u64>) -> PendingState {
         PendingState {
             until,
+            resim_at,
             fee_increase_count: self.fee_increase_count,
         }
     }
@@ -1198,10 +1855,18 @@ impl BuildingState {
 #[derive(Debug, Clone, Copy)]
 struct PendingState {
     until: u64,
+    // block at which to re-simulate the ops of the pending bundle
+    resim_at: Option<u64>,
     fee_increase_count: u64,
 }
 
 impl PendingState {
+    // Schedule the next re-simulation
+    fn resimulated(mut self, resim_after_blocks: Option<u64>, block_number: u64) -> Self {
+        self.resim_at = resim_after_blocks.map(|b| block_number + b);
+        self
+    }
+
     fn to_building(self) -> BuildingState {
         BuildingState {
             wait_for_trigger: false,
@@ -1209,6 +1874,12 @@ impl PendingState {
             underpriced_info: None,
         }
     }
+
+    fn to_cancelling(self) -> CancellingState {
+        CancellingState {
+            fee_increase_count: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -1244,6 +1915,22 @@ impl CancelPendingState {
     }
 }
 
+#[async_trait]
+#[cfg_attr(test, automock)]
+trait Clock: Send + Sync {
+    // Wait for the given duration to elapse
+    async fn sleep(&self, duration: Duration);
+}
+
+struct TokioClock;
+
+#[async_trait]
+impl Clock for TokioClock {
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
 #[async_trait]
 #[cfg_attr(test, automock)]
 trait Trigger {
@@ -1263,6 +1950,9 @@ trait Trigger {
 
     // Get the last block processed by the trigger
     fn last_block(&self) -> &NewHead;
+
+    // Take the hashes of ops whose pending bundle should be cancelled, received since the last call
+    fn take_cancel_requests(&mut self) -> Vec<B256>;
 }
 
 struct BundleSenderTrigger {
@@ -1271,6 +1961,9 @@ struct BundleSenderTrigger {
     bundle_action_receiver: mpsc::Receiver<BundleSenderAction>,
     timer: tokio::time::Interval,
     last_block: NewHead,
+    cancel_requests: Vec<B256>,
+    // send bundle requests received while waiting for a block, handled on the next trigger
+    deferred_send_requests: VecDeque<SendBundleRequest>,
 }
 
 #[async_trait]
@@ -1281,6 +1974,18 @@ impl Trigger for BundleSenderTrigger {
         let mut send_bundle_response: Option<oneshot::Sender<SendBundleResult>> = None;
         self.timer.reset();
 
+        while let Some(r) = self.deferred_send_requests.pop_front() {
+            match self.bundling_mode {
+                BundlingMode::Manual => {
+                    self.consume_blocks()?;
+                    return Ok(Some(r.responder));
+                }
+                BundlingMode::Auto => {
+                    error!("Received bundle send action while in auto mode, ignoring");
+                }
+            }
+        }
+
         loop {
             // 3 triggers for loop logic:
             // 1 - new block
@@ -1290,6 +1995,7 @@ impl Trigger for BundleSenderTrigger {
             // 3 - action recv
             //      - If change mode, change and restart loop
             //      - If send bundle and manual mode, send next bundle
+            //      - If cancel containing, record the request and restart loop
             tokio::select! {
                 b = self.block_rx.recv() => {
                     let Some(b) = b else {
@@ -1324,6 +2030,10 @@ impl Trigger for BundleSenderTrigger {
                                 }
                             }
                         },
+                        Some(BundleSenderAction::CancelContaining(op_hash)) => {
+                            self.cancel_requests.push(op_hash);
+                            continue;
+                        },
                         None => {
                             error!("Bundle action recv closed");
                             bail!("Bundle action recv closed");
@@ -1345,6 +2055,7 @@ impl Trigger for BundleSenderTrigger {
             .await
             .ok_or_else(|| anyhow::anyhow!("Block stream closed"))?;
         self.consume_blocks()?;
+        self.consume_actions()?;
         Ok(self.last_block.clone())
     }
 
@@ -1358,6 +2069,10 @@ impl Trigger for BundleSenderTrigger {
     fn last_block(&self) -> &NewHead {
         &self.last_block
     }
+
+    fn take_cancel_requests(&mut self) -> Vec<B256> {
+        std::mem::take(&mut self.cancel_requests)
+    }
 }
 
 impl BundleSenderTrigger {
@@ -1389,6 +2104,8 @@ impl BundleSenderTrigger {
                 block_number: 0,
                 address_updates: vec![],
             },
+            cancel_requests: vec![],
+            deferred_send_requests: VecDeque::new(),
         })
     }
 
@@ -1429,6 +2146,32 @@ impl BundleSenderTrigger {
             }
         }
     }
+
+    // Consume actions received while waiting for a block, so that cancellation requests
+    // are seen while a bundle is pending
+    fn consume_actions(&mut self) -> anyhow::Result<()> {
+        loop {
+            match self.bundle_action_receiver.try_recv() {
+                Ok(BundleSenderAction::ChangeMode(mode)) => {
+                    info!("changing bundling mode to {mode:?}");
+                    self.bundling_mode = mode;
+                }
+                Ok(BundleSenderAction::SendBundle(r)) => {
+                    self.deferred_send_requests.push_back(r);
+                }
+                Ok(BundleSenderAction::CancelContaining(op_hash)) => {
+                    self.cancel_requests.push(op_hash);
+                }
+                Err(mpsc::error::TryRecvError::Empty) => {
+                    return Ok(());
+                }
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    error!("Bundle action recv closed");
+                    bail!("Bundle action recv closed");
+                }
+            }
+        }
+    }
 }
 
 #[derive(Metrics)]
@@ -1454,6 +2197,14 @@ struct BuilderMetric {
     bundle_txns_nonce_used: Counter,
     #[metric(describe = "the count of bundle transactions fee increase events.")]
     bundle_txn_fee_increases: Counter,
+    #[metric(
+        describe = "the count of pending bundle transactions rebuilt after re-simulation found invalid ops."
+    )]
+    bundle_txn_resim_rebuilds: Counter,
+    #[metric(
+        describe = "the count of immediate pool re-polls after a bundle emptied out from removed ops."
+    )]
+    bundle_repolls: Counter,
     #[metric(describe = "the count of bundle transactions underpriced events.")]
     bundle_txn_underpriced: Counter,
     #[metric(describe = "the count of bundle transactions underpriced replacement events.")]
@@ -1478,19 +2229,98 @@ struct BuilderMetric {
     soft_cancellations: Counter,
     #[metric(describe = "the count of cancellation bundle transactions failed events.")]
     cancellation_txns_failed: Counter,
+    #[metric(describe = "the count of cancellation transactions that mined but reverted.")]
+    cancellation_txns_reverted: Counter,
     #[metric(describe = "the count of state machine errors.")]
     state_machine_errors: Counter,
     #[metric(describe = "the distribution of bundle transaction sizes in bytes.")]
     bundle_txn_size_bytes: Histogram,
+    #[metric(describe = "the count of ops that succeeded in mined bundle transactions.")]
+    bundle_ops_succeeded: Counter,
+    #[metric(describe = "the count of ops that reverted in mined bundle transactions.")]
+    bundle_ops_reverted: Counter,
+    #[metric(
+        describe = "the count of ops in mined bundle transactions without a UserOperationEvent."
+    )]
+    bundle_ops_dropped: Counter,
+    #[metric(
+        describe = "the count of ops removed from the pool after reverting on-chain too many times."
+    )]
+    bundle_ops_removed_after_reverts: Counter,
+    #[metric(describe = "the count of bundling rounds with no operations to bundle.")]
+    bundle_idle_rounds: Counter,
+    #[metric(describe = "the count of heartbeat transactions sent while idle.")]
+    heartbeat_txns_sent: Counter,
+    #[metric(describe = "the priority fee increase percent set by the priority fee controller.")]
+    priority_fee_increase_percent: Gauge,
 }
 
-impl BuilderMetric {
-    fn process_bundle_txn_mined(
-        &self,
-        gas_limit: Option<u64>,
-        gas_used: Option<u64>,
-        is_success: bool,
-    ) {
+/// Matches the ops of a bundle to the `UserOperationEvent`s of its mined transaction.
+/// Ops without an event were dropped by the entry point.
+fn match_op_outcomes(
+    ops: &[(Address, B256)],
+    op_results: &[MinedOpResult],
+) -> Vec<(B256, MinedOpOutcome)> {
+    let results = op_results
+        .iter()
+        .map(|result| (result.op_hash, result))
+        .collect::<HashMap<_, _>>();
+    ops.iter()
+        .map(|(_, op_hash)| {
+            let outcome = match results.get(op_hash) {
+                Some(result) if result.success => MinedOpOutcome::Succeeded {
+                    actual_gas_cost: result.actual_gas_cost,
+                },
+                Some(result) => MinedOpOutcome::Reverted {
+                    actual_gas_cost: result.actual_gas_cost,
+                },
+                None => MinedOpOutcome::Dropped,
+            };
+            (*op_hash, outcome)
+        })
+        .collect()
+}
+
+/// Collects the submission metadata of the given ops, keyed by op hash. Ops without any
+/// metadata are skipped.
+fn collect_op_metadata(ops: &[PoolOperation]) -> HashMap<B256, OpMetadata> {
+    ops.iter()
+        .filter(|op| op.perms.client_tag.is_some() || op.perms.submission_source.is_some())
+        .map(|op| {
+            (
+                op.uo.hash(),
+                OpMetadata {
+                    client_tag: op.perms.client_tag.clone(),
+                    submission_source: op.perms.submission_source.clone(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Moves the ops of the previous bundle to the front, in their previous order, so that
+/// a replacement bundle includes them before any new ops. New ops keep their pool order.
+fn prioritize_previous_ops(
+    mut ops: Vec<PoolOperation>,
+    previous: &[(Address, B256)],
+) -> Vec<PoolOperation> {
+    let positions = previous
+        .iter()
+        .enumerate()
+        .map(|(i, (_, hash))| (*hash, i))
+        .collect::<HashMap<_, _>>();
+    // stable sort, so ops not in the previous bundle stay in their original order
+    ops.sort_by_key(|op| positions.get(&op.uo.hash()).copied().unwrap_or(usize::MAX));
+    ops
+}
+
+impl BuilderMetric {
+    fn process_bundle_txn_mined(
+        &self,
+        gas_limit: Option<u64>,
+        gas_used: Option<u64>,
+        is_success: bool,
+    ) {
         if is_success {
             self.bundle_txns_success.increment(1);
         } else {
@@ -1509,7 +2339,9 @@ impl BuilderMetric {
 #[cfg(test)]
 mod tests {
     use alloy_primitives::{address, bytes, Bytes, U256};
+    use alloy_sol_types::SolCall;
     use mockall::Sequence;
+    use rundler_contracts::multicall3::Multicall3;
     use rundler_provider::{
         GethDebugTracerCallFrame, MockDAGasOracleSync, MockEntryPointV0_6, MockEvmProvider,
         MockFeeEstimator, ProvidersWithEntryPoint,
@@ -1517,21 +2349,29 @@ mod tests {
     use rundler_types::{
         chain::ChainSpec,
         pool::{AddressUpdate, MockPool, PoolOperationSummary},
-        v0_6::UserOperation,
+        v0_6::{UserOperation, UserOperationBuilder, UserOperationRequiredFields},
         EntityInfos, GasFees, UserOperation as _, UserOperationPermissions, UserOpsPerAggregator,
         ValidTimeRange,
     };
-    use tokio::sync::{broadcast, mpsc};
+    use tokio::sync::{broadcast, mpsc, Mutex};
 
     use super::*;
     use crate::{
+        audit::{InMemoryBundleAuditSink, NoopBundleAuditSink},
         bundle_proposer::{Bundle, MockBundleProposer},
         bundle_sender::{BundleSenderImpl, MockTrigger},
+        emit::BuilderEventKind,
+        tip::TipAmount,
         transaction_tracker::MockTransactionTracker,
+        transformer::NoopTransactionTransformer,
     };
 
     const ENTRY_POINT_ADDRESS_V0_6: Address = address!("5FF137D4b0FDCD49DcA30c7CF57E578a026d2789");
 
+    // Expectations on the static entry point methods are global, tests setting them must not
+    // run concurrently
+    static STATIC_EP_MOCK_LOCK: Mutex<()> = Mutex::const_new(());
+
     #[tokio::test]
     async fn test_empty_send() {
         let Mocks {
@@ -1601,6 +2441,224 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_idle_event_rate_limited() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mock_evm,
+            mock_pool,
+            ..
+        } = new_mocks();
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        let mut events = sender.event_sender.subscribe();
+
+        sender.idle_round(10);
+        sender.idle_round(11);
+        sender.idle_round(10 + IDLE_EVENT_INTERVAL_BLOCKS);
+        let idle_blocks = std::iter::from_fn(|| events.try_recv().ok())
+            .filter_map(|e| match e.event.kind {
+                BuilderEventKind::Idle { block_number } => Some(block_number),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(idle_blocks, vec![10, 10 + IDLE_EVENT_INTERVAL_BLOCKS]);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_after_idle_interval() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mock_trigger,
+            mock_evm,
+            mut mock_pool,
+        } = new_mocks();
+
+        mock_pool
+            .expect_get_ops_summaries()
+            .times(4)
+            .returning(|_, _, _| Ok(vec![]));
+        mock_proposer
+            .expect_estimate_gas_fees()
+            .returning(|_, _| Box::pin(async { Ok((GasFees::default(), 0)) }));
+        mock_tracker.expect_get_state().returning(|| {
+            Ok(TrackerState {
+                nonce: 5,
+                balance: U256::ZERO,
+                required_fees: None,
+            })
+        });
+        mock_tracker
+            .expect_num_pending_transactions()
+            .return_const(0_usize);
+        // only the round at the end of the idle interval sends a self-transfer
+        mock_tracker
+            .expect_send_transaction()
+            .once()
+            .withf(|tx, _, block_number| {
+                tx.to == Some(Address::default().into())
+                    && tx.nonce == Some(5)
+                    && tx.value.is_none()
+                    && *block_number == 12
+            })
+            .returning(|_, _, _| Box::pin(async { Ok(B256::ZERO) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.settings.heartbeat_interval_blocks = Some(2);
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+
+        for block_number in 10..14 {
+            let mut mock_trigger = new_mocks().mock_trigger;
+            add_trigger_no_update_last_block(&mut mock_trigger, &mut Sequence::new(), block_number);
+            state.trigger = mock_trigger;
+            sender.step_state(&mut state).await.unwrap();
+        }
+    }
+
+    fn expect_pool_ops(mock_pool: &mut MockPool, seq: &mut Sequence, has_ops: bool) {
+        mock_pool
+            .expect_get_ops_summaries()
+            .times(1)
+            .in_sequence(seq)
+            .returning(move |_, _, _| {
+                Ok(has_ops
+                    .then(|| PoolOperationSummary {
+                        hash: B256::ZERO,
+                        sender: Address::ZERO,
+                        entry_point: ENTRY_POINT_ADDRESS_V0_6,
+                    })
+                    .into_iter()
+                    .collect())
+            });
+        if has_ops {
+            mock_pool
+                .expect_get_ops_by_hashes()
+                .times(1)
+                .in_sequence(seq)
+                .returning(|_, _| Ok(vec![demo_pool_op()]));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repoll_on_empty() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mock_evm,
+            mut mock_pool,
+        } = new_mocks();
+
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut Sequence::new(), 0);
+        mock_tracker.expect_get_state().returning(|| {
+            Ok(TrackerState {
+                nonce: 0,
+                balance: U256::ZERO,
+                required_fees: None,
+            })
+        });
+        mock_tracker.expect_address().return_const(Address::ZERO);
+
+        // the first round rejects its only op, the repoll then finds the pool empty
+        let mut seq = Sequence::new();
+        expect_pool_ops(&mut mock_pool, &mut seq, true);
+        mock_pool.expect_remove_ops().returning(|_, _| Ok(()));
+        expect_pool_ops(&mut mock_pool, &mut seq, false);
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _, _, _| {
+                Box::pin(async {
+                    Ok(Bundle {
+                        rejected_ops: vec![UserOperation::default()],
+                        ..Default::default()
+                    })
+                })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.settings.repoll_on_empty = 3;
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+
+        sender.step_state(&mut state).await.unwrap();
+
+        // an empty pool waits for the next trigger instead of repolling again
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: true,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_repoll_on_empty_sends_new_ops() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mut mock_evm,
+            mut mock_pool,
+        } = new_mocks();
+
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut Sequence::new(), 0);
+        mock_tracker.expect_get_state().returning(|| {
+            Ok(TrackerState {
+                nonce: 0,
+                balance: U256::ZERO,
+                required_fees: None,
+            })
+        });
+        mock_tracker.expect_address().return_const(Address::ZERO);
+        mock_evm
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::MAX));
+
+        // the first round rejects its only op, the repoll bundles a newly arrived op
+        let mut pool_seq = Sequence::new();
+        expect_pool_ops(&mut mock_pool, &mut pool_seq, true);
+        mock_pool.expect_remove_ops().returning(|_, _| Ok(()));
+        expect_pool_ops(&mut mock_pool, &mut pool_seq, true);
+        let mut proposer_seq = Sequence::new();
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .in_sequence(&mut proposer_seq)
+            .returning(|_, _, _, _, _| {
+                Box::pin(async {
+                    Ok(Bundle {
+                        rejected_ops: vec![UserOperation::default()],
+                        ..Default::default()
+                    })
+                })
+            });
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .in_sequence(&mut proposer_seq)
+            .returning(|_, _, _, _, _| Box::pin(async { Ok(bundle()) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _, _| TransactionRequest::default());
+        mock_tracker
+            .expect_send_transaction()
+            .returning(|_, _, _| Box::pin(async { Ok(B256::ZERO) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.settings.repoll_on_empty = 1;
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+
+        sender.step_state(&mut state).await.unwrap();
+
+        assert!(matches!(state.inner, InnerState::Pending(_)));
+    }
+
     #[tokio::test]
     async fn test_send() {
         let Mocks {
@@ -1667,32 +2725,566 @@ mod tests {
 
         sender.step_state(&mut state).await.unwrap();
 
-        // end in the pending state
-        assert!(matches!(
-            state.inner,
-            InnerState::Pending(PendingState {
-                until: 3, // block 0 + wait 3 blocks
-                ..
+        // end in the pending state
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState {
+                until: 3, // block 0 + wait 3 blocks
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_records_bundle_to_audit_sink() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mut mock_evm,
+            mut mock_pool,
+        } = new_mocks();
+
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut Sequence::new(), 5);
+        mock_tracker.expect_get_state().returning(|| {
+            Ok(TrackerState {
+                nonce: 7,
+                balance: U256::ZERO,
+                required_fees: None,
+            })
+        });
+        mock_tracker.expect_address().return_const(Address::ZERO);
+        mock_evm
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::MAX));
+        mock_pool
+            .expect_get_ops_summaries()
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(vec![PoolOperationSummary {
+                    hash: B256::ZERO,
+                    sender: Address::ZERO,
+                    entry_point: ENTRY_POINT_ADDRESS_V0_6,
+                }])
+            });
+        mock_pool
+            .expect_get_ops_by_hashes()
+            .times(1)
+            .returning(|_, _| Ok(vec![demo_pool_op()]));
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _, _, _| Box::pin(async { Ok(bundle()) }));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _, _| TransactionRequest::default());
+        let tx_hash = B256::repeat_byte(1);
+        mock_tracker
+            .expect_send_transaction()
+            .returning(move |_, _, _| Box::pin(async move { Ok(tx_hash) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        let audit_sink = Arc::new(InMemoryBundleAuditSink::default());
+        sender.audit_sink = audit_sink.clone();
+
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+        sender.step_state(&mut state).await.unwrap();
+
+        let bundle = bundle();
+        assert_eq!(
+            audit_sink.records(),
+            vec![BundleAuditRecord {
+                builder_tag: sender.builder_tag.clone(),
+                entry_point: sender.ep_address,
+                tx_hash,
+                nonce: 7,
+                block_number: 5,
+                block_hash: B256::ZERO,
+                ops_per_aggregator: bundle
+                    .ops_per_aggregator
+                    .into_iter()
+                    .map(|ops| ops.into_uo_variants())
+                    .collect(),
+                beneficiary: bundle.beneficiary,
+                gas_limit: bundle.gas_estimate,
+                gas_fees: bundle.gas_fees,
+            }]
+        );
+    }
+
+    #[derive(Debug)]
+    struct TipTransformer {
+        multicall: Address,
+        tip: U256,
+    }
+
+    impl TransactionTransformer for TipTransformer {
+        fn transform(&self, tx: TransactionRequest) -> TransactionRequest {
+            let mut input = self.tip.to_be_bytes::<32>().to_vec();
+            input.extend_from_slice(tx.input.input().map_or(&[][..], |data| data.as_ref()));
+            tx.to(self.multicall)
+                .value(self.tip)
+                .input(Bytes::from(input).into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_with_identity_transformer() {
+        let bundle_tx = TransactionRequest::default()
+            .to(ENTRY_POINT_ADDRESS_V0_6)
+            .input(bytes!("deadbeef").into());
+        let expected = bundle_tx.clone().nonce(0);
+
+        check_sends_transformed_tx(
+            bundle_tx,
+            Arc::new(NoopTransactionTransformer),
+            None,
+            expected,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_send_with_tip_transformer() {
+        let multicall = address!("cA11bde05977b3631167028862bE2a173976CA11");
+        let tip = U256::from(1000);
+        let bundle_tx = TransactionRequest::default()
+            .to(ENTRY_POINT_ADDRESS_V0_6)
+            .input(bytes!("deadbeef").into());
+
+        let mut input = tip.to_be_bytes::<32>().to_vec();
+        input.extend_from_slice(&bytes!("deadbeef"));
+        let expected = TransactionRequest::default()
+            .to(multicall)
+            .value(tip)
+            .input(Bytes::from(input).into())
+            .nonce(0);
+
+        check_sends_transformed_tx(
+            bundle_tx,
+            Arc::new(TipTransformer { multicall, tip }),
+            None,
+            expected,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_send_with_tip_transfer() {
+        let recipient = Address::random();
+        let tip = U256::from(1000);
+        let bundle_tx = TransactionRequest::default()
+            .to(ENTRY_POINT_ADDRESS_V0_6)
+            .input(bytes!("deadbeef").into());
+
+        // handleOps is called through the multicall, followed by the tip transfer
+        let input = Multicall3::aggregate3ValueCall {
+            calls: vec![
+                Multicall3::Call3Value {
+                    target: ENTRY_POINT_ADDRESS_V0_6,
+                    allowFailure: false,
+                    value: U256::ZERO,
+                    callData: bytes!("deadbeef"),
+                },
+                Multicall3::Call3Value {
+                    target: recipient,
+                    allowFailure: false,
+                    value: tip,
+                    callData: Bytes::new(),
+                },
+            ],
+        }
+        .abi_encode();
+        let expected = TransactionRequest::default()
+            .to(ChainSpec::default().multicall3_address)
+            .value(tip)
+            .input(Bytes::from(input).into())
+            .nonce(0);
+
+        check_sends_transformed_tx(
+            bundle_tx,
+            Arc::new(NoopTransactionTransformer),
+            Some(TipTransfer {
+                recipient,
+                amount: TipAmount::Fixed(tip),
+            }),
+            expected,
+        )
+        .await;
+    }
+
+    async fn check_sends_transformed_tx(
+        bundle_tx: TransactionRequest,
+        transformer: Arc<dyn TransactionTransformer>,
+        tip_transfer: Option<TipTransfer>,
+        expected: TransactionRequest,
+    ) {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mut mock_evm,
+            mut mock_pool,
+        } = new_mocks();
+
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut Sequence::new(), 0);
+
+        mock_tracker.expect_get_state().returning(|| {
+            Ok(TrackerState {
+                nonce: 0,
+                balance: U256::ZERO,
+                required_fees: None,
+            })
+        });
+        mock_tracker.expect_address().return_const(Address::ZERO);
+
+        mock_evm
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::MAX));
+
+        mock_pool
+            .expect_get_ops_summaries()
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(vec![PoolOperationSummary {
+                    hash: B256::ZERO,
+                    sender: Address::ZERO,
+                    entry_point: ENTRY_POINT_ADDRESS_V0_6,
+                }])
+            });
+        mock_pool
+            .expect_get_ops_by_hashes()
+            .times(1)
+            .returning(|_, _| Ok(vec![demo_pool_op()]));
+
+        mock_proposer
+            .expect_make_bundle()
+            .times(1)
+            .returning(|_, _, _, _, _| Box::pin(async { Ok(bundle()) }));
+
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(move |_, _, _, _, _| bundle_tx.clone());
+
+        // the tracker is given the transformed transaction
+        mock_tracker
+            .expect_send_transaction()
+            .times(1)
+            .withf(move |tx, _, _| *tx == expected)
+            .returning(|_, _, _| Box::pin(async { Ok(B256::ZERO) }));
+
+        let mut sender = new_sender_with_transformer(
+            mock_proposer,
+            mock_entry_point,
+            mock_evm,
+            mock_pool,
+            transformer,
+        );
+        sender.settings.tip_transfer = tip_transfer;
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+
+        sender.step_state(&mut state).await.unwrap();
+
+        assert!(matches!(state.inner, InnerState::Pending(_)));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_mine_success() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mock_evm,
+            mock_pool,
+        } = new_mocks();
+
+        let mut seq = Sequence::new();
+        add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, 1);
+
+        let new_head = NewHead {
+            block_number: 2,
+            block_hash: B256::ZERO,
+            address_updates: vec![AddressUpdate {
+                address: Address::ZERO,
+                nonce: Some(0),
+                balance: U256::ZERO,
+                mined_tx_hashes: vec![B256::ZERO],
+            }],
+        };
+        let new_head_clone = new_head.clone();
+
+        mock_trigger
+            .expect_wait_for_block()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(move || {
+                Box::pin({
+                    let new_head = new_head_clone.clone();
+                    async move { Ok(new_head) }
+                })
+            });
+        mock_trigger
+            .expect_last_block()
+            .once()
+            .in_sequence(&mut seq)
+            .return_const(new_head);
+
+        mock_tracker.expect_address().return_const(Address::ZERO);
+
+        mock_tracker
+            .expect_process_update()
+            .once()
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 2,
+                        nonce: 0,
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: B256::ZERO,
+                        attempt_number: 0,
+                        is_success: true,
+                        op_results: vec![],
+                    }))
+                })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+
+        // start in pending state
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                resim_at: None,
+                fee_increase_count: 0,
+            }),
+            requires_reset: false,
+        };
+
+        // first step has no update
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState { until: 3, .. })
+        ));
+
+        // second step is mined and moves back to building
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false, // don't wait for trigger in auto mode
+                fee_increase_count: 0,
+                underpriced_info: None,
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_slow_inclusion_raises_priority_fee_mode() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mock_evm,
+            mock_pool,
+        } = new_mocks();
+
+        let new_head = NewHead {
+            block_number: 5,
+            block_hash: B256::ZERO,
+            address_updates: vec![AddressUpdate {
+                address: Address::ZERO,
+                nonce: Some(0),
+                balance: U256::ZERO,
+                mined_tx_hashes: vec![B256::ZERO],
+            }],
+        };
+        let new_head_clone = new_head.clone();
+        mock_trigger
+            .expect_wait_for_block()
+            .once()
+            .returning(move || {
+                Box::pin({
+                    let new_head = new_head_clone.clone();
+                    async move { Ok(new_head) }
+                })
+            });
+        mock_trigger.expect_last_block().return_const(new_head);
+        mock_tracker.expect_address().return_const(Address::ZERO);
+        mock_tracker
+            .expect_process_update()
+            .once()
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 5,
+                        nonce: 0,
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: B256::ZERO,
+                        attempt_number: 0,
+                        is_success: true,
+                        op_results: vec![],
+                    }))
+                })
+            });
+
+        // mined 4 blocks after it was first sent, which is slow
+        mock_proposer
+            .expect_set_priority_fee_mode()
+            .once()
+            .withf(|mode| *mode == PriorityFeeMode::PriorityFeeIncreasePercent(15))
+            .return_const(());
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.fee_controller = Some(PriorityFeeController::new(fee_controller::Settings {
+            min_percent: 10,
+            max_percent: 50,
+            step_percent: 5,
+            slow_inclusion_blocks: 3,
+            window: 1,
+        }));
+        sender.first_send_block = Some(1);
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::Pending(PendingState {
+                until: 6,
+                resim_at: None,
+                fee_increase_count: 0,
+            }),
+            requires_reset: false,
+        };
+
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::Building(_)));
+        assert_eq!(sender.first_send_block, None);
+    }
+
+    #[tokio::test]
+    async fn test_op_removed_after_max_onchain_reverts() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mock_evm,
+            mut mock_pool,
+        } = new_mocks();
+
+        let op_hash = B256::repeat_byte(1);
+        let new_head = NewHead {
+            block_number: 5,
+            block_hash: B256::ZERO,
+            address_updates: vec![AddressUpdate {
+                address: Address::ZERO,
+                nonce: Some(0),
+                balance: U256::ZERO,
+                mined_tx_hashes: vec![B256::ZERO],
+            }],
+        };
+        let new_head_clone = new_head.clone();
+        mock_trigger
+            .expect_wait_for_block()
+            .times(2)
+            .returning(move || {
+                Box::pin({
+                    let new_head = new_head_clone.clone();
+                    async move { Ok(new_head) }
+                })
+            });
+        mock_trigger.expect_last_block().return_const(new_head);
+        mock_tracker.expect_address().return_const(Address::ZERO);
+        // each bundle mines without a UserOperationEvent for the op
+        mock_tracker
+            .expect_process_update()
+            .times(2)
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 5,
+                        nonce: 0,
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: B256::ZERO,
+                        attempt_number: 0,
+                        is_success: true,
+                        op_results: vec![],
+                    }))
+                })
+            });
+
+        // only removed after the second revert
+        mock_pool
+            .expect_remove_ops()
+            .once()
+            .withf(move |_, hashes| *hashes == vec![op_hash])
+            .returning(|_, _| Ok(()));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.settings.max_onchain_revert_attempts = Some(2);
+        let mut events = sender.event_sender.subscribe();
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::new(),
+            requires_reset: false,
+        };
+
+        for _ in 0..2 {
+            sender
+                .sent_bundle_ops
+                .insert(B256::ZERO, Arc::new(vec![(Address::ZERO, op_hash)]));
+            state.inner = InnerState::Pending(PendingState {
+                until: 6,
+                resim_at: None,
+                fee_increase_count: 0,
+            });
+            sender.step_state(&mut state).await.unwrap();
+        }
+
+        let rejected = std::iter::from_fn(|| events.try_recv().ok())
+            .filter_map(|event| match event.event.kind {
+                BuilderEventKind::RejectedOp {
+                    op_hash,
+                    reason: OpRejectionReason::MaxOnchainRevertsExceeded { attempts },
+                } => Some((op_hash, attempts)),
+                _ => None,
             })
-        ));
+            .collect::<Vec<_>>();
+        assert_eq!(rejected, vec![(op_hash, 2)]);
+        assert!(sender.onchain_revert_counts.peek(&op_hash).is_none());
     }
 
     #[tokio::test]
-    async fn test_wait_for_mine_success() {
+    async fn test_reverted_bundle_only_removes_offending_op() {
+        let _lock = STATIC_EP_MOCK_LOCK.lock().await;
         let Mocks {
             mock_proposer,
             mock_entry_point,
             mut mock_tracker,
             mut mock_trigger,
-            mock_evm,
-            mock_pool,
+            mut mock_evm,
+            mut mock_pool,
         } = new_mocks();
 
-        let mut seq = Sequence::new();
-        add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, 1);
-
         let new_head = NewHead {
-            block_number: 2,
+            block_number: 5,
             block_hash: B256::ZERO,
             address_updates: vec![AddressUpdate {
                 address: Address::ZERO,
@@ -1702,71 +3294,101 @@ mod tests {
             }],
         };
         let new_head_clone = new_head.clone();
-
         mock_trigger
             .expect_wait_for_block()
             .once()
-            .in_sequence(&mut seq)
             .returning(move || {
                 Box::pin({
                     let new_head = new_head_clone.clone();
                     async move { Ok(new_head) }
                 })
             });
-        mock_trigger
-            .expect_last_block()
-            .once()
-            .in_sequence(&mut seq)
-            .return_const(new_head);
-
+        mock_trigger.expect_last_block().return_const(new_head);
         mock_tracker.expect_address().return_const(Address::ZERO);
+        mock_tracker
+            .expect_process_update()
+            .once()
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 5,
+                        nonce: 0,
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: B256::ZERO,
+                        attempt_number: 0,
+                        is_success: false,
+                        op_results: vec![],
+                    }))
+                })
+            });
 
-        mock_tracker.expect_process_update().once().returning(|_| {
-            Box::pin(async {
-                Ok(Some(TrackerUpdate::Mined {
-                    block_number: 2,
-                    nonce: 0,
-                    gas_limit: None,
-                    gas_used: None,
-                    gas_price: None,
-                    tx_hash: B256::ZERO,
-                    attempt_number: 0,
-                    is_success: true,
-                }))
+        let ops = (0..2)
+            .map(|nonce| UserOperation {
+                nonce: U256::from(nonce),
+                ..Default::default()
             })
-        });
+            .collect::<Vec<_>>();
+        let hashes = ops.iter().map(|op| op.hash()).collect::<Vec<_>>();
+        let offender = hashes[1];
+
+        let input = bytes!("1234");
+        let input_clone = input.clone();
+        let output = bytes!("5678");
+        let output_clone = output.clone();
+        mock_evm
+            .expect_debug_trace_transaction()
+            .returning(move |_, _| {
+                Ok(GethDebugTracerCallFrame {
+                    input: input.clone(),
+                    output: Some(output.clone()),
+                    ..Default::default()
+                }
+                .into())
+            });
+        let ctx = MockEntryPointV0_6::decode_ops_from_calldata_context();
+        ctx.expect()
+            .withf(move |_, data| *data == input_clone)
+            .returning(move |_, _| {
+                vec![UserOpsPerAggregator {
+                    user_ops: ops.clone(),
+                    ..Default::default()
+                }]
+            });
+        let ctx = MockEntryPointV0_6::decode_handle_ops_revert_context();
+        ctx.expect()
+            .withf(move |_, data| *data == Some(output_clone.clone()))
+            .returning(|_, _| Some(HandleOpsOut::FailedOp(1, "revert".to_string())));
+
+        // the other op of the reverted bundle is not counted against its revert limit
+        mock_pool
+            .expect_remove_ops()
+            .once()
+            .withf(move |_, removed| *removed == vec![offender])
+            .returning(|_, _| Ok(()));
 
         let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.settings.max_onchain_revert_attempts = Some(1);
+        sender.sent_bundle_ops.insert(
+            B256::ZERO,
+            Arc::new(hashes.iter().map(|hash| (Address::ZERO, *hash)).collect()),
+        );
 
-        // start in pending state
         let mut state = SenderMachineState {
             trigger: mock_trigger,
             transaction_tracker: mock_tracker,
             send_bundle_response: None,
             inner: InnerState::Pending(PendingState {
-                until: 3,
+                until: 6,
+                resim_at: None,
                 fee_increase_count: 0,
             }),
             requires_reset: false,
         };
-
-        // first step has no update
         sender.step_state(&mut state).await.unwrap();
-        assert!(matches!(
-            state.inner,
-            InnerState::Pending(PendingState { until: 3, .. })
-        ));
 
-        // second step is mined and moves back to building
-        sender.step_state(&mut state).await.unwrap();
-        assert!(matches!(
-            state.inner,
-            InnerState::Building(BuildingState {
-                wait_for_trigger: false, // don't wait for trigger in auto mode
-                fee_increase_count: 0,
-                underpriced_info: None,
-            })
-        ));
+        assert!(sender.onchain_revert_counts.peek(&hashes[0]).is_none());
     }
 
     #[tokio::test]
@@ -1794,6 +3416,7 @@ mod tests {
             send_bundle_response: None,
             inner: InnerState::Pending(PendingState {
                 until: 3,
+                resim_at: None,
                 fee_increase_count: 0,
             }),
             requires_reset: false,
@@ -1820,6 +3443,106 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_resim_pending_bundle_with_invalid_op() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mock_tracker,
+            mut mock_trigger,
+            mock_evm,
+            mut mock_pool,
+        } = new_mocks();
+
+        let valid_hash = B256::repeat_byte(1);
+        let invalid_hash = B256::repeat_byte(2);
+
+        let mut seq = Sequence::new();
+        for block_number in [2, 4] {
+            let new_head = NewHead {
+                block_number,
+                block_hash: B256::ZERO,
+                address_updates: vec![],
+            };
+            let new_head_clone = new_head.clone();
+            mock_trigger
+                .expect_wait_for_block()
+                .once()
+                .in_sequence(&mut seq)
+                .returning(move || {
+                    Box::pin({
+                        let new_head = new_head_clone.clone();
+                        async move { Ok(new_head) }
+                    })
+                });
+            mock_trigger
+                .expect_last_block()
+                .times(3)
+                .in_sequence(&mut seq)
+                .return_const(new_head);
+        }
+
+        mock_pool
+            .expect_get_ops_by_hashes()
+            .times(2)
+            .returning(|_, _| Ok(vec![demo_pool_op(), demo_pool_op()]));
+
+        // all ops are valid on the first re-simulation, one has gone invalid on the second
+        let mut resim_seq = Sequence::new();
+        mock_proposer
+            .expect_find_invalid_ops()
+            .once()
+            .in_sequence(&mut resim_seq)
+            .returning(|_, _| Box::pin(async { Ok(vec![]) }));
+        mock_proposer
+            .expect_find_invalid_ops()
+            .once()
+            .in_sequence(&mut resim_seq)
+            .returning(move |_, _| Box::pin(async move { Ok(vec![invalid_hash]) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.settings.resim_after_blocks = Some(2);
+        sender.last_bundle_ops = Some(Arc::new(vec![
+            (Address::ZERO, valid_hash),
+            (Address::ZERO, invalid_hash),
+        ]));
+
+        // pending for long enough to re-simulate twice before the fee increase
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::Pending(PendingState {
+                until: 10,
+                resim_at: Some(2),
+                fee_increase_count: 0,
+            }),
+            requires_reset: false,
+        };
+
+        // ops still valid, keep waiting and schedule the next re-simulation
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState {
+                until: 10,
+                resim_at: Some(4),
+                fee_increase_count: 0,
+            })
+        ));
+
+        // an op went invalid, rebuild before the fee increase at block 10
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Building(BuildingState {
+                wait_for_trigger: false,
+                fee_increase_count: 1,
+                underpriced_info: None,
+            })
+        ));
+    }
+
     #[tokio::test]
     async fn test_transition_to_cancel() {
         let Mocks {
@@ -2000,6 +3723,189 @@ mod tests {
         ));
     }
 
+    // Steps a sender waiting on a cancellation of the bundle sent in `bundle_tx_hash` while
+    // the tracker reports `mined_tx_hash` mined, returning the events emitted
+    async fn mine_during_cancel_pending(
+        bundle_tx_hash: B256,
+        mined_tx_hash: B256,
+        is_success: bool,
+    ) -> Vec<BuilderEventKind> {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mock_evm,
+            mock_pool,
+        } = new_mocks();
+
+        let op_hash = B256::repeat_byte(1);
+        let new_head = NewHead {
+            block_number: 2,
+            block_hash: B256::ZERO,
+            address_updates: vec![AddressUpdate {
+                address: Address::ZERO,
+                nonce: Some(0),
+                balance: U256::ZERO,
+                mined_tx_hashes: vec![mined_tx_hash],
+            }],
+        };
+        let new_head_clone = new_head.clone();
+        mock_trigger
+            .expect_wait_for_block()
+            .once()
+            .returning(move || {
+                Box::pin({
+                    let new_head = new_head_clone.clone();
+                    async move { Ok(new_head) }
+                })
+            });
+        mock_trigger.expect_last_block().return_const(new_head);
+        mock_tracker.expect_address().return_const(Address::ZERO);
+        mock_tracker
+            .expect_process_update()
+            .once()
+            .returning(move |_, _| {
+                Box::pin(async move {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 2,
+                        nonce: 0,
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: mined_tx_hash,
+                        attempt_number: 0,
+                        is_success,
+                        op_results: vec![MinedOpResult {
+                            op_hash,
+                            success: true,
+                            actual_gas_cost: U256::from(1),
+                        }],
+                    }))
+                })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender
+            .sent_bundle_ops
+            .insert(bundle_tx_hash, Arc::new(vec![(Address::ZERO, op_hash)]));
+        let mut events = sender.event_sender.subscribe();
+
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::CancelPending(CancelPendingState {
+                until: 3,
+                fee_increase_count: 0,
+            }),
+            requires_reset: false,
+        };
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::Building(_)));
+        assert!(sender.sent_bundle_ops.is_empty());
+
+        std::iter::from_fn(|| events.try_recv().ok())
+            .map(|event| event.event.kind)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_cancel_resolved_by_bundle_mined() {
+        // the cancellation fails to land and the original bundle uses the nonce
+        let bundle_tx_hash = B256::repeat_byte(2);
+        let events = mine_during_cancel_pending(bundle_tx_hash, bundle_tx_hash, true).await;
+
+        assert!(matches!(
+            events.as_slice(),
+            [
+                BuilderEventKind::MinedOp {
+                    outcome: MinedOpOutcome::Succeeded { .. },
+                    ..
+                },
+                BuilderEventKind::TransactionMined { tx_hash: mined, .. },
+                BuilderEventKind::CancellationResolved {
+                    outcome: CancellationOutcome::BundleMined { tx_hash },
+                },
+            ] if *mined == bundle_tx_hash && *tx_hash == bundle_tx_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_resolved_by_cancel_reverted() {
+        let cancel_tx_hash = B256::repeat_byte(3);
+        let events = mine_during_cancel_pending(B256::repeat_byte(2), cancel_tx_hash, false).await;
+
+        assert!(matches!(
+            events.as_slice(),
+            [BuilderEventKind::CancellationResolved {
+                outcome: CancellationOutcome::CancelReverted { tx_hash },
+            }] if *tx_hash == cancel_tx_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_recheck_cancellation_nonce() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mut mock_evm,
+            mock_pool,
+        } = new_mocks();
+
+        let mut seq = Sequence::new();
+        for i in 3..=4 {
+            add_trigger_wait_for_block_last_block(&mut mock_trigger, &mut seq, i);
+        }
+        mock_tracker.expect_get_state().returning(|| {
+            Ok(TrackerState {
+                nonce: 0,
+                balance: U256::ZERO,
+                required_fees: None,
+            })
+        });
+        // the nonce is used on-chain before the tracker reports the mined transaction
+        let mut nonces = vec![0, 1].into_iter();
+        mock_evm
+            .expect_get_transaction_count()
+            .times(2)
+            .returning(move |_| Ok(nonces.next().unwrap()));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.settings.recheck_cancellation_nonce = true;
+        let mut events = sender.event_sender.subscribe();
+
+        let pending = CancelPendingState {
+            until: 3,
+            fee_increase_count: sender.settings.max_cancellation_fee_increases,
+        };
+        let mut state = SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::CancelPending(pending),
+            requires_reset: false,
+        };
+
+        // the nonce is unused, so the cancellation is abandoned
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::Building(_)));
+        assert!(matches!(
+            events.try_recv().unwrap().event.kind,
+            BuilderEventKind::CancellationResolved {
+                outcome: CancellationOutcome::Abandoned
+            }
+        ));
+
+        // the nonce is used, so the sender keeps waiting
+        state.inner = InnerState::CancelPending(pending);
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::CancelPending(_)));
+        assert!(events.try_recv().is_err());
+    }
+
     #[tokio::test]
     async fn test_condition_not_met() {
         let Mocks {
@@ -2098,6 +4004,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_revert_remove() {
+        let _lock = STATIC_EP_MOCK_LOCK.lock().await;
         let Mocks {
             mock_proposer,
             mock_entry_point,
@@ -2140,20 +4047,24 @@ mod tests {
 
         mock_tracker.expect_address().return_const(Address::ZERO);
 
-        mock_tracker.expect_process_update().once().returning(|_| {
-            Box::pin(async {
-                Ok(Some(TrackerUpdate::Mined {
-                    block_number: 2,
-                    nonce: 0,
-                    gas_limit: None,
-                    gas_used: None,
-                    gas_price: None,
-                    tx_hash: B256::ZERO,
-                    attempt_number: 0,
-                    is_success: false, // revert
-                }))
-            })
-        });
+        mock_tracker
+            .expect_process_update()
+            .once()
+            .returning(|_, _| {
+                Box::pin(async {
+                    Ok(Some(TrackerUpdate::Mined {
+                        block_number: 2,
+                        nonce: 0,
+                        gas_limit: None,
+                        gas_used: None,
+                        gas_price: None,
+                        tx_hash: B256::ZERO,
+                        attempt_number: 0,
+                        is_success: false, // revert
+                        op_results: vec![],
+                    }))
+                })
+            });
 
         let input = bytes!("1234");
         let input_clone = input.clone();
@@ -2203,6 +4114,7 @@ mod tests {
             send_bundle_response: None,
             inner: InnerState::Pending(PendingState {
                 until: 3,
+                resim_at: None,
                 fee_increase_count: 0,
             }),
             requires_reset: false,
@@ -2227,6 +4139,260 @@ mod tests {
         ));
     }
 
+    fn pending_state_with_cancel_request(
+        mut mock_trigger: MockTrigger,
+        mock_tracker: MockTransactionTracker,
+        op_hash: B256,
+    ) -> SenderMachineState<MockTransactionTracker, MockTrigger> {
+        // replace the default of no cancel requests
+        mock_trigger.checkpoint();
+        mock_trigger
+            .expect_take_cancel_requests()
+            .once()
+            .returning(move || vec![op_hash]);
+        let new_head = NewHead {
+            block_number: 0,
+            block_hash: B256::ZERO,
+            address_updates: vec![],
+        };
+        let new_head_clone = new_head.clone();
+        mock_trigger
+            .expect_wait_for_block()
+            .once()
+            .returning(move || {
+                Box::pin({
+                    let new_head = new_head_clone.clone();
+                    async move { Ok(new_head) }
+                })
+            });
+        mock_trigger.expect_last_block().return_const(new_head);
+
+        SenderMachineState {
+            trigger: mock_trigger,
+            transaction_tracker: mock_tracker,
+            send_bundle_response: None,
+            inner: InnerState::Pending(PendingState {
+                until: 3,
+                resim_at: None,
+                fee_increase_count: 1,
+            }),
+            requires_reset: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_containing_op() {
+        let Mocks {
+            mut mock_proposer,
+            mock_entry_point,
+            mut mock_tracker,
+            mock_trigger,
+            mock_evm,
+            mock_pool,
+        } = new_mocks();
+
+        let op_hash = B256::repeat_byte(1);
+
+        mock_proposer
+            .expect_estimate_gas_fees()
+            .once()
+            .returning(|_, _| Box::pin(async { Ok((GasFees::default(), 0)) }));
+        mock_tracker
+            .expect_cancel_transaction()
+            .once()
+            .returning(|_| Box::pin(async { Ok(Some(B256::ZERO)) }));
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.last_bundle_ops = Some(Arc::new(vec![
+            (Address::ZERO, B256::repeat_byte(2)),
+            (Address::ZERO, op_hash),
+        ]));
+        let mut events = sender.event_sender.subscribe();
+
+        let mut state = pending_state_with_cancel_request(mock_trigger, mock_tracker, op_hash);
+
+        // the pending bundle is cancelled right away, without waiting for it to time out
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::CancelPending(CancelPendingState {
+                until: 3,
+                fee_increase_count: 0,
+            })
+        ));
+        assert!(matches!(
+            events.try_recv().unwrap().event.kind,
+            BuilderEventKind::CancelContainingOp { op_hash: hash, cancelling: true } if hash == op_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_containing_op_not_in_bundle() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mock_tracker,
+            mock_trigger,
+            mock_evm,
+            mock_pool,
+        } = new_mocks();
+
+        let op_hash = B256::repeat_byte(1);
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.last_bundle_ops = Some(Arc::new(vec![(Address::ZERO, B256::repeat_byte(2))]));
+        let mut events = sender.event_sender.subscribe();
+
+        let mut state = pending_state_with_cancel_request(mock_trigger, mock_tracker, op_hash);
+
+        // the pending bundle keeps waiting to be mined
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(
+            state.inner,
+            InnerState::Pending(PendingState {
+                until: 3,
+                fee_increase_count: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            events.try_recv().unwrap().event.kind,
+            BuilderEventKind::CancelContainingOp { op_hash: hash, cancelling: false } if hash == op_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_skip_pending_nonce_duplicates() {
+        let Mocks {
+            mut mock_proposer,
+            mut mock_entry_point,
+            mut mock_tracker,
+            mut mock_trigger,
+            mut mock_evm,
+            mut mock_pool,
+        } = new_mocks();
+
+        let op = |sender: Address, max_fee_per_gas: u128| {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    sender,
+                    max_fee_per_gas,
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+        let (sender_a, sender_b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        // same sender and nonce, different hashes
+        let first_op = op(sender_a, 10);
+        let replacement_op = op(sender_a, 20);
+        let other_op = op(sender_b, 10);
+        let (first_hash, replacement_hash, other_hash) =
+            (first_op.hash(), replacement_op.hash(), other_op.hash());
+
+        add_trigger_no_update_last_block(&mut mock_trigger, &mut Sequence::new(), 0);
+        mock_tracker.expect_get_state().returning(|| {
+            Ok(TrackerState {
+                nonce: 0,
+                balance: U256::ZERO,
+                required_fees: None,
+            })
+        });
+        mock_tracker.expect_address().return_const(Address::ZERO);
+        mock_evm
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::MAX));
+        mock_entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _, _| TransactionRequest::default());
+        mock_tracker
+            .expect_send_transaction()
+            .returning(|_, _, _| Box::pin(async { Ok(B256::ZERO) }));
+
+        // the pool offers the first op, then its replacement along with another op
+        let mut seq = Sequence::new();
+        for ops in [
+            vec![first_op.clone()],
+            vec![replacement_op.clone(), other_op.clone()],
+        ] {
+            let summaries = ops
+                .iter()
+                .map(|op| PoolOperationSummary {
+                    hash: op.hash(),
+                    sender: op.sender(),
+                    entry_point: ENTRY_POINT_ADDRESS_V0_6,
+                })
+                .collect::<Vec<_>>();
+            mock_pool
+                .expect_get_ops_summaries()
+                .once()
+                .in_sequence(&mut seq)
+                .returning(move |_, _, _| Ok(summaries.clone()));
+            mock_pool
+                .expect_get_ops_by_hashes()
+                .once()
+                .in_sequence(&mut seq)
+                .returning(move |_, _| {
+                    Ok(ops
+                        .iter()
+                        .map(|op| PoolOperation {
+                            uo: op.clone().into(),
+                            ..demo_pool_op()
+                        })
+                        .collect())
+                });
+        }
+
+        let mut seq = Sequence::new();
+        mock_proposer
+            .expect_make_bundle()
+            .once()
+            .in_sequence(&mut seq)
+            .returning(move |_, _, _, _, _| {
+                let op = first_op.clone();
+                Box::pin(async move { Ok(bundle_with_op(op)) })
+            });
+        // the replacement op is skipped while the first op's bundle is pending
+        mock_proposer
+            .expect_make_bundle()
+            .once()
+            .in_sequence(&mut seq)
+            .withf(move |ops, _, _, _, _| ops.len() == 1 && ops[0].uo.hash() == other_hash)
+            .returning(move |_, _, _, _, _| {
+                let op = other_op.clone();
+                Box::pin(async move { Ok(bundle_with_op(op)) })
+            });
+
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        sender.settings.skip_pending_nonce_duplicates = true;
+        let mut events = sender.event_sender.subscribe();
+
+        let mut state = SenderMachineState::new(mock_trigger, mock_tracker);
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::Pending(..)));
+
+        // the pending bundle isn't mined in time, build its replacement
+        state.update(InnerState::Building(BuildingState {
+            wait_for_trigger: false,
+            fee_increase_count: 1,
+            underpriced_info: None,
+        }));
+        sender.step_state(&mut state).await.unwrap();
+        assert!(matches!(state.inner, InnerState::Pending(..)));
+
+        let skipped = std::iter::from_fn(|| events.try_recv().ok())
+            .filter_map(|e| match e.event.kind {
+                BuilderEventKind::SkippedOp {
+                    op_hash,
+                    reason: SkipReason::PendingNonceDuplicate { pending_op_hash },
+                } => Some((op_hash, pending_op_hash)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(skipped, vec![(replacement_hash, first_hash)]);
+    }
+
     struct Mocks {
         mock_proposer: MockBundleProposer,
         mock_entry_point: MockEntryPointV0_6,
@@ -2242,11 +4408,16 @@ mod tests {
             .expect_address()
             .return_const(Address::default());
 
+        let mut mock_trigger = MockTrigger::new();
+        mock_trigger
+            .expect_take_cancel_requests()
+            .returning(Vec::new);
+
         Mocks {
             mock_proposer: MockBundleProposer::new(),
             mock_entry_point,
             mock_tracker: MockTransactionTracker::new(),
-            mock_trigger: MockTrigger::new(),
+            mock_trigger,
             mock_evm: MockEvmProvider::new(),
             mock_pool: MockPool::new(),
         }
@@ -2269,6 +4440,34 @@ mod tests {
         >,
         MockTransactionTracker,
         Arc<MockPool>,
+    > {
+        new_sender_with_transformer(
+            mock_proposer,
+            mock_entry_point,
+            mock_evm,
+            mock_pool,
+            Arc::new(NoopTransactionTransformer),
+        )
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn new_sender_with_transformer(
+        mock_proposer: MockBundleProposer,
+        mock_entry_point: MockEntryPointV0_6,
+        mock_evm: MockEvmProvider,
+        mock_pool: MockPool,
+        transaction_transformer: Arc<dyn TransactionTransformer>,
+    ) -> BundleSenderImpl<
+        MockBundleProposer,
+        ProvidersWithEntryPoint<
+            UserOperation,
+            Arc<MockEvmProvider>,
+            Arc<MockEntryPointV0_6>,
+            Arc<MockDAGasOracleSync>,
+            Arc<MockFeeEstimator>,
+        >,
+        MockTransactionTracker,
+        Arc<MockPool>,
     > {
         let pool = Arc::new(mock_pool);
         BundleSenderImpl::new(
@@ -2280,6 +4479,8 @@ mod tests {
             ChainSpec::default(),
             Address::default(),
             None,
+            transaction_transformer,
+            Arc::new(NoopBundleAuditSink),
             mock_proposer,
             ProvidersWithEntryPoint::new(
                 Arc::new(mock_evm),
@@ -2294,6 +4495,17 @@ mod tests {
                 max_cancellation_fee_increases: 3,
                 max_blocks_to_wait_for_mine: 3,
                 max_replacement_underpriced_blocks: 3,
+                sticky_replacement: false,
+                resim_after_blocks: None,
+                skip_pending_nonce_duplicates: false,
+                warm_up: Duration::ZERO,
+                repoll_on_empty: 0,
+                heartbeat_interval_blocks: None,
+                priority_fee_controller: None,
+                max_onchain_revert_attempts: None,
+                builder_index: 0,
+                recheck_cancellation_nonce: false,
+                tip_transfer: None,
             },
             broadcast::channel(1000).0,
         )
@@ -2355,6 +4567,7 @@ mod tests {
 
     fn bundle() -> Bundle<UserOperation> {
         Bundle {
+            beneficiary: Address::ZERO,
             gas_estimate: 100_000,
             gas_fees: GasFees::default(),
             expected_storage: Default::default(),
@@ -2368,6 +4581,189 @@ mod tests {
         }
     }
 
+    fn bundle_with_op(op: UserOperation) -> Bundle<UserOperation> {
+        let mut bundle = bundle();
+        bundle.ops_per_aggregator[0].user_ops = vec![op];
+        bundle
+    }
+
+    #[test]
+    fn test_match_op_outcomes() {
+        let ops = (0..3)
+            .map(|_| (Address::random(), B256::random()))
+            .collect::<Vec<_>>();
+        let op_results = vec![
+            MinedOpResult {
+                op_hash: ops[2].1,
+                success: false,
+                actual_gas_cost: U256::from(200),
+            },
+            MinedOpResult {
+                op_hash: ops[0].1,
+                success: true,
+                actual_gas_cost: U256::from(100),
+            },
+        ];
+
+        let outcomes = match_op_outcomes(&ops, &op_results);
+
+        assert_eq!(outcomes.len(), 3);
+        assert!(matches!(
+            outcomes[0],
+            (hash, MinedOpOutcome::Succeeded { actual_gas_cost }) if hash == ops[0].1 && actual_gas_cost == U256::from(100)
+        ));
+        assert!(matches!(
+            outcomes[1],
+            (hash, MinedOpOutcome::Dropped) if hash == ops[1].1
+        ));
+        assert!(matches!(
+            outcomes[2],
+            (hash, MinedOpOutcome::Reverted { actual_gas_cost }) if hash == ops[2].1 && actual_gas_cost == U256::from(200)
+        ));
+    }
+
+    #[test]
+    fn test_prioritize_previous_ops() {
+        let op_with_nonce = |nonce: u64| {
+            let mut op = demo_pool_op();
+            op.uo = UserOperation {
+                nonce: U256::from(nonce),
+                ..Default::default()
+            }
+            .into();
+            op
+        };
+        let ops = (0..4).map(op_with_nonce).collect::<Vec<_>>();
+        let hashes = ops.iter().map(|op| op.uo.hash()).collect::<Vec<_>>();
+        let previous = vec![(Address::ZERO, hashes[2]), (Address::ZERO, hashes[0])];
+
+        let prioritized = prioritize_previous_ops(ops, &previous)
+            .iter()
+            .map(|op| op.uo.hash())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            prioritized,
+            vec![hashes[2], hashes[0], hashes[1], hashes[3]]
+        );
+    }
+
+    #[test]
+    fn test_collect_op_metadata() {
+        let untagged = demo_pool_op();
+        let mut tagged = demo_pool_op();
+        tagged.uo = UserOperation {
+            nonce: U256::from(1),
+            ..Default::default()
+        }
+        .into();
+        let hash = tagged.uo.hash();
+        tagged.perms.client_tag = Some("order-42".to_string());
+        tagged.perms.submission_source = Some("client-7".to_string());
+
+        // the metadata does not change the op hash
+        assert_eq!(tagged.uo.hash(), hash);
+
+        let metadata = collect_op_metadata(&[untagged, tagged]);
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(
+            metadata.get(&hash),
+            Some(&OpMetadata {
+                client_tag: Some("order-42".to_string()),
+                submission_source: Some("client-7".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_mined_op_event_has_submission_source() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mock_evm,
+            mock_pool,
+            ..
+        } = new_mocks();
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        let mut events = sender.event_sender.subscribe();
+
+        let tx_hash = B256::repeat_byte(1);
+        let sourced = B256::repeat_byte(2);
+        let unsourced = B256::repeat_byte(3);
+        sender.sent_bundle_ops.insert(
+            tx_hash,
+            Arc::new(vec![(Address::ZERO, sourced), (Address::ZERO, unsourced)]),
+        );
+        sender.sent_op_metadata.insert(
+            sourced,
+            OpMetadata {
+                client_tag: None,
+                submission_source: Some("client-7".to_string()),
+            },
+        );
+
+        sender.process_mined_ops(tx_hash, true, &[]);
+
+        let expected_bundle_id = bundle_proposer::bundle_id([unsourced, sourced], 0);
+        for (op_hash, source) in [(sourced, Some("client-7")), (unsourced, None)] {
+            let BuilderEventKind::MinedOp {
+                bundle_id,
+                op_hash: event_op_hash,
+                outcome: MinedOpOutcome::Dropped,
+                submission_source,
+                ..
+            } = events.try_recv().unwrap().event.kind
+            else {
+                panic!("expected a dropped mined op event");
+            };
+            assert_eq!(bundle_id, expected_bundle_id);
+            assert_eq!(event_op_hash, op_hash);
+            assert_eq!(submission_source.as_deref(), source);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_delays_first_send() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mock_evm,
+            mock_pool,
+            ..
+        } = new_mocks();
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+        // third builder with a 500ms stagger interval
+        sender.settings.warm_up = Duration::from_millis(500) * 3;
+
+        let mut clock = MockClock::new();
+        clock
+            .expect_sleep()
+            .withf(|duration| *duration == Duration::from_millis(1500))
+            .once()
+            .returning(|_| ());
+        sender.clock = Arc::new(clock);
+
+        sender.warm_up().await;
+    }
+
+    #[tokio::test]
+    async fn test_no_warm_up() {
+        let Mocks {
+            mock_proposer,
+            mock_entry_point,
+            mock_evm,
+            mock_pool,
+            ..
+        } = new_mocks();
+        let mut sender = new_sender(mock_proposer, mock_entry_point, mock_evm, mock_pool);
+
+        let mut clock = MockClock::new();
+        clock.expect_sleep().never();
+        sender.clock = Arc::new(clock);
+
+        sender.warm_up().await;
+    }
+
     fn demo_pool_op() -> PoolOperation {
         PoolOperation {
             uo: UserOperation::default().into(),
@@ -0,0 +1,140 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Mutex,
+};
+
+use alloy_primitives::B256;
+use async_trait::async_trait;
+use futures::future;
+use rundler_provider::EvmProvider;
+use tracing::warn;
+
+use crate::transaction_tracker::{self, MinedTxInfo};
+
+/// Polls for the confirmations of the transactions of every tracker in the process, so
+/// that the RPC load doesn't grow with the number of builders.
+///
+/// Trackers watch the hashes of the transactions they send and take the confirmations
+/// of their own transactions by hash.
+#[async_trait]
+pub(crate) trait ConfirmationPoller: Send + Sync + Debug {
+    /// Start polling for the confirmation of a transaction
+    fn watch(&self, tx_hash: B256);
+
+    /// Stop polling for the confirmation of a transaction, dropping it if already confirmed
+    fn unwatch(&self, tx_hash: B256);
+
+    /// Poll for the confirmations of all watched transactions as of the given block.
+    ///
+    /// Only the first call for a block polls, so every tracker can call this each block.
+    async fn poll(&self, block_number: u64);
+
+    /// Take the confirmation of a watched transaction, if it has been confirmed
+    fn take_confirmation(&self, tx_hash: B256) -> Option<MinedTxInfo>;
+}
+
+/// Confirmation poller that looks up the receipts of the watched transactions
+pub(crate) struct ReceiptConfirmationPoller<P> {
+    provider: P,
+    state: Mutex<PollerState>,
+    // held across a poll so that concurrent callers for the same block wait for it
+    // rather than polling again
+    last_polled_block: tokio::sync::Mutex<Option<u64>>,
+}
+
+#[derive(Debug, Default)]
+struct PollerState {
+    watched: HashSet<B256>,
+    confirmed: HashMap<B256, MinedTxInfo>,
+}
+
+impl<P> ReceiptConfirmationPoller<P> {
+    pub(crate) fn new(provider: P) -> Self {
+        Self {
+            provider,
+            state: Mutex::new(PollerState::default()),
+            last_polled_block: tokio::sync::Mutex::new(None),
+        }
+    }
+}
+
+impl<P> Debug for ReceiptConfirmationPoller<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReceiptConfirmationPoller")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<P: EvmProvider> ConfirmationPoller for ReceiptConfirmationPoller<P> {
+    fn watch(&self, tx_hash: B256) {
+        self.state.lock().unwrap().watched.insert(tx_hash);
+    }
+
+    fn unwatch(&self, tx_hash: B256) {
+        let mut state = self.state.lock().unwrap();
+        state.watched.remove(&tx_hash);
+        state.confirmed.remove(&tx_hash);
+    }
+
+    async fn poll(&self, block_number: u64) {
+        let mut last_polled_block = self.last_polled_block.lock().await;
+        if last_polled_block.is_some_and(|b| b >= block_number) {
+            return;
+        }
+        *last_polled_block = Some(block_number);
+
+        let pending = {
+            let state = self.state.lock().unwrap();
+            state
+                .watched
+                .iter()
+                .filter(|tx_hash| !state.confirmed.contains_key(*tx_hash))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let results = future::join_all(pending.into_iter().map(|tx_hash| async move {
+            (
+                tx_hash,
+                transaction_tracker::get_mined_tx_info(&self.provider, tx_hash).await,
+            )
+        }))
+        .await;
+
+        let mut state = self.state.lock().unwrap();
+        for (tx_hash, result) in results {
+            match result {
+                // a transaction unwatched during the poll is no longer wanted
+                Ok(Some(info)) if state.watched.contains(&tx_hash) => {
+                    state.confirmed.insert(tx_hash, info);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Failed to poll for confirmation of transaction {tx_hash:?}: {e:?}");
+                }
+            }
+        }
+    }
+
+    fn take_confirmation(&self, tx_hash: B256) -> Option<MinedTxInfo> {
+        let mut state = self.state.lock().unwrap();
+        let info = state.confirmed.remove(&tx_hash)?;
+        state.watched.remove(&tx_hash);
+        Some(info)
+    }
+}
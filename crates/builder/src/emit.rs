@@ -11,12 +11,12 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{fmt::Display, sync::Arc};
+use std::{fmt::Display, sync::Arc, time::Duration};
 
-use alloy_primitives::{Address, B256, U256};
-use rundler_provider::TransactionRequest;
+use alloy_primitives::{Address, Bytes, B256, U256};
+use rundler_provider::{GasAttribution, TransactionRequest};
 use rundler_sim::SimulationError;
-use rundler_types::{GasFees, ValidTimeRange};
+use rundler_types::{EntityInfos, GasFees, Timestamp, ValidTimeRange};
 use rundler_utils::strs;
 
 /// Builder event
@@ -53,6 +53,7 @@ impl BuilderEvent {
 
     pub(crate) fn transaction_mined(
         tag: String,
+        bundle_id: Option<B256>,
         tx_hash: B256,
         nonce: u64,
         block_number: u64,
@@ -60,6 +61,7 @@ impl BuilderEvent {
         Self::new(
             tag,
             BuilderEventKind::TransactionMined {
+                bundle_id,
                 tx_hash,
                 nonce,
                 block_number,
@@ -67,6 +69,28 @@ impl BuilderEvent {
         )
     }
 
+    pub(crate) fn mined_op(
+        tag: String,
+        bundle_id: B256,
+        tx_hash: B256,
+        op_hash: B256,
+        outcome: MinedOpOutcome,
+        client_tag: Option<String>,
+        submission_source: Option<String>,
+    ) -> Self {
+        Self::new(
+            tag,
+            BuilderEventKind::MinedOp {
+                bundle_id,
+                tx_hash,
+                op_hash,
+                outcome,
+                client_tag,
+                submission_source,
+            },
+        )
+    }
+
     pub(crate) fn latest_transaction_dropped(tag: String, nonce: u64) -> Self {
         Self::new(tag, BuilderEventKind::LatestTransactionDropped { nonce })
     }
@@ -78,6 +102,24 @@ impl BuilderEvent {
         )
     }
 
+    pub(crate) fn bundle_calldata(
+        tag: String,
+        beneficiary: Address,
+        gas_limit: u64,
+        gas_fees: GasFees,
+        calldata: Bytes,
+    ) -> Self {
+        Self::new(
+            tag,
+            BuilderEventKind::BundleCalldata {
+                beneficiary,
+                gas_limit,
+                gas_fees,
+                calldata,
+            },
+        )
+    }
+
     pub(crate) fn skipped_op(tag: String, op_hash: B256, reason: SkipReason) -> Self {
         Self::new(tag, BuilderEventKind::SkippedOp { op_hash, reason })
     }
@@ -85,6 +127,99 @@ impl BuilderEvent {
     pub(crate) fn rejected_op(tag: String, op_hash: B256, reason: OpRejectionReason) -> Self {
         Self::new(tag, BuilderEventKind::RejectedOp { op_hash, reason })
     }
+
+    pub(crate) fn bundle_build_truncated(
+        tag: String,
+        max_build_duration: Duration,
+        unconsidered_ops: usize,
+    ) -> Self {
+        Self::new(
+            tag,
+            BuilderEventKind::BundleBuildTruncated {
+                max_build_duration,
+                unconsidered_ops,
+            },
+        )
+    }
+
+    pub(crate) fn aggregator_signature_failed(tag: String, aggregator: Address) -> Self {
+        Self::new(
+            tag,
+            BuilderEventKind::AggregatorSignatureFailed { aggregator },
+        )
+    }
+
+    pub(crate) fn entity_role_overlap(
+        tag: String,
+        op_hash: B256,
+        entity_infos: EntityInfos,
+    ) -> Self {
+        Self::new(
+            tag,
+            BuilderEventKind::EntityRoleOverlap {
+                op_hash,
+                entity_infos,
+            },
+        )
+    }
+
+    pub(crate) fn round_summary(
+        tag: String,
+        considered: Vec<B256>,
+        included: Vec<B256>,
+        deferred: Vec<B256>,
+        dropped: Vec<B256>,
+    ) -> Self {
+        Self::new(
+            tag,
+            BuilderEventKind::RoundSummary {
+                considered,
+                included,
+                deferred,
+                dropped,
+            },
+        )
+    }
+
+    pub(crate) fn storage_conflict(
+        tag: String,
+        kept: Option<B256>,
+        dropped: B256,
+        address: Address,
+        slot: B256,
+    ) -> Self {
+        Self::new(
+            tag,
+            BuilderEventKind::StorageConflict {
+                kept,
+                dropped,
+                address,
+                slot,
+            },
+        )
+    }
+
+    pub(crate) fn bundle_gas_attribution(tag: String, ops: Vec<(B256, GasAttribution)>) -> Self {
+        Self::new(tag, BuilderEventKind::BundleGasAttribution { ops })
+    }
+
+    pub(crate) fn cancel_containing_op(tag: String, op_hash: B256, cancelling: bool) -> Self {
+        Self::new(
+            tag,
+            BuilderEventKind::CancelContainingOp {
+                op_hash,
+                cancelling,
+            },
+        )
+    }
+
+    pub(crate) fn cancellation_resolved(tag: String, outcome: CancellationOutcome) -> Self {
+        Self::new(tag, BuilderEventKind::CancellationResolved { outcome })
+    }
+
+    pub(crate) fn idle(tag: String, block_number: u64) -> Self {
+        Self::new(tag, BuilderEventKind::Idle { block_number })
+    }
 }
 
 /// BuilderEventKind
@@ -106,6 +241,8 @@ pub enum BuilderEventKind {
     },
     /// A bundle transaction was mined
     TransactionMined {
+        /// Id of the mined bundle, `None` if the bundle was sent before a restart
+        bundle_id: Option<B256>,
         /// Transaction hash
         tx_hash: B256,
         /// Transaction nonce
@@ -113,6 +250,21 @@ pub enum BuilderEventKind {
         /// Block number containing the transaction
         block_number: u64,
     },
+    /// The outcome of an operation in a mined bundle transaction
+    MinedOp {
+        /// Id of the mined bundle
+        bundle_id: B256,
+        /// Hash of the bundle transaction
+        tx_hash: B256,
+        /// Operation hash
+        op_hash: B256,
+        /// Outcome of the operation, from the entry point's `UserOperationEvent`
+        outcome: MinedOpOutcome,
+        /// Correlation tag supplied by the client when submitting the operation
+        client_tag: Option<String>,
+        /// Origin of the operation's submission, i.e. an RPC client id
+        submission_source: Option<String>,
+    },
     /// The latest transaction was dropped
     LatestTransactionDropped {
         /// Nonce of the dropped transaction
@@ -123,6 +275,17 @@ pub enum BuilderEventKind {
         /// The used nonce
         nonce: u64,
     },
+    /// The calldata of a proposed bundle, only emitted when calldata dumping is enabled
+    BundleCalldata {
+        /// Beneficiary of the bundle
+        beneficiary: Address,
+        /// Gas limit of the bundle transaction
+        gas_limit: u64,
+        /// Gas fees of the bundle transaction
+        gas_fees: GasFees,
+        /// Calldata of the `handleOps` or `handleAggregatedOps` call
+        calldata: Bytes,
+    },
     /// An operation was skipped in the bundle
     SkippedOp {
         /// Operation hash
@@ -137,11 +300,86 @@ pub enum BuilderEventKind {
         /// Reason for rejection
         reason: OpRejectionReason,
     },
+    /// Bundle building hit its maximum duration, the bundle was proposed from the ops
+    /// simulated so far
+    BundleBuildTruncated {
+        /// The configured maximum build duration
+        max_build_duration: Duration,
+        /// Number of ops whose simulation did not finish in time, they remain in the pool
+        unconsidered_ops: usize,
+    },
+    /// How each op given to a proposal round was resolved. Every considered op is in
+    /// exactly one of `included`, `deferred` or `dropped`.
+    RoundSummary {
+        /// Hashes of the ops given to the round
+        considered: Vec<B256>,
+        /// Hashes of the ops included in the proposed bundle
+        included: Vec<B256>,
+        /// Hashes of the ops left in the pool for a later round
+        deferred: Vec<B256>,
+        /// Hashes of the ops rejected and requested to be removed from the pool
+        dropped: Vec<B256>,
+    },
+    /// An aggregator's signature validation failed in `handleOps`. Its ops were dropped
+    /// from the bundle and it was reported to the pool for a reputation penalty.
+    AggregatorSignatureFailed {
+        /// Address of the aggregator
+        aggregator: Address,
+    },
+    /// An operation uses the same address for more than one entity role, i.e. the
+    /// paymaster is also the factory. The op is still bundled.
+    EntityRoleOverlap {
+        /// Operation hash
+        op_hash: B256,
+        /// The entities of the operation
+        entity_infos: EntityInfos,
+    },
+    /// An operation expects a storage slot to hold a different value than an operation
+    /// included earlier in the bundle, so one of them would revert. The later operation
+    /// was skipped in the bundle but remains in the pool.
+    StorageConflict {
+        /// Hash of the operation earlier in the bundle that expects the other value.
+        /// `None` if that operation was itself skipped after its storage was merged.
+        kept: Option<B256>,
+        /// Hash of the skipped operation
+        dropped: B256,
+        /// Address of the contract owning the slot
+        address: Address,
+        /// The conflicting slot
+        slot: B256,
+    },
+    /// The gas used by each phase of each operation in a traced bundle, only emitted
+    /// when gas attribution is enabled
+    BundleGasAttribution {
+        /// Operation hashes and their gas attribution, in bundle order
+        ops: Vec<(B256, GasAttribution)>,
+    },
+    /// Cancellation of the pending bundle containing an operation was requested
+    CancelContainingOp {
+        /// Operation hash
+        op_hash: B256,
+        /// Whether the pending bundle contained the operation and is being cancelled.
+        /// If false, there was no such bundle and the request was ignored.
+        cancelling: bool,
+    },
+    /// A hard cancellation of a pending bundle was resolved
+    CancellationResolved {
+        /// Which transaction, if any, used the nonce of the cancelled bundle
+        outcome: CancellationOutcome,
+    },
+    /// A bundling round found no operations to bundle. Rate limited, so not every idle
+    /// round emits one.
+    Idle {
+        /// Block number the round was built on
+        block_number: u64,
+    },
 }
 
 /// Details of a bundle transaction
 #[derive(Clone, Debug)]
 pub struct BundleTxDetails {
+    /// Deterministic id of the bundle, from its op hashes and the builder's index
+    pub bundle_id: B256,
     /// Transaction hash
     pub tx_hash: B256,
     /// The transaction
@@ -150,6 +388,36 @@ pub struct BundleTxDetails {
     pub ops: Arc<Vec<(Address, B256)>>,
 }
 
+/// Outcome of an operation in a mined bundle transaction
+#[derive(Clone, Debug)]
+pub enum MinedOpOutcome {
+    /// The operation executed successfully
+    Succeeded { actual_gas_cost: U256 },
+    /// The operation's execution reverted, it was still charged
+    Reverted { actual_gas_cost: U256 },
+    /// The operation was in the bundle but the entry point emitted no event for it
+    Dropped,
+}
+
+/// How a hard cancellation of a pending bundle was resolved
+#[derive(Clone, Debug)]
+pub enum CancellationOutcome {
+    /// The cancellation transaction mined, the bundle's ops were not included
+    Cancelled { tx_hash: B256 },
+    /// The cancellation transaction mined but reverted. It still used the nonce, so the
+    /// bundle's ops were not included.
+    CancelReverted { tx_hash: B256 },
+    /// The bundle mined before the cancellation, its ops were included
+    BundleMined { tx_hash: B256 },
+    /// The cancellation transaction was dropped, the nonce is unused
+    Dropped,
+    /// A transaction not tracked by this builder used the nonce
+    NonceUsedForOtherTransaction,
+    /// The cancellation was abandoned after the maximum fee increases, with no
+    /// transaction seen to use the nonce
+    Abandoned,
+}
+
 /// Reason for skipping an operation in a bundle
 #[derive(Clone, Debug)]
 pub enum SkipReason {
@@ -167,6 +435,15 @@ pub enum SkipReason {
         required_pvg: u128,
         actual_pvg: u128,
     },
+    /// Paymaster's remaining deposit can't cover the prefund of the operation, after
+    /// accounting for the other operations it sponsors in the bundle
+    InsufficientPaymasterDeposit {
+        paymaster: Address,
+        deposit: U256,
+        required_prefund: U256,
+    },
+    /// Sender's entry point deposit and balance can't cover the prefund of the operation
+    InsufficientSenderFunds { funds: U256, required_prefund: U256 },
     /// Cost of this operation is greater than the max cost of the bundler sponsorship
     OverSponsorshipMaxCost { max_cost: U256, actual_cost: U256 },
     /// Bundle ran out of space by simulation gas limit to include the operation
@@ -177,14 +454,36 @@ pub enum SkipReason {
     MaxGasLimit,
     /// Bundle ran out of space by max bundle fee to include the operation
     OverMaxBundleFee,
-    /// Expected storage conflict
-    ExpectedStorageConflict(String),
     /// Expected storage limit reached
     ExpectedStorageLimit,
     /// Transaction size limit reached
     TransactionSizeLimit,
+    /// Bundle calldata size limit reached
+    CalldataSizeLimit,
+    /// Operation has the same sender and nonce as a different operation in the pending bundle
+    PendingNonceDuplicate { pending_op_hash: B256 },
+    /// Operation is not supported by any of the mempools the builder bundles from
+    NotInAllowedMempools { mempools: Vec<B256> },
     /// UO uses an unsupported aggregator
     UnsupportedAggregator(Address),
+    /// Bundle already has the maximum number of operations using this paymaster
+    PaymasterOpsLimit { paymaster: Address },
+    /// Bundle already has the maximum number of aggregators, and the operation uses another
+    AggregatorLimit { aggregator: Address },
+    /// Operation's summed call, verification and pre-verification gas limits are over the maximum
+    MaxOpTotalGas {
+        op_total_gas: u128,
+        max_op_total_gas: u128,
+    },
+    /// Including the operation would push the bundle's summed prefund past the maximum
+    MaxBundlePrefund {
+        bundle_prefund: U256,
+        required_prefund: U256,
+    },
+    /// Operation expires too soon to be safely included, but is still valid
+    ExpiringSoon { valid_until: Timestamp },
+    /// Operation is not valid until a later time, it is not simulated again before then
+    NotYetValid { valid_after: Timestamp },
     /// Other reason, typically internal errors
     Other { reason: Arc<String> },
 }
@@ -198,8 +497,18 @@ pub enum OpRejectionReason {
     FailedInBundle { message: Arc<String> },
     /// Operation's storage slot condition was not met
     ConditionNotMet(ConditionNotMetReason),
-    /// Current time is outside of the operation's valid time range
-    InvalidTimeRange { valid_range: ValidTimeRange },
+    /// The block timestamp is outside of the operation's valid time range
+    InvalidTimeRange {
+        valid_range: ValidTimeRange,
+        /// Timestamp of the block the time range was evaluated against
+        block_timestamp: Timestamp,
+    },
+    /// Operation was skipped for too many consecutive bundle rounds
+    MaxSkipRoundsExceeded { rounds: u64 },
+    /// Operation reverted on-chain in too many mined bundles
+    MaxOnchainRevertsExceeded { attempts: u64 },
+    /// Operation's aggregator does not implement `IAggregator`
+    NonconformingAggregator { aggregator: Address },
 }
 
 /// Reason for a condition not being met
@@ -239,6 +548,7 @@ impl Display for BuilderEvent {
                             concat!(
                                 "Bundle transaction sent!",
                                 "    Builder tag: {}",
+                                "    Bundle id: {:?}",
                                 "    Transaction hash: {:?}",
                                 "    Nonce: {}",
                                 "    Fee increases: {}",
@@ -247,6 +557,7 @@ impl Display for BuilderEvent {
                                 "    Ops: {}",
                             ),
                             self.tag,
+                            tx_details.bundle_id,
                             tx_details.tx_hash,
                             nonce,
                             fee_increase_count,
@@ -274,6 +585,7 @@ impl Display for BuilderEvent {
                 }
             }
             BuilderEventKind::TransactionMined {
+                bundle_id,
                 tx_hash,
                 nonce,
                 block_number,
@@ -282,11 +594,30 @@ impl Display for BuilderEvent {
                 concat!(
                     "Transaction mined!",
                     "    Builder tag: {}",
+                    "    Bundle id: {}",
                     "    Transaction hash: {:?}",
                     "    Nonce: {}",
                     "    Block number: {}",
                 ),
-                self.tag, tx_hash, nonce, block_number,
+                self.tag,
+                strs::to_debug_or(*bundle_id, "(unknown)"),
+                tx_hash,
+                nonce,
+                block_number,
+            ),
+            BuilderEventKind::MinedOp {
+                bundle_id,
+                tx_hash,
+                op_hash,
+                outcome,
+                client_tag,
+                submission_source,
+            } => write!(
+                f,
+                "Op mined.   Builder tag: {}    Bundle id: {bundle_id:?}    Transaction hash: {tx_hash:?}    Op hash: {op_hash:?}    Outcome: {outcome:?}    Client tag: {}    Submission source: {}",
+                self.tag,
+                client_tag.as_deref().unwrap_or("(none)"),
+                submission_source.as_deref().unwrap_or("(none)"),
             ),
             BuilderEventKind::LatestTransactionDropped { nonce } => {
                 write!(
@@ -302,6 +633,29 @@ impl Display for BuilderEvent {
                     self.tag
                 )
             }
+            BuilderEventKind::BundleCalldata {
+                beneficiary,
+                gas_limit,
+                gas_fees,
+                calldata,
+            } => write!(
+                f,
+                concat!(
+                    "Bundle calldata.",
+                    "    Builder tag: {}",
+                    "    Beneficiary: {:?}",
+                    "    Gas limit: {}",
+                    "    maxFeePerGas: {}",
+                    "    maxPriorityFeePerGas: {}",
+                    "    Calldata: {}",
+                ),
+                self.tag,
+                beneficiary,
+                gas_limit,
+                gas_fees.max_fee_per_gas,
+                gas_fees.max_priority_fee_per_gas,
+                calldata,
+            ),
             BuilderEventKind::SkippedOp { op_hash, reason } => {
                 write!(
                     f,
@@ -316,6 +670,111 @@ impl Display for BuilderEvent {
                     self.tag
                 )
             }
+            BuilderEventKind::BundleBuildTruncated {
+                max_build_duration,
+                unconsidered_ops,
+            } => {
+                write!(
+                    f,
+                    "Bundle build exceeded {max_build_duration:?}, proposing from the ops simulated so far.   Builder tag: {}    Unconsidered ops: {unconsidered_ops}",
+                    self.tag
+                )
+            }
+            BuilderEventKind::RoundSummary {
+                considered,
+                included,
+                deferred,
+                dropped,
+            } => {
+                write!(
+                    f,
+                    "Proposal round finished.   Builder tag: {}    Considered: {}    Included: {}    Deferred: {}    Dropped: {}",
+                    self.tag,
+                    considered.len(),
+                    included.len(),
+                    deferred.len(),
+                    dropped.len(),
+                )
+            }
+            BuilderEventKind::AggregatorSignatureFailed { aggregator } => {
+                write!(
+                    f,
+                    "Aggregator signature validation failed, its ops were dropped.   Builder tag: {}    Aggregator: {aggregator:?}",
+                    self.tag
+                )
+            }
+            BuilderEventKind::EntityRoleOverlap {
+                op_hash,
+                entity_infos,
+            } => {
+                write!(
+                    f,
+                    "Op uses the same address for multiple entity roles.   Builder tag: {}    Op hash: {op_hash:?}    Entities: {entity_infos:?}",
+                    self.tag
+                )
+            }
+            BuilderEventKind::StorageConflict {
+                kept,
+                dropped,
+                address,
+                slot,
+            } => {
+                write!(
+                    f,
+                    "Op skipped in bundle (but remains in pool) due to a storage conflict with an earlier op.   Builder tag: {}    Op hash: {dropped:?}    Conflicting op hash: {}    Address: {address:?}    Slot: {slot:?}",
+                    self.tag,
+                    strs::to_debug_or(*kept, "(unknown)"),
+                )
+            }
+            BuilderEventKind::BundleGasAttribution { ops } => {
+                let ops = ops
+                    .iter()
+                    .map(|(hash, gas)| {
+                        format!(
+                            "(hash: {hash:?} validation: {} execution: {} postOp: {})",
+                            gas.validation_gas, gas.execution_gas, gas.post_op_gas
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    "Bundle gas attribution.   Builder tag: {}    Ops: {ops}",
+                    self.tag
+                )
+            }
+            BuilderEventKind::CancelContainingOp {
+                op_hash,
+                cancelling,
+            } => {
+                if *cancelling {
+                    write!(
+                        f,
+                        "Cancelling pending bundle containing op.   Builder tag: {}    Op hash: {op_hash:?}",
+                        self.tag
+                    )
+                } else {
+                    write!(
+                        f,
+                        "No pending bundle contains op, cancellation ignored.   Builder tag: {}    Op hash: {op_hash:?}",
+                        self.tag
+                    )
+                }
+            }
+            BuilderEventKind::CancellationResolved { outcome } => {
+                write!(
+                    f,
+                    "Cancellation resolved.   Builder tag: {}    Outcome: {outcome:?}",
+                    self.tag
+                )
+            }
+            BuilderEventKind::Idle { block_number } => {
+                write!(
+                    f,
+                    "No operations to bundle.   Builder tag: {}    Block number: {block_number}",
+                    self.tag
+                )
+            }
         }
     }
 }
@@ -0,0 +1,160 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::collections::VecDeque;
+
+use rundler_types::PriorityFeeMode;
+
+/// Settings of the priority fee controller
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Settings {
+    /// Lowest priority fee increase percent the controller relaxes to, and starts at
+    pub(crate) min_percent: u32,
+    /// Highest priority fee increase percent the controller raises to
+    pub(crate) max_percent: u32,
+    /// Amount the percent is raised or lowered by in a single adjustment
+    pub(crate) step_percent: u32,
+    /// Inclusion latency, in blocks from first send to mine, at or above which a bundle
+    /// counts as slow
+    pub(crate) slow_inclusion_blocks: u64,
+    /// Number of consecutive bundles that must all be slow, or all fast, for an adjustment
+    pub(crate) window: usize,
+}
+
+/// Closed-loop controller over the priority fee mode required of ops.
+///
+/// Raises the required priority fee increase percent by a step when the last `window`
+/// bundles were all slow to mine, and lowers it by a step when they were all fast, within
+/// the configured bounds. The window starts over after each adjustment, so the effect of
+/// one adjustment is observed before the next.
+#[derive(Debug)]
+pub(crate) struct PriorityFeeController {
+    settings: Settings,
+    percent: u32,
+    latencies: VecDeque<u64>,
+}
+
+impl PriorityFeeController {
+    pub(crate) fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            percent: settings.min_percent,
+            latencies: VecDeque::with_capacity(settings.window),
+        }
+    }
+
+    /// The priority fee mode currently required of ops
+    pub(crate) fn mode(&self) -> PriorityFeeMode {
+        PriorityFeeMode::PriorityFeeIncreasePercent(self.percent)
+    }
+
+    /// Records the inclusion latency of a mined bundle, returning the new mode if it
+    /// changed
+    pub(crate) fn record_inclusion(&mut self, latency_blocks: u64) -> Option<PriorityFeeMode> {
+        if self.latencies.len() == self.settings.window {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency_blocks);
+        if self.latencies.len() < self.settings.window {
+            return None;
+        }
+
+        let is_slow = |latency: &u64| *latency >= self.settings.slow_inclusion_blocks;
+        let percent = if self.latencies.iter().all(is_slow) {
+            self.percent
+                .saturating_add(self.settings.step_percent)
+                .min(self.settings.max_percent)
+        } else if !self.latencies.iter().any(is_slow) {
+            self.percent
+                .saturating_sub(self.settings.step_percent)
+                .max(self.settings.min_percent)
+        } else {
+            return None;
+        };
+
+        self.latencies.clear();
+        if percent == self.percent {
+            return None;
+        }
+        self.percent = percent;
+        Some(self.mode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller() -> PriorityFeeController {
+        PriorityFeeController::new(Settings {
+            min_percent: 10,
+            max_percent: 30,
+            step_percent: 10,
+            slow_inclusion_blocks: 3,
+            window: 2,
+        })
+    }
+
+    #[test]
+    fn test_slow_then_fast_inclusion() {
+        let mut controller = controller();
+        assert_eq!(
+            controller.mode(),
+            PriorityFeeMode::PriorityFeeIncreasePercent(10)
+        );
+
+        // slow inclusions raise the percent a step per window, up to the max
+        let slow = [5, 4, 3, 6, 5, 7];
+        let modes = slow
+            .into_iter()
+            .map(|latency| controller.record_inclusion(latency))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            modes,
+            vec![
+                None,
+                Some(PriorityFeeMode::PriorityFeeIncreasePercent(20)),
+                None,
+                Some(PriorityFeeMode::PriorityFeeIncreasePercent(30)),
+                None,
+                None,
+            ]
+        );
+
+        // a mixed window holds the percent
+        assert_eq!(controller.record_inclusion(1), None);
+        assert_eq!(controller.record_inclusion(4), None);
+
+        // fast inclusions relax it a step per window, down to the min
+        let fast = [1, 2, 1, 1, 2, 1];
+        let modes = fast
+            .into_iter()
+            .map(|latency| controller.record_inclusion(latency))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            modes,
+            vec![
+                None,
+                Some(PriorityFeeMode::PriorityFeeIncreasePercent(20)),
+                None,
+                Some(PriorityFeeMode::PriorityFeeIncreasePercent(10)),
+                None,
+                None,
+            ]
+        );
+        assert_eq!(
+            controller.mode(),
+            PriorityFeeMode::PriorityFeeIncreasePercent(10)
+        );
+    }
+}
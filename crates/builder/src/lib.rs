@@ -20,16 +20,30 @@
 //! Bundle builder implementation for the Rundler.
 
 mod assigner;
+
+mod audit;
+pub use audit::{BundleAuditRecord, BundleAuditSink, InMemoryBundleAuditSink, NoopBundleAuditSink};
+
 mod bundle_proposer;
+pub use bundle_proposer::SelectionStrategy;
 mod bundle_sender;
 
+mod confirmation_poller;
+
 mod emit;
 pub use emit::{BuilderEvent, BuilderEventKind};
 
+mod fee_controller;
+
+mod nonce_manager;
+
+mod price_oracle;
+pub use price_oracle::TokenPriceOracle;
+
 mod sender;
 pub use sender::{
-    BloxrouteSenderArgs, FlashbotsSenderArgs, RawSenderArgs, TransactionSenderArgs,
-    TransactionSenderKind,
+    BloxrouteSenderArgs, FlashbotsSenderArgs, RawSenderArgs, SequencerSenderArgs,
+    TransactionSenderArgs, TransactionSenderKind,
 };
 
 mod server;
@@ -38,4 +52,10 @@ pub use server::{LocalBuilderBuilder, LocalBuilderHandle, RemoteBuilderClient};
 mod task;
 pub use task::{Args as BuilderTaskArgs, BuilderSettings, BuilderTask, EntryPointBuilderSettings};
 
+mod tip;
+pub use tip::{TipAmount, TipTransfer};
+
 mod transaction_tracker;
+
+mod transformer;
+pub use transformer::{NoopTransactionTransformer, TransactionTransformer};
@@ -0,0 +1,246 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use alloy_primitives::Address;
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use redis::{aio::MultiplexedConnection, Script};
+use rundler_utils::random;
+use tracing::info;
+
+/// Maximum number of nonces past the on-chain nonce to try to reserve before giving up
+const MAX_RESERVATION_ATTEMPTS: u64 = 32;
+
+// Reserves KEYS[1] for the owner ARGV[1] for ARGV[2] milliseconds if it is free or
+// already held by the owner. Returns 1 if the owner holds the reservation afterward.
+const RESERVE_SCRIPT: &str = r#"
+local owner = redis.call('GET', KEYS[1])
+if not owner then
+    redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+    return 1
+elseif owner == ARGV[1] then
+    redis.call('PEXPIRE', KEYS[1], ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+/// Decides the nonce a builder's signer should use for its next transaction
+#[async_trait]
+pub(crate) trait NonceManager: Send + Sync + Debug {
+    /// Returns the nonce to use for the next transaction from `address`.
+    ///
+    /// `onchain_nonce` is the address's current transaction count, the returned
+    /// nonce is never lower.
+    async fn next_nonce(&self, address: Address, onchain_nonce: u64) -> anyhow::Result<u64>;
+}
+
+/// Uses the on-chain transaction count as the next nonce.
+///
+/// This is racy if the signer's key is also used to send transactions outside of
+/// this builder.
+#[derive(Debug, Default)]
+pub(crate) struct OnChainNonceManager;
+
+#[async_trait]
+impl NonceManager for OnChainNonceManager {
+    async fn next_nonce(&self, _address: Address, onchain_nonce: u64) -> anyhow::Result<u64> {
+        Ok(onchain_nonce)
+    }
+}
+
+/// Atomic storage for nonce reservations shared between processes
+#[async_trait]
+pub(crate) trait ReservationStore: Send + Sync + Debug {
+    /// Reserves `key` for `owner` if it is free or already held by `owner`, refreshing
+    /// the reservation's TTL. Returns true if `owner` holds the reservation afterward.
+    async fn try_reserve(&self, key: &str, owner: &str, ttl_millis: u64) -> anyhow::Result<bool>;
+}
+
+/// Reserves nonces in a shared store so that processes sharing a signer never use
+/// the same nonce.
+///
+/// Reservations are owned by the manager that made them and expire after a TTL.
+/// Asking again for a nonce that this manager already holds refreshes it, so a
+/// tracker reset does not skip a nonce it has reserved but not yet mined.
+#[derive(Debug)]
+pub(crate) struct ReservingNonceManager<S> {
+    store: S,
+    chain_id: u64,
+    owner: String,
+    ttl_millis: u64,
+}
+
+impl<S> ReservingNonceManager<S> {
+    pub(crate) fn new(store: S, chain_id: u64, ttl_millis: u64) -> Self {
+        Self {
+            store,
+            chain_id,
+            owner: random::random_bytes(16).to_string(),
+            ttl_millis,
+        }
+    }
+
+    fn key(&self, address: Address, nonce: u64) -> String {
+        format!("rundler:nonce:{}:{address:?}:{nonce}", self.chain_id)
+    }
+}
+
+#[async_trait]
+impl<S: ReservationStore> NonceManager for ReservingNonceManager<S> {
+    async fn next_nonce(&self, address: Address, onchain_nonce: u64) -> anyhow::Result<u64> {
+        for nonce in onchain_nonce..onchain_nonce + MAX_RESERVATION_ATTEMPTS {
+            if self
+                .store
+                .try_reserve(&self.key(address, nonce), &self.owner, self.ttl_millis)
+                .await?
+            {
+                if nonce != onchain_nonce {
+                    info!("Nonces {onchain_nonce}..{nonce} for {address:?} are reserved by other builders, using {nonce}");
+                }
+                return Ok(nonce);
+            }
+        }
+        bail!(
+            "could not reserve a nonce for {address:?} within {MAX_RESERVATION_ATTEMPTS} of on-chain nonce {onchain_nonce}"
+        )
+    }
+}
+
+/// Redis backed reservation store
+#[derive(Clone)]
+pub(crate) struct RedisReservationStore {
+    connection: MultiplexedConnection,
+    script: Script,
+}
+
+impl Debug for RedisReservationStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisReservationStore")
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisReservationStore {
+    pub(crate) fn new(connection: MultiplexedConnection) -> Self {
+        Self {
+            connection,
+            script: Script::new(RESERVE_SCRIPT),
+        }
+    }
+}
+
+#[async_trait]
+impl ReservationStore for RedisReservationStore {
+    async fn try_reserve(&self, key: &str, owner: &str, ttl_millis: u64) -> anyhow::Result<bool> {
+        let mut connection = self.connection.clone();
+        let reserved: i64 = self
+            .script
+            .key(key)
+            .arg(owner)
+            .arg(ttl_millis)
+            .invoke_async(&mut connection)
+            .await
+            .context("should run nonce reservation script")?;
+        Ok(reserved == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+    };
+
+    use futures::future;
+    use tokio::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Clone, Debug, Default)]
+    struct MemoryStore(Arc<Mutex<HashMap<String, String>>>);
+
+    #[async_trait]
+    impl ReservationStore for MemoryStore {
+        async fn try_reserve(
+            &self,
+            key: &str,
+            owner: &str,
+            _ttl_millis: u64,
+        ) -> anyhow::Result<bool> {
+            // yield before taking the lock so concurrent callers interleave
+            tokio::task::yield_now().await;
+            let mut reservations = self.0.lock().await;
+            let holder = reservations
+                .entry(key.to_string())
+                .or_insert_with(|| owner.to_string());
+            Ok(holder == owner)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_on_chain() {
+        let manager = OnChainNonceManager;
+        assert_eq!(manager.next_nonce(Address::ZERO, 7).await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_is_stable_for_owner() {
+        let manager = ReservingNonceManager::new(MemoryStore::default(), 1, 1000);
+        assert_eq!(manager.next_nonce(Address::ZERO, 3).await.unwrap(), 3);
+        assert_eq!(manager.next_nonce(Address::ZERO, 3).await.unwrap(), 3);
+        assert_eq!(manager.next_nonce(Address::ZERO, 4).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_skips_other_owners() {
+        let store = MemoryStore::default();
+        let first = ReservingNonceManager::new(store.clone(), 1, 1000);
+        let second = ReservingNonceManager::new(store, 1, 1000);
+
+        assert_eq!(first.next_nonce(Address::ZERO, 3).await.unwrap(), 3);
+        assert_eq!(second.next_nonce(Address::ZERO, 3).await.unwrap(), 4);
+        // reservations are per address
+        assert_eq!(second.next_nonce(Address::random(), 3).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_concurrent_callers() {
+        let store = MemoryStore::default();
+        let managers = (0..10)
+            .map(|_| ReservingNonceManager::new(store.clone(), 1, 1000))
+            .collect::<Vec<_>>();
+
+        let nonces = future::try_join_all(managers.iter().map(|m| m.next_nonce(Address::ZERO, 5)))
+            .await
+            .unwrap();
+
+        let unique = nonces.iter().copied().collect::<HashSet<_>>();
+        assert_eq!(unique, (5..15).collect::<HashSet<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_reserve_exhausted() {
+        let store = MemoryStore::default();
+        let first = ReservingNonceManager::new(store.clone(), 1, 1000);
+        for nonce in 0..MAX_RESERVATION_ATTEMPTS {
+            first.next_nonce(Address::ZERO, nonce).await.unwrap();
+        }
+
+        let second = ReservingNonceManager::new(store, 1, 1000);
+        assert!(second.next_nonce(Address::ZERO, 0).await.is_err());
+    }
+}
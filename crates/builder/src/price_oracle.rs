@@ -0,0 +1,27 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use rundler_types::UserOperationVariant;
+
+/// Values the ops of an ERC-20 paymaster in ETH, for ranking them against other ops.
+///
+/// Token paymasters quote their fees in tokens, so the ETH gas price of their ops
+/// doesn't reflect what they are worth. An oracle is registered per paymaster, and is
+/// only used by selection strategies that rank ops by gas price.
+pub trait TokenPriceOracle: Send + Sync + Debug {
+    /// The ETH-equivalent effective gas price of an op sponsored by the paymaster at the
+    /// given base fee, or `None` to rank the op by its ETH gas price
+    fn eth_gas_price(&self, op: &UserOperationVariant, base_fee: u128) -> Option<u128>;
+}
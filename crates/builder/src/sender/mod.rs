@@ -14,6 +14,7 @@
 mod bloxroute;
 mod flashbots;
 mod raw;
+mod sequencer;
 
 use alloy_primitives::{Address, B256};
 pub(crate) use bloxroute::PolygonBloxrouteTransactionSender;
@@ -26,6 +27,7 @@ use rundler_provider::{EvmProvider, ProviderError, TransactionRequest};
 use rundler_signer::SignerLease;
 use rundler_types::{ExpectedStorage, GasFees};
 use secrecy::SecretString;
+pub(crate) use sequencer::SequencerTransactionSender;
 
 #[derive(Debug)]
 pub(crate) struct CancelTxInfo {
@@ -35,6 +37,19 @@ pub(crate) struct CancelTxInfo {
     pub(crate) soft_cancelled: bool,
 }
 
+/// Inclusion status of a transaction, as reported by the endpoint it was sent to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum InclusionStatus {
+    /// The sender can't report inclusion, so it is detected from chain updates
+    Unknown,
+    /// The transaction has been accepted but not yet included
+    Pending,
+    /// The transaction was included in a block
+    Included { block_number: u64, is_success: bool },
+    /// The transaction was rejected and will not be included
+    Rejected { reason: String },
+}
+
 /// Errors from transaction senders
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum TxSenderError {
@@ -87,6 +102,13 @@ pub(crate) trait TransactionSender: Send + Sync {
         gas_fees: GasFees,
         signer: &SignerLease,
     ) -> Result<CancelTxInfo>;
+
+    /// Returns the inclusion status of a transaction sent by this sender. Only senders whose
+    /// endpoint confirms inclusion itself, rather than through standard receipts, report
+    /// anything other than `Unknown`.
+    async fn get_inclusion_status(&self, _tx_hash: B256) -> Result<InclusionStatus> {
+        Ok(InclusionStatus::Unknown)
+    }
 }
 
 #[enum_dispatch(TransactionSender)]
@@ -95,6 +117,7 @@ pub(crate) enum TransactionSenderEnum<P: EvmProvider> {
     Raw(RawTransactionSender<P>),
     Flashbots(FlashbotsTransactionSender),
     PolygonBloxroute(PolygonBloxrouteTransactionSender<P>),
+    Sequencer(SequencerTransactionSender),
 }
 
 /// Transaction sender types
@@ -107,6 +130,8 @@ pub enum TransactionSenderKind {
     Flashbots,
     /// Bloxroute transaction sender
     Bloxroute,
+    /// Sequencer private endpoint transaction sender
+    Sequencer,
 }
 
 /// Transaction sender types
@@ -118,6 +143,8 @@ pub enum TransactionSenderArgs {
     Flashbots(FlashbotsSenderArgs),
     /// Bloxroute transaction sender
     Bloxroute(BloxrouteSenderArgs),
+    /// Sequencer private endpoint transaction sender
+    Sequencer(SequencerSenderArgs),
 }
 
 /// Raw sender arguments
@@ -125,6 +152,9 @@ pub enum TransactionSenderArgs {
 pub struct RawSenderArgs {
     /// Submit URL
     pub submit_url: String,
+    /// Extra URLs the signed transaction is broadcast to alongside the submit URL.
+    /// Inclusion is still tracked through the submit URL.
+    pub broadcast_urls: Vec<String>,
     /// If the sender should use the conditional endpoint
     pub use_conditional_rpc: bool,
 }
@@ -136,6 +166,15 @@ pub struct BloxrouteSenderArgs {
     pub header: SecretString,
 }
 
+/// Sequencer sender arguments
+#[derive(Debug, Clone)]
+pub struct SequencerSenderArgs {
+    /// URL of the sequencer's private endpoint
+    pub submit_url: String,
+    /// JSON-RPC method of the sequencer endpoint that reports a transaction's inclusion
+    pub inclusion_method: String,
+}
+
 /// Flashbots sender arguments
 #[derive(Debug, Clone)]
 pub struct FlashbotsSenderArgs {
@@ -161,9 +200,20 @@ impl TransactionSenderArgs {
                     &args.submit_url,
                     provider_client_timeout_seconds,
                 )?;
+                let broadcasters = args
+                    .broadcast_urls
+                    .iter()
+                    .map(|url| {
+                        rundler_provider::new_alloy_evm_provider(
+                            url,
+                            provider_client_timeout_seconds,
+                        )
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
 
                 TransactionSenderEnum::Raw(RawTransactionSender::new(
                     submitter,
+                    broadcasters,
                     args.use_conditional_rpc,
                 ))
             }
@@ -173,6 +223,9 @@ impl TransactionSenderArgs {
             Self::Bloxroute(args) => TransactionSenderEnum::PolygonBloxroute(
                 PolygonBloxrouteTransactionSender::new(provider, &args.header)?,
             ),
+            Self::Sequencer(args) => TransactionSenderEnum::Sequencer(
+                SequencerTransactionSender::new(&args.submit_url, args.inclusion_method)?,
+            ),
         };
         Ok(sender)
     }
@@ -189,7 +242,11 @@ pub(crate) enum SenderConstructorErrors {
     Other(#[from] anyhow::Error),
 }
 
-fn create_hard_cancel_tx(to: Address, nonce: u64, gas_fees: GasFees) -> TransactionRequest {
+pub(crate) fn create_hard_cancel_tx(
+    to: Address,
+    nonce: u64,
+    gas_fees: GasFees,
+) -> TransactionRequest {
     TransactionRequest::default()
         .to(to)
         .nonce(nonce)
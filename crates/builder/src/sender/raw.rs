@@ -11,13 +11,17 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::B256;
+use std::iter;
+
+use alloy_primitives::{Bytes, B256};
 use anyhow::Context;
 use async_trait::async_trait;
-use rundler_provider::{EvmProvider, TransactionRequest};
+use futures_util::future;
+use rundler_provider::{EvmProvider, ProviderError, ProviderResult, TransactionRequest};
 use rundler_signer::SignerLease;
 use rundler_types::{ExpectedStorage, GasFees};
 use serde_json::json;
+use tracing::warn;
 
 use super::{CancelTxInfo, Result};
 use crate::sender::{create_hard_cancel_tx, TransactionSender};
@@ -25,6 +29,8 @@ use crate::sender::{create_hard_cancel_tx, TransactionSender};
 #[derive(Debug)]
 pub(crate) struct RawTransactionSender<P> {
     submit_provider: P,
+    // Extra endpoints the signed transaction is also sent to, for propagation
+    broadcast_providers: Vec<P>,
     use_conditional_rpc: bool,
 }
 
@@ -44,18 +50,8 @@ where
             .await
             .context("failed to sign transaction")?;
 
-        let tx_hash = if self.use_conditional_rpc {
-            self.submit_provider
-                .request(
-                    "eth_sendRawTransactionConditional",
-                    (raw_tx, json!({ "knownAccounts": expected_storage })),
-                )
-                .await?
-        } else {
-            self.submit_provider.send_raw_transaction(raw_tx).await?
-        };
-
-        Ok(tx_hash)
+        self.broadcast(raw_tx, self.use_conditional_rpc.then_some(expected_storage))
+            .await
     }
 
     async fn cancel_transaction(
@@ -72,7 +68,7 @@ where
             .await
             .context("failed to sign transaction")?;
 
-        let tx_hash = self.submit_provider.send_raw_transaction(raw_tx).await?;
+        let tx_hash = self.broadcast(raw_tx, None).await?;
 
         Ok(CancelTxInfo {
             tx_hash,
@@ -81,11 +77,151 @@ where
     }
 }
 
-impl<P> RawTransactionSender<P> {
-    pub(crate) fn new(submit_provider: P, use_conditional_rpc: bool) -> Self {
+impl<P> RawTransactionSender<P>
+where
+    P: EvmProvider,
+{
+    pub(crate) fn new(
+        submit_provider: P,
+        broadcast_providers: Vec<P>,
+        use_conditional_rpc: bool,
+    ) -> Self {
         Self {
             submit_provider,
+            broadcast_providers,
             use_conditional_rpc,
         }
     }
+
+    // Sends the transaction to the submit endpoint and every broadcast endpoint concurrently.
+    // Returns the first successful hash, preferring the submit endpoint, or the submit
+    // endpoint's error if none succeeded.
+    async fn broadcast(
+        &self,
+        raw_tx: Bytes,
+        expected_storage: Option<&ExpectedStorage>,
+    ) -> Result<B256> {
+        let mut results = future::join_all(
+            iter::once(&self.submit_provider)
+                .chain(&self.broadcast_providers)
+                .map(|provider| Self::submit(provider, raw_tx.clone(), expected_storage)),
+        )
+        .await
+        .into_iter();
+
+        let submit_result = results
+            .next()
+            .expect("should have the submit endpoint result");
+        if submit_result.is_ok() {
+            return Ok(submit_result?);
+        }
+        for result in results {
+            match result {
+                Ok(tx_hash) => return Ok(tx_hash),
+                // the transaction already reached that endpoint, i.e. through propagation
+                Err(e) if is_already_known(&e) => {}
+                Err(e) => warn!("Failed to broadcast transaction: {e:?}"),
+            }
+        }
+        Ok(submit_result?)
+    }
+
+    async fn submit(
+        provider: &P,
+        raw_tx: Bytes,
+        expected_storage: Option<&ExpectedStorage>,
+    ) -> ProviderResult<B256> {
+        match expected_storage {
+            Some(expected_storage) => {
+                provider
+                    .request(
+                        "eth_sendRawTransactionConditional",
+                        (raw_tx, json!({ "knownAccounts": expected_storage })),
+                    )
+                    .await
+            }
+            None => provider.send_raw_transaction(raw_tx).await,
+        }
+    }
+}
+
+fn is_already_known(error: &ProviderError) -> bool {
+    let ProviderError::RPC(error) = error else {
+        return false;
+    };
+    error
+        .as_error_resp()
+        .is_some_and(|e| e.message.to_lowercase().contains("already known"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_json_rpc::ErrorPayload;
+    use alloy_primitives::Address;
+    use alloy_signer_local::PrivateKeySigner;
+    use alloy_transport::{TransportError, TransportErrorKind};
+    use rundler_provider::MockEvmProvider;
+
+    use super::*;
+
+    fn provider(result: fn() -> ProviderResult<B256>) -> MockEvmProvider {
+        let mut provider = MockEvmProvider::new();
+        provider
+            .expect_send_raw_transaction()
+            .times(1)
+            .returning(move |_| result());
+        provider
+    }
+
+    async fn send(submit: MockEvmProvider, broadcast: Vec<MockEvmProvider>) -> Result<B256> {
+        let sender = RawTransactionSender::new(submit, broadcast, false);
+        let lease = SignerLease::new(Arc::new(PrivateKeySigner::random()), 1);
+        let tx = create_hard_cancel_tx(
+            Address::ZERO,
+            0,
+            GasFees {
+                max_fee_per_gas: 1,
+                max_priority_fee_per_gas: 1,
+            },
+        );
+        sender
+            .send_transaction(tx, &ExpectedStorage::default(), &lease)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_failing_submit_endpoint() {
+        let tx_hash = send(
+            provider(|| {
+                Err(ProviderError::RPC(TransportErrorKind::custom_str(
+                    "connection refused",
+                )))
+            }),
+            vec![provider(|| Ok(B256::repeat_byte(1)))],
+        )
+        .await
+        .unwrap();
+        assert_eq!(tx_hash, B256::repeat_byte(1));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_ignores_already_known() {
+        let tx_hash = send(
+            provider(|| Ok(B256::repeat_byte(1))),
+            vec![provider(|| {
+                Err(ProviderError::RPC(TransportError::ErrorResp(
+                    ErrorPayload {
+                        code: -32000,
+                        message: "already known".into(),
+                        data: None,
+                    },
+                )))
+            })],
+        )
+        .await
+        .unwrap();
+        assert_eq!(tx_hash, B256::repeat_byte(1));
+    }
 }
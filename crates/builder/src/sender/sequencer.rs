@@ -0,0 +1,162 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Bytes, B256, U64};
+use anyhow::Context;
+use jsonrpsee::{
+    core::{client::ClientT, traits::ToRpcParams, ClientError},
+    http_client::{transport::HttpBackend, HttpClient, HttpClientBuilder},
+};
+use rundler_provider::TransactionRequest;
+use rundler_signer::SignerLease;
+use rundler_types::{ExpectedStorage, GasFees};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use tonic::async_trait;
+use tracing::warn;
+
+use super::{
+    create_hard_cancel_tx, parse_known_call_execution_failed, CancelTxInfo, InclusionStatus,
+    Result, TransactionSender, TxSenderError,
+};
+
+/// Sends transactions to a centralized sequencer's private endpoint and confirms their
+/// inclusion through the sequencer's own inclusion method rather than receipts.
+pub(crate) struct SequencerTransactionSender {
+    client: HttpClient<HttpBackend>,
+    inclusion_method: String,
+}
+
+#[async_trait]
+impl TransactionSender for SequencerTransactionSender {
+    async fn send_transaction(
+        &self,
+        tx: TransactionRequest,
+        _expected_storage: &ExpectedStorage,
+        signer: &SignerLease,
+    ) -> Result<B256> {
+        let raw_tx = signer
+            .sign_tx_raw(tx)
+            .await
+            .context("failed to sign transaction")?;
+        self.send_raw_transaction(raw_tx).await
+    }
+
+    async fn cancel_transaction(
+        &self,
+        _tx_hash: B256,
+        nonce: u64,
+        gas_fees: GasFees,
+        signer: &SignerLease,
+    ) -> Result<CancelTxInfo> {
+        // The sequencer is the only path to inclusion, so the cancellation goes through it too
+        let tx = create_hard_cancel_tx(signer.address(), nonce, gas_fees);
+
+        let raw_tx = signer
+            .sign_tx_raw(tx)
+            .await
+            .context("failed to sign transaction")?;
+        let tx_hash = self.send_raw_transaction(raw_tx).await?;
+
+        Ok(CancelTxInfo {
+            tx_hash,
+            soft_cancelled: false,
+        })
+    }
+
+    async fn get_inclusion_status(&self, tx_hash: B256) -> Result<InclusionStatus> {
+        let response: Option<SequencerInclusionResponse> = self
+            .client
+            .request(&self.inclusion_method, Params((tx_hash,)))
+            .await?;
+        // the sequencer may not know about a transaction it has just accepted
+        Ok(response.map_or(InclusionStatus::Unknown, Into::into))
+    }
+}
+
+impl SequencerTransactionSender {
+    pub(crate) fn new(submit_url: &str, inclusion_method: String) -> Result<Self> {
+        let client = HttpClientBuilder::default()
+            .build(submit_url)
+            .context("failed to create sequencer client")?;
+        Ok(Self {
+            client,
+            inclusion_method,
+        })
+    }
+
+    async fn send_raw_transaction(&self, raw_tx: Bytes) -> Result<B256> {
+        self.client
+            .request("eth_sendRawTransaction", Params((raw_tx,)))
+            .await
+            .map_err(|e| {
+                // Any call error not known to be retryable with higher fees is the sequencer
+                // rejecting the transaction, which is retried with a new bundle
+                if let ClientError::Call(call) = &e {
+                    if parse_known_call_execution_failed(call.message(), call.code() as i64)
+                        .is_none()
+                    {
+                        warn!("Sequencer rejected transaction: {call:?}");
+                        return TxSenderError::Rejected;
+                    }
+                }
+                e.into()
+            })
+    }
+}
+
+// Positional JSON-RPC params
+struct Params<T>(T);
+
+impl<T: Serialize> ToRpcParams for Params<T> {
+    fn to_rpc_params(self) -> std::result::Result<Option<Box<RawValue>>, serde_json::Error> {
+        serde_json::value::to_raw_value(&self.0).map(Some)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SequencerInclusionResponse {
+    status: SequencerInclusionStatus,
+    block_number: Option<U64>,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum SequencerInclusionStatus {
+    Pending,
+    Included,
+    Reverted,
+    Rejected,
+}
+
+impl From<SequencerInclusionResponse> for InclusionStatus {
+    fn from(value: SequencerInclusionResponse) -> Self {
+        let block_number = value.block_number.map_or(0, |b| b.to());
+        match value.status {
+            SequencerInclusionStatus::Pending => InclusionStatus::Pending,
+            SequencerInclusionStatus::Included => InclusionStatus::Included {
+                block_number,
+                is_success: true,
+            },
+            SequencerInclusionStatus::Reverted => InclusionStatus::Included {
+                block_number,
+                is_success: false,
+            },
+            SequencerInclusionStatus::Rejected => InclusionStatus::Rejected {
+                reason: value.reason.unwrap_or_default(),
+            },
+        }
+    }
+}
@@ -28,10 +28,10 @@ use rundler_task::{
     GracefulShutdown,
 };
 use rundler_types::{
-    builder::{Builder, BuilderError, BuilderResult, BundlingMode},
+    builder::{Builder, BuilderError, BuilderResult, BundlingMode, SignerStatus},
     pool::Pool,
 };
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, watch};
 
 use crate::bundle_sender::{BundleSenderAction, SendBundleRequest, SendBundleResult};
 
@@ -78,12 +78,14 @@ impl LocalBuilderBuilder {
     pub fn run(
         self,
         bundle_sender_actions: Vec<mpsc::Sender<BundleSenderAction>>,
+        signer_statuses: Vec<watch::Receiver<SignerStatus>>,
         entry_points: Vec<Address>,
         shutdown: GracefulShutdown,
     ) -> BoxFuture<'static, ()> {
         let runner = LocalBuilderServerRunner::new(
             self.req_receiver,
             bundle_sender_actions,
+            signer_statuses,
             entry_points,
             self.signer_manager,
             self.pool,
@@ -102,6 +104,7 @@ pub struct LocalBuilderHandle {
 struct LocalBuilderServerRunner {
     req_receiver: mpsc::Receiver<ServerRequest>,
     bundle_sender_actions: Vec<mpsc::Sender<BundleSenderAction>>,
+    signer_statuses: Vec<watch::Receiver<SignerStatus>>,
     entry_points: Vec<Address>,
     signer_manager: Arc<dyn SignerManager>,
     pool: Arc<dyn Pool>,
@@ -166,6 +169,24 @@ impl Builder for LocalBuilderHandle {
             _ => Err(BuilderError::UnexpectedResponse),
         }
     }
+
+    async fn get_signer_statuses(&self) -> BuilderResult<Vec<SignerStatus>> {
+        let req = ServerRequestKind::GetSignerStatuses;
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetSignerStatuses { statuses } => Ok(statuses),
+            _ => Err(BuilderError::UnexpectedResponse),
+        }
+    }
+
+    async fn debug_cancel_bundle_containing(&self, op_hash: B256) -> BuilderResult<()> {
+        let req = ServerRequestKind::DebugCancelBundleContaining { op_hash };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::DebugCancelBundleContaining => Ok(()),
+            _ => Err(BuilderError::UnexpectedResponse),
+        }
+    }
 }
 
 #[async_trait]
@@ -196,6 +217,7 @@ impl LocalBuilderServerRunner {
     fn new(
         req_receiver: mpsc::Receiver<ServerRequest>,
         bundle_sender_actions: Vec<mpsc::Sender<BundleSenderAction>>,
+        signer_statuses: Vec<watch::Receiver<SignerStatus>>,
         entry_points: Vec<Address>,
         signer_manager: Arc<dyn SignerManager>,
         pool: Arc<dyn Pool>,
@@ -203,6 +225,7 @@ impl LocalBuilderServerRunner {
         Self {
             req_receiver,
             bundle_sender_actions,
+            signer_statuses,
             entry_points,
             signer_manager,
             pool,
@@ -231,62 +254,128 @@ impl LocalBuilderServerRunner {
                     self.signer_manager.update_balances(balances);
                 }
                 Some(req) = self.req_receiver.recv() => {
-                    let resp: BuilderResult<ServerResponse> = 'a:  {
-                        match req.request {
-                            ServerRequestKind::GetSupportedEntryPoints => {
-                                Ok(ServerResponse::GetSupportedEntryPoints {
-                                    entry_points: self.entry_points.clone()
-                                })
-                            },
-                            ServerRequestKind::DebugSendBundleNow => {
-                                if self.bundle_sender_actions.len() != 1 {
-                                    break 'a Err(anyhow::anyhow!("more than 1 bundle builder not supported in debug mode").into())
-                                }
-
-                                let (tx, rx) = oneshot::channel();
-                                match self.bundle_sender_actions[0].send(BundleSenderAction::SendBundle(SendBundleRequest{
-                                    responder: tx
-                                })).await {
-                                    Ok(()) => {},
-                                    Err(e) => break 'a Err(anyhow::anyhow!("failed to send send bundle request: {}", e.to_string()).into())
-                                }
-
-                                let result = match rx.await {
-                                    Ok(result) => result,
-                                    Err(e) => break 'a Err(anyhow::anyhow!("failed to receive bundle result: {e:?}").into())
-                                };
-
-                                match result {
-                                    SendBundleResult::Success { tx_hash, block_number, .. } => {
-                                        Ok(ServerResponse::DebugSendBundleNow { hash: tx_hash, block_number })
-                                    },
-                                    SendBundleResult::NoOperationsInitially => {
-                                        Err(anyhow::anyhow!("no ops to send").into())
-                                    },
-                                    SendBundleResult::StalledAtMaxFeeIncreases => Err(anyhow::anyhow!("stalled at max fee increases").into()),
-                                    SendBundleResult::Error(e) => Err(anyhow::anyhow!("send bundle error: {e:?}").into()),
-                                }
-                            },
-                            ServerRequestKind::DebugSetBundlingMode { mode } => {
-                                if self.bundle_sender_actions.len() != 1 {
-                                    break 'a Err(anyhow::anyhow!("more than 1 bundle builder not supported in debug mode").into())
-                                }
-
-                                match self.bundle_sender_actions[0].send(BundleSenderAction::ChangeMode(mode)).await {
-                                    Ok(()) => {},
-                                    Err(e) => break 'a Err(anyhow::anyhow!("failed to change bundler mode: {}", e.to_string()).into())
-                                }
-
-                                Ok(ServerResponse::DebugSetBundlingMode)
-                            },
+                    self.handle_request(req).await;
+                }
+            }
+        }
+    }
+
+    async fn handle_request(&self, req: ServerRequest) {
+        let resp: BuilderResult<ServerResponse> = 'a: {
+            match req.request {
+                ServerRequestKind::GetSupportedEntryPoints => {
+                    Ok(ServerResponse::GetSupportedEntryPoints {
+                        entry_points: self.entry_points.clone(),
+                    })
+                }
+                ServerRequestKind::DebugSendBundleNow => {
+                    if self.bundle_sender_actions.len() != 1 {
+                        break 'a Err(anyhow::anyhow!(
+                            "more than 1 bundle builder not supported in debug mode"
+                        )
+                        .into());
+                    }
+
+                    let (tx, rx) = oneshot::channel();
+                    match self.bundle_sender_actions[0]
+                        .send(BundleSenderAction::SendBundle(SendBundleRequest {
+                            responder: tx,
+                        }))
+                        .await
+                    {
+                        Ok(()) => {}
+                        Err(e) => {
+                            break 'a Err(anyhow::anyhow!(
+                                "failed to send send bundle request: {}",
+                                e.to_string()
+                            )
+                            .into())
+                        }
+                    }
+
+                    let result = match rx.await {
+                        Ok(result) => result,
+                        Err(e) => {
+                            break 'a Err(
+                                anyhow::anyhow!("failed to receive bundle result: {e:?}").into()
+                            )
                         }
                     };
 
-                    if let Err(e) = req.response.send(resp) {
-                        tracing::error!("failed to send response: {:?}", e);
+                    match result {
+                        SendBundleResult::Success {
+                            tx_hash,
+                            block_number,
+                            ..
+                        } => Ok(ServerResponse::DebugSendBundleNow {
+                            hash: tx_hash,
+                            block_number,
+                        }),
+                        SendBundleResult::NoOperationsInitially => {
+                            Err(anyhow::anyhow!("no ops to send").into())
+                        }
+                        SendBundleResult::StalledAtMaxFeeIncreases => {
+                            Err(anyhow::anyhow!("stalled at max fee increases").into())
+                        }
+                        SendBundleResult::Error(e) => {
+                            Err(anyhow::anyhow!("send bundle error: {e:?}").into())
+                        }
                     }
                 }
+                ServerRequestKind::DebugSetBundlingMode { mode } => {
+                    if self.bundle_sender_actions.len() != 1 {
+                        break 'a Err(anyhow::anyhow!(
+                            "more than 1 bundle builder not supported in debug mode"
+                        )
+                        .into());
+                    }
+
+                    match self.bundle_sender_actions[0]
+                        .send(BundleSenderAction::ChangeMode(mode))
+                        .await
+                    {
+                        Ok(()) => {}
+                        Err(e) => {
+                            break 'a Err(anyhow::anyhow!(
+                                "failed to change bundler mode: {}",
+                                e.to_string()
+                            )
+                            .into())
+                        }
+                    }
+
+                    Ok(ServerResponse::DebugSetBundlingMode)
+                }
+                ServerRequestKind::GetSignerStatuses => Ok(ServerResponse::GetSignerStatuses {
+                    statuses: self
+                        .signer_statuses
+                        .iter()
+                        .map(|s| s.borrow().clone())
+                        .collect(),
+                }),
+                ServerRequestKind::DebugCancelBundleContaining { op_hash } => {
+                    // only the bundle sender whose pending bundle contains the op cancels it,
+                    // the others ignore the request
+                    for bundle_sender_action in &self.bundle_sender_actions {
+                        if let Err(e) = bundle_sender_action
+                            .send(BundleSenderAction::CancelContaining(op_hash))
+                            .await
+                        {
+                            break 'a Err(anyhow::anyhow!(
+                                "failed to send cancel request: {}",
+                                e.to_string()
+                            )
+                            .into());
+                        }
+                    }
+
+                    Ok(ServerResponse::DebugCancelBundleContaining)
+                }
             }
+        };
+
+        if let Err(e) = req.response.send(resp) {
+            tracing::error!("failed to send response: {:?}", e);
         }
     }
 }
@@ -296,6 +385,8 @@ enum ServerRequestKind {
     GetSupportedEntryPoints,
     DebugSendBundleNow,
     DebugSetBundlingMode { mode: BundlingMode },
+    GetSignerStatuses,
+    DebugCancelBundleContaining { op_hash: B256 },
 }
 
 #[derive(Debug)]
@@ -309,4 +400,71 @@ enum ServerResponse {
     GetSupportedEntryPoints { entry_points: Vec<Address> },
     DebugSendBundleNow { hash: B256, block_number: u64 },
     DebugSetBundlingMode,
+    GetSignerStatuses { statuses: Vec<SignerStatus> },
+    DebugCancelBundleContaining,
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+    use rundler_signer::{Result as SignerResult, SignerLease};
+    use rundler_types::pool::MockPool;
+
+    use super::*;
+
+    mockall::mock! {
+        SignerManager {}
+
+        #[async_trait::async_trait]
+        impl SignerManager for SignerManager {
+            fn addresses(&self) -> Vec<Address>;
+            fn available(&self) -> usize;
+            async fn wait_for_available(&self, num_required: usize) -> SignerResult<()>;
+            fn lease_signer(&self) -> Option<SignerLease>;
+            fn lease_signer_by_address(&self, address: &Address) -> Option<SignerLease>;
+            fn return_lease(&self, lease: SignerLease);
+            fn update_balances(&self, balances: Vec<(Address, U256)>);
+            fn fund_signers(&self) -> SignerResult<()>;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_debug_cancel_bundle_containing() {
+        let builder = LocalBuilderBuilder::new(
+            10,
+            Arc::new(MockSignerManager::new()),
+            Arc::new(MockPool::new()),
+        );
+        let handle = builder.get_handle();
+        let (action_senders, mut action_receivers): (Vec<_>, Vec<_>) =
+            (0..2).map(|_| mpsc::channel(10)).unzip();
+        let mut runner = LocalBuilderServerRunner::new(
+            builder.req_receiver,
+            action_senders,
+            vec![],
+            vec![],
+            builder.signer_manager,
+            builder.pool,
+        );
+        let server = tokio::spawn(async move {
+            let req = runner.req_receiver.recv().await.unwrap();
+            runner.handle_request(req).await;
+        });
+
+        let op_hash = B256::repeat_byte(1);
+        handle
+            .debug_cancel_bundle_containing(op_hash)
+            .await
+            .expect("should cancel");
+        server.await.unwrap();
+
+        // every bundle sender is asked, only one with a pending bundle containing the op
+        // cancels it
+        for receiver in &mut action_receivers {
+            let Ok(BundleSenderAction::CancelContaining(hash)) = receiver.try_recv() else {
+                panic!("expected a cancel request");
+            };
+            assert_eq!(hash, op_hash);
+        }
+    }
 }
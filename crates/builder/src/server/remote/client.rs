@@ -19,7 +19,7 @@ use rundler_task::{
     grpc::protos::{from_bytes, ConversionError},
     server::{HealthCheck, ServerStatus},
 };
-use rundler_types::builder::{Builder, BuilderError, BuilderResult, BundlingMode};
+use rundler_types::builder::{Builder, BuilderError, BuilderResult, BundlingMode, SignerStatus};
 use tonic::transport::{Channel, Uri};
 use tonic_health::{
     pb::{health_client::HealthClient, HealthCheckRequest},
@@ -27,9 +27,11 @@ use tonic_health::{
 };
 
 use super::protos::{
-    builder_client::BuilderClient, debug_send_bundle_now_response,
-    debug_set_bundling_mode_response, BundlingMode as ProtoBundlingMode, DebugSendBundleNowRequest,
-    DebugSetBundlingModeRequest, GetSupportedEntryPointsRequest,
+    builder_client::BuilderClient, debug_cancel_bundle_containing_response,
+    debug_send_bundle_now_response, debug_set_bundling_mode_response,
+    BundlingMode as ProtoBundlingMode, DebugCancelBundleContainingRequest,
+    DebugSendBundleNowRequest, DebugSetBundlingModeRequest, GetSignerStatusesRequest,
+    GetSupportedEntryPointsRequest,
 };
 
 /// Remote builder client, used for communicating with a remote builder server
@@ -110,6 +112,42 @@ impl Builder for RemoteBuilderClient {
             )))?,
         }
     }
+
+    async fn get_signer_statuses(&self) -> BuilderResult<Vec<SignerStatus>> {
+        Ok(self
+            .grpc_client
+            .clone()
+            .get_signer_statuses(GetSignerStatusesRequest {})
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .statuses
+            .into_iter()
+            .map(SignerStatus::try_from)
+            .collect::<Result<_, ConversionError>>()
+            .map_err(anyhow::Error::from)?)
+    }
+
+    async fn debug_cancel_bundle_containing(&self, op_hash: B256) -> BuilderResult<()> {
+        let res = self
+            .grpc_client
+            .clone()
+            .debug_cancel_bundle_containing(DebugCancelBundleContainingRequest {
+                op_hash: op_hash.to_vec(),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(debug_cancel_bundle_containing_response::Result::Success(_)) => Ok(()),
+            Some(debug_cancel_bundle_containing_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(BuilderError::Other(anyhow::anyhow!(
+                "should have received result from builder"
+            )))?,
+        }
+    }
 }
 
 #[async_trait]
@@ -11,8 +11,12 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use rundler_task::grpc::protos::ConversionError;
-use rundler_types::builder::BundlingMode as RpcBundlingMode;
+use rundler_provider::HandleOpsOut as ProviderHandleOpsOut;
+use rundler_task::grpc::protos::{from_bytes, ConversionError, ToProtoBytes};
+use rundler_types::{
+    builder::{BundlingMode as RpcBundlingMode, SignerStatus as RpcSignerStatus},
+    GasFees as RpcGasFees,
+};
 
 tonic::include_proto!("builder");
 
@@ -39,3 +43,139 @@ impl TryFrom<BundlingMode> for RpcBundlingMode {
         }
     }
 }
+
+impl From<&RpcSignerStatus> for SignerStatus {
+    fn from(status: &RpcSignerStatus) -> Self {
+        Self {
+            builder_tag: status.builder_tag.clone(),
+            signer: status.signer.to_proto_bytes(),
+            nonce: status.nonce,
+            pending: status.pending,
+            pending_blocks: status.pending_blocks,
+            fees: status.fees.map(|fees| GasFees {
+                max_fee_per_gas: fees.max_fee_per_gas.to_proto_bytes(),
+                max_priority_fee_per_gas: fees.max_priority_fee_per_gas.to_proto_bytes(),
+            }),
+            block_number: status.block_number,
+        }
+    }
+}
+
+impl TryFrom<SignerStatus> for RpcSignerStatus {
+    type Error = ConversionError;
+
+    fn try_from(status: SignerStatus) -> Result<Self, Self::Error> {
+        Ok(Self {
+            builder_tag: status.builder_tag,
+            signer: from_bytes(&status.signer)?,
+            nonce: status.nonce,
+            pending: status.pending,
+            pending_blocks: status.pending_blocks,
+            fees: status
+                .fees
+                .map(|fees| {
+                    Ok::<_, ConversionError>(RpcGasFees {
+                        max_fee_per_gas: from_bytes(&fees.max_fee_per_gas)?,
+                        max_priority_fee_per_gas: from_bytes(&fees.max_priority_fee_per_gas)?,
+                    })
+                })
+                .transpose()?,
+            block_number: status.block_number,
+        })
+    }
+}
+
+impl From<ProviderHandleOpsOut> for HandleOpsOut {
+    fn from(out: ProviderHandleOpsOut) -> Self {
+        let result = match out {
+            ProviderHandleOpsOut::Success => handle_ops_out::Result::Success(HandleOpsSuccess {}),
+            ProviderHandleOpsOut::FailedOp(index, reason) => {
+                handle_ops_out::Result::FailedOp(HandleOpsFailedOp {
+                    index: index as u64,
+                    reason,
+                })
+            }
+            ProviderHandleOpsOut::SignatureValidationFailed(aggregator) => {
+                handle_ops_out::Result::SignatureValidationFailed(
+                    HandleOpsSignatureValidationFailed {
+                        aggregator: aggregator.to_vec(),
+                    },
+                )
+            }
+            ProviderHandleOpsOut::PostOpRevert => {
+                handle_ops_out::Result::PostOpRevert(HandleOpsPostOpRevert {})
+            }
+            ProviderHandleOpsOut::Revert(revert_data) => {
+                handle_ops_out::Result::Revert(HandleOpsRevert {
+                    revert_data: revert_data.to_vec(),
+                })
+            }
+        };
+        Self {
+            result: Some(result),
+        }
+    }
+}
+
+impl TryFrom<HandleOpsOut> for ProviderHandleOpsOut {
+    type Error = ConversionError;
+
+    fn try_from(value: HandleOpsOut) -> Result<Self, Self::Error> {
+        let Some(result) = value.result else {
+            return Err(anyhow::anyhow!("HandleOpsOut result should be set").into());
+        };
+        Ok(match result {
+            handle_ops_out::Result::Success(_) => Self::Success,
+            handle_ops_out::Result::FailedOp(e) => Self::FailedOp(e.index as usize, e.reason),
+            handle_ops_out::Result::SignatureValidationFailed(e) => {
+                Self::SignatureValidationFailed(from_bytes(&e.aggregator)?)
+            }
+            handle_ops_out::Result::PostOpRevert(_) => Self::PostOpRevert,
+            handle_ops_out::Result::Revert(e) => Self::Revert(e.revert_data.into()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, Bytes};
+
+    use super::*;
+
+    #[test]
+    fn test_handle_ops_out_proto_round_trip() {
+        for out in [
+            ProviderHandleOpsOut::Success,
+            ProviderHandleOpsOut::FailedOp(1, "AA25 invalid account nonce".to_string()),
+            ProviderHandleOpsOut::SignatureValidationFailed(Address::repeat_byte(1)),
+            ProviderHandleOpsOut::PostOpRevert,
+            ProviderHandleOpsOut::Revert(Bytes::from_static(&[1, 2, 3])),
+        ] {
+            let proto = HandleOpsOut::from(out.clone());
+            assert_eq!(ProviderHandleOpsOut::try_from(proto).unwrap(), out);
+        }
+    }
+
+    #[test]
+    fn test_signer_status_proto_round_trip() {
+        let status = RpcSignerStatus {
+            builder_tag: "tag".to_string(),
+            signer: Address::repeat_byte(1),
+            nonce: 7,
+            pending: true,
+            pending_blocks: 2,
+            fees: Some(RpcGasFees {
+                max_fee_per_gas: 100,
+                max_priority_fee_per_gas: 10,
+            }),
+            block_number: 12,
+        };
+        let proto = SignerStatus::from(&status);
+        assert_eq!(RpcSignerStatus::try_from(proto).unwrap(), status);
+    }
+
+    #[test]
+    fn test_handle_ops_out_proto_missing_result() {
+        assert!(ProviderHandleOpsOut::try_from(HandleOpsOut { result: None }).is_err());
+    }
+}
@@ -13,16 +13,19 @@
 
 use std::net::SocketAddr;
 
-use rundler_task::GracefulShutdown;
+use rundler_task::{grpc::protos::from_bytes, GracefulShutdown};
 use rundler_types::builder::Builder;
 use tonic::{async_trait, transport::Server, Request, Response, Status};
 
 use super::protos::{
     builder_server::{Builder as GrpcBuilder, BuilderServer as GrpcBuilderServer},
-    debug_send_bundle_now_response, debug_set_bundling_mode_response, BundlingMode,
+    debug_cancel_bundle_containing_response, debug_send_bundle_now_response,
+    debug_set_bundling_mode_response, BundlingMode, DebugCancelBundleContainingRequest,
+    DebugCancelBundleContainingResponse, DebugCancelBundleContainingSuccess,
     DebugSendBundleNowRequest, DebugSendBundleNowResponse, DebugSetBundlingModeRequest,
-    DebugSetBundlingModeResponse, DebugSetBundlingModeSuccess, GetSupportedEntryPointsRequest,
-    GetSupportedEntryPointsResponse, BUILDER_FILE_DESCRIPTOR_SET,
+    DebugSetBundlingModeResponse, DebugSetBundlingModeSuccess, GetSignerStatusesRequest,
+    GetSignerStatusesResponse, GetSupportedEntryPointsRequest, GetSupportedEntryPointsResponse,
+    SignerStatus, BUILDER_FILE_DESCRIPTOR_SET,
 };
 use crate::server::{local::LocalBuilderHandle, remote::protos::DebugSendBundleNowSuccess};
 
@@ -142,4 +145,49 @@ impl GrpcBuilder for GrpcBuilderServerImpl {
 
         Ok(Response::new(resp))
     }
+
+    async fn get_signer_statuses(
+        &self,
+        _request: Request<GetSignerStatusesRequest>,
+    ) -> tonic::Result<Response<GetSignerStatusesResponse>> {
+        let resp = match self.local_builder.get_signer_statuses().await {
+            Ok(statuses) => GetSignerStatusesResponse {
+                statuses: statuses.iter().map(SignerStatus::from).collect(),
+            },
+            Err(e) => {
+                return Err(Status::internal(format!(
+                    "Failed to get signer statuses: {e}"
+                )));
+            }
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn debug_cancel_bundle_containing(
+        &self,
+        request: Request<DebugCancelBundleContainingRequest>,
+    ) -> tonic::Result<Response<DebugCancelBundleContainingResponse>> {
+        let op_hash = from_bytes(&request.into_inner().op_hash)
+            .map_err(|e| Status::invalid_argument(format!("Invalid op hash: {e}")))?;
+
+        let resp = match self
+            .local_builder
+            .debug_cancel_bundle_containing(op_hash)
+            .await
+        {
+            Ok(()) => DebugCancelBundleContainingResponse {
+                result: Some(debug_cancel_bundle_containing_response::Result::Success(
+                    DebugCancelBundleContainingSuccess {},
+                )),
+            },
+            Err(e) => {
+                return Err(Status::internal(format!(
+                    "Failed to cancel bundle containing op: {e}"
+                )));
+            }
+        };
+
+        Ok(Response::new(resp))
+    }
 }
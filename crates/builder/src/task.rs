@@ -18,34 +18,55 @@ use std::{
     time::Duration,
 };
 
-use alloy_primitives::{Address, B256};
-use anyhow::Context;
-use rundler_provider::{EntryPoint, Providers as ProvidersT, ProvidersWithEntryPointT};
-use rundler_signer::{SignerManager, SigningScheme};
+use alloy_primitives::{Address, B256, U256};
+use anyhow::{bail, Context};
+use redis::aio::MultiplexedConnection;
+use rundler_provider::{
+    EntryPoint, EvmProvider, FixedGasPriceSource, Providers as ProvidersT, ProvidersWithEntryPointT,
+};
+use rundler_signer::{SignerManager, SigningRetrySettings, SigningScheme};
 use rundler_sim::{
-    simulation::{self, UnsafeSimulator},
+    simulation::{self, CanarySettings, CanarySimulator, UnsafeSimulator},
     MempoolConfig, SimulationSettings, Simulator,
 };
 use rundler_task::TaskSpawnerExt;
 use rundler_types::{
-    chain::ChainSpec, pool::Pool as PoolT, EntryPointVersion, UserOperation, UserOperationVariant,
+    builder::SignerStatus, chain::ChainSpec, pool::Pool as PoolT, EntryPointVersion, GasFees,
+    PriorityFeeMode, UserOperation, UserOperationId, UserOperationVariant,
 };
 use rundler_utils::emit::WithEntryPoint;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{broadcast, mpsc, watch};
 use tracing::info;
 
 use crate::{
     assigner::Assigner,
-    bundle_proposer::{self, BundleProposerImpl, BundleProposerProviders},
+    audit::{BundleAuditSink, NoopBundleAuditSink},
+    bundle_proposer::{self, BundleProposerImpl, BundleProposerProviders, SelectionStrategy},
     bundle_sender::{self, BundleSender, BundleSenderAction, BundleSenderImpl},
+    confirmation_poller::{ConfirmationPoller, ReceiptConfirmationPoller},
     emit::BuilderEvent,
+    fee_controller,
+    nonce_manager::{
+        NonceManager, OnChainNonceManager, RedisReservationStore, ReservingNonceManager,
+    },
+    price_oracle::TokenPriceOracle,
     sender::TransactionSenderArgs,
     server::{self, LocalBuilderBuilder},
-    transaction_tracker::{self, TransactionTrackerImpl},
+    tip::TipTransfer,
+    transaction_tracker::{
+        self, RedisTrackerStateStore, TrackerStateStore, TransactionTrackerImpl,
+    },
+    transformer::{NoopTransactionTransformer, TransactionTransformer},
 };
 
 const MAX_POOL_OPS_PER_REQUEST: u64 = 1024;
 
+// The action sender and signer status receiver of a running bundle sender
+type BundleSenderChannels = (
+    mpsc::Sender<BundleSenderAction>,
+    watch::Receiver<SignerStatus>,
+);
+
 /// Builder task arguments
 #[derive(Debug)]
 pub struct Args {
@@ -73,8 +94,39 @@ pub struct Args {
     pub max_blocks_to_wait_for_mine: u64,
     /// Percentage to increase the fees by when replacing a bundle transaction
     pub replacement_fee_percent_increase: u32,
+    /// Re-include the ops of a stuck bundle first when replacing it. Requires the pool order
+    /// selection strategy.
+    pub sticky_replacement: bool,
+    /// Re-simulate the ops of a pending bundle after this many blocks without a mine
+    pub resim_after_blocks: Option<u64>,
+    /// Skip ops with the same sender and nonce as a different op in the pending bundle
+    pub skip_pending_nonce_duplicates: bool,
+    /// Maximum number of immediate pool re-polls when a bundle empties out from removed ops
+    pub repoll_on_empty: u64,
+    /// Send a self-transfer after this many blocks without sending a bundle
+    pub heartbeat_interval_blocks: Option<u64>,
+    /// If set, the priority fee increase percent required of ops is adjusted between the
+    /// minimum and this maximum based on how quickly recent bundles were mined
+    pub adaptive_priority_fee_max_percent: Option<u32>,
+    /// Priority fee increase percent the adaptive controller starts at and relaxes to
+    pub adaptive_priority_fee_min_percent: u32,
+    /// Amount the adaptive controller raises or lowers the percent by per adjustment
+    pub adaptive_priority_fee_step_percent: u32,
+    /// Blocks from first send to mine at or above which a bundle counts as slow
+    pub adaptive_priority_fee_slow_blocks: u64,
+    /// Number of consecutive bundles that must all be slow, or all fast, for an adjustment
+    pub adaptive_priority_fee_window: usize,
+    /// If set, ops are removed from the pool after reverting on-chain in this many mined
+    /// bundles
+    pub max_onchain_revert_attempts: Option<u64>,
     /// Maximum number of times to increase the fee when cancelling a transaction
     pub max_cancellation_fee_increases: u64,
+    /// Check the on-chain nonce before abandoning a cancellation that didn't mine, and keep
+    /// waiting for the mined transaction if the nonce was used
+    pub recheck_cancellation_nonce: bool,
+    /// If set, each bundle transaction also transfers a tip to a recipient, only if
+    /// `handleOps` succeeds
+    pub tip_transfer: Option<TipTransfer>,
     /// Maximum amount of blocks to spend in a replacement underpriced state before moving to cancel
     pub max_replacement_underpriced_blocks: u64,
     /// Address to bind the remote builder server to, if any. If none, no server is starter.
@@ -89,6 +141,83 @@ pub struct Args {
     pub max_expected_storage_slots: usize,
     /// Rejects user operations with a verification gas limit efficiency below this threshold.
     pub verification_gas_limit_efficiency_reject_threshold: f64,
+    /// Emit the calldata of each proposed bundle for debugging
+    pub dump_calldata: bool,
+    /// Maximum number of rounds an operation can be skipped before it is removed from the pool
+    pub max_skip_rounds: Option<u64>,
+    /// Maximum size in bytes of the `handleOps` calldata of a bundle
+    pub max_bundle_calldata_bytes: Option<usize>,
+    /// Number of times to retry a bundle gas estimation call that failed in transport
+    pub estimation_retries: u64,
+    /// Maximum number of times to remove failing ops and re-estimate a bundle in a single
+    /// round. If `None`, estimation is repeated until the bundle succeeds or is empty.
+    pub max_whittle_iterations: Option<u64>,
+    /// Check that the beneficiary can receive ETH before building each bundle, in addition
+    /// to the check at startup
+    pub check_beneficiary_per_bundle: bool,
+    /// Address that receives bundle fees, unless overridden per entry point. If `None`,
+    /// each builder's signer is paid.
+    pub beneficiary: Option<Address>,
+    /// If set, bundle fees come from this fixed source instead of the provider. For
+    /// testing only.
+    pub fixed_gas_price: Option<FixedGasPriceSource>,
+    /// Simulate each bundle as a whole before sending, raising its gas limit if it
+    /// used more gas than the summed estimate
+    pub simulate_bundle_gas: bool,
+    /// Percentage added to the estimated gas limit of each bundle
+    pub gas_estimate_buffer_percent: u32,
+    /// Trace each bundle and emit the gas used by each op's phases, for diagnostics
+    pub attribute_bundle_gas: bool,
+    /// Defer ops whose validAfter is in the future until then instead of rejecting them
+    pub defer_not_yet_valid_ops: bool,
+    /// Maximum time in milliseconds to spend building a bundle before proposing it from
+    /// the ops simulated so far. If `None`, building waits for every simulation.
+    pub max_build_duration_millis: Option<u64>,
+    /// Maximum number of ops using the same paymaster in a bundle. If `None`, unlimited.
+    pub max_ops_per_paymaster: Option<usize>,
+    /// Maximum number of distinct aggregators in a bundle
+    pub max_aggregators_per_bundle: usize,
+    /// Maximum summed call, verification and pre-verification gas limits of an op
+    pub max_op_total_gas: u128,
+    /// If set, rounds given a block more than this many blocks behind the head propose on
+    /// the head block instead
+    pub max_sim_block_age: Option<u64>,
+    /// Probe each aggregator before bundling its ops, rejecting the ops of aggregators
+    /// that don't implement `IAggregator`
+    pub verify_aggregator_interface: bool,
+    /// How candidate ops are ordered before the bundle is capped
+    pub selection_strategy: SelectionStrategy,
+    /// Maximum summed prefund, in wei, of the ops in a bundle. If `None`, unlimited.
+    pub max_bundle_prefund: Option<U256>,
+    /// Check that the sender of each op without a paymaster can cover its prefund from its
+    /// entry point deposit and ETH balance
+    pub check_sender_eth_balance: bool,
+    /// Percentage above its prefund that a self-paying sender's funds must cover
+    pub sender_deposit_buffer_percent: u32,
+    /// Minimum seconds that must remain before an op's validUntil for it to be bundled
+    pub min_validity_remaining_seconds: u64,
+    /// Percentage that the pre-op gas of a canary simulation may differ from the primary
+    /// simulation before the two are considered to disagree
+    pub canary_gas_tolerance_percent: u32,
+    /// Redis URI used to reserve signer nonces across processes. If `None`, nonces are
+    /// taken from the on-chain transaction count.
+    pub nonce_redis_uri: Option<String>,
+    /// TTL of a nonce reservation in milliseconds
+    pub nonce_reservation_ttl_millis: u64,
+    /// Persist each transaction tracker's nonce and pending transactions to the redis at
+    /// `nonce_redis_uri` and restore them on startup
+    pub persist_tracker_state: bool,
+    /// Share a single confirmation poller across the transaction trackers of all builders,
+    /// rather than each tracker looking up its own receipts
+    pub share_confirmation_poller: bool,
+    /// Maximum number of retries of a signing call that failed with a transient error,
+    /// such as KMS throttling or a timeout
+    pub signing_max_retries: u64,
+    /// Wait in milliseconds before the first signing retry, doubled for each retry after it
+    pub signing_retry_backoff_millis: u64,
+    /// Delay in milliseconds between the starts of an entry point's builders. The builder
+    /// at index `n` waits `n` times this before proposing its first bundle.
+    pub stagger_interval_millis: u64,
 }
 
 /// Builder settings
@@ -123,6 +252,18 @@ pub struct EntryPointBuilderSettings {
     pub mempool_configs: HashMap<B256, MempoolConfig>,
     /// Builder settings
     pub builders: Vec<BuilderSettings>,
+    /// Priority fee mode required of ops by this entry point's builders. If `None`, the
+    /// global priority fee mode is used.
+    pub priority_fee_mode: Option<PriorityFeeMode>,
+    /// Beneficiary of this entry point's bundles. If `None`, the global beneficiary is used.
+    pub beneficiary: Option<Address>,
+    /// Mempool IDs this entry point's builders bundle from. Ops not supported by any of
+    /// these mempools are left in the pool. If `None`, ops from any mempool are bundled.
+    pub allowed_mempools: Option<HashSet<B256>>,
+    /// Ceiling on the gas limit of this entry point's bundle transactions. Ops are left
+    /// out of a bundle until its estimated gas fits under it. If `None`, only the
+    /// builder's max bundle gas applies.
+    pub max_bundle_gas_limit: Option<u64>,
 }
 
 /// Builder task
@@ -132,7 +273,13 @@ pub struct BuilderTask<Pool, Providers> {
     builder_builder: LocalBuilderBuilder,
     pool: Pool,
     providers: Providers,
+    canary_providers: Option<Providers>,
     signer_manager: Arc<dyn SignerManager>,
+    transaction_transformer: Arc<dyn TransactionTransformer>,
+    audit_sink: Arc<dyn BundleAuditSink>,
+    token_price_oracles: HashMap<Address, Arc<dyn TokenPriceOracle>>,
+    fee_overrides: HashMap<UserOperationId, GasFees>,
+    confirmation_poller: Option<Arc<dyn ConfirmationPoller>>,
 }
 
 impl<Pool, Providers> BuilderTask<Pool, Providers> {
@@ -144,6 +291,7 @@ impl<Pool, Providers> BuilderTask<Pool, Providers> {
         builder_builder: LocalBuilderBuilder,
         pool: Pool,
         providers: Providers,
+        canary_providers: Option<Providers>,
         signer_manager: Arc<dyn SignerManager>,
     ) -> Self {
         Self {
@@ -152,9 +300,48 @@ impl<Pool, Providers> BuilderTask<Pool, Providers> {
             builder_builder,
             pool,
             providers,
+            canary_providers,
             signer_manager,
+            transaction_transformer: Arc::new(NoopTransactionTransformer),
+            audit_sink: Arc::new(NoopBundleAuditSink),
+            token_price_oracles: HashMap::new(),
+            fee_overrides: HashMap::new(),
+            confirmation_poller: None,
         }
     }
+
+    /// Apply the given transformer to every bundle transaction before it is sent
+    pub fn with_transaction_transformer(
+        mut self,
+        transaction_transformer: Arc<dyn TransactionTransformer>,
+    ) -> Self {
+        self.transaction_transformer = transaction_transformer;
+        self
+    }
+
+    /// Record every submitted bundle to the given sink
+    pub fn with_bundle_audit_sink(mut self, audit_sink: Arc<dyn BundleAuditSink>) -> Self {
+        self.audit_sink = audit_sink;
+        self
+    }
+
+    /// Rank the ops of the given token paymaster by the ETH-equivalent gas price from
+    /// the oracle, when ops are ordered by gas price
+    pub fn with_token_price_oracle(
+        mut self,
+        paymaster: Address,
+        oracle: Arc<dyn TokenPriceOracle>,
+    ) -> Self {
+        self.token_price_oracles.insert(paymaster, oracle);
+        self
+    }
+
+    /// Rank the op with the given id and check its prefund as if it paid the given fees.
+    /// The op itself, and so its hash, is unchanged.
+    pub fn with_fee_override(mut self, id: UserOperationId, fees: GasFees) -> Self {
+        self.fee_overrides.insert(id, fees);
+        self
+    }
 }
 
 impl<Pool, Providers> BuilderTask<Pool, Providers>
@@ -163,11 +350,19 @@ where
     Providers: ProvidersT + 'static,
 {
     /// Spawn the builder task on the given task spawner
-    pub async fn spawn<T>(self, task_spawner: T) -> anyhow::Result<()>
+    pub async fn spawn<T>(mut self, task_spawner: T) -> anyhow::Result<()>
     where
         T: TaskSpawnerExt,
     {
-        let mut bundle_sender_actions = vec![];
+        self.check_entry_points_deployed().await?;
+
+        if self.args.share_confirmation_poller {
+            self.confirmation_poller = Some(Arc::new(ReceiptConfirmationPoller::new(
+                self.providers.evm().clone(),
+            )));
+        }
+
+        let mut bundle_senders = vec![];
 
         let num_required_signers: usize = self
             .args
@@ -175,6 +370,8 @@ where
             .iter()
             .map(|ep| ep.builders.len())
             .sum();
+        check_signing_keys(&self.args.signing_scheme, num_required_signers)?;
+        check_selection_strategy(&self.args.selection_strategy, self.args.sticky_replacement)?;
 
         // wait 60 seconds for the signers to be available
         match tokio::time::timeout(
@@ -205,7 +402,7 @@ where
         for ep in &self.args.entry_points {
             match ep.version {
                 EntryPointVersion::V0_6 => {
-                    let actions = self
+                    let senders = self
                         .create_builders_v0_6(
                             &task_spawner,
                             ep,
@@ -213,11 +410,11 @@ where
                             assigner.clone(),
                         )
                         .await?;
-                    bundle_sender_actions.extend(actions);
+                    bundle_senders.extend(senders);
                     supported_entry_points.insert(self.args.chain_spec.entry_point_address_v0_6);
                 }
                 EntryPointVersion::V0_7 => {
-                    let actions = self
+                    let senders = self
                         .create_builders_v0_7(
                             &task_spawner,
                             ep,
@@ -225,7 +422,7 @@ where
                             assigner.clone(),
                         )
                         .await?;
-                    bundle_sender_actions.extend(actions);
+                    bundle_senders.extend(senders);
                     supported_entry_points.insert(self.args.chain_spec.entry_point_address_v0_7);
                 }
                 EntryPointVersion::Unspecified => {
@@ -234,6 +431,8 @@ where
             }
         }
 
+        let (bundle_sender_actions, signer_statuses): (Vec<_>, Vec<_>) =
+            bundle_senders.into_iter().unzip();
         let builder_handle = self.builder_builder.get_handle();
 
         task_spawner.spawn_critical_with_graceful_shutdown_signal(
@@ -241,6 +440,7 @@ where
             |shutdown| {
                 self.builder_builder.run(
                     bundle_sender_actions,
+                    signer_statuses,
                     supported_entry_points.into_iter().collect(),
                     shutdown,
                 )
@@ -265,13 +465,42 @@ where
         Ok(())
     }
 
+    /// Fail fast if any configured entry point has no code on the connected chain,
+    /// instead of surfacing confusing reverts from deep within simulation.
+    async fn check_entry_points_deployed(&self) -> anyhow::Result<()> {
+        for ep in &self.args.entry_points {
+            let address = match ep.version {
+                EntryPointVersion::V0_6 => self.args.chain_spec.entry_point_address_v0_6,
+                EntryPointVersion::V0_7 => self.args.chain_spec.entry_point_address_v0_7,
+                EntryPointVersion::Unspecified => {
+                    panic!("Unspecified entry point version")
+                }
+            };
+
+            let code = self
+                .providers
+                .evm()
+                .get_code(address, None)
+                .await
+                .with_context(|| format!("failed to get code for entry point {address:?}"))?;
+            if code.is_empty() {
+                bail!(
+                    "No code found for entry point {:?} at {address:?} on chain id {}. Check the entry point address and that the node is connected to the correct chain.",
+                    ep.version,
+                    self.args.chain_spec.id,
+                );
+            }
+        }
+        Ok(())
+    }
+
     async fn create_builders_v0_6<T>(
         &self,
         task_spawner: &T,
         ep: &EntryPointBuilderSettings,
         signer_manager: &Arc<dyn SignerManager>,
         assigner: Arc<Assigner>,
-    ) -> anyhow::Result<Vec<mpsc::Sender<BundleSenderAction>>>
+    ) -> anyhow::Result<Vec<BundleSenderChannels>>
     where
         T: TaskSpawnerExt,
     {
@@ -281,11 +510,13 @@ where
             .ep_v0_6_providers()
             .clone()
             .context("entry point v0.6 not supplied")?;
-        let mut bundle_sender_actions = vec![];
-        for settings in &ep.builders {
-            let bundle_sender_action = if self.args.unsafe_mode {
+        let mut bundle_senders = vec![];
+        for (index, settings) in ep.builders.iter().enumerate() {
+            let bundle_sender = if self.args.unsafe_mode {
                 self.create_bundle_builder(
                     task_spawner,
+                    ep,
+                    index,
                     settings,
                     ep_providers.clone(),
                     UnsafeSimulator::new(
@@ -299,22 +530,38 @@ where
             } else {
                 self.create_bundle_builder(
                     task_spawner,
+                    ep,
+                    index,
                     settings,
                     ep_providers.clone(),
-                    simulation::new_v0_6_simulator(
-                        ep_providers.evm().clone(),
-                        ep_providers.entry_point().clone(),
-                        self.args.sim_settings.clone(),
-                        ep.mempool_configs.clone(),
+                    CanarySimulator::new(
+                        simulation::new_v0_6_simulator(
+                            ep_providers.evm().clone(),
+                            ep_providers.entry_point().clone(),
+                            self.args.sim_settings.clone(),
+                            ep.mempool_configs.clone(),
+                        ),
+                        self.canary_providers
+                            .as_ref()
+                            .and_then(|p| p.ep_v0_6_providers())
+                            .map(|canary| {
+                                simulation::new_v0_6_simulator(
+                                    canary.evm().clone(),
+                                    canary.entry_point().clone(),
+                                    self.args.sim_settings.clone(),
+                                    ep.mempool_configs.clone(),
+                                )
+                            }),
+                        self.canary_settings(),
                     ),
                     signer_manager,
                     assigner.clone(),
                 )
                 .await?
             };
-            bundle_sender_actions.push(bundle_sender_action);
+            bundle_senders.push(bundle_sender);
         }
-        Ok(bundle_sender_actions)
+        Ok(bundle_senders)
     }
 
     async fn create_builders_v0_7<T>(
@@ -323,7 +570,7 @@ where
         ep: &EntryPointBuilderSettings,
         signer_manager: &Arc<dyn SignerManager>,
         assigner: Arc<Assigner>,
-    ) -> anyhow::Result<Vec<mpsc::Sender<BundleSenderAction>>>
+    ) -> anyhow::Result<Vec<BundleSenderChannels>>
     where
         T: TaskSpawnerExt,
     {
@@ -333,11 +580,13 @@ where
             .ep_v0_7_providers()
             .clone()
             .context("entry point v0.7 not supplied")?;
-        let mut bundle_sender_actions = vec![];
-        for settings in &ep.builders {
-            let bundle_sender_action = if self.args.unsafe_mode {
+        let mut bundle_senders = vec![];
+        for (index, settings) in ep.builders.iter().enumerate() {
+            let bundle_sender = if self.args.unsafe_mode {
                 self.create_bundle_builder(
                     task_spawner,
+                    ep,
+                    index,
                     settings,
                     ep_providers.clone(),
                     UnsafeSimulator::new(
@@ -351,33 +600,100 @@ where
             } else {
                 self.create_bundle_builder(
                     task_spawner,
+                    ep,
+                    index,
                     settings,
                     ep_providers.clone(),
-                    simulation::new_v0_7_simulator(
-                        ep_providers.evm().clone(),
-                        ep_providers.entry_point().clone(),
-                        self.args.sim_settings.clone(),
-                        ep.mempool_configs.clone(),
+                    CanarySimulator::new(
+                        simulation::new_v0_7_simulator(
+                            ep_providers.evm().clone(),
+                            ep_providers.entry_point().clone(),
+                            self.args.sim_settings.clone(),
+                            ep.mempool_configs.clone(),
+                        ),
+                        self.canary_providers
+                            .as_ref()
+                            .and_then(|p| p.ep_v0_7_providers())
+                            .map(|canary| {
+                                simulation::new_v0_7_simulator(
+                                    canary.evm().clone(),
+                                    canary.entry_point().clone(),
+                                    self.args.sim_settings.clone(),
+                                    ep.mempool_configs.clone(),
+                                )
+                            }),
+                        self.canary_settings(),
                     ),
                     signer_manager,
                     assigner.clone(),
                 )
                 .await?
             };
-            bundle_sender_actions.push(bundle_sender_action);
+            bundle_senders.push(bundle_sender);
+        }
+        Ok(bundle_senders)
+    }
+
+    async fn connect_redis(&self) -> anyhow::Result<Option<MultiplexedConnection>> {
+        let Some(redis_uri) = &self.args.nonce_redis_uri else {
+            if self.args.persist_tracker_state {
+                bail!("persisting tracker state requires a nonce redis uri");
+            }
+            return Ok(None);
+        };
+        let client = redis::Client::open(redis_uri.as_str()).context("should parse redis uri")?;
+        let connection = client
+            .get_multiplexed_tokio_connection()
+            .await
+            .context("should connect to redis")?;
+        Ok(Some(connection))
+    }
+
+    fn create_nonce_manager(
+        &self,
+        connection: Option<MultiplexedConnection>,
+    ) -> Arc<dyn NonceManager> {
+        let Some(connection) = connection else {
+            return Arc::new(OnChainNonceManager);
+        };
+        Arc::new(ReservingNonceManager::new(
+            RedisReservationStore::new(connection),
+            self.args.chain_spec.id,
+            self.args.nonce_reservation_ttl_millis,
+        ))
+    }
+
+    fn create_tracker_state_store(
+        &self,
+        connection: Option<MultiplexedConnection>,
+    ) -> Option<Arc<dyn TrackerStateStore>> {
+        connection
+            .filter(|_| self.args.persist_tracker_state)
+            .map(|connection| {
+                Arc::new(RedisTrackerStateStore::new(
+                    connection,
+                    self.args.chain_spec.id,
+                )) as Arc<dyn TrackerStateStore>
+            })
+    }
+
+    fn canary_settings(&self) -> CanarySettings {
+        CanarySettings {
+            gas_tolerance_percent: self.args.canary_gas_tolerance_percent,
         }
-        Ok(bundle_sender_actions)
     }
 
     async fn create_bundle_builder<T, UO, EP, S>(
         &self,
         task_spawner: &T,
+        ep: &EntryPointBuilderSettings,
+        index: usize,
         builder_settings: &BuilderSettings,
         ep_providers: EP,
         simulator: S,
         signer_manager: &Arc<dyn SignerManager>,
         assigner: Arc<Assigner>,
-    ) -> anyhow::Result<mpsc::Sender<BundleSenderAction>>
+    ) -> anyhow::Result<BundleSenderChannels>
     where
         T: TaskSpawnerExt,
         UO: UserOperation + From<UserOperationVariant>,
@@ -390,6 +706,10 @@ where
         let Some(signer) = signer_manager.lease_signer() else {
             return Err(anyhow::anyhow!("No signer available"));
         };
+        let signer = signer.with_retries(SigningRetrySettings {
+            max_retries: self.args.signing_max_retries,
+            initial_backoff: Duration::from_millis(self.args.signing_retry_backoff_millis),
+        });
 
         let submission_proxy = if let Some(proxy) = &builder_settings.submission_proxy {
             let Some(proxy) = self.args.chain_spec.get_submission_proxy(proxy) else {
@@ -404,10 +724,25 @@ where
         };
 
         let sender_eoa = signer.address();
+        let beneficiary = ep.beneficiary.or(self.args.beneficiary);
+        // fail fast if every bundle would revert at payout
+        bundle_proposer::check_beneficiary_can_receive(
+            ep_providers.evm(),
+            beneficiary.unwrap_or(sender_eoa),
+            None,
+        )
+        .await
+        .context("beneficiary check failed at builder startup")?;
+
         let proposer_settings = bundle_proposer::Settings {
             chain_spec: self.args.chain_spec.clone(),
             target_bundle_gas: self.args.target_bundle_gas,
-            max_bundle_gas: self.args.max_bundle_gas,
+            // stop adding ops once their gas reaches the entry point's ceiling
+            max_bundle_gas: ep
+                .max_bundle_gas_limit
+                .map_or(self.args.max_bundle_gas, |max| {
+                    self.args.max_bundle_gas.min(max.into())
+                }),
             sender_eoa,
             da_gas_tracking_enabled: self.args.da_gas_tracking_enabled,
             max_expected_storage_slots: self.args.max_expected_storage_slots,
@@ -415,6 +750,38 @@ where
                 .args
                 .verification_gas_limit_efficiency_reject_threshold,
             submission_proxy: submission_proxy.cloned(),
+            dump_calldata: self.args.dump_calldata,
+            max_skip_rounds: self.args.max_skip_rounds,
+            max_bundle_calldata_bytes: self.args.max_bundle_calldata_bytes,
+            estimation_retries: self.args.estimation_retries,
+            max_whittle_iterations: self.args.max_whittle_iterations,
+            check_beneficiary_per_bundle: self.args.check_beneficiary_per_bundle,
+            fixed_gas_price: self.args.fixed_gas_price,
+            simulate_bundle_gas: self.args.simulate_bundle_gas,
+            gas_estimate_buffer_percent: self.args.gas_estimate_buffer_percent,
+            attribute_bundle_gas: self.args.attribute_bundle_gas,
+            allowed_mempools: ep.allowed_mempools.clone(),
+            defer_not_yet_valid_ops: self.args.defer_not_yet_valid_ops,
+            max_build_duration: self
+                .args
+                .max_build_duration_millis
+                .map(Duration::from_millis),
+            priority_fee_mode: ep.priority_fee_mode,
+            beneficiary,
+            max_ops_per_paymaster: self.args.max_ops_per_paymaster,
+            max_aggregators_per_bundle: self.args.max_aggregators_per_bundle,
+            max_op_total_gas: self.args.max_op_total_gas,
+            per_mempool_metrics: self.args.sim_settings.per_mempool_metrics,
+            max_sim_block_age: self.args.max_sim_block_age,
+            verify_aggregator_interface: self.args.verify_aggregator_interface,
+            max_bundle_prefund: self.args.max_bundle_prefund,
+            max_bundle_gas_limit: ep.max_bundle_gas_limit,
+            check_sender_eth_balance: self.args.check_sender_eth_balance,
+            sender_deposit_buffer_percent: self.args.sender_deposit_buffer_percent,
+            min_validity_remaining: Duration::from_secs(self.args.min_validity_remaining_seconds),
+            selection_strategy: self.args.selection_strategy,
+            token_price_oracles: self.token_price_oracles.clone(),
+            fee_overrides: self.fee_overrides.clone(),
         };
 
         let transaction_sender = self.args.sender_args.clone().into_sender(
@@ -424,21 +791,49 @@ where
 
         let tracker_settings = transaction_tracker::Settings {
             replacement_fee_percent_increase: self.args.replacement_fee_percent_increase,
+            persist_state: self.args.persist_tracker_state,
         };
 
+        // the nonce manager and the tracker's state store share a connection
+        let redis_connection = self.connect_redis().await?;
+        let state_store = self.create_tracker_state_store(redis_connection.clone());
+
         let transaction_tracker = TransactionTrackerImpl::new(
             ep_providers.evm().clone(),
             transaction_sender,
             signer,
+            self.create_nonce_manager(redis_connection),
+            state_store,
+            self.confirmation_poller.clone(),
             tracker_settings,
             builder_settings.tag(ep_providers.entry_point().address(), &sender_eoa),
         )
         .await?;
+        let signer_status = transaction_tracker.subscribe_status();
 
         let sender_settings = bundle_sender::Settings {
             max_replacement_underpriced_blocks: self.args.max_replacement_underpriced_blocks,
             max_cancellation_fee_increases: self.args.max_cancellation_fee_increases,
             max_blocks_to_wait_for_mine: self.args.max_blocks_to_wait_for_mine,
+            sticky_replacement: self.args.sticky_replacement,
+            resim_after_blocks: self.args.resim_after_blocks,
+            skip_pending_nonce_duplicates: self.args.skip_pending_nonce_duplicates,
+            warm_up: Duration::from_millis(self.args.stagger_interval_millis) * index as u32,
+            repoll_on_empty: self.args.repoll_on_empty,
+            heartbeat_interval_blocks: self.args.heartbeat_interval_blocks,
+            priority_fee_controller: self.args.adaptive_priority_fee_max_percent.map(
+                |max_percent| fee_controller::Settings {
+                    min_percent: self.args.adaptive_priority_fee_min_percent,
+                    max_percent,
+                    step_percent: self.args.adaptive_priority_fee_step_percent,
+                    slow_inclusion_blocks: self.args.adaptive_priority_fee_slow_blocks,
+                    window: self.args.adaptive_priority_fee_window,
+                },
+            ),
+            max_onchain_revert_attempts: self.args.max_onchain_revert_attempts,
+            builder_index: index as u64,
+            recheck_cancellation_nonce: self.args.recheck_cancellation_nonce,
+            tip_transfer: self.args.tip_transfer,
         };
 
         let proposer = BundleProposerImpl::new(
@@ -455,6 +850,8 @@ where
             self.args.chain_spec.clone(),
             sender_eoa,
             submission_proxy.cloned(),
+            self.transaction_transformer.clone(),
+            self.audit_sink.clone(),
             proposer,
             ep_providers.clone(),
             transaction_tracker,
@@ -468,6 +865,74 @@ where
         let ts = task_spawner.clone();
         task_spawner.spawn_critical("bundle sender", builder.send_bundles_in_loop(ts));
 
-        Ok(send_bundle_tx)
+        Ok((send_bundle_tx, signer_status))
+    }
+}
+
+// Builders sharing a key contend on its lease until the wait for signers times out, so
+// fail fast with a clear message instead
+fn check_signing_keys(signing_scheme: &SigningScheme, num_builders: usize) -> anyhow::Result<()> {
+    let num_keys = signing_scheme.num_keys();
+    if num_keys < num_builders {
+        bail!(
+            "{num_builders} bundle builders are configured, but the signing scheme only has {num_keys} distinct keys. Add keys or reduce the number of builders."
+        );
+    }
+    Ok(())
+}
+
+// Reordering strategies sort a replacement's candidates after the stuck bundle's ops are
+// put first, so those ops could be dropped from the replacement
+fn check_selection_strategy(
+    selection_strategy: &SelectionStrategy,
+    sticky_replacement: bool,
+) -> anyhow::Result<()> {
+    if sticky_replacement && !matches!(selection_strategy, SelectionStrategy::PoolOrder) {
+        bail!(
+            "Sticky replacement requires the pool order selection strategy. Disable sticky replacement or the gas price ordering options."
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::SecretString;
+
+    use super::*;
+
+    #[test]
+    fn test_check_signing_keys_insufficient() {
+        let key = |k: &str| SecretString::from(k.to_string());
+        // the same key listed twice only backs one builder
+        let scheme = SigningScheme::PrivateKeys {
+            private_keys: vec![key("0x01"), key("0x02"), key("0x01")],
+        };
+        assert!(check_signing_keys(&scheme, 2).is_ok());
+        let err = check_signing_keys(&scheme, 3).unwrap_err();
+        assert!(err.to_string().contains("only has 2 distinct keys"));
+
+        let scheme = SigningScheme::AwsKms {
+            key_ids: vec!["a".to_string(), "b".to_string()],
+        };
+        assert!(check_signing_keys(&scheme, 3).is_err());
+    }
+
+    #[test]
+    fn test_check_selection_strategy_sticky_replacement() {
+        let weighted = SelectionStrategy::WeightedFairness { age_weight: 0.1 };
+        for strategy in [
+            SelectionStrategy::PoolOrder,
+            SelectionStrategy::EffectiveGasPrice,
+            weighted,
+        ] {
+            assert!(check_selection_strategy(&strategy, false).is_ok());
+        }
+
+        assert!(check_selection_strategy(&SelectionStrategy::PoolOrder, true).is_ok());
+        let err =
+            check_selection_strategy(&SelectionStrategy::EffectiveGasPrice, true).unwrap_err();
+        assert!(err.to_string().contains("requires the pool order"));
+        assert!(check_selection_strategy(&weighted, true).is_err());
     }
 }
@@ -0,0 +1,202 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::SolCall;
+use rundler_contracts::multicall3::{self, Multicall3};
+use rundler_provider::{GethDebugTracerCallFrame, TransactionRequest};
+use rundler_types::{chain::ChainSpec, UserOperation};
+
+use crate::bundle_proposer::Bundle;
+
+/// Gas added to the bundle transaction's gas limit for the multicall and the tip transfer
+const TIP_TRANSFER_GAS_OVERHEAD: u64 = 50_000;
+
+/// Amount of the tip transferred with each bundle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TipAmount {
+    /// A fixed amount, in wei
+    Fixed(U256),
+    /// A percentage of the bundle's expected reward
+    PercentOfReward(u32),
+}
+
+/// Tip transferred to a fee recipient with each bundle.
+///
+/// The bundle transaction is wrapped in a multicall3 `aggregate3Value` call that calls
+/// `handleOps` and then transfers the tip, neither allowed to fail, so the tip is only paid
+/// if `handleOps` succeeds. The sender pays the tip as the transaction's value, and the
+/// entry point sees the multicall contract as the caller of `handleOps`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TipTransfer {
+    /// Address that receives the tip
+    pub recipient: Address,
+    /// Amount of the tip
+    pub amount: TipAmount,
+}
+
+impl TipTransfer {
+    /// The tip for a bundle with the given expected reward
+    pub(crate) fn tip(&self, expected_reward: U256) -> U256 {
+        match self.amount {
+            TipAmount::Fixed(amount) => amount,
+            TipAmount::PercentOfReward(percent) => {
+                expected_reward * U256::from(percent) / U256::from(100)
+            }
+        }
+    }
+
+    /// Wraps a bundle transaction in a call to `multicall` that sends the original call and
+    /// then transfers `tip` to the recipient
+    pub(crate) fn wrap(
+        &self,
+        tx: TransactionRequest,
+        multicall: Address,
+        tip: U256,
+    ) -> TransactionRequest {
+        let target = tx.to.and_then(|to| to.to().copied()).unwrap_or_default();
+        let value = tx.value.unwrap_or_default();
+        let call = Multicall3::aggregate3ValueCall {
+            calls: vec![
+                Multicall3::Call3Value {
+                    target,
+                    allowFailure: false,
+                    value,
+                    callData: tx.input.input().cloned().unwrap_or_default(),
+                },
+                multicall3::create_call_value_only(self.recipient, tip),
+            ],
+        };
+        let gas = tx
+            .gas
+            .map(|gas| gas.saturating_add(TIP_TRANSFER_GAS_OVERHEAD));
+
+        let mut tx = tx
+            .to(multicall)
+            .value(value + tip)
+            .input(call.abi_encode().into());
+        tx.gas = gas;
+        tx
+    }
+}
+
+/// Expected reward of a bundle: what its ops pay the beneficiary at the bundle's max fee,
+/// less the cost of the bundle transaction at that fee. Ops are charged for the gas they use
+/// rather than their limits, so this is an estimate rather than the realized reward.
+pub(crate) fn expected_reward<UO: UserOperation>(
+    bundle: &Bundle<UO>,
+    chain_spec: &ChainSpec,
+) -> U256 {
+    let max_fee = bundle.gas_fees.max_fee_per_gas;
+    let bundle_size = Some(bundle.len());
+    let paid = bundle
+        .iter_ops()
+        .map(|op| {
+            U256::from(op.bundle_gas_limit(chain_spec, bundle_size))
+                * U256::from(op.max_fee_per_gas().min(max_fee))
+        })
+        .fold(U256::ZERO, |sum, cost| sum + cost);
+    paid.saturating_sub(U256::from(bundle.gas_estimate) * U256::from(max_fee))
+}
+
+/// Finds the first call to `to` in a call trace, searching depth first
+pub(crate) fn find_call_to(
+    frame: GethDebugTracerCallFrame,
+    to: Address,
+) -> Option<GethDebugTracerCallFrame> {
+    if frame.to == Some(to) {
+        return Some(frame);
+    }
+    frame
+        .calls
+        .into_iter()
+        .find_map(|call| find_call_to(call, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, bytes};
+
+    use super::*;
+
+    #[test]
+    fn test_tip_amount() {
+        let recipient = Address::random();
+        let fixed = TipTransfer {
+            recipient,
+            amount: TipAmount::Fixed(U256::from(1_000)),
+        };
+        assert_eq!(fixed.tip(U256::from(50_000)), U256::from(1_000));
+
+        let percent = TipTransfer {
+            recipient,
+            amount: TipAmount::PercentOfReward(10),
+        };
+        assert_eq!(percent.tip(U256::from(50_000)), U256::from(5_000));
+        assert_eq!(percent.tip(U256::ZERO), U256::ZERO);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let multicall = address!("cA11bde05977b3631167028862bE2a173976CA11");
+        let entry_point = Address::random();
+        let tip_transfer = TipTransfer {
+            recipient: Address::random(),
+            amount: TipAmount::Fixed(U256::from(1_000)),
+        };
+        let mut tx = TransactionRequest::default()
+            .to(entry_point)
+            .input(bytes!("deadbeef").into());
+        tx.gas = Some(100_000);
+
+        let wrapped = tip_transfer.wrap(tx, multicall, U256::from(1_000));
+        assert_eq!(wrapped.to.unwrap().to(), Some(&multicall));
+        assert_eq!(wrapped.value, Some(U256::from(1_000)));
+        assert_eq!(wrapped.gas, Some(100_000 + TIP_TRANSFER_GAS_OVERHEAD));
+
+        // handleOps is called first, then the tip is transferred
+        let call =
+            Multicall3::aggregate3ValueCall::abi_decode(wrapped.input.input().unwrap()).unwrap();
+        assert_eq!(call.calls.len(), 2);
+        assert_eq!(call.calls[0].target, entry_point);
+        assert_eq!(call.calls[0].callData, bytes!("deadbeef"));
+        assert!(!call.calls[0].allowFailure);
+        assert_eq!(call.calls[1].target, tip_transfer.recipient);
+        assert_eq!(call.calls[1].value, U256::from(1_000));
+        assert!(!call.calls[1].allowFailure);
+    }
+
+    #[test]
+    fn test_find_call_to() {
+        let entry_point = Address::random();
+        let frame = GethDebugTracerCallFrame {
+            to: Some(Address::random()),
+            calls: vec![
+                GethDebugTracerCallFrame {
+                    to: Some(Address::random()),
+                    ..Default::default()
+                },
+                GethDebugTracerCallFrame {
+                    to: Some(entry_point),
+                    input: bytes!("deadbeef"),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let call = find_call_to(frame, entry_point).unwrap();
+        assert_eq!(call.input, bytes!("deadbeef"));
+        assert!(find_call_to(GethDebugTracerCallFrame::default(), entry_point).is_none());
+    }
+}
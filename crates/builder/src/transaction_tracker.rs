@@ -11,21 +11,31 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::{fmt::Debug, sync::Arc};
+
 use alloy_consensus::Transaction;
 use alloy_primitives::{Address, B256, I256, U256};
-use anyhow::bail;
+use alloy_sol_types::SolEvent;
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 use metrics::{Gauge, Histogram};
 use metrics_derive::Metrics;
 #[cfg(test)]
 use mockall::automock;
-use rundler_provider::{EvmProvider, ReceiptResponse, TransactionRequest};
+use redis::aio::MultiplexedConnection;
+use rundler_contracts::v0_7::IEntryPoint::UserOperationEvent;
+use rundler_provider::{EvmProvider, Log, ReceiptResponse, TransactionRequest};
 use rundler_signer::SignerLease;
-use rundler_types::{pool::AddressUpdate, ExpectedStorage, GasFees};
-use tokio::time::Instant;
+use rundler_types::{builder::SignerStatus, pool::AddressUpdate, ExpectedStorage, GasFees};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::watch, time::Instant};
 use tracing::{info, warn};
 
-use crate::sender::{TransactionSender, TxSenderError};
+use crate::{
+    confirmation_poller::ConfirmationPoller,
+    nonce_manager::NonceManager,
+    sender::{InclusionStatus, TransactionSender, TxSenderError},
+};
 
 /// Keeps track of pending transactions in order to suggest nonces and
 /// replacement fees and ensure that transactions do not get stalled. All sent
@@ -72,9 +82,12 @@ pub(crate) trait TransactionTracker: Send + Sync {
     ///    that a transaction from our account other than one of the ones we are
     ///    tracking has mined. This should not normally happen.
     /// 4. Several new blocks have passed.
+    ///
+    /// `block_number` is the latest block, used to poll the shared confirmation poller.
     async fn process_update(
         &mut self,
         update: &AddressUpdate,
+        block_number: u64,
     ) -> TransactionTrackerResult<Option<TrackerUpdate>>;
 
     /// Resets the tracker to its initial state
@@ -111,6 +124,76 @@ pub(crate) enum TransactionTrackerError {
     Other(#[from] anyhow::Error),
 }
 
+/// Outcome of a single user operation in a mined bundle transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct MinedOpResult {
+    pub(crate) op_hash: B256,
+    pub(crate) success: bool,
+    pub(crate) actual_gas_cost: U256,
+}
+
+/// Storage for a tracker's nonce and pending transactions so that they survive a restart
+#[async_trait]
+pub(crate) trait TrackerStateStore: Send + Sync + Debug {
+    /// Returns the state saved for the tracker of `address`, if any
+    async fn load_state(&self, address: Address) -> anyhow::Result<Option<String>>;
+
+    /// Saves `state` for the tracker of `address`, replacing any previous state
+    async fn save_state(&self, address: Address, state: &str) -> anyhow::Result<()>;
+}
+
+/// Redis backed tracker state store.
+///
+/// Each signer's state is kept under its own key, namespaced by chain ID.
+#[derive(Clone)]
+pub(crate) struct RedisTrackerStateStore {
+    connection: MultiplexedConnection,
+    chain_id: u64,
+}
+
+impl Debug for RedisTrackerStateStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisTrackerStateStore")
+            .field("chain_id", &self.chain_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisTrackerStateStore {
+    pub(crate) fn new(connection: MultiplexedConnection, chain_id: u64) -> Self {
+        Self {
+            connection,
+            chain_id,
+        }
+    }
+
+    fn key(&self, address: Address) -> String {
+        format!("rundler:tracker:{}:{address:?}", self.chain_id)
+    }
+}
+
+#[async_trait]
+impl TrackerStateStore for RedisTrackerStateStore {
+    async fn load_state(&self, address: Address) -> anyhow::Result<Option<String>> {
+        let mut connection = self.connection.clone();
+        redis::cmd("GET")
+            .arg(self.key(address))
+            .query_async(&mut connection)
+            .await
+            .context("should load tracker state")
+    }
+
+    async fn save_state(&self, address: Address, state: &str) -> anyhow::Result<()> {
+        let mut connection = self.connection.clone();
+        redis::cmd("SET")
+            .arg(self.key(address))
+            .arg(state)
+            .query_async(&mut connection)
+            .await
+            .context("should save tracker state")
+    }
+}
+
 pub(crate) type TransactionTrackerResult<T> = std::result::Result<T, TransactionTrackerError>;
 
 #[derive(Debug)]
@@ -125,6 +208,8 @@ pub(crate) enum TrackerUpdate {
         gas_used: Option<u64>,
         gas_price: Option<u128>,
         is_success: bool,
+        /// Outcomes of the ops in the transaction, from its `UserOperationEvent` logs
+        op_results: Vec<MinedOpResult>,
     },
     LatestTxDropped {
         nonce: u64,
@@ -139,6 +224,9 @@ pub(crate) struct TransactionTrackerImpl<P, T> {
     provider: P,
     sender: T,
     signer: SignerLease,
+    nonce_manager: Arc<dyn NonceManager>,
+    state_store: Option<Arc<dyn TrackerStateStore>>,
+    confirmation_poller: Option<Arc<dyn ConfirmationPoller>>,
     settings: Settings,
     nonce: u64,
     balance: U256,
@@ -146,11 +234,15 @@ pub(crate) struct TransactionTrackerImpl<P, T> {
     has_abandoned: bool,
     attempt_count: u64,
     metrics: TransactionTrackerMetrics,
+    status: watch::Sender<SignerStatus>,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct Settings {
     pub(crate) replacement_fee_percent_increase: u32,
+    /// Save the nonce and pending transactions to the state store after each change and
+    /// restore them on startup
+    pub(crate) persist_state: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -164,6 +256,51 @@ struct PendingTransaction {
     sent_at_time: Option<Instant>,
 }
 
+/// The part of a tracker's state that is persisted across restarts
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct PersistedState {
+    nonce: u64,
+    attempt_count: u64,
+    transactions: Vec<PersistedTransaction>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct PersistedTransaction {
+    tx_hash: Option<B256>,
+    max_fee_per_gas: u128,
+    max_priority_fee_per_gas: u128,
+    attempt_number: u64,
+    sent_at_block: Option<u64>,
+}
+
+impl From<&PendingTransaction> for PersistedTransaction {
+    fn from(tx: &PendingTransaction) -> Self {
+        Self {
+            tx_hash: tx.tx_hash,
+            max_fee_per_gas: tx.gas_fees.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.gas_fees.max_priority_fee_per_gas,
+            attempt_number: tx.attempt_number,
+            sent_at_block: tx.sent_at_block,
+        }
+    }
+}
+
+impl From<PersistedTransaction> for PendingTransaction {
+    fn from(tx: PersistedTransaction) -> Self {
+        Self {
+            tx_hash: tx.tx_hash,
+            gas_fees: GasFees {
+                max_fee_per_gas: tx.max_fee_per_gas,
+                max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            },
+            attempt_number: tx.attempt_number,
+            sent_at_block: tx.sent_at_block,
+            // the send time of a transaction from before the restart is unknown
+            sent_at_time: None,
+        }
+    }
+}
+
 impl<P, T> TransactionTrackerImpl<P, T>
 where
     P: EvmProvider,
@@ -173,13 +310,24 @@ where
         provider: P,
         sender: T,
         signer: SignerLease,
+        nonce_manager: Arc<dyn NonceManager>,
+        state_store: Option<Arc<dyn TrackerStateStore>>,
+        confirmation_poller: Option<Arc<dyn ConfirmationPoller>>,
         settings: Settings,
         builder_tag: String,
     ) -> anyhow::Result<Self> {
+        let (status, _) = watch::channel(SignerStatus {
+            builder_tag: builder_tag.clone(),
+            signer: signer.address(),
+            ..Default::default()
+        });
         let mut this = Self {
             provider,
             sender,
             signer,
+            nonce_manager,
+            state_store,
+            confirmation_poller,
             settings,
             nonce: 0,
             balance: U256::ZERO,
@@ -187,14 +335,134 @@ where
             has_abandoned: false,
             attempt_count: 0,
             metrics: TransactionTrackerMetrics::new_with_labels(&[("builder_tag", builder_tag)]),
+            status,
         };
 
-        this.reset().await;
+        if !this.restore_state().await {
+            this.reset().await;
+        }
 
         Ok(this)
     }
 
+    /// Subscribe to the pending state of the tracked signer, updated as it changes and on
+    /// every block the tracker processes
+    pub(crate) fn subscribe_status(&self) -> watch::Receiver<SignerStatus> {
+        self.status.subscribe()
+    }
+
+    fn state_store(&self) -> Option<&Arc<dyn TrackerStateStore>> {
+        self.state_store
+            .as_ref()
+            .filter(|_| self.settings.persist_state)
+    }
+
+    fn persisted_state(&self) -> PersistedState {
+        PersistedState {
+            nonce: self.nonce,
+            attempt_count: self.attempt_count,
+            transactions: self.transactions.iter().map(Into::into).collect(),
+        }
+    }
+
+    // A failed save is only logged: the tracker keeps working from its in-memory state and
+    // just can't resume from it after a restart.
+    async fn save_state(&self) {
+        let Some(store) = self.state_store() else {
+            return;
+        };
+        let result = match serde_json::to_string(&self.persisted_state()) {
+            Ok(state) => store.save_state(self.signer.address(), &state).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            warn!("Failed to save transaction tracker state: {e:?}");
+        }
+    }
+
+    // Restores the state saved by a previous run unless the on-chain nonce has moved past
+    // it. Returns false if nothing was restored and the tracker should be reset instead.
+    async fn restore_state(&mut self) -> bool {
+        let Some(store) = self.state_store() else {
+            return false;
+        };
+        let state = match store.load_state(self.signer.address()).await {
+            Ok(Some(state)) => state,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!("Failed to load transaction tracker state: {e:?}");
+                return false;
+            }
+        };
+        let state: PersistedState = match serde_json::from_str(&state) {
+            Ok(state) => state,
+            Err(e) => {
+                warn!("Failed to parse transaction tracker state: {e:?}");
+                return false;
+            }
+        };
+
+        let address = self.signer.address();
+        let (onchain_nonce, balance) = match tokio::try_join!(
+            self.provider.get_transaction_count(address),
+            self.provider.get_balance(address, None)
+        ) {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Failed to get nonce and balance to restore tracker state: {e:?}");
+                return false;
+            }
+        };
+        if state.nonce < onchain_nonce {
+            info!(
+                "Saved tracker nonce {} is below on-chain nonce {onchain_nonce}, discarding saved state",
+                state.nonce
+            );
+            return false;
+        }
+
+        // A saved hash may belong to a transaction that never reached the node, e.g. if the
+        // process stopped mid-send or the node has since dropped it. Such a transaction will
+        // never mine, so don't wait on it.
+        let mut transactions = Vec::with_capacity(state.transactions.len());
+        for tx in state.transactions {
+            if let Some(tx_hash) = tx.tx_hash {
+                match self.provider.get_transaction_by_hash(tx_hash).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        info!("Saved transaction {tx_hash:?} is unknown to the node, dropping it");
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Failed to look up saved transaction {tx_hash:?} to restore tracker state: {e:?}");
+                        return false;
+                    }
+                }
+            }
+            transactions.push(tx);
+        }
+
+        info!(
+            "Restored tracker state with nonce {} and {} pending transactions",
+            state.nonce,
+            transactions.len()
+        );
+        self.nonce = state.nonce;
+        self.attempt_count = state.attempt_count;
+        self.transactions = transactions.into_iter().map(Into::into).collect();
+        for tx_hash in self.transactions.iter().filter_map(|t| t.tx_hash) {
+            self.watch(tx_hash);
+        }
+        self.has_abandoned = false;
+        self.balance = balance;
+        self.update_metrics();
+        true
+    }
+
     fn set_nonce_and_clear_state(&mut self, nonce: u64) {
+        for tx_hash in self.transactions.iter().filter_map(|t| t.tx_hash) {
+            self.unwatch(tx_hash);
+        }
         self.nonce = nonce;
         self.transactions.clear();
         self.attempt_count = 0;
@@ -202,6 +470,18 @@ where
         self.update_metrics();
     }
 
+    fn watch(&self, tx_hash: B256) {
+        if let Some(poller) = &self.confirmation_poller {
+            poller.watch(tx_hash);
+        }
+    }
+
+    fn unwatch(&self, tx_hash: B256) {
+        if let Some(poller) = &self.confirmation_poller {
+            poller.unwatch(tx_hash);
+        }
+    }
+
     fn validate_transaction(&self, tx: &TransactionRequest) -> anyhow::Result<()> {
         let Some(nonce) = tx.nonce else {
             bail!("transaction given to tracker should have nonce set");
@@ -251,40 +531,225 @@ where
                 .max_priority_fee_per_gas
                 .set(fee.max_priority_fee_per_gas as f64);
         }
+        self.publish_status(None);
     }
 
-    async fn get_mined_tx_info(&self, tx_hash: B256) -> anyhow::Result<Option<MinedTxInfo>> {
-        let (tx, tx_receipt) = tokio::try_join!(
-            self.provider.get_transaction_by_hash(tx_hash),
-            self.provider.get_transaction_receipt(tx_hash),
-        )?;
-        let gas_limit = tx.map(|t| t.inner.gas_limit()).or_else(|| {
-            warn!("failed to find transaction data for tx: {}", tx_hash);
-            None
+    // Publishes the current pending state, as of the given block or else the last one seen
+    fn publish_status(&self, block_number: Option<u64>) {
+        let mut pending = self.transactions.iter().filter(|tx| tx.tx_hash.is_some());
+        let first_sent_block = pending.clone().filter_map(|tx| tx.sent_at_block).min();
+        let fees = pending.next_back().map(|tx| tx.gas_fees);
+        self.status.send_modify(|status| {
+            if let Some(block_number) = block_number {
+                status.block_number = block_number;
+            }
+            status.nonce = self.nonce;
+            status.pending = fees.is_some();
+            status.pending_blocks =
+                first_sent_block.map_or(0, |sent| status.block_number.saturating_sub(sent));
+            status.fees = fees;
         });
-        match tx_receipt {
-            Some(r) => Ok(Some(MinedTxInfo {
-                block_number: r.block_number.unwrap_or(0),
-                gas_limit,
-                gas_used: Some(r.inner.gas_used()),
-                gas_price: Some(r.effective_gas_price),
-                is_success: r.inner.status(),
-            })),
-            None => {
-                warn!("failed to find transaction receipt for tx: {}", tx_hash);
-                Ok(None)
+    }
+
+    fn record_mined_metrics(&self, tx: &PendingTransaction, block_number: u64) {
+        if let Some(sent_at_time) = tx.sent_at_time {
+            let elapsed = Instant::now().duration_since(sent_at_time);
+            self.metrics
+                .txn_time_to_mine_ms
+                .record(elapsed.as_millis() as f64);
+        }
+        if let Some(sent_at_block) = tx.sent_at_block {
+            self.metrics
+                .txn_blocks_to_mine
+                .record((block_number.saturating_sub(sent_at_block)) as f64);
+        }
+    }
+
+    // For senders that confirm inclusion themselves, checks the status of the latest sent
+    // transaction and maps it to a tracker update.
+    async fn check_inclusion_status(&mut self) -> TransactionTrackerResult<Option<TrackerUpdate>> {
+        let Some((tx, tx_hash)) = self
+            .transactions
+            .iter()
+            .rev()
+            .find_map(|t| t.tx_hash.map(|hash| (*t, hash)))
+        else {
+            return Ok(None);
+        };
+
+        let status = match self.sender.get_inclusion_status(tx_hash).await {
+            Ok(status) => status,
+            Err(e) => {
+                warn!("Failed to get inclusion status of transaction {tx_hash:?}: {e:?}");
+                return Ok(None);
+            }
+        };
+
+        match status {
+            InclusionStatus::Unknown | InclusionStatus::Pending => Ok(None),
+            InclusionStatus::Included {
+                block_number,
+                is_success,
+            } => {
+                info!("Sender reported transaction {tx_hash:?} included in block {block_number}");
+                // Receipts carry the gas used and op outcomes, but may not be available
+                let mined_tx_info = self
+                    .get_mined_tx_info(tx_hash)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!("Failed to get receipt of included transaction {tx_hash:?}: {e:?}");
+                        None
+                    })
+                    .unwrap_or(MinedTxInfo {
+                        block_number,
+                        is_success,
+                        ..Default::default()
+                    });
+                self.record_mined_metrics(&tx, block_number);
+
+                let out = TrackerUpdate::Mined {
+                    tx_hash,
+                    nonce: self.nonce,
+                    block_number,
+                    attempt_number: tx.attempt_number,
+                    gas_limit: mined_tx_info.gas_limit,
+                    gas_used: mined_tx_info.gas_used,
+                    gas_price: mined_tx_info.gas_price,
+                    is_success,
+                    op_results: mined_tx_info.op_results,
+                };
+                let new_nonce = self.next_nonce(self.nonce + 1).await;
+                self.set_nonce_and_clear_state(new_nonce);
+                self.save_state().await;
+                Ok(Some(out))
+            }
+            InclusionStatus::Rejected { reason } => {
+                warn!("Sender reported transaction {tx_hash:?} rejected: {reason}");
+                // Keep the fees so that the next attempt still replaces the rejected one, but
+                // stop checking its status
+                for t in self.transactions.iter_mut() {
+                    if t.tx_hash == Some(tx_hash) {
+                        t.tx_hash = None;
+                    }
+                }
+                self.unwatch(tx_hash);
+                self.save_state().await;
+                Ok(Some(TrackerUpdate::LatestTxDropped { nonce: self.nonce }))
             }
         }
     }
+
+    // Takes the confirmation from the shared poller if it has one, so that the receipt
+    // isn't fetched twice
+    async fn get_mined_tx_info(&self, tx_hash: B256) -> anyhow::Result<Option<MinedTxInfo>> {
+        if let Some(info) = self
+            .confirmation_poller
+            .as_ref()
+            .and_then(|poller| poller.take_confirmation(tx_hash))
+        {
+            return Ok(Some(info));
+        }
+        let info = get_mined_tx_info(&self.provider, tx_hash).await?;
+        if info.is_none() {
+            warn!("failed to find transaction receipt for tx: {}", tx_hash);
+        }
+        Ok(info)
+    }
+
+    // Returns a mined update if the shared poller has confirmed one of our transactions
+    // before the chain's nonce update reached us
+    async fn check_confirmations(&mut self, block_number: u64) -> Option<TrackerUpdate> {
+        let poller = self.confirmation_poller.clone()?;
+        poller.poll(block_number).await;
+
+        let (tx, tx_hash, mined_tx_info) = self.transactions.iter().rev().find_map(|t| {
+            let tx_hash = t.tx_hash?;
+            poller
+                .take_confirmation(tx_hash)
+                .map(|info| (*t, tx_hash, info))
+        })?;
+        info!(
+            "Transaction {tx_hash:?} confirmed in block {}",
+            mined_tx_info.block_number
+        );
+        self.record_mined_metrics(&tx, mined_tx_info.block_number);
+
+        let out = TrackerUpdate::Mined {
+            tx_hash,
+            nonce: self.nonce,
+            block_number: mined_tx_info.block_number,
+            attempt_number: tx.attempt_number,
+            gas_limit: mined_tx_info.gas_limit,
+            gas_used: mined_tx_info.gas_used,
+            gas_price: mined_tx_info.gas_price,
+            is_success: mined_tx_info.is_success,
+            op_results: mined_tx_info.op_results,
+        };
+        let new_nonce = self.next_nonce(self.nonce + 1).await;
+        self.set_nonce_and_clear_state(new_nonce);
+        self.save_state().await;
+        Some(out)
+    }
+}
+
+/// Looks up the receipt of a transaction, returning `None` if it hasn't mined.
+///
+/// The transaction itself is only fetched, for its gas limit, once it has a receipt.
+pub(crate) async fn get_mined_tx_info<P: EvmProvider>(
+    provider: &P,
+    tx_hash: B256,
+) -> anyhow::Result<Option<MinedTxInfo>> {
+    let Some(r) = provider.get_transaction_receipt(tx_hash).await? else {
+        return Ok(None);
+    };
+    let gas_limit = provider
+        .get_transaction_by_hash(tx_hash)
+        .await?
+        .map(|t| t.inner.gas_limit())
+        .or_else(|| {
+            warn!("failed to find transaction data for tx: {}", tx_hash);
+            None
+        });
+    Ok(Some(MinedTxInfo {
+        block_number: r.block_number.unwrap_or(0),
+        gas_limit,
+        gas_used: Some(r.inner.gas_used()),
+        gas_price: Some(r.effective_gas_price),
+        is_success: r.inner.status(),
+        op_results: decode_op_results(r.inner.inner.logs()),
+    }))
 }
 
-#[derive(Debug, Default)]
-struct MinedTxInfo {
-    block_number: u64,
-    gas_limit: Option<u64>,
-    gas_used: Option<u64>,
-    gas_price: Option<u128>,
-    is_success: bool,
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MinedTxInfo {
+    pub(crate) block_number: u64,
+    pub(crate) gas_limit: Option<u64>,
+    pub(crate) gas_used: Option<u64>,
+    pub(crate) gas_price: Option<u128>,
+    pub(crate) is_success: bool,
+    pub(crate) op_results: Vec<MinedOpResult>,
+}
+
+// `UserOperationEvent` has the same signature in v0.6 and v0.7, so the v0.7
+// definition decodes both.
+fn decode_op_results(logs: &[Log]) -> Vec<MinedOpResult> {
+    logs.iter()
+        .filter(|log| log.topic0() == Some(&UserOperationEvent::SIGNATURE_HASH))
+        .filter_map(|log| match log.log_decode::<UserOperationEvent>() {
+            Ok(decoded) => {
+                let event = decoded.data();
+                Some(MinedOpResult {
+                    op_hash: event.userOpHash,
+                    success: event.success,
+                    actual_gas_cost: event.actualGasCost,
+                })
+            }
+            Err(e) => {
+                warn!("Failed to decode UserOperationEvent {log:?}: {e:?}");
+                None
+            }
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -334,6 +799,7 @@ where
         block_number: u64,
     ) -> TransactionTrackerResult<B256> {
         self.validate_transaction(&tx)?;
+        self.publish_status(Some(block_number));
         let gas_fees = GasFees {
             max_fee_per_gas: tx.max_fee_per_gas.unwrap_or(0),
             max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap_or(0),
@@ -362,6 +828,7 @@ where
         match tx_hash {
             Ok(tx_hash) => {
                 info!("Sent transaction {:?} nonce: {:?}", tx_hash, tx_nonce);
+                self.watch(tx_hash);
                 self.transactions.push(PendingTransaction {
                     tx_hash: Some(tx_hash),
                     gas_fees,
@@ -372,6 +839,7 @@ where
                 self.has_abandoned = false;
                 self.attempt_count += 1;
                 self.update_metrics();
+                self.save_state().await;
                 Ok(tx_hash)
             }
             Err(e)
@@ -403,6 +871,7 @@ where
                 self.has_abandoned = false;
                 self.attempt_count += 1;
                 self.update_metrics();
+                self.save_state().await;
                 Err(e.into())
             }
             Err(e) => Err(e.into()),
@@ -450,6 +919,7 @@ where
                     "Sent cancellation tx {:?} fees: {:?}",
                     cancel_info.tx_hash, gas_fees
                 );
+                self.watch(cancel_info.tx_hash);
 
                 self.transactions.push(PendingTransaction {
                     tx_hash: Some(cancel_info.tx_hash),
@@ -461,6 +931,7 @@ where
 
                 self.attempt_count += 1;
                 self.update_metrics();
+                self.save_state().await;
                 Ok(Some(cancel_info.tx_hash))
             }
             Err(TxSenderError::ReplacementUnderpriced | TxSenderError::Underpriced) => {
@@ -483,6 +954,7 @@ where
                         sent_at_time: None,
                     });
                 };
+                self.save_state().await;
 
                 Err(TransactionTrackerError::ReplacementUnderpriced)
             }
@@ -493,6 +965,7 @@ where
     async fn process_update(
         &mut self,
         update: &AddressUpdate,
+        block_number: u64,
     ) -> TransactionTrackerResult<Option<TrackerUpdate>> {
         // unlikely that the balance will fail to convert to I256, so just don't crash if it does
         // this is only used for logging
@@ -500,14 +973,17 @@ where
             - I256::try_from(self.balance).unwrap_or(I256::ZERO);
 
         self.balance = update.balance;
+        self.publish_status(Some(block_number));
 
-        let Some(update_nonce) = update.nonce else {
-            return Ok(None);
+        let Some(update_nonce) = update.nonce.filter(|&n| n >= self.nonce) else {
+            // The chain hasn't seen our nonce used, but the shared poller or the sender may
+            // have seen it included
+            if let Some(out) = self.check_confirmations(block_number).await {
+                return Ok(Some(out));
+            }
+            return self.check_inclusion_status().await;
         };
-        if self.nonce > update_nonce {
-            return Ok(None);
-        }
-        let new_nonce = update_nonce + 1;
+        let new_nonce = self.next_nonce(update_nonce + 1).await;
 
         // The nonce has changed. Check to see which of our transactions has
         // mined, if any.
@@ -538,22 +1014,14 @@ where
                     gas_used: mined_tx_info.gas_used,
                     gas_price: mined_tx_info.gas_price,
                     is_success: mined_tx_info.is_success,
+                    op_results: mined_tx_info.op_results,
                 };
 
-                if let Some(sent_at_time) = tx.sent_at_time {
-                    let elapsed = Instant::now().duration_since(sent_at_time);
-                    self.metrics
-                        .txn_time_to_mine_ms
-                        .record(elapsed.as_millis() as f64);
-                }
-                if let Some(sent_at_block) = tx.sent_at_block {
-                    self.metrics
-                        .txn_blocks_to_mine
-                        .record((mined_tx_info.block_number.saturating_sub(sent_at_block)) as f64);
-                }
+                self.record_mined_metrics(tx, mined_tx_info.block_number);
             }
         }
         self.set_nonce_and_clear_state(new_nonce);
+        self.save_state().await;
         return Ok(Some(out));
     }
 
@@ -562,9 +1030,11 @@ where
         let balance_fut = self.provider.get_balance(self.signer.address(), None);
         let (nonce, balance) =
             tokio::try_join!(nonce_fut, balance_fut).unwrap_or((self.nonce, self.balance));
+        let nonce = self.next_nonce(nonce).await;
 
         self.set_nonce_and_clear_state(nonce);
         self.balance = balance;
+        self.save_state().await;
 
         // reset metrics when tracker reset.
         self.metrics.num_pending_transactions.set(0);
@@ -572,6 +1042,22 @@ where
         self.metrics.max_priority_fee_per_gas.set(0);
     }
 
+    // Falls back to the on-chain nonce if the nonce manager fails, so that an outage of a
+    // shared nonce store doesn't stop the builder.
+    async fn next_nonce(&self, onchain_nonce: u64) -> u64 {
+        match self
+            .nonce_manager
+            .next_nonce(self.signer.address(), onchain_nonce)
+            .await
+        {
+            Ok(nonce) => nonce,
+            Err(e) => {
+                warn!("Failed to get next nonce from nonce manager, using on-chain nonce {onchain_nonce}: {e:?}");
+                onchain_nonce
+            }
+        }
+    }
+
     fn abandon(&mut self) {
         self.has_abandoned = true;
         self.attempt_count = 0;
@@ -626,11 +1112,11 @@ struct TransactionTrackerMetrics {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{collections::HashMap, sync::Arc};
 
     use alloy_consensus::{transaction::Recovered, Signed, TxEip1559};
     use alloy_network::TxSigner;
-    use alloy_primitives::{Address, Signature, U256};
+    use alloy_primitives::{Address, Log as PrimitiveLog, LogData, Signature, U256};
     use alloy_rpc_types_eth::{
         Transaction as AlloyTransaction, TransactionReceipt as AlloyTransactionReceipt,
     };
@@ -640,7 +1126,10 @@ mod tests {
     };
 
     use super::*;
-    use crate::sender::MockTransactionSender;
+    use crate::{
+        confirmation_poller::ReceiptConfirmationPoller, nonce_manager::OnChainNonceManager,
+        sender::MockTransactionSender,
+    };
 
     struct MockTxSigner {}
 
@@ -679,17 +1168,46 @@ mod tests {
         sender: MockTransactionSender,
         provider: MockEvmProvider,
         signer: MockTxSigner,
+    ) -> TransactionTrackerImpl<MockEvmProvider, MockTransactionSender> {
+        create_tracker_with_store(sender, provider, signer, None).await
+    }
+
+    async fn create_tracker_with_store(
+        sender: MockTransactionSender,
+        provider: MockEvmProvider,
+        signer: MockTxSigner,
+        state_store: Option<Arc<dyn TrackerStateStore>>,
+    ) -> TransactionTrackerImpl<MockEvmProvider, MockTransactionSender> {
+        create_tracker_with(sender, provider, signer, state_store, None).await
+    }
+
+    async fn create_tracker_with(
+        sender: MockTransactionSender,
+        provider: MockEvmProvider,
+        signer: MockTxSigner,
+        state_store: Option<Arc<dyn TrackerStateStore>>,
+        confirmation_poller: Option<Arc<dyn ConfirmationPoller>>,
     ) -> TransactionTrackerImpl<MockEvmProvider, MockTransactionSender> {
         let settings = Settings {
             replacement_fee_percent_increase: 5,
+            persist_state: state_store.is_some(),
         };
 
         let lease = SignerLease::new(Arc::new(signer), 1);
 
         let tracker: TransactionTrackerImpl<MockEvmProvider, MockTransactionSender> =
-            TransactionTrackerImpl::new(provider, sender, lease, settings, "test".to_string())
-                .await
-                .unwrap();
+            TransactionTrackerImpl::new(
+                provider,
+                sender,
+                lease,
+                Arc::new(OnChainNonceManager),
+                state_store,
+                confirmation_poller,
+                settings,
+                "test".to_string(),
+            )
+            .await
+            .unwrap();
 
         tracker
     }
@@ -817,7 +1335,7 @@ mod tests {
             balance: U256::ZERO,
         };
 
-        let tracker_update = tracker.process_update(&update).await.unwrap().unwrap();
+        let tracker_update = tracker.process_update(&update, 0).await.unwrap().unwrap();
 
         assert!(matches!(
             tracker_update,
@@ -825,6 +1343,76 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_signer_status() {
+        let (mut sender, provider, signer) = create_base_config(0);
+        sender
+            .expect_send_transaction()
+            .returning(move |_a, _b, _c| Box::pin(async { Ok(B256::ZERO) }));
+        sender
+            .expect_get_inclusion_status()
+            .returning(|_| Box::pin(async { Ok(InclusionStatus::Pending) }));
+
+        let mut tracker = create_tracker(sender, provider, signer).await;
+        let status = tracker.subscribe_status();
+        assert_eq!(
+            *status.borrow(),
+            SignerStatus {
+                builder_tag: "test".to_string(),
+                ..Default::default()
+            }
+        );
+
+        let tx = TransactionRequest::default()
+            .nonce(0)
+            .max_fee_per_gas(10000)
+            .max_priority_fee_per_gas(100);
+        tracker
+            .send_transaction(tx, &ExpectedStorage::default(), 5)
+            .await
+            .unwrap();
+
+        // still pending 3 blocks later
+        let update = AddressUpdate {
+            address: Address::ZERO,
+            nonce: None,
+            mined_tx_hashes: vec![],
+            balance: U256::ZERO,
+        };
+        assert!(tracker.process_update(&update, 8).await.unwrap().is_none());
+        assert_eq!(
+            *status.borrow(),
+            SignerStatus {
+                builder_tag: "test".to_string(),
+                signer: Address::ZERO,
+                nonce: 0,
+                pending: true,
+                pending_blocks: 3,
+                fees: Some(GasFees {
+                    max_fee_per_gas: 10000,
+                    max_priority_fee_per_gas: 100,
+                }),
+                block_number: 8,
+            }
+        );
+
+        // the nonce is used, so nothing is pending anymore
+        let update = AddressUpdate {
+            nonce: Some(0),
+            ..update
+        };
+        tracker.process_update(&update, 9).await.unwrap();
+        assert_eq!(
+            *status.borrow(),
+            SignerStatus {
+                builder_tag: "test".to_string(),
+                nonce: 1,
+                block_number: 9,
+                ..Default::default()
+            }
+        );
+    }
+
     #[tokio::test]
     async fn test_underpriced_txn() {
         let (mut sender, provider, signer) = create_base_config(0);
@@ -952,7 +1540,7 @@ mod tests {
             balance: U256::from(1000),
         };
 
-        let tracker_update = tracker.process_update(&update).await.unwrap().unwrap();
+        let tracker_update = tracker.process_update(&update, 0).await.unwrap().unwrap();
 
         assert!(matches!(tracker_update, TrackerUpdate::Mined { .. }));
 
@@ -966,4 +1554,370 @@ mod tests {
             }
         );
     }
+
+    #[tokio::test]
+    async fn test_shared_confirmation_poller() {
+        let tx_hashes = [B256::random(), B256::random()];
+
+        // a single poll looks up the receipts of both trackers' transactions
+        let mut poller_provider = MockEvmProvider::new();
+        poller_provider
+            .expect_get_transaction_receipt()
+            .times(2)
+            .returning(|hash: B256| {
+                Ok(Some(WithOtherFields::new(AlloyTransactionReceipt {
+                    inner: AnyReceiptEnvelope {
+                        inner: ReceiptWithBloom::default(),
+                        r#type: 0,
+                    },
+                    transaction_hash: hash,
+                    transaction_index: None,
+                    block_hash: None,
+                    block_number: Some(1),
+                    gas_used: 0,
+                    effective_gas_price: 0,
+                    blob_gas_used: None,
+                    blob_gas_price: None,
+                    from: Address::ZERO,
+                    to: None,
+                    contract_address: None,
+                })))
+            });
+        poller_provider
+            .expect_get_transaction_by_hash()
+            .times(2)
+            .returning(|hash: B256| Ok(Some(sign_transaction(hash))));
+        let poller: Arc<dyn ConfirmationPoller> =
+            Arc::new(ReceiptConfirmationPoller::new(poller_provider));
+
+        let mut trackers = vec![];
+        for tx_hash in tx_hashes {
+            // the trackers neither look up receipts nor ask their senders
+            let (mut sender, provider, signer) = create_base_config(0);
+            sender
+                .expect_send_transaction()
+                .returning(move |_a, _b, _c| Box::pin(async move { Ok(tx_hash) }));
+            let mut tracker =
+                create_tracker_with(sender, provider, signer, None, Some(poller.clone())).await;
+            tracker
+                .send_transaction(
+                    TransactionRequest::default().nonce(0),
+                    &ExpectedStorage::default(),
+                    0,
+                )
+                .await
+                .unwrap();
+            trackers.push(tracker);
+        }
+
+        // the chain's nonce update hasn't reached the trackers yet
+        let update = AddressUpdate {
+            address: Address::ZERO,
+            nonce: None,
+            mined_tx_hashes: vec![],
+            balance: U256::ZERO,
+        };
+        for (tracker, tx_hash) in trackers.iter_mut().zip(tx_hashes) {
+            let tracker_update = tracker.process_update(&update, 1).await.unwrap().unwrap();
+            assert!(matches!(
+                tracker_update,
+                TrackerUpdate::Mined {
+                    tx_hash: mined,
+                    block_number: 1,
+                    ..
+                } if mined == tx_hash
+            ));
+            assert_eq!(tracker.get_state().unwrap().nonce, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_update_included_by_sender() {
+        let (mut sender, mut provider, signer) = create_base_config(0);
+        let tx_hash = B256::random();
+
+        sender
+            .expect_send_transaction()
+            .returning(move |_a, _b, _c| Box::pin(async move { Ok(tx_hash) }));
+        sender.expect_get_inclusion_status().returning(|_| {
+            Box::pin(async {
+                Ok(InclusionStatus::Included {
+                    block_number: 5,
+                    is_success: true,
+                })
+            })
+        });
+        // the receipt isn't available
+        provider
+            .expect_get_transaction_by_hash()
+            .returning(|_| Ok(None));
+        provider
+            .expect_get_transaction_receipt()
+            .returning(|_| Ok(None));
+
+        let mut tracker = create_tracker(sender, provider, signer).await;
+        let tx = TransactionRequest::default().nonce(0);
+        tracker
+            .send_transaction(tx, &ExpectedStorage::default(), 0)
+            .await
+            .unwrap();
+
+        // the chain hasn't seen the nonce used
+        let update = AddressUpdate {
+            address: Address::ZERO,
+            nonce: None,
+            mined_tx_hashes: vec![],
+            balance: U256::ZERO,
+        };
+        let tracker_update = tracker.process_update(&update, 0).await.unwrap().unwrap();
+
+        assert!(matches!(
+            tracker_update,
+            TrackerUpdate::Mined {
+                tx_hash: mined_hash,
+                nonce: 0,
+                block_number: 5,
+                is_success: true,
+                ..
+            } if mined_hash == tx_hash
+        ));
+        assert_eq!(tracker.get_state().unwrap().nonce, 1);
+        assert_eq!(tracker.num_pending_transactions(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_process_update_rejected_by_sender() {
+        let (mut sender, provider, signer) = create_base_config(0);
+
+        sender
+            .expect_send_transaction()
+            .returning(move |_a, _b, _c| Box::pin(async { Ok(B256::repeat_byte(1)) }));
+        sender
+            .expect_get_inclusion_status()
+            .times(1)
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(InclusionStatus::Rejected {
+                        reason: "bundle rejected".to_string(),
+                    })
+                })
+            });
+
+        let mut tracker = create_tracker(sender, provider, signer).await;
+        let tx = TransactionRequest::default()
+            .nonce(0)
+            .max_fee_per_gas(10000);
+        tracker
+            .send_transaction(tx, &ExpectedStorage::default(), 0)
+            .await
+            .unwrap();
+
+        let update = AddressUpdate {
+            address: Address::ZERO,
+            nonce: None,
+            mined_tx_hashes: vec![],
+            balance: U256::ZERO,
+        };
+        let tracker_update = tracker.process_update(&update, 0).await.unwrap().unwrap();
+        assert!(matches!(
+            tracker_update,
+            TrackerUpdate::LatestTxDropped { nonce: 0 }
+        ));
+
+        // the next attempt must still replace the rejected transaction, whose status is
+        // no longer checked
+        assert_eq!(
+            tracker.get_state().unwrap().required_fees,
+            Some(GasFees {
+                max_fee_per_gas: 10500,
+                max_priority_fee_per_gas: 0,
+            })
+        );
+        assert!(tracker.process_update(&update, 0).await.unwrap().is_none());
+    }
+
+    #[derive(Debug, Default)]
+    struct MemoryStateStore(std::sync::Mutex<HashMap<Address, String>>);
+
+    #[async_trait]
+    impl TrackerStateStore for MemoryStateStore {
+        async fn load_state(&self, address: Address) -> anyhow::Result<Option<String>> {
+            Ok(self.0.lock().unwrap().get(&address).cloned())
+        }
+
+        async fn save_state(&self, address: Address, state: &str) -> anyhow::Result<()> {
+            self.0.lock().unwrap().insert(address, state.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_persist_state_round_trip() {
+        let store: Arc<dyn TrackerStateStore> = Arc::new(MemoryStateStore::default());
+
+        let (mut sender, provider, signer) = create_base_config(3);
+        sender
+            .expect_send_transaction()
+            .returning(move |_a, _b, _c| Box::pin(async { Ok(B256::repeat_byte(1)) }));
+        let mut tracker =
+            create_tracker_with_store(sender, provider, signer, Some(store.clone())).await;
+        let tx = TransactionRequest::default()
+            .nonce(3)
+            .max_fee_per_gas(10000)
+            .max_priority_fee_per_gas(1000);
+        tracker
+            .send_transaction(tx, &ExpectedStorage::default(), 10)
+            .await
+            .unwrap();
+        let saved = tracker.persisted_state();
+
+        // restart with the transaction still pending
+        let (sender, mut provider, signer) = create_base_config(3);
+        provider
+            .expect_get_transaction_by_hash()
+            .returning(|hash: B256| Ok(Some(sign_transaction(hash))));
+        let restored = create_tracker_with_store(sender, provider, signer, Some(store)).await;
+
+        assert_eq!(restored.persisted_state(), saved);
+        assert_eq!(restored.num_pending_transactions(), 1);
+        assert_eq!(
+            restored.get_state().unwrap().required_fees,
+            Some(GasFees {
+                max_fee_per_gas: 10500,
+                max_priority_fee_per_gas: 1050,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_discards_stale_state() {
+        let store: Arc<dyn TrackerStateStore> = Arc::new(MemoryStateStore::default());
+
+        let (mut sender, provider, signer) = create_base_config(3);
+        sender
+            .expect_send_transaction()
+            .returning(move |_a, _b, _c| Box::pin(async { Ok(B256::repeat_byte(1)) }));
+        let mut tracker =
+            create_tracker_with_store(sender, provider, signer, Some(store.clone())).await;
+        let tx = TransactionRequest::default()
+            .nonce(3)
+            .max_fee_per_gas(10000);
+        tracker
+            .send_transaction(tx, &ExpectedStorage::default(), 10)
+            .await
+            .unwrap();
+
+        // restart after the transaction mined
+        let (sender, provider, signer) = create_base_config(4);
+        let restored = create_tracker_with_store(sender, provider, signer, Some(store)).await;
+
+        assert_eq!(restored.num_pending_transactions(), 0);
+        assert_eq!(
+            restored.get_state().unwrap(),
+            TrackerState {
+                nonce: 4,
+                balance: U256::ZERO,
+                required_fees: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_drops_unknown_transactions() {
+        let store: Arc<dyn TrackerStateStore> = Arc::new(MemoryStateStore::default());
+
+        let (mut sender, provider, signer) = create_base_config(3);
+        sender
+            .expect_send_transaction()
+            .returning(move |_a, _b, _c| Box::pin(async { Ok(B256::repeat_byte(1)) }));
+        let mut tracker =
+            create_tracker_with_store(sender, provider, signer, Some(store.clone())).await;
+        let tx = TransactionRequest::default()
+            .nonce(3)
+            .max_fee_per_gas(10000)
+            .max_priority_fee_per_gas(1000);
+        tracker
+            .send_transaction(tx, &ExpectedStorage::default(), 10)
+            .await
+            .unwrap();
+
+        // restart against a node that never saw the transaction
+        let (sender, mut provider, signer) = create_base_config(3);
+        provider
+            .expect_get_transaction_by_hash()
+            .returning(|_: B256| Ok(None));
+        let restored = create_tracker_with_store(sender, provider, signer, Some(store)).await;
+
+        assert_eq!(restored.num_pending_transactions(), 0);
+        assert_eq!(
+            restored.get_state().unwrap(),
+            TrackerState {
+                nonce: 3,
+                balance: U256::ZERO,
+                required_fees: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_persist_state_disabled() {
+        let store = Arc::new(MemoryStateStore::default());
+
+        let (sender, provider, signer) = create_base_config(3);
+        let mut tracker = create_tracker(sender, provider, signer).await;
+        tracker.state_store = Some(store.clone() as Arc<dyn TrackerStateStore>);
+        tracker.reset().await;
+
+        assert!(store.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decode_op_results() {
+        let op_event = |op_hash: B256, success: bool, actual_gas_cost: u64| Log {
+            inner: PrimitiveLog {
+                address: Address::ZERO,
+                data: UserOperationEvent {
+                    userOpHash: op_hash,
+                    sender: Address::random(),
+                    paymaster: Address::ZERO,
+                    nonce: U256::ZERO,
+                    success,
+                    actualGasCost: U256::from(actual_gas_cost),
+                    actualGasUsed: U256::ZERO,
+                }
+                .encode_log_data(),
+            },
+            ..Default::default()
+        };
+        let other = Log {
+            inner: PrimitiveLog {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(vec![B256::random()], Default::default()),
+            },
+            ..Default::default()
+        };
+
+        let (first, second) = (B256::random(), B256::random());
+        let logs = vec![
+            op_event(first, true, 100),
+            other,
+            op_event(second, false, 200),
+        ];
+
+        assert_eq!(
+            decode_op_results(&logs),
+            vec![
+                MinedOpResult {
+                    op_hash: first,
+                    success: true,
+                    actual_gas_cost: U256::from(100),
+                },
+                MinedOpResult {
+                    op_hash: second,
+                    success: false,
+                    actual_gas_cost: U256::from(200),
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,40 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use rundler_provider::TransactionRequest;
+
+/// Transforms a bundle transaction before it is signed and sent.
+///
+/// Allows deployments to, for example, wrap `handleOps` in a custom multicall or add a
+/// tip transfer by changing the transaction's `to`, `input` and `value`. The transformed
+/// transaction is the one tracked for mining and replacement.
+///
+/// The nonce is set by the bundle sender after transforming, and the gas limit and fees
+/// are kept from the bundle estimate, so transformations that add significant gas
+/// usage should raise the gas limit themselves.
+pub trait TransactionTransformer: Send + Sync + Debug {
+    /// Transform the bundle transaction
+    fn transform(&self, tx: TransactionRequest) -> TransactionRequest;
+}
+
+/// Transformer that sends bundle transactions unchanged
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopTransactionTransformer;
+
+impl TransactionTransformer for NoopTransactionTransformer {
+    fn transform(&self, tx: TransactionRequest) -> TransactionRequest {
+        tx
+    }
+}
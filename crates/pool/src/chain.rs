@@ -42,7 +42,7 @@ use rundler_task::{block_watcher, GracefulShutdown};
 use rundler_types::{pool::AddressUpdate, EntryPointVersion, Timestamp, UserOperationId};
 use tokio::{
     select,
-    sync::{broadcast, Semaphore},
+    sync::{broadcast, watch, Semaphore},
     time,
 };
 use tracing::{info, instrument, warn};
@@ -75,6 +75,8 @@ pub(crate) struct Chain<P: EvmProvider> {
 
     sender: Arc<broadcast::Sender<Arc<ChainUpdate>>>,
     to_track: Arc<RwLock<HashSet<Address>>>,
+    /// Hashes of new blocks from a block subscription, if any.
+    new_blocks: Option<watch::Receiver<B256>>,
 }
 
 #[derive(Clone)]
@@ -225,6 +227,7 @@ impl<P: EvmProvider> Chain<P> {
             metrics: ChainMetrics::default(),
             sender: Arc::new(update_sender),
             to_track,
+            new_blocks: None,
         }
     }
 
@@ -235,6 +238,13 @@ impl<P: EvmProvider> Chain<P> {
         }
     }
 
+    /// Sets a receiver of new block hashes, used to look for a new block as soon as one is
+    /// announced instead of waiting for the next poll
+    pub(crate) fn with_new_blocks(mut self, new_blocks: watch::Receiver<B256>) -> Self {
+        self.new_blocks = Some(new_blocks);
+        self
+    }
+
     pub(crate) async fn watch(mut self, shutdown: GracefulShutdown) {
         loop {
             select! {
@@ -358,11 +368,12 @@ impl<P: EvmProvider> Chain<P> {
         let mut latest_block_hash = full_block_hash;
 
         loop {
-            let (hash, block) = block_watcher::wait_for_new_block(
+            let (hash, block) = block_watcher::wait_for_new_block_notified(
                 &self.provider,
                 latest_block_hash,
                 self.settings.poll_interval,
                 BlockId::latest(),
+                self.new_blocks.as_mut(),
             )
             .await;
             latest_block_hash = hash;
@@ -28,7 +28,10 @@ mod mempool;
 pub use mempool::PoolConfig;
 
 mod server;
-pub use server::{LocalPoolBuilder, LocalPoolHandle, RemotePoolClient};
+pub use server::{
+    remote_simulator_server_task, LocalPoolBuilder, LocalPoolHandle, RemotePoolClient,
+    RemoteSimulator,
+};
 
 mod task;
 pub use task::{Args as PoolTaskArgs, PoolTask};
@@ -167,6 +167,9 @@ pub struct PoolConfig {
     pub paymaster_cache_length: u32,
     /// Boolean field used to toggle the operation of the reputation tracker
     pub reputation_tracking_enabled: bool,
+    /// Entities first seen within this period are not throttled, regardless of their
+    /// reputation. Zero disables the grace period.
+    pub reputation_grace_period: Duration,
     /// Boolean field used to toggle the operation of the DA tracker
     pub da_gas_tracking_enabled: bool,
     /// The minimum number of blocks a user operation must be in the mempool before it can be dropped
@@ -234,22 +237,10 @@ mod tests {
             sim_block_number: 0,
             account_is_staked: true,
             entity_infos: EntityInfos {
-                factory: Some(EntityInfo {
-                    entity: Entity::factory(factory),
-                    is_staked: false,
-                }),
-                sender: EntityInfo {
-                    entity: Entity::account(sender),
-                    is_staked: false,
-                },
-                paymaster: Some(EntityInfo {
-                    entity: Entity::paymaster(paymaster),
-                    is_staked: false,
-                }),
-                aggregator: Some(EntityInfo {
-                    entity: Entity::aggregator(aggregator),
-                    is_staked: false,
-                }),
+                factory: Some(EntityInfo::new(Entity::factory(factory), false)),
+                sender: EntityInfo::new(Entity::account(sender), false),
+                paymaster: Some(EntityInfo::new(Entity::paymaster(paymaster), false)),
+                aggregator: Some(EntityInfo::new(Entity::aggregator(aggregator), false)),
             },
             da_gas_data: Default::default(),
             filter_id: None,
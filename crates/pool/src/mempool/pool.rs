@@ -1156,10 +1156,7 @@ mod tests {
         ];
         for mut op in ops.into_iter() {
             op.aggregator = Some(agg);
-            op.entity_infos.aggregator = Some(EntityInfo {
-                entity: Entity::aggregator(agg),
-                is_staked: false,
-            });
+            op.entity_infos.aggregator = Some(EntityInfo::new(Entity::aggregator(agg), false));
             pool.add_operation(op.clone(), 0, 0).unwrap();
         }
         assert_eq!(pool.by_hash.len(), 3);
@@ -1195,10 +1192,7 @@ mod tests {
             }),
         ];
         for mut op in ops.into_iter() {
-            op.entity_infos.paymaster = Some(EntityInfo {
-                entity: Entity::paymaster(paymaster),
-                is_staked: false,
-            });
+            op.entity_infos.paymaster = Some(EntityInfo::new(Entity::paymaster(paymaster), false));
             pool.add_operation(op.clone(), 0, 0).unwrap();
         }
         assert_eq!(pool.by_hash.len(), 3);
@@ -1222,14 +1216,8 @@ mod tests {
             init_code: factory.to_vec().into(),
             ..base_required_fields()
         });
-        op.entity_infos.paymaster = Some(EntityInfo {
-            entity: Entity::paymaster(paymaster),
-            is_staked: false,
-        });
-        op.entity_infos.factory = Some(EntityInfo {
-            entity: Entity::factory(factory),
-            is_staked: false,
-        });
+        op.entity_infos.paymaster = Some(EntityInfo::new(Entity::paymaster(paymaster), false));
+        op.entity_infos.factory = Some(EntityInfo::new(Entity::factory(factory), false));
 
         let count = 5;
         let mut hashes = vec![];
@@ -1333,17 +1321,11 @@ mod tests {
             ..base_required_fields()
         });
 
-        po1.entity_infos.paymaster = Some(EntityInfo {
-            entity: Entity::paymaster(paymaster1),
-            is_staked: false,
-        });
+        po1.entity_infos.paymaster = Some(EntityInfo::new(Entity::paymaster(paymaster1), false));
         let _ = pool.add_operation(po1, 0, 0).unwrap();
         assert_eq!(pool.address_count(&paymaster1), 1);
 
-        po2.entity_infos.paymaster = Some(EntityInfo {
-            entity: Entity::paymaster(paymaster2),
-            is_staked: false,
-        });
+        po2.entity_infos.paymaster = Some(EntityInfo::new(Entity::paymaster(paymaster2), false));
         let _ = pool.add_operation(po2.clone(), 0, 0).unwrap();
 
         assert_eq!(pool.address_count(&sender), 1);
@@ -1756,10 +1738,7 @@ mod tests {
                 .into(),
             entity_infos: EntityInfos {
                 factory: None,
-                sender: EntityInfo {
-                    entity: Entity::account(sender),
-                    is_staked: false,
-                },
+                sender: EntityInfo::new(Entity::account(sender), false),
                 paymaster: None,
                 aggregator: None,
             },
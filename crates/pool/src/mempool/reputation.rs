@@ -13,7 +13,7 @@
 
 use std::{
     collections::{HashMap, HashSet},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use alloy_primitives::Address;
@@ -33,6 +33,8 @@ pub(crate) struct ReputationParams {
     tracking_enabled: bool,
     decay_interval_secs: u64,
     decay_factor: u64,
+    // Entities first seen within this period are never throttled
+    grace_period: Duration,
 }
 
 impl Default for ReputationParams {
@@ -48,14 +50,16 @@ impl Default for ReputationParams {
             tracking_enabled: true,
             decay_interval_secs: 3600,
             decay_factor: 24,
+            grace_period: Duration::ZERO,
         }
     }
 }
 
 impl ReputationParams {
-    pub(crate) fn new(tracking_enabled: bool) -> Self {
+    pub(crate) fn new(tracking_enabled: bool, grace_period: Duration) -> Self {
         Self {
             tracking_enabled,
+            grace_period,
             ..Default::default()
         }
     }
@@ -196,6 +200,10 @@ impl AddressReputationInner {
     }
 
     fn status(&self, address: Address) -> ReputationStatus {
+        self.status_at(address, Instant::now())
+    }
+
+    fn status_at(&self, address: Address, now: Instant) -> ReputationStatus {
         if self.blocklist.contains(&address) {
             return ReputationStatus::Banned;
         } else if self.allowlist.contains(&address) {
@@ -215,7 +223,11 @@ impl AddressReputationInner {
         if min_expected_included <= (count.ops_included + self.params.throttling_slack) {
             ReputationStatus::Ok
         } else if min_expected_included <= (count.ops_included + self.params.ban_slack) {
-            ReputationStatus::Throttled
+            if count.in_grace_period(now, self.params.grace_period) {
+                ReputationStatus::Ok
+            } else {
+                ReputationStatus::Throttled
+            }
         } else {
             ReputationStatus::Banned
         }
@@ -224,6 +236,7 @@ impl AddressReputationInner {
     fn add_seen(&mut self, address: Address) {
         let count = self.counts.entry(address).or_default();
         count.ops_seen += 1;
+        count.first_seen.get_or_insert_with(Instant::now);
     }
 
     fn dec_seen(&mut self, address: Address) {
@@ -313,6 +326,16 @@ impl AddressReputationInner {
 struct AddressCount {
     ops_seen: u64,
     ops_included: u64,
+    // When an op with this entity was first seen, unset if its reputation was
+    // only ever set or penalized directly
+    first_seen: Option<Instant>,
+}
+
+impl AddressCount {
+    fn in_grace_period(&self, now: Instant, grace_period: Duration) -> bool {
+        self.first_seen
+            .is_some_and(|first_seen| now.saturating_duration_since(first_seen) < grace_period)
+    }
 }
 
 #[cfg(test)]
@@ -401,7 +424,7 @@ mod tests {
     #[test]
     fn reputation_banned_tracking_disabled() {
         let addr = Address::random();
-        let params = ReputationParams::new(false);
+        let params = ReputationParams::new(false, Duration::ZERO);
         let mut reputation = AddressReputationInner::new(params);
 
         let ops_seen = 1000;
@@ -410,6 +433,74 @@ mod tests {
         assert_eq!(reputation.status(addr), ReputationStatus::Ok);
     }
 
+    #[test]
+    fn reputation_grace_period() {
+        let addr = Address::random();
+        let grace_period = Duration::from_secs(60);
+        let params = ReputationParams {
+            grace_period,
+            ..ReputationParams::bundler_default()
+        };
+        let mut reputation = AddressReputationInner::new(params);
+
+        reputation.add_seen(addr);
+        let ops_seen = 1000;
+        let ops_included =
+            ops_seen / params.min_inclusion_rate_denominator - params.throttling_slack - 1;
+        reputation.set_reputation(addr, ops_seen, ops_included);
+
+        let first_seen = reputation.counts[&addr].first_seen.unwrap();
+        assert_eq!(reputation.status_at(addr, first_seen), ReputationStatus::Ok);
+        assert_eq!(
+            reputation.status_at(addr, first_seen + grace_period - Duration::from_millis(1)),
+            ReputationStatus::Ok
+        );
+        assert_eq!(
+            reputation.status_at(addr, first_seen + grace_period),
+            ReputationStatus::Throttled
+        );
+    }
+
+    #[test]
+    fn reputation_grace_period_does_not_prevent_ban() {
+        let addr = Address::random();
+        let params = ReputationParams {
+            grace_period: Duration::from_secs(60),
+            ..ReputationParams::bundler_default()
+        };
+        let mut reputation = AddressReputationInner::new(params);
+
+        reputation.add_seen(addr);
+        let ops_seen = 1000;
+        let ops_included = ops_seen / params.min_inclusion_rate_denominator - params.ban_slack - 1;
+        reputation.set_reputation(addr, ops_seen, ops_included);
+
+        let first_seen = reputation.counts[&addr].first_seen.unwrap();
+        assert_eq!(
+            reputation.status_at(addr, first_seen),
+            ReputationStatus::Banned
+        );
+    }
+
+    #[test]
+    fn reputation_no_grace_period() {
+        let addr = Address::random();
+        let params = ReputationParams::bundler_default();
+        let mut reputation = AddressReputationInner::new(params);
+
+        reputation.add_seen(addr);
+        let ops_seen = 1000;
+        let ops_included =
+            ops_seen / params.min_inclusion_rate_denominator - params.throttling_slack - 1;
+        reputation.set_reputation(addr, ops_seen, ops_included);
+
+        let first_seen = reputation.counts[&addr].first_seen.unwrap();
+        assert_eq!(
+            reputation.status_at(addr, first_seen),
+            ReputationStatus::Throttled
+        );
+    }
+
     #[test]
     fn hourly_update() {
         let addr = Address::random();
@@ -24,7 +24,7 @@ use rundler_provider::{
     DAGasOracleSync, EvmProvider, FeeEstimator, ProvidersWithEntryPointT, SimulationProvider,
     StateOverride,
 };
-use rundler_sim::{MempoolConfig, Prechecker, Simulator};
+use rundler_sim::{MempoolConfig, MempoolMetrics, Prechecker, Simulator};
 use rundler_types::{
     pool::{
         MempoolError, PaymasterMetadata, PoolOperation, PreconfInfo, Reputation, ReputationStatus,
@@ -627,7 +627,7 @@ where
         let sim_fut = self
             .pool_providers
             .simulator()
-            .simulate_validation(versioned_op, perms.trusted, block_hash, None)
+            .simulate_validation(versioned_op, perms.trusted, block_hash.into(), None)
             .map_err(Into::into);
         let execution_gas_check_future =
             self.check_execution_gas_limit_efficiency(op.clone(), block_hash);
@@ -768,6 +768,12 @@ where
             }
         });
 
+        if self.config.sim_settings.per_mempool_metrics {
+            for id in &sim_result.mempools {
+                MempoolMetrics::for_mempool(*id).ops_accepted.increment(1);
+            }
+        }
+
         // Emit event
         let op_hash = pool_op.uo.hash();
         self.emit(OpPoolEvent::ReceivedOp {
@@ -1035,7 +1041,7 @@ struct UoPoolMetrics {
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, str::FromStr, vec};
+    use std::{collections::HashMap, str::FromStr, time::Duration, vec};
 
     use alloy_primitives::{address, bytes, uint, Address, Bytes, Log as PrimitiveLog, LogData};
     use alloy_rpc_types_eth::TransactionReceipt as AlloyTransactionReceipt;
@@ -2478,6 +2484,7 @@ mod tests {
             da_gas_tracking_enabled: false,
             paymaster_cache_length: 100,
             reputation_tracking_enabled: true,
+            reputation_grace_period: Duration::ZERO,
             drop_min_num_blocks: 10,
             execution_gas_limit_efficiency_reject_threshold: 0.0,
             verification_gas_limit_efficiency_reject_threshold: 0.0,
@@ -2608,10 +2615,7 @@ mod tests {
                             account_is_staked: op.staked,
                             valid_time_range: op.valid_time_range,
                             entity_infos: EntityInfos {
-                                sender: EntityInfo {
-                                    entity: Entity::account(op.op.sender()),
-                                    is_staked: false,
-                                },
+                                sender: EntityInfo::new(Entity::account(op.op.sender()), false),
                                 ..EntityInfos::default()
                             },
                             pre_op_gas: 100_000,
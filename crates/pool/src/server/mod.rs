@@ -16,4 +16,4 @@ pub use local::{LocalPoolBuilder, LocalPoolHandle};
 
 mod remote;
 pub(crate) use remote::remote_mempool_server_task;
-pub use remote::RemotePoolClient;
+pub use remote::{remote_simulator_server_task, RemotePoolClient, RemoteSimulator};
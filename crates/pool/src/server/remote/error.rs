@@ -13,6 +13,7 @@
 
 use alloy_sol_types::Panic;
 use anyhow::{bail, Context};
+use rundler_sim::{SimulationError, ViolationError};
 use rundler_task::grpc::protos::{from_bytes, ToProtoBytes};
 use rundler_types::{
     pool::{
@@ -22,26 +23,34 @@ use rundler_types::{
 };
 
 use super::protos::{
-    mempool_error, precheck_violation_error, simulation_violation_error, validation_revert,
-    AccessedUndeployedContract, AccessedUnsupportedContractType, AggregatorError,
-    AggregatorMismatch, AssociatedStorageDuringDeploy, AssociatedStorageIsAlternateSender,
-    CallGasLimitTooLow, CallHadValue, CalledBannedEntryPointMethod, CodeHashChanged, DidNotRevert,
-    DiscardedOnInsertError, Entity, EntityThrottledError, EntityType, EntryPointRevert,
-    ExecutionGasLimitEfficiencyTooLow, ExistingSenderWithInitCode, FactoryCalledCreate2Twice,
-    FactoryIsNotContract, FactoryMustBeEmpty, Invalid7702AuthSignature, InvalidAccountSignature,
-    InvalidPaymasterSignature, InvalidSignature, InvalidStorageAccess, InvalidTimeRange,
+    mempool_error, precheck_violation_error, simulation_error, simulation_violation_error,
+    validation_revert, AccessedUndeployedContract, AccessedUnsupportedContractType,
+    AggregatorError, AggregatorMismatch, AssociatedStorageDuringDeploy,
+    AssociatedStorageIsAlternateSender, CallDepthTooDeep, CallGasLimitTooHigh, CallGasLimitTooLow,
+    CallHadValue, CalledBannedEntryPointMethod, CalledEntryPoint, CodeHashChanged,
+    CrossSenderAssociatedStorage, DidNotRevert, DiscardedOnInsertError, Entity, EntityDenied,
+    EntityThrottledError, EntityType, EntryPointRevert, ExecutionGasLimitEfficiencyTooLow,
+    ExistingSenderWithInitCode, ExpectedStorageTooLarge, FactoryCalledCreate2Twice,
+    FactoryDidNotDeploySender, FactoryIsNotContract, FactoryMustBeEmpty, Invalid7702AuthSignature,
+    InvalidAccountSignature, InvalidPaymasterSignature, InvalidSignature, InvalidStorageAccess,
+    InvalidTimeRange, MalformedInitCode, MalformedPaymasterAndData, MalformedSignature,
     MaxFeePerGasTooLow, MaxOperationsReachedError, MaxPriorityFeePerGasTooLow,
     MempoolError as ProtoMempoolError, MultipleRolesViolation, NotStaked,
-    OperationAlreadyKnownError, OperationDropTooSoon, OperationRevert, OutOfGas, OverMaxCost,
-    PanicRevert, PaymasterBalanceTooLow, PaymasterDepositTooLow, PaymasterIsNotContract,
-    PreVerificationGasTooLow, PrecheckViolationError as ProtoPrecheckViolationError,
-    ReplacementUnderpricedError, SenderAddressUsedAsAlternateEntity, SenderFundsTooLow,
-    SenderIsNotContractAndNoInitCode, SimulationViolationError as ProtoSimulationViolationError,
-    TooManyExpectedStorageSlots, TotalGasLimitTooHigh, UnintendedRevert,
-    UnintendedRevertWithMessage, UnknownEntryPointError, UnknownRevert, UnstakedPaymasterContext,
-    UseUnsupportedEip, UsedForbiddenOpcode, UsedForbiddenPrecompile,
+    OperationAlreadyKnownError, OperationDropTooSoon, OperationRevert, OperationTooLarge, OutOfGas,
+    OverMaxCost, PanicRevert, PaymasterBalanceTooLow, PaymasterDepositTooLow,
+    PaymasterIsNotContract, PreVerificationGasTooLow,
+    PrecheckViolationError as ProtoPrecheckViolationError, PriorityFeeTooLow,
+    ReplacementUnderpricedError, ReversedTimeRange, SenderAddressUsedAsAlternateEntity,
+    SenderFundsTooLow, SenderIsEntryPoint, SenderIsNotContractAndNoInitCode,
+    SimulationError as ProtoSimulationError,
+    SimulationViolationError as ProtoSimulationViolationError, SimulationViolations,
+    SuspiciousEmptyCodeHash, TooManyExpectedStorageSlots, TotalGasLimitTooHigh,
+    UndeployedExtCodeAccess, UnintendedRevert, UnintendedRevertWithMessage, UnknownEntryPointError,
+    UnknownRevert, UnstakedPaymasterContext, UnsupportedByAllMempools, UseUnsupportedEip,
+    UsedForbiddenOpcode, UsedForbiddenPrecompile, ValidAfterTooFar,
     ValidationRevert as ProtoValidationRevert, VerificationGasLimitBufferTooLow,
     VerificationGasLimitEfficiencyTooLow, VerificationGasLimitTooHigh, WrongNumberOfPhases,
+    ZeroCallGasLimit,
 };
 
 impl TryFrom<ProtoMempoolError> for PoolError {
@@ -410,6 +419,14 @@ impl From<PrecheckViolation> for ProtoPrecheckViolationError {
                     },
                 )),
             },
+            PrecheckViolation::CallGasLimitTooHigh(actual, max) => ProtoPrecheckViolationError {
+                violation: Some(precheck_violation_error::Violation::CallGasLimitTooHigh(
+                    CallGasLimitTooHigh {
+                        actual_gas_limit: actual.to_proto_bytes(),
+                        max_gas_limit: max.to_proto_bytes(),
+                    },
+                )),
+            },
         }
     }
 }
@@ -488,6 +505,12 @@ impl TryFrom<ProtoPrecheckViolationError> for PrecheckViolation {
                     from_bytes(&e.max_cost)?,
                 )
             }
+            Some(precheck_violation_error::Violation::CallGasLimitTooHigh(e)) => {
+                PrecheckViolation::CallGasLimitTooHigh(
+                    from_bytes(&e.actual_gas_limit)?,
+                    from_bytes(&e.max_gas_limit)?,
+                )
+            }
             None => {
                 bail!("unknown proto mempool precheck violation")
             }
@@ -609,6 +632,8 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                         slot: stake_data.slot.to_proto_bytes(),
                         min_stake: stake_data.min_stake.to_proto_bytes(),
                         min_unstake_delay: stake_data.min_unstake_delay,
+                        current_stake: stake_data.current_stake.to_proto_bytes(),
+                        current_unstake_delay: stake_data.current_unstake_delay,
                     },
                 )),
             },
@@ -689,6 +714,16 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     )),
                 }
             }
+            SimulationViolation::ReversedTimeRange(valid_until, valid_after) => {
+                ProtoSimulationViolationError {
+                    violation: Some(simulation_violation_error::Violation::ReversedTimeRange(
+                        ReversedTimeRange {
+                            valid_until: valid_until.seconds_since_epoch(),
+                            valid_after: valid_after.seconds_since_epoch(),
+                        },
+                    )),
+                }
+            }
             SimulationViolation::AggregatorMismatch(expected, actual) => {
                 ProtoSimulationViolationError {
                     violation: Some(simulation_violation_error::Violation::AggregatorMismatch(
@@ -723,6 +758,179 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 }
             }
+            SimulationViolation::OperationTooLarge { size, maximum } => {
+                ProtoSimulationViolationError {
+                    violation: Some(simulation_violation_error::Violation::OperationTooLarge(
+                        OperationTooLarge {
+                            size: size as u64,
+                            maximum: maximum as u64,
+                        },
+                    )),
+                }
+            }
+            SimulationViolation::MalformedInitCode(length) => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::MalformedInitCode(
+                    MalformedInitCode {
+                        length: length as u64,
+                    },
+                )),
+            },
+            SimulationViolation::MalformedPaymasterAndData(length) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::MalformedPaymasterAndData(
+                            MalformedPaymasterAndData {
+                                length: length as u64,
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::EntityDenied(entity) => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::EntityDenied(
+                    EntityDenied {
+                        entity: Some((&entity).into()),
+                    },
+                )),
+            },
+            SimulationViolation::PriorityFeeTooLow { provided, minimum } => {
+                ProtoSimulationViolationError {
+                    violation: Some(simulation_violation_error::Violation::PriorityFeeTooLow(
+                        PriorityFeeTooLow {
+                            provided: provided.to_proto_bytes(),
+                            minimum: minimum.to_proto_bytes(),
+                        },
+                    )),
+                }
+            }
+            SimulationViolation::ZeroCallGasLimit => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::ZeroCallGasLimit(
+                    ZeroCallGasLimit {},
+                )),
+            },
+            SimulationViolation::CalledEntryPoint => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::CalledEntryPoint(
+                    CalledEntryPoint {},
+                )),
+            },
+            SimulationViolation::PreVerificationGasTooLow { provided, minimum } => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::PreVerificationGasTooLow(
+                            PreVerificationGasTooLow {
+                                actual_gas: provided.to_proto_bytes(),
+                                min_gas: minimum.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::SuspiciousEmptyCodeHash => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::SuspiciousEmptyCodeHash(
+                        SuspiciousEmptyCodeHash {},
+                    ),
+                ),
+            },
+            SimulationViolation::CallDepthTooDeep(entity, depth, max_depth) => {
+                ProtoSimulationViolationError {
+                    violation: Some(simulation_violation_error::Violation::CallDepthTooDeep(
+                        CallDepthTooDeep {
+                            entity: Some((&entity).into()),
+                            depth,
+                            max_depth,
+                        },
+                    )),
+                }
+            }
+            SimulationViolation::FactoryDidNotDeploySender(factory, sender) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::FactoryDidNotDeploySender(
+                            FactoryDidNotDeploySender {
+                                factory_address: factory.to_proto_bytes(),
+                                sender_address: sender.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::CrossSenderAssociatedStorage(entity, associated_address, slot) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::CrossSenderAssociatedStorage(
+                            CrossSenderAssociatedStorage {
+                                entity: Some((&entity).into()),
+                                associated_address: associated_address.to_proto_bytes(),
+                                contract_address: slot.address.to_proto_bytes(),
+                                slot: slot.slot.to_proto_bytes(),
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::ValidAfterTooFar {
+                valid_after,
+                max_valid_after,
+            } => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::ValidAfterTooFar(
+                    ValidAfterTooFar {
+                        valid_after: valid_after.seconds_since_epoch(),
+                        max_valid_after: max_valid_after.seconds_since_epoch(),
+                    },
+                )),
+            },
+            SimulationViolation::SenderIsEntryPoint => ProtoSimulationViolationError {
+                violation: Some(simulation_violation_error::Violation::SenderIsEntryPoint(
+                    SenderIsEntryPoint {},
+                )),
+            },
+            SimulationViolation::UndeployedExtCodeAccess(entity, addr, opcode) => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::UndeployedExtCodeAccess(
+                            UndeployedExtCodeAccess {
+                                entity: Some((&entity).into()),
+                                contract_address: addr.to_proto_bytes(),
+                                opcode: opcode.0 as u32,
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::MalformedSignature { length, minimum } => {
+                ProtoSimulationViolationError {
+                    violation: Some(simulation_violation_error::Violation::MalformedSignature(
+                        MalformedSignature {
+                            length: length as u64,
+                            minimum: minimum as u64,
+                        },
+                    )),
+                }
+            }
+            SimulationViolation::ExpectedStorageTooLarge { slots, max } => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::ExpectedStorageTooLarge(
+                            ExpectedStorageTooLarge {
+                                slots: slots as u64,
+                                max: max as u64,
+                            },
+                        ),
+                    ),
+                }
+            }
+            SimulationViolation::UnsupportedByAllMempools { violations } => {
+                ProtoSimulationViolationError {
+                    violation: Some(
+                        simulation_violation_error::Violation::UnsupportedByAllMempools(
+                            UnsupportedByAllMempools {
+                                violations: violations.into_iter().map(Into::into).collect(),
+                            },
+                        ),
+                    ),
+                }
+            }
         }
     }
 }
@@ -741,6 +949,12 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                     Timestamp::new(e.valud_after),
                 )
             }
+            Some(simulation_violation_error::Violation::ReversedTimeRange(e)) => {
+                SimulationViolation::ReversedTimeRange(
+                    Timestamp::new(e.valid_until),
+                    Timestamp::new(e.valid_after),
+                )
+            }
             Some(simulation_violation_error::Violation::InvalidAccountSignature(_)) => {
                 SimulationViolation::InvalidAccountSignature
             }
@@ -820,6 +1034,8 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                     slot: from_bytes(&e.slot)?,
                     min_stake: from_bytes(&e.min_stake)?,
                     min_unstake_delay: e.min_unstake_delay,
+                    current_stake: from_bytes(&e.current_stake)?,
+                    current_unstake_delay: e.current_unstake_delay,
                 }))
             }
             Some(simulation_violation_error::Violation::UnintendedRevert(e)) => {
@@ -887,6 +1103,104 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
                     from_bytes(&e.contract_address)?,
                 )
             }
+            Some(simulation_violation_error::Violation::OperationTooLarge(e)) => {
+                SimulationViolation::OperationTooLarge {
+                    size: e.size.try_into()?,
+                    maximum: e.maximum.try_into()?,
+                }
+            }
+            Some(simulation_violation_error::Violation::MalformedInitCode(e)) => {
+                SimulationViolation::MalformedInitCode(e.length.try_into()?)
+            }
+            Some(simulation_violation_error::Violation::MalformedPaymasterAndData(e)) => {
+                SimulationViolation::MalformedPaymasterAndData(e.length.try_into()?)
+            }
+            Some(simulation_violation_error::Violation::EntityDenied(e)) => {
+                SimulationViolation::EntityDenied(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                )
+            }
+            Some(simulation_violation_error::Violation::PriorityFeeTooLow(e)) => {
+                SimulationViolation::PriorityFeeTooLow {
+                    provided: from_bytes(&e.provided)?,
+                    minimum: from_bytes(&e.minimum)?,
+                }
+            }
+            Some(simulation_violation_error::Violation::ZeroCallGasLimit(_)) => {
+                SimulationViolation::ZeroCallGasLimit
+            }
+            Some(simulation_violation_error::Violation::CalledEntryPoint(_)) => {
+                SimulationViolation::CalledEntryPoint
+            }
+            Some(simulation_violation_error::Violation::PreVerificationGasTooLow(e)) => {
+                SimulationViolation::PreVerificationGasTooLow {
+                    provided: from_bytes(&e.actual_gas)?,
+                    minimum: from_bytes(&e.min_gas)?,
+                }
+            }
+            Some(simulation_violation_error::Violation::SuspiciousEmptyCodeHash(_)) => {
+                SimulationViolation::SuspiciousEmptyCodeHash
+            }
+            Some(simulation_violation_error::Violation::CallDepthTooDeep(e)) => {
+                SimulationViolation::CallDepthTooDeep(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                    e.depth,
+                    e.max_depth,
+                )
+            }
+            Some(simulation_violation_error::Violation::FactoryDidNotDeploySender(e)) => {
+                SimulationViolation::FactoryDidNotDeploySender(
+                    from_bytes(&e.factory_address)?,
+                    from_bytes(&e.sender_address)?,
+                )
+            }
+            Some(simulation_violation_error::Violation::CrossSenderAssociatedStorage(e)) => {
+                SimulationViolation::CrossSenderAssociatedStorage(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                    from_bytes(&e.associated_address)?,
+                    StorageSlot {
+                        address: from_bytes(&e.contract_address)?,
+                        slot: from_bytes(&e.slot)?,
+                    },
+                )
+            }
+            Some(simulation_violation_error::Violation::ValidAfterTooFar(e)) => {
+                SimulationViolation::ValidAfterTooFar {
+                    valid_after: Timestamp::new(e.valid_after),
+                    max_valid_after: Timestamp::new(e.max_valid_after),
+                }
+            }
+            Some(simulation_violation_error::Violation::SenderIsEntryPoint(_)) => {
+                SimulationViolation::SenderIsEntryPoint
+            }
+            Some(simulation_violation_error::Violation::UndeployedExtCodeAccess(e)) => {
+                SimulationViolation::UndeployedExtCodeAccess(
+                    (&e.entity.context("should have entity in error")?).try_into()?,
+                    from_bytes(&e.contract_address)?,
+                    ViolationOpCode(Opcode::try_from(e.opcode as u8)?),
+                )
+            }
+            Some(simulation_violation_error::Violation::MalformedSignature(e)) => {
+                SimulationViolation::MalformedSignature {
+                    length: e.length.try_into()?,
+                    minimum: e.minimum.try_into()?,
+                }
+            }
+            Some(simulation_violation_error::Violation::ExpectedStorageTooLarge(e)) => {
+                SimulationViolation::ExpectedStorageTooLarge {
+                    slots: e.slots.try_into()?,
+                    max: e.max.try_into()?,
+                }
+            }
+            Some(simulation_violation_error::Violation::UnsupportedByAllMempools(e)) => {
+                SimulationViolation::UnsupportedByAllMempools {
+                    violations: e
+                        .violations
+                        .into_iter()
+                        .map(TryInto::try_into)
+                        .collect::<Result<_, _>>()?,
+                }
+            }
             None => {
                 bail!("unknown proto mempool simulation violation")
             }
@@ -950,6 +1264,48 @@ impl TryFrom<ProtoValidationRevert> for ValidationRevert {
     }
 }
 
+impl From<SimulationError> for ProtoSimulationError {
+    fn from(value: SimulationError) -> Self {
+        let error = match value.violation_error {
+            ViolationError::Violations(violations) => {
+                simulation_error::Error::Violations(SimulationViolations {
+                    violations: violations.into_iter().map(Into::into).collect(),
+                })
+            }
+            ViolationError::Other(e) => simulation_error::Error::Other(e.to_string()),
+        };
+        ProtoSimulationError {
+            error: Some(error),
+            entity_infos: value.entity_infos.as_ref().map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<ProtoSimulationError> for SimulationError {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ProtoSimulationError) -> Result<Self, Self::Error> {
+        let violation_error = match value.error {
+            Some(simulation_error::Error::Violations(v)) => ViolationError::Violations(
+                v.violations
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_, _>>()?,
+            ),
+            Some(simulation_error::Error::Other(e)) => ViolationError::Other(anyhow::anyhow!(e)),
+            None => bail!("unknown proto simulation error"),
+        };
+        Ok(SimulationError {
+            violation_error,
+            entity_infos: value
+                .entity_infos
+                .as_ref()
+                .map(TryInto::try_into)
+                .transpose()?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alloy_primitives::U256;
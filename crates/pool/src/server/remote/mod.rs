@@ -16,6 +16,10 @@ mod error;
 #[allow(non_snake_case, unreachable_pub, clippy::large_enum_variant)]
 mod protos;
 mod server;
+mod sim_client;
+mod sim_server;
 
 pub use client::*;
 pub(crate) use server::remote_mempool_server_task;
+pub use sim_client::RemoteSimulator;
+pub use sim_server::remote_simulator_server_task;
@@ -11,8 +11,13 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{Address, Log as RundlerLog, B256};
 use anyhow::{anyhow, Context};
+use rundler_provider::AggregatorSimOut as RundlerAggregatorSimOut;
+use rundler_sim::{
+    BlockTag as RundlerBlockTag, EntityStakeShortfall as RundlerEntityStakeShortfall,
+    GasBreakdown as RundlerGasBreakdown, SimulationResult as RundlerSimulationResult,
+};
 use rundler_task::grpc::protos::{from_bytes, ConversionError, ToProtoBytes};
 use rundler_types::{
     authorization::Eip7702Auth,
@@ -28,11 +33,14 @@ use rundler_types::{
         Reputation as PoolReputation, ReputationStatus as PoolReputationStatus,
         StakeStatus as RundlerStakeStatus,
     },
-    v0_6, v0_7, BundlerSponsorship as RundlerBundlerSponsorship, Entity as RundlerEntity,
-    EntityInfos, EntityType as RundlerEntityType, EntityUpdate as RundlerEntityUpdate,
-    EntityUpdateType as RundlerEntityUpdateType, StakeInfo as RundlerStakeInfo, UserOperation as _,
-    UserOperationPermissions as RundlerUserOperationPermissions, UserOperationVariant,
-    ValidTimeRange,
+    v0_6, v0_7, AggregatorInfo as RundlerAggregatorInfo,
+    BundlerSponsorship as RundlerBundlerSponsorship, Entity as RundlerEntity,
+    EntityInfo as RundlerEntityInfo, EntityInfos as RundlerEntityInfos,
+    EntityType as RundlerEntityType, EntityUpdate as RundlerEntityUpdate,
+    EntityUpdateType as RundlerEntityUpdateType, ExpectedStorage, StakeInfo as RundlerStakeInfo,
+    UserOperation as _, UserOperationPermissions as RundlerUserOperationPermissions,
+    UserOperationVariant, ValidTimeRange, ValidationOutput as RundlerValidationOutput,
+    ValidationReturnInfo as RundlerValidationReturnInfo,
 };
 
 tonic::include_proto!("op_pool");
@@ -540,7 +548,7 @@ impl TryUoFromProto<MempoolOp> for PoolOperation {
             sim_block_hash,
             sim_block_number: 0,
             account_is_staked: op.account_is_staked,
-            entity_infos: EntityInfos::default(),
+            entity_infos: RundlerEntityInfos::default(),
             da_gas_data: op
                 .da_gas_data
                 .context("DA gas data should be set")?
@@ -653,6 +661,8 @@ impl TryFrom<UserOperationPermissions> for RundlerUserOperationPermissions {
                 .bundler_sponsorship
                 .map(|s| s.try_into())
                 .transpose()?,
+            client_tag: permissions.client_tag,
+            submission_source: permissions.submission_source,
         })
     }
 }
@@ -667,6 +677,8 @@ impl From<RundlerUserOperationPermissions> for UserOperationPermissions {
             underpriced_accept_pct: permissions.underpriced_accept_pct,
             underpriced_bundle_pct: permissions.underpriced_bundle_pct,
             bundler_sponsorship: permissions.bundler_sponsorship.map(|s| s.into()),
+            client_tag: permissions.client_tag,
+            submission_source: permissions.submission_source,
         }
     }
 }
@@ -712,3 +724,398 @@ impl TryFrom<PoolOperationSummary> for RundlerPoolOperationSummary {
         })
     }
 }
+
+impl From<RundlerBlockTag> for BlockTag {
+    fn from(block: RundlerBlockTag) -> Self {
+        let tag = match block {
+            RundlerBlockTag::Latest => block_tag::Tag::Latest(LatestBlock {}),
+            RundlerBlockTag::Pending => block_tag::Tag::Pending(PendingBlock {}),
+            RundlerBlockTag::Hash(hash) => block_tag::Tag::Hash(hash.to_proto_bytes()),
+        };
+        BlockTag { tag: Some(tag) }
+    }
+}
+
+impl TryFrom<BlockTag> for RundlerBlockTag {
+    type Error = ConversionError;
+
+    fn try_from(block: BlockTag) -> Result<Self, Self::Error> {
+        Ok(match block.tag.context("Block tag should be set")? {
+            block_tag::Tag::Latest(_) => RundlerBlockTag::Latest,
+            block_tag::Tag::Pending(_) => RundlerBlockTag::Pending,
+            block_tag::Tag::Hash(hash) => RundlerBlockTag::Hash(from_bytes(&hash)?),
+        })
+    }
+}
+
+impl From<&RundlerEntityInfo> for EntityInfo {
+    fn from(info: &RundlerEntityInfo) -> Self {
+        EntityInfo {
+            entity: Some((&info.entity).into()),
+            is_staked: info.is_staked,
+            stake_expiring_soon: info.stake_expiring_soon,
+        }
+    }
+}
+
+impl TryFrom<&EntityInfo> for RundlerEntityInfo {
+    type Error = ConversionError;
+
+    fn try_from(info: &EntityInfo) -> Result<Self, Self::Error> {
+        Ok(RundlerEntityInfo {
+            entity: info
+                .entity
+                .as_ref()
+                .context("Entity info should contain entity")?
+                .try_into()?,
+            is_staked: info.is_staked,
+            stake_expiring_soon: info.stake_expiring_soon,
+        })
+    }
+}
+
+impl From<&RundlerEntityInfos> for EntityInfos {
+    fn from(infos: &RundlerEntityInfos) -> Self {
+        EntityInfos {
+            factory: infos.factory.as_ref().map(Into::into),
+            sender: Some((&infos.sender).into()),
+            paymaster: infos.paymaster.as_ref().map(Into::into),
+            aggregator: infos.aggregator.as_ref().map(Into::into),
+        }
+    }
+}
+
+impl TryFrom<&EntityInfos> for RundlerEntityInfos {
+    type Error = ConversionError;
+
+    fn try_from(infos: &EntityInfos) -> Result<Self, Self::Error> {
+        Ok(RundlerEntityInfos {
+            factory: infos.factory.as_ref().map(TryInto::try_into).transpose()?,
+            sender: infos
+                .sender
+                .as_ref()
+                .context("Entity infos should contain sender")?
+                .try_into()?,
+            paymaster: infos
+                .paymaster
+                .as_ref()
+                .map(TryInto::try_into)
+                .transpose()?,
+            aggregator: infos
+                .aggregator
+                .as_ref()
+                .map(TryInto::try_into)
+                .transpose()?,
+        })
+    }
+}
+
+impl From<RundlerStakeInfo> for StakeInfo {
+    fn from(info: RundlerStakeInfo) -> Self {
+        StakeInfo {
+            stake: info.stake.to_proto_bytes(),
+            unstake_delay_sec: info.unstake_delay_sec,
+        }
+    }
+}
+
+impl TryFrom<StakeInfo> for RundlerStakeInfo {
+    type Error = ConversionError;
+
+    fn try_from(info: StakeInfo) -> Result<Self, Self::Error> {
+        Ok(RundlerStakeInfo {
+            stake: from_bytes(&info.stake)?,
+            unstake_delay_sec: info.unstake_delay_sec,
+        })
+    }
+}
+
+impl From<RundlerValidationOutput> for ValidationOutput {
+    fn from(output: RundlerValidationOutput) -> Self {
+        let return_info = output.return_info;
+        ValidationOutput {
+            return_info: Some(ValidationReturnInfo {
+                pre_op_gas: return_info.pre_op_gas.to_proto_bytes(),
+                account_sig_failed: return_info.account_sig_failed,
+                paymaster_sig_failed: return_info.paymaster_sig_failed,
+                valid_after: return_info.valid_after.seconds_since_epoch(),
+                valid_until: return_info.valid_until.seconds_since_epoch(),
+                paymaster_context: return_info.paymaster_context.to_proto_bytes(),
+            }),
+            sender_info: Some(output.sender_info.into()),
+            factory_info: Some(output.factory_info.into()),
+            paymaster_info: Some(output.paymaster_info.into()),
+            aggregator_info: output.aggregator_info.map(|info| AggregatorInfo {
+                address: info.address.to_proto_bytes(),
+                stake_info: Some(info.stake_info.into()),
+            }),
+        }
+    }
+}
+
+const MISSING_STAKE_INFO_ERR_STR: &str = "Validation output should contain stake info";
+impl TryFrom<ValidationOutput> for RundlerValidationOutput {
+    type Error = ConversionError;
+
+    fn try_from(output: ValidationOutput) -> Result<Self, Self::Error> {
+        let return_info = output
+            .return_info
+            .context("Validation output should contain return info")?;
+        Ok(RundlerValidationOutput {
+            return_info: RundlerValidationReturnInfo {
+                pre_op_gas: from_bytes(&return_info.pre_op_gas)?,
+                account_sig_failed: return_info.account_sig_failed,
+                paymaster_sig_failed: return_info.paymaster_sig_failed,
+                valid_after: return_info.valid_after.into(),
+                valid_until: return_info.valid_until.into(),
+                paymaster_context: return_info.paymaster_context.into(),
+            },
+            sender_info: output
+                .sender_info
+                .context(MISSING_STAKE_INFO_ERR_STR)?
+                .try_into()?,
+            factory_info: output
+                .factory_info
+                .context(MISSING_STAKE_INFO_ERR_STR)?
+                .try_into()?,
+            paymaster_info: output
+                .paymaster_info
+                .context(MISSING_STAKE_INFO_ERR_STR)?
+                .try_into()?,
+            aggregator_info: output
+                .aggregator_info
+                .map(|info| -> Result<_, ConversionError> {
+                    Ok(RundlerAggregatorInfo {
+                        address: from_bytes(&info.address)?,
+                        stake_info: info
+                            .stake_info
+                            .context(MISSING_STAKE_INFO_ERR_STR)?
+                            .try_into()?,
+                    })
+                })
+                .transpose()?,
+        })
+    }
+}
+
+impl From<RundlerGasBreakdown> for GasBreakdown {
+    fn from(breakdown: RundlerGasBreakdown) -> Self {
+        GasBreakdown {
+            factory_gas: breakdown.factory_gas.to_proto_bytes(),
+            account_validation_gas: breakdown.account_validation_gas.to_proto_bytes(),
+            paymaster_validation_gas: breakdown.paymaster_validation_gas.to_proto_bytes(),
+            other_gas: breakdown.other_gas.to_proto_bytes(),
+        }
+    }
+}
+
+impl TryFrom<GasBreakdown> for RundlerGasBreakdown {
+    type Error = ConversionError;
+
+    fn try_from(breakdown: GasBreakdown) -> Result<Self, Self::Error> {
+        Ok(RundlerGasBreakdown {
+            factory_gas: from_bytes(&breakdown.factory_gas)?,
+            account_validation_gas: from_bytes(&breakdown.account_validation_gas)?,
+            paymaster_validation_gas: from_bytes(&breakdown.paymaster_validation_gas)?,
+            other_gas: from_bytes(&breakdown.other_gas)?,
+        })
+    }
+}
+
+impl From<&RundlerLog> for Log {
+    fn from(log: &RundlerLog) -> Self {
+        Log {
+            address: log.address.to_proto_bytes(),
+            topics: log.topics().iter().map(|t| t.to_proto_bytes()).collect(),
+            data: log.data.data.to_proto_bytes(),
+        }
+    }
+}
+
+impl TryFrom<Log> for RundlerLog {
+    type Error = ConversionError;
+
+    fn try_from(log: Log) -> Result<Self, Self::Error> {
+        Ok(RundlerLog::new_unchecked(
+            from_bytes(&log.address)?,
+            log.topics
+                .iter()
+                .map(|t| from_bytes(t))
+                .collect::<Result<_, _>>()?,
+            log.data.into(),
+        ))
+    }
+}
+
+impl From<&RundlerEntityStakeShortfall> for EntityStakeShortfall {
+    fn from(shortfall: &RundlerEntityStakeShortfall) -> Self {
+        EntityStakeShortfall {
+            entity: Some((&shortfall.entity).into()),
+            current_stake: shortfall.current_stake.to_proto_bytes(),
+            required_stake: shortfall.required_stake.to_proto_bytes(),
+            current_delay: shortfall.current_delay,
+            required_delay: shortfall.required_delay,
+        }
+    }
+}
+
+impl TryFrom<EntityStakeShortfall> for RundlerEntityStakeShortfall {
+    type Error = ConversionError;
+
+    fn try_from(shortfall: EntityStakeShortfall) -> Result<Self, Self::Error> {
+        Ok(RundlerEntityStakeShortfall {
+            entity: shortfall
+                .entity
+                .as_ref()
+                .context("Stake shortfall should contain entity")?
+                .try_into()?,
+            current_stake: from_bytes(&shortfall.current_stake)?,
+            required_stake: from_bytes(&shortfall.required_stake)?,
+            current_delay: shortfall.current_delay,
+            required_delay: shortfall.required_delay,
+        })
+    }
+}
+
+impl From<RundlerSimulationResult> for SimulationResult {
+    fn from(result: RundlerSimulationResult) -> Self {
+        SimulationResult {
+            mempools: result.mempools.iter().map(|m| m.to_proto_bytes()).collect(),
+            pre_op_gas: result.pre_op_gas.to_proto_bytes(),
+            valid_after: result.valid_time_range.valid_after.seconds_since_epoch(),
+            valid_until: result.valid_time_range.valid_until.seconds_since_epoch(),
+            code_hash: result.code_hash.to_proto_bytes(),
+            account_is_staked: result.account_is_staked,
+            accessed_addresses: result
+                .accessed_addresses
+                .iter()
+                .map(|a| a.to_proto_bytes())
+                .collect(),
+            associated_addresses: result
+                .associated_addresses
+                .iter()
+                .map(|a| a.to_proto_bytes())
+                .collect(),
+            expected_storage: result
+                .expected_storage
+                .0
+                .iter()
+                .flat_map(|(address, slots)| {
+                    slots.iter().map(|(slot, value)| ExpectedStorageSlot {
+                        address: address.to_proto_bytes(),
+                        slot: slot.to_proto_bytes(),
+                        value: value.to_proto_bytes(),
+                    })
+                })
+                .collect(),
+            requires_post_op: result.requires_post_op,
+            entity_infos: Some((&result.entity_infos).into()),
+            aggregator: result.aggregator.map(|aggregator| AggregatorSimOut {
+                address: aggregator.address.to_proto_bytes(),
+                signature: aggregator.signature.to_proto_bytes(),
+            }),
+            has_entity_role_overlap: result.has_entity_role_overlap,
+            validation_output: result.validation_output.map(Into::into),
+            gas_breakdown: result.gas_breakdown.map(Into::into),
+            validation_logs: result.validation_logs.map(|logs| ValidationLogs {
+                logs: logs.iter().map(Into::into).collect(),
+            }),
+            stake_shortfalls: result.stake_shortfalls.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<SimulationResult> for RundlerSimulationResult {
+    type Error = ConversionError;
+
+    fn try_from(result: SimulationResult) -> Result<Self, Self::Error> {
+        let mut expected_storage = ExpectedStorage::default();
+        for slot in result.expected_storage {
+            expected_storage
+                .0
+                .entry(from_bytes(&slot.address)?)
+                .or_default()
+                .insert(from_bytes(&slot.slot)?, from_bytes(&slot.value)?);
+        }
+
+        Ok(RundlerSimulationResult {
+            mempools: result
+                .mempools
+                .iter()
+                .map(|m| from_bytes(m))
+                .collect::<Result<_, _>>()?,
+            pre_op_gas: from_bytes(&result.pre_op_gas)?,
+            valid_time_range: ValidTimeRange::new(
+                result.valid_after.into(),
+                result.valid_until.into(),
+            ),
+            code_hash: from_bytes(&result.code_hash)?,
+            account_is_staked: result.account_is_staked,
+            accessed_addresses: result
+                .accessed_addresses
+                .iter()
+                .map(|a| from_bytes(a))
+                .collect::<Result<_, _>>()?,
+            associated_addresses: result
+                .associated_addresses
+                .iter()
+                .map(|a| from_bytes(a))
+                .collect::<Result<_, _>>()?,
+            expected_storage,
+            requires_post_op: result.requires_post_op,
+            entity_infos: result
+                .entity_infos
+                .as_ref()
+                .context("Simulation result should contain entity infos")?
+                .try_into()?,
+            aggregator: result
+                .aggregator
+                .map(|aggregator| -> Result<_, ConversionError> {
+                    Ok(RundlerAggregatorSimOut {
+                        address: from_bytes(&aggregator.address)?,
+                        signature: aggregator.signature.into(),
+                    })
+                })
+                .transpose()?,
+            has_entity_role_overlap: result.has_entity_role_overlap,
+            validation_output: result
+                .validation_output
+                .map(TryInto::try_into)
+                .transpose()?,
+            gas_breakdown: result.gas_breakdown.map(TryInto::try_into).transpose()?,
+            validation_logs: result
+                .validation_logs
+                .map(|logs| {
+                    logs.logs
+                        .into_iter()
+                        .map(TryInto::try_into)
+                        .collect::<Result<_, _>>()
+                })
+                .transpose()?,
+            stake_shortfalls: result
+                .stake_shortfalls
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permissions_metadata_round_trip() {
+        let perms = RundlerUserOperationPermissions {
+            trusted: true,
+            client_tag: Some("order-42".to_string()),
+            submission_source: Some("client-7".to_string()),
+            ..Default::default()
+        };
+
+        let proto: UserOperationPermissions = perms.clone().into();
+        let perms2: RundlerUserOperationPermissions = proto.try_into().unwrap();
+        assert_eq!(perms, perms2);
+    }
+}
@@ -0,0 +1,141 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{marker::PhantomData, str::FromStr};
+
+use alloy_primitives::{Bytes, B256};
+use rundler_sim::{
+    BlockTag, FactorySimResult, MempoolEvaluation, SimulationError, SimulationResult, Simulator,
+};
+use rundler_task::{
+    grpc::protos::ToProtoBytes,
+    server::{HealthCheck, ServerStatus},
+};
+use rundler_types::UserOperation;
+use tonic::{
+    async_trait,
+    transport::{Channel, Uri},
+};
+use tonic_health::{
+    pb::{health_client::HealthClient, HealthCheckRequest},
+    ServingStatus,
+};
+
+use super::protos::{
+    self, simulate_validation_response, simulator_client::SimulatorClient,
+    SimulateValidationRequest,
+};
+
+/// Remote simulator client
+///
+/// Forwards simulations to a remote simulator server, so that simulation can run on a
+/// dedicated host. Only `simulate_validation` is forwarded.
+#[derive(Debug, Clone)]
+pub struct RemoteSimulator<UO> {
+    simulator_client: SimulatorClient<Channel>,
+    simulator_health: HealthClient<Channel>,
+    _uo_type: PhantomData<UO>,
+}
+
+impl<UO> RemoteSimulator<UO> {
+    /// Connect to a remote simulator server
+    pub async fn connect(url: String) -> anyhow::Result<Self> {
+        let simulator_client = SimulatorClient::connect(url.clone()).await?;
+        let simulator_health =
+            HealthClient::new(Channel::builder(Uri::from_str(&url)?).connect().await?);
+        Ok(Self {
+            simulator_client,
+            simulator_health,
+            _uo_type: PhantomData,
+        })
+    }
+}
+
+#[async_trait]
+impl<UO> Simulator for RemoteSimulator<UO>
+where
+    UO: UserOperation,
+    for<'a> protos::UserOperation: From<&'a UO>,
+{
+    type UO = UO;
+
+    async fn simulate_validation(
+        &self,
+        op: UO,
+        trusted: bool,
+        block: BlockTag,
+        expected_code_hash: Option<B256>,
+    ) -> Result<SimulationResult, SimulationError> {
+        let res = self
+            .simulator_client
+            .clone()
+            .simulate_validation(SimulateValidationRequest {
+                uo: Some(protos::UserOperation::from(&op)),
+                trusted,
+                block: Some(block.into()),
+                expected_code_hash: expected_code_hash.map_or(vec![], |h| h.to_proto_bytes()),
+            })
+            .await
+            .map_err(anyhow::Error::from)?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(simulate_validation_response::Result::Success(s)) => {
+                Ok(s.try_into().map_err(anyhow::Error::from)?)
+            }
+            Some(simulate_validation_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(anyhow::anyhow!(
+                "should have received result from simulator"
+            ))?,
+        }
+    }
+
+    async fn evaluate_mempools(
+        &self,
+        _op: UO,
+        _block: BlockTag,
+    ) -> Result<Vec<MempoolEvaluation>, SimulationError> {
+        Err(anyhow::anyhow!(
+            "remote simulator does not support mempool evaluation"
+        ))?
+    }
+
+    async fn simulate_factory(
+        &self,
+        _init_code: Bytes,
+        _block: BlockTag,
+    ) -> Result<FactorySimResult, SimulationError> {
+        Err(anyhow::anyhow!(
+            "remote simulator does not support factory simulation"
+        ))?
+    }
+}
+
+#[async_trait]
+impl<UO: Send + Sync + 'static> HealthCheck for RemoteSimulator<UO> {
+    fn name(&self) -> &'static str {
+        "RemoteSimulatorServer"
+    }
+
+    async fn status(&self) -> ServerStatus {
+        self.simulator_health
+            .clone()
+            .check(HealthCheckRequest::default())
+            .await
+            .ok()
+            .filter(|status| status.get_ref().status == ServingStatus::Serving as i32)
+            .map(|_| ServerStatus::Serving)
+            .unwrap_or(ServerStatus::NotServing)
+    }
+}
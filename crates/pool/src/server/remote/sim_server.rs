@@ -0,0 +1,270 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+use rundler_sim::Simulator;
+use rundler_task::{grpc::protos::from_bytes, GracefulShutdown};
+use rundler_types::{chain::ChainSpec, UserOperation, UserOperationVariant};
+use tonic::{transport::Server, Request, Response, Result, Status};
+
+use super::protos::{
+    simulate_validation_response,
+    simulator_server::{Simulator as GrpcSimulator, SimulatorServer as GrpcSimulatorServer},
+    SimulateValidationRequest, SimulateValidationResponse, TryUoFromProto,
+    OP_POOL_FILE_DESCRIPTOR_SET,
+};
+
+/// Spawn a remote simulator server, serving simulations from a local simulator
+pub async fn remote_simulator_server_task<S>(
+    addr: SocketAddr,
+    chain_spec: ChainSpec,
+    simulator: S,
+    shutdown: GracefulShutdown,
+) where
+    S: Simulator + 'static,
+    S::UO: From<UserOperationVariant>,
+{
+    // gRPC server
+    let simulator_server = GrpcSimulatorServer::new(SimulatorImpl::new(chain_spec, simulator));
+
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(OP_POOL_FILE_DESCRIPTOR_SET)
+        .build_v1()
+        .expect("should build simulator reflection service");
+
+    // health service
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_serving::<GrpcSimulatorServer<SimulatorImpl<S>>>()
+        .await;
+
+    if let Err(e) = Server::builder()
+        .add_service(simulator_server)
+        .add_service(reflection_service)
+        .add_service(health_service)
+        .serve_with_shutdown(addr, async move {
+            let _ = shutdown.await;
+        })
+        .await
+    {
+        tracing::error!("simulator server failed: {e:?}");
+    }
+}
+
+struct SimulatorImpl<S> {
+    chain_spec: ChainSpec,
+    simulator: S,
+}
+
+impl<S> SimulatorImpl<S> {
+    fn new(chain_spec: ChainSpec, simulator: S) -> Self {
+        Self {
+            chain_spec,
+            simulator,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> GrpcSimulator for SimulatorImpl<S>
+where
+    S: Simulator + 'static,
+    S::UO: From<UserOperationVariant>,
+{
+    async fn simulate_validation(
+        &self,
+        request: Request<SimulateValidationRequest>,
+    ) -> Result<Response<SimulateValidationResponse>> {
+        let req = request.into_inner();
+        let uo = req.uo.ok_or_else(|| {
+            Status::invalid_argument("Operation is required in SimulateValidationRequest")
+        })?;
+        let uo = UserOperationVariant::try_uo_from_proto(uo, &self.chain_spec).map_err(|e| {
+            Status::invalid_argument(format!("Failed to convert to UserOperation: {e}"))
+        })?;
+        if uo.uo_type() != S::UO::entry_point_version() {
+            return Err(Status::invalid_argument(format!(
+                "Invalid user operation version for simulator {:?}: {:?}",
+                S::UO::entry_point_version(),
+                uo.uo_type()
+            )));
+        }
+        let block = req
+            .block
+            .ok_or_else(|| {
+                Status::invalid_argument("Block is required in SimulateValidationRequest")
+            })?
+            .try_into()
+            .map_err(|e| Status::invalid_argument(format!("Invalid block: {e}")))?;
+        let expected_code_hash = if req.expected_code_hash.is_empty() {
+            None
+        } else {
+            Some(from_bytes(&req.expected_code_hash).map_err(|e| {
+                Status::invalid_argument(format!("Invalid expected code hash: {e}"))
+            })?)
+        };
+
+        let result = match self
+            .simulator
+            .simulate_validation(uo.into(), req.trusted, block, expected_code_hash)
+            .await
+        {
+            Ok(result) => simulate_validation_response::Result::Success(result.into()),
+            Err(error) => simulate_validation_response::Result::Failure(error.into()),
+        };
+
+        Ok(Response::new(SimulateValidationResponse {
+            result: Some(result),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use alloy_primitives::{Address, B256, U256};
+    use rundler_sim::{BlockTag, MockSimulator, SimulationError, SimulationResult, ViolationError};
+    use rundler_types::{
+        pool::SimulationViolation, v0_6, Entity, EntityInfo, EntityInfos, ExpectedStorage,
+        Timestamp, ValidTimeRange,
+    };
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    use super::*;
+    use crate::server::RemoteSimulator;
+
+    // Serves the simulator on a local port and returns a client connected to it
+    async fn serve(simulator: MockSimulator) -> RemoteSimulator<v0_6::UserOperation> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = GrpcSimulatorServer::new(SimulatorImpl::new(ChainSpec::default(), simulator));
+        tokio::spawn(
+            Server::builder()
+                .add_service(server)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        RemoteSimulator::connect(format!("http://{addr}"))
+            .await
+            .unwrap()
+    }
+
+    fn entity_infos() -> EntityInfos {
+        EntityInfos {
+            sender: EntityInfo::new(Entity::account(Address::repeat_byte(1)), false),
+            paymaster: Some(EntityInfo::new(
+                Entity::paymaster(Address::repeat_byte(2)),
+                true,
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_round_trip() {
+        let block_hash = B256::repeat_byte(3);
+        let code_hash = B256::repeat_byte(4);
+        let mut expected_storage = ExpectedStorage::default();
+        expected_storage.insert(Address::repeat_byte(5), U256::from(1), U256::from(2));
+
+        let mut simulator = MockSimulator::new();
+        let expected = expected_storage.clone();
+        simulator
+            .expect_simulate_validation()
+            .withf(move |op, trusted, block, hash| {
+                op.sender() == Address::repeat_byte(1)
+                    && *trusted
+                    && *block == BlockTag::Hash(block_hash)
+                    && *hash == Some(code_hash)
+            })
+            .returning(move |_, _, _, _| {
+                Ok(SimulationResult {
+                    mempools: vec![B256::ZERO],
+                    pre_op_gas: 100_000,
+                    valid_time_range: ValidTimeRange::new(Timestamp::new(10), Timestamp::new(20)),
+                    code_hash,
+                    accessed_addresses: HashSet::from([Address::repeat_byte(5)]),
+                    expected_storage: expected.clone(),
+                    requires_post_op: true,
+                    entity_infos: entity_infos(),
+                    ..Default::default()
+                })
+            });
+        let client = serve(simulator).await;
+
+        let op = v0_6::UserOperationBuilder::new(
+            &ChainSpec::default(),
+            v0_6::UserOperationRequiredFields {
+                sender: Address::repeat_byte(1),
+                ..Default::default()
+            },
+        )
+        .build();
+        let result = client
+            .simulate_validation(op, true, BlockTag::Hash(block_hash), Some(code_hash))
+            .await
+            .unwrap();
+
+        assert_eq!(result.mempools, vec![B256::ZERO]);
+        assert_eq!(result.pre_op_gas, 100_000);
+        assert_eq!(
+            result.valid_time_range,
+            ValidTimeRange::new(Timestamp::new(10), Timestamp::new(20))
+        );
+        assert_eq!(result.code_hash, code_hash);
+        assert_eq!(
+            result.accessed_addresses,
+            HashSet::from([Address::repeat_byte(5)])
+        );
+        assert_eq!(result.expected_storage.0, expected_storage.0);
+        assert!(result.requires_post_op);
+        assert_eq!(result.entity_infos, entity_infos());
+        assert!(result.validation_logs.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_error_round_trip() {
+        let violation = SimulationViolation::InvalidAccountSignature;
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(move |_, _, _, _| {
+                Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![
+                        SimulationViolation::InvalidAccountSignature,
+                    ]),
+                    entity_infos: Some(entity_infos()),
+                })
+            });
+        let client = serve(simulator).await;
+
+        let err = client
+            .simulate_validation(
+                v0_6::UserOperation::default(),
+                false,
+                BlockTag::Latest,
+                None,
+            )
+            .await
+            .unwrap_err();
+
+        let ViolationError::Violations(violations) = err.violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(violations, vec![violation]);
+        assert_eq!(err.entity_infos, Some(entity_infos()));
+    }
+}
@@ -13,6 +13,7 @@
 
 use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
+use alloy_primitives::B256;
 use anyhow::{bail, Context};
 use futures::FutureExt;
 use rundler_provider::{EntryPoint, Providers, ProvidersWithEntryPointT};
@@ -20,10 +21,10 @@ use rundler_sim::{
     simulation::{self, UnsafeSimulator},
     PrecheckerImpl, Simulator,
 };
-use rundler_task::TaskSpawnerExt;
+use rundler_task::{block_watcher, TaskSpawnerExt};
 use rundler_types::{chain::ChainSpec, EntryPointVersion, UserOperation, UserOperationVariant};
 use rundler_utils::emit::WithEntryPoint;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
 
 use super::mempool::PoolConfig;
 use crate::{
@@ -45,6 +46,9 @@ pub struct Args {
     pub unsafe_mode: bool,
     /// HTTP URL for the full node.
     pub http_url: String,
+    /// Websocket URL for the full node, used to subscribe to new blocks. If not set,
+    /// the chain is only polled for new blocks.
+    pub ws_url: Option<String>,
     /// Interval to poll the chain for updates.
     pub chain_poll_interval: Duration,
     /// Number of times to retry a block sync at the `chain_poll_interval` before abandoning
@@ -111,7 +115,20 @@ where
             flashblocks: self.args.chain_spec.flashblocks_enabled,
         };
 
-        let chain = Chain::new(self.providers.evm().clone(), chain_settings);
+        let mut chain = Chain::new(self.providers.evm().clone(), chain_settings);
+        if let Some(ws_url) = &self.args.ws_url {
+            tracing::info!("Ws url: {ws_url:?}");
+            let ws_provider = rundler_provider::new_alloy_ws_evm_provider(ws_url)
+                .await
+                .context("should connect to ws url")?;
+            let (new_blocks_tx, new_blocks_rx) = watch::channel(B256::ZERO);
+            task_spawner.spawn(Box::pin(block_watcher::forward_block_subscription(
+                ws_provider,
+                new_blocks_tx,
+                self.args.chain_poll_interval,
+            )));
+            chain = chain.with_new_blocks(new_blocks_rx);
+        }
         let chain_subscriber = chain.subscriber();
 
         task_spawner.spawn_critical_with_graceful_shutdown_signal("chain watcher", |shutdown| {
@@ -307,7 +324,10 @@ where
         );
 
         let reputation = Arc::new(AddressReputation::new(
-            ReputationParams::new(pool_config.reputation_tracking_enabled),
+            ReputationParams::new(
+                pool_config.reputation_tracking_enabled,
+                pool_config.reputation_grace_period,
+            ),
             pool_config.blocklist.clone().unwrap_or_default(),
             pool_config.allowlist.clone().unwrap_or_default(),
         ));
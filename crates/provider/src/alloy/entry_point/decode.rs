@@ -0,0 +1,389 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Decoding of the results the entry point returns from its simulation methods.
+//!
+//! The v0.6 entry point reverts with its results from both `simulateValidation` and
+//! `simulateHandleOp`, while the v0.7 entry point simulations contract returns them and
+//! only reverts on failure.
+
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_sol_types::{ContractError as SolContractError, SolInterface, SolValue};
+use anyhow::Context;
+use rundler_contracts::{
+    v0_6::IEntryPoint::{
+        ExecutionResult as ExecutionResultV0_6, IEntryPointErrors as IEntryPointErrorsV0_6,
+    },
+    v0_7::{
+        IEntryPoint::IEntryPointErrors as IEntryPointErrorsV0_7,
+        IEntryPointSimulations::ExecutionResult as ExecutionResultV0_7,
+        ValidationResult as ValidationResultV0_7,
+    },
+};
+use rundler_types::{ValidationOutput, ValidationRevert};
+
+use crate::ExecutionResult;
+
+/// Decodes the revert data of a v0.6 `simulateHandleOp` call.
+///
+/// Returns the execution result on success, or the reason the op failed validation.
+pub fn decode_v0_6_execution_result(
+    revert_data: &[u8],
+) -> anyhow::Result<Result<ExecutionResult, ValidationRevert>> {
+    let Ok(err) = SolContractError::<IEntryPointErrorsV0_6>::abi_decode(revert_data) else {
+        return Ok(Err(ValidationRevert::Unknown(Bytes::copy_from_slice(
+            revert_data,
+        ))));
+    };
+
+    let ret = match err {
+        // success case
+        SolContractError::CustomError(IEntryPointErrorsV0_6::ExecutionResult(e)) => {
+            Ok(e.try_into()?)
+        }
+        // failure cases
+        SolContractError::CustomError(IEntryPointErrorsV0_6::FailedOp(f)) => Err(f.into()),
+        SolContractError::CustomError(IEntryPointErrorsV0_6::SignatureValidationFailed(f)) => {
+            Err(signature_validation_failed(f.aggregator))
+        }
+        SolContractError::Revert(r) => Err(r.into()),
+        SolContractError::Panic(p) => Err(p.into()),
+        // unexpected cases
+        SolContractError::CustomError(IEntryPointErrorsV0_6::ValidationResult(_))
+        | SolContractError::CustomError(IEntryPointErrorsV0_6::ValidationResultWithAggregation(
+            _,
+        )) => Err(ValidationRevert::EntryPoint(
+            "simulateHandleOp returned ValidationResult or ValidationResultWithAggregation, unexpected type".to_string(),
+        )),
+    };
+
+    Ok(ret)
+}
+
+/// Decodes the revert data of a v0.6 `simulateValidation` call.
+///
+/// Returns the validation output on success, or the reason the op failed validation.
+pub fn decode_v0_6_validation_result(
+    revert_data: &[u8],
+) -> anyhow::Result<Result<ValidationOutput, ValidationRevert>> {
+    let Ok(err) = SolContractError::<IEntryPointErrorsV0_6>::abi_decode(revert_data) else {
+        return Ok(Err(ValidationRevert::Unknown(Bytes::copy_from_slice(
+            revert_data,
+        ))));
+    };
+
+    let ret = match err {
+        // success cases
+        SolContractError::CustomError(IEntryPointErrorsV0_6::ValidationResult(s)) => {
+            Ok(s.try_into().map_err(anyhow::Error::msg)?)
+        }
+        SolContractError::CustomError(IEntryPointErrorsV0_6::ValidationResultWithAggregation(
+            s,
+        )) => Ok(s.try_into().map_err(anyhow::Error::msg)?),
+        // failure cases
+        SolContractError::CustomError(IEntryPointErrorsV0_6::FailedOp(f)) => Err(f.into()),
+        SolContractError::CustomError(IEntryPointErrorsV0_6::SignatureValidationFailed(f)) => {
+            Err(signature_validation_failed(f.aggregator))
+        }
+        SolContractError::Revert(r) => Err(r.into()),
+        SolContractError::Panic(p) => Err(p.into()),
+        // unexpected case
+        SolContractError::CustomError(IEntryPointErrorsV0_6::ExecutionResult(_)) => {
+            anyhow::bail!("simulateValidation returned ExecutionResult, unexpected type")
+        }
+    };
+
+    Ok(ret)
+}
+
+/// Decodes the return data of a v0.7 `simulateHandleOp` call
+pub fn decode_v0_7_execution_result(return_data: &[u8]) -> anyhow::Result<ExecutionResult> {
+    ExecutionResultV0_7::abi_decode(return_data)
+        .context("failed to decode execution result")?
+        .try_into()
+}
+
+/// Decodes the return data of a v0.7 `simulateValidation` call
+pub fn decode_v0_7_validation_result(return_data: &[u8]) -> anyhow::Result<ValidationOutput> {
+    ValidationResultV0_7::abi_decode(return_data)
+        .context("failed to decode validation result")?
+        .try_into()
+        .map_err(anyhow::Error::msg)
+}
+
+/// Decodes raw validation revert bytes from a v0.7 entry point
+pub fn decode_v0_7_validation_revert(revert_data: &[u8]) -> ValidationRevert {
+    let Ok(err) = SolContractError::<IEntryPointErrorsV0_7>::abi_decode(revert_data) else {
+        return ValidationRevert::Unknown(Bytes::copy_from_slice(revert_data));
+    };
+
+    match err {
+        SolContractError::CustomError(IEntryPointErrorsV0_7::FailedOp(f)) => f.into(),
+        SolContractError::CustomError(IEntryPointErrorsV0_7::FailedOpWithRevert(f)) => f.into(),
+        SolContractError::CustomError(IEntryPointErrorsV0_7::SignatureValidationFailed(f)) => {
+            signature_validation_failed(f.aggregator)
+        }
+        SolContractError::Revert(r) => r.into(),
+        SolContractError::Panic(p) => p.into(),
+    }
+}
+
+fn signature_validation_failed(aggregator: Address) -> ValidationRevert {
+    ValidationRevert::EntryPoint(format!(
+        "Aggregator signature validation failed: {aggregator}"
+    ))
+}
+
+impl TryFrom<ExecutionResultV0_6> for ExecutionResult {
+    type Error = anyhow::Error;
+
+    fn try_from(result: ExecutionResultV0_6) -> Result<Self, Self::Error> {
+        Ok(ExecutionResult {
+            pre_op_gas: pre_op_gas(result.preOpGas)?,
+            paid: result.paid,
+            valid_after: result.validAfter.to::<u64>().into(),
+            valid_until: result.validUntil.to::<u64>().into(),
+            target_success: result.targetSuccess,
+            target_result: result.targetResult,
+        })
+    }
+}
+
+impl TryFrom<ExecutionResultV0_7> for ExecutionResult {
+    type Error = anyhow::Error;
+
+    fn try_from(result: ExecutionResultV0_7) -> Result<Self, Self::Error> {
+        let account = rundler_types::parse_validation_data(result.accountValidationData);
+        let paymaster = rundler_types::parse_validation_data(result.paymasterValidationData);
+        let intersect_range = account
+            .valid_time_range()
+            .intersect(paymaster.valid_time_range());
+
+        Ok(ExecutionResult {
+            pre_op_gas: pre_op_gas(result.preOpGas)?,
+            paid: result.paid,
+            valid_after: intersect_range.valid_after,
+            valid_until: intersect_range.valid_until,
+            target_success: result.targetSuccess,
+            target_result: result.targetResult,
+        })
+    }
+}
+
+fn pre_op_gas(pre_op_gas: U256) -> anyhow::Result<u128> {
+    pre_op_gas.try_into().context("preOpGas should fit in u128")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{address, aliases::U48, bytes};
+    use alloy_sol_types::{Revert, SolError};
+    use rundler_contracts::{
+        v0_6::{
+            IEntryPoint::{FailedOp as FailedOpV0_6, ValidationResult as ValidationResultV0_6},
+            ReturnInfo as ReturnInfoV0_6, StakeInfo as StakeInfoV0_6,
+        },
+        v0_7::{
+            AggregatorStakeInfo as AggregatorStakeInfoV0_7,
+            IEntryPoint::FailedOpWithRevert as FailedOpWithRevertV0_7,
+            ReturnInfo as ReturnInfoV0_7, StakeInfo as StakeInfoV0_7,
+        },
+    };
+    use rundler_types::Timestamp;
+
+    use super::*;
+
+    // validation data with the given aggregator and time range, as packed by the entry point
+    fn validation_data(aggregator: Address, valid_after: u64, valid_until: u64) -> U256 {
+        U256::from_be_slice(aggregator.as_slice())
+            | (U256::from(valid_until) << 160)
+            | (U256::from(valid_after) << 208)
+    }
+
+    #[test]
+    fn test_decode_v0_6_execution_result() {
+        let revert_data = ExecutionResultV0_6 {
+            preOpGas: U256::from(100_000),
+            paid: U256::from(2_000_000),
+            validAfter: U48::from(10),
+            validUntil: U48::from(20),
+            targetSuccess: true,
+            targetResult: bytes!("1234"),
+        }
+        .abi_encode();
+
+        let result = decode_v0_6_execution_result(&revert_data).unwrap().unwrap();
+        assert_eq!(result.pre_op_gas, 100_000);
+        assert_eq!(result.paid, U256::from(2_000_000));
+        assert_eq!(result.valid_after, Timestamp::new(10));
+        assert_eq!(result.valid_until, Timestamp::new(20));
+        assert!(result.target_success);
+        assert_eq!(result.target_result, bytes!("1234"));
+    }
+
+    #[test]
+    fn test_decode_v0_6_execution_result_failed() {
+        let revert_data = FailedOpV0_6 {
+            opIndex: U256::ZERO,
+            reason: "AA21 didn't pay prefund".to_string(),
+        }
+        .abi_encode();
+        assert_eq!(
+            decode_v0_6_execution_result(&revert_data)
+                .unwrap()
+                .unwrap_err(),
+            ValidationRevert::EntryPoint("AA21 didn't pay prefund".to_string())
+        );
+
+        let revert_data = Revert::from("oops").abi_encode();
+        assert_eq!(
+            decode_v0_6_execution_result(&revert_data)
+                .unwrap()
+                .unwrap_err(),
+            ValidationRevert::EntryPoint("oops".to_string())
+        );
+
+        assert_eq!(
+            decode_v0_6_execution_result(&[1, 2, 3])
+                .unwrap()
+                .unwrap_err(),
+            ValidationRevert::Unknown(bytes!("010203"))
+        );
+    }
+
+    #[test]
+    fn test_decode_v0_6_validation_result() {
+        let revert_data = ValidationResultV0_6 {
+            returnInfo: ReturnInfoV0_6 {
+                preOpGas: U256::from(50_000),
+                prefund: U256::from(1_000),
+                sigFailed: false,
+                validAfter: U48::from(10),
+                validUntil: U48::from(20),
+                paymasterContext: bytes!("abcd"),
+            },
+            senderInfo: StakeInfoV0_6 {
+                stake: U256::from(5),
+                unstakeDelaySec: U256::from(60),
+            },
+            factoryInfo: StakeInfoV0_6::default(),
+            paymasterInfo: StakeInfoV0_6::default(),
+        }
+        .abi_encode();
+
+        let output = decode_v0_6_validation_result(&revert_data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.return_info.pre_op_gas, 50_000);
+        assert!(!output.return_info.account_sig_failed);
+        assert_eq!(output.return_info.valid_after, Timestamp::new(10));
+        assert_eq!(output.return_info.valid_until, Timestamp::new(20));
+        assert_eq!(output.return_info.paymaster_context, bytes!("abcd"));
+        assert_eq!(output.sender_info.stake, U256::from(5));
+        assert_eq!(output.sender_info.unstake_delay_sec, 60);
+        assert!(output.aggregator_info.is_none());
+
+        // simulateValidation never returns an execution result
+        let revert_data = ExecutionResultV0_6 {
+            preOpGas: U256::ZERO,
+            paid: U256::ZERO,
+            validAfter: U48::ZERO,
+            validUntil: U48::ZERO,
+            targetSuccess: true,
+            targetResult: Bytes::new(),
+        }
+        .abi_encode();
+        assert!(decode_v0_6_validation_result(&revert_data).is_err());
+    }
+
+    #[test]
+    fn test_decode_v0_7_execution_result() {
+        // the op is valid in the intersection of the account and paymaster time ranges
+        let return_data = ExecutionResultV0_7 {
+            preOpGas: U256::from(100_000),
+            paid: U256::from(2_000_000),
+            accountValidationData: validation_data(Address::ZERO, 10, 30),
+            paymasterValidationData: validation_data(Address::ZERO, 15, 20),
+            targetSuccess: false,
+            targetResult: bytes!("5678"),
+        }
+        .abi_encode();
+
+        let result = decode_v0_7_execution_result(&return_data).unwrap();
+        assert_eq!(result.pre_op_gas, 100_000);
+        assert_eq!(result.paid, U256::from(2_000_000));
+        assert_eq!(result.valid_after, Timestamp::new(15));
+        assert_eq!(result.valid_until, Timestamp::new(20));
+        assert!(!result.target_success);
+        assert_eq!(result.target_result, bytes!("5678"));
+
+        assert!(decode_v0_7_execution_result(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_decode_v0_7_validation_result() {
+        let aggregator = address!("0000000000000000000000000000000000000abc");
+        let return_data = ValidationResultV0_7 {
+            returnInfo: ReturnInfoV0_7 {
+                preOpGas: U256::from(50_000),
+                prefund: U256::from(1_000),
+                accountValidationData: validation_data(aggregator, 0, 0),
+                paymasterValidationData: U256::ZERO,
+                paymasterContext: bytes!("abcd"),
+            },
+            senderInfo: StakeInfoV0_7::default(),
+            factoryInfo: StakeInfoV0_7::default(),
+            paymasterInfo: StakeInfoV0_7 {
+                stake: U256::from(5),
+                unstakeDelaySec: U256::from(60),
+            },
+            aggregatorInfo: AggregatorStakeInfoV0_7 {
+                aggregator,
+                stakeInfo: StakeInfoV0_7::default(),
+            },
+        }
+        .abi_encode();
+
+        let output = decode_v0_7_validation_result(&return_data).unwrap();
+        assert_eq!(output.return_info.pre_op_gas, 50_000);
+        assert!(!output.return_info.paymaster_sig_failed);
+        assert_eq!(output.return_info.paymaster_context, bytes!("abcd"));
+        assert_eq!(output.paymaster_info.stake, U256::from(5));
+        assert_eq!(output.paymaster_info.unstake_delay_sec, 60);
+        assert_eq!(output.aggregator_info.unwrap().address, aggregator);
+    }
+
+    #[test]
+    fn test_decode_v0_7_validation_revert() {
+        let inner = Revert::from("bad signature").abi_encode();
+        let revert_data = FailedOpWithRevertV0_7 {
+            opIndex: U256::ZERO,
+            reason: "AA23 reverted".to_string(),
+            inner: inner.clone().into(),
+        }
+        .abi_encode();
+        assert_eq!(
+            decode_v0_7_validation_revert(&revert_data),
+            ValidationRevert::Operation {
+                entry_point_reason: "AA23 reverted".to_string(),
+                inner_revert_data: inner.into(),
+                inner_revert_reason: Some("bad signature".to_string()),
+            }
+        );
+
+        assert_eq!(
+            decode_v0_7_validation_revert(&[1, 2, 3]),
+            ValidationRevert::Unknown(bytes!("010203"))
+        );
+    }
+}
@@ -13,14 +13,183 @@
 
 use alloy_consensus::{transaction::SignableTransaction, TxEnvelope, TypedTransaction};
 use alloy_primitives::{address, Address, Bytes, Signature, U256};
-use alloy_provider::network::TransactionBuilder7702;
+use alloy_provider::{ext::DebugApi, network::TransactionBuilder7702};
 use alloy_rlp::Encodable;
-use alloy_rpc_types_eth::TransactionRequest;
-use rundler_types::authorization::Eip7702Auth;
+use alloy_rpc_types_eth::{BlockId, BlockNumberOrTag, BlockOverrides, TransactionRequest};
+use alloy_sol_types::SolEvent;
+use anyhow::Context;
+use rundler_types::{authorization::Eip7702Auth, UserOperation, UserOpsPerAggregator};
 
+use crate::{
+    AlloyProvider, BundleSimResult, GasAttribution, GethDebugBuiltInTracerType,
+    GethDebugTracerCallConfig, GethDebugTracerCallFrame, GethDebugTracerType,
+    GethDebugTracingCallOptions, GethDebugTracingOptions, ProviderResult,
+};
+
+pub(crate) mod decode;
 pub(crate) mod v0_6;
 pub(crate) mod v0_7;
 
+// Rejects a bundle with an op grouped under an aggregator it does not declare, as
+// handleOps would revert the whole bundle on it.
+fn check_aggregator_groups<UO: UserOperation>(
+    ops_per_aggregator: &[UserOpsPerAggregator<UO>],
+) -> ProviderResult<()> {
+    for group in ops_per_aggregator {
+        if let Some(op) = group.misgrouped_op() {
+            return Err(anyhow::anyhow!(
+                "op {:?} declares aggregator {:?} but is grouped under aggregator {:?}",
+                op.hash(),
+                op.aggregator(),
+                group.aggregator
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+// Block overrides that run an `eth_call` with the given block timestamp
+fn timestamp_block_overrides(timestamp: u64) -> BlockOverrides {
+    BlockOverrides {
+        time: Some(timestamp),
+        ..Default::default()
+    }
+}
+
+// Traces a handleOps call, reading the bundle's gas used from the top call frame and each
+// op's actual gas used from the events the entry point emits. Gas is attributed to the
+// phases of each op only if its entities are given. If the bundle reverts as a whole, the
+// top frame's error message and revert data are returned instead.
+async fn trace_handle_ops<AP, E>(
+    provider: &AP,
+    entry_point: Address,
+    tx: TransactionRequest,
+    op_entities: Option<&[OpEntities]>,
+    block_id: Option<BlockId>,
+    op_gas_used: impl Fn(&E) -> U256,
+) -> ProviderResult<Result<BundleSimResult, (String, Option<Bytes>)>>
+where
+    AP: AlloyProvider,
+    E: SolEvent,
+{
+    let trace_options = GethDebugTracingCallOptions {
+        tracing_options: GethDebugTracingOptions::new_tracer(GethDebugTracerType::BuiltInTracer(
+            GethDebugBuiltInTracerType::CallTracer,
+        ))
+        .with_call_config(GethDebugTracerCallConfig::default().with_log()),
+        ..Default::default()
+    };
+    let frame = provider
+        .debug_trace_call(
+            tx.into(),
+            block_id.unwrap_or(BlockNumberOrTag::Latest.into()),
+            trace_options,
+        )
+        .await?
+        .try_into_call_frame()
+        .context("handleOps trace should be a call frame")?;
+
+    if let Some(error) = frame.error {
+        return Ok(Err((error, frame.output)));
+    }
+
+    let per_op_gas = frame
+        .logs
+        .iter()
+        .filter(|log| log.address == Some(entry_point))
+        .filter_map(|log| {
+            let topics = log.topics.clone().unwrap_or_default();
+            let data = log.data.clone().unwrap_or_default();
+            E::decode_raw_log(topics, &data).ok()
+        })
+        .map(|event| op_gas_used(&event))
+        .collect();
+
+    Ok(Ok(BundleSimResult {
+        total_gas: frame.gas_used,
+        per_op_gas,
+        gas_attribution: op_entities
+            .map(|op_entities| attribute_gas(&frame, entry_point, op_entities))
+            .unwrap_or_default(),
+    }))
+}
+
+// The addresses the entry point calls for an op, used to attribute traced gas
+#[derive(Clone, Copy, Debug)]
+struct OpEntities {
+    sender: Address,
+    paymaster: Option<Address>,
+    aggregator: Option<Address>,
+}
+
+fn op_entities<UO: UserOperation>(
+    ops_per_aggregator: &[UserOpsPerAggregator<UO>],
+) -> Vec<OpEntities> {
+    ops_per_aggregator
+        .iter()
+        .flat_map(|group| {
+            let aggregator = (!group.aggregator.is_zero()).then_some(group.aggregator);
+            group.user_ops.iter().map(move |op| OpEntities {
+                sender: op.sender(),
+                paymaster: op.paymaster(),
+                aggregator,
+            })
+        })
+        .collect()
+}
+
+// Attributes the gas of the calls made by handleOps to each op's phases.
+//
+// The entry point first validates every op in order, with each op's validation ending
+// with the call to its paymaster, or to its sender if it has none. It then executes
+// every op in order through a call to itself, which calls the sender with the call
+// data and then the paymaster's postOp. The aggregators' signature validation, run
+// before the ops are validated, isn't attributed to any op.
+fn attribute_gas(
+    frame: &GethDebugTracerCallFrame,
+    entry_point: Address,
+    op_entities: &[OpEntities],
+) -> Vec<GasAttribution> {
+    let mut attributions = vec![GasAttribution::default(); op_entities.len()];
+
+    let mut validating = 0;
+    let mut executing = 0;
+    for call in &frame.calls {
+        if call.to.is_some() && op_entities.iter().any(|op| op.aggregator == call.to) {
+            continue;
+        }
+        if call.to == Some(entry_point) {
+            let (Some(op), Some(attribution)) =
+                (op_entities.get(executing), attributions.get_mut(executing))
+            else {
+                break;
+            };
+            for inner in &call.calls {
+                if inner.to == Some(op.sender) {
+                    attribution.execution_gas += inner.gas_used;
+                } else if inner.to.is_some() && inner.to == op.paymaster {
+                    attribution.post_op_gas += inner.gas_used;
+                }
+            }
+            executing += 1;
+        } else if executing == 0 {
+            let (Some(op), Some(attribution)) = (
+                op_entities.get(validating),
+                attributions.get_mut(validating),
+            ) else {
+                continue;
+            };
+            attribution.validation_gas += call.gas_used;
+            if call.to == Some(op.paymaster.unwrap_or(op.sender)) {
+                validating += 1;
+            }
+        }
+    }
+
+    attributions
+}
+
 fn max_bundle_transaction_data(
     to_address: Address,
     data: Bytes,
@@ -75,3 +244,129 @@ fn max_bundle_transaction_data(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(
+        to: Address,
+        gas_used: u64,
+        calls: Vec<GethDebugTracerCallFrame>,
+    ) -> GethDebugTracerCallFrame {
+        GethDebugTracerCallFrame {
+            to: Some(to),
+            gas_used: U256::from(gas_used),
+            calls,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_attribute_gas() {
+        let entry_point = Address::repeat_byte(0xee);
+        let sender_creator = Address::repeat_byte(0xcc);
+        let beneficiary = Address::repeat_byte(0xbb);
+        let ops = [
+            OpEntities {
+                sender: Address::repeat_byte(1),
+                paymaster: Some(Address::repeat_byte(0xaa)),
+                aggregator: None,
+            },
+            OpEntities {
+                sender: Address::repeat_byte(2),
+                paymaster: None,
+                aggregator: None,
+            },
+        ];
+
+        let frame = call(
+            entry_point,
+            1_000_000,
+            vec![
+                // validation
+                call(ops[0].sender, 100, vec![]),
+                call(ops[0].paymaster.unwrap(), 200, vec![]),
+                call(sender_creator, 300, vec![]),
+                call(ops[1].sender, 400, vec![]),
+                // execution
+                call(
+                    entry_point,
+                    5_000,
+                    vec![
+                        call(ops[0].sender, 1_000, vec![]),
+                        call(ops[0].paymaster.unwrap(), 500, vec![]),
+                    ],
+                ),
+                call(entry_point, 3_000, vec![call(ops[1].sender, 2_000, vec![])]),
+                // compensation
+                call(beneficiary, 0, vec![]),
+            ],
+        );
+
+        assert_eq!(
+            attribute_gas(&frame, entry_point, &ops),
+            vec![
+                GasAttribution {
+                    validation_gas: U256::from(300),
+                    execution_gas: U256::from(1_000),
+                    post_op_gas: U256::from(500),
+                },
+                GasAttribution {
+                    validation_gas: U256::from(700),
+                    execution_gas: U256::from(2_000),
+                    post_op_gas: U256::ZERO,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attribute_gas_aggregated() {
+        let entry_point = Address::repeat_byte(0xee);
+        let aggregator = Address::repeat_byte(0xa9);
+        let ops = [
+            OpEntities {
+                sender: Address::repeat_byte(1),
+                paymaster: None,
+                aggregator: Some(aggregator),
+            },
+            OpEntities {
+                sender: Address::repeat_byte(2),
+                paymaster: None,
+                aggregator: None,
+            },
+        ];
+
+        let frame = call(
+            entry_point,
+            1_000_000,
+            vec![
+                // signature validation of the aggregated group
+                call(aggregator, 50_000, vec![]),
+                // validation
+                call(ops[0].sender, 100, vec![]),
+                call(ops[1].sender, 400, vec![]),
+                // execution
+                call(entry_point, 3_000, vec![call(ops[0].sender, 1_000, vec![])]),
+                call(entry_point, 3_000, vec![call(ops[1].sender, 2_000, vec![])]),
+            ],
+        );
+
+        assert_eq!(
+            attribute_gas(&frame, entry_point, &ops),
+            vec![
+                GasAttribution {
+                    validation_gas: U256::from(100),
+                    execution_gas: U256::from(1_000),
+                    post_op_gas: U256::ZERO,
+                },
+                GasAttribution {
+                    validation_gas: U256::from(400),
+                    execution_gas: U256::from(2_000),
+                    post_op_gas: U256::ZERO,
+                },
+            ]
+        );
+    }
+}
@@ -19,14 +19,13 @@ use alloy_rpc_types_eth::{
     state::{AccountOverride, StateOverride},
     BlockId,
 };
-use alloy_sol_types::{ContractError as SolContractError, SolInterface};
+use alloy_sol_types::SolInterface;
 use alloy_transport::TransportError;
 use anyhow::Context;
 use rundler_contracts::v0_6::{
     DepositInfo as DepositInfoV0_6, GetEntryPointBalances, IAggregator,
     IEntryPoint::{
-        ExecutionResult as ExecutionResultV0_6, FailedOp, IEntryPointCalls, IEntryPointErrors,
-        IEntryPointInstance,
+        FailedOp, IEntryPointCalls, IEntryPointErrors, IEntryPointInstance, UserOperationEvent,
     },
     UserOperation as ContractUserOperation, UserOpsPerAggregator as UserOpsPerAggregatorV0_6,
 };
@@ -40,11 +39,12 @@ use rundler_types::{
 use rundler_utils::authorization_utils;
 use tracing::instrument;
 
+use super::decode;
 use crate::{
-    AggregatorOut, AggregatorSimOut, AlloyProvider, BlockHashOrNumber, BundleHandler, DAGasOracle,
-    DAGasProvider, DepositInfo, EntryPoint, EntryPointProvider as EntryPointProviderTrait,
-    ExecutionResult, HandleOpsOut, ProviderResult, SignatureAggregator, SimulationProvider,
-    TransactionRequest,
+    AggregatorOut, AggregatorSimOut, AlloyProvider, BlockHashOrNumber, BundleHandler,
+    BundleSimResult, DAGasOracle, DAGasProvider, DepositInfo, EntryPoint,
+    EntryPointProvider as EntryPointProviderTrait, ExecutionResult, HandleOpsOut, ProviderResult,
+    SignatureAggregator, SimulationProvider, TransactionRequest,
 };
 
 /// Entry point provider for v0.6
@@ -257,6 +257,7 @@ where
         proxy: Option<Address>,
         _validation_only: bool,
     ) -> ProviderResult<HandleOpsOut> {
+        super::check_aggregator_groups(&ops_per_aggregator)?;
         let tx = get_handle_ops_call(
             &self.i_entry_point,
             ops_per_aggregator,
@@ -278,6 +279,41 @@ where
         }
     }
 
+    #[instrument(skip_all)]
+    async fn simulate_handle_ops(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
+        beneficiary: Address,
+        block_id: Option<BlockId>,
+        attribute_gas: bool,
+    ) -> ProviderResult<Result<BundleSimResult, HandleOpsOut>> {
+        super::check_aggregator_groups(&ops_per_aggregator)?;
+        let op_entities = attribute_gas.then(|| super::op_entities(&ops_per_aggregator));
+        let tx = get_handle_ops_call(
+            &self.i_entry_point,
+            ops_per_aggregator,
+            beneficiary,
+            self.chain_spec.transaction_gas_limit() as u64,
+            GasFees::default(),
+            None,
+            self.chain_spec.id,
+        );
+        let res = super::trace_handle_ops(
+            self.i_entry_point.provider(),
+            *self.i_entry_point.address(),
+            tx,
+            op_entities.as_deref(),
+            block_id,
+            |event: &UserOperationEvent| event.actualGasUsed,
+        )
+        .await?;
+
+        Ok(res.map_err(|(message, revert_data)| {
+            Self::decode_handle_ops_revert(&message, &revert_data)
+                .unwrap_or_else(|| HandleOpsOut::Revert(revert_data.unwrap_or_default()))
+        }))
+    }
+
     fn get_send_bundle_transaction(
         &self,
         ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
@@ -414,47 +450,23 @@ where
         &self,
         user_op: UserOperation,
         block_id: Option<BlockId>,
+        timestamp_override: Option<u64>,
     ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>> {
-        let da_gas = user_op
-            .pre_verification_da_gas_limit(&self.chain_spec, Some(1))
-            .try_into()
-            .unwrap_or(u64::MAX);
-
-        let blockless = self
-            .i_entry_point
-            .simulateValidation(user_op.into())
-            .gas(self.max_verification_gas.saturating_add(da_gas));
-        let call = match block_id {
-            Some(block_id) => blockless.block(block_id),
-            None => blockless,
-        };
+        let (tx, _) = self.get_tracer_simulate_validation_call(user_op)?;
+        let mut call = self.i_entry_point.provider().call(tx.into());
+        if let Some(block_id) = block_id {
+            call = call.block(block_id);
+        }
+        if let Some(timestamp) = timestamp_override {
+            call = call.with_block_overrides(super::timestamp_block_overrides(timestamp));
+        }
 
-        match call.call().await {
+        match call.await {
             Ok(_) => Err(anyhow::anyhow!("simulateValidation should always revert"))?,
-            Err(ContractError::TransportError(TransportError::ErrorResp(resp))) => {
-                if let Some(err) =
-                    resp.as_decoded_interface_error::<SolContractError<IEntryPointErrors>>()
-                {
-                    match err {
-                        // success cases
-                        SolContractError::CustomError(IEntryPointErrors::ValidationResult(s)) => {
-                            Ok(Ok(s.try_into().map_err(anyhow::Error::msg)?))
-                        }
-                        SolContractError::CustomError(
-                            IEntryPointErrors::ValidationResultWithAggregation(s),
-                        ) => Ok(Ok(s.try_into().map_err(anyhow::Error::msg)?)),
-                        // failure cases
-                        SolContractError::CustomError(IEntryPointErrors::FailedOp(f)) => {
-                            Ok(Err(f.into()))
-                        }
-                        SolContractError::Revert(r) => Ok(Err(r.into())),
-                        // unexpected case or unknown error
-                        _ => Err(TransportError::ErrorResp(resp).into()),
-                    }
-                } else {
-                    Err(TransportError::ErrorResp(resp).into())
-                }
-            }
+            Err(TransportError::ErrorResp(resp)) => match resp.as_revert_data() {
+                Some(revert_data) => Ok(decode::decode_v0_6_validation_result(&revert_data)?),
+                None => Err(TransportError::ErrorResp(resp).into()),
+            },
             Err(error) => Err(error.into()),
         }
     }
@@ -506,35 +518,7 @@ where
     fn decode_simulate_handle_ops_revert(
         payload: &Bytes,
     ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
-        let ret = if let Ok(err) = SolContractError::<IEntryPointErrors>::abi_decode(payload) {
-            match err {
-                // success case
-                SolContractError::CustomError(IEntryPointErrors::ExecutionResult(e)) => {
-                    Ok(e.try_into()?)
-                }
-                // failure cases
-                SolContractError::CustomError(IEntryPointErrors::FailedOp(f)) => Err(f.into()),
-                SolContractError::CustomError(IEntryPointErrors::SignatureValidationFailed(f)) => {
-                    Err(ValidationRevert::EntryPoint(format!(
-                        "Aggregator signature validation failed: {}",
-                        f.aggregator
-                    )))
-                }
-                SolContractError::Revert(r) => Err(r.into()),
-                // unexpected cases
-                SolContractError::CustomError(IEntryPointErrors::ValidationResult(_))
-                | SolContractError::CustomError(
-                    IEntryPointErrors::ValidationResultWithAggregation(_),
-                ) => Err(ValidationRevert::EntryPoint(
-                    "simulateHandleOp returned ValidationResult or ValidationResultWithAggregation, unexpected type".to_string()
-                )),
-                SolContractError::Panic(p) => Err(p.into()),
-            }
-        } else {
-            Err(ValidationRevert::Unknown(payload.clone()))
-        };
-
-        Ok(ret)
+        Ok(decode::decode_v0_6_execution_result(payload)?)
     }
 
     fn simulation_should_revert(&self) -> bool {
@@ -703,24 +687,6 @@ async fn simulate_handle_op_inner<S: SimulationProvider, AP: AlloyProvider>(
     }
 }
 
-impl TryFrom<ExecutionResultV0_6> for ExecutionResult {
-    type Error = anyhow::Error;
-
-    fn try_from(result: ExecutionResultV0_6) -> Result<Self, Self::Error> {
-        Ok(ExecutionResult {
-            pre_op_gas: result
-                .preOpGas
-                .try_into()
-                .context("preOpGas should fit in u128")?,
-            paid: result.paid,
-            valid_after: result.validAfter.to::<u64>().into(),
-            valid_until: result.validUntil.to::<u64>().into(),
-            target_success: result.targetSuccess,
-            target_result: result.targetResult,
-        })
-    }
-}
-
 impl From<DepositInfoV0_6> for DepositInfo {
     fn from(deposit_info: DepositInfoV0_6) -> Self {
         Self {
@@ -19,18 +19,17 @@ use alloy_rpc_types_eth::{
     state::{AccountOverride, StateOverride},
     BlockId,
 };
-use alloy_sol_types::{ContractError as SolContractError, SolInterface, SolValue};
+use alloy_sol_types::SolInterface;
 use alloy_transport::TransportError;
 use anyhow::Context;
 use rundler_contracts::v0_7::{
     DepositInfo as DepositInfoV0_7, GetEntryPointBalances, IAggregator,
     IEntryPoint::{
         FailedOp, FailedOpWithRevert, IEntryPointCalls, IEntryPointErrors, IEntryPointInstance,
+        UserOperationEvent,
     },
-    IEntryPointSimulations::{
-        self, ExecutionResult as ExecutionResultV0_7, IEntryPointSimulationsInstance,
-    },
-    UserOpsPerAggregator as UserOpsPerAggregatorV0_7, ValidationResult as ValidationResultV0_7,
+    IEntryPointSimulations::{self, IEntryPointSimulationsInstance},
+    UserOpsPerAggregator as UserOpsPerAggregatorV0_7,
     ENTRY_POINT_SIMULATIONS_V0_7_DEPLOYED_BYTECODE,
 };
 use rundler_types::{
@@ -44,11 +43,12 @@ use rundler_types::{
 use rundler_utils::authorization_utils;
 use tracing::instrument;
 
+use super::decode;
 use crate::{
-    AggregatorOut, AggregatorSimOut, AlloyProvider, BlockHashOrNumber, BundleHandler, DAGasOracle,
-    DAGasProvider, DepositInfo, EntryPoint, EntryPointProvider as EntryPointProviderTrait,
-    ExecutionResult, HandleOpsOut, ProviderResult, SignatureAggregator, SimulationProvider,
-    TransactionRequest,
+    AggregatorOut, AggregatorSimOut, AlloyProvider, BlockHashOrNumber, BundleHandler,
+    BundleSimResult, DAGasOracle, DAGasProvider, DepositInfo, EntryPoint,
+    EntryPointProvider as EntryPointProviderTrait, ExecutionResult, HandleOpsOut, ProviderResult,
+    SignatureAggregator, SimulationProvider, TransactionRequest,
 };
 
 /// Entry point provider for v0.7
@@ -264,6 +264,8 @@ where
         proxy: Option<Address>,
         validation_only: bool,
     ) -> ProviderResult<HandleOpsOut> {
+        // checked before the validation only op is appended, as it declares no aggregator
+        super::check_aggregator_groups(&ops_per_aggregator)?;
         let mut expected_failure_index: Option<usize> = None;
         if validation_only && proxy.is_none() {
             expected_failure_index = Some(
@@ -343,6 +345,41 @@ where
         }
     }
 
+    #[instrument(skip_all)]
+    async fn simulate_handle_ops(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
+        beneficiary: Address,
+        block_id: Option<BlockId>,
+        attribute_gas: bool,
+    ) -> ProviderResult<Result<BundleSimResult, HandleOpsOut>> {
+        super::check_aggregator_groups(&ops_per_aggregator)?;
+        let op_entities = attribute_gas.then(|| super::op_entities(&ops_per_aggregator));
+        let tx = get_handle_ops_call(
+            &self.i_entry_point,
+            ops_per_aggregator,
+            beneficiary,
+            self.chain_spec.transaction_gas_limit() as u64,
+            GasFees::default(),
+            None,
+            self.chain_spec.id,
+        );
+        let res = super::trace_handle_ops(
+            self.i_entry_point.provider(),
+            *self.i_entry_point.address(),
+            tx,
+            op_entities.as_deref(),
+            block_id,
+            |event: &UserOperationEvent| event.actualGasUsed,
+        )
+        .await?;
+
+        Ok(res.map_err(|(message, revert_data)| {
+            Self::decode_handle_ops_revert(&message, &revert_data)
+                .unwrap_or_else(|| HandleOpsOut::Revert(revert_data.unwrap_or_default()))
+        }))
+    }
+
     fn get_send_bundle_transaction(
         &self,
         ops_per_aggregator: Vec<UserOpsPerAggregator<UserOperation>>,
@@ -490,24 +527,24 @@ where
         &self,
         user_op: Self::UO,
         block_id: Option<BlockId>,
+        timestamp_override: Option<u64>,
     ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>> {
         let (tx, overrides) = self.get_tracer_simulate_validation_call(user_op)?;
         let mut call = self.i_entry_point.provider().call(tx.into());
         if let Some(block_id) = block_id {
             call = call.block(block_id);
         }
+        if let Some(timestamp) = timestamp_override {
+            call = call.with_block_overrides(super::timestamp_block_overrides(timestamp));
+        }
 
         let result = call.overrides(overrides).await;
 
         match result {
-            Ok(output) => {
-                let out = ValidationResultV0_7::abi_decode(&output)
-                    .context("failed to decode validation result")?;
-                Ok(Ok(out.try_into().map_err(anyhow::Error::msg)?))
-            }
+            Ok(output) => Ok(Ok(decode::decode_v0_7_validation_result(&output)?)),
             Err(TransportError::ErrorResp(resp)) => {
                 if let Some(revert) = resp.as_revert_data() {
-                    Ok(Err(decode_validation_revert(&revert)))
+                    Ok(Err(decode::decode_v0_7_validation_revert(&revert)))
                 } else {
                     Err(TransportError::ErrorResp(resp).into())
                 }
@@ -567,7 +604,7 @@ where
     ) -> ProviderResult<Result<ExecutionResult, ValidationRevert>> {
         // v0.7 doesn't encode execution results in the revert data
         // so we can just decode as validation revert
-        Ok(Err(decode_validation_revert(revert_data)))
+        Ok(Err(decode::decode_v0_7_validation_revert(revert_data)))
     }
 
     fn simulation_should_revert(&self) -> bool {
@@ -654,26 +691,6 @@ fn get_handle_ops_call<AP: AlloyProvider>(
     txn_request
 }
 
-/// Decodes raw validation revert bytes from a v0.7 entry point
-pub fn decode_validation_revert(err_bytes: &Bytes) -> ValidationRevert {
-    if let Ok(rev) = SolContractError::<IEntryPointErrors>::abi_decode(err_bytes) {
-        match rev {
-            SolContractError::CustomError(IEntryPointErrors::FailedOp(f)) => f.into(),
-            SolContractError::CustomError(IEntryPointErrors::FailedOpWithRevert(f)) => f.into(),
-            SolContractError::CustomError(IEntryPointErrors::SignatureValidationFailed(f)) => {
-                ValidationRevert::EntryPoint(format!(
-                    "Aggregator signature validation failed: {}",
-                    f.aggregator
-                ))
-            }
-            SolContractError::Revert(r) => r.into(),
-            SolContractError::Panic(p) => p.into(),
-        }
-    } else {
-        ValidationRevert::Unknown(err_bytes.clone())
-    }
-}
-
 /// Decode user ops from calldata
 pub fn decode_ops_from_calldata(
     chain_spec: &ChainSpec,
@@ -771,7 +788,7 @@ async fn simulate_handle_op_inner<AP: AlloyProvider>(
         Ok(output) => Ok(Ok(output.try_into()?)),
         Err(ContractError::TransportError(TransportError::ErrorResp(resp))) => {
             if let Some(revert) = resp.as_revert_data() {
-                Ok(Err(decode_validation_revert(&revert)))
+                Ok(Err(decode::decode_v0_7_validation_revert(&revert)))
             } else {
                 Err(TransportError::ErrorResp(resp).into())
             }
@@ -792,30 +809,6 @@ impl From<DepositInfoV0_7> for DepositInfo {
     }
 }
 
-impl TryFrom<ExecutionResultV0_7> for ExecutionResult {
-    type Error = anyhow::Error;
-
-    fn try_from(result: ExecutionResultV0_7) -> Result<Self, Self::Error> {
-        let account = rundler_types::parse_validation_data(result.accountValidationData);
-        let paymaster = rundler_types::parse_validation_data(result.paymasterValidationData);
-        let intersect_range = account
-            .valid_time_range()
-            .intersect(paymaster.valid_time_range());
-
-        Ok(ExecutionResult {
-            pre_op_gas: result
-                .preOpGas
-                .try_into()
-                .context("preOpGas should fit in u128")?,
-            paid: result.paid,
-            valid_after: intersect_range.valid_after,
-            valid_until: intersect_range.valid_until,
-            target_success: result.targetSuccess,
-            target_result: result.targetResult,
-        })
-    }
-}
-
 fn add_authorization_tuple(
     sender: Address,
     authorization: Option<&Eip7702Auth>,
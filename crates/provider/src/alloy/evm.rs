@@ -22,6 +22,7 @@ use alloy_rpc_types_trace::geth::{
 };
 use alloy_serde::WithOtherFields;
 use anyhow::Context;
+use futures_util::StreamExt;
 use rundler_contracts::utils::{
     GetBalances::{self, GetBalancesInstance},
     GetCodeHashes::{self, GetCodeHashesInstance},
@@ -30,11 +31,11 @@ use rundler_contracts::utils::{
 };
 use rundler_types::ExpectedStorage;
 use serde_json::json;
-use tracing::instrument;
+use tracing::{instrument, warn};
 
 use crate::{
-    AlloyProvider, Block, EvmCall, EvmProvider, ProviderResult, RpcRecv, RpcSend, Transaction,
-    TransactionReceipt, TransactionRequest,
+    AlloyProvider, Block, BlockHashStream, EvmCall, EvmProvider, ProviderResult, RpcRecv, RpcSend,
+    Transaction, TransactionReceipt, TransactionRequest,
 };
 
 /// Evm Provider implementation using [alloy-provider](https://github.com/alloy-rs/alloy-rs)
@@ -268,6 +269,58 @@ where
         Ok(ret)
     }
 
+    #[instrument(skip_all)]
+    async fn batch_call(
+        &self,
+        calls: Vec<EvmCall>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<Vec<Bytes>> {
+        let block = block.unwrap_or(BlockNumberOrTag::Latest.into());
+        let requests = calls
+            .into_iter()
+            .map(|call| {
+                let tx = alloy_rpc_types_eth::TransactionRequest::default()
+                    .to(call.to)
+                    .value(call.value)
+                    .with_input(call.data);
+                (tx, call.state_override)
+            })
+            .collect::<Vec<_>>();
+
+        let mut batch = self.inner.client().new_batch();
+        let waiters = requests
+            .iter()
+            .map(|(tx, state_override)| {
+                batch.add_call::<_, Bytes>("eth_call", &(tx.clone(), block, state_override.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let batched = match batch.send().await {
+            Ok(()) => futures_util::future::join_all(waiters)
+                .await
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>(),
+            Err(e) => Err(e),
+        };
+        match batched {
+            Ok(outputs) => return Ok(outputs),
+            // an error response is the result of one of the calls, i.e. a revert
+            Err(e) if e.is_error_resp() => return Err(e.into()),
+            Err(e) => warn!("Batch request failed, falling back to one request per call: {e:?}"),
+        }
+
+        let mut outputs = Vec::with_capacity(requests.len());
+        for (tx, state_override) in requests {
+            outputs.push(
+                self.inner
+                    .call(tx.into())
+                    .block(block)
+                    .overrides(state_override)
+                    .await?,
+            );
+        }
+        Ok(outputs)
+    }
+
     #[instrument(skip_all)]
     async fn batch_get_storage_at(
         &self,
@@ -367,6 +420,19 @@ where
             .zip(ret.into_iter())
             .collect::<Vec<_>>())
     }
+
+    #[instrument(skip(self))]
+    async fn subscribe_blocks(&self) -> ProviderResult<Option<BlockHashStream>> {
+        // HTTP providers have no pubsub frontend
+        if self.inner.client().pubsub_frontend().is_none() {
+            return Ok(None);
+        }
+
+        let subscription = self.inner.subscribe_blocks().await?;
+        Ok(Some(Box::pin(
+            subscription.into_stream().map(|header| header.hash),
+        )))
+    }
 }
 
 #[cfg(test)]
@@ -13,7 +13,7 @@
 
 use std::time::Duration;
 
-use alloy_provider::{network::AnyNetwork, Provider as AlloyProvider, ProviderBuilder};
+use alloy_provider::{network::AnyNetwork, Provider as AlloyProvider, ProviderBuilder, WsConnect};
 use alloy_rpc_client::ClientBuilder;
 use anyhow::Context;
 use evm::AlloyEvmProvider;
@@ -39,6 +39,22 @@ pub fn new_alloy_evm_provider(
     Ok(AlloyEvmProvider::new(provider))
 }
 
+/// Create a new alloy evm provider connected to a given websocket URL, supporting
+/// subscriptions
+pub async fn new_alloy_ws_evm_provider(ws_url: &str) -> anyhow::Result<impl EvmProvider + Clone> {
+    Url::parse(ws_url).context("invalid ws url")?;
+    // the websocket transport reconnects on its own, retry for a long time before giving up
+    let connect = WsConnect::new(ws_url)
+        .with_max_retries(u32::MAX)
+        .with_retry_interval(Duration::from_secs(1));
+    let provider = ProviderBuilder::new()
+        .network::<AnyNetwork>()
+        .connect_ws(connect)
+        .await
+        .context("should connect to ws url")?;
+    Ok(AlloyEvmProvider::new(provider))
+}
+
 /// Create a new alloy provider from a given RPC URL
 pub fn new_alloy_provider(
     rpc_url: &str,
@@ -65,14 +81,21 @@ pub fn new_alloy_provider(
 #[cfg(test)]
 mod tests {
     use std::{
+        io::Read,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
         thread::{self, sleep},
         time::Duration,
     };
 
+    use alloy_primitives::{Address, Bytes, U256};
     use alloy_provider::Provider;
+    use serde_json::{json, Value};
     use tiny_http::{Response, Server};
 
-    use crate::new_alloy_provider;
+    use crate::{new_alloy_provider, AlloyEvmProvider, EvmCall, EvmProvider, StateOverride};
     fn setup() {
         let server = Server::http("0.0.0.0:9009").unwrap();
         for request in server.incoming_requests() {
@@ -82,6 +105,62 @@ mod tests {
             ));
         }
     }
+    // Serves every `eth_call` with a fixed output, counting the HTTP requests received
+    fn serve_calls(reject_batches: bool) -> (String, Arc<AtomicUsize>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let result =
+                    |req: &Value| json!({"jsonrpc": "2.0", "id": req["id"], "result": "0x01"});
+                let response = match serde_json::from_str::<Value>(&body).unwrap() {
+                    Value::Array(_) if reject_batches => json!({
+                        "jsonrpc": "2.0",
+                        "id": null,
+                        "error": {"code": -32600, "message": "batch requests not supported"},
+                    }),
+                    Value::Array(reqs) => Value::Array(reqs.iter().map(result).collect()),
+                    req => result(&req),
+                };
+                let _ = request.respond(Response::from_string(response.to_string()));
+            }
+        });
+        (url, requests)
+    }
+
+    async fn batch_call_requests(reject_batches: bool) -> usize {
+        let (url, requests) = serve_calls(reject_batches);
+        let provider = AlloyEvmProvider::new(new_alloy_provider(&url, 10).unwrap());
+        let calls = (0..3)
+            .map(|i| EvmCall {
+                to: Address::repeat_byte(i),
+                data: Bytes::new(),
+                value: U256::ZERO,
+                state_override: StateOverride::default(),
+            })
+            .collect();
+
+        let outputs = provider.batch_call(calls, None).await.unwrap();
+        assert_eq!(outputs, vec![Bytes::from_static(&[1]); 3]);
+        requests.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_batch_call_single_round_trip() {
+        assert_eq!(batch_call_requests(false).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_call_falls_back_to_sequential() {
+        // the rejected batch, then one request per call
+        assert_eq!(batch_call_requests(true).await, 4);
+    }
+
     #[ignore = "this test is flaky with github action, should only run locally"]
     #[tokio::test]
     async fn test_timeout() {
@@ -41,6 +41,54 @@ pub fn new_fee_estimator<P: EvmProvider + Clone + 'static>(
     )
 }
 
+/// Fee estimator that always returns the same fees, for deterministic bundle fees
+/// in tests without a live node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FixedGasPriceSource {
+    /// Base fee reported for every block
+    pub base_fee: u128,
+    /// Priority fee used for every bundle
+    pub priority_fee: u128,
+}
+
+impl FixedGasPriceSource {
+    fn bundle_fees(&self, min_fees: Option<GasFees>) -> (GasFees, u128) {
+        let required_fees = min_fees.unwrap_or_default();
+        let max_priority_fee_per_gas = required_fees
+            .max_priority_fee_per_gas
+            .max(self.priority_fee);
+        let max_fee_per_gas = required_fees
+            .max_fee_per_gas
+            .max(self.base_fee + max_priority_fee_per_gas);
+        (
+            GasFees {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            },
+            self.base_fee,
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl FeeEstimator for FixedGasPriceSource {
+    async fn latest_bundle_fees(&self) -> anyhow::Result<(GasFees, u128)> {
+        Ok(self.bundle_fees(None))
+    }
+
+    async fn required_bundle_fees(
+        &self,
+        _block_hash: B256,
+        min_fees: Option<GasFees>,
+    ) -> anyhow::Result<(GasFees, u128)> {
+        Ok(self.bundle_fees(min_fees))
+    }
+
+    fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
+        bundle_fees
+    }
+}
+
 // Gas fee estimator for a 4337 user operation.
 struct FeeEstimatorImpl<P, O> {
     provider: P,
@@ -163,3 +211,46 @@ impl<P: EvmProvider, O: FeeOracle> FeeEstimatorImpl<P, O> {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixed_gas_price_source() {
+        let source = FixedGasPriceSource {
+            base_fee: 1000,
+            priority_fee: 50,
+        };
+
+        assert_eq!(
+            source.required_bundle_fees(B256::ZERO, None).await.unwrap(),
+            (
+                GasFees {
+                    max_fee_per_gas: 1050,
+                    max_priority_fee_per_gas: 50,
+                },
+                1000
+            )
+        );
+
+        // replacements still get at least the required minimum
+        let min_fees = GasFees {
+            max_fee_per_gas: 2000,
+            max_priority_fee_per_gas: 100,
+        };
+        assert_eq!(
+            source
+                .required_bundle_fees(B256::ZERO, Some(min_fees))
+                .await
+                .unwrap(),
+            (
+                GasFees {
+                    max_fee_per_gas: 2000,
+                    max_priority_fee_per_gas: 100,
+                },
+                1000
+            )
+        );
+    }
+}
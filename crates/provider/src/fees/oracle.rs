@@ -12,7 +12,7 @@
 // If not, see https://www.gnu.org/licenses/.
 #![allow(dead_code)]
 
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 use futures_util::future::join_all;
 use rundler_types::chain::{ChainSpec, PriorityFeeOracleType};
@@ -65,6 +65,29 @@ where
             };
             Box::new(UsageBasedFeeOracle::new(provider, config))
         }
+        PriorityFeeOracleType::FeeHistory => Box::new(FeeHistoryOracle::new(
+            provider,
+            fee_history_oracle_config(chain_spec),
+        )),
+        PriorityFeeOracleType::ProviderWithFeeHistoryFallback => {
+            let provider = Arc::new(provider);
+            Box::new(FallbackOracle::new(
+                ProviderOracle::new(
+                    Arc::clone(&provider),
+                    chain_spec.min_max_priority_fee_per_gas(),
+                ),
+                FeeHistoryOracle::new(provider, fee_history_oracle_config(chain_spec)),
+            ))
+        }
+    }
+}
+
+fn fee_history_oracle_config(chain_spec: &ChainSpec) -> FeeHistoryOracleConfig {
+    FeeHistoryOracleConfig {
+        blocks_history: chain_spec.fee_history_block_count,
+        percentile: chain_spec.fee_history_reward_percentile,
+        minimum_fee: chain_spec.min_max_priority_fee_per_gas(),
+        maximum_fee: chain_spec.max_max_priority_fee_per_gas(),
     }
 }
 
@@ -326,6 +349,37 @@ impl FeeOracle for MaxOracle<'_> {
     }
 }
 
+/// Oracle that uses a primary oracle, falling back to a secondary oracle if the
+/// primary fails, i.e. if the provider doesn't support `eth_maxPriorityFeePerGas`
+pub(crate) struct FallbackOracle<A, B> {
+    primary: A,
+    fallback: B,
+}
+
+impl<A, B> FallbackOracle<A, B> {
+    pub(crate) fn new(primary: A, fallback: B) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+#[async_trait::async_trait]
+impl<A, B> FeeOracle for FallbackOracle<A, B>
+where
+    A: FeeOracle,
+    B: FeeOracle,
+{
+    #[instrument(skip_all)]
+    async fn estimate_priority_fee(&self) -> Result<u128> {
+        match self.primary.estimate_priority_fee().await {
+            Ok(fee) => Ok(fee),
+            Err(e) => {
+                tracing::debug!("Primary fee oracle failed, using fallback: {e:?}");
+                self.fallback.estimate_priority_fee().await
+            }
+        }
+    }
+}
+
 /// Oracle that returns a constant fee
 #[derive(Clone, Debug)]
 pub(crate) struct ConstantOracle {
@@ -436,6 +490,34 @@ mod tests {
         assert_eq!(fee, 200);
     }
 
+    #[tokio::test]
+    async fn test_get_fee_oracle_fee_history() {
+        let mut mock = MockEvmProvider::default();
+        mock.expect_fee_history()
+            .times(1)
+            .returning(|_: u64, _, _| {
+                Ok(FeeHistory {
+                    base_fee_per_gas: vec![],
+                    gas_used_ratio: vec![],
+                    oldest_block: 0,
+                    reward: Some(vec![vec![100], vec![200], vec![300]]),
+                    ..Default::default()
+                })
+            });
+
+        let chain_spec = ChainSpec {
+            priority_fee_oracle_type: PriorityFeeOracleType::FeeHistory,
+            fee_history_block_count: 3,
+            fee_history_reward_percentile: 25.0,
+            min_max_priority_fee_per_gas: 250,
+            ..Default::default()
+        };
+        let oracle = get_fee_oracle(&chain_spec, mock);
+
+        let fee = oracle.estimate_priority_fee().await.unwrap();
+        assert_eq!(fee, 250);
+    }
+
     #[tokio::test]
     async fn test_max_oracle() {
         let mut oracle = MaxOracle::new();
@@ -526,6 +608,53 @@ mod tests {
         assert_eq!(fee, 200);
     }
 
+    #[tokio::test]
+    async fn test_get_fee_oracle_provider_with_fallback() {
+        let mut mock = MockEvmProvider::default();
+        mock.expect_get_max_priority_fee()
+            .times(1)
+            .returning(|| Ok(400));
+        mock.expect_fee_history().never();
+
+        let chain_spec = ChainSpec {
+            priority_fee_oracle_type: PriorityFeeOracleType::ProviderWithFeeHistoryFallback,
+            ..Default::default()
+        };
+        let oracle = get_fee_oracle(&chain_spec, mock);
+
+        let fee = oracle.estimate_priority_fee().await.unwrap();
+        assert_eq!(fee, 400);
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_oracle_provider_unsupported_falls_back() {
+        let mut mock = MockEvmProvider::default();
+        mock.expect_get_max_priority_fee()
+            .times(1)
+            .returning(|| Err(anyhow::anyhow!("method not supported").into()));
+        mock.expect_fee_history()
+            .times(1)
+            .returning(|_: u64, _, _| {
+                Ok(FeeHistory {
+                    base_fee_per_gas: vec![],
+                    gas_used_ratio: vec![],
+                    oldest_block: 0,
+                    reward: Some(vec![vec![100], vec![200], vec![300]]),
+                    ..Default::default()
+                })
+            });
+
+        let chain_spec = ChainSpec {
+            priority_fee_oracle_type: PriorityFeeOracleType::ProviderWithFeeHistoryFallback,
+            fee_history_block_count: 3,
+            ..Default::default()
+        };
+        let oracle = get_fee_oracle(&chain_spec, mock);
+
+        let fee = oracle.estimate_priority_fee().await.unwrap();
+        assert_eq!(fee, 200);
+    }
+
     #[test]
     fn test_calculate_estimate_from_rewards_small() {
         let reward = vec![vec![300], vec![100], vec![200]];
@@ -24,18 +24,22 @@
 mod alloy;
 pub use alloy::{
     entry_point::{
+        decode::{
+            decode_v0_6_execution_result, decode_v0_6_validation_result,
+            decode_v0_7_execution_result, decode_v0_7_validation_result,
+            decode_v0_7_validation_revert,
+        },
         v0_6::{
             decode_ops_from_calldata as decode_v0_6_ops_from_calldata,
             EntryPointProvider as AlloyEntryPointV0_6,
         },
         v0_7::{
             decode_ops_from_calldata as decode_v0_7_ops_from_calldata,
-            decode_validation_revert as decode_v0_7_validation_revert,
             EntryPointProvider as AlloyEntryPointV0_7,
         },
     },
     evm::AlloyEvmProvider,
-    new_alloy_da_gas_oracle, new_alloy_evm_provider, new_alloy_provider,
+    new_alloy_da_gas_oracle, new_alloy_evm_provider, new_alloy_provider, new_alloy_ws_evm_provider,
 };
 mod fees;
 pub use alloy_network::{
@@ -43,7 +47,7 @@ pub use alloy_network::{
     AnyTxEnvelope, ReceiptResponse,
 };
 pub use alloy_serde::WithOtherFields;
-pub use fees::new_fee_estimator;
+pub use fees::{new_fee_estimator, FixedGasPriceSource};
 mod traits;
 // re-export alloy RPC types
 use std::marker::PhantomData;
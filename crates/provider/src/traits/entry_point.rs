@@ -18,6 +18,7 @@ use rundler_types::{
     EntryPointVersion, GasFees, Timestamp, UserOperation, UserOpsPerAggregator, ValidationOutput,
     ValidationRevert,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{BlockHashOrNumber, BlockId, ProviderResult, StateOverride, TransactionRequest};
 
@@ -40,7 +41,8 @@ pub enum AggregatorOut {
 }
 
 /// Result of an entry point handle ops call
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum HandleOpsOut {
     /// Call succeeded
     Success,
@@ -55,6 +57,31 @@ pub enum HandleOpsOut {
     Revert(Bytes),
 }
 
+/// Gas used by a simulated bundle
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BundleSimResult {
+    /// Gas used by the whole `handleOps` call
+    pub total_gas: U256,
+    /// Actual gas used by each op, in bundle order
+    pub per_op_gas: Vec<U256>,
+    /// Gas used by each phase of each op, in bundle order, attributed from the trace
+    pub gas_attribution: Vec<GasAttribution>,
+}
+
+/// Gas used by each phase of an op in a traced bundle, for diagnostics.
+///
+/// Only the gas of the calls the entry point makes for the op is attributed, the entry
+/// point's own overhead is not.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GasAttribution {
+    /// Gas used validating the op, including account deployment and paymaster validation
+    pub validation_gas: U256,
+    /// Gas used executing the op's call data
+    pub execution_gas: U256,
+    /// Gas used by the paymaster's `postOp`
+    pub post_op_gas: U256,
+}
+
 /// Deposit info for an address from the entry point contract
 #[derive(Clone, Debug, Default)]
 pub struct DepositInfo {
@@ -150,6 +177,19 @@ pub trait BundleHandler: Send + Sync {
         validation_only: bool,
     ) -> ProviderResult<HandleOpsOut>;
 
+    /// Simulate the entry point contract's `handleOps` function over the whole bundle
+    ///
+    /// Returns the gas used by the bundle and by each op, or the decoded revert if the
+    /// bundle reverts as a whole. The gas of each op's phases is attributed from the
+    /// trace only if `attribute_gas` is set.
+    async fn simulate_handle_ops(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator<Self::UO>>,
+        beneficiary: Address,
+        block_id: Option<BlockId>,
+        attribute_gas: bool,
+    ) -> ProviderResult<Result<BundleSimResult, HandleOpsOut>>;
+
     /// Construct the transaction to send a bundle of operations to the entry point contract
     fn get_send_bundle_transaction(
         &self,
@@ -210,10 +250,14 @@ pub trait SimulationProvider: Send + Sync {
     ) -> ProviderResult<(TransactionRequest, StateOverride)>;
 
     /// Call the entry point contract's `simulateValidation` function.
+    ///
+    /// If `timestamp_override` is set, the call is made with that block timestamp through
+    /// the `eth_call` block overrides. Otherwise the block's own timestamp is used.
     async fn simulate_validation(
         &self,
         user_op: Self::UO,
         block_id: Option<BlockId>,
+        timestamp_override: Option<u64>,
     ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>>;
 
     /// Call the entry point contract's `simulateHandleOp` function.
@@ -256,3 +300,22 @@ pub trait EntryPointProvider<UO>:
     + DAGasProvider<UO = UO>
 {
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_ops_out_serde_round_trip() {
+        for out in [
+            HandleOpsOut::Success,
+            HandleOpsOut::FailedOp(1, "AA25 invalid account nonce".to_string()),
+            HandleOpsOut::SignatureValidationFailed(Address::repeat_byte(1)),
+            HandleOpsOut::PostOpRevert,
+            HandleOpsOut::Revert(Bytes::from_static(&[1, 2, 3])),
+        ] {
+            let json = serde_json::to_string(&out).unwrap();
+            assert_eq!(serde_json::from_str::<HandleOpsOut>(&json).unwrap(), out);
+        }
+    }
+}
@@ -16,6 +16,9 @@ use alloy_primitives::Bytes;
 use alloy_sol_types::SolError;
 use alloy_transport::TransportError;
 
+// JSON-RPC error code for a method the node does not provide
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
 /// Error enumeration for the Provider trait
 #[derive(Debug, thiserror::Error)]
 pub enum ProviderError {
@@ -54,6 +57,17 @@ impl ProviderError {
         }
     }
 
+    /// Returns true if the node rejected the method as unsupported, i.e. a node without
+    /// the `debug` namespace asked to trace a call
+    pub fn is_method_not_supported(&self) -> bool {
+        match self {
+            ProviderError::RPC(TransportError::ErrorResp(e)) => {
+                e.code == METHOD_NOT_FOUND_CODE || e.message.contains("not supported")
+            }
+            _ => false,
+        }
+    }
+
     /// Attempt to decode a contract error from a provider error
     pub fn as_decoded_error<E: SolError>(&self) -> Option<E> {
         match self {
@@ -13,9 +13,10 @@
 
 //! Trait for interacting with chain data and contracts.
 
-use std::fmt::Display;
+use std::{fmt::Display, pin::Pin};
 
 use alloy_primitives::{Address, Bytes, TxHash, B256, U256};
+use futures_util::Stream;
 use rundler_types::ExpectedStorage;
 
 use crate::{
@@ -48,6 +49,9 @@ impl Display for EvmCall {
     }
 }
 
+/// Stream of the hashes of new blocks
+pub type BlockHashStream = Pin<Box<dyn Stream<Item = B256> + Send>>;
+
 /// Trait for interacting with chain data and contracts.
 #[async_trait::async_trait]
 #[auto_impl::auto_impl(&, &mut, Rc, Arc, Box)]
@@ -144,6 +148,16 @@ pub trait EvmProvider: Send + Sync {
     /// Measures the gas used by a call to target with value and data.
     async fn get_gas_used(&self, call: EvmCall) -> ProviderResult<GasUsedResult>;
 
+    /// Executes `eth_call`s against a block in a single JSON-RPC batch request,
+    /// returning their outputs in call order.
+    ///
+    /// Falls back to one request per call if the node doesn't support batch requests.
+    async fn batch_call(
+        &self,
+        calls: Vec<EvmCall>,
+        block: Option<BlockId>,
+    ) -> ProviderResult<Vec<Bytes>>;
+
     /// Get the storage values at a given address and slots
     async fn batch_get_storage_at(
         &self,
@@ -161,4 +175,10 @@ pub trait EvmProvider: Send + Sync {
 
     /// Get the balances of multiple addresses
     async fn get_balances(&self, addresses: Vec<Address>) -> ProviderResult<Vec<(Address, U256)>>;
+
+    /// Subscribe to new blocks, returning a stream of their hashes.
+    ///
+    /// Returns `None` if the provider does not support subscriptions, i.e. it is
+    /// connected over HTTP. The stream ends if the subscription is dropped.
+    async fn subscribe_blocks(&self) -> ProviderResult<Option<BlockHashStream>>;
 }
@@ -28,10 +28,11 @@ use rundler_types::{
 
 use super::error::ProviderResult;
 use crate::{
-    AggregatorOut, Block, BlockHashOrNumber, BundleHandler, DAGasOracle, DAGasOracleSync,
-    DAGasProvider, DepositInfo, EntryPoint, EntryPointProvider, EvmCall,
-    EvmProvider as EvmProviderTrait, ExecutionResult, FeeEstimator, HandleOpsOut, RpcRecv, RpcSend,
-    SignatureAggregator, SimulationProvider, Transaction, TransactionReceipt, TransactionRequest,
+    AggregatorOut, Block, BlockHashOrNumber, BlockHashStream, BundleHandler, BundleSimResult,
+    DAGasOracle, DAGasOracleSync, DAGasProvider, DepositInfo, EntryPoint, EntryPointProvider,
+    EvmCall, EvmProvider as EvmProviderTrait, ExecutionResult, FeeEstimator, HandleOpsOut, RpcRecv,
+    RpcSend, SignatureAggregator, SimulationProvider, Transaction, TransactionReceipt,
+    TransactionRequest,
 };
 
 mockall::mock! {
@@ -110,6 +111,12 @@ mockall::mock! {
 
         async fn get_gas_used(&self, call: EvmCall) -> ProviderResult<GasUsedResult>;
 
+        async fn batch_call(
+            &self,
+            calls: Vec<EvmCall>,
+            block: Option<BlockId>,
+        ) -> ProviderResult<Vec<Bytes>>;
+
         async fn batch_get_storage_at(
             &self,
             address: Address,
@@ -126,6 +133,8 @@ mockall::mock! {
             &self,
             addresses: Vec<Address>,
         ) -> ProviderResult<Vec<(Address, U256)>>;
+
+        async fn subscribe_blocks(&self) -> ProviderResult<Option<BlockHashStream>>;
     }
 }
 
@@ -167,7 +176,8 @@ mockall::mock! {
         async fn simulate_validation(
             &self,
             user_op: v0_6::UserOperation,
-            block_id: Option<BlockId>
+            block_id: Option<BlockId>,
+            timestamp_override: Option<u64>,
         ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>>;
         async fn simulate_handle_op(
             &self,
@@ -215,6 +225,13 @@ mockall::mock! {
             proxy: Option<Address>,
             validation_only: bool,
         ) -> ProviderResult<HandleOpsOut>;
+        async fn simulate_handle_ops(
+            &self,
+            ops_per_aggregator: Vec<UserOpsPerAggregator<v0_6::UserOperation>>,
+            beneficiary: Address,
+            block_id: Option<BlockId>,
+            attribute_gas: bool,
+        ) -> ProviderResult<Result<BundleSimResult, HandleOpsOut>>;
         fn get_send_bundle_transaction(
             &self,
             ops_per_aggregator: Vec<UserOpsPerAggregator<v0_6::UserOperation>>,
@@ -271,7 +288,8 @@ mockall::mock! {
         async fn simulate_validation(
             &self,
             user_op: v0_7::UserOperation,
-            block_id: Option<BlockId>
+            block_id: Option<BlockId>,
+            timestamp_override: Option<u64>,
         ) -> ProviderResult<Result<ValidationOutput, ValidationRevert>>;
         async fn simulate_handle_op(
             &self,
@@ -319,6 +337,13 @@ mockall::mock! {
             proxy: Option<Address>,
             validation_only: bool,
         ) -> ProviderResult<HandleOpsOut>;
+        async fn simulate_handle_ops(
+            &self,
+            ops_per_aggregator: Vec<UserOpsPerAggregator<v0_7::UserOperation>>,
+            beneficiary: Address,
+            block_id: Option<BlockId>,
+            attribute_gas: bool,
+        ) -> ProviderResult<Result<BundleSimResult, HandleOpsOut>>;
         fn get_send_bundle_transaction(
             &self,
             ops_per_aggregator: Vec<UserOpsPerAggregator<v0_7::UserOperation>>,
@@ -87,6 +87,10 @@ pub trait DebugApi {
     /// Clear the reputations of pool.
     #[method(name = "bundler_clearReputation")]
     async fn bundler_clear_reputation(&self) -> RpcResult<String>;
+
+    /// Cancels the pending bundle containing the given user operation, if any.
+    #[method(name = "bundler_cancelBundleContaining")]
+    async fn bundler_cancel_bundle_containing(&self, op_hash: B256) -> RpcResult<String>;
 }
 
 pub(crate) struct DebugApi<P, B> {
@@ -196,6 +200,14 @@ where
         )
         .await
     }
+
+    async fn bundler_cancel_bundle_containing(&self, op_hash: B256) -> RpcResult<String> {
+        utils::safe_call_rpc_handler(
+            "bundler_cancelBundleContaining",
+            DebugApi::bundler_cancel_bundle_containing(self, op_hash),
+        )
+        .await
+    }
 }
 
 impl<P, B> DebugApi<P, B>
@@ -282,6 +294,17 @@ where
         Ok("ok".to_string())
     }
 
+    async fn bundler_cancel_bundle_containing(&self, op_hash: B256) -> InternalRpcResult<String> {
+        tracing::debug!("Cancelling pending bundle containing op {:?}", op_hash);
+
+        self.builder
+            .debug_cancel_bundle_containing(op_hash)
+            .await
+            .context("should cancel bundle")?;
+
+        Ok("ok".to_string())
+    }
+
     async fn bundler_set_reputation(
         &self,
         reputations: Vec<RpcReputationInput>,
@@ -349,6 +349,7 @@ impl From<SimulationViolation> for EthRpcError {
             }
             SimulationViolation::UsedForbiddenPrecompile(_, _, _)
             | SimulationViolation::AccessedUndeployedContract(_, _)
+            | SimulationViolation::UndeployedExtCodeAccess(_, _, _)
             | SimulationViolation::AccessedUnsupportedContractType(_, _)
             | SimulationViolation::CalledBannedEntryPointMethod(_)
             | SimulationViolation::CallHadValue(_) => Self::OpcodeViolationMap(value),
@@ -359,7 +360,8 @@ impl From<SimulationViolation> for EthRpcError {
             SimulationViolation::AssociatedStorageDuringDeploy(e, s) => {
                 Self::AssociatedStorageDuringDeploy(e.map(|e| e.kind), s.address, s.slot)
             }
-            SimulationViolation::InvalidStorageAccess(entity, slot) => {
+            SimulationViolation::InvalidStorageAccess(entity, slot)
+            | SimulationViolation::CrossSenderAssociatedStorage(entity, _, slot) => {
                 Self::InvalidStorageAccess(entity.kind, slot.address, slot.slot)
             }
             SimulationViolation::NotStaked(stake_data) => {
@@ -376,6 +378,15 @@ impl From<SimulationViolation> for EthRpcError {
             SimulationViolation::AggregatorMismatch(e, a) => Self::AggregatorMismatch(e, a),
             SimulationViolation::OutOfGas(entity) => Self::OutOfGas(entity),
             SimulationViolation::ValidationRevert(revert) => Self::ValidationRevert(revert.into()),
+            // Report the most important violation so the error code matches the spec
+            SimulationViolation::UnsupportedByAllMempools { violations } => {
+                match violations.into_iter().min() {
+                    Some(violation) => violation.into(),
+                    None => Self::SimulationFailed(SimulationViolation::UnsupportedByAllMempools {
+                        violations: vec![],
+                    }),
+                }
+            }
             _ => Self::SimulationFailed(value),
         }
     }
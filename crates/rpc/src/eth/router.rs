@@ -379,7 +379,7 @@ where
     async fn check_signature(&self, uo: UserOperationVariant) -> anyhow::Result<bool> {
         let output = self
             .entry_point
-            .simulate_validation(uo.into(), None)
+            .simulate_validation(uo.into(), None, None)
             .await??;
 
         Ok(!output.return_info.account_sig_failed)
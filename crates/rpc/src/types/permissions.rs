@@ -37,6 +37,12 @@ pub(crate) struct RpcUserOperationPermissions {
     /// Bundler sponsorship settings
     #[serde(default)]
     pub(crate) bundler_sponsorship: Option<RpcBundlerSponsorship>,
+    /// Client supplied correlation tag, echoed back when the operation is mined
+    #[serde(default)]
+    pub(crate) client_tag: Option<String>,
+    /// Origin of the submission, reported in builder events
+    #[serde(default)]
+    pub(crate) submission_source: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -58,6 +64,8 @@ impl FromWithSpec<RpcUserOperationPermissions> for UserOperationPermissions {
             bundler_sponsorship: rpc
                 .bundler_sponsorship
                 .map(|c| c.into_with_spec(chain_spec)),
+            client_tag: rpc.client_tag,
+            submission_source: rpc.submission_source,
         }
     }
 }
@@ -19,14 +19,18 @@
 ))]
 //! Signer implementations for Rundler
 
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use alloy_network::{EthereumWallet, TxSigner};
 use anyhow::Context;
 use rundler_provider::{DAGasOracle, EvmProvider};
 use rundler_task::TaskSpawner;
 use rundler_types::chain::ChainSpec;
-use secrecy::SecretString;
+use secrecy::{ExposeSecret, SecretString};
 
 mod aws;
 use aws::LockingKmsSigner;
@@ -44,6 +48,9 @@ mod manager;
 use manager::FundingSignerManager;
 pub use manager::{SignerLease, SignerManager};
 
+mod retry;
+pub use retry::SigningRetrySettings;
+
 pub mod utils;
 
 /// Settings for locking KMS keys
@@ -122,6 +129,31 @@ impl SigningScheme {
     pub fn supports_funding(&self) -> bool {
         matches!(self, SigningScheme::KmsFunding { .. })
     }
+
+    /// Returns the number of distinct keys available to sign with, the most builders
+    /// the scheme can back at once
+    pub fn num_keys(&self) -> usize {
+        match self {
+            SigningScheme::PrivateKeys { private_keys } => private_keys
+                .iter()
+                .map(|key| key.expose_secret())
+                .collect::<HashSet<_>>()
+                .len(),
+            SigningScheme::Mnemonic { num_keys, .. } => *num_keys,
+            SigningScheme::AwsKmsLocking {
+                key_ids, to_lock, ..
+            } => key_ids.iter().collect::<HashSet<_>>().len().min(*to_lock),
+            SigningScheme::AwsKms { key_ids } => key_ids.iter().collect::<HashSet<_>>().len(),
+            // only the subkeys of the one funding key in use are available
+            SigningScheme::KmsFunding {
+                subkeys_by_key_id, ..
+            } => subkeys_by_key_id
+                .values()
+                .map(SigningScheme::num_keys)
+                .min()
+                .unwrap_or(0),
+        }
+    }
 }
 
 /// Create a new signer manager
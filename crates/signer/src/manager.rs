@@ -32,6 +32,7 @@ use tokio::sync::Notify;
 
 use crate::{
     funding::{self, FunderSettings},
+    retry::{RetryingSigner, SigningRetrySettings},
     utils, Error, Result,
 };
 
@@ -94,6 +95,17 @@ impl SignerLease {
         self.signer.address()
     }
 
+    /// Retry transient signing errors, such as KMS throttling, according to `settings`
+    pub fn with_retries(self, settings: SigningRetrySettings) -> Self {
+        if settings.max_retries == 0 {
+            return self;
+        }
+        Self {
+            signer: Arc::new(RetryingSigner::new(self.signer, settings)),
+            chain_id: self.chain_id,
+        }
+    }
+
     /// Sign a transaction
     pub async fn sign_tx(&self, mut tx: TransactionRequest) -> Result<AnyTxEnvelope> {
         tx.set_chain_id(self.chain_id);
@@ -0,0 +1,184 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{sync::Arc, time::Duration};
+
+use alloy_consensus::SignableTransaction;
+use alloy_network::TxSigner;
+use alloy_primitives::{Address, Signature};
+
+/// Upper bound on the wait between signing attempts
+const MAX_SIGNING_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
+// Substrings of errors from remote signers, such as KMS, that are worth retrying
+const TRANSIENT_ERROR_MARKERS: &[&str] = &[
+    "throttl",
+    "timeout",
+    "timed out",
+    "too many requests",
+    "service unavailable",
+    "serviceunavailable",
+    "internalexception",
+    "dispatch failure",
+    "connection",
+];
+
+/// Settings for retrying failed signing calls
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SigningRetrySettings {
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u64,
+    /// Wait before the first retry, doubled for each retry after it
+    pub initial_backoff: Duration,
+}
+
+/// Signer that retries transient signing errors with a bounded exponential backoff.
+///
+/// Permanent errors, such as access denied, are returned immediately.
+pub(crate) struct RetryingSigner {
+    inner: Arc<dyn TxSigner<Signature> + Send + Sync + 'static>,
+    settings: SigningRetrySettings,
+}
+
+impl RetryingSigner {
+    pub(crate) fn new(
+        inner: Arc<dyn TxSigner<Signature> + Send + Sync + 'static>,
+        settings: SigningRetrySettings,
+    ) -> Self {
+        Self { inner, settings }
+    }
+}
+
+#[async_trait::async_trait]
+impl TxSigner<Signature> for RetryingSigner {
+    fn address(&self) -> Address {
+        self.inner.address()
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy_signer::Result<Signature> {
+        let mut backoff = self.settings.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match self.inner.sign_transaction(tx).await {
+                Ok(sig) => return Ok(sig),
+                Err(e) if retries < self.settings.max_retries && is_transient(&e) => {
+                    retries += 1;
+                    tracing::warn!(
+                        "Transient error signing with {}, retry {retries} of {} in {backoff:?}: {e}",
+                        self.inner.address(),
+                        self.settings.max_retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_SIGNING_RETRY_BACKOFF);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+fn is_transient(error: &alloy_signer::Error) -> bool {
+    let message = error.to_string().to_lowercase();
+    TRANSIENT_ERROR_MARKERS
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use alloy_consensus::TxLegacy;
+
+    use super::*;
+
+    struct FailingSigner {
+        failures: u64,
+        error: &'static str,
+        calls: AtomicU64,
+    }
+
+    impl FailingSigner {
+        fn new(failures: u64, error: &'static str) -> Self {
+            Self {
+                failures,
+                error,
+                calls: AtomicU64::new(0),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TxSigner<Signature> for FailingSigner {
+        fn address(&self) -> Address {
+            Address::ZERO
+        }
+
+        async fn sign_transaction(
+            &self,
+            _tx: &mut dyn SignableTransaction<Signature>,
+        ) -> alloy_signer::Result<Signature> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                Err(alloy_signer::Error::other(self.error))
+            } else {
+                Ok(Signature::test_signature())
+            }
+        }
+    }
+
+    fn retrying(inner: Arc<FailingSigner>, max_retries: u64) -> RetryingSigner {
+        RetryingSigner::new(
+            inner,
+            SigningRetrySettings {
+                max_retries,
+                initial_backoff: Duration::from_millis(1),
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let inner = Arc::new(FailingSigner::new(2, "ThrottlingException: Rate exceeded"));
+        let signer = retrying(inner.clone(), 3);
+
+        let sig = signer.sign_transaction(&mut TxLegacy::default()).await;
+        assert_eq!(sig.unwrap(), Signature::test_signature());
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let inner = Arc::new(FailingSigner::new(2, "request timed out"));
+        let signer = retrying(inner.clone(), 1);
+
+        let sig = signer.sign_transaction(&mut TxLegacy::default()).await;
+        assert!(sig.is_err());
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_fails_fast_on_permanent_errors() {
+        let inner = Arc::new(FailingSigner::new(
+            2,
+            "AccessDeniedException: not authorized to perform kms:Sign",
+        ));
+        let signer = retrying(inner.clone(), 3);
+
+        let sig = signer.sign_transaction(&mut TxLegacy::default()).await;
+        assert!(sig.is_err());
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -119,6 +119,12 @@ pub struct Settings {
     pub call_gas_allowed_error_pct: u128,
     /// The maximum number of rounds to run for gas estimation
     pub max_gas_estimation_rounds: u32,
+    /// Multiplier applied to the measured verification gas of operations that use a
+    /// paymaster. For entry point v0.7 this applies to the paymaster verification gas.
+    pub verification_gas_multiplier_with_paymaster: f64,
+    /// Multiplier applied to the measured verification gas of operations that deploy
+    /// their account with a factory
+    pub verification_gas_multiplier_with_factory: f64,
 }
 
 impl Settings {
@@ -130,8 +136,34 @@ impl Settings {
                     .to_string(),
             );
         }
+        if self.verification_gas_multiplier_with_paymaster < 1.0
+            || self.verification_gas_multiplier_with_factory < 1.0
+        {
+            return Some("verification gas multipliers cannot be lower than 1.0".to_string());
+        }
         None
     }
+
+    /// Applies the verification gas multipliers for the entities an operation uses to
+    /// its measured verification gas.
+    ///
+    /// Multipliers are advisory: they only raise the limits suggested by estimation and
+    /// are never applied when validating or simulating operations.
+    pub fn apply_verification_gas_multipliers(
+        &self,
+        gas: u128,
+        has_factory: bool,
+        has_paymaster: bool,
+    ) -> u128 {
+        let mut multiplier = 1.0;
+        if has_factory {
+            multiplier *= self.verification_gas_multiplier_with_factory;
+        }
+        if has_paymaster {
+            multiplier *= self.verification_gas_multiplier_with_paymaster;
+        }
+        (gas as f64 * multiplier).ceil() as u128
+    }
 }
 
 #[derive(Metrics)]
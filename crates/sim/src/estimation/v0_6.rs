@@ -318,6 +318,11 @@ where
             .verification_gas_estimator
             .estimate_verification_gas(full_op, block_hash, state_override)
             .await?;
+        let verification_gas_limit = self.settings.apply_verification_gas_multipliers(
+            verification_gas_limit,
+            full_op.factory().is_some(),
+            full_op.paymaster().is_some(),
+        );
 
         // Add a buffer to the verification gas limit. Add 10% or 2000 gas, whichever is larger
         // to ensure we get at least a 2000 gas buffer. Cap at the max verification gas.
@@ -505,13 +510,13 @@ where
     }
 
     fn decode_revert(&self, revert_data: &Bytes) -> GasEstimationError {
-        match EP::decode_simulate_handle_ops_revert(revert_data) {
+        match rundler_provider::decode_v0_6_execution_result(revert_data) {
             Ok(Ok(res)) => GasEstimationError::Other(anyhow::anyhow!(
                 "unexpected result from simulate_handle_ops: {:?}",
                 res
             )),
             Ok(Err(e)) => GasEstimationError::RevertInValidation(e),
-            Err(e) => GasEstimationError::ProviderError(e),
+            Err(e) => GasEstimationError::Other(e),
         }
     }
 }
@@ -639,6 +644,8 @@ mod tests {
             verification_gas_allowed_error_pct: 15,
             call_gas_allowed_error_pct: 15,
             max_gas_estimation_rounds: 3,
+            verification_gas_multiplier_with_paymaster: 1.0,
+            verification_gas_multiplier_with_factory: 1.0,
         };
         let estimator = create_custom_estimator(
             ChainSpec::default(),
@@ -1129,6 +1136,97 @@ mod tests {
         assert_eq!(estimation.call_gas_limit, 10000 + CALL_GAS_BUFFER_VALUE);
     }
 
+    #[tokio::test]
+    async fn test_estimation_verification_gas_multiplier_with_factory() {
+        let (mut entry, mut provider) = create_base_config();
+        let gas_usage = 10_000;
+
+        entry
+            .expect_simulate_handle_op_estimate_gas()
+            .returning(move |_op, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateGasResult {
+                        gas: U256::from(10000),
+                        numRounds: U256::from(10),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        add_verification_gas_result(
+            &mut provider,
+            EstimationTypesErrors::EstimateGasResult(EstimateGasResult {
+                gas: U256::from(gas_usage),
+                numRounds: U256::from(10),
+            }),
+        );
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+        provider.expect_get_gas_used().returning(move |_a| {
+            Ok(GasUsedResult {
+                gasUsed: U256::from(gas_usage),
+                success: false,
+                result: Bytes::new(),
+            })
+        });
+
+        provider
+            .expect_get_pending_base_fee()
+            .returning(|| Ok(TEST_FEE));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(TEST_FEE));
+
+        let settings = Settings {
+            max_verification_gas: TEST_MAX_GAS_LIMITS,
+            max_bundle_execution_gas: TEST_MAX_GAS_LIMITS,
+            max_gas_estimation_gas: TEST_MAX_GAS_LIMITS.try_into().unwrap(),
+            max_paymaster_verification_gas: TEST_MAX_GAS_LIMITS,
+            max_paymaster_post_op_gas: TEST_MAX_GAS_LIMITS,
+            verification_estimation_gas_fee: 1_000_000_000_000,
+            verification_gas_limit_efficiency_reject_threshold: 0.5,
+            verification_gas_allowed_error_pct: 15,
+            call_gas_allowed_error_pct: 15,
+            max_gas_estimation_rounds: 3,
+            // not applied, the op doesn't use a paymaster
+            verification_gas_multiplier_with_paymaster: 3.0,
+            verification_gas_multiplier_with_factory: 2.0,
+        };
+        let estimator = create_custom_estimator(
+            ChainSpec::default(),
+            provider,
+            MockFeeEstimator::new(),
+            entry,
+            settings,
+        );
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.init_code = Address::repeat_byte(1).to_vec().into();
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op, StateOverride::default())
+            .await
+            .unwrap();
+
+        // measured gas doubled by the factory multiplier, then increased by 10%
+        let expected = 2 * (gas_usage + ChainSpec::default().deposit_transfer_overhead as u128);
+        assert_eq!(
+            estimation.verification_gas_limit,
+            cmp::max(
+                math::increase_by_percent(expected, 10),
+                expected + REQUIRED_VERIFICATION_GAS_LIMIT_BUFFER
+            )
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_estimation_optional_gas_invalid_settings() {
@@ -1147,6 +1245,8 @@ mod tests {
             verification_gas_allowed_error_pct: 15,
             call_gas_allowed_error_pct: 15,
             max_gas_estimation_rounds: 3,
+            verification_gas_multiplier_with_paymaster: 1.0,
+            verification_gas_multiplier_with_factory: 1.0,
         };
 
         create_custom_estimator(
@@ -190,7 +190,8 @@ where
             }
         };
 
-        // check the total gas limit
+        // check the total gas limit. The verification and call gas limits are estimated
+        // independently, then re-packed into `accountGasLimits` here.
         let op_with_gas = UserOperationBuilder::from_uo(full_op, &self.chain_spec)
             .pre_verification_gas(pre_verification_gas)
             .call_gas_limit(call_gas_limit)
@@ -226,6 +227,10 @@ where
             )
         };
 
+        // return the limits as packed into the op checked above
+        let (verification_gas_limit, call_gas_limit) =
+            UserOperation::unpack_account_gas_limits(op_with_gas.account_gas_limits());
+
         Ok(GasEstimate {
             pre_verification_gas,
             call_gas_limit,
@@ -378,6 +383,12 @@ where
             .verification_gas_estimator
             .estimate_verification_gas(full_op, block_hash, state_override)
             .await?;
+        // the paymaster multiplier is applied to the paymaster verification gas
+        let verification_gas_limit = self.settings.apply_verification_gas_multipliers(
+            verification_gas_limit,
+            full_op.factory().is_some(),
+            false,
+        );
 
         let verification_gas_limit = math::increase_by_percent(
             verification_gas_limit,
@@ -420,6 +431,11 @@ where
             .paymaster_verification_gas_estimator
             .estimate_verification_gas(full_op, block_hash, state_override)
             .await?;
+        let paymaster_verification_gas_limit = self.settings.apply_verification_gas_multipliers(
+            paymaster_verification_gas_limit,
+            false,
+            true,
+        );
 
         let paymaster_verification_gas_limit = math::increase_by_percent(
             paymaster_verification_gas_limit,
@@ -601,17 +617,6 @@ fn add_proxy_to_overrides(
     );
 }
 
-fn decode_validation_revert<EP: SimulationProvider>(revert_data: &Bytes) -> GasEstimationError {
-    match EP::decode_simulate_handle_ops_revert(revert_data) {
-        Ok(Ok(res)) => GasEstimationError::Other(anyhow::anyhow!(
-            "unexpected result from simulate_handle_ops: {:?}",
-            res
-        )),
-        Ok(Err(e)) => GasEstimationError::RevertInValidation(e),
-        Err(e) => GasEstimationError::ProviderError(e),
-    }
-}
-
 /// Specialization for verification gas estimation
 #[derive(Clone)]
 pub struct VerificationGasEstimatorSpecializationV07<EP> {
@@ -637,7 +642,9 @@ where
     }
 
     fn decode_revert(&self, revert_data: &Bytes) -> GasEstimationError {
-        decode_validation_revert::<EP>(revert_data)
+        GasEstimationError::RevertInValidation(rundler_provider::decode_v0_7_validation_revert(
+            revert_data,
+        ))
     }
 }
 
@@ -667,7 +674,9 @@ where
     }
 
     fn decode_revert(&self, revert_data: &Bytes) -> GasEstimationError {
-        decode_validation_revert::<EP>(revert_data)
+        GasEstimationError::RevertInValidation(rundler_provider::decode_v0_7_validation_revert(
+            revert_data,
+        ))
     }
 }
 
@@ -675,7 +684,7 @@ where
 mod tests {
     use std::sync::Arc;
 
-    use alloy_primitives::{hex, U256};
+    use alloy_primitives::{b256, hex, U256};
     use alloy_sol_types::{Revert, SolError};
     use rundler_contracts::common::EstimationTypes::TestCallGasResult;
     use rundler_provider::{
@@ -755,6 +764,8 @@ mod tests {
             verification_gas_allowed_error_pct: 15,
             call_gas_allowed_error_pct: 15,
             max_gas_estimation_rounds: 3,
+            verification_gas_multiplier_with_paymaster: 1.0,
+            verification_gas_multiplier_with_factory: 1.0,
         };
         let estimator = create_custom_estimator(ChainSpec::default(), provider, entry, settings);
         (estimator, settings)
@@ -923,6 +934,65 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_estimate_packs_account_gas_limits() {
+        let (mut entry, mut provider) = create_base_config();
+
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+
+        entry
+            .expect_simulate_handle_op_estimate_gas()
+            .returning(move |_a, _b, _c, _d, _e| {
+                Ok(Ok(ExecutionResult {
+                    target_result: TestCallGasResult {
+                        success: true,
+                        gasUsed: U256::ZERO,
+                        revertData: Bytes::new(),
+                    }
+                    .abi_encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let mut optional_op = demo_user_op_optional_gas(Some(10000));
+        optional_op.verification_gas_limit = Some(0x114fc);
+        optional_op.call_gas_limit = Some(0x12c9b5);
+
+        let estimation = estimator
+            .estimate_op_gas(optional_op.clone(), StateOverride::default())
+            .await
+            .unwrap();
+
+        // each limit takes its own half of `accountGasLimits`
+        let account_gas_limits = UserOperation::pack_account_gas_limits(
+            estimation.verification_gas_limit,
+            estimation.call_gas_limit,
+        );
+        assert_eq!(
+            account_gas_limits,
+            b256!("000000000000000000000000000114fc0000000000000000000000000012c9b5")
+        );
+
+        // an op built from the estimate packs to the same layout
+        let op = optional_op
+            .into_user_operation_builder(
+                &ChainSpec::default(),
+                TEST_MAX_GAS_LIMITS,
+                TEST_MAX_GAS_LIMITS,
+                TEST_MAX_GAS_LIMITS,
+            )
+            .verification_gas_limit(estimation.verification_gas_limit)
+            .call_gas_limit(estimation.call_gas_limit)
+            .build();
+        assert_eq!(op.pack().accountGasLimits, account_gas_limits);
+    }
+
     #[tokio::test]
     async fn test_provided_reverts() {
         let (mut entry, mut provider) = create_base_config();
@@ -55,9 +55,18 @@ pub use precheck::{
 pub mod simulation;
 #[cfg(feature = "test-utils")]
 pub use simulation::MockSimulator;
+#[cfg(any(test, feature = "test-utils"))]
 pub use simulation::{
-    MempoolConfig, MempoolConfigs, Settings as SimulationSettings, SimulationError,
-    SimulationResult, Simulator,
+    diff_results, simulate_and_diff, FaultInjectingSimulator, SimulationDiff, SimulationFault,
+    VersionDiff,
+};
+#[cfg(feature = "test-utils")]
+pub use simulation::{fork_simulator, ForkSimulator};
+pub use simulation::{
+    included_entities, simulate_at_block_number, stake_shortfalls, BlockTag, EntityStakeShortfall,
+    FactorySimResult, GasBreakdown, MempoolConfig, MempoolConfigs, MempoolEvaluation,
+    MempoolMetrics, Settings as SimulationSettings, SimulationError, SimulationObserver,
+    SimulationResult, SimulationSummary, Simulator,
 };
 
 mod types;
@@ -94,6 +94,8 @@ pub struct PrecheckerImpl<UO, P, E, F> {
 pub struct Settings {
     /// Maximum verification gas allowed for a user operation
     pub max_verification_gas: u128,
+    /// Maximum call gas limit allowed for a user operation
+    pub max_call_gas_limit: u128,
     /// Maximum bundle execution gas allowed for a user operation
     pub max_bundle_execution_gas: u128,
     /// Maximum cost of a single user operation
@@ -119,6 +121,7 @@ impl Default for Settings {
     fn default() -> Self {
         Self {
             max_verification_gas: 5_000_000,
+            max_call_gas_limit: 30_000_000,
             max_bundle_execution_gas: 10_000_000,
             max_uo_cost: U256::MAX,
             bundle_priority_fee_overhead_percent: 0,
@@ -239,10 +242,19 @@ where
     ) -> ArrayVec<PrecheckViolation, 6> {
         let Settings {
             max_verification_gas,
+            max_call_gas_limit,
             max_bundle_execution_gas,
             max_uo_cost,
             ..
         } = self.settings;
+        let max_verification_gas = self
+            .chain_spec
+            .max_verification_gas
+            .map_or(max_verification_gas, u128::from);
+        let max_call_gas_limit = self
+            .chain_spec
+            .max_call_gas_limit
+            .map_or(max_call_gas_limit, u128::from);
         let AsyncData {
             base_fee,
             mut min_pre_verification_gas,
@@ -271,7 +283,12 @@ where
                 max_bundle_execution_gas,
             ))
         }
-        if op.call_gas_limit() < MIN_CALL_GAS_LIMIT {
+        if op.call_gas_limit() > max_call_gas_limit {
+            violations.push(PrecheckViolation::CallGasLimitTooHigh(
+                op.call_gas_limit(),
+                max_call_gas_limit,
+            ));
+        } else if op.call_gas_limit() < MIN_CALL_GAS_LIMIT {
             violations.push(PrecheckViolation::CallGasLimitTooLow(
                 op.call_gas_limit(),
                 MIN_CALL_GAS_LIMIT,
@@ -561,6 +578,7 @@ mod tests {
     async fn test_check_gas() {
         let test_settings = Settings {
             max_verification_gas: 5_000_000,
+            max_call_gas_limit: 30_000_000,
             max_bundle_execution_gas: 10_000_000,
             max_uo_cost: U256::MAX,
             bundle_priority_fee_overhead_percent: 0,
@@ -619,6 +637,82 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_check_gas_chain_spec_max_verification_gas() {
+        let (_, provider, entry_point, fee_estimator) = create_base_config();
+        let cs = ChainSpec {
+            max_verification_gas: Some(20_000_000),
+            ..Default::default()
+        };
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+
+        // above the settings default of 5_000_000
+        let op = UserOperationBuilder::new(
+            &cs,
+            UserOperationRequiredFields {
+                verification_gas_limit: 10_000_000,
+                ..Default::default()
+            },
+        )
+        .build();
+
+        let res = prechecker.check_gas(
+            &op,
+            &get_test_async_data(),
+            &UserOperationPermissions::default(),
+        );
+        assert!(!res
+            .iter()
+            .any(|v| matches!(v, PrecheckViolation::VerificationGasLimitTooHigh(..))));
+    }
+
+    #[tokio::test]
+    async fn test_check_gas_max_call_gas_limit() {
+        let (_, provider, entry_point, fee_estimator) = create_base_config();
+        let cs = ChainSpec {
+            max_call_gas_limit: Some(1_000_000),
+            ..Default::default()
+        };
+        let prechecker = PrecheckerImpl::new(
+            cs.clone(),
+            Arc::new(provider),
+            entry_point,
+            fee_estimator,
+            Settings::default(),
+        );
+        let check = |call_gas_limit| {
+            let op = UserOperationBuilder::new(
+                &cs,
+                UserOperationRequiredFields {
+                    call_gas_limit,
+                    ..Default::default()
+                },
+            )
+            .build();
+            prechecker
+                .check_gas(
+                    &op,
+                    &get_test_async_data(),
+                    &UserOperationPermissions::default(),
+                )
+                .into_iter()
+                .find(|v| matches!(v, PrecheckViolation::CallGasLimitTooHigh(..)))
+        };
+
+        // the chain spec limit overrides the settings default
+        assert_eq!(check(1_000_000), None);
+        assert_eq!(
+            check(1_000_001),
+            Some(PrecheckViolation::CallGasLimitTooHigh(1_000_001, 1_000_000))
+        );
+    }
+
     #[tokio::test]
     async fn test_check_payer_paymaster_deposit_too_low() {
         let (cs, provider, entry_point, fee_estimator) = create_base_config();
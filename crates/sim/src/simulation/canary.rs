@@ -0,0 +1,259 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::{Bytes, B256};
+use metrics::Counter;
+use metrics_derive::Metrics;
+use rundler_types::UserOperation;
+use rundler_utils::math;
+use tracing::warn;
+
+use super::{
+    BlockTag, FactorySimResult, MempoolEvaluation, SimulationError, SimulationResult, Simulator,
+};
+use crate::ViolationError;
+
+/// Canary simulation settings
+#[derive(Clone, Copy, Debug)]
+pub struct CanarySettings {
+    /// Percentage that the pre-op gas reported by the canary simulation may differ
+    /// from the primary simulation before the two are considered to disagree
+    pub gas_tolerance_percent: u32,
+}
+
+/// Simulator that runs each simulation against both a primary simulator and an
+/// optional "canary" simulator, typically backed by a different RPC.
+///
+/// Disagreements between the two are logged and counted, but the primary result
+/// is always the one returned. When no canary is configured this simulator simply
+/// forwards to the primary.
+pub struct CanarySimulator<S, C> {
+    primary: S,
+    canary: Option<C>,
+    settings: CanarySettings,
+    metrics: CanaryMetrics,
+}
+
+impl<S, C> CanarySimulator<S, C> {
+    /// Creates a new canary simulator
+    pub fn new(primary: S, canary: Option<C>, settings: CanarySettings) -> Self {
+        Self {
+            primary,
+            canary,
+            settings,
+            metrics: CanaryMetrics::default(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<S, C> Simulator for CanarySimulator<S, C>
+where
+    S: Simulator,
+    C: Simulator<UO = S::UO>,
+{
+    type UO = S::UO;
+
+    async fn simulate_validation(
+        &self,
+        op: Self::UO,
+        trusted: bool,
+        block: BlockTag,
+        expected_code_hash: Option<B256>,
+    ) -> Result<SimulationResult, SimulationError> {
+        let Some(canary) = &self.canary else {
+            return self
+                .primary
+                .simulate_validation(op, trusted, block, expected_code_hash)
+                .await;
+        };
+
+        let op_hash = op.hash();
+        let (primary_result, canary_result) = tokio::join!(
+            self.primary
+                .simulate_validation(op.clone(), trusted, block, expected_code_hash),
+            canary.simulate_validation(op, trusted, block, expected_code_hash),
+        );
+
+        if let Err(SimulationError {
+            violation_error: ViolationError::Other(error),
+            ..
+        }) = &canary_result
+        {
+            warn!("Canary simulation failed for op {op_hash:?}: {error:?}");
+            self.metrics.canary_errors.increment(1);
+        } else if let Some(disagreement) = compare_results(
+            &primary_result,
+            &canary_result,
+            self.settings.gas_tolerance_percent,
+        ) {
+            warn!(
+                "Canary simulation disagreed with primary for op {op_hash:?}: {disagreement:?}. Primary: {primary_result:?} Canary: {canary_result:?}"
+            );
+            self.metrics.disagreements.increment(1);
+        }
+
+        primary_result
+    }
+
+    async fn evaluate_mempools(
+        &self,
+        op: Self::UO,
+        block: BlockTag,
+    ) -> Result<Vec<MempoolEvaluation>, SimulationError> {
+        self.primary.evaluate_mempools(op, block).await
+    }
+
+    async fn simulate_factory(
+        &self,
+        init_code: Bytes,
+        block: BlockTag,
+    ) -> Result<FactorySimResult, SimulationError> {
+        self.primary.simulate_factory(init_code, block).await
+    }
+}
+
+/// The way in which a canary simulation disagreed with the primary simulation
+#[derive(Debug, PartialEq, Eq)]
+enum Disagreement {
+    /// One simulation succeeded while the other found violations
+    Outcome,
+    /// Both simulations found violations, but not the same ones
+    Violations,
+    /// The pre-op gas differs by more than the tolerance
+    PreOpGas { primary: u128, canary: u128 },
+}
+
+fn compare_results(
+    primary: &Result<SimulationResult, SimulationError>,
+    canary: &Result<SimulationResult, SimulationError>,
+    gas_tolerance_percent: u32,
+) -> Option<Disagreement> {
+    match (primary, canary) {
+        (Ok(primary), Ok(canary)) => {
+            let tolerance = math::percent(primary.pre_op_gas, gas_tolerance_percent);
+            (primary.pre_op_gas.abs_diff(canary.pre_op_gas) > tolerance).then_some(
+                Disagreement::PreOpGas {
+                    primary: primary.pre_op_gas,
+                    canary: canary.pre_op_gas,
+                },
+            )
+        }
+        (
+            Err(SimulationError {
+                violation_error: ViolationError::Violations(primary),
+                ..
+            }),
+            Err(SimulationError {
+                violation_error: ViolationError::Violations(canary),
+                ..
+            }),
+        ) => {
+            let mut primary = primary.clone();
+            let mut canary = canary.clone();
+            primary.sort();
+            canary.sort();
+            (primary != canary).then_some(Disagreement::Violations)
+        }
+        // Errors unrelated to validation rules on the primary are not comparable
+        (
+            Err(SimulationError {
+                violation_error: ViolationError::Other(_),
+                ..
+            }),
+            _,
+        ) => None,
+        _ => Some(Disagreement::Outcome),
+    }
+}
+
+#[derive(Metrics)]
+#[metrics(scope = "sim_canary")]
+struct CanaryMetrics {
+    #[metric(describe = "the number of canary simulations that disagreed with the primary.")]
+    disagreements: Counter,
+    #[metric(describe = "the number of canary simulations that failed to run.")]
+    canary_errors: Counter,
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::{pool::SimulationViolation, EntityType};
+
+    use super::*;
+
+    fn success(pre_op_gas: u128) -> Result<SimulationResult, SimulationError> {
+        Ok(SimulationResult {
+            pre_op_gas,
+            ..Default::default()
+        })
+    }
+
+    fn violations(
+        violations: Vec<SimulationViolation>,
+    ) -> Result<SimulationResult, SimulationError> {
+        Err(SimulationError {
+            violation_error: violations.into(),
+            entity_infos: None,
+        })
+    }
+
+    #[test]
+    fn test_compare_gas_within_tolerance() {
+        assert_eq!(compare_results(&success(1000), &success(1100), 10), None);
+        assert_eq!(compare_results(&success(1000), &success(900), 10), None);
+    }
+
+    #[test]
+    fn test_compare_gas_outside_tolerance() {
+        assert_eq!(
+            compare_results(&success(1000), &success(1101), 10),
+            Some(Disagreement::PreOpGas {
+                primary: 1000,
+                canary: 1101
+            })
+        );
+    }
+
+    #[test]
+    fn test_compare_violations() {
+        let primary = violations(vec![
+            SimulationViolation::DidNotRevert,
+            SimulationViolation::UnintendedRevert(EntityType::Account, None),
+        ]);
+        let same = violations(vec![
+            SimulationViolation::UnintendedRevert(EntityType::Account, None),
+            SimulationViolation::DidNotRevert,
+        ]);
+        let different = violations(vec![SimulationViolation::DidNotRevert]);
+
+        assert_eq!(compare_results(&primary, &same, 10), None);
+        assert_eq!(
+            compare_results(&primary, &different, 10),
+            Some(Disagreement::Violations)
+        );
+    }
+
+    #[test]
+    fn test_compare_outcome() {
+        let failed = violations(vec![SimulationViolation::DidNotRevert]);
+        assert_eq!(
+            compare_results(&success(1000), &failed, 10),
+            Some(Disagreement::Outcome)
+        );
+        assert_eq!(
+            compare_results(&failed, &success(1000), 10),
+            Some(Disagreement::Outcome)
+        );
+    }
+}
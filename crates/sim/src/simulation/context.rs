@@ -13,18 +13,24 @@
 
 use std::collections::{BTreeSet, HashMap, HashSet};
 
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_sol_types::{sol, SolCall};
 use anyhow::Context;
 use rundler_provider::BlockId;
 use rundler_types::{
-    pool::SimulationViolation, EntityInfos, EntityType, ExpectedStorage, Opcode, StakeInfo,
-    UserOperation, ValidationOutput,
+    pool::SimulationViolation, Entity, EntityInfos, EntityType, ExpectedStorage, InitCodeError,
+    Opcode, PaymasterAndDataError, StakeInfo, Timestamp, UserOperation, ValidationOutput,
 };
 use serde::{Deserialize, Serialize};
 
 use super::Settings;
 use crate::ViolationError;
 
+sol! {
+    /// The single call execution method shared by most smart accounts
+    function execute(address dest, uint256 value, bytes func);
+}
+
 #[derive(Clone, Debug)]
 pub struct ValidationContext<UO> {
     pub(crate) op: UO,
@@ -46,6 +52,12 @@ pub(crate) struct TracerOutput {
     pub(crate) associated_slots_by_address: AssociatedSlotsByAddress,
     pub(crate) factory_called_create2_twice: bool,
     pub(crate) expected_storage: ExpectedStorage,
+    // logs emitted by entities during validation, including from calls that later reverted
+    #[serde(default)]
+    pub(crate) logs: Vec<LogInfo>,
+    // contracts successfully created during the factory phase
+    #[serde(default)]
+    pub(crate) deployed_contracts: Vec<Address>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -59,6 +71,22 @@ pub(crate) struct Phase {
     pub(crate) ran_out_of_gas: bool,
     pub(crate) undeployed_contract_accesses: Vec<Address>,
     pub(crate) ext_code_access_info: HashMap<Address, Opcode>,
+    // addresses that had no code when checked with EXTCODESIZE or EXTCODEHASH, even if
+    // code was deployed there later in the phase
+    #[serde(default)]
+    pub(crate) undeployed_ext_code_accesses: HashMap<Address, Opcode>,
+    #[serde(default)]
+    pub(crate) max_call_depth: u64,
+    // gas used by the calls the entry point made during this phase
+    #[serde(default)]
+    pub(crate) gas_used: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct LogInfo {
+    pub(crate) address: Address,
+    pub(crate) topics: Vec<U256>,
+    pub(crate) data: Bytes,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -96,6 +124,18 @@ impl AssociatedSlotsByAddress {
         (slot - next_smallest_slot) < U256::from(128)
     }
 
+    // The addresses the slot is associated with
+    pub(crate) fn associated_addresses(&self, slot: U256) -> impl Iterator<Item = Address> + '_ {
+        self.0
+            .keys()
+            .copied()
+            .filter(move |&address| self.is_associated_slot(address, slot))
+    }
+
+    pub(crate) fn add_associated_slot(&mut self, address: Address, slot: U256) {
+        self.0.entry(address).or_default().insert(slot);
+    }
+
     pub(crate) fn addresses(&self) -> HashSet<Address> {
         self.0.clone().into_keys().collect()
     }
@@ -161,11 +201,189 @@ pub(crate) fn infos_from_validation_output(
     ei
 }
 
+// The stake the entry point reported for the entity of the given type
+pub(crate) fn stake_info(entry_point_out: &ValidationOutput, kind: EntityType) -> StakeInfo {
+    match kind {
+        EntityType::Account => entry_point_out.sender_info,
+        EntityType::Factory => entry_point_out.factory_info,
+        EntityType::Paymaster => entry_point_out.paymaster_info,
+        EntityType::Aggregator => entry_point_out
+            .aggregator_info
+            .map(|info| info.stake_info)
+            .unwrap_or_default(),
+    }
+}
+
 pub(crate) fn is_staked(info: StakeInfo, sim_settings: &Settings) -> bool {
     info.stake >= sim_settings.min_stake_value
         && info.unstake_delay_sec >= sim_settings.min_unstake_delay
 }
 
+/// Returns true if an entity that has started unstaking, whose stake can be withdrawn at
+/// `withdraw_time`, can withdraw it within the configured warning window of `now`.
+///
+/// A zero withdraw time means the entity has not started unstaking.
+pub(crate) fn stake_expiring_soon(
+    withdraw_time: u64,
+    now: Timestamp,
+    sim_settings: &Settings,
+) -> bool {
+    let window = sim_settings.stake_expiry_warning_window_secs;
+    window > 0
+        && withdraw_time != 0
+        && withdraw_time <= now.seconds_since_epoch().saturating_add(window)
+}
+
+/// Returns a violation if an operation valid after `valid_after` only becomes valid past
+/// the configured horizon from `now`
+pub(crate) fn valid_after_violation(
+    valid_after: Timestamp,
+    now: Timestamp,
+    sim_settings: &Settings,
+) -> Option<SimulationViolation> {
+    let max_valid_after = Timestamp::new(
+        now.seconds_since_epoch()
+            .saturating_add(sim_settings.max_valid_after_horizon.as_secs()),
+    );
+    (valid_after > max_valid_after).then_some(SimulationViolation::ValidAfterTooFar {
+        valid_after,
+        max_valid_after,
+    })
+}
+
+/// Returns a violation if the aggregator an operation declares differs from the one the
+/// entry point found in validation. A missing aggregator on either side is reported as the
+/// zero address.
+pub(crate) fn aggregator_mismatch_violation(
+    declared: Option<Address>,
+    actual: Option<Address>,
+) -> Option<SimulationViolation> {
+    (declared != actual).then(|| {
+        SimulationViolation::AggregatorMismatch(
+            declared.unwrap_or_default(),
+            actual.unwrap_or_default(),
+        )
+    })
+}
+
+/// Returns a violation if the packed user operation is larger than the configured maximum
+pub(crate) fn op_size_violation<UO: UserOperation>(
+    op: &UO,
+    sim_settings: &Settings,
+) -> Option<SimulationViolation> {
+    let size = op.abi_encoded_size();
+    (size > sim_settings.max_op_calldata_bytes).then_some(SimulationViolation::OperationTooLarge {
+        size,
+        maximum: sim_settings.max_op_calldata_bytes,
+    })
+}
+
+/// Returns a violation if the max priority fee per gas is below the configured minimum
+pub(crate) fn priority_fee_violation<UO: UserOperation>(
+    op: &UO,
+    sim_settings: &Settings,
+) -> Option<SimulationViolation> {
+    let provided = op.max_priority_fee_per_gas();
+    (provided < sim_settings.min_priority_fee_per_gas).then_some(
+        SimulationViolation::PriorityFeeTooLow {
+            provided,
+            minimum: sim_settings.min_priority_fee_per_gas,
+        },
+    )
+}
+
+/// Returns a violation if the pre-verification gas doesn't cover the op's calldata cost
+/// plus the configured overhead
+pub(crate) fn pre_verification_gas_violation<UO: UserOperation>(
+    op: &UO,
+    sim_settings: &Settings,
+) -> Option<SimulationViolation> {
+    let minimum = op.calldata_gas_cost() + sim_settings.min_pre_verification_gas_overhead?;
+    let provided = op.pre_verification_gas();
+    (provided < minimum)
+        .then_some(SimulationViolation::PreVerificationGasTooLow { provided, minimum })
+}
+
+/// Returns a violation if the call gas limit is zero and the settings reject such ops
+pub(crate) fn zero_call_gas_violation<UO: UserOperation>(
+    op: &UO,
+    sim_settings: &Settings,
+) -> Option<SimulationViolation> {
+    (sim_settings.reject_zero_call_gas && op.call_gas_limit() == 0)
+        .then_some(SimulationViolation::ZeroCallGasLimit)
+}
+
+/// Returns a violation if the call data executes a call targeting the entry point,
+/// which would re-enter it during `handleOps`
+pub(crate) fn entry_point_call_violation<UO: UserOperation>(
+    op: &UO,
+    entry_point: Address,
+) -> Option<SimulationViolation> {
+    let call = executeCall::abi_decode(op.call_data()).ok()?;
+    (call.dest == entry_point).then_some(SimulationViolation::CalledEntryPoint)
+}
+
+/// Returns a violation if the sender is the entry point itself
+pub(crate) fn sender_is_entry_point_violation<UO: UserOperation>(
+    op: &UO,
+    entry_point: Address,
+) -> Option<SimulationViolation> {
+    (op.sender() == entry_point).then_some(SimulationViolation::SenderIsEntryPoint)
+}
+
+/// Returns a violation if the paymaster or factory is on the configured denylist
+pub(crate) fn denied_entity_violation<UO: UserOperation>(
+    op: &UO,
+    sim_settings: &Settings,
+) -> Option<SimulationViolation> {
+    if let Some(paymaster) = op.paymaster() {
+        if sim_settings.denied_paymasters.contains(&paymaster) {
+            return Some(SimulationViolation::EntityDenied(Entity::paymaster(
+                paymaster,
+            )));
+        }
+    }
+    if let Some(factory) = op.factory() {
+        if sim_settings.denied_factories.contains(&factory) {
+            return Some(SimulationViolation::EntityDenied(Entity::factory(factory)));
+        }
+    }
+    None
+}
+
+pub(crate) fn malformed_op_violation<UO: UserOperation>(op: &UO) -> Option<SimulationViolation> {
+    if let Err(InitCodeError::Malformed(length)) = op.validate_init_code() {
+        return Some(SimulationViolation::MalformedInitCode(length));
+    }
+    if let Err(PaymasterAndDataError::Malformed(length)) = op.validate_paymaster_and_data() {
+        return Some(SimulationViolation::MalformedPaymasterAndData(length));
+    }
+    None
+}
+
+/// Returns a violation if the signature is shorter than the configured minimum. Ops with
+/// an aggregator are exempt, as the aggregator may carry their signature.
+pub(crate) fn malformed_signature_violation<UO: UserOperation>(
+    op: &UO,
+    sim_settings: &Settings,
+) -> Option<SimulationViolation> {
+    let minimum = sim_settings.min_signature_length?;
+    let length = op.signature().len();
+    (op.aggregator().is_none() && length < minimum)
+        .then_some(SimulationViolation::MalformedSignature { length, minimum })
+}
+
+/// Returns a violation if validation captured more storage slots than are kept in memory
+/// for a single op
+pub(crate) fn expected_storage_violation(
+    expected_storage: &ExpectedStorage,
+    sim_settings: &Settings,
+) -> Option<SimulationViolation> {
+    let slots = expected_storage.num_slots();
+    let max = sim_settings.max_expected_storage_slots;
+    (slots > max).then_some(SimulationViolation::ExpectedStorageTooLarge { slots, max })
+}
+
 pub(crate) fn parse_combined_context_str<A, B>(combined: &str) -> anyhow::Result<(A, B)>
 where
     A: std::str::FromStr,
@@ -0,0 +1,188 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use alloy_primitives::{Bytes, B256};
+
+use super::{
+    BlockTag, FactorySimResult, MempoolEvaluation, SimulationError, SimulationResult, Simulator,
+};
+
+/// A fault injected into a `simulate_validation` call
+#[derive(Clone, Debug)]
+pub enum SimulationFault {
+    /// Wait for the given duration before delegating to the inner simulator
+    Delay(Duration),
+    /// Never complete, as if the response was lost
+    Drop,
+    /// Fail with the given message without calling the inner simulator
+    Error(String),
+}
+
+/// Simulator that injects faults into `simulate_validation` calls according to a fixed
+/// schedule, for testing how callers handle slow and failing simulations.
+///
+/// Each call takes the next entry of the schedule. `None` entries, and all calls after the
+/// schedule is exhausted, are forwarded to the inner simulator unchanged. All other
+/// methods are always forwarded.
+pub struct FaultInjectingSimulator<S> {
+    inner: S,
+    schedule: Mutex<VecDeque<Option<SimulationFault>>>,
+}
+
+impl<S> FaultInjectingSimulator<S> {
+    /// Creates a new fault injecting simulator
+    pub fn new(inner: S, schedule: impl IntoIterator<Item = Option<SimulationFault>>) -> Self {
+        Self {
+            inner,
+            schedule: Mutex::new(schedule.into_iter().collect()),
+        }
+    }
+
+    fn next_fault(&self) -> Option<SimulationFault> {
+        self.schedule.lock().unwrap().pop_front().flatten()
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: Simulator> Simulator for FaultInjectingSimulator<S> {
+    type UO = S::UO;
+
+    async fn simulate_validation(
+        &self,
+        op: Self::UO,
+        trusted: bool,
+        block: BlockTag,
+        expected_code_hash: Option<B256>,
+    ) -> Result<SimulationResult, SimulationError> {
+        match self.next_fault() {
+            Some(SimulationFault::Delay(delay)) => tokio::time::sleep(delay).await,
+            Some(SimulationFault::Drop) => std::future::pending().await,
+            Some(SimulationFault::Error(message)) => {
+                return Err(anyhow::anyhow!(message).into());
+            }
+            None => {}
+        }
+
+        self.inner
+            .simulate_validation(op, trusted, block, expected_code_hash)
+            .await
+    }
+
+    async fn evaluate_mempools(
+        &self,
+        op: Self::UO,
+        block: BlockTag,
+    ) -> Result<Vec<MempoolEvaluation>, SimulationError> {
+        self.inner.evaluate_mempools(op, block).await
+    }
+
+    async fn simulate_factory(
+        &self,
+        init_code: Bytes,
+        block: BlockTag,
+    ) -> Result<FactorySimResult, SimulationError> {
+        self.inner.simulate_factory(init_code, block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::v0_6::UserOperation;
+
+    use super::*;
+    use crate::ViolationError;
+
+    struct PassthroughSimulator;
+
+    #[async_trait::async_trait]
+    impl Simulator for PassthroughSimulator {
+        type UO = UserOperation;
+
+        async fn simulate_validation(
+            &self,
+            _op: UserOperation,
+            _trusted: bool,
+            _block: BlockTag,
+            _expected_code_hash: Option<B256>,
+        ) -> Result<SimulationResult, SimulationError> {
+            Ok(SimulationResult {
+                pre_op_gas: 1000,
+                ..Default::default()
+            })
+        }
+
+        async fn evaluate_mempools(
+            &self,
+            _op: UserOperation,
+            _block: BlockTag,
+        ) -> Result<Vec<MempoolEvaluation>, SimulationError> {
+            Ok(vec![])
+        }
+
+        async fn simulate_factory(
+            &self,
+            _init_code: Bytes,
+            _block: BlockTag,
+        ) -> Result<FactorySimResult, SimulationError> {
+            Ok(FactorySimResult::default())
+        }
+    }
+
+    async fn simulate(
+        simulator: &FaultInjectingSimulator<PassthroughSimulator>,
+    ) -> Result<SimulationResult, SimulationError> {
+        simulator
+            .simulate_validation(UserOperation::default(), false, B256::ZERO.into(), None)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_follows_schedule() {
+        let simulator = FaultInjectingSimulator::new(
+            PassthroughSimulator,
+            [
+                Some(SimulationFault::Error("injected".to_string())),
+                None,
+                Some(SimulationFault::Delay(Duration::from_millis(10))),
+            ],
+        );
+
+        let Err(SimulationError {
+            violation_error: ViolationError::Other(error),
+            ..
+        }) = simulate(&simulator).await
+        else {
+            panic!("expected an injected error");
+        };
+        assert_eq!(error.to_string(), "injected");
+
+        assert_eq!(simulate(&simulator).await.unwrap().pre_op_gas, 1000);
+
+        let delayed = tokio::time::timeout(Duration::from_millis(1), simulate(&simulator)).await;
+        assert!(delayed.is_err());
+
+        // the schedule is exhausted, calls pass through
+        assert_eq!(simulate(&simulator).await.unwrap().pre_op_gas, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_drop_never_completes() {
+        let simulator =
+            FaultInjectingSimulator::new(PassthroughSimulator, [Some(SimulationFault::Drop)]);
+
+        let res = tokio::time::timeout(Duration::from_millis(10), simulate(&simulator)).await;
+        assert!(res.is_err());
+    }
+}
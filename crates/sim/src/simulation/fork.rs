@@ -0,0 +1,131 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Helpers for running the simulator against a local anvil fork.
+//!
+//! These require the Foundry `anvil` binary to be installed and on `PATH`.
+
+use std::collections::HashMap;
+
+use alloy_node_bindings::{Anvil, AnvilInstance};
+use alloy_primitives::{Bytes, B256};
+use anyhow::{bail, Context};
+use rundler_provider::{AlloyEntryPointV0_6, AlloyEvmProvider, EvmProvider};
+use rundler_types::{chain::ChainSpec, v0_6::UserOperation};
+
+use super::{
+    new_v0_6_simulator, BlockTag, FactorySimResult, MempoolEvaluation, Settings, SimulationError,
+    SimulationResult, Simulator,
+};
+
+const PROVIDER_CLIENT_TIMEOUT_SECONDS: u64 = 10;
+const MAX_VERIFICATION_GAS: u64 = 5_000_000;
+const MAX_BUNDLE_EXECUTION_GAS: u64 = 25_000_000;
+const MAX_GAS_ESTIMATION_GAS: u64 = 550_000_000;
+
+/// Simulator backed by a local anvil fork.
+///
+/// Owns the anvil process, which is killed when this is dropped.
+pub struct ForkSimulator<S> {
+    inner: S,
+    anvil: AnvilInstance,
+}
+
+impl<S> ForkSimulator<S> {
+    /// The HTTP endpoint of the anvil fork, for making changes to its state in tests
+    pub fn endpoint(&self) -> String {
+        self.anvil.endpoint()
+    }
+}
+
+/// Spawns an anvil fork of `fork_url` at block `block` and returns a v0.6 simulator
+/// pointed at it, using default simulation settings and no alternative mempools.
+///
+/// Fails if anvil cannot be spawned or if the v0.6 entry point is not deployed on the fork.
+pub async fn fork_simulator(
+    fork_url: &str,
+    block: u64,
+) -> anyhow::Result<ForkSimulator<impl Simulator<UO = UserOperation>>> {
+    let anvil = Anvil::new()
+        .fork(fork_url)
+        .fork_block_number(block)
+        .try_spawn()
+        .context("should spawn anvil, is it installed?")?;
+
+    let chain_spec = ChainSpec {
+        id: anvil.chain_id(),
+        ..Default::default()
+    };
+    let provider =
+        rundler_provider::new_alloy_provider(&anvil.endpoint(), PROVIDER_CLIENT_TIMEOUT_SECONDS)?;
+    let (da_gas_oracle, _) =
+        rundler_provider::new_alloy_da_gas_oracle(&chain_spec, provider.clone());
+    let evm = AlloyEvmProvider::new(provider.clone());
+
+    let code = evm
+        .get_code(chain_spec.entry_point_address_v0_6, None)
+        .await
+        .context("should get entry point code from fork")?;
+    if code.is_empty() {
+        bail!(
+            "entry point v0.6 {} is not deployed at block {block} of the fork",
+            chain_spec.entry_point_address_v0_6
+        );
+    }
+
+    let entry_point = AlloyEntryPointV0_6::new(
+        chain_spec,
+        MAX_VERIFICATION_GAS,
+        MAX_BUNDLE_EXECUTION_GAS,
+        MAX_GAS_ESTIMATION_GAS,
+        MAX_BUNDLE_EXECUTION_GAS,
+        provider,
+        da_gas_oracle,
+    );
+    let inner = new_v0_6_simulator(evm, entry_point, Settings::default(), HashMap::new());
+
+    Ok(ForkSimulator { inner, anvil })
+}
+
+#[async_trait::async_trait]
+impl<S: Simulator> Simulator for ForkSimulator<S> {
+    type UO = S::UO;
+
+    async fn simulate_validation(
+        &self,
+        op: Self::UO,
+        trusted: bool,
+        block: BlockTag,
+        expected_code_hash: Option<B256>,
+    ) -> Result<SimulationResult, SimulationError> {
+        self.inner
+            .simulate_validation(op, trusted, block, expected_code_hash)
+            .await
+    }
+
+    async fn evaluate_mempools(
+        &self,
+        op: Self::UO,
+        block: BlockTag,
+    ) -> Result<Vec<MempoolEvaluation>, SimulationError> {
+        self.inner.evaluate_mempools(op, block).await
+    }
+
+    async fn simulate_factory(
+        &self,
+        init_code: Bytes,
+        block: BlockTag,
+    ) -> Result<FactorySimResult, SimulationError> {
+        self.inner.simulate_factory(init_code, block).await
+    }
+}
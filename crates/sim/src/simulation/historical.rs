@@ -0,0 +1,170 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::Address;
+use anyhow::Context;
+use rundler_provider::{BlockId, EvmProvider};
+
+use super::{BlockTag, SimulationError, SimulationResult, Simulator};
+
+/// Simulates the validation of `op` against the state at the given block number, for
+/// reproducing a past revert, e.g. simulating the ops of a bundle that reverted at block
+/// `N` at block `N - 1`.
+///
+/// The number is resolved to a block hash through `evm`, so the result is as it would have
+/// been at that block even if the chain has moved on. Requires `evm` to serve historical
+/// state, i.e. to be an archive node if the block is old; otherwise an error saying so is
+/// returned rather than the node's error from deep within simulation.
+pub async fn simulate_at_block_number<S, E>(
+    simulator: &S,
+    evm: &E,
+    op: S::UO,
+    block_number: u64,
+) -> Result<SimulationResult, SimulationError>
+where
+    S: Simulator,
+    E: EvmProvider,
+{
+    let block_hash = evm
+        .get_block(block_number.into())
+        .await
+        .with_context(|| format!("failed to get block {block_number}"))?
+        .with_context(|| format!("block {block_number} not found"))?
+        .header
+        .hash;
+
+    // nodes that prune state still serve old blocks, but fail to read state at them
+    evm.get_balance(Address::ZERO, Some(BlockId::from(block_hash)))
+        .await
+        .with_context(|| {
+            format!("state at block {block_number} is not available, simulating at a historical block requires an archive node")
+        })?;
+
+    simulator
+        .simulate_validation(op, false, BlockTag::Hash(block_hash), None)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{B256, U256};
+    use alloy_rpc_types_eth::Block as AlloyBlock;
+    use alloy_transport::TransportErrorKind;
+    use rundler_provider::{
+        AnyHeader, Block, BlockHeader, MockEvmProvider, ProviderError, WithOtherFields,
+    };
+    use rundler_types::v0_6;
+
+    use super::*;
+    use crate::{simulation::MockSimulator, ViolationError};
+
+    // Succeeds only when simulating at the expected block
+    fn simulator(block_hash: B256) -> MockSimulator {
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .withf(move |_, _, block, _| *block == BlockTag::Hash(block_hash))
+            .returning(|_, _, _, _| {
+                Ok(SimulationResult {
+                    pre_op_gas: 100_000,
+                    ..Default::default()
+                })
+            });
+        simulator
+    }
+
+    fn block(number: u64, hash: B256) -> Block {
+        Block::new(WithOtherFields::new(AlloyBlock {
+            header: BlockHeader {
+                hash,
+                inner: AnyHeader {
+                    number,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+    }
+
+    fn error_message(err: SimulationError) -> String {
+        let ViolationError::Other(e) = err.violation_error else {
+            panic!("expected other error");
+        };
+        e.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_simulate_at_block_number() {
+        let block_hash = B256::repeat_byte(1);
+
+        // an archive node serves the state at the old block
+        let mut evm = MockEvmProvider::new();
+        evm.expect_get_block()
+            .withf(|block_id| *block_id == BlockId::from(99_u64))
+            .returning(move |_| Ok(Some(block(99, block_hash))));
+        evm.expect_get_balance()
+            .withf(move |_, block_id| *block_id == Some(BlockId::from(block_hash)))
+            .returning(|_, _| Ok(U256::ZERO));
+
+        let result = simulate_at_block_number(
+            &simulator(block_hash),
+            &evm,
+            v0_6::UserOperation::default(),
+            99,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.pre_op_gas, 100_000);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_at_block_number_not_archive() {
+        let block_hash = B256::repeat_byte(1);
+
+        let mut evm = MockEvmProvider::new();
+        evm.expect_get_block()
+            .returning(move |_| Ok(Some(block(99, block_hash))));
+        evm.expect_get_balance().returning(|_, _| {
+            Err(ProviderError::RPC(TransportErrorKind::custom_str(
+                "missing trie node",
+            )))
+        });
+
+        let err = simulate_at_block_number(
+            &simulator(block_hash),
+            &evm,
+            v0_6::UserOperation::default(),
+            99,
+        )
+        .await
+        .unwrap_err();
+        assert!(error_message(err).contains("requires an archive node"));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_at_block_number_not_found() {
+        let mut evm = MockEvmProvider::new();
+        evm.expect_get_block().returning(|_| Ok(None));
+
+        let err = simulate_at_block_number(
+            &MockSimulator::new(),
+            &evm,
+            v0_6::UserOperation::default(),
+            99,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error_message(err), "block 99 not found");
+    }
+}
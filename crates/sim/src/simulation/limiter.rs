@@ -0,0 +1,116 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    fmt,
+    sync::{Arc, OnceLock},
+    time::Instant,
+};
+
+use metrics::Histogram;
+use metrics_derive::Metrics;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+// Shared by every simulator in the process so that the limit bounds the total
+// number of in-flight simulations, not the number per simulator.
+static SIMULATION_PERMITS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+
+/// Bounds the number of simulations that may be in flight at once.
+///
+/// The underlying semaphore is process-wide and is sized by the first limiter
+/// that is created with a limit.
+#[derive(Clone)]
+pub(crate) struct SimulationLimiter {
+    permits: Option<Arc<Semaphore>>,
+    metrics: LimiterMetrics,
+}
+
+impl SimulationLimiter {
+    pub(crate) fn new(max_concurrent_simulations: Option<usize>) -> Self {
+        let permits = max_concurrent_simulations.map(|max| {
+            SIMULATION_PERMITS
+                .get_or_init(|| Arc::new(Semaphore::new(max)))
+                .clone()
+        });
+
+        Self {
+            permits,
+            metrics: LimiterMetrics::default(),
+        }
+    }
+
+    /// Waits for a simulation permit. The permit is released when dropped.
+    ///
+    /// Returns `None` if there is no limit configured.
+    pub(crate) async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let permits = self.permits.as_ref()?;
+
+        let start = Instant::now();
+        let permit = permits
+            .acquire()
+            .await
+            .expect("simulation semaphore should never be closed");
+        self.metrics
+            .permit_wait_ms
+            .record(start.elapsed().as_millis() as f64);
+
+        Some(permit)
+    }
+}
+
+impl fmt::Debug for SimulationLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimulationLimiter")
+            .field("permits", &self.permits)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Metrics, Clone)]
+#[metrics(scope = "sim_limiter")]
+struct LimiterMetrics {
+    #[metric(describe = "the distribution of time spent waiting for a simulation permit.")]
+    permit_wait_ms: Histogram,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unlimited() {
+        let limiter = SimulationLimiter::new(None);
+        assert!(limiter.acquire().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_permit() {
+        let limiter = SimulationLimiter {
+            permits: Some(Arc::new(Semaphore::new(1))),
+            metrics: LimiterMetrics::default(),
+        };
+
+        let permit = limiter.acquire().await;
+        assert!(permit.is_some());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), limiter.acquire())
+                .await
+                .is_err()
+        );
+
+        drop(permit);
+        assert!(limiter.acquire().await.is_some());
+    }
+}
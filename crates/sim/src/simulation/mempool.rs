@@ -14,6 +14,8 @@
 use std::{collections::HashMap, str::FromStr};
 
 use alloy_primitives::{Address, B256, U256};
+use metrics::Counter;
+use metrics_derive::Metrics;
 use rundler_types::{Entity, EntityType, Opcode, UserOperation, UserOperationVariant};
 use serde::Deserialize;
 use serde_with::{serde_as, DisplayFromStr};
@@ -236,8 +238,44 @@ impl AllowlistEntry {
 pub(crate) enum MempoolMatchResult {
     /// One or more matched mempools by ID
     Matches(Vec<B256>),
-    /// No mempools matched, with the index of the first violation that didn't match
-    NoMatch(usize),
+    /// No mempools matched, with the violations that excluded each of the mempools
+    NoMatch(Vec<SimulationViolation>),
+}
+
+/// The outcome of evaluating an operation's violations against a single mempool
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MempoolEvaluation {
+    /// The mempool ID
+    pub id: B256,
+    /// True if every violation is allowlisted by the mempool
+    pub passed: bool,
+    /// The violations that are not allowlisted by the mempool, empty if passed
+    pub excluding_violations: Vec<SimulationViolation>,
+}
+
+/// Evaluate a list of violations against each mempool individually, returning
+/// one evaluation per mempool ordered by mempool ID.
+pub(crate) fn evaluate_mempools(
+    mempools: &HashMap<B256, MempoolConfig>,
+    violations: &[SimulationViolation],
+) -> Vec<MempoolEvaluation> {
+    let mut evaluations: Vec<_> = mempools
+        .iter()
+        .map(|(id, config)| {
+            let excluding_violations: Vec<_> = violations
+                .iter()
+                .filter(|v| !config.allowlist.iter().any(|r| r.is_allowed(v)))
+                .cloned()
+                .collect();
+            MempoolEvaluation {
+                id: *id,
+                passed: excluding_violations.is_empty(),
+                excluding_violations,
+            }
+        })
+        .collect();
+    evaluations.sort_by_key(|e| e.id);
+    evaluations
 }
 
 /// Match mempools based on a list of violations. Operations are matched to each of the
@@ -248,23 +286,79 @@ pub(crate) fn match_mempools(
     violations: &[SimulationViolation],
 ) -> MempoolMatchResult {
     let mut candidate_pools: Vec<B256> = mempools.keys().cloned().collect();
-    for (i, violation) in violations.iter().enumerate() {
+    let mut excluding_violations = vec![];
+    for violation in violations {
+        let num_candidates = candidate_pools.len();
         candidate_pools.retain(|p| {
             mempools[p]
                 .allowlist
                 .iter()
                 .any(|r| r.is_allowed(violation))
         });
+        // with no mempools configured, the first violation excludes the operation
+        if candidate_pools.len() < num_candidates || num_candidates == 0 {
+            excluding_violations.push(violation.clone());
+        }
         if candidate_pools.is_empty() {
-            return MempoolMatchResult::NoMatch(i);
+            return MempoolMatchResult::NoMatch(excluding_violations);
         }
     }
     MempoolMatchResult::Matches(candidate_pools)
 }
 
+/// Returns the ID of the canonical mempool if it is the only mempool configured.
+///
+/// The canonical mempool allowlists no violations, so matching against it alone needs no
+/// allowlist evaluation, see [`match_canonical_mempool`].
+pub(crate) fn canonical_only_mempool(mempools: &HashMap<B256, MempoolConfig>) -> Option<B256> {
+    if mempools.len() != 1 {
+        return None;
+    }
+    let (id, config) = mempools.iter().next()?;
+    config.allowlist.is_empty().then_some(*id)
+}
+
+/// Match violations against a lone canonical mempool, with the same result as
+/// [`match_mempools`] without evaluating any allowlist: an operation with no violations
+/// matches the mempool, otherwise its first violation excludes it.
+pub(crate) fn match_canonical_mempool(
+    id: B256,
+    violations: &[SimulationViolation],
+) -> MempoolMatchResult {
+    match violations.first() {
+        None => MempoolMatchResult::Matches(vec![id]),
+        Some(violation) => MempoolMatchResult::NoMatch(vec![violation.clone()]),
+    }
+}
+
+/// Throughput of a single mempool, labeled by the mempool's ID.
+///
+/// A series is kept per mempool ID and metric, so only enable these when the number of
+/// configured mempools is small.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "mempool")]
+pub struct MempoolMetrics {
+    #[metric(describe = "the count of ops whose simulation matched the mempool.")]
+    pub ops_simulated: Counter,
+    #[metric(describe = "the count of ops from the mempool accepted into the pool.")]
+    pub ops_accepted: Counter,
+    #[metric(describe = "the count of ops from the mempool included in a proposed bundle.")]
+    pub ops_bundled: Counter,
+    #[metric(describe = "the count of ops from the mempool rejected from a proposed bundle.")]
+    pub ops_dropped: Counter,
+}
+
+impl MempoolMetrics {
+    /// Returns the metrics of the mempool with the given ID
+    pub fn for_mempool(id: B256) -> Self {
+        Self::new_with_labels(&[("mempool_id", id.to_string())])
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alloy_primitives::U256;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
     use rundler_types::{pool::NeedsStakeInformation, StorageSlot, ViolationOpCode};
 
     use super::*;
@@ -507,6 +601,8 @@ mod tests {
             slot: U256::ZERO,
             min_stake: U256::ZERO,
             min_unstake_delay: 0,
+            current_stake: U256::ZERO,
+            current_unstake_delay: 0,
         }));
 
         assert!(entry.is_allowed(&violation));
@@ -519,6 +615,8 @@ mod tests {
             slot: U256::ZERO,
             min_stake: U256::ZERO,
             min_unstake_delay: 0,
+            current_stake: U256::ZERO,
+            current_unstake_delay: 0,
         }));
 
         assert!(!entry.is_allowed(&violation));
@@ -553,8 +651,8 @@ mod tests {
             ViolationOpCode(Opcode::BLOCKHASH),
         );
         assert_eq!(
-            match_mempools(&mempools, &[violation]),
-            MempoolMatchResult::NoMatch(0)
+            match_mempools(&mempools, &[violation.clone()]),
+            MempoolMatchResult::NoMatch(vec![violation])
         );
     }
 
@@ -596,9 +694,115 @@ mod tests {
                 ViolationOpCode(Opcode::BLOCKHASH),
             ),
         ];
+        // the first violation excludes the default mempool, the second the other
+        assert_eq!(
+            match_mempools(&mempools, &violations),
+            MempoolMatchResult::NoMatch(violations.to_vec())
+        );
+    }
+
+    #[test]
+    fn test_match_canonical_mempool() {
+        let id = B256::random();
+        let mempools = HashMap::from([(id, MempoolConfig::default())]);
+        assert_eq!(canonical_only_mempool(&mempools), Some(id));
+
+        let forbidden_opcode = |opcode| {
+            SimulationViolation::UsedForbiddenOpcode(
+                Entity {
+                    kind: EntityType::Account,
+                    address: Address::random(),
+                },
+                Address::random(),
+                ViolationOpCode(opcode),
+            )
+        };
+        let violation_sets = [
+            vec![],
+            vec![forbidden_opcode(Opcode::GAS)],
+            vec![
+                forbidden_opcode(Opcode::GAS),
+                forbidden_opcode(Opcode::BLOCKHASH),
+            ],
+        ];
+        // the fast path matches as the general path does
+        for violations in violation_sets {
+            assert_eq!(
+                match_canonical_mempool(id, &violations),
+                match_mempools(&mempools, &violations)
+            );
+        }
+
+        // a mempool with an allowlist, or a second mempool, needs the general path
+        let alt_config = MempoolConfig {
+            entry_point: Address::random(),
+            allowlist: vec![AllowlistEntry::new(
+                AllowEntity::Type(EntityType::Account),
+                AllowRule::ForbiddenOpcode {
+                    contract: Address::random(),
+                    opcode: Opcode::GAS,
+                },
+            )],
+            filters: vec![],
+        };
+        assert_eq!(
+            canonical_only_mempool(&HashMap::from([(id, alt_config.clone())])),
+            None
+        );
+        assert_eq!(
+            canonical_only_mempool(&HashMap::from([
+                (id, MempoolConfig::default()),
+                (B256::random(), alt_config),
+            ])),
+            None
+        );
+        assert_eq!(canonical_only_mempool(&HashMap::new()), None);
+    }
+
+    fn not_staked(needs_stake: Entity) -> SimulationViolation {
+        SimulationViolation::NotStaked(Box::new(NeedsStakeInformation {
+            needs_stake,
+            accessing_entity: needs_stake.kind,
+            accessed_entity: Some(needs_stake.kind),
+            accessed_address: needs_stake.address,
+            slot: U256::ZERO,
+            min_stake: U256::ZERO,
+            min_unstake_delay: 0,
+            current_stake: U256::ZERO,
+            current_unstake_delay: 0,
+        }))
+    }
+
+    #[test]
+    fn test_match_unstaked_paymaster_mempool() {
+        let default_mempool = B256::ZERO;
+        let restricted_mempool = B256::repeat_byte(1);
+        let restricted_config: MempoolConfig = serde_json::from_str(
+            r#"{
+                "entryPoint": "0x0000000071727de22e5e9d8baf0edac6f37da032",
+                "allowlist": [{ "entity": "paymaster", "rule": "notStaked" }]
+            }"#,
+        )
+        .unwrap();
+        let mempools = HashMap::from([
+            (default_mempool, MempoolConfig::default()),
+            (restricted_mempool, restricted_config),
+        ]);
+
+        // an unstaked paymaster only excludes the op from mempools that don't allow it
+        assert_eq!(
+            match_mempools(
+                &mempools,
+                &[not_staked(Entity::paymaster(Address::random()))]
+            ),
+            MempoolMatchResult::Matches(vec![restricted_mempool])
+        );
+
+        // the entry doesn't cover other unstaked entities
+        let violations = [not_staked(Entity::factory(Address::random()))];
         assert_eq!(
             match_mempools(&mempools, &violations),
-            MempoolMatchResult::NoMatch(1)
+            MempoolMatchResult::NoMatch(violations.to_vec())
         );
     }
 
@@ -728,4 +932,120 @@ mod tests {
             _ => panic!("Expected matches"),
         }
     }
+
+    #[test]
+    fn test_evaluate_mempools() {
+        let mempool0 = B256::random();
+        let mempool1 = B256::random();
+        let contract = Address::random();
+        let mempools = HashMap::from([
+            (mempool0, MempoolConfig::default()),
+            (
+                mempool1,
+                MempoolConfig {
+                    entry_point: Address::random(),
+                    allowlist: vec![AllowlistEntry::new(
+                        AllowEntity::Type(EntityType::Account),
+                        AllowRule::ForbiddenOpcode {
+                            contract,
+                            opcode: Opcode::GAS,
+                        },
+                    )],
+                    filters: vec![],
+                },
+            ),
+        ]);
+        let gas_violation = SimulationViolation::UsedForbiddenOpcode(
+            Entity {
+                kind: EntityType::Account,
+                address: Address::random(),
+            },
+            contract,
+            ViolationOpCode(Opcode::GAS),
+        );
+        let blockhash_violation = SimulationViolation::UsedForbiddenOpcode(
+            Entity {
+                kind: EntityType::Account,
+                address: Address::random(),
+            },
+            contract,
+            ViolationOpCode(Opcode::BLOCKHASH),
+        );
+
+        let mut expected = vec![
+            MempoolEvaluation {
+                id: mempool0,
+                passed: false,
+                excluding_violations: vec![gas_violation.clone()],
+            },
+            MempoolEvaluation {
+                id: mempool1,
+                passed: true,
+                excluding_violations: vec![],
+            },
+        ];
+        expected.sort_by_key(|e| e.id);
+        assert_eq!(
+            evaluate_mempools(&mempools, &[gas_violation.clone()]),
+            expected
+        );
+
+        // a violation allowed by neither mempool is reported against both
+        let evaluations = evaluate_mempools(
+            &mempools,
+            &[gas_violation.clone(), blockhash_violation.clone()],
+        );
+        assert!(evaluations.iter().all(|e| !e.passed));
+        let default_eval = evaluations.iter().find(|e| e.id == mempool0).unwrap();
+        assert_eq!(
+            default_eval.excluding_violations,
+            vec![gas_violation, blockhash_violation.clone()]
+        );
+        let other_eval = evaluations.iter().find(|e| e.id == mempool1).unwrap();
+        assert_eq!(other_eval.excluding_violations, vec![blockhash_violation]);
+    }
+
+    #[test]
+    fn test_mempool_metrics_labeled_by_id() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let id = B256::repeat_byte(1);
+        metrics::with_local_recorder(&recorder, || {
+            let metrics = MempoolMetrics::for_mempool(id);
+            metrics.ops_simulated.increment(2);
+            metrics.ops_bundled.increment(1);
+        });
+
+        let mut counters = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let key = key.key();
+                let labels = key
+                    .labels()
+                    .map(|label| (label.key().to_string(), label.value().to_string()))
+                    .collect::<Vec<_>>();
+                (key.name().to_string(), labels, value)
+            })
+            .collect::<Vec<_>>();
+        counters.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let labels = vec![("mempool_id".to_string(), id.to_string())];
+        assert_eq!(
+            counters,
+            vec![
+                (
+                    "mempool_ops_bundled".to_string(),
+                    labels.clone(),
+                    DebugValue::Counter(1)
+                ),
+                (
+                    "mempool_ops_simulated".to_string(),
+                    labels,
+                    DebugValue::Counter(2)
+                ),
+            ]
+        );
+    }
 }
@@ -11,24 +11,55 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
 
+use alloy_eips::eip2930::{AccessList, AccessListItem};
 #[cfg(feature = "test-utils")]
 use alloy_primitives::uint;
-use alloy_primitives::{Address, B256, U256};
-#[cfg(feature = "test-utils")]
+use alloy_primitives::{Address, Bytes, Log, B256, U256};
+#[cfg(any(test, feature = "test-utils"))]
 use mockall::automock;
-use rundler_provider::ProviderError;
+use rundler_provider::{AggregatorSimOut, BlockId, ProviderError};
 use rundler_types::{
-    pool::{MempoolError, SimulationViolation},
-    EntityInfos, ExpectedStorage, UserOperation, ValidTimeRange,
+    pool::{MempoolError, NeedsStakeInformation, SimulationViolation},
+    Entity, EntityInfos, EntityInfosSnapshot, EntityType, ExpectedStorage, UserOperation,
+    ValidTimeRange, ValidationOutput,
 };
 
+mod canary;
+pub use canary::{CanarySettings, CanarySimulator};
+
 mod context;
 pub use context::ValidationContextProvider;
 
+#[cfg(any(test, feature = "test-utils"))]
+mod fault;
+#[cfg(any(test, feature = "test-utils"))]
+pub use fault::{FaultInjectingSimulator, SimulationFault};
+
+#[cfg(feature = "test-utils")]
+mod fork;
+#[cfg(feature = "test-utils")]
+pub use fork::{fork_simulator, ForkSimulator};
+
+mod historical;
+pub use historical::simulate_at_block_number;
+
+mod limiter;
+
 mod mempool;
-pub use mempool::{MempoolConfig, MempoolConfigs};
+pub use mempool::{MempoolConfig, MempoolConfigs, MempoolEvaluation, MempoolMetrics};
+
+mod observer;
+pub use observer::{SimulationObserver, SimulationSummary};
+
+#[cfg(any(test, feature = "test-utils"))]
+mod parity;
+#[cfg(any(test, feature = "test-utils"))]
+pub use parity::{diff_results, simulate_and_diff, SimulationDiff, VersionDiff};
 
 mod simulator;
 pub use simulator::{new_v0_6_simulator, new_v0_7_simulator, SimulatorImpl};
@@ -68,6 +99,187 @@ pub struct SimulationResult {
     pub requires_post_op: bool,
     /// All the entities used in this operation and their staking state
     pub entity_infos: EntityInfos,
+    /// Output of the signature aggregator used by this operation, if one was
+    /// simulated. Only set by a simulator with a registered mock aggregator.
+    pub aggregator: Option<AggregatorSimOut>,
+    /// Whether the same address is used for more than one entity role
+    pub has_entity_role_overlap: bool,
+    /// The decoded output of the entry point's validation call. Only set if the
+    /// simulator is configured to include it.
+    pub validation_output: Option<ValidationOutput>,
+    /// The pre-op gas broken down by phase. Only set if the simulator is configured
+    /// to include it.
+    pub gas_breakdown: Option<GasBreakdown>,
+    /// The logs emitted by entities during validation, in emission order, including
+    /// logs from calls that later reverted. Only set if the simulator is configured to
+    /// include them.
+    pub validation_logs: Option<Vec<Log>>,
+    /// The entities that need more stake, from violations tolerated by the supporting
+    /// mempools. Use [`stake_shortfalls`] on a simulation error's violations otherwise.
+    pub stake_shortfalls: Vec<EntityStakeShortfall>,
+}
+
+/// How far an entity that needs stake is from the configured minimums
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntityStakeShortfall {
+    /// The entity needing stake
+    pub entity: Entity,
+    /// The entity's current stake
+    pub current_stake: U256,
+    /// The minimum stake
+    pub required_stake: U256,
+    /// The entity's current unstake delay, in seconds
+    pub current_delay: u32,
+    /// The minimum unstake delay, in seconds
+    pub required_delay: u32,
+}
+
+impl EntityStakeShortfall {
+    /// The stake the entity must add to reach the minimum
+    pub fn missing_stake(&self) -> U256 {
+        self.required_stake.saturating_sub(self.current_stake)
+    }
+}
+
+impl From<&NeedsStakeInformation> for EntityStakeShortfall {
+    fn from(info: &NeedsStakeInformation) -> Self {
+        Self {
+            entity: info.needs_stake,
+            current_stake: info.current_stake,
+            required_stake: info.min_stake,
+            current_delay: info.current_unstake_delay,
+            required_delay: info.min_unstake_delay,
+        }
+    }
+}
+
+/// Returns the stake shortfall of each entity that failed validation because it needs
+/// to be staked, once per entity
+pub fn stake_shortfalls(violations: &[SimulationViolation]) -> Vec<EntityStakeShortfall> {
+    let mut shortfalls: Vec<EntityStakeShortfall> = vec![];
+    for violation in violations
+        .iter()
+        .cloned()
+        .flat_map(SimulationViolation::flatten)
+    {
+        if let SimulationViolation::NotStaked(info) = violation {
+            if !shortfalls.iter().any(|s| s.entity == info.needs_stake) {
+                shortfalls.push(info.as_ref().into());
+            }
+        }
+    }
+    shortfalls
+}
+
+/// The pre-op gas of a simulation broken down by the phase that used it, as measured
+/// at the entry point's calls into each entity
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GasBreakdown {
+    /// Gas used by the factory deploying the sender
+    pub factory_gas: u128,
+    /// Gas used by the account's `validateUserOp`
+    pub account_validation_gas: u128,
+    /// Gas used by the paymaster's `validatePaymasterUserOp`
+    pub paymaster_validation_gas: u128,
+    /// The remaining pre-op gas: the pre-verification gas and the entry point's own
+    /// validation overhead
+    pub other_gas: u128,
+}
+
+impl GasBreakdown {
+    /// The total gas of all phases, equal to the pre-op gas it was broken down from
+    pub fn total(&self) -> u128 {
+        self.factory_gas
+            + self.account_validation_gas
+            + self.paymaster_validation_gas
+            + self.other_gas
+    }
+}
+
+impl SimulationResult {
+    /// The address paying for the operation: its paymaster if it has one, otherwise
+    /// the sender.
+    pub fn sponsor(&self) -> Address {
+        self.entity_infos
+            .paymaster
+            .unwrap_or(self.entity_infos.sender)
+            .address()
+    }
+
+    /// A JSON summary of this result for debugging, including the staking state of
+    /// each entity as used by the simulator.
+    pub fn debug_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "mempools": self.mempools,
+            "preOpGas": U256::from(self.pre_op_gas),
+            "validAfter": self.valid_time_range.valid_after.seconds_since_epoch(),
+            "validUntil": self.valid_time_range.valid_until.seconds_since_epoch(),
+            "codeHash": self.code_hash,
+            "accountIsStaked": self.account_is_staked,
+            "requiresPostOp": self.requires_post_op,
+            "entityInfos": EntityInfosSnapshot::from(self.entity_infos),
+            "validationOutput": self.validation_output,
+        })
+    }
+
+    /// An EIP-2930 access list of the addresses and storage slots read during
+    /// validation, which can be attached to the bundle transaction to pre-warm them.
+    ///
+    /// Items are sorted by address and storage keys by slot.
+    pub fn to_access_list(&self) -> AccessList {
+        let mut items: BTreeMap<Address, Vec<B256>> = self
+            .accessed_addresses
+            .iter()
+            .chain(&self.associated_addresses)
+            .map(|&address| (address, vec![]))
+            .collect();
+        for (&address, slots) in &self.expected_storage.0 {
+            items
+                .entry(address)
+                .or_default()
+                .extend(slots.keys().copied());
+        }
+
+        AccessList(
+            items
+                .into_iter()
+                .map(|(address, storage_keys)| AccessListItem {
+                    address,
+                    storage_keys,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Returns every entity of the given included ops and their simulation results, for
+/// updating reputation after a bundle mines.
+///
+/// Entities are deduplicated by address and type, in order of first appearance.
+pub fn included_entities<'a, UO: UserOperation + 'a>(
+    ops: impl IntoIterator<Item = (&'a UO, &'a SimulationResult)>,
+) -> Vec<(Address, EntityType)> {
+    let mut seen = HashSet::new();
+    let mut entities = vec![];
+    for (op, result) in ops {
+        let from_op = [
+            Some((op.sender(), EntityType::Account)),
+            op.factory().map(|a| (a, EntityType::Factory)),
+            op.paymaster().map(|a| (a, EntityType::Paymaster)),
+            op.aggregator().map(|a| (a, EntityType::Aggregator)),
+        ];
+        // the simulation may know of entities the op doesn't name, e.g. a mock aggregator
+        let from_result = result
+            .entity_infos
+            .entities()
+            .map(|(t, info)| (info.address(), t));
+        for entity in from_op.into_iter().flatten().chain(from_result) {
+            if seen.insert(entity) {
+                entities.push(entity);
+            }
+        }
+    }
+    entities
 }
 
 /// The result of a failed simulation. We return a list of the violations that ocurred during the failed simulation
@@ -128,22 +340,83 @@ impl From<ProviderError> for SimulationError {
     }
 }
 
+/// The result of simulating only the factory call of an operation's init code
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FactorySimResult {
+    /// Address of the account created by the factory
+    pub address: Address,
+    /// Gas used by the factory call
+    pub gas: u128,
+    /// Hash of the code of the created account
+    pub code_hash: B256,
+}
+
+/// The block to run a simulation against
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BlockTag {
+    /// The latest block
+    #[default]
+    Latest,
+    /// The pending block, including the state changes of pending transactions
+    Pending,
+    /// The block with the given hash
+    Hash(B256),
+}
+
+impl From<B256> for BlockTag {
+    fn from(block_hash: B256) -> Self {
+        Self::Hash(block_hash)
+    }
+}
+
+impl From<Option<B256>> for BlockTag {
+    fn from(block_hash: Option<B256>) -> Self {
+        block_hash.map_or(Self::Latest, Self::Hash)
+    }
+}
+
+impl From<BlockTag> for BlockId {
+    fn from(block: BlockTag) -> Self {
+        match block {
+            BlockTag::Latest => BlockId::latest(),
+            BlockTag::Pending => BlockId::pending(),
+            BlockTag::Hash(block_hash) => block_hash.into(),
+        }
+    }
+}
+
 /// Simulator trait for running user operation simulations
-#[cfg_attr(feature = "test-utils", automock(type UO = rundler_types::v0_6::UserOperation;))]
+#[cfg_attr(any(test, feature = "test-utils"), automock(type UO = rundler_types::v0_6::UserOperation;))]
 #[async_trait::async_trait]
 pub trait Simulator: Send + Sync {
     /// The type of user operation that this simulator can handle
     type UO: UserOperation;
 
-    /// Simulate a user operation, returning simulation information
+    /// Simulate a user operation against `block`, returning simulation information
     /// upon success, or simulation violations.
     async fn simulate_validation(
         &self,
         op: Self::UO,
         trusted: bool,
-        block_hash: B256,
+        block: BlockTag,
         expected_code_hash: Option<B256>,
     ) -> Result<SimulationResult, SimulationError>;
+
+    /// Evaluate a user operation against `block` and report, for each configured
+    /// mempool, whether it supports the operation and which violations excluded it.
+    async fn evaluate_mempools(
+        &self,
+        op: Self::UO,
+        block: BlockTag,
+    ) -> Result<Vec<MempoolEvaluation>, SimulationError>;
+
+    /// Simulate only the account creation of `init_code` against `block`, independent of
+    /// the rest of validation, returning the created account and the gas used.
+    async fn simulate_factory(
+        &self,
+        init_code: Bytes,
+        block: BlockTag,
+    ) -> Result<FactorySimResult, SimulationError>;
 }
 
 /// Simulation Settings
@@ -160,6 +433,99 @@ pub struct Settings {
     pub tracer_timeout: String,
     /// If set, allows the simulator to fallback to unsafe mode if the simulation tracer fails
     pub enable_unsafe_fallback: bool,
+    /// The maximum size, in bytes, of a packed user operation. Larger operations are rejected
+    /// before simulation.
+    pub max_op_calldata_bytes: usize,
+    /// Senders whose operations skip the ERC-7562 storage and opcode rules during
+    /// simulation. Gas and the valid time range are still measured.
+    ///
+    /// SECURITY: operations from these senders are not checked for mass invalidation
+    /// vectors. A trusted sender can get ops into the mempool that invalidate on chain,
+    /// causing the bundler to pay for reverted bundles. Only list senders that are
+    /// fully under the operator's control.
+    pub trusted_senders: HashSet<Address>,
+    /// The maximum number of simulations that may be in flight at once across all
+    /// simulators in the process. Simulations beyond the limit wait for a permit.
+    /// If `None`, simulations are unbounded.
+    pub max_concurrent_simulations: Option<usize>,
+    /// The maximum number of accessed contracts whose code contributes to an operation's
+    /// code hash. Contracts are chosen by ascending address, so code changes in contracts
+    /// past the limit are not detected. Must be the same for the pool and the builder.
+    /// If `None`, all accessed contracts are hashed.
+    pub code_hash_addresses_limit: Option<usize>,
+    /// Whether the BASEFEE opcode is allowed during validation. Set from the chain spec.
+    pub allow_basefee: bool,
+    /// Whether the ORIGIN opcode is allowed during validation. Set from the chain spec.
+    pub allow_origin: bool,
+    /// The minimum max priority fee per gas an operation must pay, checked before
+    /// simulation. Set from the chain spec.
+    pub min_priority_fee_per_gas: u128,
+    /// Paymasters whose operations are rejected before simulation
+    pub denied_paymasters: HashSet<Address>,
+    /// Factories whose operations are rejected before simulation
+    pub denied_factories: HashSet<Address>,
+    /// Whether operations with a zero call gas limit are rejected before simulation.
+    /// Such operations are valid per the spec but execute nothing.
+    pub reject_zero_call_gas: bool,
+    /// Whether entities may read storage outside their own or the sender's associated
+    /// slots without being staked [STO-033]. Off by default, matching the strict rules.
+    pub allow_unstaked_associated_reads: bool,
+    /// Whether a factory at the sender's address, as used by some vanity deploy patterns,
+    /// may access the sender's associated storage during the deploy without being staked
+    /// [STO-022]. Off by default.
+    pub allow_sender_factory_associated_storage: bool,
+    /// If set, operations whose pre-verification gas is below their calldata gas cost
+    /// plus this overhead are rejected before simulation
+    pub min_pre_verification_gas_overhead: Option<u128>,
+    /// Chain system contracts that validation may DELEGATECALL into without violating
+    /// the precompile and undeployed contract rules. Set from the chain spec.
+    pub allowed_delegatecall_targets: HashSet<Address>,
+    /// CREATE2 deployers that a factory may deploy through, whose storage the factory
+    /// may access without stake. Set from the chain spec.
+    pub allowed_create2_deployers: HashSet<Address>,
+    /// Whether a zero code hash, meaning validation accessed no contracts, is a
+    /// violation. Off by default.
+    pub reject_empty_code_hash: bool,
+    /// Whether simulation results carry the decoded validation output returned by the
+    /// entry point, for debugging. Off by default.
+    pub include_validation_output: bool,
+    /// The maximum depth of nested external calls an entity may make during validation,
+    /// where calls made by the entry point are at depth 1
+    pub max_validation_call_depth: u64,
+    /// If nonzero, staked entities that have started unstaking and whose stake can be
+    /// withdrawn within this many seconds are flagged as having stake expiring soon
+    pub stake_expiry_warning_window_secs: u64,
+    /// Whether the provider reads made after tracing, the code hash and any deposit
+    /// info, are sent as a single JSON-RPC batch request. Off by default.
+    pub batch_state_reads: bool,
+    /// Whether simulation results include the pre-op gas broken down by phase
+    pub include_gas_breakdown: bool,
+    /// Whether simulation results include the logs emitted during validation
+    pub include_validation_logs: bool,
+    /// Whether an op with a factory is rejected if the factory doesn't create a
+    /// contract at the op's sender address. Off by default.
+    pub require_factory_deploys_sender: bool,
+    /// Whether an entity accessing storage associated with an address other than the
+    /// op's sender, its entities, or the entry point is a violation. Off by default.
+    pub reject_cross_sender_associated_storage: bool,
+    /// How far past the time of simulation an operation's validAfter may be. Operations
+    /// that only become valid later are rejected rather than held in the mempool.
+    pub max_valid_after_horizon: Duration,
+    /// Whether checking the code size or hash of an address with no code, other than the
+    /// addresses deployed by the operation itself, is a violation. The result of such a
+    /// check depends on whether an earlier operation in the bundle deploys there. Off by
+    /// default.
+    pub reject_undeployed_ext_code_access: bool,
+    /// If set, operations without an aggregator whose signature is shorter than this many
+    /// bytes are rejected before simulation, as they can't pass validation
+    pub min_signature_length: Option<usize>,
+    /// Maximum number of storage slots captured in an operation's expected storage.
+    /// Operations whose validation touches more slots are rejected, bounding the memory
+    /// each operation holds in the pool.
+    pub max_expected_storage_slots: usize,
+    /// Whether ops simulated, accepted, bundled and dropped are counted per mempool ID.
+    /// Adds a series per configured mempool to each of these metrics. Off by default.
+    pub per_mempool_metrics: bool,
 }
 
 #[cfg(any(test, feature = "test-utils"))]
@@ -172,6 +538,232 @@ impl Default for Settings {
             min_stake_value: uint!(1_000_000_000_000_000_000_U256),
             tracer_timeout: "10s".to_string(),
             enable_unsafe_fallback: false,
+            max_op_calldata_bytes: 65536,
+            trusted_senders: HashSet::new(),
+            max_concurrent_simulations: None,
+            code_hash_addresses_limit: None,
+            allow_basefee: false,
+            allow_origin: false,
+            min_priority_fee_per_gas: 0,
+            denied_paymasters: HashSet::new(),
+            denied_factories: HashSet::new(),
+            reject_zero_call_gas: false,
+            allow_unstaked_associated_reads: false,
+            allow_sender_factory_associated_storage: false,
+            min_pre_verification_gas_overhead: None,
+            allowed_delegatecall_targets: HashSet::new(),
+            allowed_create2_deployers: HashSet::new(),
+            reject_empty_code_hash: false,
+            include_validation_output: false,
+            max_validation_call_depth: 64,
+            stake_expiry_warning_window_secs: 0,
+            batch_state_reads: false,
+            include_gas_breakdown: false,
+            include_validation_logs: false,
+            require_factory_deploys_sender: false,
+            reject_cross_sender_associated_storage: false,
+            // 30 days
+            max_valid_after_horizon: Duration::from_secs(30 * 24 * 60 * 60),
+            reject_undeployed_ext_code_access: false,
+            min_signature_length: None,
+            max_expected_storage_slots: 10_000,
+            per_mempool_metrics: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::{
+        chain::ChainSpec,
+        v0_6::{UserOperationBuilder, UserOperationRequiredFields},
+        StakeInfo, ValidationReturnInfo,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_block_tag_conversions() {
+        let block_hash = B256::repeat_byte(1);
+        assert_eq!(BlockTag::from(block_hash), BlockTag::Hash(block_hash));
+        assert_eq!(BlockTag::from(Some(block_hash)), BlockTag::Hash(block_hash));
+        assert_eq!(BlockTag::from(None), BlockTag::Latest);
+
+        assert_eq!(BlockId::from(BlockTag::Latest), BlockId::latest());
+        assert_eq!(BlockId::from(BlockTag::Pending), BlockId::pending());
+        assert_eq!(
+            BlockId::from(BlockTag::Hash(block_hash)),
+            BlockId::from(block_hash)
+        );
+    }
+
+    #[test]
+    fn test_included_entities() {
+        let chain_spec = ChainSpec::default();
+        let factory = Address::repeat_byte(1);
+        let paymaster = Address::repeat_byte(2);
+        let aggregator = Address::repeat_byte(3);
+        let (sender_1, sender_2) = (Address::repeat_byte(4), Address::repeat_byte(5));
+        let op = |sender: Address, init_code: Bytes| {
+            UserOperationBuilder::new(
+                &chain_spec,
+                UserOperationRequiredFields {
+                    sender,
+                    init_code,
+                    paymaster_and_data: paymaster.to_vec().into(),
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+        let op_1 = op(sender_1, factory.to_vec().into());
+        let op_2 = op(sender_2, Bytes::new());
+
+        let mut entity_infos_1 = EntityInfos::default();
+        entity_infos_1.set_sender(sender_1, false);
+        entity_infos_1.set_factory(factory, false);
+        entity_infos_1.set_paymaster(paymaster, true);
+        let mut entity_infos_2 = EntityInfos::default();
+        entity_infos_2.set_sender(sender_2, false);
+        entity_infos_2.set_paymaster(paymaster, true);
+        entity_infos_2.set_aggregator(aggregator);
+        let result_1 = SimulationResult {
+            entity_infos: entity_infos_1,
+            ..Default::default()
+        };
+        let result_2 = SimulationResult {
+            entity_infos: entity_infos_2,
+            ..Default::default()
+        };
+
+        // the shared paymaster is listed once
+        assert_eq!(
+            included_entities([(&op_1, &result_1), (&op_2, &result_2)]),
+            vec![
+                (sender_1, EntityType::Account),
+                (factory, EntityType::Factory),
+                (paymaster, EntityType::Paymaster),
+                (sender_2, EntityType::Account),
+                (aggregator, EntityType::Aggregator),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sponsor() {
+        let sender = Address::repeat_byte(1);
+        let paymaster = Address::repeat_byte(2);
+        let mut result = SimulationResult::default();
+        result.entity_infos.set_sender(sender, false);
+        assert_eq!(result.sponsor(), sender);
+
+        result.entity_infos.set_paymaster(paymaster, false);
+        assert_eq!(result.sponsor(), paymaster);
+    }
+
+    #[test]
+    fn test_debug_json_entity_infos() {
+        let sender = Address::repeat_byte(1);
+        let paymaster = Address::repeat_byte(2);
+        let mut entity_infos = EntityInfos::default();
+        entity_infos.set_sender(sender, false);
+        entity_infos.set_paymaster(paymaster, true);
+        let result = SimulationResult {
+            entity_infos,
+            ..Default::default()
+        };
+
+        let json = result.debug_json();
+        assert_eq!(
+            json["entityInfos"],
+            serde_json::json!({
+                "sender": { "address": format!("{sender:?}"), "isStaked": false },
+                "factory": null,
+                "paymaster": { "address": format!("{paymaster:?}"), "isStaked": true },
+                "aggregator": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_debug_json_validation_output() {
+        assert_eq!(
+            SimulationResult::default().debug_json()["validationOutput"],
+            serde_json::Value::Null
+        );
+
+        let result = SimulationResult {
+            validation_output: Some(ValidationOutput {
+                return_info: ValidationReturnInfo {
+                    pre_op_gas: 1000,
+                    account_sig_failed: false,
+                    paymaster_sig_failed: true,
+                    valid_after: 1.into(),
+                    valid_until: 100.into(),
+                    paymaster_context: Bytes::default(),
+                },
+                sender_info: StakeInfo {
+                    stake: U256::from(10),
+                    unstake_delay_sec: 5,
+                },
+                factory_info: StakeInfo::default(),
+                paymaster_info: StakeInfo::default(),
+                aggregator_info: None,
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            result.debug_json()["validationOutput"],
+            serde_json::json!({
+                "returnInfo": {
+                    "preOpGas": 1000,
+                    "accountSigFailed": false,
+                    "paymasterSigFailed": true,
+                    "validAfter": "0x1",
+                    "validUntil": "0x64",
+                    "paymasterContext": "0x",
+                },
+                "senderInfo": { "stake": "0xa", "unstakeDelaySec": 5 },
+                "factoryInfo": { "stake": "0x0", "unstakeDelaySec": 0 },
+                "paymasterInfo": { "stake": "0x0", "unstakeDelaySec": 0 },
+                "aggregatorInfo": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_access_list() {
+        let accessed = Address::repeat_byte(1);
+        let associated = Address::repeat_byte(2);
+        let stored = Address::repeat_byte(3);
+        let mut expected_storage = ExpectedStorage::default();
+        expected_storage.insert(stored, U256::from(2), U256::from(20));
+        expected_storage.insert(stored, U256::from(1), U256::from(10));
+        expected_storage.insert(accessed, U256::from(5), U256::from(50));
+        let result = SimulationResult {
+            accessed_addresses: HashSet::from([accessed, stored]),
+            associated_addresses: HashSet::from([associated]),
+            expected_storage,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            result.to_access_list(),
+            AccessList(vec![
+                AccessListItem {
+                    address: accessed,
+                    storage_keys: vec![B256::from(U256::from(5))],
+                },
+                AccessListItem {
+                    address: associated,
+                    storage_keys: vec![],
+                },
+                AccessListItem {
+                    address: stored,
+                    storage_keys: vec![B256::from(U256::from(1)), B256::from(U256::from(2))],
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,41 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{fmt::Debug, time::Duration};
+
+use alloy_primitives::B256;
+
+use super::{BlockTag, SimulationError, SimulationResult};
+
+/// Read-only summary of a single simulation
+#[derive(Debug)]
+pub struct SimulationSummary<'a> {
+    /// Hash of the simulated user operation
+    pub op_hash: B256,
+    /// Block the simulation ran against
+    pub block: BlockTag,
+    /// Time taken by the simulation, including waiting for a simulation permit
+    pub duration: Duration,
+    /// The simulation result. Gas is found in the successful result, violations in the error.
+    pub result: &'a Result<SimulationResult, SimulationError>,
+}
+
+/// Observer called with the outcome of every simulation, for exporting
+/// simulation events to external systems.
+///
+/// Called on the simulating task, implementations should return quickly and
+/// hand off any slow work.
+pub trait SimulationObserver: Send + Sync + Debug {
+    /// Called after each simulation completes
+    fn on_simulation(&self, summary: &SimulationSummary<'_>);
+}
@@ -0,0 +1,255 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use alloy_primitives::B256;
+use rundler_types::{pool::SimulationViolation, v0_6, v0_7, Entity, ValidTimeRange};
+use rundler_utils::math;
+
+use super::{BlockTag, SimulationError, SimulationResult, Simulator};
+use crate::ViolationError;
+
+/// A value that differs between the v0.6 and v0.7 simulations of the same logical op
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionDiff<T> {
+    /// The value from the v0.6 simulation
+    pub v0_6: T,
+    /// The value from the v0.7 simulation
+    pub v0_7: T,
+}
+
+/// The differences between the v0.6 and v0.7 simulations of the same logical op.
+///
+/// Each field is `None` if the two versions agree on it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimulationDiff {
+    /// One version succeeded while the other failed
+    pub succeeded: Option<VersionDiff<bool>>,
+    /// The pre-op gas differs by more than the tolerance. Only compared if both succeeded.
+    pub pre_op_gas: Option<VersionDiff<u128>>,
+    /// The entities that failed validation because they need to be staked, sorted
+    pub entities_needing_stake: Option<VersionDiff<Vec<Entity>>>,
+    /// The mempools that support the op, sorted
+    pub mempools: Option<VersionDiff<Vec<B256>>>,
+    /// The valid time range. Only compared if both succeeded.
+    pub valid_time_range: Option<VersionDiff<ValidTimeRange>>,
+    /// Whether the op requires a post-op. Only compared if both succeeded.
+    pub requires_post_op: Option<VersionDiff<bool>>,
+}
+
+impl SimulationDiff {
+    /// Returns true if the two versions agree
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Simulates equivalent v0.6 and v0.7 ops against `block` and returns the differences
+/// between the results, for catching version-specific regressions.
+///
+/// Pre-op gas is allowed to differ by `gas_tolerance_percent` of the v0.6 value, since
+/// the two entry points do different work around validation.
+pub async fn simulate_and_diff<S6, S7>(
+    v0_6_simulator: &S6,
+    v0_6_op: v0_6::UserOperation,
+    v0_7_simulator: &S7,
+    v0_7_op: v0_7::UserOperation,
+    block: BlockTag,
+    gas_tolerance_percent: u32,
+) -> SimulationDiff
+where
+    S6: Simulator<UO = v0_6::UserOperation>,
+    S7: Simulator<UO = v0_7::UserOperation>,
+{
+    let (v0_6_result, v0_7_result) = tokio::join!(
+        v0_6_simulator.simulate_validation(v0_6_op, false, block, None),
+        v0_7_simulator.simulate_validation(v0_7_op, false, block, None),
+    );
+    diff_results(&v0_6_result, &v0_7_result, gas_tolerance_percent)
+}
+
+/// Returns the differences between the results of simulating the v0.6 and v0.7 versions
+/// of the same logical op
+pub fn diff_results(
+    v0_6: &Result<SimulationResult, SimulationError>,
+    v0_7: &Result<SimulationResult, SimulationError>,
+    gas_tolerance_percent: u32,
+) -> SimulationDiff {
+    let mut diff = SimulationDiff {
+        succeeded: compare(v0_6.is_ok(), v0_7.is_ok()),
+        entities_needing_stake: compare(entities_needing_stake(v0_6), entities_needing_stake(v0_7)),
+        mempools: compare(mempools(v0_6), mempools(v0_7)),
+        ..Default::default()
+    };
+
+    if let (Ok(v0_6), Ok(v0_7)) = (v0_6, v0_7) {
+        let tolerance = math::percent(v0_6.pre_op_gas, gas_tolerance_percent);
+        if v0_6.pre_op_gas.abs_diff(v0_7.pre_op_gas) > tolerance {
+            diff.pre_op_gas = Some(VersionDiff {
+                v0_6: v0_6.pre_op_gas,
+                v0_7: v0_7.pre_op_gas,
+            });
+        }
+        diff.valid_time_range = compare(v0_6.valid_time_range, v0_7.valid_time_range);
+        diff.requires_post_op = compare(v0_6.requires_post_op, v0_7.requires_post_op);
+    }
+
+    diff
+}
+
+fn compare<T: PartialEq>(v0_6: T, v0_7: T) -> Option<VersionDiff<T>> {
+    (v0_6 != v0_7).then_some(VersionDiff { v0_6, v0_7 })
+}
+
+fn entities_needing_stake(result: &Result<SimulationResult, SimulationError>) -> Vec<Entity> {
+    let Err(SimulationError {
+        violation_error: ViolationError::Violations(violations),
+        ..
+    }) = result
+    else {
+        return vec![];
+    };
+    let mut entities = violations
+        .iter()
+        .cloned()
+        .flat_map(SimulationViolation::flatten)
+        .filter_map(|v| match v {
+            SimulationViolation::NotStaked(info) => Some(info.needs_stake),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    entities.sort();
+    entities.dedup();
+    entities
+}
+
+fn mempools(result: &Result<SimulationResult, SimulationError>) -> Vec<B256> {
+    let mut mempools = result
+        .as_ref()
+        .map(|r| r.mempools.clone())
+        .unwrap_or_default();
+    mempools.sort();
+    mempools
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Address, U256};
+    use rundler_types::{pool::NeedsStakeInformation, EntityType, Timestamp};
+
+    use super::*;
+
+    fn success(pre_op_gas: u128, mempools: Vec<B256>) -> Result<SimulationResult, SimulationError> {
+        Ok(SimulationResult {
+            pre_op_gas,
+            mempools,
+            ..Default::default()
+        })
+    }
+
+    fn not_staked(entities: Vec<Entity>) -> Result<SimulationResult, SimulationError> {
+        Err(ViolationError::Violations(
+            entities
+                .into_iter()
+                .map(|needs_stake| {
+                    SimulationViolation::NotStaked(Box::new(NeedsStakeInformation {
+                        needs_stake,
+                        accessing_entity: EntityType::Account,
+                        accessed_entity: None,
+                        accessed_address: Address::ZERO,
+                        slot: U256::ZERO,
+                        min_stake: U256::ZERO,
+                        min_unstake_delay: 0,
+                        current_stake: U256::ZERO,
+                        current_unstake_delay: 0,
+                    }))
+                })
+                .collect(),
+        )
+        .into())
+    }
+
+    #[test]
+    fn test_diff_results_agree() {
+        let (a, b) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        // mempool order and pre-op gas within tolerance are ignored
+        let diff = diff_results(
+            &success(100_000, vec![a, b]),
+            &success(105_000, vec![b, a]),
+            10,
+        );
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_results_success() {
+        let mempool = B256::repeat_byte(1);
+        let mut v0_7 = success(150_000, vec![]);
+        if let Ok(r) = &mut v0_7 {
+            r.requires_post_op = true;
+            r.valid_time_range = ValidTimeRange::new(Timestamp::new(0), Timestamp::new(100));
+        }
+
+        let diff = diff_results(&success(100_000, vec![mempool]), &v0_7, 10);
+        assert_eq!(
+            diff,
+            SimulationDiff {
+                pre_op_gas: Some(VersionDiff {
+                    v0_6: 100_000,
+                    v0_7: 150_000,
+                }),
+                mempools: Some(VersionDiff {
+                    v0_6: vec![mempool],
+                    v0_7: vec![],
+                }),
+                valid_time_range: Some(VersionDiff {
+                    v0_6: ValidTimeRange::default(),
+                    v0_7: ValidTimeRange::new(Timestamp::new(0), Timestamp::new(100)),
+                }),
+                requires_post_op: Some(VersionDiff {
+                    v0_6: false,
+                    v0_7: true,
+                }),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_diff_results_needs_stake() {
+        let paymaster = Entity::paymaster(Address::repeat_byte(1));
+        let factory = Entity::factory(Address::repeat_byte(2));
+
+        let diff = diff_results(
+            &not_staked(vec![paymaster, factory]),
+            &not_staked(vec![factory, paymaster, factory]),
+            10,
+        );
+        assert!(diff.is_empty());
+
+        let diff = diff_results(&success(100_000, vec![]), &not_staked(vec![paymaster]), 10);
+        assert_eq!(
+            diff,
+            SimulationDiff {
+                succeeded: Some(VersionDiff {
+                    v0_6: true,
+                    v0_7: false,
+                }),
+                entities_needing_stake: Some(VersionDiff {
+                    v0_6: vec![],
+                    v0_7: vec![paymaster],
+                }),
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -14,32 +14,49 @@
 use std::{
     collections::{HashMap, HashSet},
     marker::PhantomData,
+    sync::Arc,
+    time::Instant,
 };
 
-use alloy_primitives::{Address, B256, U256};
+use alloy_primitives::{keccak256, Address, Bytes, Log, B256, U256};
+use alloy_sol_types::SolCall;
+use anyhow::Context;
 use async_trait::async_trait;
 use futures_util::TryFutureExt;
-use rundler_provider::{EntryPoint, EvmProvider, SimulationProvider};
+use rundler_contracts::{utils::GetCodeHashes, v0_7::IEntryPoint::getDepositInfoCall};
+use rundler_provider::{
+    AccountOverride, BlockId, EntryPoint, EvmCall, EvmProvider, GethDebugBuiltInTracerType,
+    GethDebugTracerCallFrame, GethDebugTracerType, GethDebugTracingCallOptions,
+    GethDebugTracingOptions, GethTrace, SimulationProvider, StateOverride, TransactionBuilder,
+    TransactionRequest,
+};
 use rundler_types::{
     pool::{NeedsStakeInformation, SimulationViolation},
     v0_6::UserOperation as UserOperationV0_6,
     v0_7::UserOperation as UserOperationV0_7,
-    Entity, EntityInfo, EntityInfos, EntityType, Opcode, StorageSlot, UserOperation,
+    Entity, EntityInfo, EntityInfos, EntityType, Opcode, StorageSlot, Timestamp, UserOperation,
     ValidTimeRange, ValidationOutput, ValidationReturnInfo, ViolationOpCode,
 };
 
 use super::{
     context::{
-        self, AccessInfo, AssociatedSlotsByAddress, ValidationContext, ValidationContextProvider,
+        self, AccessInfo, AssociatedSlotsByAddress, Phase, ValidationContext,
+        ValidationContextProvider,
     },
+    limiter::SimulationLimiter,
     UnsafeSimulator,
 };
 use crate::{
     simulation::{
-        mempool::{self, AllowEntity, AllowRule, MempoolConfig, MempoolMatchResult},
+        mempool::{
+            self, AllowEntity, AllowRule, MempoolConfig, MempoolEvaluation, MempoolMatchResult,
+            MempoolMetrics,
+        },
+        stake_shortfalls,
         v0_6::ValidationContextProvider as ValidationContextProviderV0_6,
         v0_7::ValidationContextProvider as ValidationContextProviderV0_7,
-        Settings, Simulator,
+        BlockTag, FactorySimResult, GasBreakdown, Settings, SimulationObserver, SimulationSummary,
+        Simulator,
     },
     types::ViolationError,
     SimulationError, SimulationResult,
@@ -94,8 +111,12 @@ where
 /// If a mempool is found to support all of the associated violations,
 /// it will be included in the list of mempools returned by the simulator.
 ///
-/// If no mempools are found, the simulator will return an error containing
-/// the violations.
+/// If no mempools are found, the simulator will return an
+/// `UnsupportedByAllMempools` error containing the violations that excluded
+/// the operation from each mempool.
+///
+/// When the canonical mempool is the only one configured, allowlists are not evaluated:
+/// an operation without violations is matched to the canonical mempool directly.
 #[derive(Debug)]
 pub struct SimulatorImpl<UO, P, E, V> {
     provider: P,
@@ -103,8 +124,14 @@ pub struct SimulatorImpl<UO, P, E, V> {
     validation_context_provider: V,
     sim_settings: Settings,
     mempool_configs: HashMap<B256, MempoolConfig>,
+    // set when the canonical mempool is the only one configured
+    canonical_mempool: Option<B256>,
     allow_unstaked_addresses: HashSet<Address>,
     unsafe_sim: UnsafeSimulator<UO, E>,
+    limiter: SimulationLimiter,
+    observer: Option<Arc<dyn SimulationObserver>>,
+    #[cfg(any(test, feature = "test-utils"))]
+    mock_aggregators: HashMap<Address, rundler_provider::AggregatorSimOut>,
     _uo_type: PhantomData<UO>,
 }
 
@@ -142,15 +169,57 @@ where
         Self {
             provider,
             unsafe_sim: UnsafeSimulator::new(entry_point.clone(), sim_settings.clone()),
+            limiter: SimulationLimiter::new(sim_settings.max_concurrent_simulations),
             entry_point,
             validation_context_provider,
             sim_settings,
+            canonical_mempool: mempool::canonical_only_mempool(&mempool_configs),
             mempool_configs,
             allow_unstaked_addresses,
+            observer: None,
+            #[cfg(any(test, feature = "test-utils"))]
+            mock_aggregators: HashMap::new(),
             _uo_type: PhantomData,
         }
     }
 
+    /// Sets an observer that is called with the outcome of every simulation
+    pub fn with_observer(mut self, observer: Arc<dyn SimulationObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Registers a canned signature aggregator output, keyed by its aggregator address.
+    ///
+    /// Operations using the aggregator are simulated as if the entry point reported it,
+    /// and the output is returned on the simulation result without calling the
+    /// aggregator contract. For testing aggregator integrations only.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn with_mock_aggregator(mut self, out: rundler_provider::AggregatorSimOut) -> Self {
+        self.mock_aggregators.insert(out.address, out);
+        self
+    }
+
+    // Substitutes the registered mock output for the op's aggregator, if any
+    #[cfg(any(test, feature = "test-utils"))]
+    fn apply_mock_aggregator(
+        &self,
+        context: &mut ValidationContext<UO>,
+    ) -> Option<rundler_provider::AggregatorSimOut> {
+        let out = self.mock_aggregators.get(&context.op.aggregator()?)?;
+        let stake_info = context
+            .entry_point_out
+            .aggregator_info
+            .map(|info| info.stake_info)
+            .unwrap_or_default();
+        context.entry_point_out.aggregator_info = Some(rundler_types::AggregatorInfo {
+            address: out.address,
+            stake_info,
+        });
+        context.entity_infos.set_aggregator(out.address);
+        Some(out.clone())
+    }
+
     // Parse the output from tracing and return a list of violations.
     // Most violations found during this stage are allowlistable and can be added
     // to the list of allowlisted violations on a given mempool.
@@ -169,6 +238,26 @@ where
 
         let mut violations = vec![];
 
+        // Chain system contracts reached by DELEGATECALL are allowed without stake
+        let is_allowed_delegatecall = |address: &Address| {
+            self.sim_settings
+                .allowed_delegatecall_targets
+                .contains(address)
+                && tracer_out
+                    .accessed_contracts
+                    .get(address)
+                    .is_some_and(|info| info.opcode == Opcode::DELEGATECALL)
+        };
+
+        // Known CREATE2 deployers that factories deploy through are trusted with the factory
+        let is_allowed_create2_deployer = |kind: EntityType, address: &Address| {
+            kind == EntityType::Factory
+                && self
+                    .sim_settings
+                    .allowed_create2_deployers
+                    .contains(address)
+        };
+
         let sender_address = entity_infos.sender_address();
         for (index, phase) in tracer_out.phases.iter().enumerate().take(3) {
             let kind = context::entity_type_from_simulation_phase(index).unwrap();
@@ -183,6 +272,16 @@ where
                     continue;
                 }
 
+                // BASEFEE is allowed on chains where it is pinned
+                if self.sim_settings.allow_basefee && opcode == Opcode::BASEFEE {
+                    continue;
+                }
+
+                // ORIGIN is allowed on chains that explicitly permit it
+                if self.sim_settings.allow_origin && opcode == Opcode::ORIGIN {
+                    continue;
+                }
+
                 // [OP-011]
                 violations.push(SimulationViolation::UsedForbiddenOpcode(
                     ei.entity,
@@ -205,6 +304,9 @@ where
 
             for precompile in &phase.forbidden_precompiles_used {
                 let (contract, precompile) = context::parse_combined_context_str(precompile)?;
+                if is_allowed_delegatecall(&precompile) {
+                    continue;
+                }
                 // [OP-062]
                 violations.push(SimulationViolation::UsedForbiddenPrecompile(
                     ei.entity, contract, precompile,
@@ -214,6 +316,9 @@ where
             for (addr, access_info) in &phase.storage_accesses {
                 let address = *addr;
                 accessed_addresses.insert(address);
+                if is_allowed_create2_deployer(ei.entity.kind, &address) {
+                    continue;
+                }
 
                 let restrictions = parse_storage_accesses(ParseStorageAccess {
                     access_info,
@@ -223,6 +328,14 @@ where
                     entrypoint: *self.entry_point.address(),
                     has_factory,
                     entity: &ei.entity,
+                    entity_infos,
+                    allow_unstaked_reads: self.sim_settings.allow_unstaked_associated_reads,
+                    allow_sender_factory_associated_storage: self
+                        .sim_settings
+                        .allow_sender_factory_associated_storage,
+                    reject_cross_sender_associated_storage: self
+                        .sim_settings
+                        .reject_cross_sender_associated_storage,
                 });
 
                 for restriction in restrictions {
@@ -239,6 +352,7 @@ where
                                 .expect("entity type not found in entity_infos");
 
                             if !needs_stake_entity.is_staked {
+                                let current = context::stake_info(entry_point_out, ei.entity.kind);
                                 // [STO-*]
                                 violations.push(SimulationViolation::NotStaked(Box::new(
                                     NeedsStakeInformation {
@@ -249,6 +363,8 @@ where
                                         slot,
                                         min_stake: self.sim_settings.min_stake_value,
                                         min_unstake_delay: self.sim_settings.min_unstake_delay,
+                                        current_stake: current.stake,
+                                        current_unstake_delay: current.unstake_delay_sec,
                                     },
                                 )));
                             }
@@ -282,6 +398,13 @@ where
                                 StorageSlot { address, slot },
                             ))
                         }
+                        StorageRestriction::CrossSenderAssociated(associated_address, slot) => {
+                            violations.push(SimulationViolation::CrossSenderAssociatedStorage(
+                                ei.entity,
+                                associated_address,
+                                StorageSlot { address, slot },
+                            ));
+                        }
                         StorageRestriction::Banned(slot) => {
                             // [STO-*]
                             violations.push(SimulationViolation::InvalidStorageAccess(
@@ -306,11 +429,41 @@ where
                 // [OP-020]
                 violations.push(SimulationViolation::OutOfGas(ei.entity));
             }
+            if phase.max_call_depth > self.sim_settings.max_validation_call_depth {
+                violations.push(SimulationViolation::CallDepthTooDeep(
+                    ei.entity,
+                    phase.max_call_depth,
+                    self.sim_settings.max_validation_call_depth,
+                ));
+            }
+            let mut undeployed_ext_code_accesses = HashSet::new();
+            if self.sim_settings.reject_undeployed_ext_code_access {
+                for (&address, &opcode) in &phase.undeployed_ext_code_accesses {
+                    // the op's own deploy
+                    if address == sender_address || tracer_out.deployed_contracts.contains(&address)
+                    {
+                        continue;
+                    }
+                    violations.push(SimulationViolation::UndeployedExtCodeAccess(
+                        ei.entity,
+                        address,
+                        ViolationOpCode(opcode),
+                    ));
+                    undeployed_ext_code_accesses.insert(address);
+                }
+            }
             for &address in &phase.undeployed_contract_accesses {
                 // OP-042 - Factory can access undeployed sender
                 if ei.entity.kind == EntityType::Factory && address == sender_address {
                     continue;
                 }
+                if is_allowed_delegatecall(&address) {
+                    continue;
+                }
+                // already reported with the opcode that accessed it
+                if undeployed_ext_code_accesses.contains(&address) {
+                    continue;
+                }
                 // OP-041 - Access to an address without deployed code is forbidden
                 violations.push(SimulationViolation::AccessedUndeployedContract(
                     ei.entity, address,
@@ -318,27 +471,35 @@ where
             }
         }
 
-        if !entry_point_out.return_info.is_valid_time_range() {
+        // a range that ends before it starts is never valid, whatever the current time
+        let return_info = &entry_point_out.return_info;
+        if return_info.valid_until < return_info.valid_after {
+            violations.push(SimulationViolation::ReversedTimeRange(
+                return_info.valid_until,
+                return_info.valid_after,
+            ));
+        } else if !return_info.is_valid_time_range() {
             violations.push(SimulationViolation::InvalidTimeRange(
-                entry_point_out.return_info.valid_until,
-                entry_point_out.return_info.valid_after,
+                return_info.valid_until,
+                return_info.valid_after,
             ));
         }
+        if let Some(violation) = context::valid_after_violation(
+            return_info.valid_after,
+            Timestamp::now(),
+            &self.sim_settings,
+        ) {
+            violations.push(violation);
+        }
 
-        if let Some(agg_info) = entry_point_out.aggregator_info {
-            if let Some(agg) = context.op.aggregator() {
-                if agg_info.address != agg {
-                    violations.push(SimulationViolation::AggregatorMismatch(
-                        agg,
-                        agg_info.address,
-                    ));
-                }
-            } else {
-                violations.push(SimulationViolation::AggregatorMismatch(
-                    Address::ZERO,
-                    agg_info.address,
-                ));
-            }
+        if let Some(violation) = context::aggregator_mismatch_violation(
+            context.op.aggregator(),
+            entry_point_out
+                .aggregator_info
+                .as_ref()
+                .map(|info| info.address),
+        ) {
+            violations.push(violation);
         }
 
         for (address, contract_info) in &tracer_out.accessed_contracts {
@@ -371,6 +532,18 @@ where
             }
         }
 
+        // Catch counterfactual address mismatches before they surface as an entry point revert
+        if self.sim_settings.require_factory_deploys_sender {
+            if let Some(factory) = context.op.factory() {
+                let sender = context.op.sender();
+                if !tracer_out.deployed_contracts.contains(&sender) {
+                    violations.push(SimulationViolation::FactoryDidNotDeploySender(
+                        factory, sender,
+                    ));
+                }
+            }
+        }
+
         // Get violations specific to the implemented entry point from the context provider
         violations.extend(
             self.validation_context_provider
@@ -387,24 +560,41 @@ where
         context: &mut ValidationContext<UO>,
         expected_code_hash: Option<B256>,
     ) -> Result<B256, SimulationError> {
-        let &mut ValidationContext {
-            block_id,
-            ref mut tracer_out,
-            ..
-        } = context;
-
-        // collect a vector of violations to ensure a deterministic error message
-        let mut violations = vec![];
-
         let code_hash = self
             .provider
-            .get_code_hash(
-                tracer_out.accessed_contracts.keys().cloned().collect(),
-                Some(block_id),
-            )
+            .get_code_hash(self.code_hash_addresses(context), Some(context.block_id))
             .map_err(|e| SimulationError::from(anyhow::anyhow!("should call get_code_hash {e:?}")))
             .await?;
 
+        self.verify_code_hash(code_hash, expected_code_hash)?;
+        Ok(code_hash)
+    }
+
+    // The accessed contracts whose code makes up the code hash
+    fn code_hash_addresses(&self, context: &ValidationContext<UO>) -> Vec<Address> {
+        let mut addresses = context
+            .tracer_out
+            .accessed_contracts
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        // hash a deterministic subset of the accessed contracts, so that
+        // resimulation hashes the same contracts
+        addresses.sort();
+        if let Some(limit) = self.sim_settings.code_hash_addresses_limit {
+            addresses.truncate(limit);
+        }
+        addresses
+    }
+
+    fn verify_code_hash(
+        &self,
+        code_hash: B256,
+        expected_code_hash: Option<B256>,
+    ) -> Result<(), SimulationError> {
+        // collect a vector of violations to ensure a deterministic error message
+        let mut violations = vec![];
+
         if let Some(expected_code_hash) = expected_code_hash {
             // [COD-010]
             // If the expected code hash is zero, skip the check.
@@ -413,6 +603,11 @@ where
             }
         }
 
+        // A real op always accesses contracts, so this usually means a misconfigured simulation
+        if self.sim_settings.reject_empty_code_hash && code_hash == B256::ZERO {
+            violations.push(SimulationViolation::SuspiciousEmptyCodeHash)
+        }
+
         if !violations.is_empty() {
             return Err(SimulationError {
                 violation_error: ViolationError::Violations(violations),
@@ -420,35 +615,171 @@ where
             });
         }
 
+        Ok(())
+    }
+
+    // Checks that reject oversized, malformed, underpriced, denied, or re-entrant operations before
+    // doing any simulation work
+    fn pre_simulation_violation(&self, op: &UO) -> Option<SimulationViolation> {
+        context::op_size_violation(op, &self.sim_settings)
+            .or_else(|| context::malformed_op_violation(op))
+            .or_else(|| context::malformed_signature_violation(op, &self.sim_settings))
+            .or_else(|| context::priority_fee_violation(op, &self.sim_settings))
+            .or_else(|| context::pre_verification_gas_violation(op, &self.sim_settings))
+            .or_else(|| context::denied_entity_violation(op, &self.sim_settings))
+            .or_else(|| context::zero_call_gas_violation(op, &self.sim_settings))
+            .or_else(|| context::entry_point_call_violation(op, *self.entry_point.address()))
+            .or_else(|| context::sender_is_entry_point_violation(op, *self.entry_point.address()))
+    }
+
+    // Flag staked entities that have started unstaking and can withdraw their stake
+    // within the warning window, so the builder can avoid depending on their stake
+    async fn flag_expiring_stakes(
+        &self,
+        entity_infos: &mut EntityInfos,
+    ) -> Result<(), SimulationError> {
+        let now = Timestamp::now();
+        for info in self.stakes_to_check(entity_infos) {
+            let deposit_info = self
+                .entry_point
+                .get_deposit_info(info.address())
+                .await
+                .map_err(|e| {
+                    SimulationError::from(anyhow::anyhow!("should call get_deposit_info {e:?}"))
+                })?;
+            info.stake_expiring_soon =
+                context::stake_expiring_soon(deposit_info.withdraw_time, now, &self.sim_settings);
+        }
+
+        Ok(())
+    }
+
+    // The staked entities whose stake expiry is checked, none if the check is disabled
+    fn stakes_to_check<'a>(&self, entity_infos: &'a mut EntityInfos) -> Vec<&'a mut EntityInfo> {
+        if self.sim_settings.stake_expiry_warning_window_secs == 0 {
+            return vec![];
+        }
+        // aggregators are treated as staked due to the whitelist, so they are not checked
+        [
+            Some(&mut entity_infos.sender),
+            entity_infos.factory.as_mut(),
+            entity_infos.paymaster.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|info| info.is_staked)
+        .collect()
+    }
+
+    // Reads the code hash and flags expiring stakes, as in `check_code_hash` and
+    // `flag_expiring_stakes`, with all the reads sent in a single batch request
+    async fn batch_check_state(
+        &self,
+        context: &mut ValidationContext<UO>,
+        expected_code_hash: Option<B256>,
+    ) -> Result<B256, SimulationError> {
+        let helper_addr = Address::random();
+        let mut state_override = StateOverride::default();
+        state_override.insert(
+            helper_addr,
+            AccountOverride {
+                code: Some(GetCodeHashes::DEPLOYED_BYTECODE.clone()),
+                ..Default::default()
+            },
+        );
+        let mut calls = vec![EvmCall {
+            to: helper_addr,
+            data: GetCodeHashes::getCodeHashesCall {
+                addresses: self.code_hash_addresses(context),
+            }
+            .abi_encode()
+            .into(),
+            value: U256::ZERO,
+            state_override,
+        }];
+
+        let block_id = context.block_id;
+        let stakes = self.stakes_to_check(&mut context.entity_infos);
+        // `getDepositInfo` has the same signature in every entry point version, and the
+        // v0.7 output decodes the narrower v0.6 deposit
+        calls.extend(stakes.iter().map(|info| {
+            EvmCall {
+                to: *self.entry_point.address(),
+                data: getDepositInfoCall {
+                    account: info.address(),
+                }
+                .abi_encode()
+                .into(),
+                value: U256::ZERO,
+                state_override: StateOverride::default(),
+            }
+        }));
+
+        let outputs = self
+            .provider
+            .batch_call(calls, Some(block_id))
+            .await
+            .map_err(|e| {
+                SimulationError::from(anyhow::anyhow!("should batch state reads {e:?}"))
+            })?;
+        let (code_hash, deposit_infos) = outputs
+            .split_first()
+            .context("batch call should return the code hash")?;
+
+        let now = Timestamp::now();
+        for (info, output) in stakes.into_iter().zip(deposit_infos) {
+            let deposit_info = getDepositInfoCall::abi_decode_returns(output)
+                .context("should decode deposit info")?;
+            info.stake_expiring_soon = context::stake_expiring_soon(
+                deposit_info.withdrawTime.to(),
+                now,
+                &self.sim_settings,
+            );
+        }
+
+        let code_hash = GetCodeHashes::getCodeHashesCall::abi_decode_returns(code_hash)
+            .context("should decode code hash")?;
+        self.verify_code_hash(code_hash, expected_code_hash)?;
         Ok(code_hash)
     }
 }
 
-#[async_trait]
-impl<UO, P, E, V> Simulator for SimulatorImpl<UO, P, E, V>
+impl<UO, P, E, V> SimulatorImpl<UO, P, E, V>
 where
     UO: UserOperation,
     P: EvmProvider,
     E: EntryPoint + SimulationProvider<UO = UO> + Clone,
     V: ValidationContextProvider<UO = UO>,
 {
-    type UO = UO;
-
-    async fn simulate_validation(
+    async fn simulate_validation_inner(
         &self,
         op: UO,
         trusted: bool,
-        block_hash: B256,
+        block: BlockTag,
         expected_code_hash: Option<B256>,
     ) -> Result<SimulationResult, SimulationError> {
+        if let Some(violation) = self.pre_simulation_violation(&op) {
+            return Err(SimulationError {
+                violation_error: vec![violation].into(),
+                entity_infos: None,
+            });
+        }
+
+        // Trusted senders bypass the ERC-7562 rules, but the unsafe simulator
+        // still reports gas and the valid time range
+        let trusted = trusted || self.sim_settings.trusted_senders.contains(&op.sender());
         if trusted {
             return self
                 .unsafe_sim
-                .simulate_validation(op, trusted, block_hash, expected_code_hash)
+                .simulate_validation(op, trusted, block, expected_code_hash)
                 .await;
         }
 
-        let block_id = block_hash.into();
+        // The unsafe simulator acquires its own permit, so this is taken only after
+        // the trusted path has been ruled out
+        let permit = self.limiter.acquire().await;
+
+        let block_id = block.into();
         let mut context = match self
             .validation_context_provider
             .get_context(op.clone(), block_id)
@@ -460,9 +791,10 @@ where
                     tracing::warn!(
                         "tracing error with enable_unsafe_fallback set, falling back to unsafe sim. Error: {error:?}"
                     );
+                    drop(permit);
                     return self
                         .unsafe_sim
-                        .simulate_validation(op, trusted, block_hash, expected_code_hash)
+                        .simulate_validation(op, trusted, block, expected_code_hash)
                         .await;
                 } else {
                     error?
@@ -471,27 +803,52 @@ where
             error @ Err(ViolationError::Violations(_)) => error?,
         };
 
+        if let Some(violation) = context::expected_storage_violation(
+            &context.tracer_out.expected_storage,
+            &self.sim_settings,
+        ) {
+            return Err(SimulationError {
+                violation_error: vec![violation].into(),
+                entity_infos: Some(context.entity_infos),
+            });
+        }
+
+        #[cfg(any(test, feature = "test-utils"))]
+        let aggregator = self.apply_mock_aggregator(&mut context);
+        #[cfg(not(any(test, feature = "test-utils")))]
+        let aggregator = None;
+
         // Gather all violations from the tracer
         let mut overridable_violations = self.gather_context_violations(&mut context)?;
         // Sort violations so that the final error message is deterministic
         overridable_violations.sort();
         // Check violations against mempool rules, find supporting mempools, error if none found
-        let mempools = match mempool::match_mempools(&self.mempool_configs, &overridable_violations)
-        {
+        let match_result = match self.canonical_mempool {
+            Some(id) => mempool::match_canonical_mempool(id, &overridable_violations),
+            None => mempool::match_mempools(&self.mempool_configs, &overridable_violations),
+        };
+        let mempools = match match_result {
             MempoolMatchResult::Matches(pools) => pools,
-            MempoolMatchResult::NoMatch(i) => {
+            MempoolMatchResult::NoMatch(violations) => {
                 return Err(SimulationError {
                     violation_error: ViolationError::Violations(vec![
-                        overridable_violations[i].clone()
+                        SimulationViolation::UnsupportedByAllMempools { violations },
                     ]),
                     entity_infos: Some(context.entity_infos),
                 })
             }
         };
 
-        let code_hash = self
-            .check_code_hash(&mut context, expected_code_hash)
-            .await?;
+        let code_hash = if self.sim_settings.batch_state_reads {
+            self.batch_check_state(&mut context, expected_code_hash)
+                .await?
+        } else {
+            let code_hash = self
+                .check_code_hash(&mut context, expected_code_hash)
+                .await?;
+            self.flag_expiring_stakes(&mut context.entity_infos).await?;
+            code_hash
+        };
 
         // Transform outputs into success struct
         let ValidationContext {
@@ -501,6 +858,27 @@ where
             associated_addresses,
             ..
         } = context;
+        let validation_output = self
+            .sim_settings
+            .include_validation_output
+            .then(|| entry_point_out.clone());
+        let gas_breakdown = self
+            .sim_settings
+            .include_gas_breakdown
+            .then(|| gas_breakdown(&tracer_out.phases, entry_point_out.return_info.pre_op_gas));
+        let validation_logs = self.sim_settings.include_validation_logs.then(|| {
+            tracer_out
+                .logs
+                .iter()
+                .map(|log| {
+                    Log::new_unchecked(
+                        log.address,
+                        log.topics.iter().map(|t| B256::from(*t)).collect(),
+                        log.data.clone(),
+                    )
+                })
+                .collect()
+        });
         let ValidationOutput {
             return_info,
             sender_info,
@@ -518,6 +896,12 @@ where
         // Conduct any stake overrides before assigning entity_infos
         override_infos_staked(&mut context.entity_infos, &self.allow_unstaked_addresses);
 
+        if self.sim_settings.per_mempool_metrics {
+            for id in &mempools {
+                MempoolMetrics::for_mempool(*id).ops_simulated.increment(1);
+            }
+        }
+
         Ok(SimulationResult {
             mempools,
             pre_op_gas,
@@ -528,16 +912,193 @@ where
             associated_addresses,
             expected_storage: tracer_out.expected_storage,
             requires_post_op: !paymaster_context.is_empty(),
+            has_entity_role_overlap: context.entity_infos.has_role_overlap(),
             entity_infos: context.entity_infos,
+            aggregator,
+            validation_output,
+            gas_breakdown,
+            validation_logs,
+            stake_shortfalls: stake_shortfalls(&overridable_violations),
+        })
+    }
+}
+
+#[async_trait]
+impl<UO, P, E, V> Simulator for SimulatorImpl<UO, P, E, V>
+where
+    UO: UserOperation,
+    P: EvmProvider,
+    E: EntryPoint + SimulationProvider<UO = UO> + Clone,
+    V: ValidationContextProvider<UO = UO>,
+{
+    type UO = UO;
+
+    async fn simulate_validation(
+        &self,
+        op: UO,
+        trusted: bool,
+        block: BlockTag,
+        expected_code_hash: Option<B256>,
+    ) -> Result<SimulationResult, SimulationError> {
+        let Some(observer) = &self.observer else {
+            return self
+                .simulate_validation_inner(op, trusted, block, expected_code_hash)
+                .await;
+        };
+
+        let op_hash = op.hash();
+        let start = Instant::now();
+        let result = self
+            .simulate_validation_inner(op, trusted, block, expected_code_hash)
+            .await;
+        observer.on_simulation(&SimulationSummary {
+            op_hash,
+            block,
+            duration: start.elapsed(),
+            result: &result,
+        });
+        result
+    }
+
+    async fn evaluate_mempools(
+        &self,
+        op: UO,
+        block: BlockTag,
+    ) -> Result<Vec<MempoolEvaluation>, SimulationError> {
+        if let Some(violation) = self.pre_simulation_violation(&op) {
+            return Err(SimulationError {
+                violation_error: vec![violation].into(),
+                entity_infos: None,
+            });
+        }
+
+        let _permit = self.limiter.acquire().await;
+        let mut context = self
+            .validation_context_provider
+            .get_context(op, block.into())
+            .await?;
+        let mut violations = self.gather_context_violations(&mut context)?;
+        violations.sort();
+        Ok(mempool::evaluate_mempools(
+            &self.mempool_configs,
+            &violations,
+        ))
+    }
+
+    async fn simulate_factory(
+        &self,
+        init_code: Bytes,
+        block: BlockTag,
+    ) -> Result<FactorySimResult, SimulationError> {
+        if init_code.len() < 20 {
+            return Err(anyhow::anyhow!("init code should start with a factory address").into());
+        }
+        let factory = Address::from_slice(&init_code[..20]);
+        let block_id = BlockId::from(block);
+
+        let factory_code = self
+            .provider
+            .get_code(factory, Some(block_id))
+            .await
+            .context("should get factory code")?;
+        if factory_code.is_empty() {
+            return Err(SimulationError {
+                violation_error: vec![SimulationViolation::FactoryIsNotContract(factory)].into(),
+                entity_infos: None,
+            });
+        }
+
+        let tx = TransactionRequest::default()
+            .with_to(factory)
+            .with_input(init_code.slice(20..));
+        let trace = self
+            .provider
+            .debug_trace_call(
+                tx,
+                Some(block_id),
+                GethDebugTracingCallOptions {
+                    tracing_options: GethDebugTracingOptions {
+                        tracer: Some(GethDebugTracerType::BuiltInTracer(
+                            GethDebugBuiltInTracerType::CallTracer,
+                        )),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .await
+            .context("should trace factory call")?;
+        let GethTrace::CallTracer(frame) = trace else {
+            return Err(anyhow::anyhow!("factory trace should be a call frame").into());
+        };
+
+        if let Some(error) = &frame.error {
+            let message = frame
+                .revert_reason
+                .clone()
+                .or_else(|| frame.output.as_ref().map(|output| output.to_string()))
+                .unwrap_or_else(|| error.clone());
+            return Err(SimulationError {
+                violation_error: vec![SimulationViolation::UnintendedRevertWithMessage(
+                    EntityType::Factory,
+                    message,
+                    Some(factory),
+                )]
+                .into(),
+                entity_infos: None,
+            });
+        }
+
+        let gas = frame.gas_used.saturating_to::<u128>();
+        // a factory returns the existing account without deploying when it is already created
+        let (address, code_hash) = match find_create_frame(&frame) {
+            Some(create) => (
+                create
+                    .to
+                    .context("create frame should have a created address")?,
+                keccak256(create.output.clone().unwrap_or_default()),
+            ),
+            None => {
+                let output = frame.output.clone().unwrap_or_default();
+                if output.len() < 32 {
+                    return Err(anyhow::anyhow!(
+                        "factory should return the account address, returned {output}"
+                    )
+                    .into());
+                }
+                let address = Address::from_slice(&output[12..32]);
+                let code = self
+                    .provider
+                    .get_code(address, Some(block_id))
+                    .await
+                    .context("should get account code")?;
+                (address, keccak256(code))
+            }
+        };
+
+        Ok(FactorySimResult {
+            address,
+            gas,
+            code_hash,
         })
     }
 }
 
+// Returns the first frame that created a contract, searching depth first.
+fn find_create_frame(frame: &GethDebugTracerCallFrame) -> Option<&GethDebugTracerCallFrame> {
+    if matches!(frame.typ.as_str(), "CREATE" | "CREATE2") && frame.error.is_none() {
+        return Some(frame);
+    }
+    frame.calls.iter().find_map(find_create_frame)
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 enum StorageRestriction {
     /// (Entity needing stake, accessing entity type, accessed entity type, accessed address, accessed slot)
     NeedsStake(EntityType, EntityType, Option<EntityType>, Address, U256),
     AssociatedStorageDuringDeploy(Option<EntityType>, Address, U256),
+    /// (Address the slot is associated with, accessed slot)
+    CrossSenderAssociated(Address, U256),
     Banned(U256),
 }
 
@@ -550,6 +1111,10 @@ struct ParseStorageAccess<'a> {
     entrypoint: Address,
     has_factory: bool,
     entity: &'a Entity,
+    entity_infos: &'a EntityInfos,
+    allow_unstaked_reads: bool,
+    allow_sender_factory_associated_storage: bool,
+    reject_cross_sender_associated_storage: bool,
 }
 
 fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Vec<StorageRestriction> {
@@ -561,6 +1126,10 @@ fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Vec<StorageRestrictio
         entity,
         slots_by_address,
         has_factory,
+        entity_infos,
+        allow_unstaked_reads,
+        allow_sender_factory_associated_storage,
+        reject_cross_sender_associated_storage,
         ..
     } = args;
 
@@ -574,6 +1143,11 @@ fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Vec<StorageRestrictio
         return restrictions;
     }
 
+    // Some vanity deploy patterns use a factory at the sender's address. Its associated
+    // slots are then the sender's, so they are classified as sender associated below
+    // rather than as the entity's own.
+    let is_sender_factory = entity.kind == EntityType::Factory && entity.address == sender;
+
     let slots: Vec<&U256> = access_info
         .reads
         .keys()
@@ -591,8 +1165,9 @@ fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Vec<StorageRestrictio
 
         // [STO-021] - Associated storage on external contracts is allowed
         if is_sender_associated && !is_same_address {
-            // [STO-022] - Factory must be staked to access associated storage in a deploy
-            if has_factory {
+            // [STO-022] - Factory must be staked to access associated storage in a deploy,
+            // unless configured to allow a factory that is the sender to access its own
+            if has_factory && !(is_sender_factory && allow_sender_factory_associated_storage) {
                 match entity.kind {
                     EntityType::Paymaster | EntityType::Aggregator => {
                         // If its a paymaster/aggregator, then the entity OR factory must be staked to access associated storage
@@ -619,7 +1194,24 @@ fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Vec<StorageRestrictio
                 address,
                 *slot,
             ));
+        } else if let Some(associated_address) = reject_cross_sender_associated_storage
+            .then(|| {
+                // Storage associated with the op's entities or the entry point is expected
+                slots_by_address
+                    .associated_addresses(*slot)
+                    .filter(|&a| a != entrypoint && entity_infos.type_from_address(a).is_none())
+                    .min()
+            })
+            .flatten()
+        {
+            restrictions.push(StorageRestriction::CrossSenderAssociated(
+                associated_address,
+                *slot,
+            ));
         } else if is_read_permission {
+            if allow_unstaked_reads {
+                continue;
+            }
             restrictions.push(StorageRestriction::NeedsStake(
                 entity.kind,
                 entity.kind,
@@ -642,24 +1234,45 @@ fn override_is_staked(ei: &mut EntityInfo, allow_unstaked_addresses: &HashSet<Ad
 fn override_infos_staked(eis: &mut EntityInfos, allow_unstaked_addresses: &HashSet<Address>) {
     override_is_staked(&mut eis.sender, allow_unstaked_addresses);
 
-    if let Some(mut factory) = eis.factory {
-        override_is_staked(&mut factory, allow_unstaked_addresses);
+    if let Some(factory) = eis.factory.as_mut() {
+        override_is_staked(factory, allow_unstaked_addresses);
+    }
+    if let Some(paymaster) = eis.paymaster.as_mut() {
+        override_is_staked(paymaster, allow_unstaked_addresses);
     }
-    if let Some(mut paymaster) = eis.paymaster {
-        override_is_staked(&mut paymaster, allow_unstaked_addresses);
+    if let Some(aggregator) = eis.aggregator.as_mut() {
+        override_is_staked(aggregator, allow_unstaked_addresses);
     }
-    if let Some(mut aggregator) = eis.aggregator {
-        override_is_staked(&mut aggregator, allow_unstaked_addresses);
+}
+
+fn gas_breakdown(phases: &[Phase], pre_op_gas: u128) -> GasBreakdown {
+    let phase_gas = |i: usize| phases.get(i).map_or(0, |p| p.gas_used as u128);
+    let (factory_gas, account_validation_gas, paymaster_validation_gas) =
+        (phase_gas(0), phase_gas(1), phase_gas(2));
+    GasBreakdown {
+        factory_gas,
+        account_validation_gas,
+        paymaster_validation_gas,
+        other_gas: pre_op_gas
+            .saturating_sub(factory_gas + account_validation_gas + paymaster_validation_gas),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{ops::Sub, sync::Arc, time::Duration};
+    use std::{collections::BTreeSet, ops::Sub, sync::Arc, time::Duration};
 
-    use alloy_primitives::{address, b256, bytes, uint, Bytes};
+    use alloy_primitives::{
+        address,
+        aliases::{U112, U48},
+        b256, bytes, uint, Bytes,
+    };
+    use alloy_sol_types::{SolCall, SolValue};
     use context::ContractInfo;
-    use rundler_provider::{BlockId, BlockNumberOrTag, MockEntryPointV0_6, MockEvmProvider};
+    use rundler_contracts::v0_7::DepositInfo as DepositInfoV0_7;
+    use rundler_provider::{
+        AggregatorSimOut, BlockNumberOrTag, DepositInfo, MockEntryPointV0_6, MockEvmProvider,
+    };
     use rundler_types::{
         aggregator::AggregatorCosts,
         chain::ChainSpec,
@@ -667,8 +1280,9 @@ mod tests {
         AggregatorInfo, Opcode, StakeInfo, Timestamp, UserOperation as _,
     };
 
-    use self::context::{Phase, TracerOutput};
+    use self::context::{LogInfo, Phase, TracerOutput};
     use super::*;
+    use crate::simulation::EntityStakeShortfall;
 
     mockall::mock! {
         ValidationContextProviderV0_6 {}
@@ -693,9 +1307,13 @@ mod tests {
         MockEntryPointV0_6,
         MockValidationContextProviderV0_6,
     ) {
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
         (
             MockEvmProvider::new(),
-            MockEntryPointV0_6::new(),
+            entry_point,
             MockValidationContextProviderV0_6::new(),
         )
     }
@@ -742,6 +1360,8 @@ mod tests {
             }
             "#).unwrap(),
             factory_called_create2_twice: false,
+            logs: vec![],
+            deployed_contracts: vec![],
             expected_storage: serde_json::from_str(r#"
             {
                 "0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789": {
@@ -760,6 +1380,9 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    undeployed_ext_code_accesses: HashMap::new(),
+                    max_call_depth: 1,
+                    gas_used: 0,
                 },
                 Phase {
                     called_banned_entry_point_method: false,
@@ -770,6 +1393,9 @@ mod tests {
                     storage_accesses:  HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    undeployed_ext_code_accesses: HashMap::new(),
+                    max_call_depth: 1,
+                    gas_used: 0,
                 },
                 Phase {
                     called_banned_entry_point_method: false,
@@ -780,6 +1406,9 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    undeployed_ext_code_accesses: HashMap::new(),
+                    max_call_depth: 1,
+                    gas_used: 0,
                 }
             ],
             revert_data: Some("0xe0cff05f00000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000014eff00000000000000000000000000000000000000000000000000000b7679c50c24000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffff00000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000000".into()),
@@ -840,8 +1469,20 @@ mod tests {
         Arc<MockEntryPointV0_6>,
         MockValidationContextProviderV0_6,
     > {
-        let settings = Settings::default();
+        create_simulator_with_settings(provider, entry_point, context, Settings::default())
+    }
 
+    fn create_simulator_with_settings(
+        provider: MockEvmProvider,
+        entry_point: MockEntryPointV0_6,
+        context: MockValidationContextProviderV0_6,
+        settings: Settings,
+    ) -> SimulatorImpl<
+        UserOperation,
+        MockEvmProvider,
+        Arc<MockEntryPointV0_6>,
+        MockValidationContextProviderV0_6,
+    > {
         let mut mempool_configs = HashMap::new();
         mempool_configs.insert(B256::ZERO, MempoolConfig::default());
 
@@ -899,43 +1540,1287 @@ mod tests {
 
         let simulator = create_simulator(provider, entry_point, context);
         let res = simulator
-            .simulate_validation(user_operation, false, B256::ZERO, None)
+            .simulate_validation(user_operation, false, B256::ZERO.into(), None)
             .await;
         assert!(res.is_ok());
     }
 
     #[tokio::test]
-    async fn test_simulate_validation_trusted() {
-        let (provider, mut entry_point, context) = create_base_config();
-        let uo = UserOperationBuilder::new(
-            &ChainSpec::default(),
-            UserOperationRequiredFields::default(),
-        )
-        .build();
-
-        entry_point.expect_simulate_validation().returning(|_, _| {
-            Ok(Ok(ValidationOutput {
-                return_info: ValidationReturnInfo::default(),
-                sender_info: StakeInfo::default(),
-                factory_info: StakeInfo::default(),
-                paymaster_info: StakeInfo::default(),
-                aggregator_info: None,
-            }))
-        });
+    async fn test_simulate_validation_includes_validation_output() {
+        let (mut provider, entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::repeat_byte(1)));
+        context
+            .expect_get_context()
+            .returning(move |_, _| Ok(get_test_context()));
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
 
-        let simulator = create_simulator(provider, entry_point, context);
+        let settings = Settings {
+            include_validation_output: true,
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, entry_point, context, settings);
         let res = simulator
-            .simulate_validation(uo, true, B256::ZERO, None)
-            .await;
-        assert!(res.is_ok());
+            .simulate_validation(get_test_context().op, false, B256::ZERO.into(), None)
+            .await
+            .unwrap();
+
+        let validation_output = res.validation_output.unwrap();
+        assert_eq!(validation_output.return_info.pre_op_gas, 3000);
+        assert!(validation_output.return_info.account_sig_failed);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_gas_breakdown() {
+        let (mut provider, entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::repeat_byte(1)));
+        context.expect_get_context().returning(move |_, _| {
+            let mut context = get_test_context();
+            for (phase, gas_used) in context.tracer_out.phases.iter_mut().zip([500, 1000, 200]) {
+                phase.gas_used = gas_used;
+            }
+            Ok(context)
+        });
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let settings = Settings {
+            include_gas_breakdown: true,
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, entry_point, context, settings);
+        let res = simulator
+            .simulate_validation(get_test_context().op, false, B256::ZERO.into(), None)
+            .await
+            .unwrap();
+
+        let gas_breakdown = res.gas_breakdown.unwrap();
+        assert_eq!(
+            gas_breakdown,
+            GasBreakdown {
+                factory_gas: 500,
+                account_validation_gas: 1000,
+                paymaster_validation_gas: 200,
+                other_gas: 1300,
+            }
+        );
+        assert_eq!(gas_breakdown.total(), res.pre_op_gas);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_logs() {
+        // Transfer(address,address,uint256) emitted by the paymaster
+        let paymaster = address!("8abb13360b87be5eeb1b98647a016add927a136c");
+        let transfer_topic =
+            b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+        let amount = bytes!("00000000000000000000000000000000000000000000000000000000000003e8");
+
+        let (mut provider, entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::repeat_byte(1)));
+        let logged_amount = amount.clone();
+        context.expect_get_context().returning(move |_, _| {
+            let mut context = get_test_context();
+            context.tracer_out.logs = vec![LogInfo {
+                address: paymaster,
+                topics: vec![transfer_topic.into()],
+                data: logged_amount.clone(),
+            }];
+            Ok(context)
+        });
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let settings = Settings {
+            include_validation_logs: true,
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, entry_point, context, settings);
+        let res = simulator
+            .simulate_validation(get_test_context().op, false, B256::ZERO.into(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res.validation_logs,
+            Some(vec![Log::new_unchecked(
+                paymaster,
+                vec![transfer_topic],
+                amount
+            )])
+        );
+    }
+
+    #[test]
+    fn test_valid_after_horizon() {
+        let now = Timestamp::new(1_000);
+        let settings = Settings {
+            max_valid_after_horizon: Duration::from_secs(100),
+            ..Default::default()
+        };
+
+        // already valid, and valid at the edge of the horizon
+        assert_eq!(
+            context::valid_after_violation(Timestamp::new(0), now, &settings),
+            None
+        );
+        assert_eq!(
+            context::valid_after_violation(Timestamp::new(1_100), now, &settings),
+            None
+        );
+        // past the horizon
+        assert_eq!(
+            context::valid_after_violation(Timestamp::new(1_101), now, &settings),
+            Some(SimulationViolation::ValidAfterTooFar {
+                valid_after: Timestamp::new(1_101),
+                max_valid_after: Timestamp::new(1_100),
+            })
+        );
+    }
+
+    #[test]
+    fn test_stake_expiring_soon_window() {
+        let now = Timestamp::new(1_000);
+        let settings = Settings {
+            stake_expiry_warning_window_secs: 100,
+            ..Default::default()
+        };
+
+        // not unstaking
+        assert!(!context::stake_expiring_soon(0, now, &settings));
+        // already withdrawable
+        assert!(context::stake_expiring_soon(900, now, &settings));
+        // inside and at the edge of the window
+        assert!(context::stake_expiring_soon(1_050, now, &settings));
+        assert!(context::stake_expiring_soon(1_100, now, &settings));
+        // past the window
+        assert!(!context::stake_expiring_soon(1_101, now, &settings));
+
+        // disabled
+        assert!(!context::stake_expiring_soon(
+            900,
+            now,
+            &Settings::default()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_flags_expiring_stake() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::repeat_byte(1)));
+        context.expect_get_context().returning(move |_, _| {
+            let mut context = get_test_context();
+            context.entity_infos.sender.is_staked = true;
+            Ok(context)
+        });
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        // only the staked sender is checked
+        entry_point
+            .expect_get_deposit_info()
+            .times(1)
+            .returning(|_| {
+                Ok(DepositInfo {
+                    staked: true,
+                    withdraw_time: Timestamp::now().seconds_since_epoch() + 60,
+                    ..Default::default()
+                })
+            });
+
+        let settings = Settings {
+            stake_expiry_warning_window_secs: 3600,
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, entry_point, context, settings);
+        let res = simulator
+            .simulate_validation(get_test_context().op, false, B256::ZERO.into(), None)
+            .await
+            .unwrap();
+
+        assert!(res.entity_infos.sender.stake_expiring_soon);
+        assert!(!res.entity_infos.factory.unwrap().stake_expiring_soon);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_batch_state_reads() {
+        let (mut provider, mut entry_point, mut context) = create_base_config();
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((B256::ZERO, 0)));
+        provider.expect_get_code_hash().never();
+        entry_point.expect_get_deposit_info().never();
+        // a single round trip for the code hash and the staked sender's deposit info
+        provider.expect_batch_call().times(1).returning(|calls, _| {
+            assert_eq!(calls.len(), 2);
+            let deposit_info = DepositInfoV0_7 {
+                deposit: U256::ZERO,
+                staked: true,
+                stake: U112::ZERO,
+                unstakeDelaySec: 0,
+                withdrawTime: U48::from(Timestamp::now().seconds_since_epoch() + 60),
+            };
+            Ok(vec![
+                Bytes::copy_from_slice(B256::repeat_byte(1).as_slice()),
+                deposit_info.abi_encode().into(),
+            ])
+        });
+        context.expect_get_context().returning(move |_, _| {
+            let mut context = get_test_context();
+            context.entity_infos.sender.is_staked = true;
+            Ok(context)
+        });
+        context
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let settings = Settings {
+            stake_expiry_warning_window_secs: 3600,
+            batch_state_reads: true,
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, entry_point, context, settings);
+        let res = simulator
+            .simulate_validation(get_test_context().op, false, B256::ZERO.into(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(res.code_hash, B256::repeat_byte(1));
+        assert!(res.entity_infos.sender.stake_expiring_soon);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_unsupported_by_all_mempools() {
+        let (mut provider, entry_point, mut context) = create_base_config();
+
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::random()));
+
+        let violation = SimulationViolation::CallHadValue(Entity {
+            kind: EntityType::Paymaster,
+            address: Address::random(),
+        });
+        context
+            .expect_get_context()
+            .returning(move |_, _| Ok(get_test_context()));
+        context.expect_get_specific_violations().returning({
+            let violation = violation.clone();
+            move |_| Ok(vec![violation.clone()])
+        });
+
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields::default(),
+        )
+        .build();
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_validation(uo, false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::UnsupportedByAllMempools {
+                violations: vec![violation]
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_expected_storage_too_large() {
+        let (provider, entry_point, mut context) = create_base_config();
+
+        // an op whose validation touches many slots of a single contract
+        context.expect_get_context().returning(|_, _| {
+            let mut context = get_test_context();
+            let address = Address::random();
+            for slot in 0..1_000 {
+                context
+                    .tracer_out
+                    .expected_storage
+                    .insert(address, U256::from(slot), U256::ZERO);
+            }
+            Ok(context)
+        });
+
+        let simulator = create_simulator_with_settings(
+            provider,
+            entry_point,
+            context,
+            Settings {
+                max_expected_storage_slots: 500,
+                ..Default::default()
+            },
+        );
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields::default(),
+        )
+        .build();
+        let res = simulator
+            .simulate_validation(uo, false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::ExpectedStorageTooLarge {
+                slots: 1_002,
+                max: 500
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_trusted() {
+        let (provider, mut entry_point, context) = create_base_config();
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields::default(),
+        )
+        .build();
+
+        entry_point
+            .expect_simulate_validation()
+            .returning(|_, _, _| {
+                Ok(Ok(ValidationOutput {
+                    return_info: ValidationReturnInfo::default(),
+                    sender_info: StakeInfo::default(),
+                    factory_info: StakeInfo::default(),
+                    paymaster_info: StakeInfo::default(),
+                    aggregator_info: None,
+                }))
+            });
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_validation(uo, true, B256::ZERO.into(), None)
+            .await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_op_size_at_maximum() {
+        let (provider, mut entry_point, context) = create_base_config();
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                call_data: Bytes::from(vec![1; 100]),
+                ..Default::default()
+            },
+        )
+        .build();
+
+        entry_point
+            .expect_simulate_validation()
+            .returning(|_, _, _| {
+                Ok(Ok(ValidationOutput {
+                    return_info: ValidationReturnInfo::default(),
+                    sender_info: StakeInfo::default(),
+                    factory_info: StakeInfo::default(),
+                    paymaster_info: StakeInfo::default(),
+                    aggregator_info: None,
+                }))
+            });
+
+        let settings = Settings {
+            max_op_calldata_bytes: uo.abi_encoded_size(),
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, entry_point, context, settings);
+        let res = simulator
+            .simulate_validation(uo, true, B256::ZERO.into(), None)
+            .await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_trusted_sender() {
+        // no context expectations: a trusted sender must not go through the tracer
+        let (provider, mut entry_point, context) = create_base_config();
+        let sender = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender,
+                ..Default::default()
+            },
+        )
+        .build();
+
+        entry_point
+            .expect_simulate_validation()
+            .returning(|_, _, _| {
+                Ok(Ok(ValidationOutput {
+                    return_info: ValidationReturnInfo {
+                        pre_op_gas: 1000,
+                        ..Default::default()
+                    },
+                    sender_info: StakeInfo::default(),
+                    factory_info: StakeInfo::default(),
+                    paymaster_info: StakeInfo::default(),
+                    aggregator_info: None,
+                }))
+            });
+
+        let settings = Settings {
+            trusted_senders: HashSet::from([sender]),
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, entry_point, context, settings);
+        let res = simulator
+            .simulate_validation(uo, false, B256::ZERO.into(), None)
+            .await
+            .unwrap();
+        assert_eq!(res.pre_op_gas, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_malformed_init_code() {
+        let (provider, entry_point, context) = create_base_config();
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                init_code: Bytes::from(vec![1; 10]),
+                ..Default::default()
+            },
+        )
+        .build();
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_validation(uo, false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(violations, vec![SimulationViolation::MalformedInitCode(10)]);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_malformed_paymaster_and_data() {
+        let (provider, entry_point, context) = create_base_config();
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                paymaster_and_data: Bytes::from(vec![1; 19]),
+                ..Default::default()
+            },
+        )
+        .build();
+
+        let simulator = create_simulator(provider, entry_point, context);
+        let res = simulator
+            .simulate_validation(uo, false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::MalformedPaymasterAndData(19)]
+        );
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingObserver {
+        seen: std::sync::Mutex<Vec<(B256, bool)>>,
+    }
+
+    impl SimulationObserver for RecordingObserver {
+        fn on_simulation(&self, summary: &SimulationSummary<'_>) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push((summary.op_hash, summary.result.is_ok()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_notifies_observer() {
+        let (provider, entry_point, context) = create_base_config();
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                init_code: Bytes::from(vec![1; 10]),
+                ..Default::default()
+            },
+        )
+        .build();
+        let op_hash = uo.hash();
+
+        let observer = Arc::new(RecordingObserver::default());
+        let simulator =
+            create_simulator(provider, entry_point, context).with_observer(observer.clone());
+        let res = simulator
+            .simulate_validation(uo, false, B256::ZERO.into(), None)
+            .await;
+
+        assert!(res.is_err());
+        assert_eq!(*observer.seen.lock().unwrap(), vec![(op_hash, false)]);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_op_too_large() {
+        let (provider, entry_point, context) = create_base_config();
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                call_data: Bytes::from(vec![1; 100]),
+                ..Default::default()
+            },
+        )
+        .build();
+
+        let size = uo.abi_encoded_size();
+        let settings = Settings {
+            max_op_calldata_bytes: size - 1,
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, entry_point, context, settings);
+        let res = simulator
+            .simulate_validation(uo, false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::OperationTooLarge {
+                size,
+                maximum: size - 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_priority_fee_too_low() {
+        let (provider, entry_point, context_provider) = create_base_config();
+        let op_with_fee = |max_priority_fee_per_gas| {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    max_priority_fee_per_gas,
+                    max_fee_per_gas: 1000,
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+        let settings = Settings {
+            min_priority_fee_per_gas: 100,
+            ..Default::default()
+        };
+
+        // an op paying exactly the minimum passes the gate
+        assert_eq!(
+            context::priority_fee_violation(&op_with_fee(100), &settings),
+            None
+        );
+
+        let simulator =
+            create_simulator_with_settings(provider, entry_point, context_provider, settings);
+        let res = simulator
+            .simulate_validation(op_with_fee(99), false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::PriorityFeeTooLow {
+                provided: 99,
+                minimum: 100,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_pre_verification_gas_too_low() {
+        let (provider, entry_point, context_provider) = create_base_config();
+        let op = || {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    pre_verification_gas: 100_000,
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+        let calldata_gas_cost = op().calldata_gas_cost();
+
+        // an op paying exactly the minimum passes the gate
+        let settings = Settings {
+            min_pre_verification_gas_overhead: Some(100_000 - calldata_gas_cost),
+            ..Default::default()
+        };
+        assert_eq!(
+            context::pre_verification_gas_violation(&op(), &settings),
+            None
+        );
+
+        let settings = Settings {
+            min_pre_verification_gas_overhead: Some(100_001 - calldata_gas_cost),
+            ..Default::default()
+        };
+        let simulator =
+            create_simulator_with_settings(provider, entry_point, context_provider, settings);
+        let res = simulator
+            .simulate_validation(op(), false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::PreVerificationGasTooLow {
+                provided: 100_000,
+                minimum: 100_001,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_zero_call_gas_accepted_by_default() {
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                call_gas_limit: 0,
+                ..Default::default()
+            },
+        )
+        .build();
+
+        assert_eq!(
+            context::zero_call_gas_violation(&uo, &Settings::default()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_rejects_entry_point_call() {
+        // no expectations beyond the address: a rejected op must not be simulated
+        let (provider, entry_point, context_provider) = create_base_config();
+        let op_calling = |dest| {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    call_data: context::executeCall {
+                        dest,
+                        value: U256::ZERO,
+                        func: bytes!("d087d288"),
+                    }
+                    .abi_encode()
+                    .into(),
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+        let entry_point_address = *entry_point.address();
+
+        assert_eq!(
+            context::entry_point_call_violation(
+                &op_calling(address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")),
+                entry_point_address
+            ),
+            None
+        );
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        let res = simulator
+            .simulate_validation(
+                op_calling(entry_point_address),
+                false,
+                B256::ZERO.into(),
+                None,
+            )
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(violations, vec![SimulationViolation::CalledEntryPoint]);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_rejects_sender_is_entry_point() {
+        // no expectations beyond the address: a rejected op must not be simulated
+        let (provider, entry_point, context_provider) = create_base_config();
+        let entry_point_address = *entry_point.address();
+        let op_from = |sender| {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    sender,
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+
+        assert_eq!(
+            context::sender_is_entry_point_violation(
+                &op_from(address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")),
+                entry_point_address
+            ),
+            None
+        );
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        let res = simulator
+            .simulate_validation(op_from(entry_point_address), false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(violations, vec![SimulationViolation::SenderIsEntryPoint]);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_rejects_zero_call_gas() {
+        // no expectations: a rejected op must not reach the entry point or tracer
+        let (provider, entry_point, context_provider) = create_base_config();
+        let op_with_call_gas = |call_gas_limit| {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    call_gas_limit,
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+        let settings = Settings {
+            reject_zero_call_gas: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context::zero_call_gas_violation(&op_with_call_gas(1), &settings),
+            None
+        );
+
+        let simulator =
+            create_simulator_with_settings(provider, entry_point, context_provider, settings);
+        let res = simulator
+            .simulate_validation(op_with_call_gas(0), false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(violations, vec![SimulationViolation::ZeroCallGasLimit]);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_rejects_empty_signature() {
+        // no expectations: a rejected op must not reach the entry point or tracer
+        let (provider, entry_point, context_provider) = create_base_config();
+        let op_with_signature = |signature: Bytes| {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    signature,
+                    ..Default::default()
+                },
+            )
+        };
+        let settings = Settings {
+            min_signature_length: Some(1),
+            ..Default::default()
+        };
+
+        let empty = op_with_signature(Bytes::new()).build();
+        assert_eq!(
+            context::malformed_signature_violation(&empty, &Settings::default()),
+            None
+        );
+        assert_eq!(
+            context::malformed_signature_violation(
+                &op_with_signature(Bytes::from_static(&[1; 65])).build(),
+                &settings
+            ),
+            None
+        );
+        // the aggregator may carry the signature
+        assert_eq!(
+            context::malformed_signature_violation(
+                &op_with_signature(Bytes::new())
+                    .aggregator(Address::random())
+                    .build(),
+                &settings
+            ),
+            None
+        );
+
+        let simulator =
+            create_simulator_with_settings(provider, entry_point, context_provider, settings);
+        let res = simulator
+            .simulate_validation(empty, false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::MalformedSignature {
+                length: 0,
+                minimum: 1
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_validation_denied_paymaster() {
+        // no expectations: a denied op must not reach the entry point or tracer
+        let (provider, entry_point, context_provider) = create_base_config();
+        let paymaster = Address::random();
+        let uo = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                paymaster_and_data: paymaster.to_vec().into(),
+                ..Default::default()
+            },
+        )
+        .build();
+
+        let settings = Settings {
+            denied_paymasters: HashSet::from([paymaster]),
+            ..Default::default()
+        };
+        let simulator =
+            create_simulator_with_settings(provider, entry_point, context_provider, settings);
+        let res = simulator
+            .simulate_validation(uo, false, B256::ZERO.into(), None)
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::EntityDenied(Entity::paymaster(
+                paymaster
+            ))]
+        );
+    }
+
+    fn factory_init_code(factory: Address) -> Bytes {
+        [factory.as_slice(), &[1, 2, 3, 4]].concat().into()
+    }
+
+    #[tokio::test]
+    async fn test_simulate_factory() {
+        let (mut provider, entry_point, context_provider) = create_base_config();
+        let factory = Address::random();
+        let account = Address::random();
+        let account_code = bytes!("6080");
+
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("60806040")));
+        provider
+            .expect_debug_trace_call()
+            .returning(move |tx, _, _| {
+                assert_eq!(tx.input.input(), Some(&bytes!("01020304")));
+                Ok(GethTrace::CallTracer(GethDebugTracerCallFrame {
+                    typ: "CALL".to_string(),
+                    to: Some(factory),
+                    gas_used: U256::from(100_000),
+                    output: Some(account.into_word().to_vec().into()),
+                    calls: vec![GethDebugTracerCallFrame {
+                        typ: "CREATE2".to_string(),
+                        to: Some(account),
+                        output: Some(account_code.clone()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }))
+            });
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        let res = simulator
+            .simulate_factory(factory_init_code(factory), B256::ZERO.into())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res,
+            FactorySimResult {
+                address: account,
+                gas: 100_000,
+                code_hash: keccak256(bytes!("6080")),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_factory_revert() {
+        let (mut provider, entry_point, context_provider) = create_base_config();
+        let factory = Address::random();
+
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(bytes!("60806040")));
+        provider
+            .expect_debug_trace_call()
+            .returning(move |_, _, _| {
+                Ok(GethTrace::CallTracer(GethDebugTracerCallFrame {
+                    typ: "CALL".to_string(),
+                    to: Some(factory),
+                    error: Some("execution reverted".to_string()),
+                    revert_reason: Some("salt already used".to_string()),
+                    ..Default::default()
+                }))
+            });
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        let res = simulator
+            .simulate_factory(factory_init_code(factory), B256::ZERO.into())
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::UnintendedRevertWithMessage(
+                EntityType::Factory,
+                "salt already used".to_string(),
+                Some(factory),
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_factory_not_contract() {
+        let (mut provider, entry_point, context_provider) = create_base_config();
+        let factory = Address::random();
+
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(Bytes::new()));
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        let res = simulator
+            .simulate_factory(factory_init_code(factory), B256::ZERO.into())
+            .await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::FactoryIsNotContract(factory)]
+        );
+    }
+
+    fn parse_read_only_access(allow_unstaked_reads: bool) -> Vec<StorageRestriction> {
+        let access_info = AccessInfo {
+            reads: HashMap::from([(U256::from(1), U256::ZERO)]),
+            writes: HashMap::new(),
+        };
+        let slots_by_address = AssociatedSlotsByAddress(HashMap::new());
+        let entity = Entity::paymaster(Address::repeat_byte(1));
+        parse_storage_accesses(ParseStorageAccess {
+            access_info: &access_info,
+            slots_by_address: &slots_by_address,
+            address: Address::repeat_byte(2),
+            sender: Address::repeat_byte(3),
+            entrypoint: Address::repeat_byte(4),
+            has_factory: false,
+            entity: &entity,
+            entity_infos: &EntityInfos::default(),
+            allow_unstaked_reads,
+            allow_sender_factory_associated_storage: false,
+            reject_cross_sender_associated_storage: false,
+        })
+    }
+
+    fn parse_sender_factory_access(
+        allow_sender_factory_associated_storage: bool,
+    ) -> Vec<StorageRestriction> {
+        let sender = Address::repeat_byte(3);
+        let associated_slot = U256::from_be_bytes(sender.into_word().into());
+        let access_info = AccessInfo {
+            reads: HashMap::from([(associated_slot, U256::ZERO)]),
+            writes: HashMap::from([(U256::from(1), 1)]),
+        };
+        let slots_by_address = AssociatedSlotsByAddress(HashMap::new());
+        // the factory deploys the account at its own address
+        let entity = Entity::factory(sender);
+        parse_storage_accesses(ParseStorageAccess {
+            access_info: &access_info,
+            slots_by_address: &slots_by_address,
+            address: Address::repeat_byte(2),
+            sender,
+            entrypoint: Address::repeat_byte(4),
+            has_factory: true,
+            entity: &entity,
+            entity_infos: &EntityInfos::default(),
+            allow_unstaked_reads: false,
+            allow_sender_factory_associated_storage,
+            reject_cross_sender_associated_storage: false,
+        })
+    }
+
+    #[test]
+    fn test_sender_factory_storage_access() {
+        let associated_slot = U256::from_be_bytes(Address::repeat_byte(3).into_word().into());
+        // associated storage needs a staked factory and the unassociated write is banned
+        assert_eq!(
+            parse_sender_factory_access(false),
+            vec![
+                StorageRestriction::AssociatedStorageDuringDeploy(
+                    None,
+                    Address::repeat_byte(2),
+                    associated_slot,
+                ),
+                StorageRestriction::Banned(U256::from(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sender_factory_storage_access_allowed() {
+        assert_eq!(
+            parse_sender_factory_access(true),
+            vec![StorageRestriction::Banned(U256::from(1))]
+        );
+    }
+
+    fn parse_cross_sender_access(slot: U256, reject: bool) -> Vec<StorageRestriction> {
+        let access_info = AccessInfo {
+            reads: HashMap::from([(slot, U256::ZERO)]),
+            writes: HashMap::new(),
+        };
+        let slots_by_address = AssociatedSlotsByAddress(HashMap::from([
+            (Address::repeat_byte(5), BTreeSet::from([U256::from(100)])),
+            (Address::repeat_byte(9), BTreeSet::from([U256::from(200)])),
+        ]));
+        let mut entity_infos = EntityInfos::default();
+        entity_infos.set_sender(Address::repeat_byte(3), false);
+        entity_infos.set_factory(Address::repeat_byte(5), false);
+        entity_infos.set_paymaster(Address::repeat_byte(1), false);
+        let paymaster = Entity::paymaster(Address::repeat_byte(1));
+        // the paymaster reads a token contract
+        parse_storage_accesses(ParseStorageAccess {
+            access_info: &access_info,
+            slots_by_address: &slots_by_address,
+            address: Address::repeat_byte(2),
+            sender: Address::repeat_byte(3),
+            entrypoint: Address::repeat_byte(4),
+            has_factory: true,
+            entity: &paymaster,
+            entity_infos: &entity_infos,
+            allow_unstaked_reads: true,
+            allow_sender_factory_associated_storage: false,
+            reject_cross_sender_associated_storage: reject,
+        })
+    }
+
+    #[test]
+    fn test_cross_sender_associated_storage() {
+        // a slot associated with an address unrelated to the op
+        let slot = U256::from(300);
+        assert_eq!(
+            parse_cross_sender_access(slot, true),
+            vec![StorageRestriction::CrossSenderAssociated(
+                Address::repeat_byte(9),
+                slot
+            )]
+        );
+        // allowed when not rejecting
+        assert_eq!(parse_cross_sender_access(slot, false), vec![]);
+        // slots associated with the op's entities are not flagged
+        assert_eq!(parse_cross_sender_access(U256::from(101), true), vec![]);
+    }
+
+    fn parse_paymaster_self_access_during_deploy(slot: U256) -> Vec<StorageRestriction> {
+        let access_info = AccessInfo {
+            reads: HashMap::from([(slot, U256::ZERO)]),
+            writes: HashMap::new(),
+        };
+        let slots_by_address = AssociatedSlotsByAddress(HashMap::new());
+        let paymaster = Entity::paymaster(Address::repeat_byte(1));
+        parse_storage_accesses(ParseStorageAccess {
+            access_info: &access_info,
+            slots_by_address: &slots_by_address,
+            address: paymaster.address,
+            sender: Address::repeat_byte(3),
+            entrypoint: Address::repeat_byte(4),
+            has_factory: true,
+            entity: &paymaster,
+            entity_infos: &EntityInfos::default(),
+            allow_unstaked_reads: true,
+            allow_sender_factory_associated_storage: false,
+            reject_cross_sender_associated_storage: false,
+        })
+    }
+
+    #[test]
+    fn test_paymaster_self_storage_during_deploy_needs_paymaster_stake() {
+        // [STO-031] the paymaster's own storage needs the paymaster staked, a staked
+        // factory doesn't cover it, even for slots associated with the sender
+        let sender_associated_slot =
+            U256::from_be_bytes(Address::repeat_byte(3).into_word().into());
+        for slot in [U256::from(1), sender_associated_slot] {
+            assert_eq!(
+                parse_paymaster_self_access_during_deploy(slot),
+                vec![StorageRestriction::NeedsStake(
+                    EntityType::Paymaster,
+                    EntityType::Paymaster,
+                    Some(EntityType::Paymaster),
+                    Address::repeat_byte(1),
+                    slot,
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn test_unstaked_read_needs_stake_by_default() {
+        assert_eq!(
+            parse_read_only_access(false),
+            vec![StorageRestriction::NeedsStake(
+                EntityType::Paymaster,
+                EntityType::Paymaster,
+                None,
+                Address::repeat_byte(2),
+                U256::from(1),
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unstaked_read_allowed_when_enabled() {
+        assert_eq!(parse_read_only_access(true), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_gather_context_violations() {
+        let (provider, mut entry_point, mut context_provider) = create_base_config();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+
+        // add forbidden opcodes and precompiles
+        context.tracer_out.phases[1].forbidden_opcodes_used = vec![
+            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GASPRICE"),
+            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:COINBASE"),
+        ];
+        context.tracer_out.phases[1].forbidden_precompiles_used = vec![String::from(
+            "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:0x0000000000000000000000000000000000000019",
+        )];
+
+        // add a storage access for a random unrelated address
+        let mut writes: HashMap<U256, u64> = HashMap::new();
+
+        writes.insert(
+            uint!(0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b_U256),
+            1,
+        );
+
+        context.tracer_out.phases[1].storage_accesses.insert(
+            address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
+            AccessInfo {
+                reads: HashMap::new(),
+                writes,
+            },
+        );
+
+        let simulator = create_simulator(provider, entry_point, context_provider);
+        let res = simulator.gather_context_violations(&mut context);
+
+        assert_eq!(
+            res.unwrap(),
+            vec![
+                SimulationViolation::UsedForbiddenOpcode(
+                    Entity {
+                        kind: EntityType::Account,
+                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                    },
+                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                    ViolationOpCode(Opcode::GASPRICE),
+                ),
+                SimulationViolation::UsedForbiddenOpcode(
+                    Entity {
+                        kind: EntityType::Account,
+                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                    },
+                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                    ViolationOpCode(Opcode::COINBASE),
+                ),
+                SimulationViolation::UsedForbiddenPrecompile(
+                    Entity {
+                        kind: EntityType::Account,
+                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                    },
+                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
+                    address!("0000000000000000000000000000000000000019"),
+                ),
+                SimulationViolation::InvalidStorageAccess(
+                    Entity {
+                        kind: EntityType::Account,
+                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
+                    },
+                    StorageSlot {
+                        address: address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
+                        slot: uint!(
+                            0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b_U256
+                        )
+                    }
+                ),
+            ]
+        );
     }
 
     #[tokio::test]
-    async fn test_gather_context_violations() {
-        let (provider, mut entry_point, mut context_provider) = create_base_config();
-        entry_point
-            .expect_address()
-            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+    async fn test_op_080() {
+        let (provider, ep, mut context_provider) = create_base_config();
         context_provider
             .expect_get_specific_violations()
             .returning(|_| Ok(vec![]));
@@ -943,121 +2828,519 @@ mod tests {
         let mut context = get_test_context();
 
         // add forbidden opcodes and precompiles
-        context.tracer_out.phases[1].forbidden_opcodes_used = vec![
-            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GASPRICE"),
-            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:COINBASE"),
+        context.tracer_out.phases[2].forbidden_opcodes_used = vec![
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:SELFBALANCE"),
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:BALANCE"),
         ];
-        context.tracer_out.phases[1].forbidden_precompiles_used = vec![String::from(
-            "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:0x0000000000000000000000000000000000000019",
+
+        let simulator = create_simulator(provider, ep, context_provider);
+        let res = simulator.gather_context_violations(&mut context);
+
+        // unstaked causes errors
+        assert_eq!(
+            res.unwrap(),
+            vec![
+                SimulationViolation::UsedForbiddenOpcode(
+                    Entity {
+                        kind: EntityType::Paymaster,
+                        address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                    },
+                    address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                    ViolationOpCode(Opcode::SELFBALANCE)
+                ),
+                SimulationViolation::UsedForbiddenOpcode(
+                    Entity {
+                        kind: EntityType::Paymaster,
+                        address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                    },
+                    address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                    ViolationOpCode(Opcode::BALANCE)
+                )
+            ]
+        );
+
+        // staked causes no errors
+        context.entity_infos.paymaster.as_mut().unwrap().is_staked = true;
+        let res = simulator.gather_context_violations(&mut context);
+        assert!(res.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_basefee_banned() {
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].forbidden_opcodes_used = vec![String::from(
+            "0x8abb13360b87be5eeb1b98647a016add927a136c:BASEFEE",
         )];
 
-        // add a storage access for a random unrelated address
-        let mut writes: HashMap<U256, u64> = HashMap::new();
+        let simulator = create_simulator(provider, ep, context_provider);
+        let res = simulator.gather_context_violations(&mut context);
 
-        writes.insert(
-            uint!(0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b_U256),
-            1,
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::UsedForbiddenOpcode(
+                Entity {
+                    kind: EntityType::Paymaster,
+                    address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                },
+                address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                ViolationOpCode(Opcode::BASEFEE)
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_basefee_allowed() {
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].forbidden_opcodes_used = vec![
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:BASEFEE"),
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:TIMESTAMP"),
+        ];
+
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                allow_basefee: true,
+                ..Default::default()
+            },
+        );
+        let res = simulator.gather_context_violations(&mut context);
+
+        // only BASEFEE is allowed
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::UsedForbiddenOpcode(
+                Entity {
+                    kind: EntityType::Paymaster,
+                    address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                },
+                address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                ViolationOpCode(Opcode::TIMESTAMP)
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_origin_banned() {
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].forbidden_opcodes_used = vec![String::from(
+            "0x8abb13360b87be5eeb1b98647a016add927a136c:ORIGIN",
+        )];
+
+        let simulator = create_simulator(provider, ep, context_provider);
+        let violations = simulator.gather_context_violations(&mut context).unwrap();
+
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::UsedForbiddenOpcode(
+                Entity {
+                    kind: EntityType::Paymaster,
+                    address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                },
+                address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                ViolationOpCode(Opcode::ORIGIN)
+            )]
+        );
+        assert!(violations[0]
+            .to_string()
+            .contains("uses banned opcode: ORIGIN"));
+    }
+
+    #[tokio::test]
+    async fn test_origin_allowed() {
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].forbidden_opcodes_used = vec![
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:ORIGIN"),
+            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:BASEFEE"),
+        ];
+
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                allow_origin: true,
+                ..Default::default()
+            },
+        );
+        let res = simulator.gather_context_violations(&mut context);
+
+        // only ORIGIN is allowed
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::UsedForbiddenOpcode(
+                Entity {
+                    kind: EntityType::Paymaster,
+                    address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                },
+                address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                ViolationOpCode(Opcode::BASEFEE)
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_call_depth_too_deep() {
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        // paymaster validation nested 10 calls deep
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].max_call_depth = 10;
+
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                max_validation_call_depth: 8,
+                ..Default::default()
+            },
+        );
+        let res = simulator.gather_context_violations(&mut context);
+
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::CallDepthTooDeep(
+                Entity {
+                    kind: EntityType::Paymaster,
+                    address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                },
+                10,
+                8,
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_factory_did_not_deploy_sender() {
+        let factory = address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789");
+        let sender = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = get_test_context();
+        context.op = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender,
+                init_code: [factory.as_slice(), &[0xde, 0xad]].concat().into(),
+                verification_gas_limit: 2000,
+                pre_verification_gas: 1000,
+                ..Default::default()
+            },
+        )
+        .build();
+        // the factory deploys a contract, but at the wrong address
+        context.tracer_out.deployed_contracts = vec![Address::repeat_byte(0xaa)];
+
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                require_factory_deploys_sender: true,
+                ..Default::default()
+            },
+        );
+        let res = simulator.gather_context_violations(&mut context);
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::FactoryDidNotDeploySender(
+                factory, sender
+            )]
+        );
+
+        context.tracer_out.deployed_contracts = vec![sender];
+        let res = simulator.gather_context_violations(&mut context);
+        assert_eq!(res.unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_stake_shortfall_partially_staked_paymaster() {
+        let paymaster = address!("8abb13360b87be5eeb1b98647a016add927a136c");
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        // the paymaster has enough stake, but its unstake delay is too short
+        let settings = Settings::default();
+        let mut context = get_test_context();
+        context.entry_point_out.paymaster_info = StakeInfo {
+            stake: settings.min_stake_value,
+            unstake_delay_sec: 3600,
+        };
+        // and it accesses its own storage, which requires stake
+        context.tracer_out.phases[2].storage_accesses.insert(
+            paymaster,
+            AccessInfo {
+                reads: HashMap::new(),
+                writes: HashMap::from([(U256::from(1), 1)]),
+            },
+        );
+
+        let simulator = create_simulator(provider, ep, context_provider);
+        let violations = simulator.gather_context_violations(&mut context).unwrap();
+
+        let shortfalls = stake_shortfalls(&violations);
+        assert_eq!(
+            shortfalls,
+            vec![EntityStakeShortfall {
+                entity: Entity::paymaster(paymaster),
+                current_stake: settings.min_stake_value,
+                required_stake: settings.min_stake_value,
+                current_delay: 3600,
+                required_delay: settings.min_unstake_delay,
+            }]
+        );
+        assert_eq!(shortfalls[0].missing_stake(), U256::ZERO);
+    }
+
+    // paymaster phase context that accesses a precompile and an undeployed system contract
+    fn delegatecall_test_context(opcode: Opcode) -> ValidationContext<UserOperation> {
+        let precompile = address!("0000000000000000000000000000000000000064");
+        let system_contract = address!("4200000000000000000000000000000000000015");
+
+        let mut context = get_test_context();
+        context.tracer_out.phases[2].forbidden_precompiles_used = vec![format!(
+            "0x8abb13360b87be5eeb1b98647a016add927a136c:{precompile}"
+        )];
+        context.tracer_out.phases[2].undeployed_contract_accesses = vec![system_contract];
+        for address in [precompile, system_contract] {
+            context.tracer_out.accessed_contracts.insert(
+                address,
+                ContractInfo {
+                    header: "0x".to_string(),
+                    opcode,
+                    length: 0,
+                },
+            );
+        }
+        context
+    }
+
+    #[tokio::test]
+    async fn test_allowed_delegatecall_target() {
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let mut context = delegatecall_test_context(Opcode::DELEGATECALL);
+
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                allowed_delegatecall_targets: HashSet::from([
+                    address!("0000000000000000000000000000000000000064"),
+                    address!("4200000000000000000000000000000000000015"),
+                ]),
+                ..Default::default()
+            },
         );
+        let res = simulator.gather_context_violations(&mut context);
+
+        assert!(res.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_delegatecall_target() {
+        let paymaster = Entity {
+            kind: EntityType::Paymaster,
+            address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+        };
+        let expected = vec![
+            SimulationViolation::UsedForbiddenPrecompile(
+                paymaster,
+                address!("8abb13360b87be5eeb1b98647a016add927a136c"),
+                address!("0000000000000000000000000000000000000064"),
+            ),
+            SimulationViolation::AccessedUndeployedContract(
+                paymaster,
+                address!("4200000000000000000000000000000000000015"),
+            ),
+        ];
 
-        context.tracer_out.phases[1].storage_accesses.insert(
-            address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
-            AccessInfo {
-                reads: HashMap::new(),
-                writes,
+        // targets not on the chain's list are banned
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let mut context = delegatecall_test_context(Opcode::DELEGATECALL);
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                allowed_delegatecall_targets: HashSet::from([Address::random()]),
+                ..Default::default()
             },
         );
-
-        let simulator = create_simulator(provider, entry_point, context_provider);
         let res = simulator.gather_context_violations(&mut context);
+        assert_eq!(res.unwrap(), expected);
 
-        assert_eq!(
-            res.unwrap(),
-            vec![
-                SimulationViolation::UsedForbiddenOpcode(
-                    Entity {
-                        kind: EntityType::Account,
-                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
-                    },
-                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                    ViolationOpCode(Opcode::GASPRICE),
-                ),
-                SimulationViolation::UsedForbiddenOpcode(
-                    Entity {
-                        kind: EntityType::Account,
-                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
-                    },
-                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                    ViolationOpCode(Opcode::COINBASE),
-                ),
-                SimulationViolation::UsedForbiddenPrecompile(
-                    Entity {
-                        kind: EntityType::Account,
-                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
-                    },
-                    address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4"),
-                    address!("0000000000000000000000000000000000000019"),
-                ),
-                SimulationViolation::InvalidStorageAccess(
-                    Entity {
-                        kind: EntityType::Account,
-                        address: address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4")
-                    },
-                    StorageSlot {
-                        address: address!("1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6"),
-                        slot: uint!(
-                            0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b_U256
-                        )
-                    }
-                ),
-            ]
+        // targets on the list are banned when not reached by DELEGATECALL
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let mut context = delegatecall_test_context(Opcode::CALL);
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                allowed_delegatecall_targets: HashSet::from([
+                    address!("0000000000000000000000000000000000000064"),
+                    address!("4200000000000000000000000000000000000015"),
+                ]),
+                ..Default::default()
+            },
         );
+        let res = simulator.gather_context_violations(&mut context);
+        assert_eq!(res.unwrap(), expected);
     }
 
     #[tokio::test]
-    async fn test_op_080() {
+    async fn test_factory_calls_create2_deployer() {
+        let factory = Entity::factory(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        let deployer = address!("4e59b44847b379578588920ca78fbf26c0b4956c");
+        let slot = U256::from(1);
+
+        let test_context = || {
+            let mut context = get_test_context();
+            // the unstaked factory deploys the sender through the canonical deployer,
+            // which records the deployment in its own storage
+            context.tracer_out.phases[0].storage_accesses = HashMap::from([(
+                deployer,
+                AccessInfo {
+                    reads: HashMap::new(),
+                    writes: HashMap::from([(slot, 1)]),
+                },
+            )]);
+            context
+        };
+
+        // without the deployer configured, writing its storage is banned
         let (provider, ep, mut context_provider) = create_base_config();
         context_provider
             .expect_get_specific_violations()
             .returning(|_| Ok(vec![]));
+        let simulator = create_simulator(provider, ep, context_provider);
+        let res = simulator.gather_context_violations(&mut test_context());
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::InvalidStorageAccess(
+                factory,
+                StorageSlot {
+                    address: deployer,
+                    slot
+                },
+            )]
+        );
 
-        let mut context = get_test_context();
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                allowed_create2_deployers: HashSet::from([deployer]),
+                ..Default::default()
+            },
+        );
+        let mut context = test_context();
+        let res = simulator.gather_context_violations(&mut context);
+        assert!(res.unwrap().is_empty());
+        // the deployer's code is still part of the op's code hash
+        assert!(context.accessed_addresses.contains(&deployer));
+    }
 
-        // add forbidden opcodes and precompiles
-        context.tracer_out.phases[2].forbidden_opcodes_used = vec![
-            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:SELFBALANCE"),
-            String::from("0x8abb13360b87be5eeb1b98647a016add927a136c:BALANCE"),
-        ];
+    #[tokio::test]
+    async fn test_undeployed_ext_code_access() {
+        let paymaster = Entity::paymaster(address!("8abb13360b87be5eeb1b98647a016add927a136c"));
+        let undeployed = Address::repeat_byte(0xaa);
+
+        let test_context = || {
+            let mut context = get_test_context();
+            let sender = context.entity_infos.sender_address();
+            // the paymaster checks the code size of an address with no code
+            context.tracer_out.phases[2].undeployed_contract_accesses = vec![undeployed];
+            context.tracer_out.phases[2].undeployed_ext_code_accesses =
+                HashMap::from([(undeployed, Opcode::EXTCODESIZE)]);
+            // the account checks its own code hash, which is deployed by the op itself
+            context.tracer_out.phases[1].undeployed_ext_code_accesses =
+                HashMap::from([(sender, Opcode::EXTCODEHASH)]);
+            context
+        };
 
+        // reported as a generic undeployed contract access by default
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
         let simulator = create_simulator(provider, ep, context_provider);
-        let res = simulator.gather_context_violations(&mut context);
-
-        // unstaked causes errors
+        let res = simulator.gather_context_violations(&mut test_context());
         assert_eq!(
             res.unwrap(),
-            vec![
-                SimulationViolation::UsedForbiddenOpcode(
-                    Entity {
-                        kind: EntityType::Paymaster,
-                        address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
-                    },
-                    address!("8abb13360b87be5eeb1b98647a016add927a136c"),
-                    ViolationOpCode(Opcode::SELFBALANCE)
-                ),
-                SimulationViolation::UsedForbiddenOpcode(
-                    Entity {
-                        kind: EntityType::Paymaster,
-                        address: address!("8abb13360b87be5eeb1b98647a016add927a136c"),
-                    },
-                    address!("8abb13360b87be5eeb1b98647a016add927a136c"),
-                    ViolationOpCode(Opcode::BALANCE)
-                )
-            ]
+            vec![SimulationViolation::AccessedUndeployedContract(
+                paymaster, undeployed
+            )]
         );
 
-        // staked causes no errors
-        context.entity_infos.paymaster.as_mut().unwrap().is_staked = true;
-        let res = simulator.gather_context_violations(&mut context);
-        assert!(res.unwrap().is_empty());
+        let (provider, ep, mut context_provider) = create_base_config();
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                reject_undeployed_ext_code_access: true,
+                ..Default::default()
+            },
+        );
+        let res = simulator.gather_context_violations(&mut test_context());
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::UndeployedExtCodeAccess(
+                paymaster,
+                undeployed,
+                ViolationOpCode(Opcode::EXTCODESIZE),
+            )]
+        );
     }
 
     #[tokio::test]
@@ -1224,6 +3507,51 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_simulate_validation_mock_aggregator() {
+        let (mut provider, mut ep, mut context_provider) = create_base_config();
+        ep.expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::random()));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        let agg = Address::random();
+        let mut context = get_test_context();
+        context.op = context.op.transform_for_aggregator(
+            &ChainSpec::default(),
+            agg,
+            AggregatorCosts::default(),
+            Bytes::new(),
+        );
+        let op = context.op.clone();
+        context_provider
+            .expect_get_context()
+            .returning(move |_, _| Ok(context.clone()));
+
+        let out = AggregatorSimOut {
+            address: agg,
+            signature: bytes!("1234"),
+        };
+        let simulator =
+            create_simulator(provider, ep, context_provider).with_mock_aggregator(out.clone());
+        let res = simulator
+            .simulate_validation(op, false, B256::ZERO.into(), None)
+            .await
+            .unwrap();
+
+        let aggregator = res.aggregator.unwrap();
+        assert_eq!(aggregator.address, out.address);
+        assert_eq!(aggregator.signature, out.signature);
+        assert_eq!(
+            res.entity_infos.aggregator.map(|info| info.entity.address),
+            Some(agg)
+        );
+    }
+
     #[tokio::test]
     async fn test_aggregator_mismatch_zero() {
         let (provider, mut ep, mut context_provider) = create_base_config();
@@ -1253,6 +3581,37 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_aggregator_mismatch_none_found() {
+        let (provider, mut ep, mut context_provider) = create_base_config();
+        ep.expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        // the op declares an aggregator, but the entry point found none
+        let declared_agg = Address::random();
+        let mut context = get_test_context();
+        context.op = context.op.transform_for_aggregator(
+            &ChainSpec::default(),
+            declared_agg,
+            AggregatorCosts::default(),
+            Bytes::new(),
+        );
+
+        let simulator = create_simulator(provider, ep, context_provider);
+        let res = simulator.gather_context_violations(&mut context);
+
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::AggregatorMismatch(
+                declared_agg,
+                Address::ZERO
+            )]
+        );
+    }
+
     #[tokio::test]
     async fn test_invalid_time_range() {
         let (provider, mut ep, mut context_provider) = create_base_config();
@@ -1279,6 +3638,34 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_contradictory_time_range() {
+        let (provider, mut ep, mut context_provider) = create_base_config();
+        ep.expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        context_provider
+            .expect_get_specific_violations()
+            .returning(|_| Ok(vec![]));
+
+        // valid until before it is valid after, so it never becomes valid
+        let mut context = get_test_context();
+        let valid_after = Timestamp::now() + Duration::from_secs(100);
+        let valid_until = Timestamp::now() + Duration::from_secs(50);
+        context.entry_point_out.return_info.valid_after = valid_after;
+        context.entry_point_out.return_info.valid_until = valid_until;
+
+        let simulator = create_simulator(provider, ep, context_provider);
+        let res = simulator.gather_context_violations(&mut context);
+
+        assert_eq!(
+            res.unwrap(),
+            vec![SimulationViolation::ReversedTimeRange(
+                valid_until,
+                valid_after
+            )]
+        );
+    }
+
     #[tokio::test]
     async fn test_code_hash_zero() {
         // test that ensures we don't get a code hash changed violation if the expected code hash is zero
@@ -1309,6 +3696,102 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_empty_code_hash_accepted_by_default() {
+        let (mut provider, ep, context_provider) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+
+        let mut context = get_test_context();
+        let simulator = create_simulator(provider, ep, context_provider);
+        let res = simulator.check_code_hash(&mut context, None).await;
+        assert_eq!(res.unwrap(), B256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_empty_code_hash_rejected_when_enabled() {
+        let (mut provider, ep, context_provider) = create_base_config();
+        provider
+            .expect_get_code_hash()
+            .returning(|_, _| Ok(B256::ZERO));
+
+        let mut context = get_test_context();
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                reject_empty_code_hash: true,
+                ..Default::default()
+            },
+        );
+        let res = simulator.check_code_hash(&mut context, None).await;
+
+        let ViolationError::Violations(violations) = res.unwrap_err().violation_error else {
+            panic!("expected violations");
+        };
+        assert_eq!(
+            violations,
+            vec![SimulationViolation::SuspiciousEmptyCodeHash]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_empty_code_hash_accepted_when_enabled() {
+        let (mut provider, ep, context_provider) = create_base_config();
+        let code_hash = B256::random();
+        provider
+            .expect_get_code_hash()
+            .returning(move |_, _| Ok(code_hash));
+
+        let mut context = get_test_context();
+        let simulator = create_simulator_with_settings(
+            provider,
+            ep,
+            context_provider,
+            Settings {
+                reject_empty_code_hash: true,
+                ..Default::default()
+            },
+        );
+        let res = simulator.check_code_hash(&mut context, None).await;
+        assert_eq!(res.unwrap(), code_hash);
+    }
+
+    #[tokio::test]
+    async fn test_code_hash_addresses_limit() {
+        let (mut provider, ep, context_provider) = create_base_config();
+
+        let mut context = get_test_context();
+        let mut addresses = (0..4).map(|_| Address::random()).collect::<Vec<_>>();
+        for address in &addresses {
+            context.tracer_out.accessed_contracts.insert(
+                *address,
+                ContractInfo {
+                    header: "0x".to_string(),
+                    opcode: Opcode::CALL,
+                    length: 32,
+                },
+            );
+        }
+        addresses.sort();
+        addresses.truncate(2);
+
+        provider
+            .expect_get_code_hash()
+            .withf(move |hashed, _| *hashed == addresses)
+            .returning(|_, _| Ok(B256::ZERO));
+
+        let settings = Settings {
+            code_hash_addresses_limit: Some(2),
+            ..Default::default()
+        };
+        let simulator = create_simulator_with_settings(provider, ep, context_provider, settings);
+        let res = simulator.check_code_hash(&mut context, None).await;
+        assert!(res.is_ok());
+    }
+
     #[tokio::test]
     async fn test_code_hash_changed() {
         // test that ensures we get a code hash changed violation if the expected code hash is not zero
@@ -1345,4 +3828,31 @@ mod tests {
         };
         assert_eq!(*violation, SimulationViolation::CodeHashChanged);
     }
+
+    #[test]
+    fn test_override_infos_staked() {
+        let unstaked = |entity| EntityInfo {
+            entity,
+            is_staked: false,
+        };
+        let (sender, factory, paymaster, aggregator) = (
+            Address::random(),
+            Address::random(),
+            Address::random(),
+            Address::random(),
+        );
+        let mut eis = EntityInfos {
+            sender: unstaked(Entity::account(sender)),
+            factory: Some(unstaked(Entity::factory(factory))),
+            paymaster: Some(unstaked(Entity::paymaster(paymaster))),
+            aggregator: Some(unstaked(Entity::aggregator(aggregator))),
+        };
+
+        override_infos_staked(&mut eis, &HashSet::from([factory, paymaster, aggregator]));
+
+        assert!(!eis.sender.is_staked);
+        assert!(eis.factory.unwrap().is_staked);
+        assert!(eis.paymaster.unwrap().is_staked);
+        assert!(eis.aggregator.unwrap().is_staked);
+    }
 }
@@ -13,13 +13,14 @@
 
 use std::marker::PhantomData;
 
-use alloy_primitives::{map::HashSet, Address, B256};
+use alloy_primitives::{map::HashSet, Bytes, B256};
 use rundler_provider::{EntryPoint, SimulationProvider};
 use rundler_types::{
-    pool::SimulationViolation, ExpectedStorage, UserOperation, ValidTimeRange, TIME_RANGE_BUFFER,
+    pool::SimulationViolation, ExpectedStorage, Timestamp, UserOperation, ValidTimeRange,
+    TIME_RANGE_BUFFER,
 };
 
-use super::Settings;
+use super::{limiter::SimulationLimiter, BlockTag, FactorySimResult, MempoolEvaluation, Settings};
 use crate::{simulation::context, SimulationError, SimulationResult, Simulator, ViolationError};
 
 /// An unsafe simulator that can be used in place of a regular simulator
@@ -32,6 +33,8 @@ use crate::{simulation::context, SimulationError, SimulationResult, Simulator, V
 pub struct UnsafeSimulator<UO, E> {
     entry_point: E,
     settings: Settings,
+    limiter: SimulationLimiter,
+    timestamp_override: Option<u64>,
     _uo_type: PhantomData<UO>,
 }
 
@@ -40,10 +43,19 @@ impl<UO, E> UnsafeSimulator<UO, E> {
     pub fn new(entry_point: E, settings: Settings) -> Self {
         Self {
             entry_point,
+            limiter: SimulationLimiter::new(settings.max_concurrent_simulations),
             settings,
+            timestamp_override: None,
             _uo_type: PhantomData,
         }
     }
+
+    /// Simulate with the given block timestamp instead of the block's own, checking each
+    /// operation's valid time range against it. Used to test time range logic.
+    pub fn with_timestamp_override(mut self, timestamp: u64) -> Self {
+        self.timestamp_override = Some(timestamp);
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -61,15 +73,32 @@ where
         &self,
         op: UO,
         _trusted: bool,
-        block_hash: B256,
+        block: BlockTag,
         _expected_code_hash: Option<B256>,
     ) -> Result<SimulationResult, SimulationError> {
         tracing::debug!("Performing unsafe simulation");
 
+        if let Some(violation) = context::op_size_violation(&op, &self.settings)
+            .or_else(|| context::malformed_op_violation(&op))
+            .or_else(|| context::priority_fee_violation(&op, &self.settings))
+            .or_else(|| context::pre_verification_gas_violation(&op, &self.settings))
+            .or_else(|| context::denied_entity_violation(&op, &self.settings))
+            .or_else(|| context::zero_call_gas_violation(&op, &self.settings))
+            .or_else(|| context::entry_point_call_violation(&op, *self.entry_point.address()))
+            .or_else(|| context::sender_is_entry_point_violation(&op, *self.entry_point.address()))
+        {
+            return Err(SimulationError {
+                violation_error: vec![violation].into(),
+                entity_infos: None,
+            });
+        }
+
+        let _permit = self.limiter.acquire().await;
+
         // simulate the validation
         let validation_result = self
             .entry_point
-            .simulate_validation(op.clone(), Some(block_hash.into()))
+            .simulate_validation(op.clone(), Some(block.into()), self.timestamp_override)
             .await?;
 
         let validation_result = match validation_result {
@@ -82,6 +111,11 @@ where
             }
         };
 
+        let validation_output = self
+            .settings
+            .include_validation_output
+            .then(|| validation_result.clone());
+
         let valid_until = if validation_result.return_info.valid_until == 0.into() {
             u64::MAX.into()
         } else {
@@ -103,20 +137,14 @@ where
 
         let mut violations = vec![];
 
-        if let Some(agg_info) = validation_result.aggregator_info {
-            if let Some(agg) = op.aggregator() {
-                if agg_info.address != agg {
-                    violations.push(SimulationViolation::AggregatorMismatch(
-                        agg,
-                        agg_info.address,
-                    ));
-                }
-            } else {
-                violations.push(SimulationViolation::AggregatorMismatch(
-                    Address::ZERO,
-                    agg_info.address,
-                ));
-            }
+        if let Some(violation) = context::aggregator_mismatch_violation(
+            op.aggregator(),
+            validation_result
+                .aggregator_info
+                .as_ref()
+                .map(|info| info.address),
+        ) {
+            violations.push(violation);
         }
 
         if validation_result.return_info.account_sig_failed {
@@ -127,12 +155,26 @@ where
             violations.push(SimulationViolation::InvalidPaymasterSignature);
         }
 
-        if !valid_time_range.is_valid_now(TIME_RANGE_BUFFER) {
+        let valid_time = match self.timestamp_override {
+            Some(timestamp) => {
+                valid_time_range.contains(Timestamp::new(timestamp), TIME_RANGE_BUFFER)
+            }
+            None => valid_time_range.is_valid_now(TIME_RANGE_BUFFER),
+        };
+        if !valid_time {
             violations.push(SimulationViolation::InvalidTimeRange(
                 valid_time_range.valid_until,
                 valid_time_range.valid_after,
             ));
         }
+        let now = self
+            .timestamp_override
+            .map_or_else(Timestamp::now, Timestamp::new);
+        if let Some(violation) =
+            context::valid_after_violation(valid_time_range.valid_after, now, &self.settings)
+        {
+            violations.push(violation);
+        }
 
         if !violations.is_empty() {
             Err(SimulationError {
@@ -147,6 +189,7 @@ where
                 pre_op_gas,
                 valid_time_range,
                 requires_post_op,
+                has_entity_role_overlap: entity_infos.has_role_overlap(),
                 entity_infos,
                 account_is_staked: context::is_staked(
                     validation_result.sender_info,
@@ -156,7 +199,109 @@ where
                 accessed_addresses: HashSet::new(),
                 associated_addresses: HashSet::new(),
                 expected_storage: ExpectedStorage::default(),
+                aggregator: None,
+                validation_output,
+                gas_breakdown: None,
+                validation_logs: None,
+                stake_shortfalls: vec![],
             })
         }
     }
+
+    // No mempool rules are checked, so every operation is reported as supported
+    // by the default mempool, matching the result of `simulate_validation`
+    async fn evaluate_mempools(
+        &self,
+        _op: UO,
+        _block: BlockTag,
+    ) -> Result<Vec<MempoolEvaluation>, SimulationError> {
+        Ok(vec![MempoolEvaluation {
+            id: B256::ZERO,
+            passed: true,
+            excluding_violations: vec![],
+        }])
+    }
+
+    async fn simulate_factory(
+        &self,
+        _init_code: Bytes,
+        _block: BlockTag,
+    ) -> Result<FactorySimResult, SimulationError> {
+        // tracing the factory call is exactly what this simulator avoids
+        Err(anyhow::anyhow!("factory simulation is not supported by the unsafe simulator").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy_primitives::address;
+    use rundler_provider::MockEntryPointV0_6;
+    use rundler_types::{v0_6::UserOperation, StakeInfo, ValidationOutput, ValidationReturnInfo};
+
+    use super::*;
+
+    // Simulates an op that only becomes valid at `valid_after`, checking that the entry
+    // point is called with `timestamp_override`
+    async fn simulate_future_op(
+        valid_after: Timestamp,
+        timestamp_override: Option<u64>,
+    ) -> Result<SimulationResult, SimulationError> {
+        let mut entry_point = MockEntryPointV0_6::new();
+        entry_point
+            .expect_address()
+            .return_const(address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789"));
+        entry_point
+            .expect_simulate_validation()
+            .withf(move |_, _, timestamp| *timestamp == timestamp_override)
+            .returning(move |_, _, _| {
+                Ok(Ok(ValidationOutput {
+                    return_info: ValidationReturnInfo {
+                        valid_after,
+                        ..Default::default()
+                    },
+                    sender_info: StakeInfo::default(),
+                    factory_info: StakeInfo::default(),
+                    paymaster_info: StakeInfo::default(),
+                    aggregator_info: None,
+                }))
+            });
+
+        let mut simulator = UnsafeSimulator::new(entry_point, Settings::default());
+        if let Some(timestamp) = timestamp_override {
+            simulator = simulator.with_timestamp_override(timestamp);
+        }
+        simulator
+            .simulate_validation(UserOperation::default(), false, B256::ZERO.into(), None)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_future_op_invalid_now() {
+        let valid_after = Timestamp::now() + Duration::from_secs(3600);
+
+        let Err(SimulationError {
+            violation_error: ViolationError::Violations(violations),
+            ..
+        }) = simulate_future_op(valid_after, None).await
+        else {
+            panic!("expected a time range violation");
+        };
+        assert!(matches!(
+            violations[..],
+            [SimulationViolation::InvalidTimeRange(_, _)]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_future_op_valid_at_timestamp_override() {
+        let valid_after = Timestamp::now() + Duration::from_secs(3600);
+
+        let res = simulate_future_op(valid_after, Some(valid_after.seconds_since_epoch()))
+            .await
+            .unwrap();
+
+        assert_eq!(res.valid_time_range.valid_after, valid_after);
+    }
 }
@@ -13,7 +13,7 @@
 
 use std::collections::HashSet;
 
-use alloy_primitives::hex;
+use alloy_primitives::{hex, keccak256, U256};
 use alloy_sol_types::SolError;
 use anyhow::Context;
 use rundler_contracts::v0_6::IEntryPoint::FailedOp;
@@ -56,7 +56,7 @@ where
         let factory_address = op.factory();
         let sender_address = op.sender();
         let paymaster_address = op.paymaster();
-        let tracer_out = self
+        let mut tracer_out = self
             .simulate_validation_tracer
             .trace_simulate_validation(op.clone(), block_id)
             .await?;
@@ -114,8 +114,17 @@ where
             &self.sim_settings,
         );
 
-        let associated_addresses = tracer_out.associated_slots_by_address.addresses();
         let has_factory = op.factory().is_some();
+        if has_factory {
+            // The factory initializes storage keyed by the sender before the sender exists, so the
+            // tracer may not have observed the keccak that derives those slots. Register the
+            // sender's base slot so these writes are treated as associated storage.
+            tracer_out.associated_slots_by_address.add_associated_slot(
+                sender_address,
+                U256::from_be_bytes(keccak256(sender_address.into_word()).0),
+            );
+        }
+        let associated_addresses = tracer_out.associated_slots_by_address.addresses();
         Ok(ValidationContext {
             op,
             block_id,
@@ -197,7 +206,7 @@ where
 mod tests {
     use std::collections::HashMap;
 
-    use alloy_primitives::{address, bytes, hex, Bytes, U256};
+    use alloy_primitives::{address, bytes, hex, keccak256, Bytes, U256};
     use alloy_sol_types::SolError;
     use rundler_contracts::v0_6::IEntryPoint::FailedOp;
     use rundler_types::{
@@ -252,6 +261,8 @@ mod tests {
             }
             "#).unwrap(),
             factory_called_create2_twice: false,
+            logs: vec![],
+            deployed_contracts: vec![],
             expected_storage: serde_json::from_str(r#"
             {
                 "0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789": {
@@ -270,6 +281,9 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    undeployed_ext_code_accesses: HashMap::new(),
+                    max_call_depth: 1,
+                    gas_used: 0,
                 },
                 Phase {
                     called_banned_entry_point_method: false,
@@ -280,6 +294,9 @@ mod tests {
                     storage_accesses:  HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    undeployed_ext_code_accesses: HashMap::new(),
+                    max_call_depth: 1,
+                    gas_used: 0,
                 },
                 Phase {
                     called_banned_entry_point_method: false,
@@ -290,6 +307,9 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    undeployed_ext_code_accesses: HashMap::new(),
+                    max_call_depth: 1,
+                    gas_used: 0,
                 }
             ],
             revert_data: Some("0xe0cff05f00000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000014eff00000000000000000000000000000000000000000000000000000b7679c50c24000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffff00000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000000".into()),
@@ -363,4 +383,81 @@ mod tests {
             )
         ));
     }
+
+    #[tokio::test]
+    async fn test_deploy_registers_sender_associated_slot() {
+        let mut tracer = MockTracer::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(|_, _| Ok(get_test_tracer_output()));
+
+        let sender = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let factory = address!("5ff137d4b0fdcd49dca30c7cf57e578a026d2789");
+        let slot = U256::from_be_bytes(keccak256(sender.into_word()).0) + U256::from(5);
+
+        let mut init_code = factory.to_vec();
+        init_code.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let user_operation = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender,
+                init_code: init_code.into(),
+                ..Default::default()
+            },
+        )
+        .build();
+
+        let context = ValidationContextProvider {
+            simulate_validation_tracer: tracer,
+            sim_settings: Default::default(),
+        };
+
+        let context = context
+            .get_context(user_operation, BlockId::Number(0.into()))
+            .await
+            .unwrap();
+
+        assert!(context.has_factory);
+        assert!(context
+            .tracer_out
+            .associated_slots_by_address
+            .is_associated_slot(sender, slot));
+    }
+
+    #[tokio::test]
+    async fn test_no_deploy_does_not_register_sender_associated_slot() {
+        let mut tracer = MockTracer::new();
+        tracer.expect_trace_simulate_validation().returning(|_, _| {
+            let mut tracer_output = get_test_tracer_output();
+            tracer_output.associated_slots_by_address.0.clear();
+            Ok(tracer_output)
+        });
+
+        let sender = address!("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4");
+        let slot = U256::from_be_bytes(keccak256(sender.into_word()).0) + U256::from(5);
+
+        let user_operation = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender,
+                ..Default::default()
+            },
+        )
+        .build();
+
+        let context = ValidationContextProvider {
+            simulate_validation_tracer: tracer,
+            sim_settings: Default::default(),
+        };
+
+        let context = context
+            .get_context(user_operation, BlockId::Number(0.into()))
+            .await
+            .unwrap();
+
+        assert!(!context
+            .tracer_out
+            .associated_slots_by_address
+            .is_associated_slot(sender, slot));
+    }
 }
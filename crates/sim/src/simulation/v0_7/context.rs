@@ -18,9 +18,7 @@ use alloy_primitives::{
     hex::{self, FromHex},
     keccak256, Address, Bytes, U256,
 };
-use alloy_sol_types::SolType;
 use anyhow::{bail, Context};
-use rundler_contracts::v0_7::ValidationResult;
 use rundler_provider::{BlockId, EntryPoint, EvmProvider, SimulationProvider};
 use rundler_types::{
     pool::SimulationViolation, v0_7::UserOperation, EntityInfos, EntityType, Opcode,
@@ -155,6 +153,32 @@ where
                 let phase = Self::get_nearest_entity_phase(&call_stack[i..], &entity_infos);
                 tracer_out.phases[phase].called_non_entry_point_with_value = true;
             }
+
+            if call.depth > 0 {
+                let phase = Self::get_nearest_entity_phase(&call_stack[i..], &entity_infos);
+                let max_call_depth = &mut tracer_out.phases[phase].max_call_depth;
+                *max_call_depth = (*max_call_depth).max(call.depth);
+            }
+
+            // Only the factory may create contracts, so every successful create is its doing
+            if matches!(call.call_type, Opcode::CREATE | Opcode::CREATE2)
+                && matches!(call.exit_type, ExitType::Return)
+            {
+                tracer_out.deployed_contracts.push(call.to);
+            }
+
+            // Each phase's gas is measured at the entry point's call into the entity
+            if call.depth == 1 {
+                let phase = match call.method.as_str() {
+                    CREATE_SENDER_METHOD => Some(0),
+                    VALIDATE_USER_OP_METHOD => Some(1),
+                    VALIDATE_PAYMASTER_USER_OP_METHOD => Some(2),
+                    _ => None,
+                };
+                if let Some(phase) = phase {
+                    tracer_out.phases[phase].gas_used += call.gas_used;
+                }
+            }
         }
 
         Ok(ValidationContext {
@@ -203,6 +227,8 @@ struct CallWithResult {
     gas_used: u64,
     exit_type: ExitType,
     exit_data: String,
+    // depth below the entry point, where calls made by the entry point are at depth 1
+    depth: u64,
 }
 
 impl<T> ValidationContextProvider<T> {
@@ -213,6 +239,7 @@ impl<T> ValidationContextProvider<T> {
         for call in calls {
             match call {
                 CallInfo::Exit(exit_info) => {
+                    let depth = call_stack.len() as u64;
                     let method_info: MethodInfo = call_stack
                         .pop()
                         .context("unbalanced call stack, exit without method call")?;
@@ -226,6 +253,7 @@ impl<T> ValidationContextProvider<T> {
                         gas_used: exit_info.gas_used,
                         exit_type: exit_info.exit_type,
                         exit_data: exit_info.data,
+                        depth,
                     });
                 }
                 CallInfo::Method(info) => {
@@ -247,6 +275,7 @@ impl<T> ValidationContextProvider<T> {
                     gas_used: exit_info.gas_used,
                     exit_type: exit_info.exit_type,
                     exit_data: exit_info.data,
+                    depth: 0,
                 });
             }
             CallInfo::Method(info) => {
@@ -271,11 +300,10 @@ impl<T> ValidationContextProvider<T> {
             ExitType::Return => {
                 let b = Bytes::from_hex(top.exit_data.clone())
                     .context("faled to parse exit data as hex")?;
-                if let Ok(res) = ValidationResult::abi_decode(&b) {
-                    Ok(Ok(res.try_into().map_err(anyhow::Error::msg)?))
-                } else {
+                let Ok(res) = rundler_provider::decode_v0_7_validation_result(&b) else {
                     bail!("Failed to decode validation output {}", top.exit_data);
-                }
+                };
+                Ok(Ok(res))
             }
         }
     }
@@ -349,6 +377,19 @@ impl<T> ValidationContextProvider<T> {
                 Self::check_associated_slot(addr, *p, k, &mut associated_slots_by_address)?;
             }
             Self::check_associated_slot(&sender.1, sender.0, k, &mut associated_slots_by_address)?;
+            // Finding storage associated with addresses unrelated to the op needs the slots
+            // of every address, as the v0.6 tracer records them
+            if self.sim_settings.reject_cross_sender_associated_storage {
+                if let Some(address) = Self::keccak_input_address(k) {
+                    associated_slots_by_address
+                        .entry(address)
+                        .or_default()
+                        .insert(
+                            keccak256(Bytes::from_hex(k).context("failed to parse keccak as hex")?)
+                                .into(),
+                        );
+                }
+            }
         }
 
         Ok(ContextTracerOutput {
@@ -358,12 +399,20 @@ impl<T> ValidationContextProvider<T> {
             associated_slots_by_address: AssociatedSlotsByAddress(associated_slots_by_address),
             factory_called_create2_twice,
             expected_storage: tracer_out.expected_storage,
+            // match the v0.6 tracer, which doesn't collect the entry point's own logs
+            logs: tracer_out
+                .logs
+                .into_iter()
+                .filter(|log| log.address != self.entry_point_address)
+                .collect(),
+            deployed_contracts: vec![],
         })
     }
 
     fn parse_call_to_phase(call: &TopLevelCallInfo, entity_type: EntityType) -> Phase {
         // [OP-011] - banned opcodes
-        // [OP-012] - tracer will not add GAS to list if followed by *CALL
+        // [OP-012] - tracer will not add GAS to list if immediately followed by
+        // CALL, CALLCODE, DELEGATECALL or STATICCALL
         let mut forbidden_opcodes_used = vec![];
         for opcode in call.opcodes.keys() {
             if BANNED_OPCODES.contains(opcode)
@@ -393,6 +442,7 @@ impl<T> ValidationContextProvider<T> {
 
         let mut forbidden_precompiles_used = vec![];
         let mut undeployed_contract_accesses = vec![];
+        let mut undeployed_ext_code_accesses = HashMap::new();
         call.contract_info.iter().for_each(|(address, info)| {
             if info.length == 0 {
                 if *address < MAX_PRECOMPILE_ADDRESS {
@@ -403,6 +453,9 @@ impl<T> ValidationContextProvider<T> {
                 } else {
                     // [OP-041]
                     undeployed_contract_accesses.push(*address);
+                    if matches!(info.opcode, Opcode::EXTCODESIZE | Opcode::EXTCODEHASH) {
+                        undeployed_ext_code_accesses.insert(*address, info.opcode);
+                    }
                 }
             }
         });
@@ -417,6 +470,9 @@ impl<T> ValidationContextProvider<T> {
             ran_out_of_gas: call.oog.unwrap_or(false),
             undeployed_contract_accesses,
             ext_code_access_info: call.ext_code_access_info.clone(),
+            undeployed_ext_code_accesses,
+            max_call_depth: 0, // set during call stack parsing
+            gas_used: 0,       // set during call stack parsing
         }
     }
 
@@ -434,6 +490,16 @@ impl<T> ValidationContextProvider<T> {
         Ok(())
     }
 
+    // The address in the first word of a keccak input, if that word is shaped like one
+    fn keccak_input_address(k: &str) -> Option<Address> {
+        let address = k
+            .strip_prefix("0x000000000000000000000000")?
+            .get(..40)?
+            .parse::<Address>()
+            .ok()?;
+        (!address.is_zero()).then_some(address)
+    }
+
     fn get_nearest_entity_phase(calls: &[CallWithResult], entities: &EntityInfos) -> usize {
         // Call stack is ordered in order in which calls complete.
         // To attribute a particular call to an entity, scan from that call forward until
@@ -474,3 +540,139 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::{
+        chain::ChainSpec,
+        v0_7::{UserOperationBuilder, UserOperationRequiredFields},
+    };
+
+    use super::*;
+
+    const TARGET: &str = "0x0000000000000000000000000000000000000001";
+
+    fn call_with_opcodes(opcodes: &str) -> TopLevelCallInfo {
+        serde_json::from_str(&format!(
+            r#"{{
+                "topLevelMethodSig": "{VALIDATE_USER_OP_METHOD}",
+                "topLevelTargetAddress": "{TARGET}",
+                "opcodes": {opcodes},
+                "access": {{}},
+                "contractInfo": {{}},
+                "extCodeAccessInfo": {{}},
+                "oog": false
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lone_gas_is_forbidden() {
+        // GAS followed by anything other than a call opcode is counted by the tracer
+        let call = call_with_opcodes(r#"{"GAS": 1, "CALLER": 1}"#);
+        let phase =
+            ValidationContextProvider::<()>::parse_call_to_phase(&call, EntityType::Account);
+        assert_eq!(phase.forbidden_opcodes_used, vec![format!("{TARGET}:GAS")]);
+    }
+
+    #[test]
+    fn test_gas_followed_by_call_is_allowed() {
+        // GAS immediately followed by a call opcode is not counted by the tracer
+        let call = call_with_opcodes(r#"{"CALL": 1}"#);
+        let phase =
+            ValidationContextProvider::<()>::parse_call_to_phase(&call, EntityType::Account);
+        assert!(phase.forbidden_opcodes_used.is_empty());
+    }
+
+    fn tracer_out_with_keccak(keccak: &[String]) -> TracerOutput {
+        serde_json::from_str(&format!(
+            r#"{{
+                "callsFromEntryPoint": [],
+                "keccak": {},
+                "calls": [],
+                "expectedStorage": {{}},
+                "logs": [],
+                "debug": null
+            }}"#,
+            serde_json::to_string(keccak).unwrap()
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cross_sender_associated_slots() {
+        let sender = Address::repeat_byte(3);
+        let other = Address::repeat_byte(9);
+        let op = UserOperationBuilder::new(
+            &ChainSpec::default(),
+            UserOperationRequiredFields {
+                sender,
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 0,
+                verification_gas_limit: 0,
+                pre_verification_gas: 0,
+                max_priority_fee_per_gas: 0,
+                max_fee_per_gas: 0,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        // a token balance mapping read for the sender and for an unrelated address
+        let keccak = [sender, other]
+            .map(|address| hex::encode_prefixed([address.into_word().0, [0; 32]].concat()));
+        let other_slot = U256::from_be_bytes(keccak256(hex::decode(&keccak[1]).unwrap()).0);
+
+        let associated_addresses = |reject_cross_sender_associated_storage| {
+            let provider = ValidationContextProvider {
+                simulate_validation_tracer: (),
+                sim_settings: SimulationSettings {
+                    reject_cross_sender_associated_storage,
+                    ..Default::default()
+                },
+                entry_point_address: Address::ZERO,
+            };
+            provider
+                .parse_tracer_out(&op, tracer_out_with_keccak(&keccak))
+                .unwrap()
+                .associated_slots_by_address
+                .associated_addresses(other_slot)
+                .collect::<Vec<_>>()
+        };
+
+        // only the op's entities are tracked unless cross sender storage is rejected
+        assert_eq!(associated_addresses(false), vec![]);
+        assert_eq!(associated_addresses(true), vec![other]);
+    }
+
+    #[test]
+    fn test_parse_call_stack_depth() {
+        let method = |to: u8| {
+            format!(
+                r#"{{"type": "CALL", "from": "{}", "to": "{}", "method": "0x", "value": null, "gas": 0}}"#,
+                Address::repeat_byte(to - 1),
+                Address::repeat_byte(to),
+            )
+        };
+        let exit = r#"{"type": "RETURN", "gasUsed": 0, "data": "0x"}"#;
+        // entry point -> account -> 2 -> 3, followed by a sibling call from the account
+        let calls: Vec<CallInfo> = serde_json::from_str(&format!(
+            "[{}, {}, {}, {exit}, {exit}, {}, {exit}, {exit}, {exit}]",
+            method(1),
+            method(2),
+            method(3),
+            method(2),
+        ))
+        .unwrap();
+
+        let provider = ValidationContextProvider {
+            simulate_validation_tracer: (),
+            sim_settings: SimulationSettings::default(),
+            entry_point_address: Address::ZERO,
+        };
+        let call_stack = provider.parse_call_stack(calls).unwrap();
+        let depths = call_stack.iter().map(|c| c.depth).collect::<Vec<_>>();
+        assert_eq!(depths, vec![3, 2, 2, 1, 0]);
+    }
+}
@@ -22,7 +22,7 @@ use rundler_provider::{
 use rundler_types::{v0_7::UserOperation, ExpectedStorage, Opcode};
 use serde::Deserialize;
 
-use crate::simulation::context::ContractInfo;
+use crate::simulation::context::{ContractInfo, LogInfo};
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -89,14 +89,6 @@ pub(super) struct MethodInfo {
     pub(super) gas: u64,
 }
 
-#[derive(Clone, Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-#[allow(unused)]
-pub(super) struct LogInfo {
-    pub(super) topics: Vec<String>,
-    pub(super) data: String,
-}
-
 impl TryFrom<GethTrace> for TracerOutput {
     type Error = anyhow::Error;
     fn try_from(trace: GethTrace) -> Result<Self, Self::Error> {
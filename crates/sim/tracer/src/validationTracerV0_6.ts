@@ -25,6 +25,8 @@ interface Output {
   associatedSlotsByAddress: Record<string, string[]>;
   factoryCalledCreate2Twice: boolean;
   expectedStorage: Record<string, Record<string, string>>;
+  logs: LogInfo[];
+  deployedContracts: string[];
 }
 
 interface Phase {
@@ -38,6 +40,9 @@ interface Phase {
   ranOutOfGas: boolean;
   undeployedContractAccesses: string[];
   extCodeAccessInfo: Record<string, string>;
+  undeployedExtCodeAccesses: Record<string, string>;
+  maxCallDepth: number;
+  gasUsed: number;
 }
 
 interface AccessInfo {
@@ -47,6 +52,12 @@ interface AccessInfo {
   writes: { [slot: string]: number }
 }
 
+interface LogInfo {
+  address: string;
+  topics: string[];
+  data: string;
+}
+
 interface RelevantStepData {
   opcode: string;
   stackEnd: BigInt | null;
@@ -134,7 +145,14 @@ type StringSet = Record<string, boolean | undefined>;
   const associatedSlotsByAddressMap: Record<string, StringSet> = {};
   const allStorageAccesses: Record<string, Record<string, string | null>> = {};
   let factoryCreate2Count = 0;
+  // logs emitted by entities, the entry point's own logs are not collected
+  const logs: LogInfo[] = [];
+  // contracts successfully created during the factory phase
+  const deployedContracts: string[] = [];
+  // address being created by each executing call frame, or null if it isn't a create
+  const frameCreations: (string | null)[] = [];
   let currentPhase = newInternalPhase();
+  let callDepth = 0;
   let entryPointAddress = "";
   let pendingKeccakAddress = "";
   let last: RelevantStepData | null = null;
@@ -152,6 +170,9 @@ type StringSet = Record<string, boolean | undefined>;
       ranOutOfGas: false,
       undeployedContractAccesses: {},
       extCodeAccessInfo: {},
+      undeployedExtCodeAccesses: {},
+      maxCallDepth: 0,
+      gasUsed: 0,
     };
   }
 
@@ -161,6 +182,9 @@ type StringSet = Record<string, boolean | undefined>;
       calledNonEntryPointWithValue,
       ranOutOfGas,
       extCodeAccessInfo,
+      undeployedExtCodeAccesses,
+      maxCallDepth,
+      gasUsed,
     } = currentPhase;
     const forbiddenOpcodesUsed = Object.keys(currentPhase.forbiddenOpcodesUsed);
     const forbiddenPrecompilesUsed = Object.keys(
@@ -184,6 +208,9 @@ type StringSet = Record<string, boolean | undefined>;
       ranOutOfGas,
       undeployedContractAccesses,
       extCodeAccessInfo,
+      undeployedExtCodeAccesses,
+      maxCallDepth,
+      gasUsed,
     };
     phases.push(phase);
     currentPhase = newInternalPhase();
@@ -246,6 +273,8 @@ type StringSet = Record<string, boolean | undefined>;
         associatedSlotsByAddress,
         factoryCalledCreate2Twice: factoryCreate2Count > 1,
         expectedStorage,
+        logs,
+        deployedContracts,
       };
     },
 
@@ -306,6 +335,21 @@ type StringSet = Record<string, boolean | undefined>;
             getContractCombinedKey(opcode)
           ] = true;
         }
+
+        if (opcode.startsWith("LOG")) {
+          const count = parseInt(opcode.substring(3));
+          const offset = bigIntToNumber(log.stack.peek(0));
+          const length = bigIntToNumber(log.stack.peek(1));
+          const topics: string[] = [];
+          for (let i = 0; i < count; i++) {
+            topics.push(toHex(toWord(log.stack.peek(2 + i).toString(16))));
+          }
+          logs.push({
+            address: toHex(log.contract.getAddress()),
+            topics,
+            data: toHex(log.memory.slice(offset, offset + length)),
+          });
+        }
       }
 
       if (secondLast && EXT_OPCODES[secondLast.opcode]) {
@@ -401,6 +445,16 @@ type StringSet = Record<string, boolean | undefined>;
               delete currentPhase.undeployedContractAccesses[addressHex];
             }
           }
+          // Unlike the above, a code size or hash check of an address with no
+          // code is kept even if code is deployed there later in the phase, as
+          // its result depends on the order of operations in a bundle.
+          if (
+            (opcode === "EXTCODESIZE" || opcode === "EXTCODEHASH") &&
+            db.getCode(address).length === 0 &&
+            !currentPhase.undeployedExtCodeAccesses[addressHex]
+          ) {
+            currentPhase.undeployedExtCodeAccesses[addressHex] = opcode;
+          }
           accessedContracts[addressHex] = {
             header: toHex(db.getCode(address).subarray(0, 3)),
             opcode,
@@ -419,6 +473,15 @@ type StringSet = Record<string, boolean | undefined>;
     },
 
     enter(frame) {
+      // calls made by the entry point are at depth 1
+      callDepth++;
+      currentPhase.maxCallDepth = Math.max(currentPhase.maxCallDepth, callDepth);
+      const type = frame.getType();
+      frameCreations.push(
+        phases.length === 0 && (type === "CREATE" || type === "CREATE2")
+          ? toHex(frame.getTo())
+          : null
+      );
       const from = toHex(frame.getFrom());
       if (from === entryPointAddress) {
         return;
@@ -447,6 +510,16 @@ type StringSet = Record<string, boolean | undefined>;
       }
     },
 
-    exit(_frame) { },
+    exit(frame) {
+      // the gas of a phase is the gas used by the calls the entry point makes in it
+      if (callDepth === 1) {
+        currentPhase.gasUsed += frame.getGasUsed();
+      }
+      const created = frameCreations.pop();
+      if (created && frame.getError() == null) {
+        deployedContracts.push(created);
+      }
+      callDepth--;
+    },
   };
 })();
@@ -112,6 +112,7 @@ export interface AccessInfo {
 }
 
 export interface LogInfo {
+  address: string
   topics: string[]
   data: string
 }
@@ -362,8 +363,10 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
       }
 
       // [OP-012]
-      if (this.lastOp === 'GAS' && !opcode.includes('CALL')) {
-        // count "GAS" opcode only if not followed by "CALL"
+      if (this.lastOp === 'GAS' && opcode.match(/^(CALL|CALLCODE|DELEGATECALL|STATICCALL)$/) == null) {
+        // count "GAS" opcode only if not immediately followed by a call opcode.
+        // MODIFICATION: match the call opcodes exactly, as a substring match
+        // would also pair GAS with CALLER, CALLVALUE and the CALLDATA* opcodes.
         this.countSlot(this.currentLevel.opcodes, 'GAS')
       }
       if (opcode !== 'GAS') {
@@ -435,6 +438,7 @@ interface BundlerCollectorTracer extends LogTracer<BundlerTracerResult>, Bundler
         }
         const data = toHex(log.memory.slice(ofs, ofs + len))
         this.logs.push({
+          address: toHex(log.contract.getAddress()),
           topics,
           data
         })
@@ -0,0 +1,80 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+// Runs the compiled tracer from `dist` on hand built steps, run `yarn build` first.
+
+const assert = require('node:assert')
+const fs = require('node:fs')
+const path = require('node:path')
+const { test } = require('node:test')
+
+// the compiled tracer, evaluated the same way geth does with stubs for its helpers
+function newTracer () {
+  const source = fs
+    .readFileSync(path.join(__dirname, '../dist/validationTracerV0_7.js'), 'utf8')
+    .replace(/[;\s]*export\{\};\s*$/, '')
+  const toHex = (x) => '0x' + x.toString()
+  const toAddress = (s) => s
+  const toWord = (s) => s
+  const tracer = new Function('toHex', 'toAddress', 'toWord', `return (${source}\n)`)(
+    toHex,
+    toAddress,
+    toWord
+  )
+  // inside a call from the entry point
+  tracer.currentLevel = {
+    topLevelMethodSig: '0x',
+    topLevelTargetAddress: '0x',
+    access: {},
+    opcodes: {},
+    extCodeAccessInfo: {},
+    contractInfo: {}
+  }
+  return tracer
+}
+
+// a step of an entity's validation, below the entry point's depth
+function step (opcode) {
+  // a precompile address, so calls don't look up code
+  const word = { toString: () => '1' }
+  return {
+    op: { toString: () => opcode },
+    stack: { length: () => 7, peek: () => word },
+    memory: { slice: () => '' },
+    contract: { getAddress: () => '1' },
+    getGas: () => 100000,
+    getCost: () => 3,
+    getDepth: () => 2
+  }
+}
+
+function opcodesAfter (opcodes) {
+  const tracer = newTracer()
+  for (const opcode of opcodes) {
+    tracer.step(step(opcode), {})
+  }
+  return tracer.currentLevel.opcodes
+}
+
+// [OP-012]
+test('GAS followed by a call opcode is not counted', () => {
+  for (const call of ['CALL', 'CALLCODE', 'DELEGATECALL', 'STATICCALL']) {
+    assert.strictEqual(opcodesAfter(['GAS', call]).GAS, undefined, call)
+  }
+})
+
+test('GAS followed by a non-call opcode containing CALL is counted', () => {
+  for (const opcode of ['CALLER', 'CALLVALUE', 'CALLDATALOAD', 'CALLDATASIZE', 'CALLDATACOPY']) {
+    assert.strictEqual(opcodesAfter(['GAS', opcode]).GAS, 1, opcode)
+  }
+})
@@ -16,10 +16,11 @@
 use std::time::Duration;
 
 use alloy_primitives::B256;
+use futures_util::StreamExt;
 use rundler_provider::{Block, BlockId, EvmProvider};
 use rundler_utils::retry::{self, UnlimitedRetryOpts};
-use tokio::time;
-use tracing::error;
+use tokio::{select, sync::watch, time};
+use tracing::{error, info, warn};
 
 /// Wait for a new block (by hash) to be discovered and return it.
 ///
@@ -30,6 +31,20 @@ pub async fn wait_for_new_block(
     last_block_hash: B256,
     poll_interval: Duration,
     block_id: BlockId,
+) -> (B256, Block) {
+    wait_for_new_block_notified(provider, last_block_hash, poll_interval, block_id, None).await
+}
+
+/// Wait for a new block (by hash) to be discovered and return it.
+///
+/// Like [`wait_for_new_block`], but if `new_blocks` is set the provider is polled again as soon
+/// as a new block is announced on it, instead of only after `poll_interval`.
+pub async fn wait_for_new_block_notified(
+    provider: &impl EvmProvider,
+    last_block_hash: B256,
+    poll_interval: Duration,
+    block_id: BlockId,
+    mut new_blocks: Option<&mut watch::Receiver<B256>>,
 ) -> (B256, Block) {
     loop {
         let block = retry::with_unlimited_retries(
@@ -45,6 +60,57 @@ pub async fn wait_for_new_block(
         if last_block_hash != block.header.hash {
             return (block.header.hash, block);
         }
-        time::sleep(poll_interval).await;
+
+        match new_blocks.as_deref_mut() {
+            Some(rx) => {
+                select! {
+                    _ = time::sleep(poll_interval) => {}
+                    changed = rx.changed() => {
+                        if changed.is_err() {
+                            // the subscription is gone, fall back to polling
+                            new_blocks = None;
+                        }
+                    }
+                }
+            }
+            None => time::sleep(poll_interval).await,
+        }
+    }
+}
+
+/// Forwards the hashes of new blocks from the provider's block subscription to `sender`,
+/// resubscribing after `retry_interval` whenever the subscription fails or is dropped.
+///
+/// Returns immediately if the provider does not support subscriptions, and once all
+/// receivers have been dropped.
+pub async fn forward_block_subscription(
+    provider: impl EvmProvider,
+    sender: watch::Sender<B256>,
+    retry_interval: Duration,
+) {
+    loop {
+        match provider.subscribe_blocks().await {
+            Ok(Some(mut blocks)) => {
+                info!("Subscribed to new blocks");
+                while let Some(hash) = blocks.next().await {
+                    if sender.send(hash).is_err() {
+                        return;
+                    }
+                }
+                warn!("Block subscription dropped, resubscribing");
+            }
+            Ok(None) => {
+                info!("Provider does not support block subscriptions, polling for new blocks");
+                return;
+            }
+            Err(error) => {
+                warn!("Failed to subscribe to new blocks: {error:?}");
+            }
+        }
+
+        if sender.is_closed() {
+            return;
+        }
+        time::sleep(retry_interval).await;
     }
 }
@@ -0,0 +1,384 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::{self, Display, Formatter};
+
+/// Entry point `AAxx` error code, as found at the start of a `FailedOp` reason
+/// string such as `"AA23 reverted"`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AaError {
+    /// AA10: sender already constructed
+    SenderAlreadyConstructed,
+    /// AA13: initCode failed or ran out of gas
+    InitCodeFailed,
+    /// AA14: initCode must return sender
+    InitCodeWrongSender,
+    /// AA15: initCode must create sender
+    InitCodeNoSender,
+    /// AA20: account not deployed
+    AccountNotDeployed,
+    /// AA21: didn't pay prefund
+    AccountPrefundNotPaid,
+    /// AA22: expired or not due
+    AccountTimeRange,
+    /// AA23: account validation reverted or ran out of gas
+    AccountReverted,
+    /// AA24: account signature error
+    AccountSignature,
+    /// AA25: invalid account nonce
+    InvalidNonce,
+    /// AA26: over verificationGasLimit
+    AccountOverVerificationGasLimit,
+    /// AA30: paymaster not deployed
+    PaymasterNotDeployed,
+    /// AA31: paymaster deposit too low
+    PaymasterDepositTooLow,
+    /// AA32: paymaster expired or not due
+    PaymasterTimeRange,
+    /// AA33: paymaster validation reverted or ran out of gas
+    PaymasterReverted,
+    /// AA34: paymaster signature error
+    PaymasterSignature,
+    /// AA36: over paymasterVerificationGasLimit
+    PaymasterOverVerificationGasLimit,
+    /// AA40: over verificationGasLimit
+    OverVerificationGasLimit,
+    /// AA41: too little verificationGas
+    TooLittleVerificationGas,
+    /// AA50: postOp reverted
+    PostOpReverted,
+    /// AA51: prefund below actualGasCost
+    PrefundBelowActualGasCost,
+    /// AA90: invalid beneficiary
+    InvalidBeneficiary,
+    /// AA91: failed send to beneficiary
+    FailedSendToBeneficiary,
+    /// AA92: internal call only
+    InternalCallOnly,
+    /// AA93: invalid paymasterAndData
+    InvalidPaymasterAndData,
+    /// AA94: gas values overflow
+    GasValuesOverflow,
+    /// AA95: out of gas
+    OutOfGas,
+    /// AA96: invalid aggregator
+    InvalidAggregator,
+    /// An `AAxx` code that is not known to this version of Rundler
+    Unknown(u8),
+}
+
+/// Category of an entry point `AAxx` error
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AaErrorCategory {
+    /// Errors caused by the factory during account creation (`AA1x`)
+    Factory,
+    /// Errors caused by the account during validation (`AA2x`)
+    Account,
+    /// Errors caused by the paymaster during validation (`AA3x`)
+    Paymaster,
+    /// Signature validation errors of the account or paymaster
+    Signature,
+    /// Errors caused by insufficient verification gas (`AA4x`)
+    VerificationGas,
+    /// Errors during the post-op phase (`AA5x`)
+    PostOp,
+    /// Errors caused by the bundle or bundler rather than an operation (`AA9x`)
+    Bundle,
+    /// Unknown category
+    Unknown,
+}
+
+impl AaError {
+    /// Parses the error code from the start of a `FailedOp` reason string.
+    ///
+    /// Returns `None` if the reason does not start with an `AAxx` code.
+    pub fn parse(reason: &str) -> Option<Self> {
+        let code = reason.strip_prefix("AA")?.get(..2)?;
+        if !code.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        code.parse().ok().map(Self::from_code)
+    }
+
+    /// Returns the error for a numeric code, e.g. `23` for `AA23`
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            10 => Self::SenderAlreadyConstructed,
+            13 => Self::InitCodeFailed,
+            14 => Self::InitCodeWrongSender,
+            15 => Self::InitCodeNoSender,
+            20 => Self::AccountNotDeployed,
+            21 => Self::AccountPrefundNotPaid,
+            22 => Self::AccountTimeRange,
+            23 => Self::AccountReverted,
+            24 => Self::AccountSignature,
+            25 => Self::InvalidNonce,
+            26 => Self::AccountOverVerificationGasLimit,
+            30 => Self::PaymasterNotDeployed,
+            31 => Self::PaymasterDepositTooLow,
+            32 => Self::PaymasterTimeRange,
+            33 => Self::PaymasterReverted,
+            34 => Self::PaymasterSignature,
+            36 => Self::PaymasterOverVerificationGasLimit,
+            40 => Self::OverVerificationGasLimit,
+            41 => Self::TooLittleVerificationGas,
+            50 => Self::PostOpReverted,
+            51 => Self::PrefundBelowActualGasCost,
+            90 => Self::InvalidBeneficiary,
+            91 => Self::FailedSendToBeneficiary,
+            92 => Self::InternalCallOnly,
+            93 => Self::InvalidPaymasterAndData,
+            94 => Self::GasValuesOverflow,
+            95 => Self::OutOfGas,
+            96 => Self::InvalidAggregator,
+            code => Self::Unknown(code),
+        }
+    }
+
+    /// Returns the numeric code of the error, e.g. `23` for `AA23`
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::SenderAlreadyConstructed => 10,
+            Self::InitCodeFailed => 13,
+            Self::InitCodeWrongSender => 14,
+            Self::InitCodeNoSender => 15,
+            Self::AccountNotDeployed => 20,
+            Self::AccountPrefundNotPaid => 21,
+            Self::AccountTimeRange => 22,
+            Self::AccountReverted => 23,
+            Self::AccountSignature => 24,
+            Self::InvalidNonce => 25,
+            Self::AccountOverVerificationGasLimit => 26,
+            Self::PaymasterNotDeployed => 30,
+            Self::PaymasterDepositTooLow => 31,
+            Self::PaymasterTimeRange => 32,
+            Self::PaymasterReverted => 33,
+            Self::PaymasterSignature => 34,
+            Self::PaymasterOverVerificationGasLimit => 36,
+            Self::OverVerificationGasLimit => 40,
+            Self::TooLittleVerificationGas => 41,
+            Self::PostOpReverted => 50,
+            Self::PrefundBelowActualGasCost => 51,
+            Self::InvalidBeneficiary => 90,
+            Self::FailedSendToBeneficiary => 91,
+            Self::InternalCallOnly => 92,
+            Self::InvalidPaymasterAndData => 93,
+            Self::GasValuesOverflow => 94,
+            Self::OutOfGas => 95,
+            Self::InvalidAggregator => 96,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    /// Returns the category of the error
+    pub fn category(&self) -> AaErrorCategory {
+        match self {
+            Self::AccountSignature | Self::PaymasterSignature => AaErrorCategory::Signature,
+            Self::AccountOverVerificationGasLimit | Self::PaymasterOverVerificationGasLimit => {
+                AaErrorCategory::VerificationGas
+            }
+            _ => match self.code() / 10 {
+                1 => AaErrorCategory::Factory,
+                2 => AaErrorCategory::Account,
+                3 => AaErrorCategory::Paymaster,
+                4 => AaErrorCategory::VerificationGas,
+                5 => AaErrorCategory::PostOp,
+                9 => AaErrorCategory::Bundle,
+                _ => AaErrorCategory::Unknown,
+            },
+        }
+    }
+}
+
+impl Display for AaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "AA{:02}", self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_known_codes() {
+        let cases = [
+            (
+                "AA10 sender already constructed",
+                AaError::SenderAlreadyConstructed,
+                AaErrorCategory::Factory,
+            ),
+            (
+                "AA13 initCode failed or OOG",
+                AaError::InitCodeFailed,
+                AaErrorCategory::Factory,
+            ),
+            (
+                "AA14 initCode must return sender",
+                AaError::InitCodeWrongSender,
+                AaErrorCategory::Factory,
+            ),
+            (
+                "AA15 initCode must create sender",
+                AaError::InitCodeNoSender,
+                AaErrorCategory::Factory,
+            ),
+            (
+                "AA20 account not deployed",
+                AaError::AccountNotDeployed,
+                AaErrorCategory::Account,
+            ),
+            (
+                "AA21 didn't pay prefund",
+                AaError::AccountPrefundNotPaid,
+                AaErrorCategory::Account,
+            ),
+            (
+                "AA22 expired or not due",
+                AaError::AccountTimeRange,
+                AaErrorCategory::Account,
+            ),
+            (
+                "AA23 reverted (or OOG)",
+                AaError::AccountReverted,
+                AaErrorCategory::Account,
+            ),
+            (
+                "AA24 signature error",
+                AaError::AccountSignature,
+                AaErrorCategory::Signature,
+            ),
+            (
+                "AA25 invalid account nonce",
+                AaError::InvalidNonce,
+                AaErrorCategory::Account,
+            ),
+            (
+                "AA26 over verificationGasLimit",
+                AaError::AccountOverVerificationGasLimit,
+                AaErrorCategory::VerificationGas,
+            ),
+            (
+                "AA30 paymaster not deployed",
+                AaError::PaymasterNotDeployed,
+                AaErrorCategory::Paymaster,
+            ),
+            (
+                "AA31 paymaster deposit too low",
+                AaError::PaymasterDepositTooLow,
+                AaErrorCategory::Paymaster,
+            ),
+            (
+                "AA32 paymaster expired or not due",
+                AaError::PaymasterTimeRange,
+                AaErrorCategory::Paymaster,
+            ),
+            (
+                "AA33 reverted (or OOG)",
+                AaError::PaymasterReverted,
+                AaErrorCategory::Paymaster,
+            ),
+            (
+                "AA34 signature error",
+                AaError::PaymasterSignature,
+                AaErrorCategory::Signature,
+            ),
+            (
+                "AA36 over paymasterVerificationGasLimit",
+                AaError::PaymasterOverVerificationGasLimit,
+                AaErrorCategory::VerificationGas,
+            ),
+            (
+                "AA40 over verificationGasLimit",
+                AaError::OverVerificationGasLimit,
+                AaErrorCategory::VerificationGas,
+            ),
+            (
+                "AA41 too little verificationGas",
+                AaError::TooLittleVerificationGas,
+                AaErrorCategory::VerificationGas,
+            ),
+            (
+                "AA50 postOp reverted",
+                AaError::PostOpReverted,
+                AaErrorCategory::PostOp,
+            ),
+            (
+                "AA51 prefund below actualGasCost",
+                AaError::PrefundBelowActualGasCost,
+                AaErrorCategory::PostOp,
+            ),
+            (
+                "AA90 invalid beneficiary",
+                AaError::InvalidBeneficiary,
+                AaErrorCategory::Bundle,
+            ),
+            (
+                "AA91 failed send to beneficiary",
+                AaError::FailedSendToBeneficiary,
+                AaErrorCategory::Bundle,
+            ),
+            (
+                "AA92 internal call only",
+                AaError::InternalCallOnly,
+                AaErrorCategory::Bundle,
+            ),
+            (
+                "AA93 invalid paymasterAndData",
+                AaError::InvalidPaymasterAndData,
+                AaErrorCategory::Bundle,
+            ),
+            (
+                "AA94 gas values overflow",
+                AaError::GasValuesOverflow,
+                AaErrorCategory::Bundle,
+            ),
+            (
+                "AA95 out of gas",
+                AaError::OutOfGas,
+                AaErrorCategory::Bundle,
+            ),
+            (
+                "AA96 invalid aggregator",
+                AaError::InvalidAggregator,
+                AaErrorCategory::Bundle,
+            ),
+        ];
+
+        for (reason, expected, category) in cases {
+            let error = AaError::parse(reason).unwrap();
+            assert_eq!(error, expected, "{reason}");
+            assert_eq!(error.category(), category, "{reason}");
+            assert_eq!(AaError::from_code(error.code()), error);
+            assert!(reason.starts_with(&error.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_parse_unknown_code() {
+        let error = AaError::parse("AA99 something new").unwrap();
+        assert_eq!(error, AaError::Unknown(99));
+        assert_eq!(error.category(), AaErrorCategory::Bundle);
+        assert_eq!(error.to_string(), "AA99");
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(AaError::parse(""), None);
+        assert_eq!(AaError::parse("AA"), None);
+        assert_eq!(AaError::parse("AA1"), None);
+        assert_eq!(AaError::parse("AA1x: factory related errors"), None);
+        assert_eq!(AaError::parse("revert"), None);
+        assert_eq!(AaError::parse("AA-1 reverted"), None);
+    }
+}
@@ -32,6 +32,26 @@ pub struct AggregatorCosts {
     pub sig_variable_length: u128,
 }
 
+impl AggregatorCosts {
+    /// Estimates the cost of aggregating the signatures of `num_ops` user operations
+    pub fn estimate(&self, num_ops: usize) -> AggregationEstimate {
+        let num_ops = num_ops as u128;
+        AggregationEstimate {
+            execution_gas: self.execution_fixed_gas + self.execution_variable_gas * num_ops,
+            signature_length: self.sig_fixed_length + self.sig_variable_length * num_ops,
+        }
+    }
+}
+
+/// Estimated cost of aggregating the signatures of a group of user operations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AggregationEstimate {
+    /// Gas used by the aggregator's `validateSignatures` function for the group
+    pub execution_gas: u128,
+    /// Length of the aggregated signature in bytes
+    pub signature_length: u128,
+}
+
 /// Signature aggregator errors
 #[derive(Debug, thiserror::Error)]
 pub enum SignatureAggregatorError {
@@ -65,6 +85,14 @@ pub trait SignatureAggregator: Sync + Send + Debug {
     /// Dummy signature for the aggregator
     fn dummy_uo_signature(&self) -> &Bytes;
 
+    /// Estimates the gas and signature length of aggregating the signatures of a
+    /// group of `num_ops` user operations, without calling the aggregator.
+    ///
+    /// Defaults to a linear estimate from the aggregator's costs.
+    fn estimate_aggregation(&self, num_ops: usize) -> AggregationEstimate {
+        self.costs().estimate(num_ops)
+    }
+
     /// Validate the signature of a user operation
     async fn validate_user_op_signature(
         &self,
@@ -107,3 +135,26 @@ mockall::mock! {
         ) -> SignatureAggregatorResult<Bytes>;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let costs = AggregatorCosts {
+            execution_fixed_gas: 1000,
+            execution_variable_gas: 100,
+            sig_fixed_length: 64,
+            sig_variable_length: 8,
+        };
+
+        assert_eq!(
+            costs.estimate(3),
+            AggregationEstimate {
+                execution_gas: 1300,
+                signature_length: 88,
+            }
+        );
+    }
+}
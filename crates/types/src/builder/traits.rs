@@ -15,7 +15,10 @@ use alloy_primitives::{Address, B256};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
 
-use super::{error::BuilderError, types::BundlingMode};
+use super::{
+    error::BuilderError,
+    types::{BundlingMode, SignerStatus},
+};
 
 /// Builder result
 pub type BuilderResult<T> = std::result::Result<T, BuilderError>;
@@ -34,4 +37,13 @@ pub trait Builder: Send + Sync {
 
     /// Set the bundling mode
     async fn debug_set_bundling_mode(&self, mode: BundlingMode) -> BuilderResult<()>;
+
+    /// Get the current pending state of the signer of each bundle sender
+    async fn get_signer_statuses(&self) -> BuilderResult<Vec<SignerStatus>>;
+
+    /// Cancel the pending bundle that contains the op with `op_hash`, used for debugging
+    /// and operational control.
+    ///
+    /// A no-op for bundle senders whose pending bundle doesn't contain the op
+    async fn debug_cancel_bundle_containing(&self, op_hash: B256) -> BuilderResult<()>;
 }
@@ -11,9 +11,12 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use alloy_primitives::Address;
 use parse_display::Display;
 use serde::{Deserialize, Serialize};
 
+use crate::GasFees;
+
 /// Builder bundling mode
 #[derive(Display, Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 #[display(style = "lowercase")]
@@ -28,3 +31,23 @@ pub enum BundlingMode {
     /// Bundles will be sent automatically.
     Auto,
 }
+
+/// Current pending state of the signer of a bundle sender
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignerStatus {
+    /// Tag of the bundle sender using the signer
+    pub builder_tag: String,
+    /// Address of the signer
+    pub signer: Address,
+    /// Nonce of the signer's next bundle transaction
+    pub nonce: u64,
+    /// Whether a bundle transaction is pending
+    pub pending: bool,
+    /// Number of blocks since the first transaction of the pending bundle was sent, zero
+    /// if none is pending
+    pub pending_blocks: u64,
+    /// Fees of the latest pending bundle transaction, if any
+    pub fees: Option<GasFees>,
+    /// Block number the status is as of
+    pub block_number: u64,
+}
@@ -112,6 +112,10 @@ pub struct ChainSpec {
     /// Some chains have artificially high block gas limits but
     /// actually cap block gas usage at a lower value.
     pub congestion_trigger_usage_ratio_threshold: f64,
+    /// Number of blocks to request from `eth_feeHistory` when using the fee history oracle
+    pub fee_history_block_count: u64,
+    /// Reward percentile to request from `eth_feeHistory` when using the fee history oracle
+    pub fee_history_reward_percentile: f64,
     /// A boolean value to set whether to add the total gas limit for an op to the PVG calculation
     pub charge_gas_limit_via_pvg: bool,
 
@@ -141,6 +145,45 @@ pub struct ChainSpec {
     /// Size of the chain history to keep to handle reorgs
     pub chain_history_size: u64,
 
+    /*
+     * Simulation
+     */
+    /// True if the BASEFEE opcode may be used during validation.
+    ///
+    /// Only set this on chains where the base fee is pinned and can't change between
+    /// simulation and inclusion.
+    pub basefee_allowed_in_validation: bool,
+    /// True if the ORIGIN opcode may be used during validation.
+    ///
+    /// Only set this on chains where the transaction origin is fixed, so that validation
+    /// can't depend on which bundler submits the operation.
+    pub origin_allowed_in_validation: bool,
+    /// Minimum max priority fee per gas an operation must pay to pass simulation.
+    ///
+    /// A hard gate applied to every operation regardless of mempool, for chains where
+    /// bundling is only profitable above a priority fee floor.
+    pub min_priority_fee_per_gas_in_validation: u64,
+    /// System contracts that validation may DELEGATECALL into.
+    ///
+    /// Some L2s require validation to delegate into precompiles or system contracts
+    /// without deployed code, which the precompile and undeployed contract rules would
+    /// otherwise ban. Only DELEGATECALLs to these addresses are permitted.
+    pub allowed_delegatecall_targets: Vec<Address>,
+    /// CREATE2 deployers that factories may deploy accounts through.
+    ///
+    /// Some factories delegate deployment to a shared deployer, such as the canonical
+    /// CREATE2 deployer at `0x4e59b44847b379578588920ca78fbf26c0b4956c`. Storage accesses
+    /// on these addresses during the factory phase don't require the factory to be staked.
+    pub allowed_create2_deployers: Vec<Address>,
+    /// Maximum verification gas allowed for a user operation on this chain.
+    ///
+    /// If unset, the `max_verification_gas` setting is used.
+    pub max_verification_gas: Option<u64>,
+    /// Maximum call gas limit allowed for a user operation on this chain.
+    ///
+    /// If unset, the `max_call_gas_limit` setting is used.
+    pub max_call_gas_limit: Option<u64>,
+
     /*
      * Contracts
      */
@@ -165,6 +208,11 @@ pub enum PriorityFeeOracleType {
     Provider,
     /// Use the usage based oracle
     UsageBased,
+    /// Use the priority fee rewards returned by eth_feeHistory
+    FeeHistory,
+    /// Use eth_maxPriorityFeePerGas on the provider, falling back to the priority fee
+    /// rewards returned by eth_feeHistory if the provider doesn't support it
+    ProviderWithFeeHistoryFallback,
 }
 
 impl Default for ChainSpec {
@@ -199,6 +247,8 @@ impl Default for ChainSpec {
             min_max_priority_fee_per_gas: 0,
             max_max_priority_fee_per_gas: u64::MAX,
             congestion_trigger_usage_ratio_threshold: 0.75,
+            fee_history_block_count: 15,
+            fee_history_reward_percentile: 50.0,
             charge_gas_limit_via_pvg: false,
             max_transaction_size_bytes: 131072, // 128 KiB
             bundle_max_send_interval_millis: 1000,
@@ -206,6 +256,13 @@ impl Default for ChainSpec {
             flashbots_relay_url: None,
             bloxroute_enabled: false,
             chain_history_size: 64,
+            basefee_allowed_in_validation: false,
+            origin_allowed_in_validation: false,
+            min_priority_fee_per_gas_in_validation: 0,
+            allowed_delegatecall_targets: vec![],
+            allowed_create2_deployers: vec![],
+            max_verification_gas: None,
+            max_call_gas_limit: None,
             signature_aggregators: Arc::new(ContractRegistry::default()),
             submission_proxies: Arc::new(ContractRegistry::default()),
         }
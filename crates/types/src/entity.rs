@@ -181,12 +181,19 @@ pub struct EntityInfo {
     pub entity: Entity,
     /// Whether the entity is staked or not
     pub is_staked: bool,
+    /// Whether the entity has started unstaking and its stake can be withdrawn soon,
+    /// so its staked status should not be relied on
+    pub stake_expiring_soon: bool,
 }
 
 impl EntityInfo {
     /// Create a new entity info
     pub fn new(entity: Entity, is_staked: bool) -> Self {
-        Self { entity, is_staked }
+        Self {
+            entity,
+            is_staked,
+            stake_expiring_soon: false,
+        }
     }
 
     /// Get the entity address
@@ -261,6 +268,19 @@ impl EntityInfos {
         None
     }
 
+    /// Check if the same address is used for more than one entity role, i.e. the
+    /// paymaster is also the factory
+    pub fn has_role_overlap(self) -> bool {
+        let addresses = self
+            .entities()
+            .map(|(_, info)| info.entity.address)
+            .collect::<Vec<_>>();
+        addresses
+            .iter()
+            .enumerate()
+            .any(|(i, address)| addresses[i + 1..].contains(address))
+    }
+
     /// Get the sender address
     pub fn sender_address(self) -> Address {
         self.sender.entity.address
@@ -271,6 +291,7 @@ impl EntityInfos {
         self.sender = EntityInfo {
             entity: Entity::account(addr),
             is_staked,
+            stake_expiring_soon: false,
         };
     }
 
@@ -279,6 +300,7 @@ impl EntityInfos {
         self.factory = Some(EntityInfo {
             entity: Entity::factory(addr),
             is_staked,
+            stake_expiring_soon: false,
         });
     }
 
@@ -287,6 +309,7 @@ impl EntityInfos {
         self.paymaster = Some(EntityInfo {
             entity: Entity::paymaster(addr),
             is_staked,
+            stake_expiring_soon: false,
         });
     }
 
@@ -295,6 +318,73 @@ impl EntityInfos {
         self.aggregator = Some(EntityInfo {
             entity: Entity::aggregator(addr),
             is_staked: true, // aggregators are always treated as staked due to whitelist
+            stake_expiring_soon: false,
         });
     }
 }
+
+/// Serializable view of an entity's address and staking state
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityStakeSnapshot {
+    /// The entity address
+    pub address: Address,
+    /// Whether the entity is staked or not
+    pub is_staked: bool,
+}
+
+impl From<EntityInfo> for EntityStakeSnapshot {
+    fn from(info: EntityInfo) -> Self {
+        Self {
+            address: info.address(),
+            is_staked: info.is_staked(),
+        }
+    }
+}
+
+/// Serializable view of the entities used in an op and their staking state, for debugging
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityInfosSnapshot {
+    /// The op sender
+    pub sender: EntityStakeSnapshot,
+    /// The factory, if any
+    pub factory: Option<EntityStakeSnapshot>,
+    /// The paymaster, if any
+    pub paymaster: Option<EntityStakeSnapshot>,
+    /// The aggregator, if any
+    pub aggregator: Option<EntityStakeSnapshot>,
+}
+
+impl From<EntityInfos> for EntityInfosSnapshot {
+    fn from(infos: EntityInfos) -> Self {
+        Self {
+            sender: infos.sender.into(),
+            factory: infos.factory.map(Into::into),
+            paymaster: infos.paymaster.map(Into::into),
+            aggregator: infos.aggregator.map(Into::into),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_role_overlap() {
+        let sender = Address::repeat_byte(1);
+        let paymaster = Address::repeat_byte(2);
+        let mut infos = EntityInfos::default();
+        infos.set_sender(sender, false);
+        infos.set_paymaster(paymaster, false);
+        assert!(!infos.has_role_overlap());
+
+        infos.set_factory(paymaster, false);
+        assert!(infos.has_role_overlap());
+
+        infos.set_factory(Address::repeat_byte(3), false);
+        infos.set_aggregator(sender);
+        assert!(infos.has_role_overlap());
+    }
+}
@@ -52,6 +52,9 @@ pub enum PriorityFeeMode {
     BaseFeePercent(u32),
     /// The priority fee is required to be a percentage above the bundle priority fee.
     PriorityFeeIncreasePercent(u32),
+    /// The priority fee is required to be at least the bundle priority fee, and at least
+    /// a percentage of the bundle base fee, so the floor scales with the base fee.
+    PercentOfBaseFee(u64),
 }
 
 impl PriorityFeeMode {
@@ -60,6 +63,7 @@ impl PriorityFeeMode {
         match kind {
             "base_fee_percent" => Ok(Self::BaseFeePercent(value)),
             "priority_fee_increase_percent" => Ok(Self::PriorityFeeIncreasePercent(value)),
+            "percent_of_base_fee" => Ok(Self::PercentOfBaseFee(value.into())),
             _ => anyhow::bail!("Invalid priority fee mode: {}", kind),
         }
     }
@@ -74,6 +78,9 @@ impl PriorityFeeMode {
             PriorityFeeMode::PriorityFeeIncreasePercent(percent) => {
                 math::increase_by_percent(bundle_fees.max_priority_fee_per_gas, percent)
             }
+            PriorityFeeMode::PercentOfBaseFee(percent) => bundle_fees
+                .max_priority_fee_per_gas
+                .max(percent_of(base_fee, percent)),
         };
 
         let max_fee_per_gas = base_fee + max_priority_fee_per_gas;
@@ -100,6 +107,52 @@ impl PriorityFeeMode {
                 min_max_priority_fee_per_gas,
                 percent + bundle_priority_fee_overhead_percent,
             ),
+            PriorityFeeMode::PercentOfBaseFee(percent) => math::increase_by_percent(
+                min_max_priority_fee_per_gas,
+                bundle_priority_fee_overhead_percent,
+            )
+            .max(percent_of(
+                math::percent(base_fee, base_fee_accept_percent),
+                percent,
+            )),
         }
     }
 }
+
+fn percent_of(n: u128, percent: u64) -> u128 {
+    n.saturating_mul(percent.into()) / 100
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_of_base_fee_floor_scales() {
+        let mode = PriorityFeeMode::PercentOfBaseFee(5);
+        let bundle_fees = |base_fee: u128, priority_fee: u128| GasFees {
+            max_fee_per_gas: base_fee + priority_fee,
+            max_priority_fee_per_gas: priority_fee,
+        };
+
+        // the bundle priority fee is above 5% of the base fee
+        assert_eq!(
+            mode.required_fees(bundle_fees(100, 10)),
+            bundle_fees(100, 10)
+        );
+        // 5% of the base fee is above the bundle priority fee
+        assert_eq!(
+            mode.required_fees(bundle_fees(1_000, 10)),
+            bundle_fees(1_000, 50)
+        );
+        assert_eq!(
+            mode.required_fees(bundle_fees(10_000, 10)),
+            bundle_fees(10_000, 500)
+        );
+
+        assert_eq!(mode.minimum_priority_fee(100, 100, 10, 0), 10);
+        assert_eq!(mode.minimum_priority_fee(1_000, 100, 10, 0), 50);
+        // only the accepted share of the base fee counts toward the floor
+        assert_eq!(mode.minimum_priority_fee(10_000, 50, 10, 0), 250);
+    }
+}
@@ -20,6 +20,9 @@
 
 //! Rundler common types
 
+mod aa_error;
+pub use aa_error::{AaError, AaErrorCategory};
+
 pub mod aggregator;
 
 pub mod builder;
@@ -29,7 +32,10 @@ pub mod chain;
 pub mod da;
 
 mod entity;
-pub use entity::{Entity, EntityInfo, EntityInfos, EntityType, EntityUpdate, EntityUpdateType};
+pub use entity::{
+    Entity, EntityInfo, EntityInfos, EntityInfosSnapshot, EntityStakeSnapshot, EntityType,
+    EntityUpdate, EntityUpdateType,
+};
 
 mod opcode;
 pub use opcode::{Opcode, ViolationOpCode};
@@ -160,6 +160,9 @@ pub enum PrecheckViolation {
     /// The UO's maximum cost is above the max bundle fee
     #[display("UO's maximum cost is {0} but must be at most {1}")]
     OverMaxCost(U256, U256),
+    /// The call gas limit of the user operation is too high.
+    #[display("callGasLimit is {0} but must be at most {1}")]
+    CallGasLimitTooHigh(u128, u128),
 }
 
 /// All possible simulation violations
@@ -179,6 +182,9 @@ pub enum SimulationViolation {
         "User Operation expired or has an invalid time range. validUntil: {0}, validAfter: {1}"
     )]
     InvalidTimeRange(Timestamp, Timestamp),
+    /// The user operation's `valid_until` is before its `valid_after`, so it is never valid
+    #[display("User Operation is never valid, validUntil: {0} is before validAfter: {1}")]
+    ReversedTimeRange(Timestamp, Timestamp),
     /// The signature is invalid for the paymaster
     #[display("invalid paymaster signature")]
     InvalidPaymasterSignature,
@@ -246,6 +252,108 @@ pub enum SimulationViolation {
     /// Unsupported contract type
     #[display("accessed unsupported contract type: {0:?} at {1:?}. Address must be whitelisted")]
     AccessedUnsupportedContractType(String, Address),
+    /// The packed user operation is larger than the maximum allowed size
+    #[display("operation size is {size} bytes but must be at most {maximum} bytes")]
+    OperationTooLarge {
+        /// Size of the packed user operation in bytes
+        size: usize,
+        /// Maximum allowed size in bytes
+        maximum: usize,
+    },
+    /// The init code is not empty but too short to contain a factory address
+    #[display("init code is {0} bytes but must be empty or at least 20 bytes")]
+    MalformedInitCode(usize),
+    /// The paymaster and data is not empty but too short to contain a paymaster address
+    #[display("paymasterAndData is {0} bytes but must be empty or at least 20 bytes")]
+    MalformedPaymasterAndData(usize),
+    /// The entity is on the operator's denylist
+    #[display("{0.kind} {0.address:?} is denied")]
+    EntityDenied(Entity),
+    /// The max priority fee per gas is below the chain's minimum for simulation
+    #[display("maxPriorityFeePerGas is {provided} but must be at least {minimum}")]
+    PriorityFeeTooLow {
+        /// The max priority fee per gas of the user operation
+        provided: u128,
+        /// The minimum max priority fee per gas
+        minimum: u128,
+    },
+    /// The call gas limit is zero and the operator rejects ops that execute nothing
+    #[display("callGasLimit is zero")]
+    ZeroCallGasLimit,
+    /// The call data executes a call whose target is the entry point
+    #[display("callData calls the entry point")]
+    CalledEntryPoint,
+    /// The pre-verification gas doesn't cover the calldata cost plus the configured overhead
+    #[display("preVerificationGas is {provided} but must be at least {minimum}")]
+    PreVerificationGasTooLow {
+        /// The pre-verification gas of the user operation
+        provided: u128,
+        /// The minimum pre-verification gas
+        minimum: u128,
+    },
+    /// Validation accessed no contracts, so the code hash is zero. A real operation
+    /// always accesses contracts, so this usually means the simulation is misconfigured.
+    #[display("validation accessed no contracts, code hash is empty")]
+    SuspiciousEmptyCodeHash,
+    /// The entity made external calls nested deeper than allowed during validation
+    #[display("{0.kind} call depth {1} during validation exceeds the maximum of {2}")]
+    CallDepthTooDeep(Entity, u64, u64),
+    /// The factory did not deploy a contract at the sender address during initialization
+    #[display("factory {0} did not deploy the sender {1} during initialization")]
+    FactoryDidNotDeploySender(Address, Address),
+    /// The user operation accessed storage associated with an address other than its
+    /// sender or entities
+    #[display("{0.kind} accessed storage associated with unrelated address {1:?} at {2:?} during validation")]
+    CrossSenderAssociatedStorage(Entity, Address, StorageSlot),
+    /// The user operation becomes valid further in the future than the configured horizon
+    #[display("validAfter {valid_after} is beyond the maximum of {max_valid_after}")]
+    ValidAfterTooFar {
+        /// The time the user operation becomes valid
+        valid_after: Timestamp,
+        /// The latest validAfter accepted at the time of simulation
+        max_valid_after: Timestamp,
+    },
+    /// The sender of the user operation is the entry point
+    #[display("sender is the entry point")]
+    SenderIsEntryPoint,
+    /// The user operation checked the code size or hash of an address with no code, so the
+    /// result depends on whether an earlier op in the bundle deploys there
+    #[display(
+        "{0.kind} used {2} on {1:?}, which has no code, the result depends on bundle ordering"
+    )]
+    UndeployedExtCodeAccess(Entity, Address, ViolationOpCode),
+    /// The signature is shorter than the configured minimum, so it can't pass validation
+    #[display("signature is {length} bytes but must be at least {minimum} bytes")]
+    MalformedSignature {
+        /// Length of the signature in bytes
+        length: usize,
+        /// Minimum signature length in bytes
+        minimum: usize,
+    },
+    /// Validation touched more storage slots than are kept in memory for a single op
+    #[display("validation accessed {slots} storage slots, more than the maximum of {max}")]
+    ExpectedStorageTooLarge {
+        /// Number of storage slots captured during validation
+        slots: usize,
+        /// Maximum number of storage slots allowed
+        max: usize,
+    },
+    /// The violations found exclude the user operation from every configured mempool
+    #[display("operation is not supported by any mempool, violations: {violations:?}")]
+    UnsupportedByAllMempools {
+        /// The violations that excluded the user operation from the mempools
+        violations: Vec<SimulationViolation>,
+    },
+}
+
+impl SimulationViolation {
+    /// Returns the individual violations, unwrapping `UnsupportedByAllMempools`
+    pub fn flatten(self) -> Vec<SimulationViolation> {
+        match self {
+            Self::UnsupportedByAllMempools { violations } => violations,
+            violation => vec![violation],
+        }
+    }
 }
 
 /// Information about a storage violation based on stake status
@@ -265,4 +373,8 @@ pub struct NeedsStakeInformation {
     pub min_stake: U256,
     /// Minumum delay after an unstake event
     pub min_unstake_delay: u32,
+    /// The entity's current stake
+    pub current_stake: U256,
+    /// The entity's current delay after an unstake event
+    pub current_unstake_delay: u32,
 }
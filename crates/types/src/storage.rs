@@ -47,6 +47,20 @@ impl ExpectedStorage {
     }
 }
 
+/// A storage slot expected to hold a different value by two ops in the same bundle
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("a storage slot was read with a different value from multiple ops. Address: {address:?}, slot: {slot}, first value seen: {first}, second value seen: {second}")]
+pub struct StorageConflict {
+    /// The address of the contract owning the slot
+    pub address: Address,
+    /// The conflicting slot
+    pub slot: B256,
+    /// The value expected by the bundle
+    pub first: B256,
+    /// The value expected by the op being added
+    pub second: B256,
+}
+
 /// The expected storage values for a bundle of user operations
 #[derive(Clone, Debug, Default)]
 pub struct BundleExpectedStorage {
@@ -58,7 +72,7 @@ pub struct BundleExpectedStorage {
 
 impl BundleExpectedStorage {
     /// Add the expected storage from a UO into this bundle's expected storage.
-    pub fn add(&mut self, to_add: &ExpectedStorage) -> anyhow::Result<()> {
+    pub fn add(&mut self, to_add: &ExpectedStorage) -> Result<(), StorageConflict> {
         let mut new_inner = self.inner.clone(); // no side effects on failure
         let mut new_counts = self.counts.clone();
 
@@ -68,10 +82,12 @@ impl BundleExpectedStorage {
                 match values_by_slot.entry(slot) {
                     btree_map::Entry::Occupied(mut entry) => {
                         if *entry.get() != value {
-                            anyhow::bail!(
-                                "a storage slot was read with a different value from multiple ops. Address: {address:?}, slot: {slot}, first value seen: {value}, second value seen: {}",
-                                entry.get(),
-                            );
+                            return Err(StorageConflict {
+                                address,
+                                slot,
+                                first: *entry.get(),
+                                second: value,
+                            });
                         }
                         entry.insert(value);
                     }
@@ -178,7 +194,17 @@ mod tests {
         expected_storage1.insert(address0, U256::from(1), U256::from(5));
 
         bundle_expected_storage.add(&expected_storage0).unwrap();
-        bundle_expected_storage.add(&expected_storage1).unwrap_err();
+        assert_eq!(
+            bundle_expected_storage.add(&expected_storage1).unwrap_err(),
+            StorageConflict {
+                address: address0,
+                slot: b256(1),
+                first: b256(2),
+                second: b256(5),
+            }
+        );
+        // no side effects on failure
+        assert_eq!(bundle_expected_storage.inner.num_slots(), 3);
     }
 
     #[test]
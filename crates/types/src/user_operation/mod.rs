@@ -95,6 +95,22 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Get the user operation factory address, if any
     fn factory(&self) -> Option<Address>;
 
+    /// Checks that the init code is either empty or starts with a factory address.
+    ///
+    /// `factory()` treats init code that is too short to hold an address as no factory,
+    /// this distinguishes that case from empty init code.
+    fn validate_init_code(&self) -> Result<(), InitCodeError> {
+        Ok(())
+    }
+
+    /// Checks that the paymaster and data is either empty or starts with a paymaster address.
+    ///
+    /// `paymaster()` treats paymaster and data that is too short to hold an address as no
+    /// paymaster, this distinguishes that case from empty paymaster and data.
+    fn validate_paymaster_and_data(&self) -> Result<(), PaymasterAndDataError> {
+        Ok(())
+    }
+
     /// Get the user operation aggregator address, if any
     fn aggregator(&self) -> Option<Address>;
 
@@ -118,8 +134,15 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
 
     /// Returns the gas price for this UO given the base fee
     fn gas_price(&self, base_fee: u128) -> u128 {
-        self.max_fee_per_gas()
-            .min(base_fee + self.max_priority_fee_per_gas())
+        // bounded by the max fee per gas, so always fits
+        self.effective_gas_price(U256::from(base_fee)).to()
+    }
+
+    /// Returns the effective gas price for this UO given the base fee:
+    /// `min(max_fee_per_gas, base_fee + max_priority_fee_per_gas)`
+    fn effective_gas_price(&self, base_fee: U256) -> U256 {
+        U256::from(self.max_fee_per_gas())
+            .min(base_fee.saturating_add(U256::from(self.max_priority_fee_per_gas())))
     }
 
     /// Returns the signature of the user operation
@@ -138,6 +161,21 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// It does not include the signature field.
     fn hash(&self) -> B256;
 
+    /// Hash the fields of a user operation, without the entry point and chain id.
+    ///
+    /// This is the inner hash that `hash` combines with the entry point and chain id.
+    fn packed_hash(&self) -> B256;
+
+    /// Hash many user operations against the same entry point & chain.
+    ///
+    /// The encoded entry point and chain id tail is shared across all operations instead of
+    /// being re-encoded per operation. The results are identical to hashing each operation
+    /// individually.
+    fn op_hashes(ops: &[Self], entry_point: Address, chain_id: u64) -> Vec<B256> {
+        let hasher = OpHasher::new(entry_point, chain_id);
+        ops.iter().map(|op| hasher.hash(op)).collect()
+    }
+
     /// Get the user operation id
     fn id(&self) -> UserOperationId;
 
@@ -158,6 +196,9 @@ pub trait UserOperation: Debug + Clone + Send + Sync + 'static {
     /// Returns the calldata floor gas limit
     fn calldata_floor_gas_limit(&self) -> u128;
 
+    /// Returns the gas cost of the user operation's calldata in a bundle
+    fn calldata_gas_cost(&self) -> u128;
+
     /// Abi encode size of the user operation
     fn abi_encoded_size(&self) -> usize;
 
@@ -480,6 +521,22 @@ fn dummy_transform_for_aggregator<UO: UserOperation>(
     )
 }
 
+/// Error returned when a user operation's init code is invalid
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum InitCodeError {
+    /// The init code is not empty but is too short to contain a factory address
+    #[error("init code is {0} bytes but must be empty or at least 20 bytes")]
+    Malformed(usize),
+}
+
+/// Error returned when a user operation's paymaster and data is invalid
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum PaymasterAndDataError {
+    /// The paymaster and data is not empty but is too short to contain a paymaster address
+    #[error("paymaster and data is {0} bytes but must be empty or at least 20 bytes")]
+    Malformed(usize),
+}
+
 /// User operation enum
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[allow(clippy::large_enum_variant)]
@@ -518,6 +575,13 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn packed_hash(&self) -> B256 {
+        match self {
+            UserOperationVariant::V0_6(op) => op.packed_hash(),
+            UserOperationVariant::V0_7(op) => op.packed_hash(),
+        }
+    }
+
     fn id(&self) -> UserOperationId {
         match self {
             UserOperationVariant::V0_6(op) => op.id(),
@@ -546,6 +610,20 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn validate_init_code(&self) -> Result<(), InitCodeError> {
+        match self {
+            UserOperationVariant::V0_6(op) => op.validate_init_code(),
+            UserOperationVariant::V0_7(op) => op.validate_init_code(),
+        }
+    }
+
+    fn validate_paymaster_and_data(&self) -> Result<(), PaymasterAndDataError> {
+        match self {
+            UserOperationVariant::V0_6(op) => op.validate_paymaster_and_data(),
+            UserOperationVariant::V0_7(op) => op.validate_paymaster_and_data(),
+        }
+    }
+
     fn factory(&self) -> Option<Address> {
         match self {
             UserOperationVariant::V0_6(op) => op.factory(),
@@ -644,6 +722,13 @@ impl UserOperation for UserOperationVariant {
         }
     }
 
+    fn calldata_gas_cost(&self) -> u128 {
+        match self {
+            UserOperationVariant::V0_6(op) => op.calldata_gas_cost(),
+            UserOperationVariant::V0_7(op) => op.calldata_gas_cost(),
+        }
+    }
+
     fn max_fee_per_gas(&self) -> u128 {
         match self {
             UserOperationVariant::V0_6(op) => op.max_fee_per_gas(),
@@ -787,6 +872,19 @@ impl UserOperationVariant {
     pub fn is_v0_6(&self) -> bool {
         matches!(self, UserOperationVariant::V0_6(_))
     }
+
+    /// Hash the user operation for the given entry point and chain id, using the
+    /// hashing scheme of the operation's entry point version.
+    ///
+    /// Unlike `hash`, which is fixed to the entry point and chain the operation was
+    /// built for, this hashes against any entry point of the same version.
+    pub fn op_hash(&self, entry_point: Address, chain_id: u64) -> B256 {
+        let hasher = OpHasher::new(entry_point, chain_id);
+        match self {
+            UserOperationVariant::V0_6(op) => hasher.hash(op),
+            UserOperationVariant::V0_7(op) => hasher.hash(op),
+        }
+    }
 }
 
 /// User operation optional gas enum
@@ -845,6 +943,18 @@ pub struct UserOpsPerAggregator<UO: UserOperation> {
     pub signature: Bytes,
 }
 
+impl<UO: UserOperation> UserOpsPerAggregator<UO> {
+    /// Returns the first user operation that does not declare this group's aggregator.
+    ///
+    /// The entry point validates every op in a group with the group's aggregator, so a
+    /// mis-grouped op reverts the whole bundle.
+    pub fn misgrouped_op(&self) -> Option<&UO> {
+        self.user_ops
+            .iter()
+            .find(|op| op.aggregator().unwrap_or(Address::ZERO) != self.aggregator)
+    }
+}
+
 impl<UO: UserOperation + Into<UserOperationVariant>> UserOpsPerAggregator<UO> {
     /// Convert the user operations to a vector of user operation variants
     pub fn into_uo_variants(self) -> UserOpsPerAggregator<UserOperationVariant> {
@@ -856,6 +966,41 @@ impl<UO: UserOperation + Into<UserOperationVariant>> UserOpsPerAggregator<UO> {
     }
 }
 
+/// Hashes user operations for a fixed entry point and chain id.
+///
+/// The encoded entry point and chain id are computed once, so hashing an operation
+/// only encodes its packed hash. Hold one per entry point to hash operations for it
+/// without re-encoding the shared tail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpHasher {
+    // abi.encode(address entryPoint, uint256 chainId)
+    tail: [u8; 64],
+}
+
+impl OpHasher {
+    /// Create a hasher for the given entry point and chain id
+    pub fn new(entry_point: Address, chain_id: u64) -> Self {
+        let mut tail = [0_u8; 64];
+        tail[12..32].copy_from_slice(entry_point.as_slice());
+        tail[32..64].copy_from_slice(&U256::from(chain_id).to_be_bytes::<32>());
+        Self { tail }
+    }
+
+    /// Hash a user operation against this hasher's entry point and chain id
+    pub fn hash<UO: UserOperation>(&self, op: &UO) -> B256 {
+        self.hash_packed(op.packed_hash())
+    }
+
+    /// Hash a user operation given its packed hash, see [`UserOperation::packed_hash`]
+    pub fn hash_packed(&self, packed_hash: B256) -> B256 {
+        // abi.encode(bytes32 encodedHash, address entryPoint, uint256 chainId)
+        let mut encoded = [0_u8; 96];
+        encoded[..32].copy_from_slice(packed_hash.as_slice());
+        encoded[32..].copy_from_slice(&self.tail);
+        alloy_primitives::keccak256(encoded)
+    }
+}
+
 pub(crate) fn calc_calldata_gas_costs<UO: SolValue>(
     uo: &UO,
     chain_spec: &ChainSpec,
@@ -903,8 +1048,158 @@ pub(crate) fn default_if_none_or_equal<V: Copy + PartialEq>(
 
 #[cfg(test)]
 mod tests {
+    use alloy_primitives::{address, b256, bytes, uint};
+    use rundler_contracts::v0_7::PackedUserOperation;
+
     use super::*;
 
+    fn op_with_fees(max_fee_per_gas: u128, max_priority_fee_per_gas: u128) -> UserOperationVariant {
+        UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                    ..Default::default()
+                },
+            )
+            .build(),
+        )
+    }
+
+    #[test]
+    fn test_effective_gas_price_uncapped() {
+        // base fee plus priority fee is below the max fee
+        let op = op_with_fees(100, 10);
+        assert_eq!(op.effective_gas_price(U256::from(50)), U256::from(60));
+        assert_eq!(op.gas_price(50), 60);
+    }
+
+    #[test]
+    fn test_effective_gas_price_capped() {
+        // base fee plus priority fee is above the max fee
+        let op = op_with_fees(100, 10);
+        assert_eq!(op.effective_gas_price(U256::from(95)), U256::from(100));
+        assert_eq!(op.effective_gas_price(U256::from(90)), U256::from(100));
+        assert_eq!(op.gas_price(95), 100);
+    }
+
+    #[test]
+    fn test_effective_gas_price_base_fee_above_max_fee() {
+        let op = op_with_fees(100, 10);
+        assert_eq!(op.effective_gas_price(U256::from(1000)), U256::from(100));
+        assert_eq!(op.effective_gas_price(U256::MAX), U256::from(100));
+        assert_eq!(op.gas_price(u128::MAX), 100);
+    }
+
+    #[test]
+    fn test_variant_op_hash_v0_6() {
+        // same vector as the v0.6 zeroed hash test, built for a different chain
+        let op = UserOperationVariant::V0_6(
+            v0_6::UserOperationBuilder::new(
+                &ChainSpec::default(),
+                v0_6::UserOperationRequiredFields {
+                    sender: Address::ZERO,
+                    nonce: U256::ZERO,
+                    init_code: Bytes::default(),
+                    call_data: Bytes::default(),
+                    call_gas_limit: 0,
+                    verification_gas_limit: 0,
+                    pre_verification_gas: 0,
+                    max_fee_per_gas: 0,
+                    max_priority_fee_per_gas: 0,
+                    paymaster_and_data: Bytes::default(),
+                    signature: Bytes::default(),
+                },
+            )
+            .build(),
+        );
+
+        assert_eq!(
+            op.op_hash(address!("66a15edcc3b50a663e72f1457ffd49b9ae284ddc"), 1337),
+            b256!("dca97c3b49558ab360659f6ead939773be8bf26631e61bb17045bb70dc983b2d")
+        );
+    }
+
+    #[test]
+    fn test_variant_op_hash_v0_7() {
+        // same vector as the v0.7 hash test, from a Sepolia transaction
+        let cs = ChainSpec::default();
+        let puo = PackedUserOperation {
+            sender: address!("b292Cf4a8E1fF21Ac27C4f94071Cd02C022C414b"),
+            nonce: uint!(0xF83D07238A7C8814A48535035602123AD6DBFA63000000000000000000000001_U256),
+            initCode: Bytes::default(),
+            callData: bytes!("e9ae5c530000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001d8b292cf4a8e1ff21ac27c4f94071cd02c022c414b00000000000000000000000000000000000000000000000000000000000000009517e29f0000000000000000000000000000000000000000000000000000000000000002000000000000000000000000ad6330089d9a1fe89f4020292e1afe9969a5a2fc00000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000120000000000000000000000000000000000000000000000000000000000001518000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000018e2fbe8980000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000000800000000000000000000000002372912728f93ab3daaaebea4f87e6e28476d987000000000000000000000000000000000000000000000000002386f26fc10000000000000000000000000000000000000000000000000000000000000000006000000000000000000000000000000000000000000000000000000000000000000000000000000000"),
+            accountGasLimits: b256!("000000000000000000000000000114fc0000000000000000000000000012c9b5"),
+            preVerificationGas: U256::from(48916),
+            gasFees: b256!("000000000000000000000000524121000000000000000000000000109a4a441a"),
+            paymasterAndData: Bytes::default(),
+            signature: bytes!("3c7bfe22c9c2ef8994a9637bcc4df1741c5dc0c25b209545a7aeb20f7770f351479b683bd17c4d55bc32e2a649c8d2dff49dcfcc1f3fd837bcd88d1e69a434cf1c"),
+        };
+        let op = UserOperationVariant::V0_7(
+            v0_7::UserOperationBuilder::from_packed(puo, &cs)
+                .unwrap()
+                .build(),
+        );
+
+        assert_eq!(
+            op.op_hash(cs.entry_point_address_v0_7, 11155111),
+            b256!("e486401370d145766c3cf7ba089553214a1230d38662ae532c9b62eb6dadcf7e")
+        );
+    }
+
+    #[test]
+    fn test_misgrouped_op() {
+        let cs = ChainSpec::default();
+        let aggregator = Address::random();
+        let op = |aggregator: Option<Address>| {
+            let builder = v0_6::UserOperationBuilder::new(
+                &cs,
+                v0_6::UserOperationRequiredFields {
+                    sender: Address::random(),
+                    nonce: U256::ZERO,
+                    init_code: Bytes::new(),
+                    call_data: Bytes::new(),
+                    call_gas_limit: 0,
+                    verification_gas_limit: 0,
+                    pre_verification_gas: 0,
+                    max_fee_per_gas: 0,
+                    max_priority_fee_per_gas: 0,
+                    paymaster_and_data: Bytes::new(),
+                    signature: Bytes::new(),
+                },
+            );
+            match aggregator {
+                Some(aggregator) => builder.aggregator(aggregator),
+                None => builder,
+            }
+            .build()
+        };
+
+        let aggregated = UserOpsPerAggregator {
+            user_ops: vec![op(Some(aggregator)), op(Some(aggregator))],
+            aggregator,
+            signature: Bytes::new(),
+        };
+        assert_eq!(aggregated.misgrouped_op(), None);
+
+        let unaggregated = UserOpsPerAggregator {
+            user_ops: vec![op(None)],
+            aggregator: Address::ZERO,
+            signature: Bytes::new(),
+        };
+        assert_eq!(unaggregated.misgrouped_op(), None);
+
+        // an op without an aggregator placed in an aggregated group
+        let wrong = op(None);
+        let misgrouped = UserOpsPerAggregator {
+            user_ops: vec![op(Some(aggregator)), wrong.clone()],
+            aggregator,
+            signature: Bytes::new(),
+        };
+        assert_eq!(misgrouped.misgrouped_op(), Some(&wrong));
+    }
+
     #[test]
     fn test_byte_array_abi_len() {
         let b = Bytes::from(vec![0u8; 32]);
@@ -916,4 +1211,123 @@ mod tests {
         let b = Bytes::from(vec![0u8; 33]);
         assert_eq!(byte_array_abi_len(&b), 64);
     }
+
+    #[test]
+    fn test_op_hashes_matches_hash() {
+        let cs = ChainSpec {
+            id: 1337,
+            ..Default::default()
+        };
+
+        let ops: Vec<UserOperationVariant> = (0..3_u128)
+            .map(|i| {
+                v0_6::UserOperationBuilder::new(
+                    &cs,
+                    v0_6::UserOperationRequiredFields {
+                        sender: Address::random(),
+                        nonce: U256::from(i),
+                        init_code: Bytes::new(),
+                        call_data: Bytes::new(),
+                        call_gas_limit: 10_000 * i,
+                        verification_gas_limit: 0,
+                        pre_verification_gas: 0,
+                        max_fee_per_gas: 0,
+                        max_priority_fee_per_gas: 0,
+                        paymaster_and_data: Bytes::new(),
+                        signature: Bytes::new(),
+                    },
+                )
+                .build()
+                .into()
+            })
+            .chain((0..3_u128).map(|i| {
+                v0_7::UserOperationBuilder::new(
+                    &cs,
+                    v0_7::UserOperationRequiredFields {
+                        sender: Address::random(),
+                        nonce: U256::from(i),
+                        call_data: Bytes::new(),
+                        call_gas_limit: 0,
+                        verification_gas_limit: 0,
+                        pre_verification_gas: 1_000 * i,
+                        max_priority_fee_per_gas: 0,
+                        max_fee_per_gas: 0,
+                        signature: Bytes::new(),
+                    },
+                )
+                .build()
+                .into()
+            }))
+            .collect();
+
+        let v0_6_hashes =
+            UserOperationVariant::op_hashes(&ops[..3], cs.entry_point_address_v0_6, cs.id);
+        let v0_7_hashes =
+            UserOperationVariant::op_hashes(&ops[3..], cs.entry_point_address_v0_7, cs.id);
+
+        for (op, hash) in ops.iter().zip(v0_6_hashes.into_iter().chain(v0_7_hashes)) {
+            assert_eq!(op.hash(), hash);
+        }
+    }
+
+    #[test]
+    fn test_op_hasher_matches_hash() {
+        let cs = ChainSpec {
+            id: 8453,
+            ..Default::default()
+        };
+
+        let v0_6_op = v0_6::UserOperationBuilder::new(
+            &cs,
+            v0_6::UserOperationRequiredFields {
+                sender: Address::random(),
+                nonce: U256::from(7),
+                init_code: Bytes::from(vec![1, 2, 3]),
+                call_data: Bytes::from(vec![4, 5, 6]),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 200_000,
+                pre_verification_gas: 50_000,
+                max_fee_per_gas: 10,
+                max_priority_fee_per_gas: 1,
+                paymaster_and_data: Bytes::from(vec![7, 8, 9]),
+                signature: Bytes::from(vec![10, 11]),
+            },
+        )
+        .build();
+        let v0_7_op = v0_7::UserOperationBuilder::new(
+            &cs,
+            v0_7::UserOperationRequiredFields {
+                sender: Address::random(),
+                nonce: U256::from(7),
+                call_data: Bytes::from(vec![4, 5, 6]),
+                call_gas_limit: 100_000,
+                verification_gas_limit: 200_000,
+                pre_verification_gas: 50_000,
+                max_priority_fee_per_gas: 1,
+                max_fee_per_gas: 10,
+                signature: Bytes::from(vec![10, 11]),
+            },
+        )
+        .build();
+
+        let v0_6_hasher = OpHasher::new(cs.entry_point_address_v0_6, cs.id);
+        assert_eq!(v0_6_hasher.hash(&v0_6_op), v0_6_op.hash());
+        assert_eq!(
+            v0_6_hasher.hash_packed(v0_6_op.packed_hash()),
+            v0_6_op.hash()
+        );
+
+        let v0_7_hasher = OpHasher::new(cs.entry_point_address_v0_7, cs.id);
+        assert_eq!(v0_7_hasher.hash(&v0_7_op), v0_7_op.hash());
+
+        // a different chain id or entry point changes the hash
+        assert_ne!(
+            OpHasher::new(cs.entry_point_address_v0_6, cs.id + 1).hash(&v0_6_op),
+            v0_6_op.hash()
+        );
+        assert_ne!(
+            OpHasher::new(cs.entry_point_address_v0_7, cs.id).hash(&v0_6_op),
+            v0_6_op.hash()
+        );
+    }
 }
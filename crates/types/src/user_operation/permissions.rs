@@ -26,6 +26,14 @@ pub struct UserOperationPermissions {
     pub underpriced_bundle_pct: Option<u32>,
     /// Bundler sponsorship settings
     pub bundler_sponsorship: Option<BundlerSponsorship>,
+    /// Client supplied correlation tag, echoed back when the operation is mined.
+    ///
+    /// Metadata only, not part of the user operation hash.
+    pub client_tag: Option<String>,
+    /// Origin of the submission, i.e. an RPC client id or IP bucket, reported in builder events.
+    ///
+    /// Metadata only, not part of the user operation hash.
+    pub submission_source: Option<String>,
 }
 
 /// Bundler sponsorship settings
@@ -18,7 +18,10 @@ use rundler_utils::random::{random_bytes, random_bytes_array};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 
-use super::{UserOperation as UserOperationTrait, UserOperationId, UserOperationVariant};
+use super::{
+    InitCodeError, PaymasterAndDataError, UserOperation as UserOperationTrait, UserOperationId,
+    UserOperationVariant,
+};
 use crate::{
     aggregator::AggregatorCosts,
     authorization::Eip7702Auth,
@@ -184,19 +187,19 @@ sol! {
     }
 }
 
-impl From<UserOperation> for UserOperationPackedForHash {
-    fn from(op: UserOperation) -> UserOperationPackedForHash {
+impl From<&UserOperation> for UserOperationPackedForHash {
+    fn from(op: &UserOperation) -> UserOperationPackedForHash {
         UserOperationPackedForHash {
             sender: op.sender,
             nonce: op.nonce,
-            hashInitCode: alloy_primitives::keccak256(op.init_code),
-            hashCallData: alloy_primitives::keccak256(op.call_data),
+            hashInitCode: alloy_primitives::keccak256(&op.init_code),
+            hashCallData: alloy_primitives::keccak256(&op.call_data),
             callGasLimit: U256::from(op.call_gas_limit),
             verificationGasLimit: U256::from(op.verification_gas_limit),
             preVerificationGas: U256::from(op.pre_verification_gas),
             maxFeePerGas: U256::from(op.max_fee_per_gas),
             maxPriorityFeePerGas: U256::from(op.max_priority_fee_per_gas),
-            hashPaymasterAndData: alloy_primitives::keccak256(op.paymaster_and_data),
+            hashPaymasterAndData: alloy_primitives::keccak256(&op.paymaster_and_data),
         }
     }
 }
@@ -220,6 +223,10 @@ impl UserOperationTrait for UserOperation {
         self.hash
     }
 
+    fn packed_hash(&self) -> B256 {
+        alloy_primitives::keccak256(UserOperationPackedForHash::from(self).abi_encode())
+    }
+
     fn id(&self) -> UserOperationId {
         UserOperationId {
             sender: self.sender,
@@ -239,6 +246,22 @@ impl UserOperationTrait for UserOperation {
         Self::get_address_from_field(&self.init_code)
     }
 
+    fn validate_init_code(&self) -> Result<(), InitCodeError> {
+        match self.init_code.len() {
+            1..20 => Err(InitCodeError::Malformed(self.init_code.len())),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_paymaster_and_data(&self) -> Result<(), PaymasterAndDataError> {
+        match self.paymaster_and_data.len() {
+            1..20 => Err(PaymasterAndDataError::Malformed(
+                self.paymaster_and_data.len(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
     fn paymaster(&self) -> Option<Address> {
         Self::get_address_from_field(&self.paymaster_and_data)
     }
@@ -332,6 +355,10 @@ impl UserOperationTrait for UserOperation {
         self.calldata_floor_gas_limit
     }
 
+    fn calldata_gas_cost(&self) -> u128 {
+        self.calldata_gas_cost
+    }
+
     fn aggregator_gas_limit(&self, chain_spec: &ChainSpec, bundle_size: Option<usize>) -> u128 {
         if self.aggregator.is_none() {
             return 0;
@@ -924,9 +951,8 @@ impl<'a> UserOperationBuilder<'a> {
         (uo.calldata_gas_cost, uo.calldata_floor_gas_limit) =
             super::calc_calldata_gas_costs(&cuo, self.chain_spec);
 
-        let packed = UserOperationPackedForHash::from(uo.clone());
         let encoded = UserOperationHashEncoded {
-            encodedHash: alloy_primitives::keccak256(packed.abi_encode()),
+            encodedHash: uo.packed_hash(),
             entryPoint: self.chain_spec.entry_point_address_v0_6,
             chainId: U256::from(self.chain_spec.id),
         };
@@ -1070,6 +1096,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_init_code() {
+        let op = |init_code: Vec<u8>| {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    init_code: init_code.into(),
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+
+        assert_eq!(op(vec![]).validate_init_code(), Ok(()));
+        assert_eq!(op(vec![1; 20]).validate_init_code(), Ok(()));
+        assert_eq!(op(vec![1; 40]).validate_init_code(), Ok(()));
+        assert_eq!(
+            op(vec![1; 19]).validate_init_code(),
+            Err(InitCodeError::Malformed(19))
+        );
+        assert_eq!(
+            op(vec![1]).validate_init_code(),
+            Err(InitCodeError::Malformed(1))
+        );
+    }
+
+    #[test]
+    fn test_validate_paymaster_and_data() {
+        let op = |paymaster_and_data: Vec<u8>| {
+            UserOperationBuilder::new(
+                &ChainSpec::default(),
+                UserOperationRequiredFields {
+                    paymaster_and_data: paymaster_and_data.into(),
+                    ..Default::default()
+                },
+            )
+            .build()
+        };
+
+        // empty, no paymaster
+        assert_eq!(op(vec![]).validate_paymaster_and_data(), Ok(()));
+        // address only
+        assert_eq!(op(vec![1; 20]).validate_paymaster_and_data(), Ok(()));
+        // address and data
+        assert_eq!(op(vec![1; 52]).validate_paymaster_and_data(), Ok(()));
+        // malformed
+        assert_eq!(
+            op(vec![1; 19]).validate_paymaster_and_data(),
+            Err(PaymasterAndDataError::Malformed(19))
+        );
+    }
+
     #[test]
     fn test_abi_encoded_size() {
         let operation = UserOperationBuilder::new(
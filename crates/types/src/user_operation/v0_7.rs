@@ -176,6 +176,10 @@ impl UserOperationTrait for UserOperation {
         self.hash
     }
 
+    fn packed_hash(&self) -> B256 {
+        hash_packed_user_operation_fields(&self.packed)
+    }
+
     fn id(&self) -> UserOperationId {
         UserOperationId {
             sender: self.sender,
@@ -285,6 +289,10 @@ impl UserOperationTrait for UserOperation {
         self.calldata_floor_gas_limit
     }
 
+    fn calldata_gas_cost(&self) -> u128 {
+        self.calldata_gas_cost
+    }
+
     fn required_pre_execution_buffer(&self) -> u128 {
         // See EntryPoint::innerHandleOp
         //
@@ -382,6 +390,26 @@ impl UserOperation {
         &self.packed
     }
 
+    /// Returns the packed `accountGasLimits` of the user operation
+    pub fn account_gas_limits(&self) -> B256 {
+        self.packed.accountGasLimits
+    }
+
+    /// Packs a verification gas limit and a call gas limit into the `accountGasLimits`
+    /// layout, the verification gas limit in the high 16 bytes and the call gas limit in
+    /// the low 16 bytes
+    pub fn pack_account_gas_limits(verification_gas_limit: u128, call_gas_limit: u128) -> B256 {
+        B256::from(concat_u128_be(verification_gas_limit, call_gas_limit))
+    }
+
+    /// Unpacks `accountGasLimits` into its verification gas limit and call gas limit
+    pub fn unpack_account_gas_limits(account_gas_limits: B256) -> (u128, u128) {
+        (
+            u128_from_be_slice(&account_gas_limits[..16]),
+            u128_from_be_slice(&account_gas_limits[16..]),
+        )
+    }
+
     /// Converts the user operation into an unstructured user operation
     pub fn into_unstructured(self) -> UnstructuredUserOperation {
         UnstructuredUserOperation {
@@ -809,14 +837,16 @@ impl<'a> UserOperationBuilder<'a> {
         puo: PackedUserOperation,
         chain_spec: &'a ChainSpec,
     ) -> Result<Self, FromUintError<u128>> {
+        let (verification_gas_limit, call_gas_limit) =
+            UserOperation::unpack_account_gas_limits(puo.accountGasLimits);
         let mut builder = UserOperationBuilder::new(
             chain_spec,
             UserOperationRequiredFields {
                 sender: puo.sender,
                 nonce: puo.nonce,
                 call_data: puo.callData.clone(),
-                call_gas_limit: u128_from_be_slice(&puo.accountGasLimits[16..]),
-                verification_gas_limit: u128_from_be_slice(&puo.accountGasLimits[..16]),
+                call_gas_limit,
+                verification_gas_limit,
                 pre_verification_gas: puo.preVerificationGas.try_into()?,
                 max_priority_fee_per_gas: u128_from_be_slice(&puo.gasFees[..16]),
                 max_fee_per_gas: u128_from_be_slice(&puo.gasFees[16..]),
@@ -1035,7 +1065,8 @@ fn pack_user_operation(uo: UserOperation) -> PackedUserOperation {
         Bytes::new()
     };
 
-    let account_gas_limits = concat_u128_be(uo.verification_gas_limit, uo.call_gas_limit);
+    let account_gas_limits =
+        UserOperation::pack_account_gas_limits(uo.verification_gas_limit, uo.call_gas_limit);
     let gas_fees = concat_u128_be(uo.max_priority_fee_per_gas, uo.max_fee_per_gas);
 
     let pvgl: [u8; 16] = uo.paymaster_verification_gas_limit.to_be_bytes();
@@ -1055,7 +1086,7 @@ fn pack_user_operation(uo: UserOperation) -> PackedUserOperation {
         nonce: uo.nonce,
         initCode: init_code,
         callData: uo.call_data,
-        accountGasLimits: FixedBytes::from(account_gas_limits),
+        accountGasLimits: account_gas_limits,
         preVerificationGas: U256::from(uo.pre_verification_gas),
         gasFees: FixedBytes::from(gas_fees),
         paymasterAndData: paymaster_and_data,
@@ -1096,6 +1127,16 @@ fn hash_packed_user_operation(
     entry_point: Address,
     chain_id: u64,
 ) -> B256 {
+    let encoded = UserOperationHashEncoded {
+        encodedHash: hash_packed_user_operation_fields(puo),
+        entryPoint: entry_point,
+        chainId: U256::from(chain_id),
+    };
+
+    alloy_primitives::keccak256(encoded.abi_encode())
+}
+
+fn hash_packed_user_operation_fields(puo: &PackedUserOperation) -> B256 {
     let hash_init_code = alloy_primitives::keccak256(&puo.initCode);
     let hash_call_data = alloy_primitives::keccak256(&puo.callData);
     let hash_paymaster_and_data = alloy_primitives::keccak256(&puo.paymasterAndData);
@@ -1111,15 +1152,7 @@ fn hash_packed_user_operation(
         hashPaymasterAndData: hash_paymaster_and_data,
     };
 
-    let hashed = alloy_primitives::keccak256(packed_for_hash.abi_encode());
-
-    let encoded = UserOperationHashEncoded {
-        encodedHash: hashed,
-        entryPoint: entry_point,
-        chainId: U256::from(chain_id),
-    };
-
-    alloy_primitives::keccak256(encoded.abi_encode())
+    alloy_primitives::keccak256(packed_for_hash.abi_encode())
 }
 
 fn concat_u128_be(a: u128, b: u128) -> [u8; 32] {
@@ -1197,6 +1230,57 @@ mod tests {
         assert_eq!(uo, unpacked);
     }
 
+    #[test]
+    fn test_pack_unpack_account_gas_limits() {
+        let account_gas_limits = UserOperation::pack_account_gas_limits(0x114fc, 0x12c9b5);
+        assert_eq!(
+            account_gas_limits,
+            b256!("000000000000000000000000000114fc0000000000000000000000000012c9b5")
+        );
+        assert_eq!(
+            UserOperation::unpack_account_gas_limits(account_gas_limits),
+            (0x114fc, 0x12c9b5)
+        );
+
+        // the limits are independent, each using its full 16 bytes
+        for (verification_gas_limit, call_gas_limit) in
+            [(0, 0), (u128::MAX, 0), (0, u128::MAX), (u128::MAX, 1)]
+        {
+            let account_gas_limits =
+                UserOperation::pack_account_gas_limits(verification_gas_limit, call_gas_limit);
+            assert_eq!(
+                UserOperation::unpack_account_gas_limits(account_gas_limits),
+                (verification_gas_limit, call_gas_limit)
+            );
+        }
+
+        let cs = ChainSpec::default();
+        let uo = UserOperationBuilder::new(
+            &cs,
+            UserOperationRequiredFields {
+                sender: Address::ZERO,
+                nonce: U256::ZERO,
+                call_data: Bytes::new(),
+                call_gas_limit: 200_000,
+                verification_gas_limit: 100_000,
+                pre_verification_gas: 0,
+                max_priority_fee_per_gas: 0,
+                max_fee_per_gas: 0,
+                signature: Bytes::new(),
+            },
+        )
+        .build();
+        assert_eq!(
+            uo.account_gas_limits(),
+            UserOperation::pack_account_gas_limits(100_000, 200_000)
+        );
+        let unpacked = UserOperationBuilder::from_packed(uo.clone().pack(), &cs)
+            .unwrap()
+            .build();
+        assert_eq!(unpacked.verification_gas_limit, 100_000);
+        assert_eq!(unpacked.call_gas_limit, 200_000);
+    }
+
     #[test]
     fn test_hash() {
         // From https://sepolia.etherscan.io/tx/0x51c1f40ce6e997a54b39a0eb783e472c2afa4ed3f2f11f97986f7f3a347b9d50
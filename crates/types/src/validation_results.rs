@@ -32,6 +32,8 @@ use rundler_contracts::{
     },
 };
 
+use serde::Serialize;
+
 use crate::{Timestamp, ValidTimeRange, TIME_RANGE_BUFFER};
 
 /// Both v0.6 and v0.7 contracts use this aggregator address to indicate that the signature validation failed
@@ -142,7 +144,8 @@ impl From<FailedOpWithRevertV0_7> for ValidationRevert {
 /// Equivalent to the generated `ValidationResult` or
 /// `ValidationResultWithAggregation` from `EntryPoint`, but with named structs
 /// instead of tuples and with a helper for deserializing.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ValidationOutput {
     /// The return info from the validation function
     pub return_info: ValidationReturnInfo,
@@ -239,7 +242,8 @@ impl TryFrom<ValidationResultV0_7> for ValidationOutput {
 }
 
 /// ValidationReturnInfo from EntryPoint contract
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ValidationReturnInfo {
     /// The amount of gas used before the op was executed (pre verification gas and validation gas)
     pub pre_op_gas: u128,
@@ -377,7 +381,8 @@ pub fn parse_validation_data(data: U256) -> ValidationData {
 }
 
 /// StakeInfo from EntryPoint contract
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct StakeInfo {
     /// The amount of stake
     pub stake: U256,
@@ -420,7 +425,8 @@ impl TryFrom<StakeInfoV0_7> for StakeInfo {
 }
 
 /// AggregatorInfo from EntryPoint contract
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AggregatorInfo {
     /// The address of the aggregator
     pub address: Address,